@@ -16,6 +16,8 @@ impl Provider {
 }
 
 impl BoxProvider for Provider {
+    type Error = vault::Error;
+
     fn box_key_len() -> usize {
         32
     }
@@ -24,6 +26,10 @@ impl BoxProvider for Provider {
         Self::NONCE_LEN + Self::TAG_LEN
     }
 
+    fn box_id() -> [u8; 4] {
+        *b"xcp1"
+    }
+
     fn box_seal(key: &Key<Self>, ad: &[u8], data: &[u8]) -> vault::Result<Vec<u8>> {
         let mut bx = vec![0; data.len() + Self::box_overhead()];
         let (nonce, cipher) = bx.split_at_mut(Self::NONCE_LEN);
@@ -31,21 +37,26 @@ impl BoxProvider for Provider {
 
         XChaChaPoly
             .seal_with(cipher, data, ad, key.bytes(), nonce)
-            .map_err(|_| vault::Error::CryptoError(String::from("Unable to seal data")))?;
+            .map_err(|_| vault::Error::crypto("seal", "Unable to seal data"))?;
         Ok(bx)
     }
 
     fn box_open(key: &Key<Self>, ad: &[u8], data: &[u8]) -> vault::Result<Vec<u8>> {
         let mut plain = match data.len() {
             len if len >= Self::box_overhead() => vec![0; len - Self::box_overhead()],
-            _ => return Err(vault::Error::CryptoError(String::from("Truncated cipher"))),
+            got => {
+                return Err(vault::Error::MalformedCiphertext {
+                    expected_min: Self::box_overhead(),
+                    got,
+                })
+            }
         };
 
         let (nonce, cipher) = data.split_at(Self::NONCE_LEN);
 
         XChaChaPoly
             .open_to(&mut plain, cipher, ad, key.bytes(), nonce)
-            .map_err(|_| vault::Error::CryptoError(String::from("Invalid Cipher")))?;
+            .map_err(|_| vault::Error::AuthenticationFailed)?;
 
         Ok(plain)
     }
@@ -53,6 +64,6 @@ impl BoxProvider for Provider {
     fn random_buf(buf: &mut [u8]) -> vault::Result<()> {
         OsRng
             .random(buf)
-            .map_err(|_| vault::Error::CryptoError(String::from("Can't generated random Bytes")))
+            .map_err(|_| vault::Error::crypto("random", "Can't generated random Bytes"))
     }
 }
@@ -28,6 +28,8 @@ impl Provider {
 
 // implement the BoxProvider trait.
 impl BoxProvider for Provider {
+    type Error = engine::vault::Error;
+
     // setup the box key length.
     fn box_key_len() -> usize {
         32
@@ -38,6 +40,10 @@ impl BoxProvider for Provider {
         Self::NONCE_LEN + Self::TAG_LEN
     }
 
+    fn box_id() -> [u8; 4] {
+        *b"xcp1"
+    }
+
     // seal a box with the key.  Append the nonce to the data after encryption
     fn box_seal(key: &Key<Self>, ad: &[u8], data: &[u8]) -> engine::vault::Result<Vec<u8>> {
         let mut boxx = vec![0; data.len() + Self::box_overhead()];
@@ -46,7 +52,7 @@ impl BoxProvider for Provider {
 
         XChaChaPoly
             .seal_with(cipher, data, ad, key.bytes(), nonce)
-            .map_err(|_| engine::vault::Error::CryptoError(String::from("Unable to seal data")))?;
+            .map_err(|_| engine::vault::Error::crypto("seal", "Unable to seal data"))?;
         Ok(boxx)
     }
 
@@ -54,14 +60,19 @@ impl BoxProvider for Provider {
     fn box_open(key: &Key<Self>, ad: &[u8], data: &[u8]) -> engine::vault::Result<Vec<u8>> {
         let mut plain = match data.len() {
             len if len >= Self::box_overhead() => vec![0; len - Self::box_overhead()],
-            _ => return Err(engine::vault::Error::CryptoError(String::from("Truncated cipher"))),
+            got => {
+                return Err(engine::vault::Error::MalformedCiphertext {
+                    expected_min: Self::box_overhead(),
+                    got,
+                })
+            }
         };
 
         let (nonce, cipher) = data.split_at(Self::NONCE_LEN);
 
         XChaChaPoly
             .open_to(&mut plain, cipher, ad, key.bytes(), nonce)
-            .map_err(|_| engine::vault::Error::CryptoError(String::from("Invalid Cipher")))?;
+            .map_err(|_| engine::vault::Error::AuthenticationFailed)?;
 
         Ok(plain)
     }
@@ -70,6 +81,6 @@ impl BoxProvider for Provider {
     fn random_buf(buf: &mut [u8]) -> engine::vault::Result<()> {
         OsRng
             .random(buf)
-            .map_err(|_| engine::vault::Error::CryptoError(String::from("Can't generated random Bytes")))
+            .map_err(|_| engine::vault::Error::crypto("random", "Can't generated random Bytes"))
     }
 }
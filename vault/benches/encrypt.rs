@@ -0,0 +1,66 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+//! compares sealing ~200 byte records with `Encrypt::encrypt` to the former path, which allocated the nonce and
+//! copied the box into the result, run with `cargo bench --features provider-xchacha --bench encrypt`
+
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use vault::{providers::XChaChaPoly, BoxProvider, Encrypt, Key};
+
+struct Plain<'a>(&'a [u8]);
+struct Sealed(Vec<u8>);
+
+impl AsRef<[u8]> for Plain<'_> {
+    fn as_ref(&self) -> &[u8] {
+        self.0
+    }
+}
+
+impl From<Vec<u8>> for Sealed {
+    fn from(data: Vec<u8>) -> Self {
+        Self(data)
+    }
+}
+
+impl Encrypt<Sealed> for Plain<'_> {}
+
+/// the seal before the boxes were written into a single buffer: a heap allocated nonce and a box assembled from the
+/// nonce and the output of the cipher
+fn seal_before(key: &Key<XChaChaPoly>, ad: &[u8], data: &[u8]) -> Vec<u8> {
+    let nonce = XChaChaPoly::random_vec(XChaChaPoly::box_nonce_len()).unwrap();
+    let sealed = XChaChaPoly::box_seal_with_nonce(key, &nonce, ad, data).unwrap();
+    [&nonce[..], &sealed[nonce.len()..]].concat()
+}
+
+fn bench_encrypt(c: &mut Criterion) {
+    let key = Key::<XChaChaPoly>::random().unwrap();
+    let mut group = c.benchmark_group("encrypt");
+
+    for len in [200, 4096].iter() {
+        let data = vec![0xa5; *len];
+        group.throughput(Throughput::Bytes(*len as u64));
+        group.bench_with_input(BenchmarkId::new("before", len), &data, |b, data| {
+            b.iter(|| black_box(Sealed::from(seal_before(&key, b"ad", data))))
+        });
+        group.bench_with_input(BenchmarkId::new("after", len), &data, |b, data| {
+            b.iter(|| {
+                let sealed: Sealed = Plain(data).encrypt(&key, b"ad").unwrap();
+                black_box(sealed.0)
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_encrypt);
+criterion_main!(benches);
@@ -0,0 +1,102 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+//! compares the allocations and the time of sealing with `Encrypt::encrypt` and with a `BufferPool`, run with
+//! `cargo bench --features provider-xchacha --bench pool`
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    hint::black_box,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Instant,
+};
+
+use vault::{providers::XChaChaPoly, BufferPool, Encrypt, Key};
+
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOC: CountingAllocator = CountingAllocator;
+
+struct Plain<'a>(&'a [u8]);
+struct Sealed(Vec<u8>);
+
+impl AsRef<[u8]> for Plain<'_> {
+    fn as_ref(&self) -> &[u8] {
+        self.0
+    }
+}
+
+impl From<Vec<u8>> for Sealed {
+    fn from(data: Vec<u8>) -> Self {
+        Self(data)
+    }
+}
+
+impl Encrypt<Sealed> for Plain<'_> {}
+
+const ROUNDS: usize = 100_000;
+
+/// run `seal` `ROUNDS` times and print the allocations per call and the time per call
+fn bench(name: &str, len: usize, mut seal: impl FnMut()) {
+    // warm up the pool
+    seal();
+
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        seal();
+    }
+    let elapsed = start.elapsed();
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - allocations;
+
+    println!(
+        "{:<8} {:>6} bytes: {:>5.2} allocations, {:>8.0?} per seal",
+        name,
+        len,
+        allocations as f64 / ROUNDS as f64,
+        elapsed / ROUNDS as u32
+    );
+}
+
+fn main() {
+    let key = Key::<XChaChaPoly>::random().unwrap();
+    let pool = BufferPool::default();
+
+    for len in [64, 1024, 16 << 10].iter() {
+        let data = vec![0xa5; *len];
+        bench("encrypt", *len, || {
+            let sealed: Sealed = Plain(&data).encrypt(&key, b"ad").unwrap();
+            black_box(sealed.0);
+        });
+        bench("pool", *len, || {
+            black_box(pool.seal_with(&key, b"ad", &data).unwrap());
+        });
+    }
+}
@@ -107,6 +107,8 @@ impl CRng {
 }
 
 impl BoxProvider for Provider {
+    type Error = Error;
+
     fn box_key_len() -> usize {
         32
     }
@@ -114,6 +116,10 @@ impl BoxProvider for Provider {
         Self::NONCE + Self::TAG
     }
 
+    fn box_id() -> [u8; 4] {
+        *b"xcp1"
+    }
+
     fn box_seal(key: &Key<Self>, ad: &[u8], plaintext: &[u8]) -> vault::Result<Vec<u8>> {
         // partition buffer and generate a nonce
         let mut cbox = vec![0; plaintext.len() + Self::box_overhead()];
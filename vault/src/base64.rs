@@ -15,8 +15,54 @@ impl Base64 {
     /// base64 padding character
     const PADDING: u8 = b'=';
 
+    /// the last two characters of the uri-safe character set
+    const URI_SAFE: [u8; 2] = *b"-_";
+    /// the last two characters of the standard character set of RFC 4648
+    const STANDARD: [u8; 2] = *b"+/";
+
     /// encode `data` using a base64 uri-safe character set.
     pub fn encode_data(data: &[u8]) -> String {
+        Self::encode_with(data, Self::URI_SAFE)
+    }
+
+    /// decode data from base64 based off of the URI safe character set
+    pub fn decode_data(base: &[u8]) -> crate::Result<Vec<u8>> {
+        Self::decode_with(base, Self::URI_SAFE)
+    }
+
+    /// encode `data` using the standard base64 character set with padding
+    pub fn encode_standard(data: &[u8]) -> String {
+        Self::encode_with(data, Self::STANDARD)
+    }
+
+    /// decode padded data of the standard base64 character set.  Unlike `decode_data` it only accepts the canonical
+    /// encoding, so every value has exactly one representation.
+    pub fn decode_standard(base: &[u8]) -> crate::Result<Vec<u8>> {
+        let data = Self::decode_with(base, Self::STANDARD)?;
+        if Self::encode_standard(&data).as_bytes() != base {
+            return Err(crate::Error::Base64ErrorDetailed(String::from(
+                "Non canonical base64 encoding",
+            )));
+        }
+        Ok(data)
+    }
+
+    /// decode padded secret data of the standard or the uri-safe character set.  The characters are checked up front
+    /// and the output is allocated once, so no partial copies of the data are left behind.
+    pub(crate) fn decode_secret(base: &[u8]) -> crate::Result<Vec<u8>> {
+        let last = match base.iter().any(|b| Self::URI_SAFE.contains(b)) {
+            true => Self::URI_SAFE,
+            false => Self::STANDARD,
+        };
+        let data = &base[..base.len() - base.iter().rev().take_while(|b| **b == Self::PADDING).count()];
+        if data.iter().any(|b| Self::decode_byte(*b, last).is_err()) {
+            return Err(crate::Error::Base64Error);
+        }
+        Self::decode_with(base, last)
+    }
+
+    /// encode `data` using the character set ending with `last`
+    fn encode_with(data: &[u8], last: [u8; 2]) -> String {
         // encode data
         let mut base = Vec::new();
         for chunk in data.chunks(3) {
@@ -27,7 +73,7 @@ impl Base64 {
             [18usize, 12, 6, 0]
                 .iter()
                 .map(|s| (num >> s) & 0b0011_1111)
-                .for_each(|b| base.push(Self::encode_byte(b)));
+                .for_each(|b| base.push(Self::encode_byte(b, last)));
         }
 
         // apply padding
@@ -49,8 +95,8 @@ impl Base64 {
         }
     }
 
-    /// decode data from base64 based off of the URI safe character set
-    pub fn decode_data(base: &[u8]) -> crate::Result<Vec<u8>> {
+    /// decode data from base64 of the character set ending with `last`
+    fn decode_with(base: &[u8], last: [u8; 2]) -> crate::Result<Vec<u8>> {
         // find and remove padding.
         let (padded, base) = match base.iter().rev().take_while(|b| **b == Self::PADDING).count() {
             _ if base.len() % 4 != 0 => return Err(crate::Error::Base64Error),
@@ -59,12 +105,12 @@ impl Base64 {
         };
 
         // decode the data.
-        let mut data = Vec::new();
+        let mut data = Vec::with_capacity(base.len() / 4 * 3 + 3);
         for chunk in base.chunks(4) {
             let num: usize = [18usize, 12, 6, 0]
                 .iter()
                 .zip(chunk.iter())
-                .try_fold(0, |acc, (s, b)| Self::decode_byte(*b).map(|b| acc + (b << *s)))?;
+                .try_fold(0, |acc, (s, b)| Self::decode_byte(*b, last).map(|b| acc + (b << *s)))?;
             [16, 8, 0].iter().map(|s| (num >> s) as u8).for_each(|b| data.push(b));
         }
 
@@ -74,25 +120,25 @@ impl Base64 {
     }
 
     /// encode a single byte
-    fn encode_byte(b: usize) -> u8 {
+    fn encode_byte(b: usize, last: [u8; 2]) -> u8 {
         match b {
             b @ 0..=25 => (b as u8) + b'A',
             b @ 26..=51 => (b as u8 - 26) + b'a',
             b @ 52..=61 => (b as u8 - 52) + b'0',
-            62 => b'-',
-            63 => b'_',
+            62 => last[0],
+            63 => last[1],
             _ => panic!("{} ({})", crate::Error::Base64Error, b),
         }
     }
 
     /// decode a single byte
-    fn decode_byte(b: u8) -> crate::Result<usize> {
+    fn decode_byte(b: u8, last: [u8; 2]) -> crate::Result<usize> {
         match b {
             b @ b'A'..=b'Z' => Ok((b - b'A') as usize),
             b @ b'a'..=b'z' => Ok((b - b'a') as usize + 26),
             b @ b'0'..=b'9' => Ok((b - b'0') as usize + 52),
-            b'-' => Ok(62),
-            b'_' => Ok(63),
+            b if b == last[0] => Ok(62),
+            b if b == last[1] => Ok(63),
             _ => Err(crate::Error::Base64Error),
         }
     }
@@ -9,46 +9,439 @@
 // an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
 // See the License for the specific language governing permissions and limitations under the License.
 
+use crate::{ct::ct_eq, metrics};
+
 use std::{
     convert::TryFrom,
+    fmt::Debug,
     hash::{Hash, Hasher},
+    io::{Read, Write},
     marker::PhantomData,
+    mem,
+    panic::{self, AssertUnwindSafe},
+    sync::Arc,
 };
 
+use hkdf::Hkdf;
+#[cfg(feature = "rand")]
+use rand_core::{CryptoRng, RngCore};
+#[cfg(feature = "insecure-serde")]
 use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use zeroize::Zeroize;
+
+#[cfg(feature = "age-export")]
+mod age;
+/// PEM armoring of sealed data, see `armor::to_pem`, and the `vaultkey1` armoring of keys, see `Key::to_armored`.
+pub mod armor;
+mod associated_data;
+#[cfg(feature = "async")]
+mod async_provider;
+#[cfg(feature = "audit")]
+mod audit;
+mod batch;
+mod blob;
+mod candidates;
+mod chunked;
+#[cfg(feature = "compress")]
+mod compress;
+#[cfg(feature = "cose")]
+mod cose;
+mod envelope;
+#[cfg(feature = "mmap")]
+mod file;
+mod fingerprint;
+#[cfg(feature = "guarded-memory")]
+mod guarded;
+mod instance;
+#[cfg(feature = "password-kdf")]
+mod kdf;
+/// X25519 key agreement deriving a `Key` shared by two parties, see `kx::derive_shared`.
+#[cfg(feature = "key-exchange")]
+pub mod kx;
+#[cfg(feature = "key-formats")]
+mod key_formats;
+/// storing keys in the keychain of the platform, see `keychain::store_key`.
+#[cfg(feature = "keychain")]
+pub mod keychain;
+mod keysource;
+mod lockout;
+mod mac;
+mod meta;
+#[cfg(feature = "mnemonic")]
+mod mnemonic;
+mod padding;
+mod pool;
+mod progress;
+mod reencrypt;
+mod rekey;
+/// sealing data to the X25519 public key of a recipient, see `sealed_box::seal`.
+#[cfg(feature = "asymmetric")]
+pub mod sealed_box;
+mod self_test;
+#[cfg(feature = "serde-seal")]
+mod serde_seal;
+mod shamir;
+mod shared;
+mod sign;
+#[cfg(feature = "password-kdf")]
+pub mod snapshot;
+mod storage;
+mod stream;
+mod tag;
+mod tagged;
+mod usage;
+mod wrap;
+
+#[cfg(feature = "guarded-memory")]
+use guarded::GuardedBytes;
+use storage::KeyBytes;
+use usage::UsageLimit;
+
+#[cfg(feature = "age-export")]
+pub use age::{export_age, export_age_with_work_factor, import_age, AGE_DEFAULT_WORK_FACTOR, AGE_MAX_WORK_FACTOR};
+pub use associated_data::AssociatedData;
+#[cfg(feature = "async")]
+pub use async_provider::{AsyncBoxProvider, DecryptAsync, EncryptAsync};
+#[cfg(feature = "audit")]
+pub use audit::{audit_failures, clear_audit_sink, set_audit_sink, AuditEvent, AuditOperation, AuditSink};
+pub use batch::{decrypt_many, decrypt_many_collect_errors, encrypt_many, encrypt_many_collect_errors};
+pub use blob::SealedBlob;
+pub use candidates::open_with_any;
+pub use chunked::{
+    open_chunked, open_chunked_with_progress, seal_chunked, seal_chunked_with_progress, ChunkedCiphertext,
+};
+#[cfg(feature = "compress")]
+pub use compress::MAX_DECOMPRESSED_LEN;
+#[cfg(feature = "cose")]
+pub use cose::{from_cose_encrypt0, to_cose_encrypt0};
+pub use envelope::Envelope;
+#[cfg(feature = "mmap")]
+pub use file::{open_file, open_file_with_progress, seal_file, seal_file_with_progress, FileSealReport};
+pub use fingerprint::KeyFingerprint;
+pub use instance::BoxProviderInstance;
+#[cfg(feature = "password-kdf")]
+pub use kdf::KdfParams;
+pub use keysource::{KeyEncoding, MAX_KEY_SOURCE_LEN};
+pub use lockout::{GuardedOpen, LockoutPolicy, LockoutState};
+pub use mac::{Authenticate, Verify};
+pub use meta::KeyMeta;
+#[cfg(feature = "mnemonic")]
+pub use mnemonic::Mnemonic;
+pub use padding::PaddingScheme;
+pub use pool::{BufferPool, PooledCiphertext};
+pub use progress::{NoProgress, Progress};
+pub use reencrypt::{reencrypt, reencrypt_across};
+pub(crate) use rekey::record_rekey;
+pub use rekey::{rekey_all, rekey_all_with_progress, RekeyOptions, RekeyReport};
+pub use self_test::{SelfTestCheck, SelfTestReport};
+#[cfg(feature = "serde-seal")]
+pub use serde_seal::{open_serde, open_serde_with_limit, seal_serde, seal_serde_with_limit, MAX_SERDE_LEN};
+pub use shamir::KeyShare;
+pub use shared::SharedKey;
+pub use sign::{Sign, SignProvider, SigningKey, VerifySig};
+pub use tag::Tag;
+pub use wrap::WrappedKey;
 
 /// A provider interface between the vault and a crypto box. See libsodium's [secretbox](https://libsodium.gitbook.io/doc/secret-key_cryptography/secretbox) for an example.
 pub trait BoxProvider: Sized {
+    /// the error of the provider.  The vault converts it into `crate::Error` with `Into`, and the default methods
+    /// report their own failures through `From<crate::Error>`.  Providers without errors of their own can use
+    /// `crate::Error`.
+    type Error: Debug + From<crate::Error> + Into<crate::Error>;
+
     /// function for the key length of the crypto box
     fn box_key_len() -> usize;
     /// gets the crypto box's overhead
     fn box_overhead() -> usize;
+    /// gets the identifier of the algorithm, which `seal_tagged` records in the header of the box.  Providers with
+    /// interchangeable boxes share an identifier.
+    fn box_id() -> [u8; 4];
 
-    /// seals some data into the crypto box using the `key` and the `ad`
-    fn box_seal(key: &Key<Self>, ad: &[u8], data: &[u8]) -> crate::Result<Vec<u8>>;
+    /// gets the length of the nonces used by `box_seal_with_nonce`.  Defaults to 0 for providers that don't support
+    /// explicit nonces.
+    fn box_nonce_len() -> usize {
+        0
+    }
+
+    /// gets the COSE algorithm of the boxes for `to_cose_encrypt0`, which needs boxes of the `box_nonce_len` byte
+    /// nonce followed by the ciphertext and the tag.  Defaults to the registered algorithm of the `box_id`, A256GCM
+    /// for `a256` and ChaCha20/Poly1305 for `c20p`, and `None` for other providers.
+    #[cfg(feature = "cose")]
+    fn cose_algorithm() -> Option<i64> {
+        cose::algorithm_for(Self::box_id())
+    }
+
+    /// gets the JWA name of the algorithm of the boxes, the `alg` of `Key::to_jwk`.  Defaults to the name for the
+    /// `box_id`, A256GCM for `a256`, C20P for `c20p` and XC20P for `xc20`, and `None` for other providers.
+    #[cfg(feature = "key-formats")]
+    fn jwk_algorithm() -> Option<&'static str> {
+        key_formats::algorithm_for(Self::box_id())
+    }
+
+    /// seals some data into the crypto box using the `key` and the `ad`.  The default implementation generates a
+    /// random nonce of `box_nonce_len` bytes on the stack and calls `box_seal_with_nonce`, nonces longer than 32
+    /// bytes are allocated.
+    ///
+    /// The box should be written into a single `Vec` allocated with its final length of `data.len() +
+    /// box_overhead()` bytes, e.g. with `Vec::with_capacity`, instead of being assembled from temporaries.
+    /// `Encrypt::encrypt` hands the `Vec` on to the caller, so a seal then costs one allocation.  The in-tree
+    /// providers follow this contract, `CommittingBox` and `Auto` additionally allocate their derived keys.
+    fn box_seal(key: &Key<Self>, ad: &[u8], data: &[u8]) -> Result<Vec<u8>, Self::Error> {
+        let len = Self::box_nonce_len();
+        if len > MAX_STACK_NONCE_LEN {
+            let mut nonce = Self::random_vec(len)?;
+            let sealed = Self::box_seal_with_nonce(key, &nonce, ad, data);
+            nonce.zeroize();
+            return sealed;
+        }
+
+        let mut nonce = [0; MAX_STACK_NONCE_LEN];
+        let sealed =
+            Self::random_buf(&mut nonce[..len]).and_then(|_| Self::box_seal_with_nonce(key, &nonce[..len], ad, data));
+        nonce.zeroize();
+        sealed
+    }
+
+    /// seals some data into the crypto box using the `key`, the `nonce` and the `ad`.  The nonce must be
+    /// `box_nonce_len` bytes long.  Like `box_seal` the box should be written into a single `Vec` of its final
+    /// length.
+    ///
+    /// **Dangerous**: a nonce must never be used twice with the same key.  Depending on the provider a repeated nonce
+    /// reveals the plaintexts or allows forging boxes.  Use `box_seal` unless the nonces are coordinated, with the
+    /// `test-utils` feature debug builds of the in-tree providers panic on reuse, see `test_utils::record_nonce`.
+    /// The default implementation fails for providers that don't support explicit nonces.
+    fn box_seal_with_nonce(_key: &Key<Self>, _nonce: &[u8], _ad: &[u8], _data: &[u8]) -> Result<Vec<u8>, Self::Error> {
+        Err(crate::Error::crypto("seal with nonce", "Explicit nonces are not supported").into())
+    }
 
     /// opens a crypto box to get data using the `key` and the `ad`.
-    fn box_open(key: &Key<Self>, ad: &[u8], data: &[u8]) -> crate::Result<Vec<u8>>;
+    fn box_open(key: &Key<Self>, ad: &[u8], data: &[u8]) -> Result<Vec<u8>, Self::Error>;
+
+    /// seals the data of `reader` into `writer` in chunks of `chunk_size` bytes so the data doesn't need to fit into
+    /// memory.  Every chunk is sealed with the `ad`, its position and whether it is the last chunk, and written as a
+    /// frame prefixed by its length.
+    fn seal_stream(
+        key: &Key<Self>,
+        ad: &[u8],
+        reader: &mut dyn Read,
+        writer: &mut dyn Write,
+        chunk_size: usize,
+    ) -> crate::Result<()> {
+        Self::seal_stream_with_progress(key, ad, reader, writer, chunk_size, &NoProgress)
+    }
+
+    /// like `seal_stream` but reports the number of sealed bytes to `progress` and fails with `Error::Cancelled`
+    /// before the next chunk once it is cancelled.  The frames written until then don't end the stream, so
+    /// `open_stream` fails on them.
+    fn seal_stream_with_progress(
+        key: &Key<Self>,
+        ad: &[u8],
+        reader: &mut dyn Read,
+        writer: &mut dyn Write,
+        chunk_size: usize,
+        progress: &dyn Progress,
+    ) -> crate::Result<()> {
+        stream::seal(key, ad, reader, writer, chunk_size, progress)
+    }
+
+    /// opens a stream sealed by `seal_stream` with the same `chunk_size` from `reader` into `writer`.  Fails if
+    /// frames are reordered or missing, including a truncated end of the stream.  Data of the frames before the
+    /// failure may have been written already.
+    fn open_stream(
+        key: &Key<Self>,
+        ad: &[u8],
+        reader: &mut dyn Read,
+        writer: &mut dyn Write,
+        chunk_size: usize,
+    ) -> crate::Result<()> {
+        Self::open_stream_with_progress(key, ad, reader, writer, chunk_size, &NoProgress)
+    }
+
+    /// like `open_stream` but reports the number of opened bytes to `progress` and fails with `Error::Cancelled`
+    /// before the next chunk once it is cancelled.  Data of the frames before may have been written already.
+    fn open_stream_with_progress(
+        key: &Key<Self>,
+        ad: &[u8],
+        reader: &mut dyn Read,
+        writer: &mut dyn Write,
+        chunk_size: usize,
+        progress: &dyn Progress,
+    ) -> crate::Result<()> {
+        stream::open(key, ad, reader, writer, chunk_size, progress)
+    }
+
+    /// seals some data like `box_seal` and prepends a header with the `box_id`, so `open_tagged` can tell which
+    /// provider sealed the box.  The header is authenticated along with the `ad`.
+    fn seal_tagged(key: &Key<Self>, ad: &[u8], data: &[u8]) -> crate::Result<Vec<u8>> {
+        tagged::seal(key, ad, data)
+    }
+
+    /// opens a box sealed by `seal_tagged`.  Fails with `Error::ProviderMismatch` before opening the box if it was
+    /// sealed by a provider with another `box_id`.
+    fn open_tagged(key: &Key<Self>, ad: &[u8], data: &[u8]) -> crate::Result<Vec<u8>> {
+        tagged::open(key, ad, data)
+    }
+
+    /// gets the length of the tag returned by `box_seal_detached`.  Defaults to `box_overhead`.
+    fn tag_len() -> usize {
+        Self::box_overhead()
+    }
+
+    /// seals `data` like `box_seal` but returns the authentication tag apart from the rest of the box.  The default
+    /// implementation splits the last `tag_len` bytes off the result of `box_seal`.
+    fn box_seal_detached(key: &Key<Self>, ad: &[u8], data: &[u8]) -> crate::Result<(Vec<u8>, Tag)> {
+        let mut sealed = Self::box_seal(key, ad, data).map_err(Into::into)?;
+        let at = sealed
+            .len()
+            .checked_sub(Self::tag_len())
+            .ok_or_else(|| crate::Error::crypto("seal detached", "Box is shorter than its tag"))?;
+        let tag = Tag::from(&sealed[at..]);
+        sealed.truncate(at);
+        Ok((sealed, tag))
+    }
+
+    /// opens a box sealed by `box_seal_detached` with its `tag`.  The default implementation appends the tag to
+    /// `data` and calls `box_open`.
+    fn box_open_detached(key: &Key<Self>, ad: &[u8], data: &[u8], tag: &Tag) -> crate::Result<Vec<u8>> {
+        if tag.len() != Self::tag_len() {
+            return Err(crate::Error::AuthenticationFailed);
+        }
+        let mut boxx = try_alloc(data.len() + tag.len())?;
+        boxx[..data.len()].copy_from_slice(data);
+        boxx[data.len()..].copy_from_slice(tag.as_bytes());
+        Self::box_open(key, ad, &boxx).map_err(Into::into)
+    }
+
+    /// seals the contents of `buf` in place and extends it by `box_overhead` bytes.  The default implementation
+    /// falls back to `box_seal` and copies the box into `buf`, which keeps its allocation; providers can override it
+    /// to avoid the intermediate allocation.
+    fn box_seal_in_place(key: &Key<Self>, ad: &[u8], buf: &mut Vec<u8>) -> crate::Result<()> {
+        let sealed = Self::box_seal(key, ad, buf).map_err(Into::into)?;
+        buf.zeroize();
+        buf.extend_from_slice(&sealed);
+        Ok(())
+    }
+
+    /// opens the box in `buf` in place and truncates it to the plaintext.  The default implementation falls back to
+    /// `box_open` and copies the plaintext into `buf`, which keeps its allocation; providers can override it to avoid
+    /// the intermediate allocation.
+    fn box_open_in_place(key: &Key<Self>, ad: &[u8], buf: &mut Vec<u8>) -> crate::Result<()> {
+        let mut plain = Self::box_open(key, ad, buf).map_err(Into::into)?;
+        buf.clear();
+        buf.extend_from_slice(&plain);
+        plain.zeroize();
+        Ok(())
+    }
+
+    /// computes a MAC over the `ad` and the `data` without encrypting them, for data that has to stay readable.  The
+    /// default implementation is a 32 byte HMAC-SHA256 keyed with a key derived from `key` for this purpose only.
+    fn box_mac(key: &Key<Self>, ad: &[u8], data: &[u8]) -> crate::Result<Vec<u8>> {
+        mac::compute(key, ad, data)
+    }
+
+    /// verifies a MAC of `box_mac` in constant time.  Fails with `Error::AuthenticationFailed` if the `mac` doesn't
+    /// match, including truncated MACs.
+    fn box_verify_mac(key: &Key<Self>, ad: &[u8], data: &[u8], mac: &[u8]) -> crate::Result<()> {
+        mac::verify(key, ad, data, mac)
+    }
+
+    /// fills a buffer `buf` with secure random bytes.  An implementation either fills all of `buf` or fails, on failure
+    /// the contents of `buf` are unspecified; the default methods wipe `buf` before they return the error.
+    fn random_buf(buf: &mut [u8]) -> Result<(), Self::Error>;
+
+    /// checks that the provider works before it is trusted with data, see `SelfTestReport::run_generic` for the
+    /// checks of the default implementation.  Providers can override it to add known-answer tests.  The report lists
+    /// failed checks, use `SelfTestReport::ensure_passed` to turn them into an error.
+    fn self_test() -> crate::Result<SelfTestReport> {
+        SelfTestReport::run_generic::<Self>()
+    }
+
+    /// gets the largest length `random_vec` accepts.  Defaults to 1 GiB.
+    fn max_random_len() -> usize {
+        1 << 30
+    }
+
+    /// creates a vector with secure random bytes based off of an inputted length `len`.  Fails if `len` is larger
+    /// than `max_random_len` or the vector can't be allocated.
+    fn random_vec(len: usize) -> Result<Vec<u8>, Self::Error> {
+        if len > Self::max_random_len() {
+            return Err(crate::Error::InterfaceErrorDetailed(format!(
+                "Requested `{}` random bytes, at most `{}` are allowed",
+                len,
+                Self::max_random_len()
+            ))
+            .into());
+        }
+        let mut buf = try_alloc(len)?;
+        match Self::random_buf(&mut buf) {
+            Ok(()) => Ok(buf),
+            Err(e) => {
+                wipe(buf);
+                Err(e)
+            }
+        }
+    }
 
-    /// fills a buffer `buf` with secure random bytes.
-    fn random_buf(buf: &mut [u8]) -> crate::Result<()>;
+    /// creates an array of `N` secure random bytes without allocating, e.g. for a nonce or a salt.
+    fn random_array<const N: usize>() -> Result<[u8; N], Self::Error> {
+        let mut array = [0; N];
+        if let Err(e) = Self::random_buf(&mut array) {
+            array.zeroize();
+            return Err(e);
+        }
+        Ok(array)
+    }
 
-    /// creates a vector with secure random bytes based off of an inputted length `len`.
-    fn random_vec(len: usize) -> crate::Result<Vec<u8>> {
-        let mut buf = vec![0; len];
-        Self::random_buf(&mut buf)?;
-        Ok(buf)
+    /// fills a buffer `buf` with secure random bytes none of which is zero.  Zero bytes are drawn again, so every
+    /// byte is uniform in `1..=255`.  On failure `buf` is wiped.
+    fn random_nonzero_buf(buf: &mut [u8]) -> Result<(), Self::Error> {
+        let filled = Self::random_buf(buf).and_then(|_| {
+            buf.iter_mut().filter(|b| **b == 0).try_for_each(|byte| {
+                while *byte == 0 {
+                    Self::random_buf(std::slice::from_mut(byte))?;
+                }
+                Ok(())
+            })
+        });
+        if filled.is_err() {
+            buf.zeroize();
+        }
+        filled
+    }
+
+    /// fills a buffer `buf` with random bytes from `rng` instead of the provider's own source.  On failure `buf` is
+    /// wiped.
+    #[cfg(feature = "rand")]
+    fn random_buf_with_rng<R: CryptoRng + RngCore>(rng: &mut R, buf: &mut [u8]) -> crate::Result<()> {
+        rng.try_fill_bytes(buf).map_err(|e| {
+            buf.zeroize();
+            crate::Error::crypto("random", e)
+        })
     }
 }
 
-/// A key to the crypto box.  Key is stored on the heap which makes it easier to erase.
-#[derive(Serialize, Deserialize)]
+/// A callback invoked with the key bytes when a key is dropped.
+pub(crate) type DropHook = Arc<dyn Fn(&mut [u8]) + Send + Sync>;
+
+/// A key to the crypto box.  Key is stored on the heap which makes it easier to erase.  With the `guarded-memory`
+/// feature a key can also be stored in locked memory, see `Key::random_guarded`.  The key bytes are securely wiped
+/// when the key is dropped.  Serializing the raw key requires the `insecure-serde` feature, use `WrappedKey`
+/// to serialize a sealed key instead.
+#[cfg_attr(feature = "insecure-serde", derive(Serialize, Deserialize))]
 pub struct Key<T: BoxProvider> {
     /// the raw bytes that make up the key
-    pub key: Vec<u8>,
-    /// callback function invoked on drop. Used top drop the data out of memory or pass it to a file.
-    #[serde(skip_serializing, skip_deserializing)]
-    drop_fn: Option<&'static fn(&mut [u8])>,
+    key: KeyBytes,
+    /// callback functions invoked on drop. Used to drop the data out of memory or to persist it, see
+    /// `persist_hooks`.
+    #[cfg_attr(feature = "insecure-serde", serde(skip_serializing, skip_deserializing))]
+    drop_hooks: Vec<DropHook>,
+    /// optional metadata identifying the key
+    #[cfg_attr(feature = "insecure-serde", serde(default))]
+    meta: Option<KeyMeta>,
+    /// optional limit of the number of seals, shared by clones
+    #[cfg_attr(feature = "insecure-serde", serde(skip_serializing, skip_deserializing))]
+    usage: Option<Arc<UsageLimit>>,
     /// associated Provider data
     _box_provider: PhantomData<T>,
 }
@@ -56,92 +449,566 @@ pub struct Key<T: BoxProvider> {
 impl<T: BoxProvider> Key<T> {
     /// generate a random key using secure random bytes
     pub fn random() -> crate::Result<Self> {
-        Ok(Self {
-            key: T::random_vec(T::box_key_len())?,
-            drop_fn: None,
-            _box_provider: PhantomData,
-        })
+        Ok(Self::generated(KeyBytes::Heap(
+            T::random_vec(T::box_key_len()).map_err(Into::into)?,
+        )))
+    }
+
+    /// generate a random key using the random bytes of `rng`.  A seeded `rng` always yields the same key.
+    #[cfg(feature = "rand")]
+    pub fn random_with_rng<R: CryptoRng + RngCore>(rng: &mut R) -> crate::Result<Self> {
+        let mut key = vec![0; T::box_key_len()];
+        if let Err(e) = T::random_buf_with_rng(rng, &mut key) {
+            wipe(key);
+            return Err(e);
+        }
+        Ok(Self::generated(KeyBytes::Heap(key)))
+    }
+
+    /// generate a random key stored in memory that is locked into RAM and excluded from core dumps.  Fails if the
+    /// memory can't be locked, for instance because of the `RLIMIT_MEMLOCK` limit or an unsupported platform.
+    #[cfg(feature = "guarded-memory")]
+    pub fn random_guarded() -> crate::Result<Self> {
+        let mut key = GuardedBytes::new(T::box_key_len())?;
+        T::random_buf(&mut key).map_err(Into::into)?;
+        Ok(Self::generated(KeyBytes::Guarded(key)))
     }
 
     /// attempts to load a key from inputted data
     pub fn load(key: Vec<u8>) -> crate::Result<Self> {
         match key {
-            key if key.len() != T::box_key_len() => Err(crate::Error::InterfaceError),
-            key => Ok(Self {
-                key,
-                drop_fn: None,
-                _box_provider: PhantomData,
+            key if key.len() != T::box_key_len() => {
+                let actual = key.len();
+                // the rejected bytes may still be secret; wipe them before they are freed.
+                wipe(key);
+                Err(crate::Error::InvalidKeyLength {
+                    expected: T::box_key_len(),
+                    actual,
+                })
+            }
+            key => Ok(Self::from_bytes(KeyBytes::Heap(key))),
+        }
+    }
+
+    /// attempts to load a key by copying it from `bytes`.  The caller keeps ownership of `bytes` and is responsible
+    /// for wiping it.
+    pub fn load_from_slice(bytes: &[u8]) -> crate::Result<Self> {
+        match bytes.len() {
+            len if len == T::box_key_len() => Ok(Self::from_bytes(KeyBytes::Heap(bytes.to_vec()))),
+            actual => Err(crate::Error::InvalidKeyLength {
+                expected: T::box_key_len(),
+                actual,
             }),
         }
     }
 
-    /// set up the drop hook function which will be called if the instance gets dropped
-    pub fn on_drop(&mut self, hook: &'static fn(&mut [u8])) {
-        self.drop_fn = Some(hook)
+    /// create a randomly generated key from its storage and report it with the `audit` feature
+    fn generated(key: KeyBytes) -> Self {
+        #[cfg(feature = "audit")]
+        audit::record_key(AuditOperation::KeyGenerate, &key);
+        Self::from_bytes(key)
+    }
+
+    /// create a key from its storage
+    fn from_bytes(key: KeyBytes) -> Self {
+        Self {
+            key,
+            drop_hooks: Vec::new(),
+            meta: None,
+            usage: None,
+            _box_provider: PhantomData,
+        }
+    }
+
+    /// add a drop hook function which will be called if the instance gets dropped. The hooks are called in the
+    /// order they were added and receive the key bytes before they are wiped.  A panicking hook doesn't prevent the
+    /// remaining hooks from running nor the key from being wiped.  Clones of the key share the hooks, so they are
+    /// called once for every dropped copy.
+    pub fn on_drop<F>(&mut self, hook: F)
+    where
+        F: Fn(&mut [u8]) + Send + Sync + 'static,
+    {
+        self.drop_hooks.push(Arc::new(hook))
+    }
+
+    /// remove all drop hooks.  The key is still wiped when it is dropped.
+    pub fn clear_drop_hooks(&mut self) {
+        self.drop_hooks.clear()
+    }
+
+    /// convert the key into a key of the provider `U` with the same key length.  The key bytes are moved, not
+    /// copied, and the drop hooks, metadata and usage limit are carried over.  If the key lengths differ the key is dropped.
+    pub fn convert<U: BoxProvider>(mut self) -> crate::Result<Key<U>> {
+        if T::box_key_len() != U::box_key_len() {
+            return Err(crate::Error::InvalidKeyLength {
+                expected: U::box_key_len(),
+                actual: T::box_key_len(),
+            });
+        }
+
+        Ok(Key {
+            key: mem::replace(&mut self.key, KeyBytes::Heap(Vec::new())),
+            drop_hooks: mem::take(&mut self.drop_hooks),
+            meta: self.meta.take(),
+            usage: self.usage.take(),
+            _box_provider: PhantomData,
+        })
     }
 
     /// get the key's bytes
     pub fn bytes(&self) -> &[u8] {
         &self.key
     }
-}
 
-impl<T: BoxProvider> Clone for Key<T> {
-    fn clone(&self) -> Self {
+    /// get a wrapper whose `Debug` prints the key bytes, unlike the `Debug` of the key.  Never log it.
+    pub fn reveal_debug(&self) -> RevealedKey<'_> {
+        RevealedKey(self.bytes())
+    }
+
+    /// checks if the key is stored in guarded memory
+    pub fn is_guarded(&self) -> bool {
+        self.key.is_guarded()
+    }
+
+    /// copies the key like `clone`, but keeps a guarded key in guarded memory.  Fails if the memory can't be
+    /// allocated or locked.
+    pub fn try_clone(&self) -> crate::Result<Self> {
+        Ok(self.with_bytes(self.key.try_clone()?))
+    }
+
+    /// a copy of the key with the bytes `key`
+    fn with_bytes(&self, key: KeyBytes) -> Self {
         Self {
-            key: self.key.clone(),
-            drop_fn: self.drop_fn,
+            key,
+            drop_hooks: self.drop_hooks.clone(),
+            meta: self.meta,
+            usage: self.usage.clone(),
             _box_provider: PhantomData,
         }
     }
+
+    /// derive a child key for the purpose described by `info` using HKDF-SHA256 over this key.  The same `info`
+    /// always yields the same child key.
+    pub fn derive_child(&self, info: &[u8]) -> crate::Result<Self> {
+        let mut child = vec![0; T::box_key_len()];
+        if Hkdf::<Sha256>::new(None, &self.key).expand(info, &mut child).is_err() {
+            wipe(child);
+            return Err(crate::Error::crypto("derive child key", "Unable to derive child key"));
+        }
+        Self::load(child)
+    }
+}
+
+impl<T: BoxProvider> TryFrom<&[u8]> for Key<T> {
+    type Error = crate::Error;
+
+    fn try_from(bytes: &[u8]) -> crate::Result<Self> {
+        Self::load_from_slice(bytes)
+    }
 }
 
+/// copies the key bytes into a new allocation which is wiped on its own, use `SharedKey` to share a key without copies.
+/// Panics if a guarded key can't be copied into new locked memory, use `Key::try_clone` to handle the error.
+impl<T: BoxProvider> Clone for Key<T> {
+    fn clone(&self) -> Self {
+        self.with_bytes(self.key.clone())
+    }
+}
+
+/// compares the keys in constant time.
 impl<T: BoxProvider> PartialEq for Key<T> {
     fn eq(&self, other: &Self) -> bool {
-        self.key == other.key && self._box_provider == other._box_provider
+        ct_eq(&self.key, &other.key) && self._box_provider == other._box_provider
     }
 }
 
 impl<T: BoxProvider> Eq for Key<T> {}
 
+/// hashes the fingerprint of the key so the key bytes never reach the hasher.  Equal keys have equal fingerprints.
 impl<T: BoxProvider> Hash for Key<T> {
     fn hash<H: Hasher>(&self, state: &mut H) {
-        self.key.hash(state);
+        self.fingerprint().hash(state);
         self._box_provider.hash(state);
     }
 }
 
-/// call the drop hook on dropping the key.
+/// call the drop hooks on dropping the key and then wipe the key bytes.  Panics of the hooks are caught.
 impl<T: BoxProvider> Drop for Key<T> {
     fn drop(&mut self) {
-        if let Some(hook) = self.drop_fn {
-            hook(&mut self.key);
+        // before the hooks, which may wipe the key
+        #[cfg(feature = "audit")]
+        audit::record_key(AuditOperation::KeyDrop, &self.key);
+        for hook in &self.drop_hooks {
+            let key = &mut self.key;
+            let _ = panic::catch_unwind(AssertUnwindSafe(|| hook(key)));
         }
+        self.key.zeroize();
+    }
+}
+
+/// seals `data` with `B::box_seal` and reports the seal with the `audit` feature, see `set_audit_sink`
+pub(crate) fn seal_box<B: BoxProvider>(key: &Key<B>, ad: &[u8], data: &[u8]) -> crate::Result<Vec<u8>> {
+    audit_seal(key, ad, data.len(), B::box_seal(key, ad, data).map_err(Into::into))
+}
+
+/// opens `data` with `B::box_open` and reports the open with the `audit` feature, see `set_audit_sink`.  Data shorter
+/// than `BoxProvider::box_overhead` fails with `Error::MalformedCiphertext`, see `check_box_len`.
+pub(crate) fn open_box<B: BoxProvider>(key: &Key<B>, ad: &[u8], data: &[u8]) -> crate::Result<Vec<u8>> {
+    let opened = check_box_len(data, B::box_overhead()).and_then(|_| B::box_open(key, ad, data).map_err(Into::into));
+    audit_open(key, ad, data.len(), opened)
+}
+
+/// reports the seal of `len` bytes of plaintext with the `audit` feature and to the `metrics`, and passes its
+/// `result` on
+#[cfg_attr(not(feature = "audit"), allow(unused_variables))]
+pub(crate) fn audit_seal<B: BoxProvider, R>(
+    key: &Key<B>,
+    ad: &[u8],
+    len: usize,
+    result: crate::Result<R>,
+) -> crate::Result<R> {
+    #[cfg(feature = "audit")]
+    audit::record_box(AuditOperation::Seal, key, ad, len, result.is_ok());
+    if result.is_ok() {
+        metrics::incr(metrics::SEALS, 1);
+        metrics::incr(metrics::SEALED_BYTES, len as u64);
+    }
+    result
+}
+
+/// reports the open of a box of `len` bytes with the `audit` feature and to the `metrics`, and passes its `result`
+/// on
+#[cfg_attr(not(feature = "audit"), allow(unused_variables))]
+pub(crate) fn audit_open<B: BoxProvider, R>(
+    key: &Key<B>,
+    ad: &[u8],
+    len: usize,
+    result: crate::Result<R>,
+) -> crate::Result<R> {
+    #[cfg(feature = "audit")]
+    audit::record_box(AuditOperation::Open, key, ad, len, result.is_ok());
+    match &result {
+        Ok(_) => metrics::incr(metrics::OPENS, 1),
+        Err(crate::Error::AuthenticationFailed) => metrics::incr(metrics::AUTH_FAILURES, 1),
+        Err(_) => {}
     }
+    result
+}
+
+/// the longest nonce the default `BoxProvider::box_seal` generates on the stack, longer nonces are allocated
+const MAX_STACK_NONCE_LEN: usize = 32;
+
+/// allocates a zeroed buffer of `len` bytes.  Fails with `Error::MemoryError` instead of aborting if the memory
+/// can't be allocated.
+pub(crate) fn try_alloc(len: usize) -> crate::Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    buf.try_reserve_exact(len)
+        .map_err(|_| crate::Error::MemoryError(format!("Unable to allocate `{}` bytes", len)))?;
+    buf.resize(len, 0);
+    Ok(buf)
+}
+
+/// wipes key material that was rejected or is no longer needed, including the spare capacity of `bytes`, before it
+/// is freed.  All paths which take ownership of candidate key bytes wipe them with it.  With the `test-utils` feature
+/// it's recorded for `test_utils::take_wiped_buffers`.
+pub(crate) fn wipe(mut bytes: Vec<u8>) {
+    bytes.as_mut_slice().zeroize();
+    #[cfg(feature = "test-utils")]
+    crate::test_utils::record_wipe(&bytes);
+    bytes.zeroize();
 }
 
-use std::fmt::Debug;
+/// copies `data` into a buffer allocated with `try_alloc`
+pub(crate) fn try_copy(data: &[u8]) -> crate::Result<Vec<u8>> {
+    let mut buf = try_alloc(data.len())?;
+    buf.copy_from_slice(data);
+    Ok(buf)
+}
 
+/// prints the length, the fingerprint and the provider of the key but no key material, see `Key::reveal_debug`.
 impl<T: BoxProvider> Debug for Key<T> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("Key").field("key data", &self.key).finish()
+        f.debug_struct("Key")
+            .field("len", &self.bytes().len())
+            .field("fingerprint", &self.fingerprint().to_string())
+            .field("provider", &std::any::type_name::<T>())
+            .finish()
+    }
+}
+
+/// A borrowed key whose `Debug` prints the key bytes, for debugging locally.  Created by `Key::reveal_debug`.
+pub struct RevealedKey<'a>(&'a [u8]);
+
+impl Debug for RevealedKey<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Key").field("key data", &self.0).finish()
     }
 }
 
 /// trait for encryptable data
 pub trait Encrypt<T: From<Vec<u8>>>: AsRef<[u8]> {
-    /// encrypts a raw data and creates a type T from the ciphertext
+    /// encrypts a raw data and creates a type T from the ciphertext.  Counts as a use of the key, see
+    /// `Key::with_max_uses`.
     fn encrypt<B: BoxProvider>(&self, key: &Key<B>, ad: &[u8]) -> crate::Result<T> {
-        let sealed = B::box_seal(key, ad, self.as_ref())?;
+        key.checkout_use()?;
+        let sealed = seal_box(key, ad, self.as_ref())?;
+        Ok(T::from(sealed))
+    }
+
+    /// encrypts raw data like `encrypt` with the canonical bytes of `ad` as AD, see `AssociatedData`
+    fn encrypt_ad<B: BoxProvider>(&self, key: &Key<B>, ad: &AssociatedData) -> crate::Result<T> {
+        self.encrypt(key, ad.as_bytes())
+    }
+
+    /// encrypts raw data with a key that has `KeyMeta` attached.  The key id and version are prepended to the AD and
+    /// to the ciphertext so `Decrypt::decrypt_with_meta` can detect the wrong key before opening the box.
+    fn encrypt_with_meta<B: BoxProvider>(&self, key: &Key<B>, ad: &[u8]) -> crate::Result<T> {
+        let header = key.require_meta()?.header();
+        key.checkout_use()?;
+        let mut sealed = seal_box(key, &[&header[..], ad].concat(), self.as_ref())?;
+        sealed.splice(0..0, header.iter().copied());
+        Ok(T::from(sealed))
+    }
+
+    /// encrypts the data in `buf` in place using `BoxProvider::box_seal_in_place`.  Counts as a use of the key, see
+    /// `Key::with_max_uses`.
+    fn encrypt_in_place<B: BoxProvider>(key: &Key<B>, ad: &[u8], buf: &mut Vec<u8>) -> crate::Result<()>
+    where
+        Self: Sized,
+    {
+        key.checkout_use()?;
+        let len = buf.len();
+        audit_seal(key, ad, len, B::box_seal_in_place(key, ad, buf))
+    }
+
+    /// encrypts raw data into `out` using `BoxProvider::box_seal_in_place` and returns the length of the box.  `out`
+    /// is cleared first and keeps its allocation if it can hold the box, so it can be reused across calls.  On failure
+    /// `out` is wiped.  Counts as a use of the key, see `Key::with_max_uses`.
+    fn encrypt_into<B: BoxProvider>(&self, key: &Key<B>, ad: &[u8], out: &mut Vec<u8>) -> crate::Result<usize> {
+        out.clear();
+        key.checkout_use()?;
+
+        let data = self.as_ref();
+        out.reserve(data.len() + B::box_overhead());
+        out.extend_from_slice(data);
+        match audit_seal(key, ad, data.len(), B::box_seal_in_place(key, ad, out)) {
+            Ok(()) => Ok(out.len()),
+            Err(e) => {
+                out.zeroize();
+                Err(e)
+            }
+        }
+    }
+
+    /// encrypts raw data into an `Envelope` which records the format version, the provider and the lengths.  Counts
+    /// as a use of the key, see `Key::with_max_uses`.
+    fn encrypt_enveloped<B: BoxProvider>(&self, key: &Key<B>, ad: &[u8]) -> crate::Result<T> {
+        key.checkout_use()?;
+        Ok(T::from(envelope::seal(key, ad, self.as_ref())?))
+    }
+
+    /// compresses raw data with zstd at `level` before encrypting it.  Data that doesn't compress is stored
+    /// uncompressed, the plaintext records which one it is.  Counts as a use of the key, see `Key::with_max_uses`.
+    #[cfg(feature = "compress")]
+    fn encrypt_compressed<B: BoxProvider>(&self, key: &Key<B>, ad: &[u8], level: i32) -> crate::Result<T> {
+        let mut plain = compress::compress(self.as_ref(), level)?;
+        key.checkout_use()?;
+        let sealed = seal_box(key, ad, &plain);
+        plain.zeroize();
+        Ok(T::from(sealed?))
+    }
+
+    /// pads raw data according to `scheme` before encrypting it, so boxes of plaintext with lengths in the same
+    /// bucket have the same length.  Counts as a use of the key, see `Key::with_max_uses`.
+    fn encrypt_padded<B: BoxProvider>(&self, key: &Key<B>, ad: &[u8], scheme: PaddingScheme) -> crate::Result<T> {
+        let mut plain = padding::pad(self.as_ref(), scheme)?;
+        key.checkout_use()?;
+        let sealed = seal_box(key, ad, &plain);
+        plain.zeroize();
+        Ok(T::from(sealed?))
+    }
+
+    /// encrypts raw data into a `SealedBlob` and creates a type T from its canonical encoding.  Counts as a use of
+    /// the key, see `Key::with_max_uses`.
+    fn encrypt_blob<B: BoxProvider>(&self, key: &Key<B>, ad: &[u8]) -> crate::Result<T> {
+        key.checkout_use()?;
+        Ok(T::from(SealedBlob::seal(key, ad, self.as_ref())?.to_bytes()))
+    }
+
+    /// encrypts raw data into a `SealedBlob` and armors its canonical encoding as a PEM block labeled
+    /// `armor::SEALED_DATA_LABEL`, see `armor::to_pem`.  Counts as a use of the key, see `Key::with_max_uses`.
+    fn encrypt_armored<B: BoxProvider>(&self, key: &Key<B>, ad: &[u8]) -> crate::Result<String> {
+        key.checkout_use()?;
+        let blob = SealedBlob::seal(key, ad, self.as_ref())?.to_bytes();
+        Ok(armor::to_pem(armor::SEALED_DATA_LABEL, &blob))
+    }
+
+    /// encrypts raw data with a provider instance, see `BoxProviderInstance`.  Counts as a use of the key, see
+    /// `Key::with_max_uses`.
+    fn encrypt_with<I: BoxProviderInstance>(&self, provider: &I, key: &Key<I::Marker>, ad: &[u8]) -> crate::Result<T> {
+        key.checkout_use()?;
+        let sealed = provider.box_seal(key, ad, self.as_ref()).map_err(Into::into);
+        let sealed = audit_seal(key, ad, self.as_ref().len(), sealed)?;
         Ok(T::from(sealed))
     }
 }
 
+/// check that `data` is at least `expected_min` bytes long, the overhead of a box.  Shorter data fails with
+/// `Error::MalformedCiphertext` before it reaches the AEAD of a provider, so every provider tells truncated data
+/// apart from `Error::AuthenticationFailed` the same way.
+pub(crate) fn check_box_len(data: &[u8], expected_min: usize) -> crate::Result<()> {
+    match data.len() {
+        got if got < expected_min => Err(crate::Error::MalformedCiphertext { expected_min, got }),
+        _ => Ok(()),
+    }
+}
+
+/// converts an opened plaintext into `T`.  Fails with `Error::ConversionError` carrying the conversion error, which
+/// keeps failed conversions apart from failed authentication.
+pub(crate) fn convert_plaintext<E: Debug, T: TryFrom<Vec<u8>, Error = E>>(plain: Vec<u8>) -> crate::Result<T> {
+    T::try_from(plain).map_err(|e| crate::Error::ConversionError {
+        type_name: std::any::type_name::<T>(),
+        message: format!("{:?}", e),
+    })
+}
+
 /// Trait for decryptable data
-pub trait Decrypt<E, T: TryFrom<Vec<u8>, Error = E>>: AsRef<[u8]> {
-    /// decrypts raw data and creates a new type T from the plaintext
+pub trait Decrypt<E: Debug, T: TryFrom<Vec<u8>, Error = E>>: AsRef<[u8]> {
+    /// decrypts raw data and creates a new type T from the plaintext.  Data shorter than `BoxProvider::box_overhead`
+    /// fails with `Error::MalformedCiphertext`, a box that doesn't open with the key and the AD with
+    /// `Error::AuthenticationFailed`, as do the other methods.
     fn decrypt<B: BoxProvider>(&self, key: &Key<B>, ad: &[u8]) -> crate::Result<T> {
-        let opened = B::box_open(key, ad, self.as_ref())?;
-        Ok(T::try_from(opened).map_err(|_| crate::Error::DatabaseError(String::from("Invalid Entry")))?)
+        let opened = open_box(key, ad, self.as_ref())?;
+        convert_plaintext(opened)
+    }
+
+    /// decrypts data created by `Encrypt::encrypt_ad`.  AD of another domain or with other fields fails like other
+    /// raw AD with `Error::AuthenticationFailed`.
+    fn decrypt_ad<B: BoxProvider>(&self, key: &Key<B>, ad: &AssociatedData) -> crate::Result<T> {
+        self.decrypt(key, ad.as_bytes())
+    }
+
+    /// decrypts data created by `Encrypt::encrypt_with_meta`.  Fails with `Error::KeyMismatch` before opening the box
+    /// if the data was sealed with a key of another id or version.
+    fn decrypt_with_meta<B: BoxProvider>(&self, key: &Key<B>, ad: &[u8]) -> crate::Result<T> {
+        let (header, sealed) = key.require_meta()?.check_header(self.as_ref())?;
+        let opened = open_box(key, &[header, ad].concat(), sealed)?;
+        convert_plaintext(opened)
+    }
+
+    /// decrypts the data in `buf` in place using `BoxProvider::box_open_in_place`.
+    fn decrypt_in_place<B: BoxProvider>(key: &Key<B>, ad: &[u8], buf: &mut Vec<u8>) -> crate::Result<()>
+    where
+        Self: Sized,
+    {
+        let len = buf.len();
+        let opened = check_box_len(buf, B::box_overhead()).and_then(|_| B::box_open_in_place(key, ad, buf));
+        audit_open(key, ad, len, opened)
+    }
+
+    /// decrypts the data in the allocation of `self` using `BoxProvider::box_open_in_place` and creates a new type T
+    /// from the plaintext, without the copies of `decrypt` for providers which open in place like `XChaChaPoly`.
+    /// Other providers fall back to `BoxProvider::box_open` and copy the plaintext into the allocation.  On success the
+    /// allocation that held the ciphertext holds the plaintext and is moved into the T, which is responsible for
+    /// wiping it; on failure it is wiped before it is freed.
+    fn decrypt_owned<B: BoxProvider>(self, key: &Key<B>, ad: &[u8]) -> crate::Result<T>
+    where
+        Self: Sized + Into<Vec<u8>>,
+    {
+        let mut buf = self.into();
+        match Self::decrypt_in_place(key, ad, &mut buf) {
+            Ok(()) => convert_plaintext(buf),
+            Err(e) => {
+                wipe(buf);
+                Err(e)
+            }
+        }
+    }
+
+    /// decrypts raw data into `out` using `BoxProvider::box_open_in_place` and returns the length of the plaintext.
+    /// `out` is cleared first and keeps its allocation, so it can be reused across calls.  On failure `out` is wiped.
+    fn decrypt_into<B: BoxProvider>(&self, key: &Key<B>, ad: &[u8], out: &mut Vec<u8>) -> crate::Result<usize> {
+        out.clear();
+        let data = self.as_ref();
+        let opened = check_box_len(data, B::box_overhead()).and_then(|_| {
+            out.extend_from_slice(data);
+            B::box_open_in_place(key, ad, out)
+        });
+        match audit_open(key, ad, data.len(), opened) {
+            Ok(()) => Ok(out.len()),
+            Err(e) => {
+                out.zeroize();
+                Err(e)
+            }
+        }
+    }
+
+    /// decrypts raw data with the first of `keys` that opens it and returns the index of that key with the
+    /// plaintext, see `open_with_any`.  Fails with `Error::NoMatchingKey` if no key opens the data.
+    fn decrypt_with_any<B: BoxProvider>(&self, keys: &[&Key<B>], ad: &[u8]) -> crate::Result<(usize, T)> {
+        let (index, opened) = candidates::open_with_any(keys, ad, self.as_ref())?;
+        Ok((index, convert_plaintext(opened)?))
+    }
+
+    /// decrypts data created by `Encrypt::encrypt_enveloped`.  Unknown versions fail with
+    /// `Error::UnsupportedVersion` and other providers with `Error::ProviderMismatch` before the box is opened.
+    fn decrypt_enveloped<B: BoxProvider>(&self, key: &Key<B>, ad: &[u8]) -> crate::Result<T> {
+        let opened = envelope::open(key, ad, self.as_ref())?;
+        convert_plaintext(opened)
+    }
+
+    /// decrypts data created by `Encrypt::encrypt_compressed` and decompresses it.  Fails with
+    /// `Error::DecompressionLimit` if the plaintext inflates beyond `MAX_DECOMPRESSED_LEN` bytes.
+    #[cfg(feature = "compress")]
+    fn decrypt_decompressed<B: BoxProvider>(&self, key: &Key<B>, ad: &[u8]) -> crate::Result<T> {
+        self.decrypt_decompressed_with_limit(key, ad, MAX_DECOMPRESSED_LEN)
+    }
+
+    /// like `decrypt_decompressed` with a limit of `limit` bytes for the decompressed plaintext
+    #[cfg(feature = "compress")]
+    fn decrypt_decompressed_with_limit<B: BoxProvider>(
+        &self,
+        key: &Key<B>,
+        ad: &[u8],
+        limit: usize,
+    ) -> crate::Result<T> {
+        let mut opened = open_box(key, ad, self.as_ref())?;
+        let plain = compress::decompress(&opened, limit);
+        opened.zeroize();
+        convert_plaintext(plain?)
+    }
+
+    /// decrypts data created by `Encrypt::encrypt_padded` and removes the padding.  The padding is only checked once
+    /// the box opened, padding that doesn't parse fails with `Error::MalformedCiphertext`.
+    fn decrypt_unpadded<B: BoxProvider>(&self, key: &Key<B>, ad: &[u8]) -> crate::Result<T> {
+        let mut opened = open_box(key, ad, self.as_ref())?;
+        let plain = padding::unpad(&opened);
+        opened.zeroize();
+        convert_plaintext(plain?)
+    }
+
+    /// decrypts data created by `Encrypt::encrypt_blob`, see `SealedBlob::from_bytes` and `SealedBlob::open`.
+    fn decrypt_blob<B: BoxProvider>(&self, key: &Key<B>, ad: &[u8]) -> crate::Result<T> {
+        let opened = SealedBlob::from_bytes(self.as_ref())?.open(key, ad)?;
+        convert_plaintext(opened)
+    }
+
+    /// decrypts a PEM block created by `Encrypt::encrypt_armored`, see `armor::from_pem`.  The text around the block
+    /// is ignored.
+    fn decrypt_armored<B: BoxProvider>(&self, key: &Key<B>, ad: &[u8]) -> crate::Result<T> {
+        let text = std::str::from_utf8(self.as_ref())
+            .map_err(|e| crate::Error::ArmorError(format!("armored data is not UTF-8: {}", e)))?;
+        let blob = armor::from_pem(armor::SEALED_DATA_LABEL, text)?;
+        let opened = SealedBlob::from_bytes(&blob)?.open(key, ad)?;
+        convert_plaintext(opened)
+    }
+
+    /// decrypts raw data with a provider instance, see `BoxProviderInstance`.
+    fn decrypt_with<I: BoxProviderInstance>(&self, provider: &I, key: &Key<I::Marker>, ad: &[u8]) -> crate::Result<T> {
+        let data = self.as_ref();
+        let opened = check_box_len(data, provider.box_overhead())
+            .and_then(|_| provider.box_open(key, ad, data).map_err(Into::into));
+        let opened = audit_open(key, ad, data.len(), opened)?;
+        convert_plaintext(opened)
     }
 }
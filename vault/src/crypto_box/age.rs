@@ -0,0 +1,312 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+use crate::{base64::Base64, crypto_box::try_alloc};
+
+use chacha20poly1305::{
+    aead::{Aead, AeadInPlace, KeyInit},
+    ChaCha20Poly1305, Nonce, Tag,
+};
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use zeroize::{Zeroize, Zeroizing};
+
+/// the work factor of `export_age`, the one `age` calibrates for about a second on current hardware
+pub const AGE_DEFAULT_WORK_FACTOR: u8 = 18;
+/// the largest work factor `import_age` accepts, like `age`.  Higher factors need more than 4 GiB of memory.
+pub const AGE_MAX_WORK_FACTOR: u8 = 22;
+
+/// the first line of the header
+const VERSION_LINE: &[u8] = b"age-encryption.org/v1\n";
+/// the prefix of the salt of the scrypt stanza
+const SCRYPT_LABEL: &[u8] = b"age-encryption.org/v1/scrypt";
+/// the length of the plaintext of a payload chunk, the last chunk may be shorter
+const CHUNK_LEN: usize = 64 * 1024;
+const TAG_LEN: usize = 16;
+const FILE_KEY_LEN: usize = 16;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 16;
+/// the length of the lines of a stanza body
+const COLUMNS: usize = 64;
+
+fn age_error(message: &str) -> crate::Error {
+    crate::Error::AgeError(String::from(message))
+}
+
+fn random_array<const N: usize>() -> crate::Result<[u8; N]> {
+    let mut bytes = [0; N];
+    getrandom::getrandom(&mut bytes).map_err(|e| crate::Error::crypto("random", e))?;
+    Ok(bytes)
+}
+
+/// the standard base64 encoding without padding
+fn encode(data: &[u8]) -> String {
+    let mut base = Base64::encode_standard(data);
+    base.truncate(base.trim_end_matches('=').len());
+    base
+}
+
+/// decode the canonical standard base64 encoding without padding
+fn decode(base: &[u8]) -> crate::Result<Vec<u8>> {
+    if base.contains(&b'=') || base.len() % 4 == 1 {
+        return Err(age_error("Invalid base64"));
+    }
+    let mut padded = base.to_vec();
+    padded.resize(base.len().div_ceil(4) * 4, b'=');
+    Base64::decode_standard(&padded).map_err(|_| age_error("Invalid base64"))
+}
+
+/// the key wrapping the file key for a passphrase
+fn scrypt_key(passphrase: &str, salt: &[u8], log_n: u8) -> crate::Result<Zeroizing<[u8; 32]>> {
+    let params = scrypt::Params::new(log_n, 8, 1, 32).map_err(|_| age_error("Invalid work factor"))?;
+    let mut key = Zeroizing::new([0; 32]);
+    scrypt::scrypt(
+        passphrase.as_bytes(),
+        &[SCRYPT_LABEL, salt].concat(),
+        &params,
+        &mut key[..],
+    )
+    .map_err(|_| crate::Error::crypto("derive scrypt key", "Unable to derive the scrypt key"))?;
+    Ok(key)
+}
+
+/// a key derived from the file key
+fn derive_key(file_key: &[u8], salt: &[u8], info: &[u8]) -> crate::Result<Zeroizing<[u8; 32]>> {
+    let mut key = Zeroizing::new([0; 32]);
+    Hkdf::<Sha256>::new(Some(salt), file_key)
+        .expand(info, &mut key[..])
+        .map_err(|_| crate::Error::crypto("derive key", "Unable to derive the key"))?;
+    Ok(key)
+}
+
+/// the MAC of the header up to and including the `---` of the MAC line
+fn header_mac(file_key: &[u8], header: &[u8]) -> crate::Result<Hmac<Sha256>> {
+    let mac_key = derive_key(file_key, &[], b"header")?;
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(&mac_key[..]).expect("HMAC accepts keys of any length");
+    mac.update(header);
+    Ok(mac)
+}
+
+/// the nonce of a payload chunk, the 11 byte big endian chunk counter followed by the last chunk flag
+fn chunk_nonce(index: usize, last: bool) -> Nonce {
+    let mut nonce = Nonce::default();
+    nonce[3..11].copy_from_slice(&(index as u64).to_be_bytes());
+    nonce[11] = last as u8;
+    nonce
+}
+
+/// encrypt `plaintext` into an age v1 file for `passphrase`, which decrypts with `age -d` and `rage -d`.  Uses the
+/// `AGE_DEFAULT_WORK_FACTOR`, see `export_age_with_work_factor`.
+pub fn export_age(passphrase: &str, plaintext: &[u8]) -> crate::Result<Vec<u8>> {
+    export_age_with_work_factor(passphrase, plaintext, AGE_DEFAULT_WORK_FACTOR)
+}
+
+/// like `export_age` with a scrypt work factor of `log_n`, between 1 and `AGE_MAX_WORK_FACTOR`
+pub fn export_age_with_work_factor(passphrase: &str, plaintext: &[u8], log_n: u8) -> crate::Result<Vec<u8>> {
+    if log_n == 0 || log_n > AGE_MAX_WORK_FACTOR {
+        return Err(crate::Error::InterfaceErrorDetailed(format!(
+            "The work factor has to be between 1 and {}",
+            AGE_MAX_WORK_FACTOR
+        )));
+    }
+
+    let file_key = Zeroizing::new(random_array::<FILE_KEY_LEN>()?);
+    let salt = random_array::<SALT_LEN>()?;
+    let wrap_key = scrypt_key(passphrase, &salt, log_n)?;
+    let body = ChaCha20Poly1305::new((&*wrap_key).into())
+        .encrypt(&Nonce::default(), &file_key[..])
+        .map_err(|e| crate::Error::crypto("seal", e))?;
+
+    // the 32 byte body fits into a single line, which is shorter than the column limit and ends the stanza
+    let mut header = VERSION_LINE.to_vec();
+    header.extend_from_slice(format!("-> scrypt {} {}\n{}\n---", encode(&salt), log_n, encode(&body)).as_bytes());
+    let mac = header_mac(&file_key[..], &header)?.finalize().into_bytes();
+    header.extend_from_slice(format!(" {}\n", encode(&mac)).as_bytes());
+
+    let nonce = random_array::<NONCE_LEN>()?;
+    let payload_key = derive_key(&file_key[..], &nonce, b"payload")?;
+    let cipher = ChaCha20Poly1305::new((&*payload_key).into());
+
+    // an empty plaintext is a single empty chunk
+    let chunks = plaintext.len().saturating_sub(1) / CHUNK_LEN + 1;
+    let mut age = Vec::new();
+    age.try_reserve_exact(header.len() + NONCE_LEN + plaintext.len() + chunks * TAG_LEN)
+        .map_err(|_| crate::Error::MemoryError(String::from("Unable to allocate the age file")))?;
+    age.extend_from_slice(&header);
+    age.extend_from_slice(&nonce);
+    for index in 0..chunks {
+        let chunk = &plaintext[index * CHUNK_LEN..plaintext.len().min((index + 1) * CHUNK_LEN)];
+        let start = age.len();
+        age.extend_from_slice(chunk);
+        match cipher.encrypt_in_place_detached(&chunk_nonce(index, index + 1 == chunks), &[], &mut age[start..]) {
+            Ok(tag) => age.extend_from_slice(&tag),
+            Err(_) => {
+                age.zeroize();
+                return Err(crate::Error::crypto("seal", "Unable to encrypt the payload"));
+            }
+        }
+    }
+    Ok(age)
+}
+
+/// a recipient stanza of the header
+struct Stanza<'a> {
+    args: Vec<&'a [u8]>,
+    body: Vec<u8>,
+}
+
+/// split the next line off the front of `data`, without the newline
+fn next_line<'a>(data: &mut &'a [u8]) -> crate::Result<&'a [u8]> {
+    let end = data
+        .iter()
+        .position(|b| *b == b'\n')
+        .ok_or_else(|| age_error("Truncated header"))?;
+    let line = &data[..end];
+    *data = &data[end + 1..];
+    Ok(line)
+}
+
+/// parse a stanza of the arguments after the `-> ` of its first line and the body lines in `data`
+fn parse_stanza<'a>(args: &'a [u8], data: &mut &[u8]) -> crate::Result<Stanza<'a>> {
+    let args: Vec<_> = args.split(|b| *b == b' ').collect();
+    if args
+        .iter()
+        .any(|arg| arg.is_empty() || !arg.iter().all(|b| (0x21..=0x7e).contains(b)))
+    {
+        return Err(age_error("Invalid stanza arguments"));
+    }
+
+    // full lines are continued, the first shorter line ends the body
+    let mut body = Vec::new();
+    loop {
+        let line = next_line(data)?;
+        if line.len() > COLUMNS {
+            return Err(age_error("Stanza body line too long"));
+        }
+        body.extend_from_slice(line);
+        if line.len() < COLUMNS {
+            break;
+        }
+    }
+    Ok(Stanza {
+        args,
+        body: decode(&body)?,
+    })
+}
+
+/// the work factor of a scrypt stanza, a decimal without leading zeros
+fn parse_work_factor(arg: &[u8]) -> crate::Result<u8> {
+    if arg.is_empty() || arg[0] == b'0' || !arg.iter().all(u8::is_ascii_digit) {
+        return Err(age_error("Invalid work factor"));
+    }
+    match std::str::from_utf8(arg).ok().and_then(|arg| arg.parse::<u8>().ok()) {
+        Some(log_n) if log_n <= AGE_MAX_WORK_FACTOR => Ok(log_n),
+        _ => Err(crate::Error::AgeError(format!(
+            "The work factor `{}` exceeds the limit of `{}`",
+            arg.escape_ascii(),
+            AGE_MAX_WORK_FACTOR
+        ))),
+    }
+}
+
+/// unwrap the file key of the scrypt stanza, which has to be the only stanza
+fn unwrap_file_key(passphrase: &str, stanzas: &[Stanza]) -> crate::Result<Zeroizing<Vec<u8>>> {
+    let stanza = match stanzas {
+        [stanza] if stanza.args[0] == b"scrypt" => stanza,
+        _ if stanzas.iter().any(|stanza| stanza.args[0] == b"scrypt") => {
+            return Err(age_error("The scrypt stanza has to be the only stanza"))
+        }
+        _ => {
+            return Err(age_error(
+                "Not encrypted with a passphrase, only passphrases are supported",
+            ))
+        }
+    };
+
+    let (salt, log_n) = match &stanza.args[1..] {
+        [salt, log_n] => (decode(salt)?, parse_work_factor(log_n)?),
+        _ => return Err(age_error("Invalid scrypt stanza")),
+    };
+    if salt.len() != SALT_LEN || stanza.body.len() != FILE_KEY_LEN + TAG_LEN {
+        return Err(age_error("Invalid scrypt stanza"));
+    }
+
+    let wrap_key = scrypt_key(passphrase, &salt, log_n)?;
+    ChaCha20Poly1305::new((&*wrap_key).into())
+        .decrypt(&Nonce::default(), &stanza.body[..])
+        .map(Zeroizing::new)
+        .map_err(|_| crate::Error::AuthenticationFailed)
+}
+
+/// decrypt an age v1 file encrypted for `passphrase`, like the files of `age -p` and `export_age`.  Fails with
+/// `Error::AuthenticationFailed` for another passphrase, if the header MAC doesn't match or if a payload chunk was
+/// modified or truncated, and with `Error::AgeError` for malformed files, files without a passphrase stanza and work
+/// factors above `AGE_MAX_WORK_FACTOR`.
+pub fn import_age(passphrase: &str, data: &[u8]) -> crate::Result<Vec<u8>> {
+    if !data.starts_with(VERSION_LINE) {
+        return Err(age_error("Not an age v1 file"));
+    }
+
+    let mut rest = &data[VERSION_LINE.len()..];
+    let mut stanzas = Vec::new();
+    let (header_len, mac) = loop {
+        let offset = data.len() - rest.len();
+        let line = next_line(&mut rest)?;
+        if let Some(args) = line.strip_prefix(b"-> ") {
+            stanzas.push(parse_stanza(args, &mut rest)?);
+        } else if let Some(mac) = line.strip_prefix(b"--- ") {
+            break (offset + 3, decode(mac)?);
+        } else {
+            return Err(age_error("Invalid header line"));
+        }
+    };
+    if stanzas.is_empty() {
+        return Err(age_error("Missing recipient stanza"));
+    }
+
+    let file_key = unwrap_file_key(passphrase, &stanzas)?;
+    header_mac(&file_key, &data[..header_len])?
+        .verify_slice(&mac)
+        .map_err(|_| crate::Error::AuthenticationFailed)?;
+
+    if rest.len() < NONCE_LEN + TAG_LEN {
+        return Err(age_error("Truncated payload"));
+    }
+    let (nonce, payload) = rest.split_at(NONCE_LEN);
+    let payload_key = derive_key(&file_key, nonce, b"payload")?;
+    let cipher = ChaCha20Poly1305::new((&*payload_key).into());
+
+    let chunks = (payload.len() - 1) / (CHUNK_LEN + TAG_LEN) + 1;
+    let last_len = payload.len() - (chunks - 1) * (CHUNK_LEN + TAG_LEN);
+    // a final chunk without plaintext is only valid for empty files
+    if last_len < TAG_LEN || (last_len == TAG_LEN && chunks > 1) {
+        return Err(age_error("Truncated payload"));
+    }
+
+    let mut plain = try_alloc(payload.len() - chunks * TAG_LEN)?;
+    for (index, chunk) in payload.chunks(CHUNK_LEN + TAG_LEN).enumerate() {
+        let (cipher_text, tag) = chunk.split_at(chunk.len() - TAG_LEN);
+        let opened = &mut plain[index * CHUNK_LEN..index * CHUNK_LEN + cipher_text.len()];
+        opened.copy_from_slice(cipher_text);
+        let decrypted = cipher.decrypt_in_place_detached(
+            &chunk_nonce(index, index + 1 == chunks),
+            &[],
+            opened,
+            Tag::from_slice(tag),
+        );
+        if decrypted.is_err() {
+            plain.zeroize();
+            return Err(crate::Error::AuthenticationFailed);
+        }
+    }
+    Ok(plain)
+}
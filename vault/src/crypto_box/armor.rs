@@ -0,0 +1,284 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+use crate::{
+    base64::Base64,
+    crypto_box::{wipe, BoxProvider, Key},
+    ct::ct_eq,
+};
+
+use sha2::{Digest, Sha256};
+use zeroize::Zeroize;
+
+/// prefix of an armored key
+const PREFIX: &str = "vaultkey1";
+/// alphabet of the Base32 encoding, the same one Bech32 uses
+const CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+/// size of the embedded key length field
+const LEN_SIZE: usize = 2;
+/// size of the checksum
+const CHECKSUM_SIZE: usize = 4;
+
+/// the label of the PEM blocks of `Encrypt::encrypt_armored`
+pub const SEALED_DATA_LABEL: &str = "VAULT SEALED DATA";
+/// the number of Base64 characters on a line of a PEM block
+const PEM_LINE_LEN: usize = 64;
+/// the initial value of the CRC-24 of RFC 4880
+const CRC24_INIT: u32 = 0x00b7_04ce;
+/// the generator of the CRC-24 of RFC 4880
+const CRC24_POLY: u32 = 0x0186_4cfb;
+
+impl<T: BoxProvider> Key<T> {
+    /// encode the key as `vaultkey1` followed by the Base32 encoded key length, key bytes and a 4 byte checksum.
+    /// The checksum makes sure mistyped or truncated strings are rejected by `Key::from_armored`.
+    pub fn to_armored(&self) -> String {
+        let key = self.bytes();
+        let mut payload = Vec::with_capacity(LEN_SIZE + key.len() + CHECKSUM_SIZE);
+        payload.extend_from_slice(&(key.len() as u16).to_be_bytes());
+        payload.extend_from_slice(key);
+        let checksum = checksum(&payload);
+        payload.extend_from_slice(&checksum);
+
+        let mut armored = String::with_capacity(PREFIX.len() + (payload.len() * 8).div_ceil(5));
+        armored.push_str(PREFIX);
+        encode(&payload, &mut armored);
+        payload.zeroize();
+        armored
+    }
+
+    /// decode a key encoded by `Key::to_armored`.  Surrounding whitespace and the case of the string are ignored.
+    pub fn from_armored(s: &str) -> crate::Result<Self> {
+        let mut armored = s.trim().to_ascii_lowercase();
+        let payload = match armored.strip_prefix(PREFIX) {
+            Some(data) => decode(data),
+            None => Err(crate::Error::ArmorError(String::from("missing `vaultkey1` prefix"))),
+        };
+        armored.zeroize();
+        let payload = payload?;
+
+        let key = parse::<T>(&payload);
+        wipe(payload);
+        key
+    }
+}
+
+/// check and split a decoded payload into the key
+fn parse<T: BoxProvider>(payload: &[u8]) -> crate::Result<Key<T>> {
+    if payload.len() < LEN_SIZE + CHECKSUM_SIZE {
+        return Err(crate::Error::ArmorError(String::from("armored key is too short")));
+    }
+
+    let (data, actual) = payload.split_at(payload.len() - CHECKSUM_SIZE);
+    let expected = checksum(data);
+    if !ct_eq(&expected, actual) {
+        return Err(crate::Error::ArmorChecksumMismatch);
+    }
+
+    let (len, key) = data.split_at(LEN_SIZE);
+    let len = u16::from_be_bytes([len[0], len[1]]) as usize;
+    if len != key.len() {
+        return Err(crate::Error::ArmorError(format!(
+            "embedded length `{}` does not match the `{}` encoded key bytes",
+            len,
+            key.len()
+        )));
+    }
+    Key::load_from_slice(key)
+}
+
+/// first 4 bytes of SHA-256 over a domain separator and the data
+fn checksum(data: &[u8]) -> [u8; CHECKSUM_SIZE] {
+    let digest = Sha256::new().chain_update(PREFIX).chain_update(data).finalize();
+    let mut checksum = [0; CHECKSUM_SIZE];
+    checksum.copy_from_slice(&digest[..CHECKSUM_SIZE]);
+    checksum
+}
+
+/// append the Base32 encoding of `data` to `out`.  The last group is padded with zero bits.
+fn encode(data: &[u8], out: &mut String) {
+    let mut acc = 0u32;
+    let mut bits = 0;
+    for byte in data {
+        acc = (acc << 8) | *byte as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            out.push(CHARSET[((acc >> bits) & 0x1f) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        out.push(CHARSET[((acc << (5 - bits)) & 0x1f) as usize] as char);
+    }
+    acc.zeroize();
+}
+
+/// decode Base32 encoded `data`.  Fails on unknown characters and non-zero padding bits.
+fn decode(data: &str) -> crate::Result<Vec<u8>> {
+    let mut out = Vec::with_capacity(data.len() * 5 / 8);
+    let mut acc = 0u32;
+    let mut bits = 0;
+    for (index, c) in data.bytes().enumerate() {
+        let value = match CHARSET.iter().position(|x| *x == c) {
+            Some(value) => value as u32,
+            None => {
+                out.zeroize();
+                return Err(crate::Error::ArmorError(format!(
+                    "invalid character at index `{}`",
+                    index
+                )));
+            }
+        };
+        acc = (acc << 5) | value;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((acc >> bits) as u8);
+        }
+    }
+    let padding = acc & ((1 << bits) - 1);
+    acc.zeroize();
+    if bits >= 5 || padding != 0 {
+        out.zeroize();
+        return Err(crate::Error::ArmorError(String::from("invalid padding")));
+    }
+    Ok(out)
+}
+
+/// encode `bytes` as a PEM block, `-----BEGIN <label>-----`, the standard Base64 encoding in lines of 64 characters,
+/// an OpenPGP style checksum line of `=` and the Base64 encoded CRC-24 of RFC 4880 and `-----END <label>-----`.  The
+/// checksum makes sure blocks corrupted by copy and paste are rejected by `from_pem`.
+pub fn to_pem(label: &str, bytes: &[u8]) -> String {
+    let body = Base64::encode_standard(bytes);
+    let mut pem = String::with_capacity(body.len() + body.len() / PEM_LINE_LEN + 2 * label.len() + 40);
+    pem.push_str(&format!("-----BEGIN {}-----\n", label));
+    for line in body.as_bytes().chunks(PEM_LINE_LEN) {
+        pem.push_str(std::str::from_utf8(line).expect("base64 is ascii"));
+        pem.push('\n');
+    }
+    pem.push('=');
+    pem.push_str(&Base64::encode_standard(&crc24(bytes)));
+    pem.push_str(&format!("\n-----END {}-----\n", label));
+    pem
+}
+
+/// decode the first PEM block in `text` created by `to_pem`.  Lines may end with CRLF and be indented, text before
+/// and after the block is ignored and so are empty lines and the lengths of the lines in it.  Fails with
+/// `Error::ArmorLabelMismatch` if the block has another label than `label`, with `Error::ArmorChecksumMismatch` if
+/// the data doesn't match the checksum line and with `Error::ArmorError` if the block is malformed.
+pub fn from_pem(label: &str, text: &str) -> crate::Result<Vec<u8>> {
+    let mut lines = text.lines().map(str::trim).enumerate();
+    let found = loop {
+        match lines.next() {
+            Some((_, line)) => {
+                if let Some(found) = boundary(line, "BEGIN") {
+                    break found;
+                }
+            }
+            None => {
+                return Err(crate::Error::ArmorError(format!(
+                    "missing `-----BEGIN {}-----` line",
+                    label
+                )))
+            }
+        }
+    };
+    if found != label {
+        return Err(crate::Error::ArmorLabelMismatch {
+            expected: label.to_owned(),
+            found: found.to_owned(),
+        });
+    }
+
+    let mut body = String::new();
+    let mut checksum = None;
+    loop {
+        let (index, line) = lines
+            .next()
+            .ok_or_else(|| crate::Error::ArmorError(format!("missing `-----END {}-----` line", label)))?;
+        if let Some(end) = boundary(line, "END") {
+            if end != label {
+                return Err(crate::Error::ArmorError(format!(
+                    "`-----BEGIN {}-----` is closed by `-----END {}-----` on line `{}`",
+                    label,
+                    end,
+                    index + 1
+                )));
+            }
+            break;
+        }
+        if line.is_empty() {
+            continue;
+        }
+        if checksum.is_some() {
+            return Err(crate::Error::ArmorError(format!(
+                "unexpected data after the checksum on line `{}`",
+                index + 1
+            )));
+        }
+        if let Some(crc) = line.strip_prefix('=') {
+            checksum = Some((index, crc));
+            continue;
+        }
+        if let Some(c) = line
+            .chars()
+            .find(|c| !c.is_ascii_alphanumeric() && !matches!(c, '+' | '/' | '='))
+        {
+            return Err(crate::Error::ArmorError(format!(
+                "invalid character `{}` on line `{}`",
+                c.escape_default(),
+                index + 1
+            )));
+        }
+        body.push_str(line);
+    }
+
+    let (index, crc) = checksum.ok_or_else(|| crate::Error::ArmorError(String::from("missing checksum line")))?;
+    let expected = match Base64::decode_standard(crc.as_bytes()) {
+        Ok(expected) if expected.len() == 3 => expected,
+        _ => {
+            return Err(crate::Error::ArmorError(format!(
+                "invalid checksum on line `{}`",
+                index + 1
+            )))
+        }
+    };
+    let data = Base64::decode_standard(body.as_bytes())
+        .map_err(|_| crate::Error::ArmorError(String::from("invalid Base64 data")))?;
+    let actual = crc24(&data);
+    if !ct_eq(&expected, &actual) {
+        return Err(crate::Error::ArmorChecksumMismatch);
+    }
+    Ok(data)
+}
+
+/// the label of a `-----<kind> <label>-----` line
+fn boundary<'a>(line: &'a str, kind: &str) -> Option<&'a str> {
+    line.strip_prefix("-----")?
+        .strip_prefix(kind)?
+        .strip_prefix(' ')?
+        .strip_suffix("-----")
+}
+
+/// the CRC-24 of RFC 4880 over `data`
+fn crc24(data: &[u8]) -> [u8; 3] {
+    let mut crc = CRC24_INIT;
+    for byte in data {
+        crc ^= (*byte as u32) << 16;
+        for _ in 0..8 {
+            crc <<= 1;
+            if crc & 0x0100_0000 != 0 {
+                crc ^= CRC24_POLY;
+            }
+        }
+    }
+    let crc = crc.to_be_bytes();
+    [crc[1], crc[2], crc[3]]
+}
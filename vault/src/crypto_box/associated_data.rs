@@ -0,0 +1,68 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+/// Canonical AD made of a domain label and named fields, so AD of one protocol or record type can't be mistaken for
+/// AD of another.  Every part is prefixed by its big endian `u64` length, so `("ab", "c")` and `("a", "bc")` encode
+/// differently.
+///
+/// ```
+/// use vault::AssociatedData;
+///
+/// let record = AssociatedData::new("record").field("record_id", b"id").field("version", &[1]);
+/// let payload = AssociatedData::new("payload").field("record_id", b"id").field("version", &[1]);
+/// assert_ne!(record.as_bytes(), payload.as_bytes());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AssociatedData {
+    bytes: Vec<u8>,
+}
+
+impl AssociatedData {
+    /// prefix of the encoding, keeps it apart from raw AD
+    const MAGIC: &'static [u8] = b"vault-ad-v1";
+
+    /// start AD of the domain `domain`
+    pub fn new(domain: &'static str) -> Self {
+        let mut ad = Self {
+            bytes: Self::MAGIC.to_vec(),
+        };
+        ad.push(domain.as_bytes());
+        ad
+    }
+
+    /// append the field `name` with the value `value`.  The order of the fields matters.
+    pub fn field(mut self, name: &str, value: &[u8]) -> Self {
+        self.push(name.as_bytes());
+        self.push(value);
+        self
+    }
+
+    /// get the canonical bytes of the AD
+    pub fn finish(self) -> Vec<u8> {
+        self.bytes
+    }
+
+    /// get the canonical bytes of the AD without consuming it
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    fn push(&mut self, part: &[u8]) {
+        self.bytes.extend_from_slice(&(part.len() as u64).to_be_bytes());
+        self.bytes.extend_from_slice(part);
+    }
+}
+
+impl AsRef<[u8]> for AssociatedData {
+    fn as_ref(&self) -> &[u8] {
+        &self.bytes
+    }
+}
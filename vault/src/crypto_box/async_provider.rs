@@ -0,0 +1,90 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+use crate::crypto_box::{audit_open, audit_seal, convert_plaintext, BoxProvider, Key};
+
+use async_trait::async_trait;
+use std::{convert::TryFrom, fmt::Debug};
+
+/// An asynchronous provider for backends where sealing is a round trip, such as a remote KMS.  Keys stay typed:
+/// `Marker` is a `BoxProvider` that fixes the key length and generates the keys.  Every `BoxProvider` is an
+/// asynchronous provider which is its own marker.
+#[async_trait]
+pub trait AsyncBoxProvider {
+    /// the provider the keys of this provider are created with
+    type Marker: BoxProvider + Sync;
+    type Error: Debug + Send + From<crate::Error> + Into<crate::Error>;
+
+    /// seals some data into the crypto box using the `key` and the `ad`.
+    async fn box_seal(key: &Key<Self::Marker>, ad: &[u8], data: &[u8]) -> Result<Vec<u8>, Self::Error>;
+
+    /// opens a crypto box to get data using the `key` and the `ad`.
+    async fn box_open(key: &Key<Self::Marker>, ad: &[u8], data: &[u8]) -> Result<Vec<u8>, Self::Error>;
+
+    /// fills a buffer with random bytes.
+    async fn random_buf(buf: &mut [u8]) -> Result<(), Self::Error>;
+}
+
+#[async_trait]
+impl<P> AsyncBoxProvider for P
+where
+    P: BoxProvider + Sync,
+    P::Error: Send,
+{
+    type Marker = P;
+    type Error = P::Error;
+
+    async fn box_seal(key: &Key<P>, ad: &[u8], data: &[u8]) -> Result<Vec<u8>, P::Error> {
+        P::box_seal(key, ad, data)
+    }
+
+    async fn box_open(key: &Key<P>, ad: &[u8], data: &[u8]) -> Result<Vec<u8>, P::Error> {
+        P::box_open(key, ad, data)
+    }
+
+    async fn random_buf(buf: &mut [u8]) -> Result<(), P::Error> {
+        P::random_buf(buf)
+    }
+}
+
+/// Trait for data which can be encrypted with an `AsyncBoxProvider`
+#[async_trait]
+pub trait EncryptAsync<T: From<Vec<u8>>>: AsRef<[u8]> + Sync {
+    /// encrypts raw data and creates a type T from the ciphertext.  Counts as a use of the key, see
+    /// `Key::with_max_uses`.
+    async fn encrypt_async<P: AsyncBoxProvider>(&self, key: &Key<P::Marker>, ad: &[u8]) -> crate::Result<T> {
+        key.checkout_use()?;
+        let data = self.as_ref();
+        let sealed = audit_seal(
+            key,
+            ad,
+            data.len(),
+            P::box_seal(key, ad, data).await.map_err(Into::into),
+        )?;
+        Ok(T::from(sealed))
+    }
+}
+
+/// Trait for data which can be decrypted with an `AsyncBoxProvider`
+#[async_trait]
+pub trait DecryptAsync<E: Debug, T: TryFrom<Vec<u8>, Error = E>>: AsRef<[u8]> + Sync {
+    /// decrypts raw data and creates a new type T from the plaintext
+    async fn decrypt_async<P: AsyncBoxProvider>(&self, key: &Key<P::Marker>, ad: &[u8]) -> crate::Result<T> {
+        let data = self.as_ref();
+        let opened = audit_open(
+            key,
+            ad,
+            data.len(),
+            P::box_open(key, ad, data).await.map_err(Into::into),
+        )?;
+        convert_plaintext(opened)
+    }
+}
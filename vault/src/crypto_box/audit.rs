@@ -0,0 +1,136 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+use crate::crypto_box::{BoxProvider, Key, KeyFingerprint};
+
+use std::{
+    cell::Cell,
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        RwLock,
+    },
+    time::SystemTime,
+};
+
+use sha2::{Digest, Sha256};
+
+/// the sink events are reported to, see `set_audit_sink`
+static SINK: RwLock<Option<Box<dyn AuditSink>>> = RwLock::new(None);
+/// the number of events the sink panicked on
+static FAILURES: AtomicU64 = AtomicU64::new(0);
+
+thread_local! {
+    /// set while the sink records an event, so seals and opens of the sink itself aren't reported again
+    static RECORDING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// A receiver of the `AuditEvent`s of all seals and opens of the vault, see `set_audit_sink`.
+pub trait AuditSink: Send + Sync {
+    /// record an event.  Called on the thread of the operation right after it finished, a panic is caught and
+    /// counted in `audit_failures`.
+    fn record(&self, event: AuditEvent);
+}
+
+/// The kind of operation of an `AuditEvent`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum AuditOperation {
+    /// a box was sealed
+    Seal,
+    /// a box was opened, or failed to open
+    Open,
+    /// a random key was generated
+    KeyGenerate,
+    /// a key was dropped and wiped
+    KeyDrop,
+}
+
+/// An operation of the vault with a key.  Carries neither plaintext nor key bytes, only the fingerprint of the key and
+/// a hash of the AD.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditEvent {
+    /// the kind of operation
+    pub operation: AuditOperation,
+    /// the fingerprint of the key which sealed or opened the box, or which was generated or dropped
+    pub fingerprint: KeyFingerprint,
+    /// the SHA-256 of the AD of a seal or open, `None` for key events
+    pub ad_hash: Option<[u8; 32]>,
+    /// the length of the plaintext of a seal or of the box of an open, 0 for key events
+    pub payload_len: usize,
+    /// when the operation finished
+    pub timestamp: SystemTime,
+    /// whether the operation succeeded, a box that doesn't open is reported as failure
+    pub success: bool,
+}
+
+/// report all seals and opens of boxes, through `Encrypt`, `Decrypt` and the other APIs of the vault, as well as the
+/// generation of random keys and drops of keys to `sink`.  Replaces the previous sink.  The raw `BoxProvider` methods
+/// aren't reported.
+pub fn set_audit_sink<S: AuditSink + 'static>(sink: S) {
+    *SINK.write().unwrap_or_else(|e| e.into_inner()) = Some(Box::new(sink));
+}
+
+/// stop reporting events and drop the sink
+pub fn clear_audit_sink() {
+    *SINK.write().unwrap_or_else(|e| e.into_inner()) = None;
+}
+
+/// the number of events the sink panicked on since the start of the process.  The panics don't reach the operations.
+pub fn audit_failures() -> u64 {
+    FAILURES.load(Ordering::Relaxed)
+}
+
+/// report an event to the sink, if there is one.  The event is only built if it is reported.
+fn record(event: impl FnOnce() -> AuditEvent) {
+    if RECORDING.with(Cell::get) {
+        return;
+    }
+    let sink = SINK.read().unwrap_or_else(|e| e.into_inner());
+    if let Some(sink) = sink.as_ref() {
+        RECORDING.with(|recording| recording.set(true));
+        let recorded = panic::catch_unwind(AssertUnwindSafe(|| sink.record(event())));
+        RECORDING.with(|recording| recording.set(false));
+        if recorded.is_err() {
+            FAILURES.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// report a seal or an open of `len` bytes with the `key` and the `ad`
+pub(crate) fn record_box<B: BoxProvider>(
+    operation: AuditOperation,
+    key: &Key<B>,
+    ad: &[u8],
+    len: usize,
+    success: bool,
+) {
+    record(|| AuditEvent {
+        operation,
+        fingerprint: key.fingerprint(),
+        ad_hash: Some(Sha256::digest(ad).into()),
+        payload_len: len,
+        timestamp: SystemTime::now(),
+        success,
+    })
+}
+
+/// report the generation or the drop of the key with the bytes `key`
+pub(crate) fn record_key(operation: AuditOperation, key: &[u8]) {
+    record(|| AuditEvent {
+        operation,
+        fingerprint: KeyFingerprint::of(key),
+        ad_hash: None,
+        payload_len: 0,
+        timestamp: SystemTime::now(),
+        success: true,
+    })
+}
@@ -0,0 +1,95 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+use crate::crypto_box::{open_box, seal_box, BoxProvider, Key};
+
+#[cfg(not(feature = "parallel"))]
+use std::iter::FromIterator;
+
+#[cfg(feature = "parallel")]
+use rayon::iter::{FromParallelIterator, IntoParallelRefIterator, ParallelIterator};
+use zeroize::Zeroizing;
+
+/// apply `f` to the data and the AD of every item and collect the results in the order of the items
+#[cfg(not(feature = "parallel"))]
+fn map_items<D, R, C>(items: &[(D, &[u8])], f: impl Fn(&[u8], &[u8]) -> R + Sync + Send) -> C
+where
+    D: AsRef<[u8]> + Sync,
+    C: FromIterator<R>,
+{
+    items.iter().map(|(data, ad)| f(data.as_ref(), ad)).collect()
+}
+
+/// apply `f` to the data and the AD of every item on the rayon thread pool and collect the results in the order of
+/// the items
+#[cfg(feature = "parallel")]
+fn map_items<D, R, C>(items: &[(D, &[u8])], f: impl Fn(&[u8], &[u8]) -> R + Sync + Send) -> C
+where
+    D: AsRef<[u8]> + Sync,
+    R: Send,
+    C: FromParallelIterator<R>,
+{
+    items.par_iter().map(|(data, ad)| f(data.as_ref(), ad)).collect()
+}
+
+fn seal<B: BoxProvider>(key: &Key<B>, ad: &[u8], data: &[u8]) -> crate::Result<Vec<u8>> {
+    key.checkout_use()?;
+    seal_box(key, ad, data)
+}
+
+fn open<B: BoxProvider>(key: &Key<B>, ad: &[u8], data: &[u8]) -> crate::Result<Zeroizing<Vec<u8>>> {
+    open_box(key, ad, data).map(Zeroizing::new)
+}
+
+/// seal the data of every `(data, ad)` item with its AD.  The boxes are returned in the order of the items, with the
+/// `parallel` feature they are sealed on the rayon thread pool.  Fails with the first error, every item counts as a
+/// use of the key, see `Key::with_max_uses`.
+pub fn encrypt_many<B, D>(key: &Key<B>, items: &[(D, &[u8])]) -> crate::Result<Vec<Vec<u8>>>
+where
+    B: BoxProvider + Sync,
+    D: AsRef<[u8]> + Sync,
+{
+    map_items(items, |data, ad| seal(key, ad, data))
+}
+
+/// like `encrypt_many` but returns the result of every item, so failing items don't abort the batch
+pub fn encrypt_many_collect_errors<B, D>(key: &Key<B>, items: &[(D, &[u8])]) -> Vec<crate::Result<Vec<u8>>>
+where
+    B: BoxProvider + Sync,
+    D: AsRef<[u8]> + Sync,
+{
+    map_items(items, |data, ad| seal(key, ad, data))
+}
+
+/// open the box of every `(data, ad)` item with its AD.  The plaintexts are returned in the order of the items, with
+/// the `parallel` feature they are opened on the rayon thread pool.  Fails with the first error, the plaintexts
+/// opened until then are wiped.
+pub fn decrypt_many<B, D>(key: &Key<B>, items: &[(D, &[u8])]) -> crate::Result<Vec<Vec<u8>>>
+where
+    B: BoxProvider + Sync,
+    D: AsRef<[u8]> + Sync,
+{
+    let opened: crate::Result<Vec<Zeroizing<Vec<u8>>>> = map_items(items, |data, ad| open(key, ad, data));
+    let opened = opened?;
+    Ok(opened
+        .into_iter()
+        .map(|mut plain| std::mem::take(&mut *plain))
+        .collect())
+}
+
+/// like `decrypt_many` but returns the result of every item, so failing items don't abort the batch
+pub fn decrypt_many_collect_errors<B, D>(key: &Key<B>, items: &[(D, &[u8])]) -> Vec<crate::Result<Vec<u8>>>
+where
+    B: BoxProvider + Sync,
+    D: AsRef<[u8]> + Sync,
+{
+    map_items(items, |data, ad| open_box(key, ad, data))
+}
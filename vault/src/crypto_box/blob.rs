@@ -0,0 +1,194 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+use crate::{
+    crypto_box::{open_box, seal_box, BoxProvider, Key},
+    ct::ct_eq,
+};
+
+use std::convert::{TryFrom, TryInto};
+
+use sha2::{Digest, Sha256};
+
+/// A sealed blob in the crate's canonical wire format, which doesn't depend on a serde backend.  Version 1 is laid
+/// out as
+///
+/// | bytes | field |
+/// |---|---|
+/// | 1 | the format version |
+/// | 2 | the little endian length of the provider id, 4 |
+/// | 4 | the `BoxProvider::box_id` of the provider |
+/// | 2 | the little endian length of the AD hash, 32 |
+/// | 32 | the SHA-256 hash of the AD |
+/// | 4 | the little endian length of the ciphertext |
+/// | n | the ciphertext |
+///
+/// The box is sealed with the encoded header before the ciphertext length followed by the AD.  With the
+/// `serde-base64` feature the blob serializes as its canonical encoding, a base64 string in human readable formats.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SealedBlob {
+    /// the format version
+    pub version: u8,
+    /// the id of the provider that sealed the ciphertext
+    pub provider_id: [u8; 4],
+    /// the SHA-256 hash of the AD the ciphertext was sealed with
+    pub ad_hash: [u8; 32],
+    /// the sealed data
+    pub ciphertext: Vec<u8>,
+}
+
+fn truncated() -> crate::Error {
+    crate::Error::crypto("parse blob", "Truncated blob")
+}
+
+/// split `len` bytes off the front of `data`
+fn take<'a>(data: &mut &'a [u8], len: usize) -> crate::Result<&'a [u8]> {
+    if data.len() < len {
+        return Err(truncated());
+    }
+    let (front, rest) = data.split_at(len);
+    *data = rest;
+    Ok(front)
+}
+
+/// split a field with a little endian `u16` length prefix of exactly `N` bytes off the front of `data`
+fn take_field<const N: usize>(data: &mut &[u8]) -> crate::Result<[u8; N]> {
+    let len = u16::from_le_bytes(take(data, 2)?.try_into().expect("2 bytes"));
+    if len as usize != N {
+        return Err(crate::Error::crypto("parse blob", "Invalid blob field length"));
+    }
+    Ok(take(data, N)?.try_into().expect("N bytes"))
+}
+
+impl SealedBlob {
+    /// the current format version
+    pub const VERSION: u8 = 1;
+    /// the length of the header of version 1, including the ciphertext length
+    pub const HEADER_LEN: usize = 45;
+
+    /// seal `data` into a blob
+    pub fn seal<B: BoxProvider>(key: &Key<B>, ad: &[u8], data: &[u8]) -> crate::Result<Self> {
+        let mut blob = Self {
+            version: Self::VERSION,
+            provider_id: B::box_id(),
+            ad_hash: Sha256::digest(ad).into(),
+            ciphertext: Vec::new(),
+        };
+        blob.ciphertext = seal_box(key, &[&blob.authenticated_header()[..], ad].concat(), data)?;
+        if blob.ciphertext.len() > u32::MAX as usize {
+            return Err(crate::Error::PayloadTooLarge {
+                len: blob.ciphertext.len() as u64,
+                limit: u32::MAX as usize,
+            });
+        }
+        Ok(blob)
+    }
+
+    /// open the blob.  Fails with `Error::ProviderMismatch` if it was sealed by another provider and with
+    /// `Error::AuthenticationFailed` if it was sealed with another AD, before the box is opened.
+    pub fn open<B: BoxProvider>(&self, key: &Key<B>, ad: &[u8]) -> crate::Result<Vec<u8>> {
+        if self.version != Self::VERSION {
+            return Err(crate::Error::UnsupportedVersion(self.version));
+        }
+        if self.provider_id != B::box_id() {
+            return Err(crate::Error::ProviderMismatch {
+                expected: B::box_id().escape_ascii().to_string(),
+                found: self.provider_id.escape_ascii().to_string(),
+            });
+        }
+        if !ct_eq(&self.ad_hash, &Sha256::digest(ad)) {
+            return Err(crate::Error::AuthenticationFailed);
+        }
+
+        open_box(key, &[&self.authenticated_header()[..], ad].concat(), &self.ciphertext)
+    }
+
+    /// the header without the ciphertext length
+    fn authenticated_header(&self) -> Vec<u8> {
+        let mut header = Vec::with_capacity(Self::HEADER_LEN);
+        header.push(self.version);
+        header.extend_from_slice(&(self.provider_id.len() as u16).to_le_bytes());
+        header.extend_from_slice(&self.provider_id);
+        header.extend_from_slice(&(self.ad_hash.len() as u16).to_le_bytes());
+        header.extend_from_slice(&self.ad_hash);
+        header
+    }
+
+    /// encode the blob in the canonical format
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.authenticated_header();
+        bytes.reserve(4 + self.ciphertext.len());
+        bytes.extend_from_slice(&(self.ciphertext.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&self.ciphertext);
+        bytes
+    }
+
+    /// parse a blob of the canonical format.  Fails with `Error::UnsupportedVersion` for versions other than
+    /// `VERSION`, and if the data is truncated, a length doesn't match or the blob is followed by trailing bytes.
+    pub fn from_bytes(mut data: &[u8]) -> crate::Result<Self> {
+        let version = take(&mut data, 1)?[0];
+        if version != Self::VERSION {
+            return Err(crate::Error::UnsupportedVersion(version));
+        }
+
+        let provider_id = take_field::<4>(&mut data)?;
+        let ad_hash = take_field::<32>(&mut data)?;
+        let len = u32::from_le_bytes(take(&mut data, 4)?.try_into().expect("4 bytes"));
+        let ciphertext = take(&mut data, len as usize)?.to_vec();
+        if !data.is_empty() {
+            return Err(crate::Error::crypto("parse blob", "Trailing data after the blob"));
+        }
+
+        Ok(Self {
+            version,
+            provider_id,
+            ad_hash,
+            ciphertext,
+        })
+    }
+}
+
+impl TryFrom<Vec<u8>> for SealedBlob {
+    type Error = crate::Error;
+
+    fn try_from(data: Vec<u8>) -> crate::Result<Self> {
+        Self::from_bytes(&data)
+    }
+}
+
+impl TryFrom<&[u8]> for SealedBlob {
+    type Error = crate::Error;
+
+    fn try_from(data: &[u8]) -> crate::Result<Self> {
+        Self::from_bytes(data)
+    }
+}
+
+impl From<SealedBlob> for Vec<u8> {
+    fn from(blob: SealedBlob) -> Self {
+        blob.to_bytes()
+    }
+}
+
+#[cfg(feature = "serde-base64")]
+impl serde::Serialize for SealedBlob {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        crate::serde_base64::serialize(&self.to_bytes(), serializer)
+    }
+}
+
+#[cfg(feature = "serde-base64")]
+impl<'de> serde::Deserialize<'de> for SealedBlob {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let bytes = crate::serde_base64::deserialize(deserializer)?;
+        Self::from_bytes(&bytes).map_err(serde::de::Error::custom)
+    }
+}
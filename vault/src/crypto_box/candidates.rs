@@ -0,0 +1,32 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+use crate::crypto_box::{check_box_len, open_box, BoxProvider, Key};
+
+/// open `data` with the first of `keys` that opens it, and return the index of that key with the plaintext.  Data
+/// shorter than `BoxProvider::box_overhead` can't be a box of any key and fails with `Error::MalformedCiphertext`
+/// before a key is tried, as does an empty list of keys.  If every key fails, the error is `Error::NoMatchingKey`.
+pub fn open_with_any<B: BoxProvider>(keys: &[&Key<B>], ad: &[u8], data: &[u8]) -> crate::Result<(usize, Vec<u8>)> {
+    if keys.is_empty() {
+        return Err(crate::Error::InterfaceErrorDetailed(String::from("No candidate keys")));
+    }
+    check_box_len(data, B::box_overhead())?;
+
+    for (index, key) in keys.iter().enumerate() {
+        match open_box(key, ad, data) {
+            Ok(plain) => return Ok((index, plain)),
+            // running out of memory doesn't depend on the key
+            Err(e @ crate::Error::MemoryError(_)) => return Err(e),
+            Err(_) => {}
+        }
+    }
+    Err(crate::Error::NoMatchingKey { attempts: keys.len() })
+}
@@ -0,0 +1,254 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+use crate::crypto_box::{
+    open_box,
+    progress::{check_cancelled, NoProgress, Progress},
+    seal_box, BoxProvider, Key,
+};
+
+use std::convert::{TryFrom, TryInto};
+
+#[cfg(not(feature = "serde-base64"))]
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroize;
+
+/// A payload sealed by `seal_chunked` as a sequence of boxes.  Every chunk is sealed with the caller's AD followed
+/// by the big endian `u64` index of the chunk and the total number of chunks, so each chunk can be opened on its own
+/// with `open_chunk` while reordered, duplicated or missing chunks fail to open.
+///
+/// Serializes as its compact framing, see `to_bytes`.  With the `serde-base64` feature the framing is a base64
+/// string in human readable formats, see `serde_base64`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(
+    not(feature = "serde-base64"),
+    derive(Serialize, Deserialize),
+    serde(try_from = "Vec<u8>", into = "Vec<u8>")
+)]
+pub struct ChunkedCiphertext {
+    /// the sealed chunks in order
+    chunks: Vec<Vec<u8>>,
+}
+
+/// the AD of the chunk `index` of `total`
+pub(crate) fn chunk_ad(ad: &[u8], index: usize, total: usize) -> Vec<u8> {
+    [ad, &(index as u64).to_be_bytes(), &(total as u64).to_be_bytes()].concat()
+}
+
+pub(crate) fn truncated() -> crate::Error {
+    crate::Error::crypto("parse chunked ciphertext", "Truncated chunked ciphertext")
+}
+
+/// read a big endian `u32` length from the front of `data`
+pub(crate) fn take_len(data: &mut &[u8]) -> crate::Result<usize> {
+    if data.len() < 4 {
+        return Err(truncated());
+    }
+    let (len, rest) = data.split_at(4);
+    *data = rest;
+    Ok(u32::from_be_bytes(len.try_into().expect("4 bytes")) as usize)
+}
+
+/// split `data` into chunks of `chunk_size` bytes and seal each of them.  The last chunk holds the rest of the data,
+/// empty data is sealed as a single empty chunk.  Every chunk counts as a use of the key, see `Key::with_max_uses`.
+pub fn seal_chunked<B: BoxProvider>(
+    key: &Key<B>,
+    ad: &[u8],
+    data: &[u8],
+    chunk_size: usize,
+) -> crate::Result<ChunkedCiphertext> {
+    seal_chunked_with_progress(key, ad, data, chunk_size, &NoProgress)
+}
+
+/// like `seal_chunked` but reports the number of sealed chunks to `progress` and fails with `Error::Cancelled`
+/// before the next chunk once it is cancelled
+pub fn seal_chunked_with_progress<B: BoxProvider>(
+    key: &Key<B>,
+    ad: &[u8],
+    data: &[u8],
+    chunk_size: usize,
+    progress: &dyn Progress,
+) -> crate::Result<ChunkedCiphertext> {
+    let total = chunk_total::<B>(data.len(), chunk_size)?;
+    let mut chunks = Vec::with_capacity(total);
+    for index in 0..total {
+        check_cancelled(progress)?;
+        let chunk = &data[(index * chunk_size).min(data.len())..((index + 1) * chunk_size).min(data.len())];
+        key.checkout_use()?;
+        chunks.push(seal_box(key, &chunk_ad(ad, index, total), chunk)?);
+        progress.report(index as u64 + 1, Some(total as u64));
+    }
+    Ok(ChunkedCiphertext { chunks })
+}
+
+/// the number of chunks of `chunk_size` bytes for `len` bytes of data.  Fails if the boxes of the chunks or their
+/// number don't fit the `u32` lengths of the framing.
+pub(crate) fn chunk_total<B: BoxProvider>(len: usize, chunk_size: usize) -> crate::Result<usize> {
+    if chunk_size == 0 || chunk_size.saturating_add(B::box_overhead()) > u32::MAX as usize {
+        return Err(crate::Error::InterfaceErrorDetailed(format!(
+            "Invalid chunk size `{}`",
+            chunk_size
+        )));
+    }
+
+    let total = len.max(1).div_ceil(chunk_size);
+    if total > u32::MAX as usize {
+        return Err(crate::Error::InterfaceErrorDetailed(format!(
+            "Too many chunks: `{}`",
+            total
+        )));
+    }
+    Ok(total)
+}
+
+/// the error of empty chunked ciphertexts, `seal_chunked` seals empty data as one empty chunk so no chunks at all
+/// means they were dropped
+pub(crate) fn missing_chunks() -> crate::Error {
+    crate::Error::crypto("open chunked", "Missing chunks")
+}
+
+/// the error of data after the last chunk
+pub(crate) fn trailing_data() -> crate::Error {
+    crate::Error::crypto("parse chunked ciphertext", "Trailing data after the chunked ciphertext")
+}
+
+/// open all chunks of `chunked` and join them.  Fails if any chunk doesn't open, which includes chunks that were
+/// reordered, duplicated or dropped, and if there are no chunks at all.
+pub fn open_chunked<B: BoxProvider>(key: &Key<B>, ad: &[u8], chunked: &ChunkedCiphertext) -> crate::Result<Vec<u8>> {
+    open_chunked_with_progress(key, ad, chunked, &NoProgress)
+}
+
+/// like `open_chunked` but reports the number of opened chunks to `progress` and fails with `Error::Cancelled`
+/// before the next chunk once it is cancelled.  The plaintexts opened until then are wiped.
+pub fn open_chunked_with_progress<B: BoxProvider>(
+    key: &Key<B>,
+    ad: &[u8],
+    chunked: &ChunkedCiphertext,
+    progress: &dyn Progress,
+) -> crate::Result<Vec<u8>> {
+    if chunked.is_empty() {
+        return Err(missing_chunks());
+    }
+
+    let mut data = Vec::new();
+    for index in 0..chunked.len() {
+        let opened = check_cancelled(progress).and_then(|_| chunked.open_chunk(key, ad, index));
+        let mut chunk = match opened {
+            Ok(chunk) => chunk,
+            Err(e) => {
+                data.zeroize();
+                return Err(e);
+            }
+        };
+        if data.capacity() - data.len() < chunk.len() {
+            // grow into a new buffer, a reallocation would leave unwiped copies behind
+            let mut grown = Vec::with_capacity((2 * data.capacity()).max(data.len() + chunk.len()));
+            grown.extend_from_slice(&data);
+            data.zeroize();
+            data = grown;
+        }
+        data.extend_from_slice(&chunk);
+        chunk.zeroize();
+        progress.report(index as u64 + 1, Some(chunked.len() as u64));
+    }
+    Ok(data)
+}
+
+impl ChunkedCiphertext {
+    /// the number of chunks
+    pub fn len(&self) -> usize {
+        self.chunks.len()
+    }
+
+    /// whether there are no chunks.  `seal_chunked` always creates at least one chunk, such a ciphertext doesn't open.
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+
+    /// get the sealed chunks
+    pub fn chunks(&self) -> &[Vec<u8>] {
+        &self.chunks
+    }
+
+    /// verify and open the chunk `index` on its own.  Fails with `Error::InterfaceErrorDetailed` if there is no such
+    /// chunk.
+    pub fn open_chunk<B: BoxProvider>(&self, key: &Key<B>, ad: &[u8], index: usize) -> crate::Result<Vec<u8>> {
+        let chunk = self.chunks.get(index).ok_or_else(|| {
+            crate::Error::InterfaceErrorDetailed(format!("No chunk `{}`, there are `{}` chunks", index, self.len()))
+        })?;
+        open_box(key, &chunk_ad(ad, index, self.len()), chunk)
+    }
+
+    /// the compact framing: the big endian `u32` number of chunks followed by every chunk prefixed by its big endian
+    /// `u32` length.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(4 + self.chunks.iter().map(|chunk| 4 + chunk.len()).sum::<usize>());
+        bytes.extend_from_slice(&(self.chunks.len() as u32).to_be_bytes());
+        for chunk in &self.chunks {
+            bytes.extend_from_slice(&(chunk.len() as u32).to_be_bytes());
+            bytes.extend_from_slice(chunk);
+        }
+        bytes
+    }
+
+    /// parse the framing of `to_bytes`.  Fails if the data is truncated or followed by trailing bytes.
+    pub fn from_bytes(mut data: &[u8]) -> crate::Result<Self> {
+        let total = take_len(&mut data)?;
+        // every chunk takes at least its length prefix, don't trust the count any further
+        if total > data.len() / 4 {
+            return Err(truncated());
+        }
+
+        let mut chunks = Vec::with_capacity(total);
+        for _ in 0..total {
+            let len = take_len(&mut data)?;
+            if len > data.len() {
+                return Err(truncated());
+            }
+            let (chunk, rest) = data.split_at(len);
+            chunks.push(chunk.to_vec());
+            data = rest;
+        }
+        if !data.is_empty() {
+            return Err(trailing_data());
+        }
+        Ok(Self { chunks })
+    }
+}
+
+impl TryFrom<Vec<u8>> for ChunkedCiphertext {
+    type Error = crate::Error;
+
+    fn try_from(data: Vec<u8>) -> crate::Result<Self> {
+        Self::from_bytes(&data)
+    }
+}
+
+impl From<ChunkedCiphertext> for Vec<u8> {
+    fn from(chunked: ChunkedCiphertext) -> Self {
+        chunked.to_bytes()
+    }
+}
+
+#[cfg(feature = "serde-base64")]
+impl serde::Serialize for ChunkedCiphertext {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        crate::serde_base64::serialize(&self.to_bytes(), serializer)
+    }
+}
+
+#[cfg(feature = "serde-base64")]
+impl<'de> serde::Deserialize<'de> for ChunkedCiphertext {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let bytes = crate::serde_base64::deserialize(deserializer)?;
+        Self::from_bytes(&bytes).map_err(serde::de::Error::custom)
+    }
+}
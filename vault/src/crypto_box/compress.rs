@@ -0,0 +1,108 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+use crate::crypto_box::{try_alloc, try_copy};
+
+use std::io::Read;
+
+use zeroize::Zeroize;
+
+/// the largest plaintext `Decrypt::decrypt_decompressed` inflates a box into, 64 MiB
+pub const MAX_DECOMPRESSED_LEN: usize = 64 << 20;
+
+/// the plaintext is stored as it is
+const STORED: u8 = 0;
+/// the plaintext is a zstd frame
+const ZSTD: u8 = 1;
+
+/// the size of the first output buffer of `decompress`, it doubles until the output fits
+const INITIAL_LEN: usize = 4096;
+
+/// compress `data` with zstd at `level` and prefix it with the compression id.  Data that doesn't get smaller is
+/// stored uncompressed.
+pub(crate) fn compress(data: &[u8], level: i32) -> crate::Result<Vec<u8>> {
+    let mut buf = try_alloc(1 + zstd::zstd_safe::compress_bound(data.len()))?;
+    let compressed = zstd::bulk::Compressor::new(level)
+        .and_then(|mut compressor| compressor.compress_to_buffer(data, &mut buf[1..]))
+        .map_err(|e| crate::Error::CompressionError(e.to_string()));
+
+    match compressed {
+        Ok(len) if len < data.len() => {
+            buf[0] = ZSTD;
+            buf.truncate(1 + len);
+            Ok(buf)
+        }
+        compressed => {
+            buf.zeroize();
+            compressed?;
+
+            let mut stored = try_alloc(1 + data.len())?;
+            stored[0] = STORED;
+            stored[1..].copy_from_slice(data);
+            Ok(stored)
+        }
+    }
+}
+
+/// undo `compress`.  Fails with `Error::DecompressionLimit` as soon as the output grows beyond `limit` bytes.  The
+/// buffers holding the output are wiped when they are replaced and on failure; zstd's own window isn't.
+pub(crate) fn decompress(data: &[u8], limit: usize) -> crate::Result<Vec<u8>> {
+    match data.split_first() {
+        Some((&STORED, stored)) if stored.len() > limit => Err(crate::Error::DecompressionLimit(limit)),
+        Some((&STORED, stored)) => try_copy(stored),
+        Some((&ZSTD, frame)) => {
+            let decoder = zstd::stream::read::Decoder::with_buffer(frame)
+                .map_err(|e| crate::Error::CompressionError(e.to_string()))?;
+            let mut out = Vec::new();
+            match read_limited(decoder, limit, &mut out) {
+                Ok(len) => {
+                    out.truncate(len);
+                    Ok(out)
+                }
+                Err(e) => {
+                    out.zeroize();
+                    Err(e)
+                }
+            }
+        }
+        Some((id, _)) => Err(crate::Error::CompressionError(format!(
+            "Unknown compression id `{}`",
+            id
+        ))),
+        None => Err(crate::Error::CompressionError(String::from("Missing compression id"))),
+    }
+}
+
+/// read `reader` into `out` and return the number of bytes read.  `out` grows by doubling into fresh buffers so no
+/// unwiped copies are left behind by a reallocation.
+fn read_limited(mut reader: impl Read, limit: usize, out: &mut Vec<u8>) -> crate::Result<usize> {
+    let mut len = 0;
+    loop {
+        if len == out.len() {
+            if len > limit {
+                return Err(crate::Error::DecompressionLimit(limit));
+            }
+            let mut grown = try_alloc((2 * len).clamp(INITIAL_LEN, limit.saturating_add(1)))?;
+            grown[..len].copy_from_slice(&out[..len]);
+            out.zeroize();
+            *out = grown;
+        }
+
+        match reader.read(&mut out[len..]) {
+            Ok(0) => return Ok(len),
+            Ok(n) => len += n,
+            Err(e) => return Err(crate::Error::CompressionError(e.to_string())),
+        }
+        if len > limit {
+            return Err(crate::Error::DecompressionLimit(limit));
+        }
+    }
+}
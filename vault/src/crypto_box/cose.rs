@@ -0,0 +1,225 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+use crate::crypto_box::{open_box, seal_box, BoxProvider, Key};
+
+use std::convert::TryFrom;
+
+use ciborium::value::Value;
+
+/// the CBOR tag of a COSE_Encrypt0 structure
+const TAG: u64 = 16;
+/// the header labels of RFC 9052 section 3.1
+const ALG: i64 = 1;
+const CRIT: i64 = 2;
+const IV: i64 = 5;
+const PARTIAL_IV: i64 = 6;
+
+/// the COSE algorithm registered for the boxes of the provider with `box_id`
+pub(crate) fn algorithm_for(box_id: [u8; 4]) -> Option<i64> {
+    match &box_id {
+        b"a256" => Some(3),
+        b"c20p" => Some(24),
+        _ => None,
+    }
+}
+
+/// the name of an AEAD algorithm of the IANA COSE registry
+fn algorithm_name(algorithm: i128) -> Option<&'static str> {
+    Some(match algorithm {
+        1 => "A128GCM",
+        2 => "A192GCM",
+        3 => "A256GCM",
+        10 => "AES-CCM-16-64-128",
+        11 => "AES-CCM-16-64-256",
+        12 => "AES-CCM-64-64-128",
+        13 => "AES-CCM-64-64-256",
+        24 => "ChaCha20/Poly1305",
+        30 => "AES-CCM-16-128-128",
+        31 => "AES-CCM-16-128-256",
+        32 => "AES-CCM-64-128-128",
+        33 => "AES-CCM-64-128-256",
+        _ => return None,
+    })
+}
+
+fn describe(algorithm: i128) -> String {
+    match algorithm_name(algorithm) {
+        Some(name) => format!("{} ({})", algorithm, name),
+        None => algorithm.to_string(),
+    }
+}
+
+fn cose_error(message: &str) -> crate::Error {
+    crate::Error::CoseError(String::from(message))
+}
+
+fn expected_algorithm<B: BoxProvider>() -> crate::Result<i64> {
+    B::cose_algorithm().ok_or_else(|| {
+        crate::Error::CoseError(format!(
+            "Provider `{}` has no COSE algorithm",
+            B::box_id().escape_ascii()
+        ))
+    })
+}
+
+fn encode(value: &Value) -> crate::Result<Vec<u8>> {
+    let mut encoded = Vec::new();
+    ciborium::ser::into_writer(value, &mut encoded)
+        .map_err(|e| crate::Error::CoseError(format!("Unable to encode CBOR: {}", e)))?;
+    Ok(encoded)
+}
+
+/// decode a single CBOR item, trailing data is rejected
+fn decode(data: &[u8]) -> crate::Result<Value> {
+    let mut rest = data;
+    let value =
+        ciborium::de::from_reader(&mut rest).map_err(|e| crate::Error::CoseError(format!("Invalid CBOR: {}", e)))?;
+    if !rest.is_empty() {
+        return Err(cose_error("Trailing data after the COSE structure"));
+    }
+    Ok(value)
+}
+
+/// the Enc_structure of RFC 9052 section 5.3, which is the AD of the box
+fn enc_structure(protected: &[u8], external_aad: &[u8]) -> crate::Result<Vec<u8>> {
+    encode(&Value::Array(vec![
+        Value::Text(String::from("Encrypt0")),
+        Value::Bytes(protected.to_vec()),
+        Value::Bytes(external_aad.to_vec()),
+    ]))
+}
+
+/// seal `plaintext` into a tagged COSE_Encrypt0 structure of RFC 9052.  The protected header holds the
+/// `BoxProvider::cose_algorithm` of the provider, the unprotected header the IV and `ad` is the external AAD.  Fails
+/// for providers without a COSE algorithm.  Counts as a use of `key`, see `Key::with_max_uses`.
+pub fn to_cose_encrypt0<B: BoxProvider>(key: &Key<B>, ad: &[u8], plaintext: &[u8]) -> crate::Result<Vec<u8>> {
+    let algorithm = expected_algorithm::<B>()?;
+    let protected = encode(&Value::Map(vec![(Value::from(ALG), Value::from(algorithm))]))?;
+
+    key.checkout_use()?;
+    let mut iv = seal_box(key, &enc_structure(&protected, ad)?, plaintext)?;
+    if iv.len() < B::box_nonce_len() {
+        return Err(cose_error("The box is shorter than its nonce"));
+    }
+    let ciphertext = iv.split_off(B::box_nonce_len());
+    encode(&Value::Tag(
+        TAG,
+        Box::new(Value::Array(vec![
+            Value::Bytes(protected),
+            Value::Map(vec![(Value::from(IV), Value::Bytes(iv))]),
+            Value::Bytes(ciphertext),
+        ])),
+    ))
+}
+
+/// find the algorithm and the IV in the headers.  Labels may only occur once, the algorithm has to be protected and
+/// critical headers and partial IVs are rejected.
+fn parse_headers(protected: Vec<(Value, Value)>, unprotected: Vec<(Value, Value)>) -> crate::Result<(Value, Value)> {
+    let mut labels = Vec::new();
+    let mut algorithm = None;
+    let mut iv = None;
+
+    let headers = protected
+        .into_iter()
+        .map(|header| (true, header))
+        .chain(unprotected.into_iter().map(|header| (false, header)));
+    for (is_protected, (label, value)) in headers {
+        if labels.contains(&label) {
+            return Err(cose_error("Duplicate header label"));
+        }
+        match label.as_integer().and_then(|label| i64::try_from(label).ok()) {
+            Some(ALG) if is_protected => algorithm = Some(value),
+            Some(ALG) => return Err(cose_error("The algorithm has to be in the protected header")),
+            Some(CRIT) => return Err(cose_error("Critical headers are not supported")),
+            Some(IV) => iv = Some(value),
+            Some(PARTIAL_IV) => return Err(cose_error("Partial IVs are not supported")),
+            _ => {}
+        }
+        labels.push(label);
+    }
+
+    match (algorithm, iv) {
+        (Some(algorithm), Some(iv)) => Ok((algorithm, iv)),
+        (None, _) => Err(cose_error("Missing algorithm")),
+        (_, None) => Err(cose_error("Missing IV")),
+    }
+}
+
+/// open a COSE_Encrypt0 structure of RFC 9052, tagged or untagged, with `ad` as the external AAD.  Fails with
+/// `Error::CoseError` if the algorithm isn't the `BoxProvider::cose_algorithm` of the provider, with
+/// `Error::InvalidNonceLength` if the IV doesn't fit and with `Error::AuthenticationFailed` if the box doesn't open.
+pub fn from_cose_encrypt0<B: BoxProvider>(key: &Key<B>, ad: &[u8], data: &[u8]) -> crate::Result<Vec<u8>> {
+    let expected = expected_algorithm::<B>()?;
+
+    let structure = match decode(data)? {
+        Value::Tag(TAG, structure) => *structure,
+        Value::Tag(tag, _) => {
+            return Err(crate::Error::CoseError(format!(
+                "Unexpected CBOR tag `{}`, COSE_Encrypt0 uses `{}`",
+                tag, TAG
+            )))
+        }
+        structure => structure,
+    };
+    let mut items = match structure {
+        Value::Array(items) if items.len() == 3 => items.into_iter(),
+        _ => return Err(cose_error("Not a COSE_Encrypt0 structure")),
+    };
+    let (protected, unprotected, ciphertext) = match (items.next(), items.next(), items.next()) {
+        (Some(Value::Bytes(protected)), Some(Value::Map(unprotected)), Some(Value::Bytes(ciphertext))) => {
+            (protected, unprotected, ciphertext)
+        }
+        (_, _, Some(Value::Null)) => return Err(cose_error("Detached ciphertexts are not supported")),
+        _ => return Err(cose_error("Not a COSE_Encrypt0 structure")),
+    };
+    // an empty protected header stands for the empty map
+    let protected_headers = if protected.is_empty() {
+        Vec::new()
+    } else {
+        match decode(&protected)? {
+            Value::Map(headers) => headers,
+            _ => return Err(cose_error("The protected header isn't a map")),
+        }
+    };
+
+    let (algorithm, iv) = parse_headers(protected_headers, unprotected)?;
+    match algorithm {
+        Value::Integer(algorithm) if i128::from(algorithm) == expected as i128 => {}
+        Value::Integer(algorithm) => {
+            return Err(crate::Error::CoseError(format!(
+                "Unsupported algorithm `{}`, the provider uses `{}`",
+                describe(i128::from(algorithm)),
+                describe(expected as i128)
+            )))
+        }
+        Value::Text(algorithm) => {
+            return Err(crate::Error::CoseError(format!(
+                "Unsupported algorithm `{}`, the provider uses `{}`",
+                algorithm,
+                describe(expected as i128)
+            )))
+        }
+        _ => return Err(cose_error("Invalid algorithm")),
+    }
+    let iv = match iv {
+        Value::Bytes(iv) if iv.len() == B::box_nonce_len() => iv,
+        Value::Bytes(iv) => {
+            return Err(crate::Error::InvalidNonceLength {
+                expected: B::box_nonce_len(),
+                actual: iv.len(),
+            })
+        }
+        _ => return Err(cose_error("Invalid IV")),
+    };
+
+    open_box(key, &enc_structure(&protected, ad)?, &[iv, ciphertext].concat())
+}
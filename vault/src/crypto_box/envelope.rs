@@ -0,0 +1,119 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+use crate::crypto_box::{open_box, seal_box, BoxProvider, Key};
+
+use std::convert::TryInto;
+
+/// A parsed envelope of `Encrypt::encrypt_enveloped`.  Version 1 is laid out as
+///
+/// | bytes | field |
+/// |---|---|
+/// | 4 | the magic bytes `RCEV` |
+/// | 1 | the format version |
+/// | 4 | the `BoxProvider::box_id` of the provider |
+/// | 4 | the big endian length of the AD |
+/// | 8 | the big endian length of the ciphertext |
+/// | n | the ciphertext |
+///
+/// Everything but the ciphertext length is authenticated as part of the AD.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Envelope<'a> {
+    /// the format version
+    pub version: u8,
+    /// the id of the provider that sealed the ciphertext
+    pub box_id: [u8; 4],
+    /// the length of the AD the ciphertext was sealed with
+    pub ad_len: u32,
+    /// the sealed data
+    pub ciphertext: &'a [u8],
+}
+
+impl<'a> Envelope<'a> {
+    /// the magic bytes starting an envelope
+    pub const MAGIC: [u8; 4] = *b"RCEV";
+    /// the current format version
+    pub const VERSION: u8 = 1;
+    /// the length of the header of version 1
+    pub const HEADER_LEN: usize = 21;
+
+    /// parse an envelope.  Fails with `Error::UnsupportedVersion` for versions other than `VERSION`, and if the
+    /// data is truncated or the ciphertext length doesn't match the data.
+    pub fn parse(data: &'a [u8]) -> crate::Result<Self> {
+        if data.len() < 5 || data[..4] != Self::MAGIC {
+            return Err(crate::Error::crypto("parse envelope", "Not an envelope"));
+        }
+        if data[4] != Self::VERSION {
+            return Err(crate::Error::UnsupportedVersion(data[4]));
+        }
+        if data.len() < Self::HEADER_LEN {
+            return Err(crate::Error::crypto("parse envelope", "Truncated envelope"));
+        }
+
+        let (header, ciphertext) = data.split_at(Self::HEADER_LEN);
+        let ciphertext_len = u64::from_be_bytes(header[13..21].try_into().expect("the length has 8 bytes"));
+        if ciphertext_len != ciphertext.len() as u64 {
+            return Err(crate::Error::crypto("parse envelope", "Invalid envelope length"));
+        }
+        Ok(Self {
+            version: header[4],
+            box_id: header[5..9].try_into().expect("the box id has 4 bytes"),
+            ad_len: u32::from_be_bytes(header[9..13].try_into().expect("the length has 4 bytes")),
+            ciphertext,
+        })
+    }
+}
+
+/// the length of the authenticated part of the header
+const AUTHENTICATED_LEN: usize = 13;
+
+/// the header without the ciphertext length
+fn authenticated_header(box_id: [u8; 4], ad_len: u32) -> [u8; AUTHENTICATED_LEN] {
+    let mut header = [0; AUTHENTICATED_LEN];
+    header[..4].copy_from_slice(&Envelope::MAGIC);
+    header[4] = Envelope::VERSION;
+    header[5..9].copy_from_slice(&box_id);
+    header[9..].copy_from_slice(&ad_len.to_be_bytes());
+    header
+}
+
+/// seal `data` into an envelope
+pub(crate) fn seal<B: BoxProvider>(key: &Key<B>, ad: &[u8], data: &[u8]) -> crate::Result<Vec<u8>> {
+    let ad_len: u32 = ad
+        .len()
+        .try_into()
+        .map_err(|_| crate::Error::crypto("seal envelope", "AD too long for an envelope"))?;
+    let header = authenticated_header(B::box_id(), ad_len);
+
+    let sealed = seal_box(key, &[&header[..], ad].concat(), data)?;
+    let mut envelope = Vec::with_capacity(Envelope::HEADER_LEN + sealed.len());
+    envelope.extend_from_slice(&header);
+    envelope.extend_from_slice(&(sealed.len() as u64).to_be_bytes());
+    envelope.extend_from_slice(&sealed);
+    Ok(envelope)
+}
+
+/// parse an envelope and open it.  The header is checked before the box is opened.
+pub(crate) fn open<B: BoxProvider>(key: &Key<B>, ad: &[u8], data: &[u8]) -> crate::Result<Vec<u8>> {
+    let envelope = Envelope::parse(data)?;
+    if envelope.box_id != B::box_id() {
+        return Err(crate::Error::ProviderMismatch {
+            expected: B::box_id().escape_ascii().to_string(),
+            found: envelope.box_id.escape_ascii().to_string(),
+        });
+    }
+    if envelope.ad_len as usize != ad.len() {
+        return Err(crate::Error::AuthenticationFailed);
+    }
+
+    let header = &data[..AUTHENTICATED_LEN];
+    open_box(key, &[header, ad].concat(), envelope.ciphertext)
+}
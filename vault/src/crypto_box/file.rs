@@ -0,0 +1,223 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+use crate::{
+    crypto_box::{
+        chunked::{chunk_ad, chunk_total, missing_chunks, take_len, trailing_data, truncated},
+        open_box,
+        progress::{check_cancelled, NoProgress, Progress},
+        seal_box, BoxProvider, Key,
+    },
+    persist_hooks::TempFile,
+};
+
+use std::{
+    fs::{self, File},
+    io::Write,
+    path::Path,
+    time::SystemTime,
+};
+
+use memmap2::Mmap;
+use sha2::{Digest, Sha256};
+use zeroize::Zeroize;
+
+/// The outcome of `seal_file` and `open_file`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileSealReport {
+    /// the number of chunks of the sealed file
+    pub chunks: usize,
+    /// the length of the written file
+    pub len: u64,
+    /// the SHA-256 digest of the written file
+    pub digest: [u8; 32],
+}
+
+/// a read-only memory map of a source file with the length and the modification time it was mapped at
+struct Source<'a> {
+    path: &'a Path,
+    map: Option<Mmap>,
+    len: u64,
+    modified: Option<SystemTime>,
+}
+
+impl<'a> Source<'a> {
+    fn map(path: &'a Path) -> crate::Result<Self> {
+        let file = File::open(path)?;
+        let metadata = file.metadata()?;
+        // empty files can't be mapped
+        let map = match metadata.len() {
+            0 => None,
+            // the map is only read, changes to the file while it's mapped are caught by `check_unchanged`
+            _ => Some(unsafe { Mmap::map(&file)? }),
+        };
+        Ok(Self {
+            path,
+            map,
+            len: metadata.len(),
+            modified: metadata.modified().ok(),
+        })
+    }
+
+    fn bytes(&self) -> &[u8] {
+        self.map.as_deref().unwrap_or(&[])
+    }
+
+    /// fail with `Error::SourceModified` if the length or the modification time of the file changed since it was
+    /// mapped
+    fn check_unchanged(&self) -> crate::Result<()> {
+        let metadata = fs::metadata(self.path)?;
+        if metadata.len() != self.len || metadata.modified().ok() != self.modified {
+            return Err(crate::Error::SourceModified {
+                path: self.path.to_path_buf(),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// a file which is written to a temporary file next to its path and renamed once it is complete
+struct AtomicFile {
+    file: TempFile,
+    len: u64,
+    digest: Sha256,
+}
+
+impl AtomicFile {
+    fn write(&mut self, data: &[u8]) -> crate::Result<()> {
+        self.file.write_all(data)?;
+        self.digest.update(data);
+        self.len += data.len() as u64;
+        Ok(())
+    }
+
+    /// sync the file and move it to its path
+    fn commit(self, chunks: usize) -> crate::Result<FileSealReport> {
+        self.file.commit()?;
+        Ok(FileSealReport {
+            chunks,
+            len: self.len,
+            digest: self.digest.finalize().into(),
+        })
+    }
+
+    /// write the file with `write` and commit it with the number of chunks `write` returns, the temporary file is
+    /// removed if writing fails
+    fn write_with<B: BoxProvider>(
+        path: &Path,
+        write: impl FnOnce(&mut Self) -> crate::Result<usize>,
+    ) -> crate::Result<FileSealReport> {
+        let mut file = Self {
+            file: TempFile::create::<B>(path)?,
+            len: 0,
+            digest: Sha256::new(),
+        };
+        write(&mut file).and_then(|chunks| file.commit(chunks))
+    }
+}
+
+/// seal the file `src` into `dst` in chunks of `chunk_size` bytes with the framing and the ADs of `seal_chunked`,
+/// so the sealed file can also be opened by `ChunkedCiphertext::from_bytes` and `open_chunked`.  The source is
+/// memory mapped and the chunks are sealed one after another, so the file doesn't need to fit into memory.
+///
+/// `dst` is written to a temporary file next to it which is synced and renamed, so `dst` holds either its old
+/// content or the complete sealed file.  Fails with `Error::SourceModified` if the length or the modification time of
+/// `src` changed while it was sealed.  The file must not be truncated while it is mapped, depending on the platform
+/// reading the missing pages crashes the process.  Every chunk counts as a use of the key, see `Key::with_max_uses`.
+pub fn seal_file<B: BoxProvider>(
+    key: &Key<B>,
+    ad: &[u8],
+    src: &Path,
+    dst: &Path,
+    chunk_size: usize,
+) -> crate::Result<FileSealReport> {
+    seal_file_with_progress(key, ad, src, dst, chunk_size, &NoProgress)
+}
+
+/// like `seal_file` but reports the number of sealed chunks to `progress` and fails with `Error::Cancelled` before
+/// the next chunk once it is cancelled.  The temporary file is removed and `dst` is left as it was.
+pub fn seal_file_with_progress<B: BoxProvider>(
+    key: &Key<B>,
+    ad: &[u8],
+    src: &Path,
+    dst: &Path,
+    chunk_size: usize,
+    progress: &dyn Progress,
+) -> crate::Result<FileSealReport> {
+    let source = Source::map(src)?;
+    let data = source.bytes();
+    let total = chunk_total::<B>(data.len(), chunk_size)?;
+
+    AtomicFile::write_with::<B>(dst, |out| {
+        out.write(&(total as u32).to_be_bytes())?;
+        for index in 0..total {
+            check_cancelled(progress)?;
+            let chunk = &data[(index * chunk_size).min(data.len())..((index + 1) * chunk_size).min(data.len())];
+            key.checkout_use()?;
+            let sealed = seal_box(key, &chunk_ad(ad, index, total), chunk)?;
+            out.write(&(sealed.len() as u32).to_be_bytes())?;
+            out.write(&sealed)?;
+            progress.report(index as u64 + 1, Some(total as u64));
+        }
+        source.check_unchanged()?;
+        Ok(total)
+    })
+}
+
+/// open a file sealed by `seal_file`, or the framing of a `ChunkedCiphertext`, from `src` into `dst`.  Like
+/// `seal_file` the source is memory mapped and `dst` is written atomically, it's left untouched if any chunk doesn't
+/// open, which includes truncated files and reordered, duplicated or dropped chunks.
+pub fn open_file<B: BoxProvider>(key: &Key<B>, ad: &[u8], src: &Path, dst: &Path) -> crate::Result<FileSealReport> {
+    open_file_with_progress(key, ad, src, dst, &NoProgress)
+}
+
+/// like `open_file` but reports the number of opened chunks to `progress` and fails with `Error::Cancelled` before
+/// the next chunk once it is cancelled.  The temporary file is removed and `dst` is left as it was.
+pub fn open_file_with_progress<B: BoxProvider>(
+    key: &Key<B>,
+    ad: &[u8],
+    src: &Path,
+    dst: &Path,
+    progress: &dyn Progress,
+) -> crate::Result<FileSealReport> {
+    let source = Source::map(src)?;
+    let mut data = source.bytes();
+    let total = take_len(&mut data)?;
+    if total == 0 {
+        return Err(missing_chunks());
+    }
+    // every chunk takes at least its length prefix, don't trust the count any further
+    if total > data.len() / 4 {
+        return Err(truncated());
+    }
+
+    AtomicFile::write_with::<B>(dst, |out| {
+        for index in 0..total {
+            check_cancelled(progress)?;
+            let len = take_len(&mut data)?;
+            if len > data.len() {
+                return Err(truncated());
+            }
+            let (chunk, rest) = data.split_at(len);
+            let mut plain = open_box(key, &chunk_ad(ad, index, total), chunk)?;
+            let written = out.write(&plain);
+            plain.zeroize();
+            written?;
+            data = rest;
+            progress.report(index as u64 + 1, Some(total as u64));
+        }
+        if !data.is_empty() {
+            return Err(trailing_data());
+        }
+        source.check_unchanged()?;
+        Ok(total)
+    })
+}
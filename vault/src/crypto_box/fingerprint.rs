@@ -0,0 +1,100 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+use crate::{
+    crypto_box::{BoxProvider, Key},
+    ct::ct_eq,
+};
+
+use std::{
+    fmt::{self, Debug, Display, Formatter},
+    hash::{Hash, Hasher},
+    str::FromStr,
+};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// A stable identifier of a key which can be logged or stored without revealing the key.
+#[derive(Copy, Clone, Ord, PartialOrd, Serialize, Deserialize)]
+pub struct KeyFingerprint(#[cfg_attr(feature = "serde-base64", serde(with = "crate::serde_base64::array"))] [u8; 32]);
+
+impl KeyFingerprint {
+    /// domain separation prefix for the fingerprint digest
+    const DOMAIN: &'static [u8] = b"vault-key-fingerprint-v1";
+
+    /// compute the fingerprint of some key bytes
+    pub(crate) fn of(key: &[u8]) -> Self {
+        let digest = Sha256::new().chain_update(Self::DOMAIN).chain_update(key).finalize();
+        let mut fingerprint = [0; 32];
+        fingerprint.copy_from_slice(&digest);
+        Self(fingerprint)
+    }
+}
+
+impl<T: BoxProvider> Key<T> {
+    /// get the fingerprint of this key
+    pub fn fingerprint(&self) -> KeyFingerprint {
+        KeyFingerprint::of(&self.key)
+    }
+}
+
+/// compares the fingerprints in constant time.
+impl PartialEq for KeyFingerprint {
+    fn eq(&self, other: &Self) -> bool {
+        ct_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for KeyFingerprint {}
+
+impl Hash for KeyFingerprint {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state);
+    }
+}
+
+impl AsRef<[u8]> for KeyFingerprint {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl Display for KeyFingerprint {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        self.0.iter().try_for_each(|b| write!(f, "{:02x}", b))
+    }
+}
+
+impl Debug for KeyFingerprint {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "KeyFingerprint({})", self)
+    }
+}
+
+impl FromStr for KeyFingerprint {
+    type Err = crate::Error;
+
+    /// parse a fingerprint from its hex representation
+    fn from_str(s: &str) -> crate::Result<Self> {
+        let s = s.as_bytes();
+        if s.len() != 64 || !s.iter().all(u8::is_ascii_hexdigit) {
+            return Err(crate::Error::InterfaceError);
+        }
+
+        let mut fingerprint = [0; 32];
+        for (b, pair) in fingerprint.iter_mut().zip(s.chunks(2)) {
+            let pair = std::str::from_utf8(pair).map_err(|_| crate::Error::InterfaceError)?;
+            *b = u8::from_str_radix(pair, 16).map_err(|_| crate::Error::InterfaceError)?;
+        }
+        Ok(Self(fingerprint))
+    }
+}
@@ -0,0 +1,120 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+use std::{
+    ops::{Deref, DerefMut},
+    ptr::NonNull,
+    slice,
+};
+
+use zeroize::Zeroize;
+
+/// Bytes stored in their own page aligned memory mapping which is locked into RAM and excluded from core dumps where
+/// supported.  The memory is wiped, unlocked and unmapped on drop.
+pub struct GuardedBytes {
+    ptr: NonNull<u8>,
+    len: usize,
+    mapped: usize,
+}
+
+// the mapping is exclusively owned.
+unsafe impl Send for GuardedBytes {}
+unsafe impl Sync for GuardedBytes {}
+
+impl GuardedBytes {
+    /// allocate `len` zeroed bytes of guarded memory.
+    #[cfg(unix)]
+    pub fn new(len: usize) -> crate::Result<Self> {
+        let error = |op: &str| crate::Error::MemoryError(format!("{} failed: {}", op, std::io::Error::last_os_error()));
+
+        let page = match unsafe { libc::sysconf(libc::_SC_PAGESIZE) } {
+            page if page > 0 => page as usize,
+            _ => return Err(error("sysconf")),
+        };
+        let mapped = len.max(1).div_ceil(page) * page;
+
+        let ptr = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                mapped,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+                -1,
+                0,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            return Err(error("mmap"));
+        }
+        let bytes = Self {
+            ptr: NonNull::new(ptr.cast()).ok_or_else(|| error("mmap"))?,
+            len,
+            mapped,
+        };
+
+        // dropping `bytes` unmaps the memory on the error paths below.
+        if unsafe { libc::mlock(ptr, mapped) } != 0 {
+            return Err(error("mlock"));
+        }
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        if unsafe { libc::madvise(ptr, mapped, libc::MADV_DONTDUMP) } != 0 {
+            return Err(error("madvise"));
+        }
+        Ok(bytes)
+    }
+
+    /// allocate `len` zeroed bytes of guarded memory.
+    #[cfg(not(unix))]
+    pub fn new(_len: usize) -> crate::Result<Self> {
+        Err(crate::Error::MemoryError(String::from(
+            "Guarded memory is not supported on this platform",
+        )))
+    }
+
+    /// copy the bytes into a new guarded allocation.
+    pub fn try_clone(&self) -> crate::Result<Self> {
+        let mut clone = Self::new(self.len)?;
+        clone.copy_from_slice(self);
+        Ok(clone)
+    }
+}
+
+impl Deref for GuardedBytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl DerefMut for GuardedBytes {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        unsafe { slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl Zeroize for GuardedBytes {
+    fn zeroize(&mut self) {
+        self.deref_mut().zeroize();
+    }
+}
+
+impl Drop for GuardedBytes {
+    fn drop(&mut self) {
+        unsafe { slice::from_raw_parts_mut(self.ptr.as_ptr(), self.mapped) }.zeroize();
+        #[cfg(unix)]
+        unsafe {
+            // munlock fails if the locking failed during allocation, which is fine.
+            libc::munlock(self.ptr.as_ptr().cast(), self.mapped);
+            libc::munmap(self.ptr.as_ptr().cast(), self.mapped);
+        }
+    }
+}
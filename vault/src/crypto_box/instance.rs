@@ -0,0 +1,50 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+use crate::crypto_box::{BoxProvider, Key};
+
+use std::fmt::Debug;
+
+/// A provider whose methods take `&self`, for backends with state such as an HSM session, a KMS client or a per
+/// tenant configuration.  Keys stay typed: `Marker` is a stateless `BoxProvider` that fixes the key length and
+/// generates the keys, while the instance seals and opens the boxes.  Every `BoxProvider` is an instance provider
+/// which is its own marker.
+pub trait BoxProviderInstance {
+    /// the provider the keys of this instance are created with
+    type Marker: BoxProvider;
+    type Error: Debug + From<crate::Error> + Into<crate::Error>;
+
+    /// the number of bytes the boxes of this instance are larger than the data
+    fn box_overhead(&self) -> usize;
+
+    /// seals the data with the key and the associated data.
+    fn box_seal(&self, key: &Key<Self::Marker>, ad: &[u8], data: &[u8]) -> Result<Vec<u8>, Self::Error>;
+
+    /// opens a box sealed by `box_seal` with the same key and associated data.
+    fn box_open(&self, key: &Key<Self::Marker>, ad: &[u8], data: &[u8]) -> Result<Vec<u8>, Self::Error>;
+}
+
+impl<P: BoxProvider> BoxProviderInstance for P {
+    type Marker = P;
+    type Error = P::Error;
+
+    fn box_overhead(&self) -> usize {
+        P::box_overhead()
+    }
+
+    fn box_seal(&self, key: &Key<P>, ad: &[u8], data: &[u8]) -> Result<Vec<u8>, P::Error> {
+        P::box_seal(key, ad, data)
+    }
+
+    fn box_open(&self, key: &Key<P>, ad: &[u8], data: &[u8]) -> Result<Vec<u8>, P::Error> {
+        P::box_open(key, ad, data)
+    }
+}
@@ -0,0 +1,98 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+use crate::crypto_box::{BoxProvider, Key};
+
+use argon2::{Algorithm, Argon2, Params, Version};
+use zeroize::Zeroize;
+
+/// Cost parameters for deriving a key from a password with Argon2id.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KdfParams {
+    /// memory cost in KiB
+    pub memory_cost: u32,
+    /// number of iterations
+    pub iterations: u32,
+    /// degree of parallelism
+    pub parallelism: u32,
+}
+
+impl KdfParams {
+    /// minimum salt length accepted by `Key::derive_from_password`
+    pub const MIN_SALT_LEN: usize = 16;
+
+    /// the most expensive parameters accepted from stored data, like the header of a snapshot
+    pub(crate) const MAX: KdfParams = KdfParams {
+        memory_cost: 1024 * 1024,
+        iterations: 64,
+        parallelism: 64,
+    };
+
+    /// checks that none of the parameters exceeds `KdfParams::MAX`
+    pub(crate) fn is_bounded(&self) -> bool {
+        self.memory_cost <= Self::MAX.memory_cost
+            && self.iterations <= Self::MAX.iterations
+            && self.parallelism <= Self::MAX.parallelism
+    }
+
+    /// parameters for interactive use like unlocking a vault on login.  Uses 64 MiB of memory.
+    pub fn interactive() -> Self {
+        Self {
+            memory_cost: 64 * 1024,
+            iterations: 2,
+            parallelism: 1,
+        }
+    }
+
+    /// parameters for highly sensitive data where derivation may take multiple seconds.  Uses 1 GiB of memory.
+    pub fn sensitive() -> Self {
+        Self {
+            memory_cost: 1024 * 1024,
+            iterations: 4,
+            parallelism: 1,
+        }
+    }
+}
+
+impl Default for KdfParams {
+    fn default() -> Self {
+        Self::interactive()
+    }
+}
+
+impl<T: BoxProvider> Key<T> {
+    /// derive a key from a `password` and a `salt` using Argon2id.  The salt must be at least 16 bytes long.
+    pub fn derive_from_password(password: &[u8], salt: &[u8], params: KdfParams) -> crate::Result<Self> {
+        if salt.len() < KdfParams::MIN_SALT_LEN {
+            return Err(crate::Error::InterfaceError);
+        }
+
+        let params = Params::new(
+            params.memory_cost,
+            params.iterations,
+            params.parallelism,
+            Some(T::box_key_len()),
+        )
+        .map_err(|e| crate::Error::crypto("derive key", format!("Invalid KDF parameters: {}", e)))?;
+
+        let mut key = vec![0; T::box_key_len()];
+        if let Err(e) =
+            Argon2::new(Algorithm::Argon2id, Version::V0x13, params).hash_password_into(password, salt, &mut key)
+        {
+            key.zeroize();
+            return Err(crate::Error::crypto(
+                "derive key",
+                format!("Unable to derive key: {}", e),
+            ));
+        }
+        Self::load(key)
+    }
+}
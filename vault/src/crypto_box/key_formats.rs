@@ -0,0 +1,167 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+use crate::{
+    base64::Base64,
+    crypto_box::{wipe, BoxProvider, Key},
+    ct::ct_eq,
+};
+
+use std::convert::TryFrom;
+
+use pkcs8::{
+    der::{asn1::OctetStringRef, Decode},
+    pkcs5, EncryptedPrivateKeyInfo, ObjectIdentifier, PrivateKeyInfo,
+};
+use serde::Deserialize;
+use zeroize::Zeroize;
+
+/// the algorithms of RFC 8410, whose private key is wrapped in another octet string
+const CURVE_KEYS: [ObjectIdentifier; 4] = [
+    ObjectIdentifier::new_unwrap("1.3.101.110"),
+    ObjectIdentifier::new_unwrap("1.3.101.111"),
+    ObjectIdentifier::new_unwrap("1.3.101.112"),
+    ObjectIdentifier::new_unwrap("1.3.101.113"),
+];
+
+/// the JWA name of the algorithm of the boxes of the provider with `box_id`
+pub(crate) fn algorithm_for(box_id: [u8; 4]) -> Option<&'static str> {
+    match &box_id {
+        b"a256" => Some("A256GCM"),
+        b"c20p" => Some("C20P"),
+        b"xc20" => Some("XC20P"),
+        _ => None,
+    }
+}
+
+/// the members of a symmetric JWK the vault understands, other members are ignored.  `k` is wiped when it is dropped.
+#[derive(Deserialize)]
+struct Jwk {
+    kty: String,
+    k: Option<String>,
+    alg: Option<String>,
+}
+
+impl Drop for Jwk {
+    fn drop(&mut self) {
+        self.k.zeroize();
+    }
+}
+
+impl<T: BoxProvider> Key<T> {
+    /// encode the key as a symmetric JSON Web Key of RFC 7517, `{"kty":"oct","k":"..."}`, with the
+    /// `BoxProvider::jwk_algorithm` of the provider as `alg` if it has one.
+    pub fn to_jwk(&self) -> crate::Result<String> {
+        let mut k = Base64::encode_data(self.bytes());
+        k.truncate(k.trim_end_matches('=').len());
+        let jwk = match T::jwk_algorithm() {
+            Some(alg) => format!(r#"{{"kty":"oct","k":"{}","alg":"{}"}}"#, k, alg),
+            None => format!(r#"{{"kty":"oct","k":"{}"}}"#, k),
+        };
+        k.zeroize();
+        Ok(jwk)
+    }
+
+    /// load a key from a symmetric JSON Web Key.  Fails with `Error::UnsupportedKeyType` unless `kty` is `oct`, with
+    /// `Error::Base64ErrorDetailed` if `k` is padded or not the canonical base64url encoding, with
+    /// `Error::InvalidKeyLength` if the key doesn't have `BoxProvider::box_key_len` bytes and with `Error::JwkError` if
+    /// an `alg` is given that isn't the `BoxProvider::jwk_algorithm` of the provider.
+    pub fn from_jwk(json: &str) -> crate::Result<Self> {
+        let jwk: Jwk = serde_json::from_str(json).map_err(|e| crate::Error::JwkError(format!("Invalid JWK: {}", e)))?;
+        if jwk.kty != "oct" {
+            return Err(crate::Error::UnsupportedKeyType(jwk.kty.clone()));
+        }
+        match (&jwk.alg, T::jwk_algorithm()) {
+            (Some(alg), Some(expected)) if alg != expected => {
+                return Err(crate::Error::JwkError(format!(
+                    "The algorithm `{}` doesn't match the provider's `{}`",
+                    alg, expected
+                )))
+            }
+            (Some(alg), None) => {
+                return Err(crate::Error::JwkError(format!(
+                    "The algorithm `{}` doesn't match the provider, which has no JWA algorithm",
+                    alg
+                )))
+            }
+            _ => {}
+        }
+
+        let k = match &jwk.k {
+            Some(k) => k.as_bytes(),
+            None => return Err(crate::Error::JwkError(String::from("Missing `k`"))),
+        };
+        if k.contains(&b'=') {
+            return Err(crate::Error::Base64ErrorDetailed(String::from(
+                "The key of a JWK must not be padded",
+            )));
+        }
+        if k.len() % 4 == 1 {
+            return Err(crate::Error::Base64ErrorDetailed(String::from(
+                "Invalid base64url length",
+            )));
+        }
+
+        let mut padded = k.to_vec();
+        padded.resize(k.len().div_ceil(4) * 4, b'=');
+        let decoded = Base64::decode_data(&padded);
+        wipe(padded);
+        let bytes = decoded?;
+
+        // the unused bits of the last character have to be zero, so every key has a single encoding
+        let mut encoded = Base64::encode_data(&bytes);
+        let key = if ct_eq(encoded.trim_end_matches('=').as_bytes(), k) {
+            Self::load_from_slice(&bytes)
+        } else {
+            Err(crate::Error::Base64ErrorDetailed(String::from(
+                "Non canonical base64url encoding",
+            )))
+        };
+        encoded.zeroize();
+        wipe(bytes);
+        key
+    }
+
+    /// load a key from a DER encoded, PBES2 encrypted PKCS#8 `EncryptedPrivateKeyInfo` of RFC 5958, as exported by
+    /// `openssl pkcs8 -topk8` or HSMs.  PBKDF2 with HMAC-SHA1 or HMAC-SHA2 and scrypt are supported as key
+    /// derivation, AES-CBC as encryption.  The private key of the `PrivateKeyInfo` is taken as the key bytes, for the
+    /// X25519, X448, Ed25519 and Ed448 keys of RFC 8410 the octet string inside it.  Fails with
+    /// `Error::AuthenticationFailed` if the password is wrong, with `Error::Pkcs8Error` for malformed or unsupported
+    /// documents and with `Error::InvalidKeyLength` if the key doesn't have `BoxProvider::box_key_len` bytes.  The
+    /// decrypted document is wiped.
+    pub fn from_pkcs8_encrypted(der: &[u8], password: &[u8]) -> crate::Result<Self> {
+        let encrypted = EncryptedPrivateKeyInfo::try_from(der)
+            .map_err(|e| crate::Error::Pkcs8Error(format!("Invalid EncryptedPrivateKeyInfo: {}", e)))?;
+        // pkcs5 reports the invalid padding of a wrong password as a failed encryption
+        let document = encrypted.decrypt(password).map_err(|e| match e {
+            pkcs8::Error::EncryptedPrivateKey(pkcs5::Error::DecryptFailed | pkcs5::Error::EncryptFailed) => {
+                crate::Error::AuthenticationFailed
+            }
+            pkcs8::Error::EncryptedPrivateKey(pkcs5::Error::UnsupportedAlgorithm { oid }) => {
+                crate::Error::Pkcs8Error(format!("Unsupported algorithm `{}`", oid))
+            }
+            e => crate::Error::Pkcs8Error(e.to_string()),
+        })?;
+
+        // the document is a `SecretDocument`, which is wiped when it is dropped.  CBC isn't authenticated, so a wrong
+        // password passes the padding check now and then and fails here.
+        let info = PrivateKeyInfo::try_from(document.as_bytes()).map_err(|e| {
+            crate::Error::Pkcs8Error(format!("Invalid PrivateKeyInfo, the password may be wrong: {}", e))
+        })?;
+        if CURVE_KEYS.contains(&info.algorithm.oid) {
+            let key = OctetStringRef::from_der(info.private_key)
+                .map_err(|e| crate::Error::Pkcs8Error(format!("Invalid curve private key: {}", e)))?;
+            Self::load_from_slice(key.as_bytes())
+        } else {
+            Self::load_from_slice(info.private_key)
+        }
+    }
+}
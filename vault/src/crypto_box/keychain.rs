@@ -0,0 +1,79 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+use crate::crypto_box::{wipe, BoxProvider, Key};
+
+use keyring::Entry;
+use zeroize::Zeroize;
+
+/// the longest armored key that is stored.  The Windows Credential Manager keeps at most 2560 bytes and stores the
+/// ASCII of the armored key as UTF-16.
+pub const KEYCHAIN_MAX_LEN: usize = 1280;
+
+/// the entry of `account` of `service` in the platform's keychain
+fn entry(service: &str, account: &str) -> crate::Result<Entry> {
+    Entry::new(service, account).map_err(|e| map_error(e, service, account))
+}
+
+/// map the errors of `keyring`, `Error::KeychainNotFound` if there is no entry
+fn map_error(e: keyring::Error, service: &str, account: &str) -> crate::Error {
+    match e {
+        keyring::Error::NoEntry => crate::Error::KeychainNotFound {
+            service: service.to_owned(),
+            account: account.to_owned(),
+        },
+        keyring::Error::BadEncoding(data) => {
+            wipe(data);
+            crate::Error::KeychainError(String::from("The stored key is not UTF-8"))
+        }
+        e => crate::Error::KeychainError(e.to_string()),
+    }
+}
+
+/// store `key` as the password of `account` of `service` in the keychain of the platform, the macOS Keychain, the
+/// Windows Credential Manager or the Secret Service on Linux.  The key is stored in the form of `Key::to_armored`,
+/// which fails with `Error::PayloadTooLarge` if it's longer than `KEYCHAIN_MAX_LEN`.  An existing key is replaced.
+pub fn store_key<P: BoxProvider>(service: &str, account: &str, key: &Key<P>) -> crate::Result<()> {
+    let mut armored = key.to_armored();
+    if armored.len() > KEYCHAIN_MAX_LEN {
+        let len = armored.len();
+        armored.zeroize();
+        return Err(crate::Error::PayloadTooLarge {
+            len: len as u64,
+            limit: KEYCHAIN_MAX_LEN,
+        });
+    }
+    let stored = entry(service, account)
+        .and_then(|entry| entry.set_password(&armored).map_err(|e| map_error(e, service, account)));
+    armored.zeroize();
+    stored
+}
+
+/// load a key stored with `store_key`.  Fails with `Error::KeychainNotFound` if there is none, with
+/// `Error::InvalidKeyLength` if it doesn't have `BoxProvider::box_key_len` bytes and with the errors of
+/// `Key::from_armored` if the stored value was modified.
+pub fn load_key<P: BoxProvider>(service: &str, account: &str) -> crate::Result<Key<P>> {
+    let mut armored = entry(service, account)?
+        .get_password()
+        .map_err(|e| map_error(e, service, account))?;
+    let key = Key::from_armored(&armored);
+    armored.zeroize();
+    key
+}
+
+/// delete a key stored with `store_key`.  Fails with `Error::KeychainNotFound` if there is none and with
+/// `Error::KeychainDeleteFailed` if the keychain doesn't delete it.
+pub fn delete_key(service: &str, account: &str) -> crate::Result<()> {
+    entry(service, account)?.delete_credential().map_err(|e| match e {
+        keyring::Error::NoEntry => map_error(e, service, account),
+        e => crate::Error::KeychainDeleteFailed(e.to_string()),
+    })
+}
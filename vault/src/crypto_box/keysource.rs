@@ -0,0 +1,212 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+use crate::{
+    base64::Base64,
+    crypto_box::{wipe, BoxProvider, Key},
+};
+
+#[cfg(unix)]
+use std::os::unix::{ffi::OsStringExt, io::OwnedFd};
+use std::{fs::File, io::Read, path::Path};
+
+/// the most bytes read from a key source
+pub const MAX_KEY_SOURCE_LEN: usize = 4096;
+
+/// the encoding of a key read by `Key::from_env_with`, `Key::from_file_with` or `Key::from_fd_with`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyEncoding {
+    /// the first of hex, base64 and the raw bytes that decodes to a key of `BoxProvider::box_key_len` bytes
+    Auto,
+    /// hex digits in lower or upper case
+    Hex,
+    /// padded base64 of the standard or the uri-safe character set
+    Base64,
+    /// the key bytes
+    Raw,
+}
+
+fn too_large(len: usize) -> crate::Error {
+    crate::Error::PayloadTooLarge {
+        len: len as u64,
+        limit: MAX_KEY_SOURCE_LEN,
+    }
+}
+
+/// `data` without a single trailing `\n` or `\r\n`
+fn strip_newline(data: &[u8]) -> &[u8] {
+    let data = data.strip_suffix(b"\n").unwrap_or(data);
+    data.strip_suffix(b"\r").unwrap_or(data)
+}
+
+/// the raw key in `data`.  A trailing `\n` is only stripped if `data` is one byte longer than a key, as it may be
+/// the last byte of the key.
+fn raw(data: &[u8], key_len: usize) -> &[u8] {
+    match data.strip_suffix(b"\n") {
+        Some(key) if key.len() == key_len => key,
+        _ => data,
+    }
+}
+
+fn decode_hex(text: &[u8]) -> crate::Result<Vec<u8>> {
+    if !text.len().is_multiple_of(2) {
+        return Err(crate::Error::KeySourceError(String::from("Odd number of hex digits")));
+    }
+    if let Some(index) = text.iter().position(|b| !b.is_ascii_hexdigit()) {
+        return Err(crate::Error::KeySourceError(format!(
+            "Invalid hex digit at index `{}`",
+            index
+        )));
+    }
+
+    let nibble = |b: u8| (b as char).to_digit(16).expect("checked hex digit") as u8;
+    let mut bytes = Vec::with_capacity(text.len() / 2);
+    bytes.extend(text.chunks(2).map(|pair| nibble(pair[0]) << 4 | nibble(pair[1])));
+    Ok(bytes)
+}
+
+fn decode(text: &[u8], encoding: KeyEncoding) -> crate::Result<Vec<u8>> {
+    match encoding {
+        KeyEncoding::Hex => decode_hex(text),
+        KeyEncoding::Base64 => Base64::decode_secret(text),
+        _ => Ok(text.to_vec()),
+    }
+}
+
+/// load a key, which wipes `bytes` if they don't have the length of a key
+fn load<T: BoxProvider>(bytes: crate::Result<Vec<u8>>) -> crate::Result<Key<T>> {
+    Key::load(bytes?)
+}
+
+/// parse the key in the contents `data` of a key source
+fn parse<T: BoxProvider>(data: &[u8], encoding: KeyEncoding) -> crate::Result<Key<T>> {
+    let text = strip_newline(data);
+    match encoding {
+        KeyEncoding::Raw => Key::load_from_slice(raw(data, T::box_key_len())),
+        KeyEncoding::Hex | KeyEncoding::Base64 => load(decode(text, encoding)),
+        KeyEncoding::Auto => {
+            // report the length of the first encoding that decodes if none has the length of a key
+            let mut rejected = None;
+            for encoding in [KeyEncoding::Hex, KeyEncoding::Base64] {
+                match decode(text, encoding) {
+                    Ok(bytes) if bytes.len() == T::box_key_len() => return Key::load(bytes),
+                    Ok(bytes) => {
+                        rejected.get_or_insert(bytes.len());
+                        wipe(bytes);
+                    }
+                    Err(_) => {}
+                }
+            }
+            let raw = raw(data, T::box_key_len());
+            match rejected {
+                Some(actual) if raw.len() != T::box_key_len() => Err(crate::Error::InvalidKeyLength {
+                    expected: T::box_key_len(),
+                    actual,
+                }),
+                _ => Key::load_from_slice(raw),
+            }
+        }
+    }
+}
+
+/// read at most `MAX_KEY_SOURCE_LEN` bytes from `reader` into a buffer allocated once and parse the key in them
+fn read<T: BoxProvider>(mut reader: impl Read, encoding: KeyEncoding) -> crate::Result<Key<T>> {
+    let mut buf = vec![0; MAX_KEY_SOURCE_LEN + 1];
+    let mut len = 0;
+    while len < buf.len() {
+        match reader.read(&mut buf[len..]) {
+            Ok(0) => break,
+            Ok(read) => len += read,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(e) => {
+                wipe(buf);
+                return Err(e.into());
+            }
+        }
+    }
+
+    let key = match len {
+        len if len > MAX_KEY_SOURCE_LEN => Err(too_large(len)),
+        len => parse(&buf[..len], encoding),
+    };
+    wipe(buf);
+    key
+}
+
+impl<T: BoxProvider> Key<T> {
+    /// load a key from the environment variable `var`, in hex, base64 or raw, see `KeyEncoding::Auto`.  The
+    /// variable is kept, see `Key::from_env_with`.
+    pub fn from_env(var: &str) -> crate::Result<Self> {
+        Self::from_env_with(var, KeyEncoding::Auto, false)
+    }
+
+    /// load a key in `encoding` from the environment variable `var` and remove it from the environment of the
+    /// process if `remove` is set, even if it doesn't hold a valid key.  A single trailing newline is stripped.
+    /// Fails with `Error::KeySourceError` if the variable isn't set, with `Error::PayloadTooLarge` if it's longer than
+    /// `MAX_KEY_SOURCE_LEN` and with `Error::InvalidKeyLength` if the key doesn't have `BoxProvider::box_key_len`
+    /// bytes.  All copies of the value are wiped, except the one in the environment if it's kept.
+    pub fn from_env_with(var: &str, encoding: KeyEncoding, remove: bool) -> crate::Result<Self> {
+        let value = std::env::var_os(var)
+            .ok_or_else(|| crate::Error::KeySourceError(format!("The environment variable `{}` is not set", var)))?;
+        if remove {
+            std::env::remove_var(var);
+        }
+        #[cfg(unix)]
+        let data = value.into_vec();
+        #[cfg(not(unix))]
+        let data = value
+            .into_string()
+            .map_err(|_| crate::Error::KeySourceError(format!("The environment variable `{}` is not unicode", var)))?
+            .into_bytes();
+
+        let key = match data.len() {
+            len if len > MAX_KEY_SOURCE_LEN => Err(too_large(len)),
+            _ => parse(&data, encoding),
+        };
+        wipe(data);
+        key
+    }
+
+    /// load a key from a file of hex digits, see `Key::from_file_with`
+    pub fn from_hex_file(path: impl AsRef<Path>) -> crate::Result<Self> {
+        Self::from_file_with(path, KeyEncoding::Hex)
+    }
+
+    /// load a key from a file of the raw key bytes, see `Key::from_file_with`
+    pub fn from_raw_file(path: impl AsRef<Path>) -> crate::Result<Self> {
+        Self::from_file_with(path, KeyEncoding::Raw)
+    }
+
+    /// load a key in `encoding` from the file at `path`.  A single trailing newline is stripped, of raw keys only if
+    /// the file is one byte longer than a key.  Fails with `Error::Io` if the file can't be read, with
+    /// `Error::PayloadTooLarge` if it's longer than `MAX_KEY_SOURCE_LEN` and with `Error::InvalidKeyLength` if the key
+    /// doesn't have `BoxProvider::box_key_len` bytes.  The read buffer is wiped.
+    pub fn from_file_with(path: impl AsRef<Path>, encoding: KeyEncoding) -> crate::Result<Self> {
+        let path = path.as_ref();
+        let file = File::open(path)
+            .map_err(|e| std::io::Error::new(e.kind(), format!("Unable to open `{}`: {}", path.display(), e)))?;
+        read(file, encoding)
+    }
+
+    /// load a key from the file descriptor `fd` in hex, base64 or raw, see `KeyEncoding::Auto` and
+    /// `Key::from_fd_with`
+    #[cfg(unix)]
+    pub fn from_fd(fd: OwnedFd) -> crate::Result<Self> {
+        Self::from_fd_with(fd, KeyEncoding::Auto)
+    }
+
+    /// load a key in `encoding` from the file descriptor `fd` like `Key::from_file_with`, for example one passed by
+    /// systemd.  The descriptor is read to its end and closed.
+    #[cfg(unix)]
+    pub fn from_fd_with(fd: OwnedFd, encoding: KeyEncoding) -> crate::Result<Self> {
+        read(File::from(fd), encoding)
+    }
+}
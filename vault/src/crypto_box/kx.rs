@@ -0,0 +1,176 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+use crate::{
+    crypto_box::{wipe, BoxProvider, Key},
+    ct::ct_eq,
+};
+
+use std::{convert::TryFrom, fmt};
+
+use hkdf::Hkdf;
+use sha2::Sha256;
+use zeroize::{Zeroize, Zeroizing};
+
+/// the length of X25519 secrets, public keys and shared secrets
+pub const KX_KEY_LEN: usize = 32;
+
+/// the HKDF info of the shared key, followed by the context of `derive_shared`
+const SHARED_KEY_INFO: &[u8] = b"vault x25519 shared key";
+/// the HKDF info of a secret derived from a `Key`, followed by the info of `StaticSecret::from_key`
+const SECRET_INFO: &[u8] = b"vault x25519 secret";
+
+/// An X25519 secret, wiped when it's dropped.  Its `Debug` doesn't print it.
+pub struct StaticSecret(Zeroizing<[u8; KX_KEY_LEN]>);
+
+/// The X25519 public key of a `StaticSecret`, sent to the other side over the untrusted channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PublicKey([u8; KX_KEY_LEN]);
+
+impl StaticSecret {
+    /// a random secret from the randomness of `P`
+    pub fn random<P: BoxProvider>() -> crate::Result<Self> {
+        let mut secret = Zeroizing::new([0; KX_KEY_LEN]);
+        P::random_buf(&mut secret[..]).map_err(Into::into)?;
+        Ok(Self(secret))
+    }
+
+    /// the secret `bytes`, which are clamped when they are used
+    pub fn from_bytes(mut bytes: [u8; KX_KEY_LEN]) -> Self {
+        let secret = Self(Zeroizing::new(bytes));
+        bytes.zeroize();
+        secret
+    }
+
+    /// derive a secret from `key` for the purpose described by `info` using HKDF-SHA256, so an owner of a vault
+    /// has a public key without storing another secret.  The same `info` always yields the same secret.
+    pub fn from_key<P: BoxProvider>(key: &Key<P>, info: &[u8]) -> crate::Result<Self> {
+        let mut secret = Zeroizing::new([0; KX_KEY_LEN]);
+        Hkdf::<Sha256>::new(None, key.bytes())
+            .expand(&[SECRET_INFO, info].concat(), &mut secret[..])
+            .map_err(|_| crate::Error::crypto("derive secret", "Unable to derive the X25519 secret"))?;
+        Ok(Self(secret))
+    }
+
+    /// get the secret's bytes
+    pub fn bytes(&self) -> &[u8] {
+        &self.0[..]
+    }
+
+    /// the public key of the secret
+    pub fn public_key(&self) -> PublicKey {
+        PublicKey(x25519_dalek::x25519(*self.0, x25519_dalek::X25519_BASEPOINT_BYTES))
+    }
+
+    /// the raw X25519 Diffie-Hellman of the secret and `their_public`.  Fails with `Error::CryptoError` if the
+    /// shared secret is all zeros, which a public key of low order forces regardless of the secret.  Use
+    /// `derive_shared` for a key, the shared secret isn't uniformly random.
+    pub fn diffie_hellman(&self, their_public: &PublicKey) -> crate::Result<Zeroizing<[u8; KX_KEY_LEN]>> {
+        let shared = Zeroizing::new(x25519_dalek::x25519(*self.0, their_public.0));
+        if ct_eq(&shared[..], &[0; KX_KEY_LEN]) {
+            return Err(crate::Error::crypto(
+                "key exchange",
+                "The shared secret is zero, the public key is of low order",
+            ));
+        }
+        Ok(shared)
+    }
+}
+
+impl fmt::Debug for StaticSecret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("StaticSecret(..)")
+    }
+}
+
+impl TryFrom<&[u8]> for StaticSecret {
+    type Error = crate::Error;
+
+    fn try_from(bytes: &[u8]) -> crate::Result<Self> {
+        let mut secret = Zeroizing::new([0; KX_KEY_LEN]);
+        if bytes.len() != KX_KEY_LEN {
+            return Err(crate::Error::InvalidKeyLength {
+                expected: KX_KEY_LEN,
+                actual: bytes.len(),
+            });
+        }
+        secret.copy_from_slice(bytes);
+        Ok(Self(secret))
+    }
+}
+
+impl PublicKey {
+    /// the public key `bytes`, the u-coordinate of the point
+    pub const fn from_bytes(bytes: [u8; KX_KEY_LEN]) -> Self {
+        Self(bytes)
+    }
+
+    /// get the public key's bytes
+    pub fn bytes(&self) -> &[u8; KX_KEY_LEN] {
+        &self.0
+    }
+}
+
+impl AsRef<[u8]> for PublicKey {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl From<[u8; KX_KEY_LEN]> for PublicKey {
+    fn from(bytes: [u8; KX_KEY_LEN]) -> Self {
+        Self(bytes)
+    }
+}
+
+impl TryFrom<&[u8]> for PublicKey {
+    type Error = crate::Error;
+
+    fn try_from(bytes: &[u8]) -> crate::Result<Self> {
+        let bytes = <[u8; KX_KEY_LEN]>::try_from(bytes).map_err(|_| crate::Error::InvalidKeyLength {
+            expected: KX_KEY_LEN,
+            actual: bytes.len(),
+        })?;
+        Ok(Self(bytes))
+    }
+}
+
+/// derive the `Key` shared with the owner of `their_public`: HKDF-SHA256 over the X25519 Diffie-Hellman of
+/// `my_secret` and `their_public`, salted with both public keys and bound to `context`, a label of the purpose of
+/// the key.  The other side derives the same key from its secret, the public key of `my_secret` and the same
+/// `context`; the public keys are ordered, so it doesn't matter who is who.  Different contexts yield unrelated keys.
+///
+/// Fails with `Error::CryptoError` if the shared secret is all zeros, see `StaticSecret::diffie_hellman`.  Neither
+/// side is authenticated, check the public keys out of band.
+pub fn derive_shared<P: BoxProvider>(
+    my_secret: &StaticSecret,
+    their_public: &PublicKey,
+    context: &[u8],
+) -> crate::Result<Key<P>> {
+    let shared = my_secret.diffie_hellman(their_public)?;
+    let my_public = my_secret.public_key();
+    let (first, second) = match my_public.0 <= their_public.0 {
+        true => (&my_public, their_public),
+        false => (their_public, &my_public),
+    };
+    let salt = [first.as_ref(), second.as_ref()].concat();
+    let info = [SHARED_KEY_INFO, context].concat();
+
+    let mut key = vec![0; P::box_key_len()];
+    if Hkdf::<Sha256>::new(Some(&salt), &shared[..])
+        .expand(&info, &mut key)
+        .is_err()
+    {
+        wipe(key);
+        return Err(crate::Error::crypto("key exchange", "Unable to derive the shared key"));
+    }
+    Key::load(key)
+}
@@ -0,0 +1,190 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+use crate::crypto_box::{open_box, BoxProvider, Key, KeyFingerprint};
+
+use std::{
+    collections::HashMap,
+    marker::PhantomData,
+    time::{Duration, SystemTime},
+};
+
+use serde::{Deserialize, Serialize};
+
+/// When a `GuardedOpen` locks a key out.  After `threshold` consecutive failed opens the key is locked for `base_delay`,
+/// every further failure doubles the delay up to `max_delay`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LockoutPolicy {
+    /// the number of consecutive failures which lock the key
+    pub threshold: u32,
+    /// the delay after `threshold` failures
+    pub base_delay: Duration,
+    /// the longest delay
+    pub max_delay: Duration,
+}
+
+impl Default for LockoutPolicy {
+    /// locks for a second after 5 failures, and up to an hour
+    fn default() -> Self {
+        Self {
+            threshold: 5,
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(60 * 60),
+        }
+    }
+}
+
+impl LockoutPolicy {
+    /// the delay after `failures` consecutive failures, `None` below the threshold
+    pub fn delay(&self, failures: u32) -> Option<Duration> {
+        let doublings = failures.checked_sub(self.threshold.max(1))?;
+        let delay = 2u32
+            .checked_pow(doublings)
+            .and_then(|factor| self.base_delay.checked_mul(factor))
+            .unwrap_or(self.max_delay);
+        Some(delay.min(self.max_delay))
+    }
+}
+
+/// the failed attempts with a key
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+struct Attempts {
+    /// the fingerprint of the key
+    fingerprint: KeyFingerprint,
+    /// the number of consecutive failures
+    failures: u32,
+    /// the end of the lockout
+    locked_until: Option<SystemTime>,
+}
+
+/// The failed attempts of a `GuardedOpen` per key fingerprint.  Serializable, so the caller can persist it and the
+/// lockout survives restarts of the process.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "Vec<Attempts>", into = "Vec<Attempts>")]
+pub struct LockoutState {
+    attempts: HashMap<KeyFingerprint, Attempts>,
+}
+
+impl From<Vec<Attempts>> for LockoutState {
+    fn from(attempts: Vec<Attempts>) -> Self {
+        Self {
+            attempts: attempts.into_iter().map(|a| (a.fingerprint, a)).collect(),
+        }
+    }
+}
+
+impl From<LockoutState> for Vec<Attempts> {
+    fn from(state: LockoutState) -> Self {
+        let mut attempts: Vec<_> = state.attempts.into_values().collect();
+        attempts.sort_by_key(|a| a.fingerprint);
+        attempts
+    }
+}
+
+impl LockoutState {
+    /// the number of consecutive failed opens with the key of `fingerprint`
+    pub fn failures(&self, fingerprint: &KeyFingerprint) -> u32 {
+        self.attempts.get(fingerprint).map_or(0, |a| a.failures)
+    }
+
+    /// the end of the lockout of the key of `fingerprint`, if it was locked
+    pub fn locked_until(&self, fingerprint: &KeyFingerprint) -> Option<SystemTime> {
+        self.attempts.get(fingerprint).and_then(|a| a.locked_until)
+    }
+
+    /// forget the failures with the key of `fingerprint` and lift its lockout, e.g. after an unlock code was entered
+    pub fn reset(&mut self, fingerprint: &KeyFingerprint) {
+        self.attempts.remove(fingerprint);
+    }
+}
+
+/// Opens boxes like `Decrypt`, but counts consecutive `Error::AuthenticationFailed` results per key fingerprint and
+/// locks the key out with the exponential backoff of a `LockoutPolicy`.  A success resets the count.  Other errors,
+/// like `Error::MalformedCiphertext`, don't count.
+#[derive(Debug, Clone)]
+pub struct GuardedOpen<P: BoxProvider> {
+    policy: LockoutPolicy,
+    state: LockoutState,
+    _provider: PhantomData<P>,
+}
+
+impl<P: BoxProvider> GuardedOpen<P> {
+    /// guard opens with `policy` and no failures yet
+    pub fn new(policy: LockoutPolicy) -> Self {
+        Self::with_state(policy, LockoutState::default())
+    }
+
+    /// guard opens with `policy` and the failures of a persisted `state`
+    pub fn with_state(policy: LockoutPolicy, state: LockoutState) -> Self {
+        Self {
+            policy,
+            state,
+            _provider: PhantomData,
+        }
+    }
+
+    /// the policy of the lockout
+    pub fn policy(&self) -> &LockoutPolicy {
+        &self.policy
+    }
+
+    /// the failed attempts, to be persisted by the caller
+    pub fn state(&self) -> &LockoutState {
+        &self.state
+    }
+
+    /// the failed attempts, e.g. to reset a key
+    pub fn state_mut(&mut self) -> &mut LockoutState {
+        &mut self.state
+    }
+
+    /// open `data` with `key`, see `open_at`
+    pub fn open(&mut self, key: &Key<P>, ad: &[u8], data: &[u8]) -> crate::Result<Vec<u8>> {
+        self.open_at(key, ad, data, SystemTime::now())
+    }
+
+    /// open `data` with `key` at the time `now`.  While the key is locked out the box isn't opened and the error is
+    /// `Error::TooManyAttempts` with the rest of the lockout.  The failure which reaches the threshold of the policy,
+    /// and every failure after it, also fails with `Error::TooManyAttempts` with the new delay.
+    pub fn open_at(&mut self, key: &Key<P>, ad: &[u8], data: &[u8], now: SystemTime) -> crate::Result<Vec<u8>> {
+        let fingerprint = key.fingerprint();
+        if let Some(until) = self.state.locked_until(&fingerprint) {
+            if let Ok(retry_after) = until.duration_since(now) {
+                if retry_after > Duration::ZERO {
+                    return Err(crate::Error::TooManyAttempts { retry_after });
+                }
+            }
+        }
+
+        match open_box(key, ad, data) {
+            Ok(plain) => {
+                self.state.reset(&fingerprint);
+                Ok(plain)
+            }
+            Err(crate::Error::AuthenticationFailed) => {
+                let attempts = self.state.attempts.entry(fingerprint).or_insert(Attempts {
+                    fingerprint,
+                    failures: 0,
+                    locked_until: None,
+                });
+                attempts.failures = attempts.failures.saturating_add(1);
+                match self.policy.delay(attempts.failures) {
+                    Some(retry_after) => {
+                        attempts.locked_until = now.checked_add(retry_after);
+                        Err(crate::Error::TooManyAttempts { retry_after })
+                    }
+                    None => Err(crate::Error::AuthenticationFailed),
+                }
+            }
+            Err(e) => Err(e),
+        }
+    }
+}
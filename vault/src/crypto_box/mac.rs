@@ -0,0 +1,62 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+use crate::crypto_box::{BoxProvider, Key};
+
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use zeroize::Zeroizing;
+
+/// the HKDF info of the MAC key, which keeps it apart from the box key and from other derived keys
+const MAC_KEY_INFO: &[u8] = b"vault mac key";
+
+/// HMAC-SHA256 keyed with a key derived from `key`, over the length of the AD, the AD and the data
+fn hmac<B: BoxProvider>(key: &Key<B>, ad: &[u8], data: &[u8]) -> crate::Result<Hmac<Sha256>> {
+    let mut mac_key = Zeroizing::new([0; 32]);
+    Hkdf::<Sha256>::new(None, key.bytes())
+        .expand(MAC_KEY_INFO, &mut mac_key[..])
+        .map_err(|_| crate::Error::crypto("derive MAC key", "Unable to derive the MAC key"))?;
+
+    let mut mac = Hmac::<Sha256>::new_from_slice(&mac_key[..]).expect("HMAC accepts keys of any length");
+    mac.update(&(ad.len() as u64).to_be_bytes());
+    mac.update(ad);
+    mac.update(data);
+    Ok(mac)
+}
+
+pub(crate) fn compute<B: BoxProvider>(key: &Key<B>, ad: &[u8], data: &[u8]) -> crate::Result<Vec<u8>> {
+    Ok(hmac(key, ad, data)?.finalize().into_bytes().to_vec())
+}
+
+pub(crate) fn verify<B: BoxProvider>(key: &Key<B>, ad: &[u8], data: &[u8], mac: &[u8]) -> crate::Result<()> {
+    // compares in constant time and rejects MACs of any other length, truncated ones included
+    hmac(key, ad, data)?
+        .verify_slice(mac)
+        .map_err(|_| crate::Error::AuthenticationFailed)
+}
+
+/// trait for data that stays readable but needs integrity, like record metadata
+pub trait Authenticate<T: From<Vec<u8>>>: AsRef<[u8]> {
+    /// computes the MAC of the data and the `ad` with `BoxProvider::box_mac` and creates a type T from it
+    fn authenticate<B: BoxProvider>(&self, key: &Key<B>, ad: &[u8]) -> crate::Result<T> {
+        Ok(T::from(B::box_mac(key, ad, self.as_ref())?))
+    }
+}
+
+/// trait for data authenticated with `Authenticate`
+pub trait Verify: AsRef<[u8]> {
+    /// verifies the `mac` of the data and the `ad` with `BoxProvider::box_verify_mac`.  Fails with
+    /// `Error::AuthenticationFailed` if it doesn't match.
+    fn verify<B: BoxProvider>(&self, key: &Key<B>, ad: &[u8], mac: &[u8]) -> crate::Result<()> {
+        B::box_verify_mac(key, ad, self.as_ref(), mac)
+    }
+}
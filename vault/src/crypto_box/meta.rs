@@ -0,0 +1,77 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+use crate::crypto_box::{BoxProvider, Key};
+
+use std::convert::TryInto;
+
+use serde::{Deserialize, Serialize};
+
+/// Metadata identifying a key across rotations.  The metadata never influences the key bytes and is ignored when
+/// comparing or hashing keys.
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyMeta {
+    /// unique identifier of the key
+    pub id: [u8; 16],
+    /// version of the key, incremented on rotation
+    pub version: u32,
+    /// creation time of the key in seconds since the unix epoch
+    pub created_at: u64,
+}
+
+impl KeyMeta {
+    /// length of the header which `Encrypt::encrypt_with_meta` prepends to the ciphertext
+    pub const HEADER_LEN: usize = 20;
+
+    /// the key id and the big endian version.  Used as header of the ciphertext and as prefix of the AD.
+    pub(crate) fn header(&self) -> [u8; Self::HEADER_LEN] {
+        let mut header = [0; Self::HEADER_LEN];
+        header[..16].copy_from_slice(&self.id);
+        header[16..].copy_from_slice(&self.version.to_be_bytes());
+        header
+    }
+
+    /// split a ciphertext created by `Encrypt::encrypt_with_meta` into its header and the sealed data, and check
+    /// that the header belongs to this metadata.
+    pub(crate) fn check_header<'a>(&self, data: &'a [u8]) -> crate::Result<(&'a [u8], &'a [u8])> {
+        if data.len() < Self::HEADER_LEN {
+            return Err(crate::Error::crypto("parse key header", "Missing key header"));
+        }
+
+        let (header, sealed) = data.split_at(Self::HEADER_LEN);
+        if header != self.header() {
+            let version = u32::from_be_bytes(header[16..].try_into().expect("header has a 4 byte version"));
+            return Err(crate::Error::KeyMismatch {
+                id: header[..16].iter().map(|b| format!("{:02x}", b)).collect(),
+                version,
+            });
+        }
+        Ok((header, sealed))
+    }
+}
+
+impl<T: BoxProvider> Key<T> {
+    /// attach metadata to the key
+    pub fn with_meta(mut self, meta: KeyMeta) -> Self {
+        self.meta = Some(meta);
+        self
+    }
+
+    /// get the metadata attached to the key
+    pub fn meta(&self) -> Option<&KeyMeta> {
+        self.meta.as_ref()
+    }
+
+    /// get the metadata or fail if the key has none
+    pub(crate) fn require_meta(&self) -> crate::Result<&KeyMeta> {
+        self.meta().ok_or(crate::Error::InterfaceError)
+    }
+}
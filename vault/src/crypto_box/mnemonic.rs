@@ -0,0 +1,113 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+use crate::crypto_box::{BoxProvider, Key};
+
+use std::fmt;
+
+use bip39::Language;
+use zeroize::Zeroize;
+
+/// A BIP39 English mnemonic phrase for a key.  The phrase is wiped when it is dropped.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Mnemonic(String);
+
+impl Mnemonic {
+    /// get the phrase as space separated words
+    pub fn phrase(&self) -> &str {
+        &self.0
+    }
+
+    /// get an iterator over the words of the phrase
+    pub fn words(&self) -> impl Iterator<Item = &str> {
+        self.0.split(' ')
+    }
+
+    /// get the number of words in the phrase
+    pub fn word_count(&self) -> usize {
+        self.words().count()
+    }
+}
+
+impl fmt::Debug for Mnemonic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Mnemonic").field(&"<redacted>").finish()
+    }
+}
+
+impl Drop for Mnemonic {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl<T: BoxProvider> Key<T> {
+    /// encode the key bytes as a BIP39 English mnemonic.  Only keys of 16, 20, 24, 28 or 32 bytes can be encoded.
+    pub fn to_mnemonic(&self) -> crate::Result<Mnemonic> {
+        let mnemonic = bip39::Mnemonic::from_entropy_in(Language::English, self.bytes()).map_err(map_err)?;
+
+        let mut phrase = String::with_capacity(mnemonic.word_count() * 9);
+        for (i, word) in mnemonic.words().enumerate() {
+            if i > 0 {
+                phrase.push(' ');
+            }
+            phrase.push_str(word);
+        }
+        Ok(Mnemonic(phrase))
+    }
+
+    /// load a key from a BIP39 English mnemonic.  Surrounding and repeated whitespace as well as upper case letters
+    /// are accepted.  The passphrase isn't part of the encoding of `Key::to_mnemonic`, so a non-empty `passphrase`
+    /// fails with `Error::InterfaceError`; use `Key::from_mnemonic_seed` to derive a key from the phrase and a
+    /// passphrase.
+    pub fn from_mnemonic(phrase: &str, passphrase: &str) -> crate::Result<Self> {
+        if !passphrase.is_empty() {
+            return Err(crate::Error::InterfaceError);
+        }
+        let mnemonic = parse(phrase)?;
+        let (mut entropy, len) = mnemonic.to_entropy_array();
+        let key = Self::load_from_slice(&entropy[..len]);
+        entropy.zeroize();
+        key
+    }
+
+    /// derive a key from the BIP39 seed of a mnemonic and `passphrase`, the first `box_key_len` bytes of the 64 byte
+    /// seed.  The phrase is parsed like in `Key::from_mnemonic` and the passphrase must be NFKD normalized.  The key
+    /// isn't the one encoded by the phrase, so `Key::to_mnemonic` doesn't yield the phrase again.
+    pub fn from_mnemonic_seed(phrase: &str, passphrase: &str) -> crate::Result<Self> {
+        let mut seed = parse(phrase)?.to_seed_normalized(passphrase);
+        let key = match seed.get(..T::box_key_len()) {
+            Some(bytes) => Self::load_from_slice(bytes),
+            None => Err(crate::Error::InvalidKeyLength {
+                expected: T::box_key_len(),
+                actual: seed.len(),
+            }),
+        };
+        seed.zeroize();
+        key
+    }
+}
+
+/// parse a phrase with any whitespace and case, the normalized copy is wiped
+fn parse(phrase: &str) -> crate::Result<bip39::Mnemonic> {
+    let mut normalized = phrase.split_whitespace().collect::<Vec<_>>().join(" ");
+    normalized.make_ascii_lowercase();
+    let mnemonic = bip39::Mnemonic::parse_in_normalized(Language::English, &normalized).map_err(map_err);
+    normalized.zeroize();
+    mnemonic
+}
+
+fn map_err(e: bip39::Error) -> crate::Error {
+    match e {
+        bip39::Error::UnknownWord(index) => crate::Error::InvalidMnemonicWord(index),
+        e => crate::Error::MnemonicError(e.to_string()),
+    }
+}
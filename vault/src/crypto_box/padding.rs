@@ -0,0 +1,78 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+use crate::crypto_box::{try_alloc, try_copy};
+
+use std::convert::{TryFrom, TryInto};
+
+/// the length of the big endian `u64` length of the plaintext in front of the padded plaintext
+const HEADER_LEN: usize = 8;
+
+/// How `Encrypt::encrypt_padded` pads the plaintext to hide its length.  The padded plaintext starts with the length
+/// of the plaintext, so plaintext ending in zero bytes is never truncated by `Decrypt::decrypt_unpadded`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaddingScheme {
+    /// pad to a multiple of the bucket size, which must not be 0
+    Bucket(usize),
+    /// pad with [Padmé](https://lbarman.ch/blog/padme/), which leaks `O(log log n)` bits of the length and adds at
+    /// most 12% of it
+    Padme,
+    /// only add the length
+    None,
+}
+
+impl PaddingScheme {
+    /// the length of the padded plaintext, including the length in front of it, for `len` bytes of plaintext
+    pub fn padded_len(&self, len: usize) -> crate::Result<usize> {
+        let too_large = || crate::Error::InterfaceErrorDetailed(format!("Unable to pad `{}` bytes", len));
+        let len = len.checked_add(HEADER_LEN).ok_or_else(too_large)?;
+        match *self {
+            PaddingScheme::Bucket(0) => Err(crate::Error::InterfaceErrorDetailed(String::from(
+                "Invalid bucket size `0`",
+            ))),
+            PaddingScheme::Bucket(size) => len.div_ceil(size).checked_mul(size).ok_or_else(too_large),
+            PaddingScheme::Padme => {
+                // keep the top `log2(log2(len)) + 1` bits of the length and round up the rest
+                let exponent = usize::BITS - 1 - len.leading_zeros();
+                let mantissa = u32::BITS - exponent.leading_zeros();
+                let mask = (1 << (exponent - mantissa)) - 1;
+                len.checked_add(mask).map(|len| len & !mask).ok_or_else(too_large)
+            }
+            PaddingScheme::None => Ok(len),
+        }
+    }
+}
+
+/// prefix `data` with its length and pad it with zeros according to `scheme`
+pub(crate) fn pad(data: &[u8], scheme: PaddingScheme) -> crate::Result<Vec<u8>> {
+    let mut padded = try_alloc(scheme.padded_len(data.len())?)?;
+    padded[..HEADER_LEN].copy_from_slice(&(data.len() as u64).to_be_bytes());
+    padded[HEADER_LEN..HEADER_LEN + data.len()].copy_from_slice(data);
+    Ok(padded)
+}
+
+/// undo `pad`.  Fails with `Error::MalformedCiphertext` if `padded` is shorter than its length says.
+pub(crate) fn unpad(padded: &[u8]) -> crate::Result<Vec<u8>> {
+    let malformed = |expected_min| crate::Error::MalformedCiphertext {
+        expected_min,
+        got: padded.len(),
+    };
+    if padded.len() < HEADER_LEN {
+        return Err(malformed(HEADER_LEN));
+    }
+    let (len, rest) = padded.split_at(HEADER_LEN);
+    let len = u64::from_be_bytes(len.try_into().expect("8 bytes"));
+    match usize::try_from(len) {
+        Ok(len) if len <= rest.len() => try_copy(&rest[..len]),
+        Ok(len) => Err(malformed(HEADER_LEN.saturating_add(len))),
+        Err(_) => Err(malformed(usize::MAX)),
+    }
+}
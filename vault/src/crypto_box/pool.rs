@@ -0,0 +1,149 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+use crate::crypto_box::{audit_seal, BoxProvider, Key};
+
+use std::{
+    fmt::{self, Debug, Formatter},
+    ops::Deref,
+    sync::Mutex,
+};
+
+use zeroize::Zeroize;
+
+/// A pool of buffers for sealing many boxes without allocating a new buffer for each of them.  Buffers come back
+/// wiped when the `PooledCiphertext` holding them is dropped.  At most `max_buffers` idle buffers are kept, and
+/// buffers that grew beyond `high_water` bytes shrink back to it before they are kept.
+pub struct BufferPool {
+    buffers: Mutex<Vec<Vec<u8>>>,
+    max_buffers: usize,
+    high_water: usize,
+}
+
+/// A box sealed into a buffer of a `BufferPool`.  Derefs to the box, the buffer is wiped and returned to the pool
+/// when it is dropped.
+pub struct PooledCiphertext<'a> {
+    buf: Vec<u8>,
+    pool: &'a BufferPool,
+}
+
+impl Default for BufferPool {
+    /// keeps up to 16 buffers of up to 64 KiB
+    fn default() -> Self {
+        Self::new(16, 64 << 10)
+    }
+}
+
+impl BufferPool {
+    /// create an empty pool which keeps at most `max_buffers` idle buffers of at most `high_water` bytes
+    pub fn new(max_buffers: usize, high_water: usize) -> Self {
+        Self {
+            buffers: Mutex::new(Vec::with_capacity(max_buffers)),
+            max_buffers,
+            high_water,
+        }
+    }
+
+    /// seal `data` into a buffer of the pool, like `Encrypt::encrypt_into`.  Counts as a use of the key, see
+    /// `Key::with_max_uses`.  The buffer only allocates if it's too small for the box, providers with their own
+    /// `BoxProvider::box_seal_in_place` seal without further allocations.
+    pub fn seal_with<B: BoxProvider>(
+        &self,
+        key: &Key<B>,
+        ad: &[u8],
+        data: &[u8],
+    ) -> crate::Result<PooledCiphertext<'_>> {
+        key.checkout_use()?;
+
+        let mut buf = self.buffer();
+        buf.reserve(data.len() + B::box_overhead());
+        buf.extend_from_slice(data);
+        match audit_seal(key, ad, data.len(), B::box_seal_in_place(key, ad, &mut buf)) {
+            Ok(()) => Ok(PooledCiphertext { buf, pool: self }),
+            Err(e) => {
+                self.recycle(buf);
+                Err(e)
+            }
+        }
+    }
+
+    /// take an empty buffer from the pool, or a new one if the pool is empty.  Its spare capacity is zeroed.
+    pub fn buffer(&self) -> Vec<u8> {
+        self.buffers
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .pop()
+            .unwrap_or_default()
+    }
+
+    /// wipe `buf` and keep it for reuse if there is room in the pool
+    pub fn recycle(&self, mut buf: Vec<u8>) {
+        buf.zeroize();
+        if buf.capacity() > self.high_water {
+            // the shrunk buffer is copied out of the wiped one, both only hold zeros
+            buf.shrink_to(self.high_water);
+        }
+        let mut buffers = self.buffers.lock().unwrap_or_else(|e| e.into_inner());
+        if buffers.len() < self.max_buffers {
+            buffers.push(buf);
+        }
+    }
+
+    /// the number of idle buffers in the pool
+    pub fn idle(&self) -> usize {
+        self.buffers.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }
+}
+
+impl Debug for BufferPool {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_struct("BufferPool")
+            .field("idle", &self.idle())
+            .field("max_buffers", &self.max_buffers)
+            .field("high_water", &self.high_water)
+            .finish()
+    }
+}
+
+impl PooledCiphertext<'_> {
+    /// take the box out of the pool, the buffer isn't returned to it
+    pub fn into_vec(mut self) -> Vec<u8> {
+        std::mem::take(&mut self.buf)
+    }
+}
+
+impl Deref for PooledCiphertext<'_> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.buf
+    }
+}
+
+impl AsRef<[u8]> for PooledCiphertext<'_> {
+    fn as_ref(&self) -> &[u8] {
+        &self.buf
+    }
+}
+
+impl Debug for PooledCiphertext<'_> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_tuple("PooledCiphertext").field(&self.buf).finish()
+    }
+}
+
+impl Drop for PooledCiphertext<'_> {
+    fn drop(&mut self) {
+        if self.buf.capacity() > 0 {
+            self.pool.recycle(std::mem::take(&mut self.buf));
+        }
+    }
+}
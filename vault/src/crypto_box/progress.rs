@@ -0,0 +1,42 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+/// A handle to follow and cancel long-running operations like `seal_chunked_with_progress`, `seal_file_with_progress`,
+/// `BoxProvider::seal_stream_with_progress` and `rekey_all_with_progress`.  The operations check for cancellation
+/// before every chunk and report after it, a cancelled operation fails with `Error::Cancelled`.
+pub trait Progress {
+    /// `done` of `total` units of the operation were processed, see the operation for the unit.  `total` is `None`
+    /// if it isn't known up front, like the length of a stream.  `done` never decreases.
+    fn report(&self, done: u64, total: Option<u64>);
+
+    /// whether the operation should stop
+    fn is_cancelled(&self) -> bool;
+}
+
+/// The `Progress` of the operations without progress handle, which ignores reports and is never cancelled.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NoProgress;
+
+impl Progress for NoProgress {
+    fn report(&self, _done: u64, _total: Option<u64>) {}
+
+    fn is_cancelled(&self) -> bool {
+        false
+    }
+}
+
+/// fail with `Error::Cancelled` if `progress` was cancelled
+pub(crate) fn check_cancelled(progress: &dyn Progress) -> crate::Result<()> {
+    match progress.is_cancelled() {
+        true => Err(crate::Error::Cancelled),
+        false => Ok(()),
+    }
+}
@@ -0,0 +1,46 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+use crate::crypto_box::{open_box, seal_box, BoxProvider, Key};
+
+use zeroize::Zeroize;
+
+/// open `ciphertext` with `old_key` and seal the plaintext under `new_key` with the same `ad`, for key rotation.  The
+/// plaintext never leaves the function and is wiped before it returns.  Counts as a use of `new_key`, see
+/// `Key::with_max_uses`.
+pub fn reencrypt<B: BoxProvider>(
+    ciphertext: &[u8],
+    old_key: &Key<B>,
+    new_key: &Key<B>,
+    ad: &[u8],
+) -> crate::Result<Vec<u8>> {
+    reencrypt_across(ciphertext, old_key, new_key, ad, ad)
+}
+
+/// like `reencrypt` but the new key may belong to another provider and the box is opened with `old_ad` and sealed
+/// with `new_ad`, which rebinds it to a new context like a migrated record id.
+pub fn reencrypt_across<B1: BoxProvider, B2: BoxProvider>(
+    ciphertext: &[u8],
+    old_key: &Key<B1>,
+    new_key: &Key<B2>,
+    old_ad: &[u8],
+    new_ad: &[u8],
+) -> crate::Result<Vec<u8>> {
+    let plain = open_box(old_key, old_ad, ciphertext)?;
+    reseal(plain, new_key, new_ad)
+}
+
+/// seal the opened `plain` under `new_key` and wipe it, the second half of `reencrypt_across`
+pub(crate) fn reseal<B: BoxProvider>(mut plain: Vec<u8>, new_key: &Key<B>, new_ad: &[u8]) -> crate::Result<Vec<u8>> {
+    let sealed = new_key.checkout_use().and_then(|_| seal_box(new_key, new_ad, &plain));
+    plain.zeroize();
+    sealed
+}
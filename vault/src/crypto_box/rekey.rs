@@ -0,0 +1,211 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+use crate::{
+    crypto_box::{
+        open_box,
+        progress::{check_cancelled, Progress},
+        reencrypt::reseal,
+        BoxProvider, Key,
+    },
+    metrics,
+};
+
+use std::{
+    cell::RefCell,
+    num::NonZeroUsize,
+    panic, thread,
+    time::{Duration, Instant},
+};
+
+use zeroize::Zeroize;
+
+/// the number of records every worker handles per round
+const RECORDS_PER_WORKER: usize = 64;
+
+/// The options of `rekey_all`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RekeyOptions {
+    /// the number of worker threads, `0` uses one per available CPU
+    pub workers: usize,
+    /// only verify that every record opens under the old key, nothing is sealed or committed
+    pub dry_run: bool,
+    /// the offset of the first record to re-key, the records before it are skipped.  Resumes an aborted run from
+    /// the offset after the last committed record.
+    pub resume_from: usize,
+}
+
+/// The outcome of `rekey_all`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RekeyReport<I> {
+    /// the number of records which were re-keyed and committed, or verified in a dry run
+    pub rekeyed: usize,
+    /// the ids of the records which didn't open under the old key, in the order of the records
+    pub failed: Vec<I>,
+    /// the time the run took
+    pub elapsed: Duration,
+}
+
+/// the `Progress` of `rekey_all`, a callback of the processed records and the total
+struct Callback<F>(RefCell<F>);
+
+impl<F: FnMut(usize, usize)> Progress for Callback<F> {
+    fn report(&self, done: u64, total: Option<u64>) {
+        (self.0.borrow_mut())(done as usize, total.unwrap_or(done) as usize)
+    }
+
+    fn is_cancelled(&self) -> bool {
+        false
+    }
+}
+
+/// the outcome of a single record
+enum Outcome {
+    Sealed(Vec<u8>),
+    Verified,
+    Unopened,
+    Failed(crate::Error),
+}
+
+fn rekey_one<B: BoxProvider>(data: &[u8], ad: &[u8], old_key: &Key<B>, new_key: &Key<B>, dry_run: bool) -> Outcome {
+    match open_box(old_key, ad, data) {
+        Err(_) => Outcome::Unopened,
+        Ok(mut plain) if dry_run => {
+            plain.zeroize();
+            Outcome::Verified
+        }
+        Ok(plain) => match reseal(plain, new_key, ad) {
+            Ok(sealed) => Outcome::Sealed(sealed),
+            Err(e) => Outcome::Failed(e),
+        },
+    }
+}
+
+/// re-key the records of a round on up to `workers` threads, the outcomes are in the order of the records
+fn rekey_round<B, I, D>(
+    round: &[(I, D, &[u8])],
+    old_key: &Key<B>,
+    new_key: &Key<B>,
+    workers: usize,
+    dry_run: bool,
+) -> Vec<Outcome>
+where
+    B: BoxProvider + Sync,
+    I: Sync,
+    D: AsRef<[u8]> + Sync,
+{
+    let rekey_slice = |slice: &[(I, D, &[u8])]| -> Vec<Outcome> {
+        slice
+            .iter()
+            .map(|(_, data, ad)| rekey_one(data.as_ref(), ad, old_key, new_key, dry_run))
+            .collect()
+    };
+    if workers == 1 {
+        return rekey_slice(round);
+    }
+
+    thread::scope(|scope| {
+        let handles: Vec<_> = round
+            .chunks(round.len().div_ceil(workers))
+            .map(|slice| scope.spawn(move || rekey_slice(slice)))
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap_or_else(|e| panic::resume_unwind(e)))
+            .collect()
+    })
+}
+
+/// re-key every `(id, ciphertext, ad)` record from `old_key` to `new_key` with `reencrypt`, fanned out over
+/// `options.workers` threads.  The new ciphertexts are passed to `commit` with the offset and the id of their record
+/// strictly in the order of the records, so a run which aborts leaves the records up to the last committed one
+/// re-keyed and `options.resume_from` continues after it.  `progress` gets the number of processed records and the
+/// total after every round of work.
+///
+/// Records which don't open under the old key are skipped and listed in the report.  Fails with the first error of
+/// `commit` or of sealing under the new key, the records before it are committed.  Every re-keyed record counts as a
+/// use of the new key, see `Key::with_max_uses`.
+pub fn rekey_all<B, I, D>(
+    records: &[(I, D, &[u8])],
+    old_key: &Key<B>,
+    new_key: &Key<B>,
+    options: RekeyOptions,
+    progress: impl FnMut(usize, usize),
+    commit: impl FnMut(usize, &I, Vec<u8>) -> crate::Result<()>,
+) -> crate::Result<RekeyReport<I>>
+where
+    B: BoxProvider + Sync,
+    I: Clone + Sync,
+    D: AsRef<[u8]> + Sync,
+{
+    let progress = Callback(RefCell::new(progress));
+    rekey_all_with_progress(records, old_key, new_key, options, &progress, commit)
+}
+
+/// like `rekey_all` but reports the number of processed records to `progress` and fails with `Error::Cancelled`
+/// before the next round of work once it is cancelled.  The records committed until then stay re-keyed, the run can
+/// be resumed like an aborted one.
+pub fn rekey_all_with_progress<B, I, D>(
+    records: &[(I, D, &[u8])],
+    old_key: &Key<B>,
+    new_key: &Key<B>,
+    options: RekeyOptions,
+    progress: &dyn Progress,
+    mut commit: impl FnMut(usize, &I, Vec<u8>) -> crate::Result<()>,
+) -> crate::Result<RekeyReport<I>>
+where
+    B: BoxProvider + Sync,
+    I: Clone + Sync,
+    D: AsRef<[u8]> + Sync,
+{
+    let start = Instant::now();
+    let workers = match options.workers {
+        0 => thread::available_parallelism().map_or(1, NonZeroUsize::get),
+        workers => workers,
+    };
+    let total = records.len();
+    let mut report = RekeyReport {
+        rekeyed: 0,
+        failed: Vec::new(),
+        elapsed: Duration::ZERO,
+    };
+
+    let mut offset = options.resume_from.min(total);
+    while offset < total {
+        check_cancelled(progress)?;
+        let round = &records[offset..(offset + workers * RECORDS_PER_WORKER).min(total)];
+        let outcomes = rekey_round(round, old_key, new_key, workers, options.dry_run);
+        for (index, ((id, _, _), outcome)) in round.iter().zip(outcomes).enumerate() {
+            match outcome {
+                Outcome::Sealed(sealed) => {
+                    commit(offset + index, id, sealed)?;
+                    report.rekeyed += 1;
+                }
+                Outcome::Verified => report.rekeyed += 1,
+                Outcome::Unopened => report.failed.push(id.clone()),
+                Outcome::Failed(e) => return Err(e),
+            }
+        }
+        offset += round.len();
+        progress.report(offset as u64, Some(total as u64));
+    }
+    report.elapsed = start.elapsed();
+    record_rekey(&report);
+    Ok(report)
+}
+
+/// reports the run of `report` to the metrics
+pub(crate) fn record_rekey<I>(report: &RekeyReport<I>) {
+    metrics::incr(metrics::REKEYS, 1);
+    metrics::incr(metrics::REKEYED_RECORDS, report.rekeyed as u64);
+    metrics::incr(metrics::REKEY_FAILURES, report.failed.len() as u64);
+    metrics::observe(metrics::REKEY_DURATION, report.elapsed);
+}
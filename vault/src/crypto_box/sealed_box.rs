@@ -0,0 +1,56 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+use crate::crypto_box::{
+    check_box_len,
+    kx::{derive_shared, PublicKey, StaticSecret, KX_KEY_LEN},
+    open_box, seal_box, BoxProvider,
+};
+
+use std::convert::TryFrom;
+
+/// the context of the keys of sealed boxes, see `kx::derive_shared`
+const SEALED_BOX_CONTEXT: &[u8] = b"vault sealed box";
+
+/// the bytes a sealed box adds to the data: the ephemeral public key and the `BoxProvider::box_overhead`
+pub fn overhead<P: BoxProvider>() -> usize {
+    KX_KEY_LEN + P::box_overhead()
+}
+
+/// seal `data` and `ad` to the owner of `recipient`, who opens it with `open` and the secret of `recipient`.
+/// The sender holds no key: every box has its own ephemeral X25519 secret, the key is derived from it and
+/// `recipient` with `kx::derive_shared` and the data is sealed with `P`.  The box is laid out as
+///
+/// | bytes | field |
+/// |---|---|
+/// | 32 | the ephemeral public key |
+/// | n | the data sealed with `P` and `ad` |
+///
+/// The box doesn't tell who sealed it.  The layout isn't the one of libsodium's `crypto_box_seal`.
+pub fn seal<P: BoxProvider>(recipient: &PublicKey, ad: &[u8], data: &[u8]) -> crate::Result<Vec<u8>> {
+    let ephemeral = StaticSecret::random::<P>()?;
+    let key = derive_shared::<P>(&ephemeral, recipient, SEALED_BOX_CONTEXT)?;
+    let sealed = seal_box(&key, ad, data)?;
+    Ok([ephemeral.public_key().as_ref(), &sealed].concat())
+}
+
+/// open a box of `seal` with the `recipient` secret and the `ad` it was sealed with.  Fails with
+/// `Error::MalformedCiphertext` if `blob` is shorter than `overhead`, and with `Error::AuthenticationFailed` if it
+/// was tampered with or sealed to someone else.
+pub fn open<P: BoxProvider>(recipient: &StaticSecret, ad: &[u8], blob: &[u8]) -> crate::Result<Vec<u8>> {
+    check_box_len(blob, overhead::<P>())?;
+    let (ephemeral, sealed) = blob.split_at(KX_KEY_LEN);
+    let ephemeral = PublicKey::try_from(ephemeral)?;
+    // an ephemeral key of low order is a forgery
+    let key = derive_shared::<P>(recipient, &ephemeral, SEALED_BOX_CONTEXT)
+        .map_err(|_| crate::Error::AuthenticationFailed)?;
+    open_box(&key, ad, sealed)
+}
@@ -0,0 +1,100 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+use crate::crypto_box::{BoxProvider, Key};
+
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+/// the data sealed by the generic checks
+const DATA: &[u8] = b"vault provider self test";
+/// the AD of the generic checks
+const AD: &[u8] = b"vault self test ad";
+
+/// A check of a provider self test, see `BoxProvider::self_test`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SelfTestCheck {
+    /// the name of the check
+    pub name: String,
+    /// whether the provider passed the check
+    pub passed: bool,
+    /// the time the check took
+    pub duration: Duration,
+}
+
+/// The checks run by `BoxProvider::self_test` and their results.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SelfTestReport {
+    /// the checks in the order they ran
+    pub checks: Vec<SelfTestCheck>,
+}
+
+impl SelfTestReport {
+    /// run the generic checks of the default `BoxProvider::self_test` against the provider `P`: a round trip, the
+    /// length of the boxes against `box_overhead`, the rejection of tampered boxes and a sanity check of
+    /// `random_buf`.
+    pub fn run_generic<P: BoxProvider>() -> crate::Result<Self> {
+        let key = Key::<P>::load(vec![0x42; P::box_key_len()])?;
+        let seal = || P::box_seal(&key, AD, DATA).map_err(Into::into);
+
+        let mut report = Self::default();
+        report.check("round trip", || {
+            let sealed = seal()?;
+            Ok(P::box_open(&key, AD, &sealed).map_err(Into::into)? == DATA)
+        });
+        report.check("overhead", || Ok(seal()?.len() == DATA.len() + P::box_overhead()));
+        report.check("tamper", || {
+            let mut sealed = seal()?;
+            let wrong_ad = P::box_open(&key, b"wrong ad", &sealed).is_err();
+            let last = sealed.len() - 1;
+            sealed[last] ^= 1;
+            Ok(wrong_ad && P::box_open(&key, AD, &sealed).is_err())
+        });
+        report.check("random", || {
+            let (mut first, mut second) = ([0; 64], [0; 64]);
+            P::random_buf(&mut first).map_err(Into::into)?;
+            P::random_buf(&mut second).map_err(Into::into)?;
+            Ok(first.iter().any(|b| *b != first[0]) && first != second)
+        });
+        Ok(report)
+    }
+
+    /// run a check and record its result.  A check that fails with an error doesn't pass.
+    pub fn check<F: FnOnce() -> crate::Result<bool>>(&mut self, name: &str, check: F) {
+        let start = Instant::now();
+        let passed = matches!(check(), Ok(true));
+        self.checks.push(SelfTestCheck {
+            name: String::from(name),
+            passed,
+            duration: start.elapsed(),
+        });
+    }
+
+    /// whether the provider passed all checks
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(|c| c.passed)
+    }
+
+    /// the checks the provider failed
+    pub fn failures(&self) -> impl Iterator<Item = &SelfTestCheck> {
+        self.checks.iter().filter(|c| !c.passed)
+    }
+
+    /// fail with `Error::SelfTestFailed` naming the failed checks, if any
+    pub fn ensure_passed(self) -> crate::Result<Self> {
+        if self.passed() {
+            return Ok(self);
+        }
+        let failed: Vec<&str> = self.failures().map(|c| c.name.as_str()).collect();
+        Err(crate::Error::SelfTestFailed(failed.join(", ")))
+    }
+}
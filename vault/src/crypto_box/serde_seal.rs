@@ -0,0 +1,80 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+use crate::crypto_box::{open_box, seal_box, BoxProvider, Key};
+
+use bincode::Options;
+use serde::{de::DeserializeOwned, Serialize};
+use zeroize::Zeroize;
+
+/// the largest serialized value `seal_serde` and `open_serde` accept, 16 MiB
+pub const MAX_SERDE_LEN: usize = 16 << 20;
+
+/// the bincode options of the sealed values.  Part of the format, don't change them.
+fn options(limit: usize) -> impl Options {
+    bincode::DefaultOptions::new().with_limit(limit as u64)
+}
+
+/// serialize `value` with bincode and seal it.  Fails with `Error::PayloadTooLarge` if the serialized value is
+/// larger than `MAX_SERDE_LEN`.
+pub fn seal_serde<B: BoxProvider, T: Serialize>(key: &Key<B>, ad: &[u8], value: &T) -> crate::Result<Vec<u8>> {
+    seal_serde_with_limit(key, ad, value, MAX_SERDE_LEN)
+}
+
+/// like `seal_serde` with a limit of `limit` bytes for the serialized value
+pub fn seal_serde_with_limit<B: BoxProvider, T: Serialize>(
+    key: &Key<B>,
+    ad: &[u8],
+    value: &T,
+    limit: usize,
+) -> crate::Result<Vec<u8>> {
+    let len = options(usize::MAX)
+        .serialized_size(value)
+        .map_err(|e| crate::Error::SerializeError(e.to_string()))?;
+    if len > limit as u64 {
+        return Err(crate::Error::PayloadTooLarge { len, limit });
+    }
+
+    let mut plain = options(limit)
+        .serialize(value)
+        .map_err(|e| crate::Error::SerializeError(e.to_string()))?;
+    key.checkout_use()?;
+    let sealed = seal_box(key, ad, &plain);
+    plain.zeroize();
+    sealed
+}
+
+/// open a box sealed by `seal_serde` and deserialize the value.  Boxes which can't hold a value of at most
+/// `MAX_SERDE_LEN` bytes are refused before they are opened.  A value that doesn't deserialize into `T` fails with
+/// `Error::DeserializeError`.
+pub fn open_serde<B: BoxProvider, T: DeserializeOwned>(key: &Key<B>, ad: &[u8], bytes: &[u8]) -> crate::Result<T> {
+    open_serde_with_limit(key, ad, bytes, MAX_SERDE_LEN)
+}
+
+/// like `open_serde` with a limit of `limit` bytes for the serialized value
+pub fn open_serde_with_limit<B: BoxProvider, T: DeserializeOwned>(
+    key: &Key<B>,
+    ad: &[u8],
+    bytes: &[u8],
+    limit: usize,
+) -> crate::Result<T> {
+    let len = bytes.len().saturating_sub(B::box_overhead());
+    if len > limit {
+        return Err(crate::Error::PayloadTooLarge { len: len as u64, limit });
+    }
+
+    let mut plain = open_box(key, ad, bytes)?;
+    let value = options(limit)
+        .deserialize(&plain)
+        .map_err(|e| crate::Error::DeserializeError(e.to_string()));
+    plain.zeroize();
+    value
+}
@@ -0,0 +1,184 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+use crate::{
+    crypto_box::{BoxProvider, Key},
+    ct::{ct_eq, ct_select},
+};
+
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use zeroize::Zeroize;
+
+/// A share of a key split with Shamir's secret sharing.  The share bytes are wiped when the share is dropped.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct KeyShare {
+    /// the x coordinate of the share. Never zero.
+    pub index: u8,
+    /// the amount of shares required to recover the key
+    pub threshold: u8,
+    /// the share bytes, one for every byte of the key
+    pub bytes: Vec<u8>,
+    /// a checksum over the other fields to detect corrupted shares
+    pub checksum: [u8; 4],
+}
+
+impl KeyShare {
+    /// create a new share and compute its checksum
+    fn new(index: u8, threshold: u8, bytes: Vec<u8>) -> Self {
+        let checksum = Self::compute_checksum(index, threshold, &bytes);
+        Self {
+            index,
+            threshold,
+            bytes,
+            checksum,
+        }
+    }
+
+    /// checks if the checksum matches the share
+    pub fn is_valid(&self) -> bool {
+        ct_eq(
+            &Self::compute_checksum(self.index, self.threshold, &self.bytes),
+            &self.checksum,
+        )
+    }
+
+    fn compute_checksum(index: u8, threshold: u8, bytes: &[u8]) -> [u8; 4] {
+        let digest = Sha256::new()
+            .chain_update(b"vault-key-share")
+            .chain_update([index, threshold])
+            .chain_update(bytes)
+            .finalize();
+        let mut checksum = [0; 4];
+        checksum.copy_from_slice(&digest[..4]);
+        checksum
+    }
+}
+
+impl Zeroize for KeyShare {
+    fn zeroize(&mut self) {
+        self.bytes.zeroize();
+        self.index.zeroize();
+        self.checksum.zeroize();
+    }
+}
+
+impl Drop for KeyShare {
+    fn drop(&mut self) {
+        self.bytes.zeroize();
+    }
+}
+
+impl<T: BoxProvider> Key<T> {
+    /// split the key into `shares` shares of which any `threshold` can be combined to recover the key.
+    pub fn split(&self, threshold: u8, shares: u8) -> crate::Result<Vec<KeyShare>> {
+        if threshold == 0 || threshold > shares {
+            return Err(crate::Error::InterfaceError);
+        }
+
+        // one random polynomial of degree `threshold - 1` for every byte of the key.
+        let degree = threshold as usize - 1;
+        let mut coefficients = vec![0; self.key.len() * degree];
+        T::random_buf(&mut coefficients).map_err(Into::into)?;
+
+        let shares = (1..=shares)
+            .map(|x| {
+                let bytes = self
+                    .key
+                    .iter()
+                    .enumerate()
+                    .map(|(i, secret)| {
+                        // horner's method, starting with the highest coefficient.
+                        let y = coefficients[i * degree..(i + 1) * degree]
+                            .iter()
+                            .rev()
+                            .fold(0, |acc, c| gf_mul(acc, x) ^ c);
+                        gf_mul(y, x) ^ secret
+                    })
+                    .collect();
+                KeyShare::new(x, threshold, bytes)
+            })
+            .collect();
+
+        coefficients.zeroize();
+        Ok(shares)
+    }
+
+    /// recover a key from at least `threshold` distinct shares.
+    pub fn combine(shares: &[KeyShare]) -> crate::Result<Self> {
+        if let Some(corrupt) = shares.iter().find(|s| !s.is_valid()) {
+            return Err(crate::Error::CorruptShare(corrupt.index));
+        }
+
+        let first = shares.first().ok_or(crate::Error::NotEnoughShares {
+            required: 1,
+            provided: 0,
+        })?;
+        let threshold = first.threshold;
+        let len = first.bytes.len();
+
+        let mut indices = HashSet::new();
+        if shares
+            .iter()
+            .any(|s| s.index == 0 || s.threshold != threshold || s.bytes.len() != len || !indices.insert(s.index))
+        {
+            return Err(crate::Error::InterfaceError);
+        }
+        if shares.len() < threshold as usize {
+            return Err(crate::Error::NotEnoughShares {
+                required: threshold,
+                provided: shares.len(),
+            });
+        }
+        let shares = &shares[..threshold as usize];
+
+        // lagrange interpolation at x = 0
+        let mut key = vec![0; len];
+        for (i, share) in shares.iter().enumerate() {
+            let basis = shares
+                .iter()
+                .enumerate()
+                .filter(|(j, _)| *j != i)
+                .fold(1, |acc, (_, other)| {
+                    gf_mul(acc, gf_mul(other.index, gf_inv(other.index ^ share.index)))
+                });
+            key.iter_mut()
+                .zip(share.bytes.iter())
+                .for_each(|(k, y)| *k ^= gf_mul(*y, basis));
+        }
+        Self::load(key)
+    }
+}
+
+/// multiplication in GF(2^8) with the AES polynomial, without data dependent branches.
+fn gf_mul(mut a: u8, mut b: u8) -> u8 {
+    let mut product = 0;
+    for _ in 0..8 {
+        product ^= ct_select(0, a, b & 1 == 1);
+        a = (a << 1) ^ ct_select(0, 0x1b, a >> 7 == 1);
+        b >>= 1;
+    }
+    product
+}
+
+/// inversion in GF(2^8) as `a^254`.
+fn gf_inv(a: u8) -> u8 {
+    let a2 = gf_mul(a, a);
+    let a4 = gf_mul(a2, a2);
+    let a8 = gf_mul(a4, a4);
+    let a16 = gf_mul(a8, a8);
+    let a32 = gf_mul(a16, a16);
+    let a64 = gf_mul(a32, a32);
+    let a128 = gf_mul(a64, a64);
+    [a2, a4, a8, a16, a32, a64].iter().fold(a128, |acc, x| gf_mul(acc, *x))
+}
@@ -0,0 +1,73 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+use crate::crypto_box::{BoxProvider, Key};
+
+use std::{
+    fmt::{self, Debug, Formatter},
+    ops::Deref,
+    sync::Arc,
+};
+
+/// A key shared by many handles without copying its bytes.  Unlike `Key::clone`, cloning a `SharedKey` only counts
+/// another handle; the key is wiped, and its drop hooks run, once when the last handle is dropped.  Derefs to the
+/// `Key`, so `&shared` can be passed wherever a `&Key` is accepted, e.g. to `Encrypt::encrypt`.
+pub struct SharedKey<T: BoxProvider>(Arc<Key<T>>);
+
+impl<T: BoxProvider> SharedKey<T> {
+    /// get the key
+    pub fn key(&self) -> &Key<T> {
+        &self.0
+    }
+
+    /// the number of handles of the key
+    pub fn handles(&self) -> usize {
+        Arc::strong_count(&self.0)
+    }
+
+    /// get the key back if this is its last handle, otherwise the handle is returned
+    pub fn try_unwrap(self) -> Result<Key<T>, Self> {
+        Arc::try_unwrap(self.0).map_err(Self)
+    }
+}
+
+impl<T: BoxProvider> From<Key<T>> for SharedKey<T> {
+    fn from(key: Key<T>) -> Self {
+        Self(Arc::new(key))
+    }
+}
+
+impl<T: BoxProvider> Clone for SharedKey<T> {
+    /// another handle of the same key, the key bytes aren't copied
+    fn clone(&self) -> Self {
+        Self(Arc::clone(&self.0))
+    }
+}
+
+impl<T: BoxProvider> Deref for SharedKey<T> {
+    type Target = Key<T>;
+
+    fn deref(&self) -> &Key<T> {
+        &self.0
+    }
+}
+
+impl<T: BoxProvider> AsRef<Key<T>> for SharedKey<T> {
+    fn as_ref(&self) -> &Key<T> {
+        &self.0
+    }
+}
+
+impl<T: BoxProvider> Debug for SharedKey<T> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_tuple("SharedKey").field(&*self.0).finish()
+    }
+}
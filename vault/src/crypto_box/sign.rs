@@ -0,0 +1,196 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+#[cfg(feature = "guarded-memory")]
+use crate::crypto_box::guarded::GuardedBytes;
+use crate::{
+    crypto_box::{storage::KeyBytes, wipe},
+    ct::ct_eq,
+};
+
+use std::{convert::TryFrom, fmt::Debug, marker::PhantomData};
+
+use zeroize::Zeroize;
+
+/// A provider interface between the vault and a signature scheme, the counterpart of `BoxProvider` for detached
+/// signatures.  Signatures prove to anyone with the public key who produced the data, e.g. a sealed box, which a box
+/// of a shared key can't.
+pub trait SignProvider: Sized {
+    /// the error of the provider, converted into `crate::Error` like the one of `BoxProvider`
+    type Error: Debug + From<crate::Error> + Into<crate::Error>;
+
+    /// function for the length of the secret signing key
+    fn sign_key_len() -> usize;
+    /// function for the length of the public key
+    fn public_key_len() -> usize;
+    /// function for the length of the signatures
+    fn signature_len() -> usize;
+
+    /// derives the public key of `key`
+    fn public_key(key: &SigningKey<Self>) -> Result<Vec<u8>, Self::Error>;
+
+    /// signs `data` with `key`, the signature is `signature_len` bytes long
+    fn sign(key: &SigningKey<Self>, data: &[u8]) -> Result<Vec<u8>, Self::Error>;
+
+    /// verifies the signature `sig` of `data` by the owner of `public`.  Fails with `Error::MalformedSignature` if
+    /// `sig` or `public` can't be a signature or a public key of the scheme, e.g. because of their lengths, and with
+    /// `Error::SignatureMismatch` if the well-formed signature doesn't match.
+    fn verify(public: &[u8], data: &[u8], sig: &[u8]) -> Result<(), Self::Error>;
+
+    /// fills a buffer `buf` with secure random bytes, like `BoxProvider::random_buf`
+    fn random_buf(buf: &mut [u8]) -> Result<(), Self::Error>;
+}
+
+/// A secret signing key of a `SignProvider`.  Stored like a `Key`, on the heap or with the `guarded-memory` feature
+/// in locked memory, and securely wiped when it is dropped.
+pub struct SigningKey<T: SignProvider> {
+    /// the raw bytes that make up the key
+    key: KeyBytes,
+    /// associated Provider data
+    _sign_provider: PhantomData<T>,
+}
+
+impl<T: SignProvider> SigningKey<T> {
+    /// generate a random signing key using secure random bytes
+    pub fn random() -> crate::Result<Self> {
+        let mut key = vec![0; T::sign_key_len()];
+        if let Err(e) = T::random_buf(&mut key) {
+            wipe(key);
+            return Err(e.into());
+        }
+        Ok(Self::from_bytes(KeyBytes::Heap(key)))
+    }
+
+    /// generate a random signing key stored in memory that is locked into RAM and excluded from core dumps, see
+    /// `Key::random_guarded`
+    #[cfg(feature = "guarded-memory")]
+    pub fn random_guarded() -> crate::Result<Self> {
+        let mut key = GuardedBytes::new(T::sign_key_len())?;
+        T::random_buf(&mut key).map_err(Into::into)?;
+        Ok(Self::from_bytes(KeyBytes::Guarded(key)))
+    }
+
+    /// attempts to load a signing key from inputted data.  Rejected bytes are wiped.
+    pub fn load(key: Vec<u8>) -> crate::Result<Self> {
+        match key {
+            key if key.len() != T::sign_key_len() => {
+                let actual = key.len();
+                wipe(key);
+                Err(crate::Error::InvalidKeyLength {
+                    expected: T::sign_key_len(),
+                    actual,
+                })
+            }
+            key => Ok(Self::from_bytes(KeyBytes::Heap(key))),
+        }
+    }
+
+    /// attempts to load a signing key by copying it from `bytes`.  The caller keeps ownership of `bytes` and is
+    /// responsible for wiping it.
+    pub fn load_from_slice(bytes: &[u8]) -> crate::Result<Self> {
+        match bytes.len() {
+            len if len == T::sign_key_len() => Ok(Self::from_bytes(KeyBytes::Heap(bytes.to_vec()))),
+            actual => Err(crate::Error::InvalidKeyLength {
+                expected: T::sign_key_len(),
+                actual,
+            }),
+        }
+    }
+
+    fn from_bytes(key: KeyBytes) -> Self {
+        Self {
+            key,
+            _sign_provider: PhantomData,
+        }
+    }
+
+    /// get the key's bytes
+    pub fn bytes(&self) -> &[u8] {
+        &self.key
+    }
+
+    /// checks if the key is stored in guarded memory
+    pub fn is_guarded(&self) -> bool {
+        self.key.is_guarded()
+    }
+
+    /// copies the key like `clone`, but keeps a guarded key in guarded memory.  Fails if the memory can't be
+    /// allocated or locked.
+    pub fn try_clone(&self) -> crate::Result<Self> {
+        Ok(Self::from_bytes(self.key.try_clone()?))
+    }
+
+    /// derive the public key, which verifies the signatures of the key
+    pub fn public_key(&self) -> crate::Result<Vec<u8>> {
+        T::public_key(self).map_err(Into::into)
+    }
+}
+
+impl<T: SignProvider> TryFrom<&[u8]> for SigningKey<T> {
+    type Error = crate::Error;
+
+    fn try_from(bytes: &[u8]) -> crate::Result<Self> {
+        Self::load_from_slice(bytes)
+    }
+}
+
+/// copies the key bytes into a new allocation which is wiped on its own.  Panics if a guarded key can't be copied
+/// into new locked memory, use `SigningKey::try_clone` to handle the error.
+impl<T: SignProvider> Clone for SigningKey<T> {
+    fn clone(&self) -> Self {
+        Self::from_bytes(self.key.clone())
+    }
+}
+
+/// compares the keys in constant time.
+impl<T: SignProvider> PartialEq for SigningKey<T> {
+    fn eq(&self, other: &Self) -> bool {
+        ct_eq(self.bytes(), other.bytes())
+    }
+}
+
+impl<T: SignProvider> Eq for SigningKey<T> {}
+
+impl<T: SignProvider> Debug for SigningKey<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SigningKey")
+            .field("len", &self.bytes().len())
+            .field("provider", &std::any::type_name::<T>())
+            .finish()
+    }
+}
+
+impl<T: SignProvider> Drop for SigningKey<T> {
+    fn drop(&mut self) {
+        self.key.zeroize();
+    }
+}
+
+/// trait for data which is signed, e.g. a sealed box.  Implemented for everything that is `AsRef<[u8]>`.
+pub trait Sign: AsRef<[u8]> {
+    /// signs the data with `SignProvider::sign` and returns the detached signature
+    fn sign<S: SignProvider>(&self, key: &SigningKey<S>) -> crate::Result<Vec<u8>> {
+        S::sign(key, self.as_ref()).map_err(Into::into)
+    }
+}
+
+impl<D: AsRef<[u8]> + ?Sized> Sign for D {}
+
+/// trait for data signed with `Sign`.  Implemented for everything that is `AsRef<[u8]>`.
+pub trait VerifySig: AsRef<[u8]> {
+    /// verifies the detached `signature` of the data by the owner of `public` with `SignProvider::verify`.  Fails
+    /// with `Error::MalformedSignature` or `Error::SignatureMismatch`.
+    fn verify_signature<S: SignProvider>(&self, public: &[u8], signature: &[u8]) -> crate::Result<()> {
+        S::verify(public, self.as_ref(), signature).map_err(Into::into)
+    }
+}
+
+impl<D: AsRef<[u8]> + ?Sized> VerifySig for D {}
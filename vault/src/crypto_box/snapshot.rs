@@ -0,0 +1,378 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+//! Password protected snapshots of the records of a vault in a single file, which can be restored on another machine.
+//!
+//! A snapshot of version 1 is laid out as
+//!
+//! | bytes | field |
+//! |---|---|
+//! | 4 | the magic bytes `RCSN` |
+//! | 1 | the format version |
+//! | 4 | the `BoxProvider::box_id` of the provider |
+//! | 12 | the big endian memory cost, iterations and parallelism of the `KdfParams` |
+//! | 16 | the salt of the file key |
+//! | 16 | a check value of the file key, which tells a wrong password from a corrupt file |
+//! | n | the records sealed with `BoxProvider::seal_stream` and the header as AD |
+//! | 32 | an HMAC-SHA256 of everything before it |
+//!
+//! The file key is derived from the password with Argon2id.  The records are framed as the big endian `u64` number
+//! of records followed by every record as its `u32` id length, its id, its `u64` data length and its data.
+//!
+//! Snapshots of two vaults are reconciled by `diff` and `merge`, which compare the entries without opening them.
+
+use crate::{
+    crypto_box::{BoxProvider, KdfParams, Key},
+    ct::ct_eq,
+    metrics,
+    persist_hooks::TempFile,
+    vault::ReadResult,
+};
+
+use std::{
+    convert::TryInto,
+    fs::File,
+    io::{self, BufReader, Cursor, Read, Seek, SeekFrom, Write},
+    path::Path,
+    time::Instant,
+};
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use zeroize::{Zeroize, Zeroizing};
+
+mod merge;
+
+pub use merge::{diff, merge, tombstone, EntryInfo, MergePolicy, Side, SnapshotDiff};
+
+/// the magic bytes starting a snapshot
+pub const MAGIC: [u8; 4] = *b"RCSN";
+/// the current format version
+pub const VERSION: u8 = 1;
+/// the length of the header of version 1
+pub const HEADER_LEN: usize = 53;
+
+const SALT_LEN: usize = 16;
+const CHECK_LEN: usize = 16;
+const MAC_LEN: usize = 32;
+/// the chunk size of the sealed stream
+const CHUNK_SIZE: usize = 64 << 10;
+
+fn corrupt(reason: &str) -> crate::Error {
+    crate::Error::CorruptSnapshot(reason.into())
+}
+
+/// the keys of a snapshot derived from the file key
+struct SnapshotKeys<P: BoxProvider> {
+    key: Key<P>,
+    check: [u8; CHECK_LEN],
+    mac: Hmac<Sha256>,
+}
+
+impl<P: BoxProvider> SnapshotKeys<P> {
+    fn derive(password: &[u8], salt: &[u8], params: KdfParams) -> crate::Result<Self> {
+        let key = Key::derive_from_password(password, salt, params)?;
+        let check = key.derive_child(b"vault snapshot key check")?.bytes()[..CHECK_LEN]
+            .try_into()
+            .expect("keys have at least 16 bytes");
+        let mac_key = key.derive_child(b"vault snapshot mac")?;
+        let mac = Hmac::<Sha256>::new_from_slice(mac_key.bytes()).expect("HMAC accepts keys of any length");
+        Ok(Self { key, check, mac })
+    }
+}
+
+fn header<P: BoxProvider>(params: KdfParams, salt: &[u8; SALT_LEN], check: &[u8; CHECK_LEN]) -> [u8; HEADER_LEN] {
+    let mut header = [0; HEADER_LEN];
+    header[..4].copy_from_slice(&MAGIC);
+    header[4] = VERSION;
+    header[5..9].copy_from_slice(&P::box_id());
+    header[9..13].copy_from_slice(&params.memory_cost.to_be_bytes());
+    header[13..17].copy_from_slice(&params.iterations.to_be_bytes());
+    header[17..21].copy_from_slice(&params.parallelism.to_be_bytes());
+    header[21..37].copy_from_slice(salt);
+    header[37..].copy_from_slice(check);
+    header
+}
+
+/// the records framed as described in the module documentation
+fn serialize(records: &[ReadResult]) -> crate::Result<Zeroizing<Vec<u8>>> {
+    let len = 8 + records
+        .iter()
+        .map(|r| 12 + r.id().len() + r.data().len())
+        .sum::<usize>();
+    let mut plain = Zeroizing::new(Vec::with_capacity(len));
+    plain.extend_from_slice(&(records.len() as u64).to_be_bytes());
+    for record in records {
+        let id_len: u32 = record
+            .id()
+            .len()
+            .try_into()
+            .map_err(|_| crate::Error::InterfaceErrorDetailed("Record id too long for a snapshot".into()))?;
+        plain.extend_from_slice(&id_len.to_be_bytes());
+        plain.extend_from_slice(record.id());
+        plain.extend_from_slice(&(record.data().len() as u64).to_be_bytes());
+        plain.extend_from_slice(record.data());
+    }
+    Ok(plain)
+}
+
+/// a writer which feeds everything written to it into a MAC
+struct MacWriter<W: Write> {
+    inner: W,
+    mac: Hmac<Sha256>,
+}
+
+impl<W: Write> Write for MacWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.mac.update(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// export `records` into a snapshot at `path` sealed under a key derived from `password` with the default
+/// `KdfParams`, see `export_with_params`
+pub fn export<P: BoxProvider>(records: &[ReadResult], password: &[u8], path: &Path) -> crate::Result<()> {
+    export_with_params::<P>(records, password, path, KdfParams::default())
+}
+
+/// export `records` into a snapshot at `path` sealed under a key derived from `password` with `params`.  The
+/// snapshot is written to a temporary file next to `path` which is synced and renamed, so `path` holds either its
+/// old content or the complete snapshot.
+pub fn export_with_params<P: BoxProvider>(
+    records: &[ReadResult],
+    password: &[u8],
+    path: &Path,
+    params: KdfParams,
+) -> crate::Result<()> {
+    let start = Instant::now();
+    let salt = P::random_array::<SALT_LEN>().map_err(Into::into)?;
+    let keys = SnapshotKeys::<P>::derive(password, &salt, params)?;
+    let header = header::<P>(params, &salt, &keys.check);
+    let plain = serialize(records)?;
+
+    let mut out = MacWriter {
+        inner: TempFile::create::<P>(path)?,
+        mac: keys.mac.clone(),
+    };
+    out.write_all(&header)?;
+    P::seal_stream(&keys.key, &header, &mut Cursor::new(&plain[..]), &mut out, CHUNK_SIZE)?;
+    let mac = out.mac.finalize().into_bytes();
+    let mut file = out.inner;
+    file.write_all(&mac)?;
+    file.commit()?;
+    metrics::incr(metrics::SNAPSHOT_EXPORTS, 1);
+    metrics::observe(metrics::SNAPSHOT_EXPORT_DURATION, start.elapsed());
+    Ok(())
+}
+
+/// import the records of the snapshot at `path` which was exported with `password`, see `import_each`
+pub fn import<P: BoxProvider>(path: &Path, password: &[u8]) -> crate::Result<Vec<ReadResult>> {
+    let mut records = Vec::new();
+    import_each::<P>(path, password, |record| {
+        records.push(record);
+        Ok(())
+    })?;
+    Ok(records)
+}
+
+/// import the records of the snapshot at `path` which was exported with `password` and pass them to `f` in order.
+/// The file is streamed: the MAC over the whole file is checked before any record is parsed, then the records are
+/// opened chunk by chunk.
+///
+/// Fails with `Error::WrongPassword` if the key derived from `password` doesn't match the snapshot, with
+/// `Error::CorruptSnapshot` if the file is truncated, tampered with or isn't a snapshot, with
+/// `Error::UnsupportedVersion` for other format versions and with `Error::ProviderMismatch` if it was exported with
+/// another provider.
+pub fn import_each<P: BoxProvider>(
+    path: &Path,
+    password: &[u8],
+    mut f: impl FnMut(ReadResult) -> crate::Result<()>,
+) -> crate::Result<()> {
+    let start = Instant::now();
+    let mut file = File::open(path)?;
+    let len = file.metadata()?.len();
+
+    let mut header = [0; HEADER_LEN];
+    let read = read_full(&mut file, &mut header)?;
+    if read < 5 || header[..4] != MAGIC {
+        return Err(corrupt("Not a snapshot"));
+    }
+    if header[4] != VERSION {
+        return Err(crate::Error::UnsupportedVersion(header[4]));
+    }
+    if len < (HEADER_LEN + MAC_LEN) as u64 {
+        return Err(corrupt("Truncated snapshot"));
+    }
+    if header[5..9] != P::box_id() {
+        return Err(crate::Error::ProviderMismatch {
+            expected: P::box_id().escape_ascii().to_string(),
+            found: header[5..9].escape_ascii().to_string(),
+        });
+    }
+
+    let be_u32 = |at: usize| u32::from_be_bytes(header[at..at + 4].try_into().expect("4 bytes"));
+    let params = KdfParams {
+        memory_cost: be_u32(9),
+        iterations: be_u32(13),
+        parallelism: be_u32(17),
+    };
+    if !params.is_bounded() {
+        return Err(corrupt("Invalid KDF parameters"));
+    }
+
+    let keys = SnapshotKeys::<P>::derive(password, &header[21..37], params)?;
+    if !ct_eq(&keys.check, &header[37..]) {
+        return Err(crate::Error::WrongPassword);
+    }
+
+    // check the MAC of the whole file before anything is parsed
+    let body_len = len - (HEADER_LEN + MAC_LEN) as u64;
+    let mut mac = keys.mac.clone();
+    mac.update(&header);
+    let mut reader = BufReader::new(&mut file);
+    let mut block = vec![0; CHUNK_SIZE];
+    let mut left = body_len;
+    while left > 0 {
+        let n = read_full(&mut reader, &mut block[..left.min(CHUNK_SIZE as u64) as usize])?;
+        if n == 0 {
+            return Err(corrupt("Truncated snapshot"));
+        }
+        mac.update(&block[..n]);
+        left -= n as u64;
+    }
+    let mut expected = [0; MAC_LEN];
+    if read_full(&mut reader, &mut expected)? < MAC_LEN || !ct_eq(&mac.finalize().into_bytes(), &expected) {
+        return Err(corrupt("MAC mismatch"));
+    }
+
+    // open the records
+    reader.seek(SeekFrom::Start(HEADER_LEN as u64))?;
+    let mut parser = Parser::new(&mut f);
+    P::open_stream(&keys.key, &header, &mut reader.take(body_len), &mut parser, CHUNK_SIZE)?;
+    parser.finish()?;
+    metrics::incr(metrics::SNAPSHOT_IMPORTS, 1);
+    metrics::observe(metrics::SNAPSHOT_IMPORT_DURATION, start.elapsed());
+    Ok(())
+}
+
+/// read from `reader` until `buf` is full or the reader is exhausted
+fn read_full(reader: &mut impl Read, buf: &mut [u8]) -> crate::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(filled)
+}
+
+/// a writer which parses the framed records written to it and passes them on
+struct Parser<'a, F: FnMut(ReadResult) -> crate::Result<()>> {
+    f: &'a mut F,
+    /// the bytes of the incomplete record
+    buf: Vec<u8>,
+    /// the number of records and how many of them are left, once the count was read
+    left: Option<u64>,
+}
+
+impl<'a, F: FnMut(ReadResult) -> crate::Result<()>> Parser<'a, F> {
+    fn new(f: &'a mut F) -> Self {
+        Self {
+            f,
+            buf: Vec::new(),
+            left: None,
+        }
+    }
+
+    /// parse the complete records at the front of the buffer and return the number of bytes they took
+    fn parse(&mut self) -> crate::Result<usize> {
+        let mut pos = 0;
+        loop {
+            let rest = &self.buf[pos..];
+            let left = match self.left {
+                None if rest.len() >= 8 => {
+                    self.left = Some(u64::from_be_bytes(rest[..8].try_into().expect("8 bytes")));
+                    pos += 8;
+                    continue;
+                }
+                Some(left) if left > 0 && rest.len() >= 4 => left,
+                _ => return Ok(pos),
+            };
+
+            let id_len = u32::from_be_bytes(rest[..4].try_into().expect("4 bytes")) as usize;
+            if rest.len() < 4 + id_len + 8 {
+                return Ok(pos);
+            }
+            let data_len = u64::from_be_bytes(rest[4 + id_len..12 + id_len].try_into().expect("8 bytes"));
+            if ((rest.len() - 12 - id_len) as u64) < data_len {
+                return Ok(pos);
+            }
+            let data_len = data_len as usize;
+            let id = rest[4..4 + id_len].to_vec();
+            let data = rest[12 + id_len..12 + id_len + data_len].to_vec();
+            pos += 12 + id_len + data_len;
+            self.left = Some(left - 1);
+            (self.f)(ReadResult::new(id, data))?;
+        }
+    }
+
+    /// fail if the records didn't end where the data ended
+    fn finish(mut self) -> crate::Result<()> {
+        match (self.left, self.buf.is_empty()) {
+            (Some(0), true) => Ok(()),
+            _ => {
+                self.buf.zeroize();
+                Err(corrupt("Incomplete records"))
+            }
+        }
+    }
+}
+
+impl<F: FnMut(ReadResult) -> crate::Result<()>> Write for Parser<'_, F> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        if self.left == Some(0) {
+            return Err(corrupt("Trailing data after the records").into());
+        }
+        if self.buf.capacity() - self.buf.len() < data.len() {
+            // grow into a new buffer, a reallocation would leave unwiped copies behind
+            let mut grown = Vec::with_capacity((2 * self.buf.capacity()).max(self.buf.len() + data.len()));
+            grown.extend_from_slice(&self.buf);
+            self.buf.zeroize();
+            self.buf = grown;
+        }
+        self.buf.extend_from_slice(data);
+
+        let parsed = self.parse()?;
+        let len = self.buf.len();
+        self.buf.copy_within(parsed.., 0);
+        self.buf[len - parsed..].zeroize();
+        self.buf.truncate(len - parsed);
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<F: FnMut(ReadResult) -> crate::Result<()>> Drop for Parser<'_, F> {
+    fn drop(&mut self) {
+        self.buf.zeroize();
+    }
+}
@@ -0,0 +1,193 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+use crate::vault::{parse_version, ReadResult, VersionInfo};
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    convert::TryInto,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use sha2::{Digest, Sha256};
+
+/// the prefix of the id of a tombstone
+const TOMBSTONE: &[u8] = b"vault tombstone";
+
+/// The metadata of an entry of a snapshot by which it's compared, read without opening it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntryInfo {
+    /// the id of the entry
+    pub id: Vec<u8>,
+    /// the SHA-256 digest of the data of the entry
+    pub digest: [u8; 32],
+    /// the length of the data of the entry
+    pub len: usize,
+    /// the version if the entry is a version of a versioned record
+    pub version: Option<VersionInfo>,
+    /// the time of the deletion if the entry is a tombstone, see `tombstone`
+    pub deleted_at: Option<SystemTime>,
+}
+
+impl EntryInfo {
+    fn new(entry: &ReadResult) -> Self {
+        Self {
+            id: entry.id().to_vec(),
+            digest: Sha256::digest(entry.data()).into(),
+            len: entry.data().len(),
+            version: parse_version(entry.id()).map(|(_, info)| info),
+            deleted_at: parse_tombstone(entry).map(|(_, at)| at),
+        }
+    }
+
+    /// the time of the version or the deletion, other entries have none
+    pub fn time(&self) -> Option<SystemTime> {
+        self.version.map(|info| info.timestamp).or(self.deleted_at)
+    }
+}
+
+/// The differences of two snapshots, see `diff`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SnapshotDiff {
+    /// the entries only the first snapshot has
+    pub only_in_a: Vec<EntryInfo>,
+    /// the entries only the second snapshot has
+    pub only_in_b: Vec<EntryInfo>,
+    /// the entries both snapshots have with different data, the entry of the first snapshot first.  Versions of a
+    /// record with the same number differ if their ids or their data differ.
+    pub different: Vec<(EntryInfo, EntryInfo)>,
+}
+
+impl SnapshotDiff {
+    /// whether the snapshots have the same entries
+    pub fn is_empty(&self) -> bool {
+        self.only_in_a.is_empty() && self.only_in_b.is_empty() && self.different.is_empty()
+    }
+}
+
+/// The snapshot an entry is kept from by `merge`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    /// the first snapshot
+    A,
+    /// the second snapshot
+    B,
+}
+
+/// How `merge` resolves an entry which differs between the snapshots.
+pub enum MergePolicy<'a> {
+    /// keep the entry with the later `EntryInfo::time`, the entry of the first snapshot if they have none or the same
+    PreferNewer,
+    /// keep the entry of the first snapshot
+    PreferA,
+    /// keep the entry of the second snapshot
+    PreferB,
+    /// ask the callback with the entry of the first and of the second snapshot
+    Resolve(&'a mut dyn FnMut(&EntryInfo, &EntryInfo) -> Side),
+}
+
+/// A tombstone of the entry `id` deleted at `deleted_at`, to the second.  Export the tombstones of the deleted
+/// entries with the snapshot, `merge` drops the entries with a tombstone on either side so a deletion isn't undone
+/// by the other snapshot.
+pub fn tombstone(id: &[u8], deleted_at: SystemTime) -> ReadResult {
+    let secs = deleted_at
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or(Duration::ZERO)
+        .as_secs();
+    ReadResult::new([TOMBSTONE, id].concat(), secs.to_be_bytes().to_vec())
+}
+
+/// the deleted entry and the time of the deletion of a tombstone, `None` for other entries
+fn parse_tombstone(entry: &ReadResult) -> Option<(&[u8], SystemTime)> {
+    let id = entry.id().strip_prefix(TOMBSTONE)?;
+    let secs = u64::from_be_bytes(entry.data().try_into().ok()?);
+    Some((id, UNIX_EPOCH + Duration::from_secs(secs)))
+}
+
+/// the key entries are matched by: versions by their record and number, so concurrent versions of the same number
+/// conflict, other entries by their id
+fn match_key(entry: &ReadResult) -> Vec<u8> {
+    match parse_version(entry.id()) {
+        Some((id, info)) => [id.as_ref(), &info.version.to_be_bytes()].concat(),
+        None => entry.id().to_vec(),
+    }
+}
+
+fn by_key(entries: &[ReadResult]) -> BTreeMap<Vec<u8>, &ReadResult> {
+    entries.iter().map(|entry| (match_key(entry), entry)).collect()
+}
+
+/// Compares the entries of the snapshots `a` and `b`, e.g. imported by `import`, by the digests of their ciphertext
+/// and the metadata of their ids.  Nothing is opened, the snapshots may be sealed with different keys.
+pub fn diff(a: &[ReadResult], b: &[ReadResult]) -> SnapshotDiff {
+    let (a, b) = (by_key(a), by_key(b));
+    let mut diff = SnapshotDiff::default();
+    for (key, entry) in &a {
+        match b.get(key) {
+            None => diff.only_in_a.push(EntryInfo::new(entry)),
+            Some(other) if other.id() != entry.id() || other.data() != entry.data() => {
+                diff.different.push((EntryInfo::new(entry), EntryInfo::new(other)))
+            }
+            Some(_) => {}
+        }
+    }
+    diff.only_in_b = b
+        .iter()
+        .filter(|(key, _)| !a.contains_key(*key))
+        .map(|(_, entry)| EntryInfo::new(entry))
+        .collect();
+    diff
+}
+
+/// Merges the entries of the snapshots `a` and `b` into the entries of a snapshot, which can be exported by `export`.
+/// The entries only one snapshot has are kept, those which differ are resolved by `policy`.  Entries with a tombstone
+/// in either snapshot are dropped, the tombstones are kept.  Nothing is opened.
+///
+/// The chains of the snapshots are merged entry by entry, so each chain should only be written on one side.
+pub fn merge(a: &[ReadResult], b: &[ReadResult], mut policy: MergePolicy) -> Vec<ReadResult> {
+    let deleted: HashSet<&[u8]> = a
+        .iter()
+        .chain(b)
+        .filter_map(|entry| parse_tombstone(entry).map(|(id, _)| id))
+        .collect();
+
+    let (a, b) = (by_key(a), by_key(b));
+    let mut merged: BTreeMap<&[u8], ReadResult> = BTreeMap::new();
+    for (key, entry) in &a {
+        let kept = match b.get(key) {
+            Some(other) if other.id() != entry.id() || other.data() != entry.data() => {
+                match resolve(&mut policy, &EntryInfo::new(entry), &EntryInfo::new(other)) {
+                    Side::A => *entry,
+                    Side::B => *other,
+                }
+            }
+            _ => *entry,
+        };
+        merged.insert(kept.id(), kept.clone());
+    }
+    for (_, entry) in b.iter().filter(|(key, _)| !a.contains_key(*key)) {
+        merged.insert(entry.id(), (*entry).clone());
+    }
+    merged
+        .into_iter()
+        .filter(|(id, _)| !deleted.contains(id))
+        .map(|(_, entry)| entry)
+        .collect()
+}
+
+fn resolve(policy: &mut MergePolicy, a: &EntryInfo, b: &EntryInfo) -> Side {
+    match policy {
+        MergePolicy::PreferNewer if b.time() > a.time() => Side::B,
+        MergePolicy::PreferNewer | MergePolicy::PreferA => Side::A,
+        MergePolicy::PreferB => Side::B,
+        MergePolicy::Resolve(f) => f(a, b),
+    }
+}
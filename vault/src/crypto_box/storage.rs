@@ -0,0 +1,104 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+#[cfg(feature = "guarded-memory")]
+use crate::crypto_box::guarded::GuardedBytes;
+
+use std::ops::{Deref, DerefMut};
+
+#[cfg(feature = "insecure-serde")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use zeroize::Zeroize;
+
+/// The memory holding the bytes of a key.
+pub(crate) enum KeyBytes {
+    /// bytes stored on the heap
+    Heap(Vec<u8>),
+    /// bytes stored in locked memory pages
+    #[cfg(feature = "guarded-memory")]
+    Guarded(GuardedBytes),
+}
+
+impl KeyBytes {
+    /// checks if the bytes are stored in locked memory
+    pub fn is_guarded(&self) -> bool {
+        match self {
+            KeyBytes::Heap(_) => false,
+            #[cfg(feature = "guarded-memory")]
+            KeyBytes::Guarded(_) => true,
+        }
+    }
+
+    /// copies the bytes into a new allocation of the same kind.  Fails if guarded bytes can't be copied into new
+    /// locked memory.
+    pub fn try_clone(&self) -> crate::Result<Self> {
+        match self {
+            KeyBytes::Heap(bytes) => Ok(KeyBytes::Heap(bytes.clone())),
+            #[cfg(feature = "guarded-memory")]
+            KeyBytes::Guarded(bytes) => bytes.try_clone().map(KeyBytes::Guarded),
+        }
+    }
+}
+
+impl Deref for KeyBytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            KeyBytes::Heap(bytes) => bytes,
+            #[cfg(feature = "guarded-memory")]
+            KeyBytes::Guarded(bytes) => bytes,
+        }
+    }
+}
+
+impl DerefMut for KeyBytes {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        match self {
+            KeyBytes::Heap(bytes) => bytes,
+            #[cfg(feature = "guarded-memory")]
+            KeyBytes::Guarded(bytes) => bytes,
+        }
+    }
+}
+
+/// clones guarded bytes into new locked memory.  Panics if the memory can't be locked instead of falling back to the
+/// heap, use `try_clone` to handle the error.
+impl Clone for KeyBytes {
+    fn clone(&self) -> Self {
+        self.try_clone()
+            .expect("Unable to allocate guarded memory for the cloned key")
+    }
+}
+
+impl Zeroize for KeyBytes {
+    fn zeroize(&mut self) {
+        match self {
+            KeyBytes::Heap(bytes) => bytes.zeroize(),
+            #[cfg(feature = "guarded-memory")]
+            KeyBytes::Guarded(bytes) => bytes.zeroize(),
+        }
+    }
+}
+
+#[cfg(feature = "insecure-serde")]
+impl Serialize for KeyBytes {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.deref().serialize(serializer)
+    }
+}
+
+#[cfg(feature = "insecure-serde")]
+impl<'de> Deserialize<'de> for KeyBytes {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Vec::deserialize(deserializer).map(KeyBytes::Heap)
+    }
+}
@@ -0,0 +1,137 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+use crate::crypto_box::{
+    open_box,
+    progress::{check_cancelled, Progress},
+    seal_box, try_alloc, BoxProvider, Key,
+};
+
+use std::io::{ErrorKind, Read, Write};
+
+use zeroize::Zeroize;
+
+/// flag of a frame which is followed by more frames
+const MORE: u8 = 0;
+/// flag of the last frame of a stream
+const LAST: u8 = 1;
+/// size of the frame header: the flag and the big endian length of the sealed chunk
+const HEADER_LEN: usize = 5;
+
+/// the AD of a chunk: the stream AD followed by the chunk counter and the frame flag
+fn chunk_ad(ad: &[u8], counter: u64, flag: u8) -> Vec<u8> {
+    [ad, &counter.to_be_bytes(), &[flag]].concat()
+}
+
+/// read from `reader` until `buf` is full or the reader is exhausted.  Returns the number of bytes read.
+fn read_full(reader: &mut dyn Read, buf: &mut [u8]) -> crate::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(filled)
+}
+
+pub(crate) fn seal<T: BoxProvider>(
+    key: &Key<T>,
+    ad: &[u8],
+    reader: &mut dyn Read,
+    writer: &mut dyn Write,
+    chunk_size: usize,
+    progress: &dyn Progress,
+) -> crate::Result<()> {
+    if chunk_size == 0 || chunk_size + T::box_overhead() > u32::MAX as usize {
+        return Err(crate::Error::InterfaceError);
+    }
+
+    let mut chunk = try_alloc(chunk_size)?;
+    let result = (|| {
+        let mut counter = 0u64;
+        let mut done = 0u64;
+        loop {
+            check_cancelled(progress)?;
+            let len = read_full(reader, &mut chunk)?;
+            // a full chunk may be followed by more data, an empty last frame ends the stream in that case.
+            let flag = if len < chunk_size { LAST } else { MORE };
+
+            let sealed = seal_box(key, &chunk_ad(ad, counter, flag), &chunk[..len])?;
+            writer.write_all(&[flag])?;
+            writer.write_all(&(sealed.len() as u32).to_be_bytes())?;
+            writer.write_all(&sealed)?;
+            done += len as u64;
+            progress.report(done, None);
+
+            if flag == LAST {
+                return Ok(writer.flush()?);
+            }
+            counter = counter
+                .checked_add(1)
+                .ok_or_else(|| crate::Error::crypto("seal stream", "Too many chunks"))?;
+        }
+    })();
+    chunk.zeroize();
+    result
+}
+
+pub(crate) fn open<T: BoxProvider>(
+    key: &Key<T>,
+    ad: &[u8],
+    reader: &mut dyn Read,
+    writer: &mut dyn Write,
+    chunk_size: usize,
+    progress: &dyn Progress,
+) -> crate::Result<()> {
+    if chunk_size == 0 || chunk_size + T::box_overhead() > u32::MAX as usize {
+        return Err(crate::Error::InterfaceError);
+    }
+
+    let mut sealed = try_alloc(chunk_size + T::box_overhead())?;
+    let mut counter = 0u64;
+    let mut done = 0u64;
+    loop {
+        check_cancelled(progress)?;
+        let mut header = [0; HEADER_LEN];
+        if read_full(reader, &mut header)? < HEADER_LEN {
+            return Err(crate::Error::crypto("open stream", "Truncated stream"));
+        }
+
+        let flag = header[0];
+        let len = u32::from_be_bytes([header[1], header[2], header[3], header[4]]) as usize;
+        if (flag != MORE && flag != LAST) || len > sealed.len() {
+            return Err(crate::Error::crypto("open stream", "Invalid stream frame"));
+        }
+        if read_full(reader, &mut sealed[..len])? < len {
+            return Err(crate::Error::crypto("open stream", "Truncated stream"));
+        }
+
+        let mut plain = open_box(key, &chunk_ad(ad, counter, flag), &sealed[..len])?;
+        let written = writer.write_all(&plain);
+        done += plain.len() as u64;
+        plain.zeroize();
+        written?;
+        progress.report(done, None);
+
+        if flag == LAST {
+            if read_full(reader, &mut [0])? != 0 {
+                return Err(crate::Error::crypto("open stream", "Trailing data after the stream"));
+            }
+            return Ok(writer.flush()?);
+        }
+        counter = counter
+            .checked_add(1)
+            .ok_or_else(|| crate::Error::crypto("open stream", "Too many chunks"))?;
+    }
+}
@@ -0,0 +1,52 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+use serde::{Deserialize, Serialize};
+
+/// An authentication tag stored apart from its ciphertext, see `BoxProvider::box_seal_detached`.  The length of a
+/// tag is given by `BoxProvider::tag_len`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Tag(#[cfg_attr(feature = "serde-base64", serde(with = "crate::serde_base64"))] Vec<u8>);
+
+impl Tag {
+    /// get the bytes of the tag
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// get the length of the tag
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// checks if the tag is empty
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl From<Vec<u8>> for Tag {
+    fn from(bytes: Vec<u8>) -> Self {
+        Self(bytes)
+    }
+}
+
+impl From<&[u8]> for Tag {
+    fn from(bytes: &[u8]) -> Self {
+        Self(bytes.to_vec())
+    }
+}
+
+impl AsRef<[u8]> for Tag {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
@@ -0,0 +1,57 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+use crate::crypto_box::{open_box, seal_box, BoxProvider, Key};
+
+/// magic bytes starting the header of a tagged box
+const MAGIC: [u8; 2] = *b"vb";
+/// version of the header format
+const VERSION: u8 = 1;
+/// length of the magic bytes, the box id and the version
+const HEADER_LEN: usize = 7;
+
+/// the header of the boxes sealed by the provider `T`
+fn header<T: BoxProvider>() -> [u8; HEADER_LEN] {
+    let mut header = [0; HEADER_LEN];
+    header[..2].copy_from_slice(&MAGIC);
+    header[2..6].copy_from_slice(&T::box_id());
+    header[6] = VERSION;
+    header
+}
+
+/// the printable form of a box id
+fn display_id(id: &[u8]) -> String {
+    id.escape_ascii().to_string()
+}
+
+/// seal `data` and prepend the header.  The header is part of the AD so it can't be swapped.
+pub(crate) fn seal<T: BoxProvider>(key: &Key<T>, ad: &[u8], data: &[u8]) -> crate::Result<Vec<u8>> {
+    let header = header::<T>();
+    let mut sealed = seal_box(key, &[&header[..], ad].concat(), data)?;
+    sealed.splice(0..0, header.iter().copied());
+    Ok(sealed)
+}
+
+/// check the header of `data` and open the box behind it
+pub(crate) fn open<T: BoxProvider>(key: &Key<T>, ad: &[u8], data: &[u8]) -> crate::Result<Vec<u8>> {
+    if data.len() < HEADER_LEN || data[..2] != MAGIC || data[6] != VERSION {
+        return Err(crate::Error::crypto("parse box header", "Invalid box header"));
+    }
+
+    let (header, sealed) = data.split_at(HEADER_LEN);
+    if header[2..6] != T::box_id() {
+        return Err(crate::Error::ProviderMismatch {
+            expected: display_id(&T::box_id()),
+            found: display_id(&header[2..6]),
+        });
+    }
+    open_box(key, &[header, ad].concat(), sealed)
+}
@@ -0,0 +1,64 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+use crate::crypto_box::{BoxProvider, Key};
+
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
+
+/// Limits how often a key may be used for sealing.
+#[derive(Debug)]
+pub(crate) struct UsageLimit {
+    /// maximum number of uses
+    max: u64,
+    /// number of uses so far
+    used: AtomicU64,
+}
+
+impl<T: BoxProvider> Key<T> {
+    /// limit the key to `max` seals.  Once the limit is reached `Encrypt::encrypt` fails with
+    /// `Error::KeyExhausted`, opening is never limited.  Clones share the counter of the key they were cloned from,
+    /// calling `with_max_uses` again starts a new counter.  The counter is not serialized.
+    pub fn with_max_uses(mut self, max: u64) -> Self {
+        self.usage = Some(Arc::new(UsageLimit {
+            max,
+            used: AtomicU64::new(0),
+        }));
+        self
+    }
+
+    /// get the number of seals left before the key is exhausted, or `None` if the key isn't limited
+    pub fn remaining_uses(&self) -> Option<u64> {
+        self.usage
+            .as_ref()
+            .map(|usage| usage.max.saturating_sub(usage.used.load(Ordering::SeqCst)))
+    }
+
+    /// count a use of the key.  Fails with `Error::KeyExhausted` if the key reached its limit.
+    pub fn checkout_use(&self) -> crate::Result<()> {
+        match &self.usage {
+            Some(usage) => usage
+                .used
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |used| {
+                    if used < usage.max {
+                        Some(used + 1)
+                    } else {
+                        None
+                    }
+                })
+                .map(|_| ())
+                .map_err(|_| crate::Error::KeyExhausted(usage.max)),
+            None => Ok(()),
+        }
+    }
+}
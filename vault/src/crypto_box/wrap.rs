@@ -0,0 +1,66 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+use crate::crypto_box::{open_box, seal_box, BoxProvider, Key};
+
+use std::marker::PhantomData;
+
+use serde::{Deserialize, Serialize};
+
+/// A key sealed under a key encryption key.  Unlike `Key`, this type can be serialized since it only contains the
+/// ciphertext.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct WrappedKey<T: BoxProvider> {
+    /// the sealed key bytes
+    #[cfg_attr(feature = "serde-base64", serde(with = "crate::serde_base64"))]
+    sealed: Vec<u8>,
+    /// associated Provider data
+    #[serde(skip)]
+    _box_provider: PhantomData<T>,
+}
+
+impl<T: BoxProvider> WrappedKey<T> {
+    /// associated data used to seal wrapped keys
+    const AD: &'static [u8] = b"key-wrap";
+
+    /// unwrap the key with the key encryption key `kek`.
+    pub fn unwrap(&self, kek: &Key<T>) -> crate::Result<Key<T>> {
+        Key::unwrap_with(&self.sealed, kek, Self::AD)
+    }
+
+    /// get the sealed key bytes
+    pub fn sealed(&self) -> &[u8] {
+        &self.sealed
+    }
+}
+
+impl<T: BoxProvider> Key<T> {
+    /// wrap the key with the key encryption key `kek`.
+    pub fn to_wrapped(&self, kek: &Key<T>) -> crate::Result<WrappedKey<T>> {
+        Ok(WrappedKey {
+            sealed: self.wrap_with(kek, WrappedKey::<T>::AD)?,
+            _box_provider: PhantomData,
+        })
+    }
+
+    /// seal the key bytes under the key encryption key `kek` of a possibly different provider.  The `ad` binds the
+    /// wrapped key to its context, like a record or vault id.
+    pub fn wrap_with<W: BoxProvider>(&self, kek: &Key<W>, ad: &[u8]) -> crate::Result<Vec<u8>> {
+        seal_box(kek, ad, &self.key)
+    }
+
+    /// open a key sealed with `wrap_with` using the same `kek` and `ad`.
+    pub fn unwrap_with<W: BoxProvider>(bytes: &[u8], kek: &Key<W>, ad: &[u8]) -> crate::Result<Self> {
+        // `load` wipes the plaintext if it doesn't have the expected length
+        Self::load(open_box(kek, ad, bytes)?)
+    }
+}
@@ -0,0 +1,31 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+use std::hint::black_box;
+
+/// compares two byte slices in constant time.  Slices of different lengths are unequal, only the lengths of the
+/// slices are leaked: the bytes of the shorter length are compared either way.
+pub fn ct_eq(a: &[u8], b: &[u8]) -> bool {
+    let diff = a
+        .iter()
+        .zip(b.iter())
+        .fold(black_box((a.len() != b.len()) as u8), |acc, (a, b)| {
+            black_box(acc | (a ^ b))
+        });
+    black_box(diff) == 0
+}
+
+/// selects `b` if `choice` is set and `a` otherwise without branching on `choice`
+pub fn ct_select(a: u8, b: u8, choice: bool) -> u8 {
+    // all ones if `choice` is set, all zeros otherwise
+    let mask = black_box(0u8.wrapping_sub(choice as u8));
+    a ^ (mask & (a ^ b))
+}
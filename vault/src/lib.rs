@@ -40,35 +40,321 @@ use thiserror::Error as DeriveError;
 
 mod base64;
 mod crypto_box;
+/// constant time comparisons of secret data, see `ct::ct_eq`.
+pub mod ct;
+/// operational metrics like the number of seals reported to a `metrics::Metrics`, see `metrics::set_metrics`.
+pub mod metrics;
+/// drop hooks which persist a key when it is dropped, see `Key::on_drop`.
+pub mod persist_hooks;
+/// ready to use `BoxProvider` and `SignProvider` implementations, each behind its own `provider-*` feature, and wrappers around them.
+pub mod providers;
+/// serde helpers writing bytes as base64 strings in human readable formats, see `serde_base64::serialize`.
+#[cfg(feature = "serde-base64")]
+pub mod serde_base64;
+#[cfg(feature = "test-utils")]
+pub mod test_utils;
 mod types;
 mod vault;
 
 pub use crate::{
     base64::{Base64Decodable, Base64Encodable},
-    crypto_box::{BoxProvider, Decrypt, Encrypt, Key},
+    crypto_box::{
+        armor, decrypt_many, decrypt_many_collect_errors, encrypt_many, encrypt_many_collect_errors, open_chunked,
+        open_chunked_with_progress, open_with_any, reencrypt, reencrypt_across, rekey_all, rekey_all_with_progress,
+        seal_chunked, seal_chunked_with_progress, AssociatedData, Authenticate, BoxProvider, BoxProviderInstance,
+        BufferPool, ChunkedCiphertext, Decrypt, Encrypt, Envelope, GuardedOpen, Key, KeyEncoding, KeyFingerprint,
+        KeyMeta, KeyShare, LockoutPolicy, LockoutState, NoProgress, PaddingScheme, PooledCiphertext, Progress,
+        RekeyOptions, RekeyReport, RevealedKey, SealedBlob, SelfTestCheck, SelfTestReport, SharedKey, Sign,
+        SignProvider, SigningKey, Tag, Verify, VerifySig, WrappedKey, MAX_KEY_SOURCE_LEN,
+    },
     types::utils::{Id, RecordHint},
-    vault::{DBReader, DBView, DBWriter, DeleteRequest, ListResult, ReadRequest, ReadResult, Record, WriteRequest},
+    vault::{
+        migrate_records, AccessContext, AllOf, AllowAll, AnyOf, AutoCompact, BlindIndex, Claims, Clock, CommitStep,
+        CompactReport, CompactStep, ConcurrentVault, DBReader, DBView, DBWriter, Decision, DeleteRequest, Durability,
+        ExpiryPolicy, FileStore, GcReport, IntegrityTree, JournaledVault, ListResult, LockMode, MigrationReport,
+        Operation, Policy, ReadOnlyVault, ReadRequest, ReadResult, Record, RecordMeta, Recovery, RetentionPolicy,
+        Store, SystemClock, VaultAccess, VaultNamespace, VaultTransaction, VersionInfo, WalRecovery, WalStep, WalStore,
+        WriteRequest,
+    },
 };
 
-/// Errors for the Vault Crate
+#[cfg(feature = "keychain")]
+pub use crate::crypto_box::keychain;
+#[cfg(feature = "key-exchange")]
+pub use crate::crypto_box::kx;
+#[cfg(feature = "asymmetric")]
+pub use crate::crypto_box::sealed_box;
+#[cfg(feature = "password-kdf")]
+pub use crate::crypto_box::snapshot;
+#[cfg(feature = "password-kdf")]
+pub use crate::crypto_box::KdfParams;
+#[cfg(feature = "mnemonic")]
+pub use crate::crypto_box::Mnemonic;
+#[cfg(feature = "compress")]
+pub use crate::crypto_box::MAX_DECOMPRESSED_LEN;
+#[cfg(feature = "audit")]
+pub use crate::crypto_box::{audit_failures, clear_audit_sink, set_audit_sink, AuditEvent, AuditOperation, AuditSink};
+#[cfg(feature = "age-export")]
+pub use crate::crypto_box::{
+    export_age, export_age_with_work_factor, import_age, AGE_DEFAULT_WORK_FACTOR, AGE_MAX_WORK_FACTOR,
+};
+#[cfg(feature = "cose")]
+pub use crate::crypto_box::{from_cose_encrypt0, to_cose_encrypt0};
+#[cfg(feature = "mmap")]
+pub use crate::crypto_box::{open_file, open_file_with_progress, seal_file, seal_file_with_progress, FileSealReport};
+#[cfg(feature = "serde-seal")]
+pub use crate::crypto_box::{open_serde, open_serde_with_limit, seal_serde, seal_serde_with_limit, MAX_SERDE_LEN};
+#[cfg(feature = "async")]
+pub use crate::crypto_box::{AsyncBoxProvider, DecryptAsync, EncryptAsync};
+#[cfg(feature = "password-kdf")]
+pub use crate::vault::LockableVault;
+
+/// Errors for the Vault Crate.  Causes like the errors of the crypto backends are chained as `source`.
 #[derive(DeriveError, Debug)]
+#[non_exhaustive]
 pub enum Error {
+    #[deprecated(note = "match the structured `RecordNotFound`, `InvalidEntry`, `VersionMismatch` or `CorruptRecord`")]
     #[error("Database Error: `{0}`")]
     DatabaseError(String),
     #[error("Version Error: `{0}`")]
     VersionError(String),
     #[error("Chain error: `{0}`")]
     ChainError(String),
-    #[error("Base64Error")]
+    #[error("Base64 Error: invalid length, padding or character")]
     Base64Error,
-    #[error("Base64Error: `{0}`")]
+    #[error("Base64 Error: `{0}`")]
     Base64ErrorDetailed(String),
     #[error("Interface Error")]
     InterfaceError,
-    #[error("Other Error")]
+    #[error("Interface Error: `{0}`")]
+    InterfaceErrorDetailed(String),
+    #[error("Other Error: `{0}`")]
     OtherError(String),
-    #[error("Crypto Error: `{0}`")]
-    CryptoError(String),
+    #[error("Crypto Error: `{op}` failed: `{source}`")]
+    CryptoError {
+        op: &'static str,
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+    #[error("Not enough shares: `{provided}` of `{required}`")]
+    NotEnoughShares { required: u8, provided: usize },
+    #[error("Corrupt share: `{0}`")]
+    CorruptShare(u8),
+    #[error("Memory Error: `{0}`")]
+    MemoryError(String),
+    #[error("Invalid key length: expected `{expected}` bytes, got `{actual}`")]
+    InvalidKeyLength { expected: usize, actual: usize },
+    #[error("Invalid mnemonic word at index `{0}`")]
+    InvalidMnemonicWord(usize),
+    #[error("Mnemonic Error: `{0}`")]
+    MnemonicError(String),
+    #[error("Armor Error: `{0}`")]
+    ArmorError(String),
+    #[error("Armor checksum mismatch")]
+    ArmorChecksumMismatch,
+    #[error("Key Mismatch: data was sealed with key `{id}` version `{version}`")]
+    KeyMismatch { id: String, version: u32 },
+    #[error("Key exhausted after `{0}` uses")]
+    KeyExhausted(u64),
+    #[error("Authentication Failed")]
+    AuthenticationFailed,
+    #[error("No matching key: none of the `{attempts}` keys opened the data")]
+    NoMatchingKey { attempts: usize },
+    #[error("Invalid nonce length: expected `{expected}` bytes, got `{actual}`")]
+    InvalidNonceLength { expected: usize, actual: usize },
+    #[error("Self test failed: `{0}`")]
+    SelfTestFailed(String),
+    #[error("Serialize Error: `{0}`")]
+    SerializeError(String),
+    #[error("Deserialize Error: `{0}`")]
+    DeserializeError(String),
+    #[error("Payload too large: `{len}` bytes, at most `{limit}` are allowed")]
+    PayloadTooLarge { len: u64, limit: usize },
+    #[error("Unsupported Version: `{0}`")]
+    UnsupportedVersion(u8),
+    #[error("Conversion Error: unable to convert the plaintext into `{type_name}`: `{message}`")]
+    ConversionError { type_name: &'static str, message: String },
+    #[error("Compression Error: `{0}`")]
+    CompressionError(String),
+    #[error("Decompressed data exceeds the limit of `{0}` bytes")]
+    DecompressionLimit(usize),
+    #[error("Provider Error: `{0}`")]
+    ProviderError(#[source] Box<dyn std::error::Error + Send + Sync>),
+    #[error("Provider Mismatch: expected provider `{expected}`, data was sealed by `{found}`")]
+    ProviderMismatch { expected: String, found: String },
+    #[error("COSE Error: `{0}`")]
+    CoseError(String),
+    #[error("Age Error: `{0}`")]
+    AgeError(String),
+    #[error("Unsupported key type: `{0}`, expected `oct`")]
+    UnsupportedKeyType(String),
+    #[error("JWK Error: `{0}`")]
+    JwkError(String),
+    #[error("PKCS#8 Error: `{0}`")]
+    Pkcs8Error(String),
+    #[error("Armor label mismatch: expected `{expected}`, found `{found}`")]
+    ArmorLabelMismatch { expected: String, found: String },
+    #[error("No key in the keychain for account `{account}` of `{service}`")]
+    KeychainNotFound { service: String, account: String },
+    #[error("Keychain Error: `{0}`")]
+    KeychainError(String),
+    #[error("Unable to delete the key from the keychain: `{0}`")]
+    KeychainDeleteFailed(String),
+    #[error("Key Source Error: `{0}`")]
+    KeySourceError(String),
+    #[error("Malformed ciphertext: expected at least `{expected_min}` bytes, got `{got}`")]
+    MalformedCiphertext { expected_min: usize, got: usize },
+    #[error("Record not found: no valid record `{id:?}`")]
+    RecordNotFound { id: Id },
+    #[error("Invalid Entry: `{reason}`")]
+    InvalidEntry { reason: String },
+    #[error("Version Mismatch: expected a counter of at least `{expected}`, found `{found}`")]
+    VersionMismatch { expected: u64, found: u64 },
+    #[error("Corrupt Record: the record at offset `{offset}` of the list opens but isn't a transaction")]
+    CorruptRecord { offset: usize },
+    #[error("IO Error: `{0}`")]
+    Io(#[source] std::io::Error),
+    #[error("Too many failed attempts: retry after `{retry_after:?}`")]
+    TooManyAttempts { retry_after: std::time::Duration },
+    #[error("Source modified: `{path:?}` changed while it was read")]
+    SourceModified { path: std::path::PathBuf },
+    #[error("Operation cancelled")]
+    Cancelled,
+    #[error("Wrong password: the key derived from the password doesn't match")]
+    WrongPassword,
+    #[error("Corrupt snapshot: `{0}`")]
+    CorruptSnapshot(String),
+    #[error("Rekey Error: `{0}`")]
+    RekeyError(String),
+    #[error("Record expired at `{expired_at:?}`")]
+    RecordExpired { expired_at: std::time::SystemTime },
+    #[error("Version not found: record `{id:?}` has no such version")]
+    VersionNotFound { id: Id },
+    #[error("Nested transactions aren't supported")]
+    NestedTransaction,
+    #[error("Integrity check failed: `{count}` entries differ", count = .ids.len())]
+    IntegrityMismatch { ids: Vec<Vec<u8>> },
+    #[error("Metadata too large: `{len}` bytes, at most `{limit}` are allowed")]
+    MetadataTooLarge { len: usize, limit: usize },
+    #[error("Corrupt store: `{0}`")]
+    CorruptStore(String),
+    #[error("Vault locked{}", locked_by(.holder_pid))]
+    VaultLocked { holder_pid: Option<u32> },
+    #[error("Access denied: `{reason}`")]
+    AccessDenied { reason: String },
+    #[error("Signature Mismatch: the signature doesn't match the data and the public key")]
+    SignatureMismatch,
+    #[error("Malformed signature: `{0}`")]
+    MalformedSignature(String),
+}
+
+/// errors are equal if they are the same variant and render the same, causes which are chained as `source` are
+/// compared by their message.
+impl PartialEq for Error {
+    fn eq(&self, other: &Self) -> bool {
+        std::mem::discriminant(self) == std::mem::discriminant(other) && self.to_string() == other.to_string()
+    }
+}
+
+impl Error {
+    /// an `Error::CryptoError` of the operation `op` caused by `source`, the error of a backend or a message
+    pub fn crypto(op: &'static str, source: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> Self {
+        Error::CryptoError {
+            op,
+            source: source.into(),
+        }
+    }
+
+    /// the `std::io::ErrorKind` the error is converted to, see `From<Error> for std::io::Error`
+    pub fn io_kind(&self) -> std::io::ErrorKind {
+        use std::io::ErrorKind;
+
+        match self {
+            Error::Io(e) => e.kind(),
+            Error::RecordNotFound { .. } | Error::KeychainNotFound { .. } | Error::VersionNotFound { .. } => {
+                ErrorKind::NotFound
+            }
+            Error::InterfaceError
+            | Error::InterfaceErrorDetailed(_)
+            | Error::InvalidKeyLength { .. }
+            | Error::InvalidNonceLength { .. }
+            | Error::InvalidEntry { .. }
+            | Error::NotEnoughShares { .. }
+            | Error::PayloadTooLarge { .. }
+            | Error::MetadataTooLarge { .. }
+            | Error::RekeyError(_)
+            | Error::NestedTransaction => ErrorKind::InvalidInput,
+            Error::AuthenticationFailed
+            | Error::MalformedCiphertext { .. }
+            | Error::CorruptRecord { .. }
+            | Error::ChainError(_)
+            | Error::VersionMismatch { .. }
+            | Error::Base64Error
+            | Error::Base64ErrorDetailed(_)
+            | Error::ArmorError(_)
+            | Error::ArmorChecksumMismatch
+            | Error::ArmorLabelMismatch { .. }
+            | Error::KeyMismatch { .. }
+            | Error::NoMatchingKey { .. }
+            | Error::ProviderMismatch { .. }
+            | Error::UnsupportedVersion(_)
+            | Error::DeserializeError(_)
+            | Error::ConversionError { .. }
+            | Error::CompressionError(_)
+            | Error::DecompressionLimit(_)
+            | Error::CoseError(_)
+            | Error::AgeError(_)
+            | Error::UnsupportedKeyType(_)
+            | Error::JwkError(_)
+            | Error::Pkcs8Error(_)
+            | Error::CorruptShare(_)
+            | Error::InvalidMnemonicWord(_)
+            | Error::MnemonicError(_)
+            | Error::CorruptSnapshot(_)
+            | Error::CorruptStore(_)
+            | Error::IntegrityMismatch { .. }
+            | Error::SignatureMismatch
+            | Error::MalformedSignature(_) => ErrorKind::InvalidData,
+            Error::MemoryError(_) => ErrorKind::OutOfMemory,
+            Error::TooManyAttempts { .. }
+            | Error::WrongPassword
+            | Error::RecordExpired { .. }
+            | Error::VaultLocked { .. }
+            | Error::AccessDenied { .. } => ErrorKind::PermissionDenied,
+            _ => ErrorKind::Other,
+        }
+    }
+}
+
+/// the process holding a lock, if it is known
+fn locked_by(holder_pid: &Option<u32>) -> String {
+    match holder_pid {
+        Some(pid) => format!(" by process `{}`", pid),
+        None => String::new(),
+    }
+}
+
+/// an `std::io::Error` which wraps an `Error` is unwrapped again, any other one becomes an `Error::Io`.
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        match e.get_ref().map(|inner| inner.is::<Error>()) {
+            Some(true) => *e
+                .into_inner()
+                .and_then(|inner| inner.downcast().ok())
+                .expect("the `Error` inside"),
+            _ => Error::Io(e),
+        }
+    }
+}
+
+/// an `Error::Io` is unwrapped again, any other error is wrapped into an `std::io::Error` of its `Error::io_kind`, so
+/// `std::io::Error::get_ref` can downcast it back.
+impl From<Error> for std::io::Error {
+    fn from(error: Error) -> Self {
+        match error {
+            Error::Io(e) => e,
+            error => std::io::Error::new(error.io_kind(), error),
+        }
+    }
 }
 
 // Crate result type
@@ -0,0 +1,158 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+//! Operational metrics of the vault, reported to the `Metrics` set with `set_metrics`.
+//!
+//! The names of the metrics are the constants of this module, in the style of Prometheus.  The metrics carry counts,
+//! byte lengths and durations only, never plaintext, key material or ids.
+
+use std::{
+    collections::BTreeMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, RwLock,
+    },
+    time::Duration,
+};
+
+/// boxes sealed, through `Encrypt` and the other APIs of the vault
+pub const SEALS: &str = "vault_seals_total";
+/// bytes of plaintext sealed
+pub const SEALED_BYTES: &str = "vault_sealed_bytes_total";
+/// boxes opened
+pub const OPENS: &str = "vault_opens_total";
+/// boxes which failed to open with `Error::AuthenticationFailed`
+pub const AUTH_FAILURES: &str = "vault_auth_failures_total";
+/// runs of `rekey_all` and `DBView::rekey` which finished
+pub const REKEYS: &str = "vault_rekeys_total";
+/// records re-keyed
+pub const REKEYED_RECORDS: &str = "vault_rekeyed_records_total";
+/// records which failed to re-key
+pub const REKEY_FAILURES: &str = "vault_rekey_failures_total";
+/// the duration of a re-key run
+pub const REKEY_DURATION: &str = "vault_rekey_duration";
+/// runs of `DBView::gc`
+pub const GC_RUNS: &str = "vault_gc_runs_total";
+/// blobs collected by `DBView::gc`
+pub const GC_REMOVED_BLOBS: &str = "vault_gc_removed_blobs_total";
+/// bytes collected by `DBView::gc`
+pub const GC_RECLAIMED_BYTES: &str = "vault_gc_reclaimed_bytes_total";
+/// the duration of a run of `DBView::gc`
+pub const GC_DURATION: &str = "vault_gc_duration";
+/// snapshots exported
+pub const SNAPSHOT_EXPORTS: &str = "vault_snapshot_exports_total";
+/// snapshots imported
+pub const SNAPSHOT_IMPORTS: &str = "vault_snapshot_imports_total";
+/// the duration of a snapshot export
+pub const SNAPSHOT_EXPORT_DURATION: &str = "vault_snapshot_export_duration";
+/// the duration of a snapshot import
+pub const SNAPSHOT_IMPORT_DURATION: &str = "vault_snapshot_import_duration";
+
+/// the metrics set with `set_metrics`
+static METRICS: RwLock<Option<Arc<dyn Metrics>>> = RwLock::new(None);
+/// whether there are metrics, so operations don't take the lock without them
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// A receiver of the metrics of the vault, e.g. an adapter to a metrics library.  Called on the thread of the
+/// operation right after it finished.  Both methods do nothing by default.
+pub trait Metrics: Send + Sync {
+    /// add `by` to the counter `name`
+    fn incr_counter(&self, _name: &'static str, _by: u64) {}
+
+    /// record the duration `duration` of `name`
+    fn observe_duration(&self, _name: &'static str, _duration: Duration) {}
+}
+
+/// `Metrics` which drop everything, the metrics of the vault until `set_metrics`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NoMetrics;
+
+impl Metrics for NoMetrics {}
+
+/// `Metrics` kept in memory, for tests and debugging.  Clones share the metrics.
+#[derive(Debug, Clone, Default)]
+pub struct InMemoryMetrics(Arc<Mutex<Recorded>>);
+
+#[derive(Debug, Default)]
+struct Recorded {
+    counters: BTreeMap<&'static str, u64>,
+    durations: BTreeMap<&'static str, Vec<Duration>>,
+}
+
+impl Metrics for InMemoryMetrics {
+    fn incr_counter(&self, name: &'static str, by: u64) {
+        *self.recorded().counters.entry(name).or_default() += by;
+    }
+
+    fn observe_duration(&self, name: &'static str, duration: Duration) {
+        self.recorded().durations.entry(name).or_default().push(duration);
+    }
+}
+
+impl InMemoryMetrics {
+    /// the value of the counter `name`, 0 if it was never incremented
+    pub fn counter(&self, name: &str) -> u64 {
+        self.recorded().counters.get(name).copied().unwrap_or(0)
+    }
+
+    /// the counters which were incremented, by name
+    pub fn counters(&self) -> BTreeMap<&'static str, u64> {
+        self.recorded().counters.clone()
+    }
+
+    /// the durations of `name` in the order they were observed
+    pub fn durations(&self, name: &str) -> Vec<Duration> {
+        self.recorded().durations.get(name).cloned().unwrap_or_default()
+    }
+
+    /// forget all metrics
+    pub fn clear(&self) {
+        *self.recorded() = Recorded::default();
+    }
+
+    fn recorded(&self) -> std::sync::MutexGuard<'_, Recorded> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// report the metrics of all operations of the vault to `metrics`.  Replaces the previous metrics.
+pub fn set_metrics<M: Metrics + 'static>(metrics: M) {
+    *METRICS.write().unwrap_or_else(|e| e.into_inner()) = Some(Arc::new(metrics));
+    ENABLED.store(true, Ordering::Release);
+}
+
+/// stop reporting metrics and drop the metrics, the vault reports to `NoMetrics` again
+pub fn clear_metrics() {
+    ENABLED.store(false, Ordering::Release);
+    *METRICS.write().unwrap_or_else(|e| e.into_inner()) = None;
+}
+
+/// pass the metrics to `f`, if there are any
+fn with(f: impl FnOnce(&dyn Metrics)) {
+    if !ENABLED.load(Ordering::Acquire) {
+        return;
+    }
+    // the lock isn't held while reporting, so metrics can use the vault
+    let metrics = METRICS.read().unwrap_or_else(|e| e.into_inner()).clone();
+    if let Some(metrics) = metrics {
+        f(metrics.as_ref());
+    }
+}
+
+/// add `by` to the counter `name`
+pub(crate) fn incr(name: &'static str, by: u64) {
+    with(|metrics| metrics.incr_counter(name, by))
+}
+
+/// record the duration `duration` of `name`
+pub(crate) fn observe(name: &'static str, duration: Duration) {
+    with(|metrics| metrics.observe_duration(name, duration))
+}
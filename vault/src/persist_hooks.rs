@@ -0,0 +1,170 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+use crate::crypto_box::{open_box, seal_box, BoxProvider, Key};
+
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+/// AD used to seal persisted keys
+const AD: &[u8] = b"persisted-key";
+
+/// create a drop hook which seals the key bytes under `kek` and writes them atomically to `path` before the key is
+/// wiped.  The bytes are left as they are for later hooks, the key wipes them after the last one.  A drop hook can't
+/// report errors, so a failed write is lost; call `persist` directly to handle errors.
+pub fn encrypted_file<P>(path: PathBuf, kek: Key<P>) -> impl Fn(&mut [u8]) + Send + Sync + 'static
+where
+    P: BoxProvider + Send + Sync + 'static,
+{
+    move |bytes: &mut [u8]| {
+        let _ = persist(&path, &kek, bytes);
+    }
+}
+
+/// seal `bytes` under `kek` and write them to `path`.  The data is written to a temporary file next to `path` which
+/// is then renamed, so `path` contains either the old or the new key.
+pub fn persist<P: BoxProvider>(path: &Path, kek: &Key<P>, bytes: &[u8]) -> crate::Result<()> {
+    let sealed = seal_box(kek, AD, bytes)?;
+    let mut file = TempFile::create::<P>(path)?;
+    file.write_all(&sealed)?;
+    file.commit()
+}
+
+/// load a key persisted by `encrypted_file` or `persist` from `path` and open it with `kek`.
+pub fn load_persisted_key<T: BoxProvider, P: BoxProvider>(path: &Path, kek: &Key<P>) -> crate::Result<Key<T>> {
+    let sealed = fs::read(path)?;
+    Key::load(open_box(kek, AD, &sealed)?)
+}
+
+/// create a file which is only accessible by the owner
+#[cfg(unix)]
+pub(crate) fn create(path: &Path) -> std::io::Result<File> {
+    use std::os::unix::fs::OpenOptionsExt;
+
+    OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(path)
+}
+
+/// create a file
+#[cfg(not(unix))]
+pub(crate) fn create(path: &Path) -> std::io::Result<File> {
+    OpenOptions::new().write(true).create(true).truncate(true).open(path)
+}
+
+/// create a new file which is only accessible by the owner, fails with `io::ErrorKind::AlreadyExists` if there is a
+/// file or a symlink at `path`
+#[cfg(unix)]
+fn create_new(path: &Path) -> io::Result<File> {
+    use std::os::unix::fs::OpenOptionsExt;
+
+    OpenOptions::new().write(true).create_new(true).mode(0o600).open(path)
+}
+
+/// create a new file, fails with `io::ErrorKind::AlreadyExists` if there is one at `path`
+#[cfg(not(unix))]
+fn create_new(path: &Path) -> io::Result<File> {
+    OpenOptions::new().write(true).create_new(true).open(path)
+}
+
+/// persist a rename or a creation in the directory of `path`
+pub(crate) fn sync_dir(path: &Path) -> crate::Result<()> {
+    #[cfg(unix)]
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        File::open(dir)?.sync_all()?;
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+    Ok(())
+}
+
+/// A file which is written next to its path under a random name and renamed to the path by `commit`.  The
+/// temporary file is created exclusively, so a file or a symlink planted at its name is never written to, and it is
+/// removed if it isn't committed.
+pub(crate) struct TempFile {
+    path: PathBuf,
+    tmp: PathBuf,
+    file: File,
+    committed: bool,
+}
+
+impl TempFile {
+    /// the number of random names tried before giving up
+    const ATTEMPTS: usize = 8;
+
+    /// create the temporary file of `path` with a random suffix from `P`.  The temporary file is on the filesystem
+    /// of `path`, so the rename never moves the data.
+    pub(crate) fn create<P: BoxProvider>(path: &Path) -> crate::Result<Self> {
+        let name = path.file_name().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Invalid path `{}`", path.display()),
+            )
+        })?;
+        for _ in 0..Self::ATTEMPTS {
+            let suffix = P::random_array::<8>().map_err(Into::into)?;
+            let mut tmp = name.to_os_string();
+            tmp.push(".");
+            tmp.push(suffix.iter().map(|b| format!("{:02x}", b)).collect::<String>());
+            tmp.push(".tmp");
+            let tmp = path.with_file_name(tmp);
+            match create_new(&tmp) {
+                Ok(file) => {
+                    return Ok(Self {
+                        path: path.to_path_buf(),
+                        tmp,
+                        file,
+                        committed: false,
+                    })
+                }
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("Unable to create a temporary file next to `{}`", path.display()),
+        )
+        .into())
+    }
+
+    /// sync the file, rename it to its path and sync the directory
+    pub(crate) fn commit(mut self) -> crate::Result<()> {
+        self.file.sync_all()?;
+        fs::rename(&self.tmp, &self.path)?;
+        self.committed = true;
+        sync_dir(&self.path)
+    }
+}
+
+impl Write for TempFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.file.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        if !self.committed {
+            let _ = fs::remove_file(&self.tmp);
+        }
+    }
+}
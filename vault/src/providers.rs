@@ -0,0 +1,101 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+#[cfg(feature = "provider-aes-gcm")]
+mod aes_gcm;
+#[cfg(feature = "provider-auto")]
+mod auto;
+mod committing;
+#[cfg(feature = "provider-ed25519")]
+mod ed25519;
+#[cfg(feature = "provider-ring")]
+mod ring;
+#[cfg(feature = "aead-interop")]
+mod rust_crypto;
+#[cfg(feature = "provider-siv")]
+mod siv;
+#[cfg(feature = "provider-sodium")]
+mod sodium;
+#[cfg(feature = "provider-xchacha")]
+mod xchacha;
+
+#[cfg(feature = "provider-aes-gcm")]
+pub use self::aes_gcm::AesGcm256;
+#[cfg(feature = "provider-auto")]
+pub use self::auto::{Auto, ProviderKind};
+#[cfg(feature = "provider-ring")]
+pub use self::ring::{RingAesGcm, RingAlgorithm, RingChaCha, RingProvider};
+pub use committing::CommittingBox;
+#[cfg(feature = "provider-ed25519")]
+pub use ed25519::Ed25519;
+#[cfg(all(feature = "aead-interop", feature = "provider-aes-gcm"))]
+pub use rust_crypto::RustAesGcm;
+#[cfg(all(feature = "aead-interop", feature = "provider-xchacha"))]
+pub use rust_crypto::{RustChaCha, RustXChaCha};
+#[cfg(feature = "aead-interop")]
+pub use rust_crypto::{RustCryptoAlgorithm, RustCryptoBox};
+#[cfg(feature = "provider-siv")]
+pub use siv::AesGcmSiv;
+#[cfg(feature = "provider-sodium")]
+pub use sodium::Sodium;
+#[cfg(feature = "provider-xchacha")]
+pub use xchacha::XChaChaPoly;
+
+#[cfg(any(
+    feature = "aead-interop",
+    feature = "provider-aes-gcm",
+    feature = "provider-ring",
+    feature = "provider-sodium",
+    feature = "provider-xchacha"
+))]
+use crate::crypto_box::{BoxProvider, Key};
+
+/// the XChaCha20-Poly1305 box of 16 zero bytes sealed with the zero key, the zero nonce and an empty AD
+#[cfg(any(feature = "provider-sodium", feature = "provider-xchacha"))]
+const XCHACHA_KNOWN_ANSWER: [u8; 56] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x78, 0x9e, 0x96, 0x89, 0xe5, 0x20, 0x8d,
+    0x7f, 0xd9, 0xe1, 0xf3, 0xc5, 0xb5, 0x34, 0x1f, 0x48, 0x39, 0x59, 0xfc, 0x0b, 0x77, 0x0c, 0x8e, 0x6d, 0x61, 0x16,
+    0x83, 0x0d, 0xcb, 0x63, 0x0c, 0xc5,
+];
+
+/// check that the provider opens `boxx`, sealed with the zero key and an empty AD, to 16 zero bytes.  Used as known
+/// answer test by `BoxProvider::self_test`.
+#[cfg(any(
+    feature = "provider-aes-gcm",
+    feature = "provider-sodium",
+    feature = "provider-xchacha"
+))]
+fn open_known_answer<T: BoxProvider>(boxx: &[u8]) -> crate::Result<bool> {
+    let key = Key::<T>::load(vec![0; T::box_key_len()])?;
+    Ok(T::box_open(&key, &[], boxx).map_err(Into::into)? == [0; 16])
+}
+
+/// check the length of an explicit nonce.  With the `test-utils` feature debug builds also panic if the nonce was
+/// used with the key before.
+#[cfg(any(
+    feature = "aead-interop",
+    feature = "provider-aes-gcm",
+    feature = "provider-ring",
+    feature = "provider-sodium",
+    feature = "provider-xchacha"
+))]
+#[cfg_attr(not(all(debug_assertions, feature = "test-utils")), allow(unused_variables))]
+fn check_nonce<T: BoxProvider>(key: &Key<T>, nonce: &[u8]) -> crate::Result<()> {
+    if nonce.len() != T::box_nonce_len() {
+        return Err(crate::Error::InvalidNonceLength {
+            expected: T::box_nonce_len(),
+            actual: nonce.len(),
+        });
+    }
+    #[cfg(all(debug_assertions, feature = "test-utils"))]
+    crate::test_utils::record_nonce(key, nonce);
+    Ok(())
+}
@@ -0,0 +1,172 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+use crate::{
+    crypto_box::{check_box_len, try_alloc, try_copy, BoxProvider, Key, SelfTestReport, Tag},
+    providers::{check_nonce, open_known_answer},
+};
+
+use aes_gcm::{
+    aead::{AeadInPlace, KeyInit},
+    Aes256Gcm, Nonce, Tag as AeadTag,
+};
+use zeroize::Zeroize;
+
+/// A provider sealing data with AES-256-GCM.  The box is the random 12 byte nonce followed by the ciphertext and
+/// the 16 byte tag.  AES-NI and CLMUL are used when the CPU supports them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AesGcm256;
+
+impl AesGcm256 {
+    const NONCE_LEN: usize = 12;
+    const TAG_LEN: usize = 16;
+    /// test case 14 of the GCM specification submitted to NIST: 16 zero bytes sealed with the zero key and nonce
+    const KNOWN_ANSWER: [u8; 44] = [
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xce, 0xa7, 0x40, 0x3d, 0x4d, 0x60, 0x6b, 0x6e, 0x07, 0x4e, 0xc5, 0xd3,
+        0xba, 0xf3, 0x9d, 0x18, 0xd0, 0xd1, 0xc8, 0xa7, 0x99, 0x99, 0x6b, 0xf0, 0x26, 0x5b, 0x98, 0xb5, 0xd4, 0x8a,
+        0xb9, 0x19,
+    ];
+}
+
+impl BoxProvider for AesGcm256 {
+    type Error = crate::Error;
+
+    fn box_key_len() -> usize {
+        32
+    }
+
+    fn box_overhead() -> usize {
+        Self::NONCE_LEN + Self::TAG_LEN
+    }
+
+    fn box_id() -> [u8; 4] {
+        *b"a256"
+    }
+
+    fn box_nonce_len() -> usize {
+        Self::NONCE_LEN
+    }
+
+    fn box_seal_with_nonce(key: &Key<Self>, nonce: &[u8], ad: &[u8], data: &[u8]) -> crate::Result<Vec<u8>> {
+        check_nonce::<Self>(key, nonce)?;
+
+        let mut boxx = try_alloc(data.len() + Self::box_overhead())?;
+        let (boxx_nonce, rest) = boxx.split_at_mut(Self::NONCE_LEN);
+        let (cipher, tag) = rest.split_at_mut(data.len());
+        boxx_nonce.copy_from_slice(nonce);
+        cipher.copy_from_slice(data);
+
+        let computed = Aes256Gcm::new(key.bytes().into())
+            .encrypt_in_place_detached(Nonce::from_slice(nonce), ad, cipher)
+            .map_err(|e| crate::Error::crypto("seal", e))?;
+        tag.copy_from_slice(&computed);
+        Ok(boxx)
+    }
+
+    fn box_open(key: &Key<Self>, ad: &[u8], data: &[u8]) -> crate::Result<Vec<u8>> {
+        check_box_len(data, Self::box_overhead())?;
+
+        let (nonce, rest) = data.split_at(Self::NONCE_LEN);
+        let (cipher, tag) = rest.split_at(rest.len() - Self::TAG_LEN);
+        let mut plain = try_copy(cipher)?;
+
+        Aes256Gcm::new(key.bytes().into())
+            .decrypt_in_place_detached(Nonce::from_slice(nonce), ad, &mut plain, AeadTag::from_slice(tag))
+            .map_err(|_| crate::Error::AuthenticationFailed)?;
+        Ok(plain)
+    }
+
+    fn tag_len() -> usize {
+        Self::TAG_LEN
+    }
+
+    fn box_seal_detached(key: &Key<Self>, ad: &[u8], data: &[u8]) -> crate::Result<(Vec<u8>, Tag)> {
+        let mut boxx = try_alloc(Self::NONCE_LEN + data.len())?;
+        let (nonce, cipher) = boxx.split_at_mut(Self::NONCE_LEN);
+        Self::random_buf(nonce)?;
+        cipher.copy_from_slice(data);
+
+        let computed = Aes256Gcm::new(key.bytes().into())
+            .encrypt_in_place_detached(Nonce::from_slice(nonce), ad, cipher)
+            .map_err(|e| crate::Error::crypto("seal", e))?;
+        Ok((boxx, Tag::from(computed.to_vec())))
+    }
+
+    fn box_open_detached(key: &Key<Self>, ad: &[u8], data: &[u8], tag: &Tag) -> crate::Result<Vec<u8>> {
+        check_box_len(data, Self::NONCE_LEN)?;
+        if tag.len() != Self::TAG_LEN {
+            return Err(crate::Error::AuthenticationFailed);
+        }
+
+        let (nonce, cipher) = data.split_at(Self::NONCE_LEN);
+        let mut plain = try_copy(cipher)?;
+        Aes256Gcm::new(key.bytes().into())
+            .decrypt_in_place_detached(
+                Nonce::from_slice(nonce),
+                ad,
+                &mut plain,
+                AeadTag::from_slice(tag.as_bytes()),
+            )
+            .map_err(|_| crate::Error::AuthenticationFailed)?;
+        Ok(plain)
+    }
+
+    fn box_seal_in_place(key: &Key<Self>, ad: &[u8], buf: &mut Vec<u8>) -> crate::Result<()> {
+        let len = buf.len();
+        buf.resize(len + Self::box_overhead(), 0);
+        buf.copy_within(..len, Self::NONCE_LEN);
+
+        let (nonce, rest) = buf.split_at_mut(Self::NONCE_LEN);
+        let (cipher, tag) = rest.split_at_mut(len);
+        let sealed = Self::random_buf(nonce).and_then(|_| {
+            Aes256Gcm::new(key.bytes().into())
+                .encrypt_in_place_detached(Nonce::from_slice(nonce), ad, cipher)
+                .map_err(|e| crate::Error::crypto("seal", e))
+        });
+        match sealed {
+            Ok(computed) => {
+                tag.copy_from_slice(&computed);
+                Ok(())
+            }
+            Err(e) => {
+                buf.zeroize();
+                Err(e)
+            }
+        }
+    }
+
+    fn box_open_in_place(key: &Key<Self>, ad: &[u8], buf: &mut Vec<u8>) -> crate::Result<()> {
+        check_box_len(buf, Self::box_overhead())?;
+
+        let len = buf.len() - Self::box_overhead();
+        let (nonce, rest) = buf.split_at_mut(Self::NONCE_LEN);
+        let (cipher, tag) = rest.split_at_mut(len);
+        Aes256Gcm::new(key.bytes().into())
+            .decrypt_in_place_detached(Nonce::from_slice(nonce), ad, cipher, AeadTag::from_slice(tag))
+            .map_err(|_| crate::Error::AuthenticationFailed)?;
+
+        buf.copy_within(Self::NONCE_LEN..Self::NONCE_LEN + len, 0);
+        // the moved plaintext leaves copies behind the new end
+        buf[len..].zeroize();
+        buf.truncate(len);
+        Ok(())
+    }
+
+    fn self_test() -> crate::Result<SelfTestReport> {
+        let mut report = SelfTestReport::run_generic::<Self>()?;
+        report.check("known answer", || open_known_answer::<Self>(&Self::KNOWN_ANSWER));
+        Ok(report)
+    }
+
+    fn random_buf(buf: &mut [u8]) -> crate::Result<()> {
+        getrandom::getrandom(buf).map_err(|e| crate::Error::crypto("random", e))
+    }
+}
@@ -0,0 +1,184 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+use crate::{
+    crypto_box::{check_box_len, BoxProvider, Key},
+    providers::{AesGcm256, XChaChaPoly},
+};
+
+use std::sync::{
+    atomic::{AtomicU8, Ordering},
+    OnceLock,
+};
+
+use zeroize::Zeroize;
+
+/// A provider of `Auto`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ProviderKind {
+    /// `AesGcm256`, the fastest with AES and carry-less multiplication instructions
+    AesGcm,
+    /// `XChaChaPoly`, the fastest without them
+    XChaCha,
+}
+
+/// A provider sealing with the fastest in-tree provider on the CPU it runs on, `AesGcm256` on CPUs supporting AES-NI
+/// and CLMUL or the AES and PMULL instructions of aarch64, `XChaChaPoly` on others.  The CPU is checked once, see
+/// `Auto::detected`, and the choice can be overridden with `Auto::force`.
+///
+/// The box is the 4 byte `BoxProvider::box_id` of the provider that sealed it, zero bytes padding the header to a
+/// fixed `box_overhead`, and the box of that provider.  The header is authenticated as a prefix of the AD.  Boxes are
+/// opened by the provider recorded in them, so a box sealed with AES-GCM opens on a CPU that prefers XChaCha20.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Auto;
+
+/// the forced provider, `NONE` if it isn't forced
+static FORCED: AtomicU8 = AtomicU8::new(NONE);
+const NONE: u8 = 0;
+const AES_GCM: u8 = 1;
+const XCHACHA: u8 = 2;
+
+impl Auto {
+    const ID_LEN: usize = 4;
+
+    /// seal with `kind` from now on, regardless of the CPU
+    pub fn force(kind: ProviderKind) {
+        let forced = match kind {
+            ProviderKind::AesGcm => AES_GCM,
+            ProviderKind::XChaCha => XCHACHA,
+        };
+        FORCED.store(forced, Ordering::SeqCst);
+    }
+
+    /// seal with the `detected` provider again
+    pub fn clear_forced() {
+        FORCED.store(NONE, Ordering::SeqCst);
+    }
+
+    /// the provider boxes are sealed with, the forced or the detected one
+    pub fn selected() -> ProviderKind {
+        match FORCED.load(Ordering::SeqCst) {
+            AES_GCM => ProviderKind::AesGcm,
+            XCHACHA => ProviderKind::XChaCha,
+            _ => Self::detected(),
+        }
+    }
+
+    /// the fastest provider on this CPU, detected at the first call
+    pub fn detected() -> ProviderKind {
+        static DETECTED: OnceLock<ProviderKind> = OnceLock::new();
+        *DETECTED.get_or_init(|| match has_aes() {
+            true => ProviderKind::AesGcm,
+            false => ProviderKind::XChaCha,
+        })
+    }
+
+    /// the header of boxes of `P` followed by `ad`, the AD of the box of `P`
+    fn header_ad<P: BoxProvider>(ad: &[u8]) -> Vec<u8> {
+        let len = Self::box_overhead() - P::box_overhead();
+        let mut header = Vec::with_capacity(len + ad.len());
+        header.resize(len, 0);
+        header[..Self::ID_LEN].copy_from_slice(&P::box_id());
+        header.extend_from_slice(ad);
+        header
+    }
+
+    fn seal<P: BoxProvider<Error = crate::Error>>(key: &Key<Self>, ad: &[u8], data: &[u8]) -> crate::Result<Vec<u8>> {
+        let key = Key::<P>::load_from_slice(key.bytes())?;
+        let header_ad = Self::header_ad::<P>(ad);
+
+        // seal the box of `P` into a buffer which already has room for the header
+        let mut boxx = Vec::new();
+        boxx.try_reserve_exact(data.len() + Self::box_overhead())
+            .map_err(|_| crate::Error::MemoryError(String::from("Unable to allocate the box")))?;
+        boxx.extend_from_slice(data);
+        if let Err(e) = P::box_seal_in_place(&key, &header_ad, &mut boxx) {
+            boxx.zeroize();
+            return Err(e);
+        }
+        let header = &header_ad[..header_ad.len() - ad.len()];
+        boxx.splice(0..0, header.iter().copied());
+        Ok(boxx)
+    }
+
+    fn open<P: BoxProvider<Error = crate::Error>>(key: &Key<Self>, ad: &[u8], data: &[u8]) -> crate::Result<Vec<u8>> {
+        let key = Key::<P>::load_from_slice(key.bytes())?;
+        let header_ad = Self::header_ad::<P>(ad);
+        let (header, sealed) = data.split_at(header_ad.len() - ad.len());
+        if header != &header_ad[..header.len()] {
+            return Err(crate::Error::AuthenticationFailed);
+        }
+        P::box_open(&key, &header_ad, sealed)
+    }
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+fn has_aes() -> bool {
+    std::is_x86_feature_detected!("aes") && std::is_x86_feature_detected!("pclmulqdq")
+}
+
+#[cfg(target_arch = "aarch64")]
+fn has_aes() -> bool {
+    std::arch::is_aarch64_feature_detected!("aes") && std::arch::is_aarch64_feature_detected!("pmull")
+}
+
+#[cfg(not(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64")))]
+fn has_aes() -> bool {
+    false
+}
+
+impl BoxProvider for Auto {
+    type Error = crate::Error;
+
+    fn box_key_len() -> usize {
+        32
+    }
+
+    /// the overhead of `XChaChaPoly`, the larger one, and the id of the provider
+    fn box_overhead() -> usize {
+        Self::ID_LEN + AesGcm256::box_overhead().max(XChaChaPoly::box_overhead())
+    }
+
+    fn box_id() -> [u8; 4] {
+        *b"auto"
+    }
+
+    fn box_seal(key: &Key<Self>, ad: &[u8], data: &[u8]) -> crate::Result<Vec<u8>> {
+        match Self::selected() {
+            ProviderKind::AesGcm => Self::seal::<AesGcm256>(key, ad, data),
+            ProviderKind::XChaCha => Self::seal::<XChaChaPoly>(key, ad, data),
+        }
+    }
+
+    fn box_open(key: &Key<Self>, ad: &[u8], data: &[u8]) -> crate::Result<Vec<u8>> {
+        check_box_len(data, Self::box_overhead())?;
+        let id = &data[..Self::ID_LEN];
+        if id == AesGcm256::box_id() {
+            Self::open::<AesGcm256>(key, ad, data)
+        } else if id == XChaChaPoly::box_id() {
+            Self::open::<XChaChaPoly>(key, ad, data)
+        } else {
+            Err(crate::Error::ProviderMismatch {
+                expected: format!(
+                    "{} or {}",
+                    AesGcm256::box_id().escape_ascii(),
+                    XChaChaPoly::box_id().escape_ascii()
+                ),
+                found: id.escape_ascii().to_string(),
+            })
+        }
+    }
+
+    fn random_buf(buf: &mut [u8]) -> crate::Result<()> {
+        XChaChaPoly::random_buf(buf)
+    }
+}
@@ -0,0 +1,100 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+use crate::{
+    crypto_box::{check_box_len, BoxProvider, Key},
+    ct::ct_eq,
+};
+
+use std::marker::PhantomData;
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use zeroize::Zeroize;
+
+/// A wrapper that makes the boxes of `P` key-committing.  AEADs like AES-GCM and ChaCha20-Poly1305 allow crafting a
+/// box which opens under two different keys.  The wrapper appends a random 16 byte nonce and an HMAC-SHA256 of the
+/// nonce to the box of `P`, keyed with a key derived from the box key, and checks it before opening the box.  A box
+/// therefore only opens under the key that sealed it.
+pub struct CommittingBox<P: BoxProvider>(PhantomData<P>);
+
+impl<P: BoxProvider> CommittingBox<P> {
+    const NONCE_LEN: usize = 16;
+    const COMMITMENT_LEN: usize = 32;
+
+    /// the key of `P` with the same bytes
+    fn inner_key(key: &Key<Self>) -> crate::Result<Key<P>> {
+        Key::load_from_slice(key.bytes())
+    }
+
+    /// the commitment to the key and the nonce
+    fn commitment(key: &Key<Self>, nonce: &[u8]) -> crate::Result<[u8; 32]> {
+        let commitment_key = key.derive_child(b"vault key commitment")?;
+        let mut mac = Hmac::<Sha256>::new_from_slice(commitment_key.bytes())
+            .map_err(|_| crate::Error::crypto("commit to key", "Unable to commit to key"))?;
+        mac.update(nonce);
+        Ok(mac.finalize().into_bytes().into())
+    }
+}
+
+impl<P: BoxProvider> BoxProvider for CommittingBox<P> {
+    type Error = P::Error;
+
+    fn box_key_len() -> usize {
+        P::box_key_len()
+    }
+
+    fn box_overhead() -> usize {
+        P::box_overhead() + Self::NONCE_LEN + Self::COMMITMENT_LEN
+    }
+
+    fn box_id() -> [u8; 4] {
+        let id = P::box_id();
+        [b'k', id[0], id[1], id[2]]
+    }
+
+    fn box_seal(key: &Key<Self>, ad: &[u8], data: &[u8]) -> Result<Vec<u8>, P::Error> {
+        let nonce = P::random_array::<16>()?;
+        let commitment = Self::commitment(key, &nonce)?;
+
+        // seal the box of `P` into a buffer which already has room for the commitment
+        let mut boxx = Vec::new();
+        boxx.try_reserve_exact(data.len() + Self::box_overhead())
+            .map_err(|_| crate::Error::MemoryError(String::from("Unable to allocate the box")))?;
+        boxx.extend_from_slice(data);
+        if let Err(e) = P::box_seal_in_place(&Self::inner_key(key)?, ad, &mut boxx) {
+            boxx.zeroize();
+            return Err(e.into());
+        }
+        boxx.extend_from_slice(&nonce);
+        boxx.extend_from_slice(&commitment);
+        Ok(boxx)
+    }
+
+    fn box_open(key: &Key<Self>, ad: &[u8], data: &[u8]) -> Result<Vec<u8>, P::Error> {
+        check_box_len(data, Self::box_overhead())?;
+
+        let (inner, rest) = data.split_at(data.len() - Self::NONCE_LEN - Self::COMMITMENT_LEN);
+        let (nonce, commitment) = rest.split_at(Self::NONCE_LEN);
+        if !ct_eq(&Self::commitment(key, nonce)?, commitment) {
+            return Err(crate::Error::AuthenticationFailed.into());
+        }
+        P::box_open(&Self::inner_key(key)?, ad, inner)
+    }
+
+    fn max_random_len() -> usize {
+        P::max_random_len()
+    }
+
+    fn random_buf(buf: &mut [u8]) -> Result<(), P::Error> {
+        P::random_buf(buf)
+    }
+}
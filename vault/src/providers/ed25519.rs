@@ -0,0 +1,135 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+use crate::crypto_box::{SignProvider, SigningKey};
+
+use std::convert::TryInto;
+
+use curve25519_dalek::{
+    edwards::{CompressedEdwardsY, EdwardsPoint},
+    scalar::Scalar,
+};
+use sha2::{Digest, Sha512};
+use zeroize::Zeroizing;
+
+/// A provider signing data with Ed25519 of RFC 8032.  The signing key is the 32 byte seed, the public key the 32 byte
+/// encoded point and the signature the 32 byte encoded point `R` followed by the 32 byte scalar `S`.  Verification
+/// is cofactorless and rejects non-canonical scalars as well as public keys and points `R` of small order, like
+/// libsodium.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Ed25519;
+
+impl Ed25519 {
+    const KEY_LEN: usize = 32;
+    const SIGNATURE_LEN: usize = 64;
+
+    /// the secret scalar and the prefix of the nonces of `seed`, the halves of its hash
+    fn expand(seed: &[u8]) -> (Zeroizing<Scalar>, Zeroizing<[u8; 32]>) {
+        let hash = Zeroizing::new(<[u8; 64]>::from(Sha512::digest(seed)));
+        let mut scalar = Zeroizing::new([0; 32]);
+        scalar.copy_from_slice(&hash[..32]);
+        scalar[0] &= 248;
+        scalar[31] &= 127;
+        scalar[31] |= 64;
+        let mut prefix = Zeroizing::new([0; 32]);
+        prefix.copy_from_slice(&hash[32..]);
+        (Zeroizing::new(Scalar::from_bytes_mod_order(*scalar)), prefix)
+    }
+
+    /// the hash of `parts` as scalar
+    fn hash_to_scalar(parts: &[&[u8]]) -> Scalar {
+        let mut hash = Sha512::new();
+        parts.iter().for_each(|part| hash.update(part));
+        Scalar::from_bytes_mod_order_wide(&hash.finalize().into())
+    }
+}
+
+impl SignProvider for Ed25519 {
+    type Error = crate::Error;
+
+    fn sign_key_len() -> usize {
+        Self::KEY_LEN
+    }
+
+    fn public_key_len() -> usize {
+        Self::KEY_LEN
+    }
+
+    fn signature_len() -> usize {
+        Self::SIGNATURE_LEN
+    }
+
+    fn public_key(key: &SigningKey<Self>) -> crate::Result<Vec<u8>> {
+        let (scalar, _) = Self::expand(key.bytes());
+        Ok(EdwardsPoint::mul_base(&scalar).compress().to_bytes().to_vec())
+    }
+
+    fn sign(key: &SigningKey<Self>, data: &[u8]) -> crate::Result<Vec<u8>> {
+        let (scalar, prefix) = Self::expand(key.bytes());
+        let public = EdwardsPoint::mul_base(&scalar).compress();
+        let nonce = Zeroizing::new(Self::hash_to_scalar(&[&prefix[..], data]));
+        let r = EdwardsPoint::mul_base(&nonce).compress();
+        let k = Self::hash_to_scalar(&[r.as_bytes(), public.as_bytes(), data]);
+        let s = k * *scalar + *nonce;
+
+        let mut signature = Vec::with_capacity(Self::SIGNATURE_LEN);
+        signature.extend_from_slice(r.as_bytes());
+        signature.extend_from_slice(s.as_bytes());
+        Ok(signature)
+    }
+
+    fn verify(public: &[u8], data: &[u8], sig: &[u8]) -> crate::Result<()> {
+        let public: [u8; 32] = public.try_into().map_err(|_| {
+            crate::Error::MalformedSignature(format!(
+                "The public key is `{}` bytes long, expected `{}`",
+                public.len(),
+                Self::KEY_LEN
+            ))
+        })?;
+        if sig.len() != Self::SIGNATURE_LEN {
+            return Err(crate::Error::MalformedSignature(format!(
+                "The signature is `{}` bytes long, expected `{}`",
+                sig.len(),
+                Self::SIGNATURE_LEN
+            )));
+        }
+        let point = CompressedEdwardsY(public)
+            .decompress()
+            .ok_or_else(|| crate::Error::MalformedSignature(String::from("The public key isn't a point")))?;
+        // a public key of small order verifies signatures of any key
+        if point.is_small_order() {
+            return Err(crate::Error::MalformedSignature(String::from(
+                "The public key is of small order",
+            )));
+        }
+        let (r, s) = sig.split_at(32);
+        let r_point = CompressedEdwardsY(r.try_into().expect("the point has 32 bytes"))
+            .decompress()
+            .ok_or_else(|| crate::Error::MalformedSignature(String::from("R isn't a point")))?;
+        if r_point.is_small_order() {
+            return Err(crate::Error::MalformedSignature(String::from("R is of small order")));
+        }
+        let s: Option<Scalar> = Scalar::from_canonical_bytes(s.try_into().expect("the scalar has 32 bytes")).into();
+        let s = s.ok_or_else(|| crate::Error::MalformedSignature(String::from("The scalar isn't canonical")))?;
+
+        // [S]B = R + [k]A, compared by the encoding of R
+        let k = Self::hash_to_scalar(&[r, &public, data]);
+        let expected = EdwardsPoint::vartime_double_scalar_mul_basepoint(&k, &-point, &s).compress();
+        match expected.as_bytes()[..] == *r {
+            true => Ok(()),
+            false => Err(crate::Error::SignatureMismatch),
+        }
+    }
+
+    fn random_buf(buf: &mut [u8]) -> crate::Result<()> {
+        getrandom::getrandom(buf).map_err(|e| crate::Error::crypto("random", e))
+    }
+}
@@ -0,0 +1,149 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+use crate::{
+    crypto_box::{check_box_len, try_alloc, try_copy, BoxProvider, Key},
+    providers::check_nonce,
+};
+
+use std::{convert::TryInto, marker::PhantomData};
+
+use ring::{
+    aead::{self, Aad, LessSafeKey, Nonce, UnboundKey},
+    rand::{SecureRandom, SystemRandom},
+};
+use zeroize::Zeroize;
+
+/// An AEAD algorithm of ring usable with `RingProvider`.
+pub trait RingAlgorithm {
+    /// the ring algorithm
+    fn algorithm() -> &'static aead::Algorithm;
+    /// the `BoxProvider::box_id` of the provider
+    fn box_id() -> [u8; 4];
+}
+
+/// ChaCha20-Poly1305 for `RingProvider`.  The boxes match the boxes of `RustCryptoBox<RustChaCha>`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RingChaCha;
+
+impl RingAlgorithm for RingChaCha {
+    fn algorithm() -> &'static aead::Algorithm {
+        &aead::CHACHA20_POLY1305
+    }
+
+    fn box_id() -> [u8; 4] {
+        *b"c20p"
+    }
+}
+
+/// AES-256-GCM for `RingProvider`.  The boxes match the boxes of `AesGcm256`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RingAesGcm;
+
+impl RingAlgorithm for RingAesGcm {
+    fn algorithm() -> &'static aead::Algorithm {
+        &aead::AES_256_GCM
+    }
+
+    fn box_id() -> [u8; 4] {
+        // the boxes are interchangeable with the boxes of `AesGcm256`
+        *b"a256"
+    }
+}
+
+/// A provider sealing data with ring.  The box is the random 12 byte nonce followed by the ciphertext and the 16 byte
+/// tag, the algorithm is chosen with `A`, e.g. `RingProvider<RingChaCha>`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RingProvider<A: RingAlgorithm>(PhantomData<A>);
+
+impl<A: RingAlgorithm> RingProvider<A> {
+    const NONCE_LEN: usize = aead::NONCE_LEN;
+
+    fn sealing_key(key: &Key<Self>) -> crate::Result<LessSafeKey> {
+        let key = UnboundKey::new(A::algorithm(), key.bytes()).map_err(|_| crate::Error::InvalidKeyLength {
+            expected: A::algorithm().key_len(),
+            actual: key.bytes().len(),
+        })?;
+        Ok(LessSafeKey::new(key))
+    }
+
+    fn nonce(nonce: &[u8]) -> crate::Result<Nonce> {
+        let nonce = nonce.try_into().map_err(|_| crate::Error::InvalidNonceLength {
+            expected: Self::NONCE_LEN,
+            actual: nonce.len(),
+        })?;
+        Ok(Nonce::assume_unique_for_key(nonce))
+    }
+}
+
+impl<A: RingAlgorithm> BoxProvider for RingProvider<A> {
+    type Error = crate::Error;
+
+    fn box_key_len() -> usize {
+        A::algorithm().key_len()
+    }
+
+    fn box_overhead() -> usize {
+        Self::NONCE_LEN + A::algorithm().tag_len()
+    }
+
+    fn box_id() -> [u8; 4] {
+        A::box_id()
+    }
+
+    fn box_nonce_len() -> usize {
+        Self::NONCE_LEN
+    }
+
+    fn box_seal_with_nonce(key: &Key<Self>, nonce: &[u8], ad: &[u8], data: &[u8]) -> crate::Result<Vec<u8>> {
+        check_nonce::<Self>(key, nonce)?;
+
+        // the data is copied once, into the box, and sealed there
+        let mut boxx = try_alloc(data.len() + Self::box_overhead())?;
+        let (boxx_nonce, rest) = boxx.split_at_mut(Self::NONCE_LEN);
+        let (cipher, tag) = rest.split_at_mut(data.len());
+        boxx_nonce.copy_from_slice(nonce);
+        cipher.copy_from_slice(data);
+
+        let computed = Self::sealing_key(key)?
+            .seal_in_place_separate_tag(Self::nonce(nonce)?, Aad::from(ad), cipher)
+            .map_err(|e| crate::Error::crypto("seal", e))?;
+        tag.copy_from_slice(computed.as_ref());
+        Ok(boxx)
+    }
+
+    fn box_open(key: &Key<Self>, ad: &[u8], data: &[u8]) -> crate::Result<Vec<u8>> {
+        check_box_len(data, Self::box_overhead())?;
+
+        let (nonce, sealed) = data.split_at(Self::NONCE_LEN);
+        let mut plain = try_copy(sealed)?;
+        let opened = Self::sealing_key(key)?
+            .open_in_place(Self::nonce(nonce)?, Aad::from(ad), &mut plain)
+            .map(|p| p.len());
+        match opened {
+            Ok(len) => {
+                plain[len..].zeroize();
+                plain.truncate(len);
+                Ok(plain)
+            }
+            Err(_) => {
+                plain.zeroize();
+                Err(crate::Error::AuthenticationFailed)
+            }
+        }
+    }
+
+    fn random_buf(buf: &mut [u8]) -> crate::Result<()> {
+        SystemRandom::new()
+            .fill(buf)
+            .map_err(|e| crate::Error::crypto("random", e))
+    }
+}
@@ -0,0 +1,128 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+use crate::{
+    crypto_box::{check_box_len, try_alloc, BoxProvider, Key},
+    providers::check_nonce,
+};
+
+use std::marker::PhantomData;
+
+use aead::{generic_array::typenum::Unsigned, Aead, AeadCore, AeadInPlace, KeyInit, KeySizeUser, Nonce, Payload};
+
+/// A RustCrypto AEAD usable with `RustCryptoBox`.  Implemented by a marker type per cipher, so the `box_id` is
+/// chosen once and stays the same across compiler and dependency upgrades.
+pub trait RustCryptoAlgorithm {
+    /// the RustCrypto cipher, one without a ciphertext overhead besides the tag
+    type Aead: AeadInPlace + KeyInit;
+
+    /// the `BoxProvider::box_id` of the provider
+    const BOX_ID: [u8; 4];
+}
+
+/// ChaCha20-Poly1305 for `RustCryptoBox`.  The boxes match the boxes of `RingProvider<RingChaCha>`.
+#[cfg(feature = "provider-xchacha")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RustChaCha;
+
+#[cfg(feature = "provider-xchacha")]
+impl RustCryptoAlgorithm for RustChaCha {
+    type Aead = chacha20poly1305::ChaCha20Poly1305;
+
+    const BOX_ID: [u8; 4] = *b"c20p";
+}
+
+/// XChaCha20-Poly1305 for `RustCryptoBox`.  The boxes match the boxes of `XChaChaPoly`.
+#[cfg(feature = "provider-xchacha")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RustXChaCha;
+
+#[cfg(feature = "provider-xchacha")]
+impl RustCryptoAlgorithm for RustXChaCha {
+    type Aead = chacha20poly1305::XChaCha20Poly1305;
+
+    const BOX_ID: [u8; 4] = *b"xc20";
+}
+
+/// AES-256-GCM for `RustCryptoBox`.  The boxes match the boxes of `AesGcm256`.
+#[cfg(feature = "provider-aes-gcm")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RustAesGcm;
+
+#[cfg(feature = "provider-aes-gcm")]
+impl RustCryptoAlgorithm for RustAesGcm {
+    type Aead = aes_gcm::Aes256Gcm;
+
+    const BOX_ID: [u8; 4] = *b"a256";
+}
+
+/// A provider for any RustCrypto AEAD, picked by a `RustCryptoAlgorithm`.  The box is the random nonce of the
+/// cipher's nonce size followed by the ciphertext and the tag, the output of `Aead::encrypt`, so the overhead depends
+/// on the nonce and tag sizes of the cipher.
+pub struct RustCryptoBox<A>(PhantomData<A>);
+
+impl<A: RustCryptoAlgorithm> RustCryptoBox<A> {
+    fn cipher(key: &Key<Self>) -> crate::Result<A::Aead> {
+        A::Aead::new_from_slice(key.bytes()).map_err(|_| crate::Error::InvalidKeyLength {
+            expected: <A::Aead as KeySizeUser>::KeySize::USIZE,
+            actual: key.bytes().len(),
+        })
+    }
+}
+
+impl<A: RustCryptoAlgorithm> BoxProvider for RustCryptoBox<A> {
+    type Error = crate::Error;
+
+    fn box_key_len() -> usize {
+        <A::Aead as KeySizeUser>::KeySize::USIZE
+    }
+
+    fn box_overhead() -> usize {
+        <A::Aead as AeadCore>::NonceSize::USIZE + <A::Aead as AeadCore>::TagSize::USIZE
+    }
+
+    fn box_id() -> [u8; 4] {
+        A::BOX_ID
+    }
+
+    fn box_nonce_len() -> usize {
+        <A::Aead as AeadCore>::NonceSize::USIZE
+    }
+
+    fn box_seal_with_nonce(key: &Key<Self>, nonce: &[u8], ad: &[u8], data: &[u8]) -> crate::Result<Vec<u8>> {
+        check_nonce::<Self>(key, nonce)?;
+
+        let mut boxx = try_alloc(nonce.len() + data.len() + <A::Aead as AeadCore>::TagSize::USIZE)?;
+        let (boxx_nonce, rest) = boxx.split_at_mut(nonce.len());
+        let (cipher, tag) = rest.split_at_mut(data.len());
+        boxx_nonce.copy_from_slice(nonce);
+        cipher.copy_from_slice(data);
+
+        let computed = Self::cipher(key)?
+            .encrypt_in_place_detached(Nonce::<A::Aead>::from_slice(nonce), ad, cipher)
+            .map_err(|e| crate::Error::crypto("seal", e))?;
+        tag.copy_from_slice(&computed);
+        Ok(boxx)
+    }
+
+    fn box_open(key: &Key<Self>, ad: &[u8], data: &[u8]) -> crate::Result<Vec<u8>> {
+        check_box_len(data, Self::box_overhead())?;
+
+        let (nonce, sealed) = data.split_at(Self::box_nonce_len());
+        Self::cipher(key)?
+            .decrypt(Nonce::<A::Aead>::from_slice(nonce), Payload { msg: sealed, aad: ad })
+            .map_err(|_| crate::Error::AuthenticationFailed)
+    }
+
+    fn random_buf(buf: &mut [u8]) -> crate::Result<()> {
+        getrandom::getrandom(buf).map_err(|e| crate::Error::crypto("random", e))
+    }
+}
@@ -0,0 +1,93 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+use crate::crypto_box::{check_box_len, try_alloc, try_copy, BoxProvider, Key};
+
+use aes_gcm_siv::{
+    aead::{AeadInPlace, KeyInit},
+    Aes256GcmSiv, Nonce, Tag,
+};
+
+/// A nonce misuse-resistant provider sealing data with AES-256-GCM-SIV.  The box is the 12 byte nonce followed by
+/// the ciphertext and the 16 byte synthetic IV, which is also the authentication tag.  Sealing the same plaintext
+/// twice with the same key, nonce and AD yields the same box, but unlike with AES-GCM a repeated nonce reveals
+/// nothing beyond that.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AesGcmSiv;
+
+impl AesGcmSiv {
+    const NONCE_LEN: usize = 12;
+    const TAG_LEN: usize = 16;
+}
+
+impl BoxProvider for AesGcmSiv {
+    type Error = crate::Error;
+
+    fn box_key_len() -> usize {
+        32
+    }
+
+    fn box_overhead() -> usize {
+        Self::NONCE_LEN + Self::TAG_LEN
+    }
+
+    fn box_id() -> [u8; 4] {
+        *b"asiv"
+    }
+
+    fn box_nonce_len() -> usize {
+        Self::NONCE_LEN
+    }
+
+    /// seals some data using an explicit nonce.  A repeated nonce is safe to the extent that equal plaintexts are
+    /// revealed, so nonces aren't recorded by `test_utils::record_nonce`.
+    fn box_seal_with_nonce(key: &Key<Self>, nonce: &[u8], ad: &[u8], data: &[u8]) -> crate::Result<Vec<u8>> {
+        if nonce.len() != Self::NONCE_LEN {
+            return Err(crate::Error::InvalidNonceLength {
+                expected: Self::NONCE_LEN,
+                actual: nonce.len(),
+            });
+        }
+
+        let mut boxx = try_alloc(data.len() + Self::box_overhead())?;
+        let (boxx_nonce, rest) = boxx.split_at_mut(Self::NONCE_LEN);
+        let (cipher, tag) = rest.split_at_mut(data.len());
+        boxx_nonce.copy_from_slice(nonce);
+        cipher.copy_from_slice(data);
+
+        let computed = Aes256GcmSiv::new(key.bytes().into())
+            .encrypt_in_place_detached(Nonce::from_slice(nonce), ad, cipher)
+            .map_err(|e| crate::Error::crypto("seal", e))?;
+        tag.copy_from_slice(&computed);
+        Ok(boxx)
+    }
+
+    fn box_open(key: &Key<Self>, ad: &[u8], data: &[u8]) -> crate::Result<Vec<u8>> {
+        check_box_len(data, Self::box_overhead())?;
+
+        let (nonce, rest) = data.split_at(Self::NONCE_LEN);
+        let (cipher, tag) = rest.split_at(rest.len() - Self::TAG_LEN);
+        let mut plain = try_copy(cipher)?;
+
+        Aes256GcmSiv::new(key.bytes().into())
+            .decrypt_in_place_detached(Nonce::from_slice(nonce), ad, &mut plain, Tag::from_slice(tag))
+            .map_err(|_| crate::Error::AuthenticationFailed)?;
+        Ok(plain)
+    }
+
+    fn tag_len() -> usize {
+        Self::TAG_LEN
+    }
+
+    fn random_buf(buf: &mut [u8]) -> crate::Result<()> {
+        getrandom::getrandom(buf).map_err(|e| crate::Error::crypto("random", e))
+    }
+}
@@ -0,0 +1,101 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+use crate::{
+    crypto_box::{check_box_len, try_alloc, BoxProvider, Key, SelfTestReport},
+    providers::{check_nonce, open_known_answer, XCHACHA_KNOWN_ANSWER},
+};
+
+use sodiumoxide::{crypto::aead::xchacha20poly1305_ietf as aead, randombytes};
+
+/// A provider sealing data with libsodium.  Secretbox can't authenticate AD, so the provider uses libsodium's
+/// `crypto_aead_xchacha20poly1305_ietf` construction instead.  The box is the random 24 byte nonce followed by the
+/// output of `crypto_aead_xchacha20poly1305_ietf_encrypt`, which allows exchanging boxes with libsodium
+/// applications and with the `XChaChaPoly` provider.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Sodium;
+
+impl Sodium {
+    /// initialize libsodium.  Can be called multiple times.
+    fn init() -> crate::Result<()> {
+        sodiumoxide::init().map_err(|_| crate::Error::crypto("initialize libsodium", "Unable to initialize libsodium"))
+    }
+
+    /// convert the key into a libsodium key, which is wiped when it is dropped
+    fn sodium_key(key: &Key<Self>) -> crate::Result<aead::Key> {
+        aead::Key::from_slice(key.bytes()).ok_or(crate::Error::InvalidKeyLength {
+            expected: aead::KEYBYTES,
+            actual: key.bytes().len(),
+        })
+    }
+}
+
+impl BoxProvider for Sodium {
+    type Error = crate::Error;
+
+    fn box_key_len() -> usize {
+        aead::KEYBYTES
+    }
+
+    fn box_overhead() -> usize {
+        aead::NONCEBYTES + aead::TAGBYTES
+    }
+
+    fn box_id() -> [u8; 4] {
+        // the boxes are interchangeable with the boxes of `XChaChaPoly`
+        *b"xc20"
+    }
+
+    fn box_nonce_len() -> usize {
+        aead::NONCEBYTES
+    }
+
+    fn box_seal_with_nonce(key: &Key<Self>, nonce: &[u8], ad: &[u8], data: &[u8]) -> crate::Result<Vec<u8>> {
+        Self::init()?;
+        check_nonce::<Self>(key, nonce)?;
+
+        let mut boxx = try_alloc(data.len() + Self::box_overhead())?;
+        let (boxx_nonce, rest) = boxx.split_at_mut(aead::NONCEBYTES);
+        let (cipher, tag) = rest.split_at_mut(data.len());
+        boxx_nonce.copy_from_slice(nonce);
+        cipher.copy_from_slice(data);
+
+        let computed = aead::seal_detached(
+            cipher,
+            Some(ad),
+            &aead::Nonce::from_slice(nonce).ok_or(crate::Error::InterfaceError)?,
+            &Self::sodium_key(key)?,
+        );
+        tag.copy_from_slice(computed.as_ref());
+        Ok(boxx)
+    }
+
+    fn box_open(key: &Key<Self>, ad: &[u8], data: &[u8]) -> crate::Result<Vec<u8>> {
+        Self::init()?;
+        check_box_len(data, Self::box_overhead())?;
+
+        let (nonce, sealed) = data.split_at(aead::NONCEBYTES);
+        let nonce = aead::Nonce::from_slice(nonce).ok_or(crate::Error::AuthenticationFailed)?;
+        aead::open(sealed, Some(ad), &nonce, &Self::sodium_key(key)?).map_err(|_| crate::Error::AuthenticationFailed)
+    }
+
+    fn self_test() -> crate::Result<SelfTestReport> {
+        let mut report = SelfTestReport::run_generic::<Self>()?;
+        report.check("known answer", || open_known_answer::<Self>(&XCHACHA_KNOWN_ANSWER));
+        Ok(report)
+    }
+
+    fn random_buf(buf: &mut [u8]) -> crate::Result<()> {
+        Self::init()?;
+        randombytes::randombytes_into(buf);
+        Ok(())
+    }
+}
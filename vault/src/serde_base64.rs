@@ -0,0 +1,104 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+use crate::base64::Base64;
+
+use std::{convert::TryInto, fmt};
+
+use serde::{
+    de::{self, SeqAccess, Unexpected, Visitor},
+    ser::SerializeTuple,
+    Deserializer, Serializer,
+};
+
+/// accepts a base64 string, raw bytes or a sequence of bytes, the serde default of `Vec<u8>`
+struct BytesVisitor;
+
+impl<'de> Visitor<'de> for BytesVisitor {
+    type Value = Vec<u8>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a base64 string or bytes")
+    }
+
+    fn visit_str<E: de::Error>(self, s: &str) -> Result<Self::Value, E> {
+        Base64::decode_standard(s.as_bytes()).map_err(|_| E::invalid_value(Unexpected::Str(s), &self))
+    }
+
+    fn visit_bytes<E: de::Error>(self, bytes: &[u8]) -> Result<Self::Value, E> {
+        Ok(bytes.to_vec())
+    }
+
+    fn visit_byte_buf<E: de::Error>(self, bytes: Vec<u8>) -> Result<Self::Value, E> {
+        Ok(bytes)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or(0).min(4096));
+        while let Some(b) = seq.next_element()? {
+            bytes.push(b);
+        }
+        Ok(bytes)
+    }
+}
+
+/// serialize bytes as a padded standard base64 string in human readable formats like JSON and as raw bytes in binary
+/// formats like bincode.  Use it with `#[serde(with = "vault::serde_base64")]` on `Vec<u8>` fields.
+pub fn serialize<S: Serializer, T: AsRef<[u8]> + ?Sized>(bytes: &T, serializer: S) -> Result<S::Ok, S::Error> {
+    if serializer.is_human_readable() {
+        serializer.serialize_str(&Base64::encode_standard(bytes.as_ref()))
+    } else {
+        serializer.serialize_bytes(bytes.as_ref())
+    }
+}
+
+/// deserialize bytes written by `serialize`.  Human readable formats also accept the array of numbers serde writes
+/// for `Vec<u8>` by default, so data written before switching to base64 still deserializes.  Invalid base64 is
+/// rejected.
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+    if deserializer.is_human_readable() {
+        deserializer.deserialize_any(BytesVisitor)
+    } else {
+        deserializer.deserialize_byte_buf(BytesVisitor)
+    }
+}
+
+/// like the parent module for fixed size arrays, `#[serde(with = "vault::serde_base64::array")]`.  Binary formats
+/// write the bytes as a tuple without a length, the serde default of arrays.
+pub mod array {
+    use super::*;
+
+    /// serialize an array as a base64 string in human readable formats and as a tuple of bytes otherwise
+    pub fn serialize<S: Serializer, const N: usize>(bytes: &[u8; N], serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            return super::serialize(bytes, serializer);
+        }
+
+        let mut tuple = serializer.serialize_tuple(N)?;
+        for b in bytes {
+            tuple.serialize_element(b)?;
+        }
+        tuple.end()
+    }
+
+    /// deserialize an array written by `serialize`.  Fails if the data doesn't have exactly `N` bytes.
+    pub fn deserialize<'de, D: Deserializer<'de>, const N: usize>(deserializer: D) -> Result<[u8; N], D::Error> {
+        let bytes = if deserializer.is_human_readable() {
+            deserializer.deserialize_any(BytesVisitor)?
+        } else {
+            deserializer.deserialize_tuple(N, BytesVisitor)?
+        };
+        let len = bytes.len();
+        bytes
+            .try_into()
+            .map_err(|_| de::Error::invalid_length(len, &format!("{} bytes", N).as_str()))
+    }
+}
@@ -0,0 +1,204 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+use crate::{
+    crypto_box::{check_box_len, BoxProvider, Key, KeyFingerprint},
+    ct::ct_eq,
+};
+
+use std::{
+    any,
+    cell::{Cell, RefCell},
+    collections::BTreeSet,
+    convert::TryInto,
+    sync::Mutex,
+};
+
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+
+/// the nonces recorded by `record_nonce` with their providers and the fingerprints of their keys
+static NONCES: Mutex<BTreeSet<(&'static str, KeyFingerprint, Vec<u8>)>> = Mutex::new(BTreeSet::new());
+
+/// record that `nonce` was used with `key` and panic if it was used with the key before.  Providers call it from
+/// `BoxProvider::box_seal_with_nonce` in debug builds to catch nonce reuse in tests.  The recorded nonces are kept
+/// for the lifetime of the process.
+pub fn record_nonce<T: BoxProvider>(key: &Key<T>, nonce: &[u8]) {
+    if NONCES_PAUSED.with(Cell::get) {
+        return;
+    }
+    let fresh = NONCES.lock().unwrap_or_else(|e| e.into_inner()).insert((
+        any::type_name::<T>(),
+        key.fingerprint(),
+        nonce.to_vec(),
+    ));
+    if !fresh {
+        panic!("nonce reused with key {}", key.fingerprint());
+    }
+}
+
+/// run `f` without recording nonces on this thread, for measurements like counting the allocations of a seal which
+/// the records would skew.  Nonce reuse isn't caught within `f`.
+pub fn without_nonce_records<R>(f: impl FnOnce() -> R) -> R {
+    NONCES_PAUSED.with(|paused| paused.set(true));
+    let result = f();
+    NONCES_PAUSED.with(|paused| paused.set(false));
+    result
+}
+
+thread_local! {
+    /// whether `record_nonce` is paused on this thread, see `without_nonce_records`
+    static NONCES_PAUSED: Cell<bool> = const { Cell::new(false) };
+    /// the buffers recorded by `record_wipe` on this thread
+    static WIPED: RefCell<Vec<Vec<u8>>> = const { RefCell::new(Vec::new()) };
+}
+
+/// record the contents of a temporary buffer after it was wiped, see `take_wiped_buffers`
+pub(crate) fn record_wipe(buf: &[u8]) {
+    WIPED.with(|wiped| wiped.borrow_mut().push(buf.to_vec()));
+}
+
+/// take the temporary buffers the key sources like `Key::from_env` wiped on this thread since the last call, with
+/// their contents after the wipe
+pub fn take_wiped_buffers() -> Vec<Vec<u8>> {
+    WIPED.with(|wiped| std::mem::take(&mut *wiped.borrow_mut()))
+}
+
+/// the state of `TestProvider`, kept per thread so tests running in parallel don't see each other's calls
+#[derive(Default)]
+struct TestState {
+    rng: u64,
+    nonce: u64,
+    seals: usize,
+    opens: usize,
+}
+
+thread_local! {
+    static STATE: RefCell<TestState> = RefCell::new(TestState::default());
+}
+
+/// A deterministic provider for tests, **not** for production data.  The data is xored with a SHA-256 keystream of
+/// the key and a counter nonce and authenticated with a truncated HMAC-SHA256, so wrong keys, wrong AD and tampered
+/// boxes fail like with a real provider.  The box is the 8 byte nonce followed by the ciphertext and the 16 byte
+/// tag.  The random bytes come from a seedable generator, see `TestProvider::seed`.  The nonce counter, the
+/// generator and the call counters are kept per thread.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TestProvider;
+
+impl TestProvider {
+    const NONCE_LEN: usize = 8;
+    const TAG_LEN: usize = 16;
+
+    /// seed the random generator of the current thread so `random_buf` is reproducible
+    pub fn seed(seed: u64) {
+        STATE.with(|s| s.borrow_mut().rng = seed);
+    }
+
+    /// reset the generator, the nonce counter and the call counters of the current thread
+    pub fn reset() {
+        STATE.with(|s| *s.borrow_mut() = TestState::default());
+    }
+
+    /// the number of `box_seal` calls on the current thread
+    pub fn seal_count() -> usize {
+        STATE.with(|s| s.borrow().seals)
+    }
+
+    /// the number of `box_open` calls on the current thread
+    pub fn open_count() -> usize {
+        STATE.with(|s| s.borrow().opens)
+    }
+
+    /// xor `data` with the keystream of `key` and `nonce`
+    fn apply_keystream(key: &[u8], nonce: &[u8], data: &mut [u8]) {
+        for (i, chunk) in data.chunks_mut(32).enumerate() {
+            let block = Sha256::new()
+                .chain_update(key)
+                .chain_update(nonce)
+                .chain_update((i as u64).to_be_bytes())
+                .finalize();
+            chunk.iter_mut().zip(block.iter()).for_each(|(b, k)| *b ^= k);
+        }
+    }
+
+    /// the tag of the nonce, the AD and the ciphertext
+    fn tag(key: &[u8], nonce: &[u8], ad: &[u8], cipher: &[u8]) -> [u8; 16] {
+        let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+        mac.update(nonce);
+        mac.update(&(ad.len() as u64).to_be_bytes());
+        mac.update(ad);
+        mac.update(cipher);
+        mac.finalize().into_bytes()[..Self::TAG_LEN]
+            .try_into()
+            .expect("the tag is truncated to 16 bytes")
+    }
+}
+
+impl BoxProvider for TestProvider {
+    type Error = crate::Error;
+
+    fn box_key_len() -> usize {
+        32
+    }
+
+    fn box_overhead() -> usize {
+        Self::NONCE_LEN + Self::TAG_LEN
+    }
+
+    fn box_id() -> [u8; 4] {
+        *b"test"
+    }
+
+    fn box_seal(key: &Key<Self>, ad: &[u8], data: &[u8]) -> crate::Result<Vec<u8>> {
+        let nonce = STATE.with(|s| {
+            let mut state = s.borrow_mut();
+            state.seals += 1;
+            state.nonce += 1;
+            state.nonce.to_be_bytes()
+        });
+
+        let mut cipher = data.to_vec();
+        Self::apply_keystream(key.bytes(), &nonce, &mut cipher);
+        let tag = Self::tag(key.bytes(), &nonce, ad, &cipher);
+        Ok([&nonce[..], &cipher, &tag].concat())
+    }
+
+    fn box_open(key: &Key<Self>, ad: &[u8], data: &[u8]) -> crate::Result<Vec<u8>> {
+        STATE.with(|s| s.borrow_mut().opens += 1);
+        check_box_len(data, Self::box_overhead())?;
+
+        let (nonce, rest) = data.split_at(Self::NONCE_LEN);
+        let (cipher, tag) = rest.split_at(rest.len() - Self::TAG_LEN);
+        if !ct_eq(&Self::tag(key.bytes(), nonce, ad, cipher), tag) {
+            return Err(crate::Error::AuthenticationFailed);
+        }
+
+        let mut plain = cipher.to_vec();
+        Self::apply_keystream(key.bytes(), nonce, &mut plain);
+        Ok(plain)
+    }
+
+    fn random_buf(buf: &mut [u8]) -> crate::Result<()> {
+        STATE.with(|s| {
+            let mut state = s.borrow_mut();
+            for chunk in buf.chunks_mut(8) {
+                // splitmix64
+                state.rng = state.rng.wrapping_add(0x9e37_79b9_7f4a_7c15);
+                let mut z = state.rng;
+                z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+                z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+                z ^= z >> 31;
+                chunk.copy_from_slice(&z.to_be_bytes()[..chunk.len()]);
+            }
+        });
+        Ok(())
+    }
+}
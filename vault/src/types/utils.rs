@@ -38,7 +38,7 @@ impl Id {
     /// create a random ID
     pub fn random<P: BoxProvider>() -> crate::Result<Self> {
         let mut buf = [0; 24];
-        P::random_buf(&mut buf)?;
+        P::random_buf(&mut buf).map_err(Into::into)?;
 
         Ok(Self(buf))
     }
@@ -10,35 +10,87 @@
 // See the License for the specific language governing permissions and limitations under the License.
 
 use crate::{
-    crypto_box::{BoxProvider, Key},
+    crypto_box::{record_rekey, BoxProvider, Key, RekeyReport},
+    ct::ct_eq,
     types::{
-        transactions::{DataTransaction, InitTransaction, RevocationTransaction},
+        transactions::{DataTransaction, InitTransaction, RevocationTransaction, SealedPayload},
         utils::{Id, RecordHint, Val},
     },
-    vault::record::{ChainRecord, ValidRecord},
+    vault::{
+        expiry::{open_expiring, payload_expiry, seal_payload},
+        namespace::RecordAd,
+        record::{ChainRecord, ValidRecord},
+    },
+};
+
+use std::{
+    collections::HashMap,
+    time::{Instant, SystemTime},
 };
 
-use std::collections::HashMap;
+use zeroize::Zeroize;
 
+#[cfg(feature = "insecure-serde")]
 use serde::{Deserialize, Serialize};
 
+mod concurrent;
+mod expiry;
+mod file_store;
+mod gc;
+mod index;
+mod integrity;
+mod journal;
+#[cfg(feature = "password-kdf")]
+mod lockable;
+mod meta;
+mod migrate;
+mod namespace;
+mod policy;
+mod read_only;
 mod record;
 mod results;
-
+mod versions;
+mod wal;
+
+pub use crate::vault::concurrent::{ConcurrentVault, VaultAccess};
+pub use crate::vault::expiry::{Clock, ExpiryPolicy, SystemClock};
+pub use crate::vault::file_store::{AutoCompact, CompactReport, CompactStep, FileStore, LockMode};
+pub use crate::vault::gc::GcReport;
+pub use crate::vault::index::BlindIndex;
+pub use crate::vault::integrity::IntegrityTree;
+pub use crate::vault::journal::{CommitStep, JournaledVault, Recovery, Store, VaultTransaction};
+#[cfg(feature = "password-kdf")]
+pub use crate::vault::lockable::LockableVault;
+pub use crate::vault::meta::RecordMeta;
+pub use crate::vault::migrate::{migrate_records, MigrationReport};
+pub use crate::vault::namespace::VaultNamespace;
+pub use crate::vault::policy::{AccessContext, AllOf, AllowAll, AnyOf, Claims, Decision, Operation, Policy};
+pub use crate::vault::read_only::ReadOnlyVault;
 pub use crate::vault::results::{DeleteRequest, ListResult, ReadRequest, ReadResult, Record, WriteRequest};
+pub use crate::vault::versions::{RetentionPolicy, VersionInfo};
+pub use crate::vault::wal::{Durability, WalRecovery, WalStep, WalStore};
+
+#[cfg(feature = "password-kdf")]
+pub(crate) use crate::vault::versions::parse_entry as parse_version;
 
 /// A view over the vault.  `key` is the Key used to lock the data. `chain` is a `ChainRecord` that contains all of the
-/// associated records in the vault.  `valid` is a ValidRecord which contains only valid records.   
-#[derive(Serialize, Deserialize, Clone)]
+/// associated records in the vault.  `valid` is a ValidRecord which contains only valid records.  Serializing the view
+/// requires the `insecure-serde` feature since it contains the raw key.
+#[cfg_attr(feature = "insecure-serde", derive(Serialize, Deserialize))]
+#[derive(Clone)]
 pub struct DBView<P: BoxProvider> {
     key: Key<P>,
     chain: ChainRecord,
     valid: ValidRecord,
+    /// the AD of the records, the AD of the namespace of the view
+    #[cfg_attr(feature = "insecure-serde", serde(default))]
+    ad: RecordAd,
 }
 
 /// A reader for the `DBView`
 pub struct DBReader<'a, P: BoxProvider> {
     view: &'a DBView<P>,
+    expiry: ExpiryPolicy<'a>,
 }
 
 /// A writer for the `DBView`
@@ -48,16 +100,27 @@ pub struct DBWriter<P: BoxProvider> {
 }
 
 impl<P: BoxProvider> DBView<P> {
-    /// Opens a vault using a key. Accepts the `ids` of the records that you want to load.  
+    /// Opens a vault using a key. Accepts the `ids` of the records that you want to load.  Fails with
+    /// `Error::CorruptRecord` if a record opens with the key but doesn't hold a transaction.
     pub fn load(key: Key<P>, ids: ListResult) -> crate::Result<Self> {
-        // get records based on the Ids and open them with the key.
-        let records = ids.into_iter().filter_map(|id| Record::open(&key, &id));
+        Self::load_in(key, RecordAd::default(), ids)
+    }
+
+    /// like `load` for the records sealed with `ad`
+    pub(crate) fn load_in(key: Key<P>, ad: RecordAd, ids: ListResult) -> crate::Result<Self> {
+        // get records based on the Ids and open them with the key.  Records of other keys are skipped.
+        let mut records = Vec::new();
+        for (offset, id) in ids.into_iter().enumerate() {
+            if let Some(record) = Record::try_open(&key, &ad, &id, offset)? {
+                records.push(record);
+            }
+        }
 
         // build indices
-        let chain = ChainRecord::new(records)?;
+        let chain = ChainRecord::new(records.into_iter())?;
         let valid = ValidRecord::new(&chain);
 
-        Ok(Self { key, chain, valid })
+        Ok(Self { key, chain, valid, ad })
     }
 
     /// Creates an iterator over all valid records. Iterates over ids and record hints
@@ -90,7 +153,8 @@ impl<P: BoxProvider> DBView<P> {
     }
 
     /// Check the age of the chains. Fills the `chain_ctr` with a HashMap of the chain's owner
-    /// ids their counter size.
+    /// ids their counter size.  Fails with `Error::VersionMismatch` if a chain is older than in `chain_ctrs` and with
+    /// `Error::VersionError` if a chain of `chain_ctrs` is missing.
     pub fn not_older_than(&self, chain_ctrs: &HashMap<Id, u64>) -> crate::Result<()> {
         let this_ctrs = self.chain_ctrs();
         chain_ctrs.iter().try_for_each(|(chain, other_ctr)| {
@@ -101,16 +165,145 @@ impl<P: BoxProvider> DBView<P> {
             if this_ctr >= other_ctr {
                 Ok(())
             } else {
-                Err(crate::Error::VersionError(String::from(
-                    "This database is older than the reference database",
-                )))
+                Err(crate::Error::VersionMismatch {
+                    expected: *other_ctr,
+                    found: *this_ctr,
+                })
             }
         })
     }
 
+    /// Re-key the vault from `old_key`, the key of the view, to `new_key`.  Every transaction of the chains is sealed
+    /// under `new_key` and the payload, the metadata and the versions of every valid record are read with `read`,
+    /// opened under `old_key` and sealed under `new_key`; the versions are found among the ids of the store in `list`.  Every
+    /// re-sealed record is opened under `new_key` again before it is added to the requests.  Records don't carry a
+    /// key id, the key is implied by the seal.
+    ///
+    /// The change is all or nothing: if the payload or a version of any record is missing, or it or the metadata
+    /// doesn't open or doesn't verify, its id is listed in the `failed` of the report and no requests are returned, so
+    /// the vault is left unchanged under `old_key`.  Otherwise the `WriteRequest`s overwrite the payloads, the metadata
+    /// and the versions and add the new transactions and the `DeleteRequest`s remove the old transactions; apply them in a single storage
+    /// transaction, writes first.
+    ///
+    /// Fails with `Error::RekeyError` if `old_key` isn't the key of the view or equals `new_key`.
+    #[allow(clippy::type_complexity)]
+    pub fn rekey(
+        &self,
+        old_key: &Key<P>,
+        new_key: &Key<P>,
+        list: &ListResult,
+        mut read: impl FnMut(ReadRequest) -> Option<ReadResult>,
+    ) -> crate::Result<(RekeyReport<Id>, Vec<WriteRequest>, Vec<DeleteRequest>)> {
+        let start = Instant::now();
+        if old_key != &self.key {
+            return Err(crate::Error::RekeyError(String::from(
+                "The old key isn't the key of the view",
+            )));
+        }
+        if old_key == new_key {
+            return Err(crate::Error::RekeyError(String::from("The new key equals the old key")));
+        }
+
+        // re-seal the payloads of the valid records
+        let mut to_write = Vec::new();
+        let mut failed = Vec::new();
+        for record in self.valid.all() {
+            let id = record.force_typed::<DataTransaction>().id;
+            // the metadata and the versions are sealed under the key as well
+            let meta = meta::rekey_meta(old_key, new_key, &self.ad, id, read(meta::read_request(id))).ok();
+            let versions = versions::rekey_versions(old_key, new_key, &self.ad, id, list, &mut read).ok();
+            let payload = match read(ReadRequest::payload::<P>(id)) {
+                Some(res) => Self::rekey_payload(old_key, new_key, &self.ad.payload(id), res.data())
+                    .map(|payload| vec![WriteRequest::payload(id, payload)]),
+                // a versioned record has no payload
+                None => versions
+                    .as_ref()
+                    .filter(|versions| !versions.is_empty())
+                    .map(|_| Vec::new()),
+            };
+            let entries = payload
+                .zip(meta)
+                .zip(versions)
+                .map(|((payload, meta), versions)| payload.into_iter().chain(meta).chain(versions));
+            match entries {
+                Some(entries) => to_write.extend(entries),
+                None => failed.push(id),
+            }
+        }
+        let payloads = to_write.len();
+
+        // re-seal every transaction and delete the old ones
+        let mut to_delete = Vec::new();
+        if failed.is_empty() {
+            for (offset, record) in self.chain.all().enumerate() {
+                let sealed = Record::seal(new_key, &self.ad, record.transaction().clone());
+                match Record::try_open(new_key, &self.ad, sealed.sealed().as_ref(), offset) {
+                    Ok(Some(reopened)) if reopened.transaction().as_ref() == record.transaction().as_ref() => {
+                        to_write.push(sealed.write());
+                        to_delete.push(DeleteRequest::transaction(record.sealed()));
+                    }
+                    _ => failed.push(match record.typed::<InitTransaction>() {
+                        Some(init) => init.owner,
+                        None => record.force_uid(),
+                    }),
+                }
+            }
+        }
+
+        let rekeyed = match failed.is_empty() {
+            true => payloads + to_delete.len(),
+            false => {
+                to_write.clear();
+                to_delete.clear();
+                0
+            }
+        };
+        let report = RekeyReport {
+            rekeyed,
+            failed,
+            elapsed: start.elapsed(),
+        };
+        record_rekey(&report);
+        Ok((report, to_write, to_delete))
+    }
+
+    /// re-seal the payload `data` from `old_key` to `new_key` and open it again, `None` if it doesn't open.  Expired
+    /// payloads are re-keyed too, keeping their expiry.
+    fn rekey_payload(old_key: &Key<P>, new_key: &Key<P>, ad: &[u8], data: &[u8]) -> Option<SealedPayload> {
+        let (mut plain, expires_at) = open_expiring(old_key, ad, data).ok()?;
+        let payload = seal_payload(new_key, ad, &plain, expires_at).ok();
+        let payload = payload.filter(|payload| {
+            open_expiring(new_key, ad, payload.as_ref()).is_ok_and(|(mut reopened, reopened_at)| {
+                let same = ct_eq(&reopened, &plain) && reopened_at == expires_at;
+                reopened.zeroize();
+                same
+            })
+        });
+        plain.zeroize();
+        payload
+    }
+
+    /// The ids of the valid records which are expired under `policy`.  The payloads are read with `read` and opened to
+    /// authenticate their expiry, payloads which are missing or don't open are skipped.
+    pub fn expired(&self, mut read: impl FnMut(ReadRequest) -> Option<ReadResult>, policy: &ExpiryPolicy) -> Vec<Id> {
+        self.valid
+            .all()
+            .map(|record| record.force_typed::<DataTransaction>().id)
+            .filter(|id| {
+                read(ReadRequest::payload::<P>(*id))
+                    .and_then(|res| payload_expiry(&self.key, &self.ad.payload(*id), res.data()).ok())
+                    .flatten()
+                    .is_some_and(|expires_at| policy.is_expired(expires_at))
+            })
+            .collect()
+    }
+
     /// Converts the `DBView` into a `DBReader`.
     pub fn reader(&self) -> DBReader<P> {
-        DBReader { view: self }
+        DBReader {
+            view: self,
+            expiry: ExpiryPolicy::default(),
+        }
     }
 
     /// Converts the `DBView` into a `DBWriter`.  Requires the owner's id as the `owned_chain`.
@@ -123,22 +316,35 @@ impl<P: BoxProvider> DBView<P> {
 }
 
 impl<'a, P: BoxProvider> DBReader<'a, P> {
-    /// Prepare a record for reading. Create a `ReadRequest` to read the record with inputted `id`. Returns `None` if
-    /// there was no record for that ID
+    /// Check the expiry of records under `policy` instead of the system clock without skew tolerance.
+    pub fn with_expiry(mut self, policy: ExpiryPolicy<'a>) -> Self {
+        self.expiry = policy;
+        self
+    }
+
+    /// Prepare a record for reading. Create a `ReadRequest` to read the record with inputted `id`. Fails with
+    /// `Error::RecordNotFound` if there is no valid record for that ID
     pub fn prepare_read(&self, id: Id) -> crate::Result<ReadRequest> {
         match self.view.valid.get(&id) {
             Some(_) => Ok(ReadRequest::payload::<P>(id)),
-            _ => Err(crate::Error::InterfaceError),
+            _ => Err(crate::Error::RecordNotFound { id }),
         }
     }
 
-    /// Open a record given a `ReadResult`.  Returns a vector of bytes.
+    /// Open a record given a `ReadResult`.  Returns a vector of bytes.  Fails with `Error::InvalidEntry` if the id of
+    /// the result isn't a record id, with `Error::RecordNotFound` if there is no valid record for it and with
+    /// `Error::RecordExpired` if the record is expired, see `with_expiry`.
     pub fn read(&self, res: ReadResult) -> crate::Result<Vec<u8>> {
         // reverse lookup
-        let id = Id::load(res.id()).map_err(|_| crate::Error::InterfaceError)?;
+        let id = Id::load(res.id()).map_err(|_| crate::Error::InvalidEntry {
+            reason: format!(
+                "The read result has an id of `{}` bytes instead of a record id",
+                res.id().len()
+            ),
+        })?;
         match self.view.valid.get(&id) {
-            Some(e) => e.open_payload(&self.view.key, res.data()),
-            _ => Err(crate::Error::InterfaceError),
+            Some(e) => e.open_payload_in(&self.view.key, &self.view.ad, res.data(), &self.expiry),
+            _ => Err(crate::Error::RecordNotFound { id }),
         }
     }
 }
@@ -169,35 +375,85 @@ impl<P: BoxProvider> DBWriter<P> {
         // create transaction
         let transaction = DataTransaction::new(self.owner, ctr, id, hint);
         // create record
-        let record = Record::new(&self.view.key, transaction);
-        Ok((id, record.write_payload(&self.view.key, data)?))
+        let record = Record::seal(&self.view.key, &self.view.ad, transaction);
+        Ok((id, record.write_payload_in(&self.view.key, &self.view.ad, data, None)?))
+    }
+
+    /// Write the `data` to the chain like `write`, but the record expires at `expires_at`, to the second.  Reading it
+    /// after that fails with `Error::RecordExpired`.  Fails with `Error::InvalidEntry` if `expires_at` is before the
+    /// UNIX epoch.
+    pub fn write_expiring(
+        self,
+        data: &[u8],
+        hint: RecordHint,
+        expires_at: SystemTime,
+    ) -> crate::Result<(Id, Vec<WriteRequest>)> {
+        let id = Id::random::<P>()?;
+        let ctr = self.view.chain.force_last(&self.owner).ctr() + 1;
+
+        let transaction = DataTransaction::new(self.owner, ctr, id, hint);
+        let record = Record::seal(&self.view.key, &self.view.ad, transaction);
+        Ok((
+            id,
+            record.write_payload_in(&self.view.key, &self.view.ad, data, Some(expires_at))?,
+        ))
     }
 
     /// Revoke a record. Creates a revocation transaction for the given `id`.  Returns a `WriteRequest` and
-    /// a `DeleteRequest`
+    /// a `DeleteRequest`, fails with `Error::RecordNotFound` if there is no valid record for the `id`.
     pub fn revoke(self, id: Id) -> crate::Result<(WriteRequest, DeleteRequest)> {
         // check if id is still valid and get counter
         let start_ctr = match self.view.valid.get(&id) {
             Some(_) => self.view.chain.force_last(&self.owner).ctr() + 1,
-            _ => return Err(crate::Error::InterfaceError),
+            _ => return Err(crate::Error::RecordNotFound { id }),
         };
 
         // generate transaction
         let transaction = RevocationTransaction::new(self.owner, start_ctr, id);
         // generate record
-        let to_write = Record::new(&self.view.key, transaction).write();
+        let to_write = Record::seal(&self.view.key, &self.view.ad, transaction).write();
         // create delete request
         let to_delete = DeleteRequest::uid(id);
         Ok((to_write, to_delete))
     }
 
+    /// Revoke the records of this chain which are expired under `policy`, see `DBView::expired`.  Returns the
+    /// `WriteRequest`s of the revocations and the `DeleteRequest`s of the payloads.
+    pub fn sweep(
+        self,
+        read: impl FnMut(ReadRequest) -> Option<ReadResult>,
+        policy: &ExpiryPolicy,
+    ) -> crate::Result<(Vec<WriteRequest>, Vec<DeleteRequest>)> {
+        let own: Vec<Id> = self
+            .view
+            .valid
+            .all_for_owner(&self.owner)
+            .map(|record| record.force_typed::<DataTransaction>().id)
+            .collect();
+        let start_ctr = self.view.chain.force_last(&self.owner).ctr() + 1;
+
+        let mut to_write = Vec::new();
+        let mut to_delete = Vec::new();
+        for id in self
+            .view
+            .expired(read, policy)
+            .into_iter()
+            .filter(|id| own.contains(id))
+        {
+            let transaction = RevocationTransaction::new(self.owner, start_ctr + to_write.len() as u64, id);
+            to_write.push(Record::seal(&self.view.key, &self.view.ad, transaction).write());
+            to_delete.push(DeleteRequest::uid(id));
+        }
+        Ok((to_write, to_delete))
+    }
+
     /// Garbage Collect the records of a chain. create a new `InitTransaction` for an owned chain.  Returns
     /// `WriteRequests` and `DeleteRequests` of that chain.
     pub fn gc(self) -> crate::Result<(Vec<WriteRequest>, Vec<DeleteRequest>)> {
         // create InitTransaction
         let start_ctr = self.view.chain.force_last(&self.owner).ctr() + 1;
         let start = InitTransaction::new(self.owner, start_ctr);
-        let mut to_write = vec![Record::new(&self.view.key, start).write()];
+        let mut to_write = vec![Record::seal(&self.view.key, &self.view.ad, start).write()];
 
         // locate revocation transactions
         let revoked: HashMap<_, _> = self.view.chain.own_revoked(&self.owner).collect();
@@ -209,7 +465,7 @@ impl<P: BoxProvider> DBWriter<P> {
 
                 // update transaction and create transaction
                 view.ctr = start_ctr + to_write.len() as u64;
-                to_write.push(Record::new(&self.view.key, transaction).write())
+                to_write.push(Record::seal(&self.view.key, &self.view.ad, transaction).write())
             }
         }
 
@@ -221,7 +477,7 @@ impl<P: BoxProvider> DBWriter<P> {
             view.ctr = start_ctr + to_write.len() as u64;
 
             // create the transaction
-            to_write.push(Record::new(&self.view.key, transaction).write());
+            to_write.push(Record::seal(&self.view.key, &self.view.ad, transaction).write());
         }
         // move init transaction to end.  Keeps the old chain valid until the new InitTransaction is written.
         to_write.rotate_left(1);
@@ -252,7 +508,7 @@ impl<P: BoxProvider> DBWriter<P> {
             if let Some(record) = revoked.get(&data.force_uid()) {
                 let this_ctr = this_ctr + to_write.len() as u64;
                 let transaction = RevocationTransaction::new(self.owner, this_ctr, record.force_uid());
-                to_write.push(Record::new(&self.view.key, transaction).write())
+                to_write.push(Record::seal(&self.view.key, &self.view.ad, transaction).write())
             }
         }
 
@@ -261,12 +517,12 @@ impl<P: BoxProvider> DBWriter<P> {
             let this_ctr = this_ctr + to_write.len() as u64;
             let record = record.force_typed::<DataTransaction>();
             let transaction = DataTransaction::new(self.owner, this_ctr, record.id, record.record_hint);
-            to_write.push(Record::new(&self.view.key, transaction).write());
+            to_write.push(Record::seal(&self.view.key, &self.view.ad, transaction).write());
         }
 
         // create an InitTransaction
         let other_start_transaction = InitTransaction::new(*other, other_ctr);
-        to_write.push(Record::new(&self.view.key, other_start_transaction).write());
+        to_write.push(Record::seal(&self.view.key, &self.view.ad, other_start_transaction).write());
 
         // delete the old transactions
         let mut to_delete = Vec::new();
@@ -0,0 +1,282 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+use crate::{
+    crypto_box::{BoxProvider, SharedKey},
+    types::{
+        transactions::{DataTransaction, InitTransaction, RevocationTransaction},
+        utils::{Id, RecordHint, Val},
+    },
+    vault::{
+        meta,
+        namespace::RecordAd,
+        policy::{self, AccessContext, AllowAll, Claims, Operation, Policy},
+        record::{ChainRecord, ValidRecord},
+        DBView, DeleteRequest, ExpiryPolicy, ReadRequest, Record, RecordMeta, Store, WriteRequest,
+    },
+};
+
+use std::{
+    sync::{RwLock, RwLockReadGuard, RwLockWriteGuard},
+    time::SystemTime,
+};
+
+/// the claims of the methods of the vault itself
+static NO_CLAIMS: Claims = Claims::new();
+
+/// A vault handle for many threads: the read methods run in parallel with each other and the write methods take
+/// exclusive access, all of them through `&self`.  Share it with an `Arc`, the key is a `SharedKey` whose bytes
+/// aren't copied for other threads.
+///
+/// The records are written to the chain of `owner`.  A read copies the record and its ciphertext from the store
+/// under the read lock and opens it after releasing the lock, so it never sees a payload which is being written.
+///
+/// Every operation is checked by the `Policy` of the vault before the key is used, see `with_policy`.  The methods
+/// of the vault have no claims, `with_claims` runs them with the claims of the caller.
+pub struct ConcurrentVault<P: BoxProvider, S: Store> {
+    key: SharedKey<P>,
+    owner: Id,
+    state: RwLock<State<P, S>>,
+    policy: Box<dyn Policy + Send + Sync>,
+}
+
+struct State<P: BoxProvider, S: Store> {
+    store: S,
+    view: DBView<P>,
+}
+
+/// The operations of a `ConcurrentVault` on behalf of a caller with `Claims`, see `ConcurrentVault::with_claims`.
+pub struct VaultAccess<'a, P: BoxProvider, S: Store> {
+    vault: &'a ConcurrentVault<P, S>,
+    claims: &'a Claims,
+}
+
+impl<P: BoxProvider, S: Store> ConcurrentVault<P, S> {
+    /// Opens the `store` sealed with `key`, and creates the chain of `owner` if there is none.  The vault allows
+    /// everything until a policy is set with `with_policy`.
+    pub fn open(key: SharedKey<P>, owner: Id, mut store: S) -> crate::Result<Self> {
+        let mut view = DBView::load(key.key().try_clone()?, store.list()?)?;
+        if view.chain.get(&owner).is_none() {
+            let init = Record::new(key.key(), InitTransaction::new(owner, Val::from(0u64)));
+            store.write(init.write())?;
+            insert(&mut view, Some(init))?;
+        }
+        Ok(Self {
+            key,
+            owner,
+            state: RwLock::new(State { store, view }),
+            policy: Box::new(AllowAll),
+        })
+    }
+
+    /// checks every operation with `policy` instead of allowing everything
+    pub fn with_policy(mut self, policy: impl Policy + Send + Sync + 'static) -> Self {
+        self.policy = Box::new(policy);
+        self
+    }
+
+    /// the operations of the vault on behalf of a caller with `claims`, which the policy of the vault decides on
+    pub fn with_claims<'a>(&'a self, claims: &'a Claims) -> VaultAccess<'a, P, S> {
+        VaultAccess { vault: self, claims }
+    }
+
+    /// the key of the vault
+    pub fn key(&self) -> &SharedKey<P> {
+        &self.key
+    }
+
+    /// the owner of the chain
+    pub fn owner(&self) -> Id {
+        self.owner
+    }
+
+    /// The ids and hints of the valid records the policy allows to list without claims.
+    pub fn records(&self) -> Vec<(Id, RecordHint)> {
+        self.with_claims(&NO_CLAIMS).records()
+    }
+
+    /// The ids, hints and metadata of the valid records the policy allows to list without claims, see
+    /// `VaultAccess::list_with_meta`.
+    #[allow(clippy::type_complexity)]
+    pub fn list_with_meta(&self) -> crate::Result<Vec<(Id, RecordHint, Option<RecordMeta>)>> {
+        self.with_claims(&NO_CLAIMS).list_with_meta()
+    }
+
+    /// Reads the record `id` without claims, see `VaultAccess::read`.
+    pub fn read(&self, id: Id) -> crate::Result<Vec<u8>> {
+        self.with_claims(&NO_CLAIMS).read(id)
+    }
+
+    /// Writes `data` as a new record without claims and returns its id, see `VaultAccess::write`.
+    pub fn write(&self, data: &[u8], hint: RecordHint) -> crate::Result<Id> {
+        self.with_claims(&NO_CLAIMS).write(data, hint)
+    }
+
+    /// Overwrites the payload of the record `id` with `data` without claims, see `VaultAccess::overwrite`.
+    pub fn overwrite(&self, id: Id, data: &[u8]) -> crate::Result<()> {
+        self.with_claims(&NO_CLAIMS).overwrite(id, data)
+    }
+
+    /// Revokes the record `id` without claims, see `VaultAccess::revoke`.
+    pub fn revoke(&self, id: Id) -> crate::Result<()> {
+        self.with_claims(&NO_CLAIMS).revoke(id)
+    }
+
+    /// the store of the vault
+    pub fn into_store(self) -> S {
+        self.state.into_inner().unwrap_or_else(|e| e.into_inner()).store
+    }
+
+    // the state is consistent after a panic of another thread, the store applies each request atomically and the
+    // view is only updated after the store
+    fn read_state(&self) -> RwLockReadGuard<'_, State<P, S>> {
+        self.state.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn write_state(&self) -> RwLockWriteGuard<'_, State<P, S>> {
+        self.state.write().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl<'a, P: BoxProvider, S: Store> VaultAccess<'a, P, S> {
+    /// the claims of the caller
+    pub fn claims(&self) -> &Claims {
+        self.claims
+    }
+
+    /// The ids and hints of the valid records the policy allows to list.
+    pub fn records(&self) -> Vec<(Id, RecordHint)> {
+        let state = self.vault.read_state();
+        let ad = &state.view.ad;
+        state
+            .view
+            .records()
+            .filter(|(id, _)| self.check(ad, *id, Operation::List).is_ok())
+            .collect()
+    }
+
+    /// The ids, hints and metadata of the valid records the policy allows to list, records without metadata have
+    /// `None`.  Only the metadata of these records is opened, never the payloads.  Fails if metadata doesn't open.
+    #[allow(clippy::type_complexity)]
+    pub fn list_with_meta(&self) -> crate::Result<Vec<(Id, RecordHint, Option<RecordMeta>)>> {
+        let state = self.vault.read_state();
+        let ad = &state.view.ad;
+        state
+            .view
+            .records()
+            .filter(|(id, _)| self.check(ad, *id, Operation::List).is_ok())
+            .map(|(id, hint)| {
+                let meta = match state.store.read(meta::read_request(id))? {
+                    Some(res) => Some(meta::open_meta(self.vault.key.key(), ad, id, res.data())?),
+                    None => None,
+                };
+                Ok((id, hint, meta))
+            })
+            .collect()
+    }
+
+    /// Reads the record `id`.  Fails with `Error::AccessDenied` if the policy denies it, with
+    /// `Error::RecordNotFound` if there is no valid record for the `id` or its payload is missing, and with
+    /// `Error::RecordExpired` if it expired by the system clock.
+    pub fn read(&self, id: Id) -> crate::Result<Vec<u8>> {
+        let (record, ad, payload) = {
+            let state = self.vault.read_state();
+            self.check(&state.view.ad, id, Operation::Read)?;
+            let record = state.view.valid.get(&id).cloned();
+            let record = record.ok_or(crate::Error::RecordNotFound { id })?;
+            let payload = state.store.read(ReadRequest::payload::<P>(id))?;
+            let payload = payload.ok_or(crate::Error::RecordNotFound { id })?;
+            (record, state.view.ad.clone(), payload)
+        };
+        record.open_payload_in(self.vault.key.key(), &ad, payload.data(), &ExpiryPolicy::default())
+    }
+
+    /// Writes `data` as a new record and returns its id.  Fails with `Error::AccessDenied` if the policy denies
+    /// writing the new record.
+    pub fn write(&self, data: &[u8], hint: RecordHint) -> crate::Result<Id> {
+        let (key, owner) = (self.vault.key.key(), self.vault.owner);
+        let mut state = self.vault.write_state();
+        let id = Id::random::<P>()?;
+        self.check(&state.view.ad, id, Operation::Write)?;
+        let ctr = state.view.chain.force_last(&owner).ctr() + 1;
+        let record = Record::seal(key, &state.view.ad, DataTransaction::new(owner, ctr, id, hint));
+        let requests = record.write_payload_in(key, &state.view.ad, data, None)?;
+        apply(&mut state, requests, None)?;
+        insert(&mut state.view, Some(record))?;
+        Ok(id)
+    }
+
+    /// Overwrites the payload of the record `id` with `data`, without expiry, the record keeps its id and hint.  The
+    /// `modified` time of its metadata, if it has any, is set to now.  Fails with `Error::AccessDenied` if the
+    /// policy denies writing the record and with `Error::RecordNotFound` if there is no valid record for the `id`.
+    pub fn overwrite(&self, id: Id, data: &[u8]) -> crate::Result<()> {
+        let key = self.vault.key.key();
+        let mut state = self.vault.write_state();
+        self.check(&state.view.ad, id, Operation::Write)?;
+        let record = state.view.valid.get(&id).cloned();
+        let record = record.ok_or(crate::Error::RecordNotFound { id })?;
+        let mut requests = record.write_payload_in(key, &state.view.ad, data, None)?;
+        let current = state.store.read(meta::read_request(id))?;
+        requests.extend(meta::touch(key, &state.view.ad, id, SystemTime::now(), current)?);
+        apply(&mut state, requests, None)
+    }
+
+    /// Revokes the record `id` and deletes its payload.  Fails with `Error::AccessDenied` if the policy denies
+    /// deleting the record and with `Error::RecordNotFound` if there is no valid record for the `id`.
+    pub fn revoke(&self, id: Id) -> crate::Result<()> {
+        let (key, owner) = (self.vault.key.key(), self.vault.owner);
+        let mut state = self.vault.write_state();
+        self.check(&state.view.ad, id, Operation::Delete)?;
+        if state.view.valid.get(&id).is_none() {
+            return Err(crate::Error::RecordNotFound { id });
+        }
+        let ctr = state.view.chain.force_last(&owner).ctr() + 1;
+        let record = Record::seal(key, &state.view.ad, RevocationTransaction::new(owner, ctr, id));
+        apply(&mut state, vec![record.write()], Some(DeleteRequest::uid(id)))?;
+        insert(&mut state.view, Some(record))
+    }
+
+    /// fails with `Error::AccessDenied` if the policy denies the `operation` on the record `id`
+    fn check(&self, ad: &RecordAd, id: Id, operation: Operation) -> crate::Result<()> {
+        let ctx = AccessContext {
+            record: id,
+            namespace: ad.namespace(),
+            operation,
+            claims: self.claims,
+            timestamp: SystemTime::now(),
+        };
+        policy::check(self.vault.policy.as_ref(), &ctx)
+    }
+}
+
+fn apply<P: BoxProvider, S: Store>(
+    state: &mut State<P, S>,
+    to_write: Vec<WriteRequest>,
+    to_delete: Option<DeleteRequest>,
+) -> crate::Result<()> {
+    for request in to_write {
+        state.store.write(request)?;
+    }
+    match to_delete {
+        Some(request) => state.store.delete(request),
+        None => Ok(()),
+    }
+}
+
+/// adds the `records` written to the store to the view, without opening the others again
+pub(super) fn insert<P: BoxProvider>(
+    view: &mut DBView<P>,
+    records: impl IntoIterator<Item = Record>,
+) -> crate::Result<()> {
+    view.chain = ChainRecord::new(view.chain.all().cloned().chain(records))?;
+    view.valid = ValidRecord::new(&view.chain);
+    Ok(())
+}
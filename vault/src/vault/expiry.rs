@@ -0,0 +1,183 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+use crate::{
+    crypto_box::{AssociatedData, BoxProvider, Decrypt, Encrypt, Key},
+    types::transactions::SealedPayload,
+};
+
+use std::{
+    convert::TryInto,
+    fmt::{self, Debug, Formatter},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use zeroize::Zeroize;
+
+/// the tag in front of an expiring payload
+const EXPIRING_TAG: u8 = 0xe5;
+/// the length of the expiry after the tag of an expiring payload
+const EXPIRY_LEN: usize = 8;
+
+/// A source of the current time, so tests can fast-forward it.
+pub trait Clock {
+    /// the current time
+    fn now(&self) -> SystemTime;
+}
+
+/// The `Clock` of the system, `SystemTime::now`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// How expiring records are checked: against the time of `clock`, and only once it is past the expiry by more than
+/// `skew_tolerance`.  The default is the `SystemClock` without tolerance.
+#[derive(Clone, Copy)]
+pub struct ExpiryPolicy<'a> {
+    /// the clock the expiries are compared to
+    pub clock: &'a dyn Clock,
+    /// how long a record stays readable after its expiry, to tolerate clocks which are ahead
+    pub skew_tolerance: Duration,
+}
+
+impl Default for ExpiryPolicy<'_> {
+    fn default() -> Self {
+        Self {
+            clock: &SystemClock,
+            skew_tolerance: Duration::ZERO,
+        }
+    }
+}
+
+impl Debug for ExpiryPolicy<'_> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_struct("ExpiryPolicy")
+            .field("now", &self.clock.now())
+            .field("skew_tolerance", &self.skew_tolerance)
+            .finish()
+    }
+}
+
+impl ExpiryPolicy<'_> {
+    /// checks if a record of `expires_at` is expired
+    pub fn is_expired(&self, expires_at: SystemTime) -> bool {
+        match self.clock.now().duration_since(expires_at) {
+            Ok(past) => past > self.skew_tolerance,
+            Err(_) => false,
+        }
+    }
+}
+
+/// the expiry in whole seconds since the UNIX epoch, fails with `Error::InvalidEntry` before the epoch
+fn expiry_secs(expires_at: SystemTime) -> crate::Result<u64> {
+    expires_at
+        .duration_since(UNIX_EPOCH)
+        .map(|since| since.as_secs())
+        .map_err(|_| crate::Error::InvalidEntry {
+            reason: String::from("The expiry is before the UNIX epoch"),
+        })
+}
+
+/// the AD of a payload with the AD `ad` expiring at `secs`
+fn expiry_ad(ad: &[u8], secs: [u8; EXPIRY_LEN]) -> Vec<u8> {
+    AssociatedData::new("vault expiring payload")
+        .field("record", ad)
+        .field("expiry", &secs)
+        .finish()
+}
+
+/// seal a payload with the AD `ad`, the id of the record or the `RecordAd::payload` of its namespace.  A payload
+/// without expiry is the box of `data` with `ad`, an expiring one is `EXPIRING_TAG`, the big endian seconds of the
+/// expiry since the UNIX epoch and the box of `data` with `ad` and the seconds in the AD, so the expiry can't be
+/// stripped or changed without failing to open.
+pub(crate) fn seal_payload<P: BoxProvider>(
+    key: &Key<P>,
+    ad: &[u8],
+    data: &[u8],
+    expires_at: Option<SystemTime>,
+) -> crate::Result<SealedPayload> {
+    let mut data = data.to_vec();
+    let sealed = match expires_at {
+        None => data.encrypt(key, ad),
+        Some(expires_at) => {
+            let secs = expiry_secs(expires_at)?.to_be_bytes();
+            data.encrypt(key, &expiry_ad(ad, secs)).map(|boxx: SealedPayload| {
+                let mut payload = vec![EXPIRING_TAG];
+                payload.extend_from_slice(&secs);
+                payload.extend_from_slice(boxx.as_ref());
+                SealedPayload::from(payload)
+            })
+        }
+    };
+    data.zeroize();
+    sealed
+}
+
+/// open a payload sealed with `ad` and return the data and the expiry.  A payload starting with `EXPIRING_TAG` is
+/// opened as an expiring one, others as payloads without expiry.  The box of a payload without expiry starts with
+/// the tag by chance too, so it is opened without expiry if it doesn't open as an expiring one.
+pub(crate) fn open_expiring<P: BoxProvider>(
+    key: &Key<P>,
+    ad: &[u8],
+    data: &[u8],
+) -> crate::Result<(Vec<u8>, Option<SystemTime>)> {
+    let open_plain = || {
+        SealedPayload::from(data.to_vec())
+            .decrypt(key, ad)
+            .map(|plain| (plain, None))
+    };
+    let (secs, boxx) = match data.split_first() {
+        Some((&EXPIRING_TAG, rest)) if rest.len() >= EXPIRY_LEN => rest.split_at(EXPIRY_LEN),
+        _ => return open_plain(),
+    };
+
+    let secs: [u8; EXPIRY_LEN] = secs.try_into().expect("8 bytes");
+    match SealedPayload::from(boxx.to_vec()).decrypt(key, &expiry_ad(ad, secs)) {
+        Ok(opened) => {
+            let expires_at = UNIX_EPOCH + Duration::from_secs(u64::from_be_bytes(secs));
+            Ok((opened, Some(expires_at)))
+        }
+        Err(e) => open_plain().map_err(|_| e),
+    }
+}
+
+/// open a payload sealed by `seal_payload` with `ad`.  Fails with `Error::RecordExpired` if the payload is expired
+/// under `policy`, the data is wiped and not returned.
+pub(crate) fn open_payload<P: BoxProvider>(
+    key: &Key<P>,
+    ad: &[u8],
+    data: &[u8],
+    policy: &ExpiryPolicy,
+) -> crate::Result<Vec<u8>> {
+    match open_expiring(key, ad, data)? {
+        (mut plain, Some(expired_at)) if policy.is_expired(expired_at) => {
+            plain.zeroize();
+            Err(crate::Error::RecordExpired { expired_at })
+        }
+        (plain, _) => Ok(plain),
+    }
+}
+
+/// the authenticated expiry of a payload sealed with `ad`, `None` if it doesn't expire
+pub(crate) fn payload_expiry<P: BoxProvider>(
+    key: &Key<P>,
+    ad: &[u8],
+    data: &[u8],
+) -> crate::Result<Option<SystemTime>> {
+    let (mut plain, expires_at) = open_expiring(key, ad, data)?;
+    plain.zeroize();
+    Ok(expires_at)
+}
@@ -0,0 +1,572 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+//! A `Store` persisted in a single append-only file.
+//!
+//! The file is laid out as
+//!
+//! | bytes | field |
+//! |---|---|
+//! | 4 | the magic bytes `RCVS` |
+//! | 1 | the format version |
+//! | n | the frames of the writes and deletes, in the order they were applied |
+//!
+//! A frame is its kind, 1 for a write and 2 for a delete, the big endian `u32` lengths of its id and data, the id,
+//! the data and the first 8 bytes of the SHA-256 digest of everything before it in the frame.  Overwritten and
+//! deleted entries stay in the file until it is compacted, see `FileStore::compact`.
+//!
+//! The store is locked against other processes with an advisory lock of the file `<path>.lock` next to it, which
+//! holds the process id of an exclusive holder.  The store file itself isn't locked since a compaction replaces it.
+
+use crate::{
+    persist_hooks::{create, sync_dir},
+    vault::{DeleteRequest, ListResult, ReadRequest, ReadResult, Store, WriteRequest},
+};
+
+use std::{
+    collections::HashMap,
+    convert::TryInto,
+    ffi::OsString,
+    fs::{self, File, OpenOptions, TryLockError},
+    io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+    thread,
+    time::{Duration, Instant},
+};
+
+use sha2::{Digest, Sha256};
+
+/// the magic bytes starting a store file
+pub const MAGIC: [u8; 4] = *b"RCVS";
+/// the current format version
+pub const VERSION: u8 = 1;
+
+const HEADER_LEN: u64 = 5;
+const WRITE: u8 = 1;
+const DELETE: u8 = 2;
+/// the kind and the lengths of a frame
+const FRAME_HEADER_LEN: usize = 9;
+const CHECKSUM_LEN: usize = 8;
+/// the size of the zeros written over the old file after a compaction
+const WIPE_CHUNK: usize = 64 << 10;
+/// how long `FileStore::try_open_timeout` waits between attempts to take the lock
+const LOCK_RETRY: Duration = Duration::from_millis(10);
+
+/// The outcome of `FileStore::compact`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CompactReport {
+    /// the length of the file before the compaction
+    pub before: u64,
+    /// the length of the compacted file
+    pub after: u64,
+}
+
+/// When a `FileStore` compacts itself after a write or a delete: once the file is at least `min_len` bytes long and
+/// more than `max_ratio` times as long as the frames of its live entries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AutoCompact {
+    /// the length below which the file is never compacted
+    pub min_len: u64,
+    /// the largest ratio of the length of the file to the length of its live frames
+    pub max_ratio: u64,
+}
+
+/// The steps of `FileStore::compact`, see `FileStore::crash_before`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompactStep {
+    /// writing and syncing the compacted file next to the store
+    Write,
+    /// renaming the compacted file over the store
+    Swap,
+    /// overwriting the old file with zeros
+    Wipe,
+}
+
+/// How a `FileStore` locks its file against other stores, in this or another process.  The locks are advisory,
+/// they only keep out other `FileStore`s.  A lock is released when the store is dropped, also when a panic unwinds,
+/// and by the OS when the process dies, so the lock file left behind by a dead holder doesn't keep others out.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LockMode {
+    /// the store reads and writes the file, opening the file with any other store fails
+    #[default]
+    Exclusive,
+    /// the store only reads the file, any number of them can open it while opening it exclusively fails
+    Shared,
+}
+
+/// the position of the data of an entry in the file
+#[derive(Clone, Copy)]
+struct Entry {
+    offset: u64,
+    len: u32,
+    /// the length of the frame which wrote the entry
+    frame_len: u64,
+}
+
+/// A `Store` persisted in a single file, every write and delete is appended and synced before it returns.  A frame
+/// torn by a crash is dropped by the next `open`.  The file only grows, `compact` rewrites the live entries into a
+/// new file, or set `auto_compact`.  The store holds a lock of the file while it is open, see `LockMode`.
+pub struct FileStore {
+    path: PathBuf,
+    file: Mutex<File>,
+    /// the lock file, its lock is released when it is closed
+    _lock: File,
+    mode: LockMode,
+    entries: HashMap<Vec<u8>, Entry>,
+    len: u64,
+    live_len: u64,
+    auto_compact: Option<AutoCompact>,
+    #[cfg(feature = "test-utils")]
+    crash_before: Option<CompactStep>,
+}
+
+impl FileStore {
+    /// Opens the store file at `path` with an exclusive lock, or creates it if there is none.  A compaction which
+    /// didn't finish is removed, the store is the file it was compacting then.  Fails with `Error::VaultLocked` if
+    /// another store has the file open, with the process id of an exclusive holder where the platform lets it be
+    /// read, and with `Error::CorruptStore` if the file isn't a store or a frame before the last one is corrupt.
+    pub fn open(path: impl Into<PathBuf>) -> crate::Result<Self> {
+        Self::try_open_timeout(path, LockMode::Exclusive, Duration::ZERO)
+    }
+
+    /// Opens the store file at `path` like `open`, locked by `mode`.  A `LockMode::Shared` store neither creates
+    /// the file nor repairs it, a torn last frame is skipped, and its writes, deletes and compactions fail with an
+    /// `Error::Io` of `ErrorKind::PermissionDenied`.
+    pub fn open_with(path: impl Into<PathBuf>, mode: LockMode) -> crate::Result<Self> {
+        Self::try_open_timeout(path, mode, Duration::ZERO)
+    }
+
+    /// Opens the store file at `path` like `open_with`, waiting up to `timeout` for other stores to release the
+    /// lock.  Fails with `Error::VaultLocked` if they still hold it then.
+    pub fn try_open_timeout(path: impl Into<PathBuf>, mode: LockMode, timeout: Duration) -> crate::Result<Self> {
+        let path = path.into();
+        let lock = lock(&path, mode, timeout)?;
+        let mut file = match mode {
+            LockMode::Exclusive => {
+                match fs::remove_file(compact_path(&path)?) {
+                    Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e.into()),
+                    _ => {}
+                }
+                if !path.exists() {
+                    let mut file = create(&path)?;
+                    file.write_all(&MAGIC)?;
+                    file.write_all(&[VERSION])?;
+                    file.sync_all()?;
+                    sync_dir(&path)?;
+                }
+                OpenOptions::new().read(true).write(true).open(&path)?
+            }
+            LockMode::Shared => File::open(&path)?,
+        };
+
+        let (entries, len) = scan(&mut file)?;
+        if mode == LockMode::Exclusive && len < file.metadata()?.len() {
+            // drop the frame torn by a crash, the next write would follow it otherwise
+            file.set_len(len)?;
+            file.sync_all()?;
+        }
+        let live_len = entries.values().map(|entry| entry.frame_len).sum();
+        Ok(Self {
+            path,
+            file: Mutex::new(file),
+            _lock: lock,
+            mode,
+            entries,
+            len,
+            live_len,
+            auto_compact: None,
+            #[cfg(feature = "test-utils")]
+            crash_before: None,
+        })
+    }
+
+    /// the path of the store file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// how the store locks its file
+    pub fn lock_mode(&self) -> LockMode {
+        self.mode
+    }
+
+    /// the length of the store file
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// whether the store has no entries
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// the length of the frames of the live entries, the length of the file after a compaction without the header
+    pub fn live_len(&self) -> u64 {
+        self.live_len
+    }
+
+    /// compact the store by `policy` after every write and delete, `None` only compacts it by `compact`
+    pub fn auto_compact(&mut self, policy: Option<AutoCompact>) {
+        self.auto_compact = policy;
+    }
+
+    /// Rewrites the live entries contiguously, in the order they were written, into a file next to the store, syncs
+    /// it and renames it over the store.  The old file is overwritten with zeros through its handle afterwards.  The
+    /// ids and the data of the entries are copied as they are.
+    ///
+    /// The rename is atomic, a crash leaves either the old or the compacted file at `path`; `open` removes a
+    /// compacted file which wasn't swapped in.  If the process dies after the rename the old file isn't wiped, its
+    /// blocks are freed by the filesystem as they are.
+    pub fn compact(&mut self) -> crate::Result<CompactReport> {
+        self.writable()?;
+        let before = self.len;
+        self.step(CompactStep::Write)?;
+        let tmp = compact_path(&self.path)?;
+        let (file, entries, len) = match self.write_compacted(&tmp) {
+            Ok(compacted) => compacted,
+            Err(e) => {
+                let _ = fs::remove_file(&tmp);
+                return Err(e);
+            }
+        };
+
+        self.step(CompactStep::Swap)?;
+        fs::rename(&tmp, &self.path)?;
+        sync_dir(&self.path)?;
+        let old = std::mem::replace(self.file.get_mut().unwrap_or_else(|e| e.into_inner()), file);
+        self.entries = entries;
+        self.len = len;
+        self.live_len = len - HEADER_LEN;
+
+        self.step(CompactStep::Wipe)?;
+        wipe(old, before)?;
+        Ok(CompactReport { before, after: len })
+    }
+
+    /// Stops the next compaction before `step` as if the process was killed, `compact` fails with
+    /// `Error::InterfaceErrorDetailed` and leaves the files as they are.  Open the store again afterwards.
+    #[cfg(feature = "test-utils")]
+    pub fn crash_before(&mut self, step: CompactStep) {
+        self.crash_before = Some(step);
+    }
+
+    /// writes the live entries to `tmp` and returns the synced file, its entries and its length
+    fn write_compacted(&mut self, tmp: &Path) -> crate::Result<(File, HashMap<Vec<u8>, Entry>, u64)> {
+        let mut live: Vec<(&Vec<u8>, &Entry)> = self.entries.iter().collect();
+        live.sort_by_key(|(_, entry)| entry.offset);
+
+        let mut out = BufWriter::new(create(tmp)?);
+        out.write_all(&MAGIC)?;
+        out.write_all(&[VERSION])?;
+        let mut entries = HashMap::with_capacity(live.len());
+        let mut len = HEADER_LEN;
+        let file = self.file.get_mut().unwrap_or_else(|e| e.into_inner());
+        for (id, entry) in live {
+            let data = read_at(file, entry)?;
+            let frame = frame(WRITE, id, &data);
+            out.write_all(&frame)?;
+            entries.insert(
+                id.clone(),
+                Entry {
+                    offset: len + (FRAME_HEADER_LEN + id.len()) as u64,
+                    len: entry.len,
+                    frame_len: frame.len() as u64,
+                },
+            );
+            len += frame.len() as u64;
+        }
+        let file = out.into_inner().map_err(|e| e.into_error())?;
+        file.sync_all()?;
+        drop(file);
+        let file = OpenOptions::new().read(true).write(true).open(tmp)?;
+        Ok((file, entries, len))
+    }
+
+    /// appends the frame of `kind` and syncs it
+    fn append(&mut self, kind: u8, id: &[u8], data: &[u8]) -> crate::Result<Entry> {
+        let frame = frame(kind, id, data);
+        let file = self.file.get_mut().unwrap_or_else(|e| e.into_inner());
+        file.seek(SeekFrom::Start(self.len))?;
+        file.write_all(&frame)?;
+        file.sync_data()?;
+        let entry = Entry {
+            offset: self.len + (FRAME_HEADER_LEN + id.len()) as u64,
+            len: data.len() as u32,
+            frame_len: frame.len() as u64,
+        };
+        self.len += frame.len() as u64;
+        Ok(entry)
+    }
+
+    fn writable(&self) -> crate::Result<()> {
+        match self.mode {
+            LockMode::Exclusive => Ok(()),
+            LockMode::Shared => Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!("The store `{}` is opened shared for reading", self.path.display()),
+            )
+            .into()),
+        }
+    }
+
+    fn maybe_compact(&mut self) -> crate::Result<()> {
+        if let Some(policy) = self.auto_compact {
+            if self.len >= policy.min_len && self.len > self.live_len.saturating_mul(policy.max_ratio) {
+                self.compact()?;
+            }
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "test-utils")]
+    fn step(&mut self, step: CompactStep) -> crate::Result<()> {
+        if self.crash_before == Some(step) {
+            self.crash_before = None;
+            return Err(crate::Error::InterfaceErrorDetailed(format!(
+                "Simulated crash before {:?}",
+                step
+            )));
+        }
+        Ok(())
+    }
+
+    #[cfg(not(feature = "test-utils"))]
+    fn step(&mut self, _step: CompactStep) -> crate::Result<()> {
+        Ok(())
+    }
+}
+
+impl Store for FileStore {
+    fn list(&self) -> crate::Result<ListResult> {
+        Ok(ListResult::new(self.entries.keys().cloned().collect()))
+    }
+
+    fn read(&self, request: ReadRequest) -> crate::Result<Option<ReadResult>> {
+        let id: Vec<u8> = request.into();
+        match self.entries.get(&id) {
+            Some(entry) => {
+                let mut file = self.file.lock().unwrap_or_else(|e| e.into_inner());
+                let data = read_at(&mut file, entry)?;
+                Ok(Some(ReadResult::new(id, data)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn write(&mut self, request: WriteRequest) -> crate::Result<()> {
+        self.writable()?;
+        let (id, data) = request.into();
+        if id.len() > u32::MAX as usize || data.len() > u32::MAX as usize {
+            return Err(crate::Error::PayloadTooLarge {
+                len: id.len().max(data.len()) as u64,
+                limit: u32::MAX as usize,
+            });
+        }
+        let entry = self.append(WRITE, &id, &data)?;
+        if let Some(old) = self.entries.insert(id, entry) {
+            self.live_len -= old.frame_len;
+        }
+        self.live_len += entry.frame_len;
+        self.maybe_compact()
+    }
+
+    fn delete(&mut self, request: DeleteRequest) -> crate::Result<()> {
+        self.writable()?;
+        let id: Vec<u8> = request.into();
+        if !self.entries.contains_key(&id) {
+            return Ok(());
+        }
+        self.append(DELETE, &id, &[])?;
+        if let Some(old) = self.entries.remove(&id) {
+            self.live_len -= old.frame_len;
+        }
+        self.maybe_compact()
+    }
+}
+
+fn corrupt(reason: &str) -> crate::Error {
+    crate::Error::CorruptStore(reason.into())
+}
+
+/// the path of the compacted file of the store at `path`
+fn compact_path(path: &Path) -> crate::Result<PathBuf> {
+    suffixed(path, ".compact")
+}
+
+/// the path of the lock file of the store at `path`
+fn lock_path(path: &Path) -> crate::Result<PathBuf> {
+    suffixed(path, ".lock")
+}
+
+/// the path of the file next to `path` with `suffix` after its name
+fn suffixed(path: &Path, suffix: &str) -> crate::Result<PathBuf> {
+    let mut name: OsString = path
+        .file_name()
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Invalid path `{}`", path.display()),
+            )
+        })?
+        .to_os_string();
+    name.push(suffix);
+    Ok(path.with_file_name(name))
+}
+
+/// Takes the lock of the store at `path` by `mode`, retrying until `timeout` passed.  An exclusive holder writes its
+/// process id to the lock file, a shared one clears it.
+fn lock(path: &Path, mode: LockMode, timeout: Duration) -> crate::Result<File> {
+    let lock_path = lock_path(path)?;
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(&lock_path)?;
+    let start = Instant::now();
+    loop {
+        let locked = match mode {
+            LockMode::Exclusive => file.try_lock(),
+            LockMode::Shared => file.try_lock_shared(),
+        };
+        match locked {
+            Ok(()) => break,
+            Err(TryLockError::Error(e)) => return Err(e.into()),
+            Err(TryLockError::WouldBlock) => {
+                let waited = start.elapsed();
+                if waited >= timeout {
+                    // the holder may be writing its id, or not allow reading it at all
+                    let holder_pid = fs::read_to_string(&lock_path)
+                        .ok()
+                        .and_then(|pid| pid.trim().parse().ok());
+                    return Err(crate::Error::VaultLocked { holder_pid });
+                }
+                thread::sleep(LOCK_RETRY.min(timeout - waited));
+            }
+        }
+    }
+
+    match mode {
+        LockMode::Exclusive => {
+            file.set_len(0)?;
+            file.write_all(std::process::id().to_string().as_bytes())?;
+        }
+        // the id in the file is of a holder which released the lock, other shared holders may keep it from being
+        // cleared on some platforms
+        LockMode::Shared => {
+            let _ = file.set_len(0);
+        }
+    }
+    Ok(file)
+}
+
+fn frame(kind: u8, id: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(FRAME_HEADER_LEN + id.len() + data.len() + CHECKSUM_LEN);
+    frame.push(kind);
+    frame.extend_from_slice(&(id.len() as u32).to_be_bytes());
+    frame.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    frame.extend_from_slice(id);
+    frame.extend_from_slice(data);
+    let checksum = Sha256::digest(&frame);
+    frame.extend_from_slice(&checksum[..CHECKSUM_LEN]);
+    frame
+}
+
+fn read_at(file: &mut File, entry: &Entry) -> crate::Result<Vec<u8>> {
+    let mut data = vec![0; entry.len as usize];
+    file.seek(SeekFrom::Start(entry.offset))?;
+    file.read_exact(&mut data)?;
+    Ok(data)
+}
+
+/// reads the frames of the store `file` and returns its entries and the length of its complete frames
+fn scan(file: &mut File) -> crate::Result<(HashMap<Vec<u8>, Entry>, u64)> {
+    let file_len = file.metadata()?.len();
+    let mut reader = BufReader::new(&mut *file);
+    let mut header = [0; HEADER_LEN as usize];
+    reader
+        .read_exact(&mut header)
+        .map_err(|_| corrupt("The file is too short for a store"))?;
+    if header[..4] != MAGIC {
+        return Err(corrupt("The file isn't a store"));
+    }
+    if header[4] != VERSION {
+        return Err(crate::Error::UnsupportedVersion(header[4]));
+    }
+
+    let mut entries = HashMap::new();
+    let mut len = HEADER_LEN;
+    loop {
+        let rest = file_len - len;
+        if rest == 0 {
+            break;
+        }
+        let mut frame_header = [0; FRAME_HEADER_LEN];
+        if rest < (FRAME_HEADER_LEN + CHECKSUM_LEN) as u64 {
+            break;
+        }
+        reader.read_exact(&mut frame_header)?;
+        let id_len = u32::from_be_bytes(frame_header[1..5].try_into().expect("4 bytes")) as u64;
+        let data_len = u32::from_be_bytes(frame_header[5..9].try_into().expect("4 bytes")) as u64;
+        let frame_len = FRAME_HEADER_LEN as u64 + id_len + data_len + CHECKSUM_LEN as u64;
+        if frame_len > rest {
+            // the last frame was torn
+            break;
+        }
+        let mut body = vec![0; (id_len + data_len) as usize];
+        reader.read_exact(&mut body)?;
+        let mut checksum = [0; CHECKSUM_LEN];
+        reader.read_exact(&mut checksum)?;
+
+        let digest = Sha256::new().chain_update(frame_header).chain_update(&body).finalize();
+        if digest[..CHECKSUM_LEN] != checksum {
+            if frame_len == rest {
+                // the last frame was torn
+                break;
+            }
+            return Err(corrupt("A frame of the store is corrupt"));
+        }
+        let id = body[..id_len as usize].to_vec();
+        match frame_header[0] {
+            WRITE => {
+                entries.insert(
+                    id,
+                    Entry {
+                        offset: len + FRAME_HEADER_LEN as u64 + id_len,
+                        len: data_len as u32,
+                        frame_len,
+                    },
+                );
+            }
+            DELETE => {
+                entries.remove(&id);
+            }
+            _ => return Err(corrupt("A frame of the store has an unknown kind")),
+        }
+        len += frame_len;
+    }
+    Ok((entries, len))
+}
+
+/// overwrites the `len` bytes of the old store `file` with zeros and syncs them
+fn wipe(mut file: File, len: u64) -> crate::Result<()> {
+    let zeros = vec![0; WIPE_CHUNK];
+    file.seek(SeekFrom::Start(0))?;
+    let mut left = len;
+    while left > 0 {
+        let n = left.min(WIPE_CHUNK as u64) as usize;
+        file.write_all(&zeros[..n])?;
+        left -= n as u64;
+    }
+    file.sync_all()?;
+    Ok(())
+}
@@ -0,0 +1,159 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+use crate::{
+    crypto_box::{BoxProvider, Key},
+    metrics,
+    types::{
+        transactions::{DataTransaction, SealedPayload},
+        utils::Id,
+    },
+    vault::{
+        expiry::open_expiring, index, meta, namespace::RecordAd, versions, DBView, DeleteRequest, ListResult,
+        ReadResult, Record, WriteRequest,
+    },
+};
+
+use std::{
+    collections::HashSet,
+    time::{Duration, Instant},
+};
+
+use zeroize::Zeroize;
+
+/// The outcome of `DBView::gc`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GcReport {
+    /// the bytes of the ids and the data of the removed blobs
+    pub reclaimed_bytes: u64,
+    /// the number of removed blobs
+    pub removed_blobs: usize,
+    /// the time the collection took
+    pub duration: Duration,
+}
+
+impl<P: BoxProvider> DBView<P> {
+    /// Collect the ciphertext of `key` which isn't reachable from any live record: transactions which aren't part of
+    /// a chain anymore, e.g. left behind by an interrupted `DBWriter::gc`, and payloads, versions, metadata and index
+    /// entries of revoked or unknown records.  `entries` are all entries of the store, the view is loaded from them so it
+    /// matches the store.  Blobs of other keys are never collected, nor are the blobs written by `in_flight` and the
+    /// payloads their transactions refer to.
+    ///
+    /// Returns `WriteRequest`s overwriting the collected payloads with zeros, for stores which overwrite in place, and
+    /// the `DeleteRequest`s of every collected blob; apply the writes first.  The store compacts the freed space
+    /// itself.  The collection relies on locking writes: hold the write lock of the store from reading `entries`
+    /// until the requests are applied.  Reads can go on, only blobs no live record refers to are removed.
+    #[allow(clippy::type_complexity)]
+    pub fn gc(
+        key: &Key<P>,
+        entries: &[ReadResult],
+        in_flight: &[WriteRequest],
+    ) -> crate::Result<(GcReport, Vec<WriteRequest>, Vec<DeleteRequest>)> {
+        Self::gc_in(key, &RecordAd::default(), entries, in_flight)
+    }
+
+    /// like `gc` for the records sealed with `ad`
+    #[allow(clippy::type_complexity)]
+    pub(crate) fn gc_in(
+        key: &Key<P>,
+        ad: &RecordAd,
+        entries: &[ReadResult],
+        in_flight: &[WriteRequest],
+    ) -> crate::Result<(GcReport, Vec<WriteRequest>, Vec<DeleteRequest>)> {
+        let start = Instant::now();
+        let ids = entries.iter().map(|entry| entry.id().to_vec()).collect();
+        let view = Self::load_in(key.try_clone()?, ad.clone(), ListResult::new(ids))?;
+
+        // the blobs of the chains and the payloads of the valid records
+        let mut live: HashSet<&[u8]> = view.chain.all().map(|record| record.sealed().as_ref()).collect();
+        let valid: Vec<Id> = view
+            .valid
+            .all()
+            .map(|record| record.force_typed::<DataTransaction>().id)
+            .collect();
+        live.extend(valid.iter().map(|id| id.as_ref()));
+
+        // the blobs of the transactions in flight and their payloads
+        let in_flight_payloads: Vec<Id> = in_flight
+            .iter()
+            .filter_map(|request| Record::try_open(key, ad, request.id(), 0).ok().flatten())
+            .filter_map(|record| Some(record.typed::<DataTransaction>()?.id))
+            .collect();
+        live.extend(in_flight.iter().map(|request| request.id()));
+        live.extend(in_flight_payloads.iter().map(|id| id.as_ref()));
+
+        let mut report = GcReport {
+            reclaimed_bytes: 0,
+            removed_blobs: 0,
+            duration: Duration::ZERO,
+        };
+        let mut to_write = Vec::new();
+        let mut to_delete = Vec::new();
+        for entry in entries.iter().filter(|entry| !live.contains(entry.id())) {
+            let payload = Id::load(entry.id()).ok().filter(|_| !entry.data().is_empty());
+            let collected = match (
+                index::record_of(entry.id()),
+                versions::parse_entry(entry.id()),
+                meta::record_of(entry.id()),
+                payload,
+            ) {
+                // an index entry of a record of the key, live as long as the record is valid
+                (Some(id), _, _, _) if valid.contains(&id) => None,
+                (Some(_), _, _, _) => index::open_entry(key, entry).map(|_| {
+                    to_write.push(WriteRequest::new(entry.id().to_vec(), vec![0; entry.data().len()]));
+                    DeleteRequest::new(entry.id().to_vec())
+                }),
+                // a version of a record of the key, live as long as the record is valid
+                (None, Some((id, _)), _, _) if valid.contains(&id) => None,
+                (None, Some((id, info)), _, _) => match versions::open_version(key, ad, id, info, entry.data()) {
+                    Ok(mut plain) => {
+                        plain.zeroize();
+                        to_write.push(WriteRequest::new(entry.id().to_vec(), vec![0; entry.data().len()]));
+                        Some(DeleteRequest::new(entry.id().to_vec()))
+                    }
+                    Err(_) => None,
+                },
+                // the metadata of a record of the key, live as long as the record is valid
+                (None, None, Some(id), _) if valid.contains(&id) => None,
+                (None, None, Some(id), _) => meta::open_meta(key, ad, id, entry.data()).ok().map(|_| {
+                    to_write.push(WriteRequest::new(entry.id().to_vec(), vec![0; entry.data().len()]));
+                    DeleteRequest::new(entry.id().to_vec())
+                }),
+                // a payload of the key
+                (None, None, None, Some(id)) => match open_expiring(key, &ad.payload(id), entry.data()) {
+                    Ok((mut plain, _)) => {
+                        plain.zeroize();
+                        let zeros = SealedPayload::from(vec![0; entry.data().len()]);
+                        to_write.push(WriteRequest::payload(id, zeros));
+                        Some(DeleteRequest::uid(id))
+                    }
+                    Err(_) => None,
+                },
+                // a transaction of the key
+                (None, None, None, None) => Record::try_open(key, ad, entry.id(), 0)
+                    .ok()
+                    .flatten()
+                    .map(|record| DeleteRequest::transaction(record.sealed())),
+            };
+            if let Some(request) = collected {
+                report.reclaimed_bytes += (entry.id().len() + entry.data().len()) as u64;
+                report.removed_blobs += 1;
+                to_delete.push(request);
+            }
+        }
+        report.duration = start.elapsed();
+        metrics::incr(metrics::GC_RUNS, 1);
+        metrics::incr(metrics::GC_REMOVED_BLOBS, report.removed_blobs as u64);
+        metrics::incr(metrics::GC_RECLAIMED_BYTES, report.reclaimed_bytes);
+        metrics::observe(metrics::GC_DURATION, report.duration);
+        Ok((report, to_write, to_delete))
+    }
+}
@@ -0,0 +1,219 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+use crate::{
+    crypto_box::{AssociatedData, BoxProvider, Decrypt, Encrypt, Key},
+    types::{
+        transactions::SealedPayload,
+        utils::{Id, RecordHint},
+    },
+    vault::{DBWriter, DeleteRequest, ListResult, ReadResult, WriteRequest},
+};
+
+use std::convert::TryInto;
+
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use zeroize::{Zeroize, Zeroizing};
+
+/// the HKDF info of the index key, which keeps it apart from the box key and from other derived keys
+const INDEX_KEY_INFO: &[u8] = b"vault blind index key";
+/// the prefix of the ids of the index entries
+const MARKER: &[u8] = b"index";
+/// the length of a record id
+const ID_LEN: usize = 24;
+
+/// A blind index of the records of a vault.  For each lookup field of a record an entry is stored whose id holds
+/// `HMAC(index_key, field, value)` truncated to `truncation` bytes and the id of the record, the `index_key` is
+/// derived from the vault key.  `find_by_hint` finds records by the value of a field from the ids of the store
+/// alone, nothing is decrypted.
+///
+/// Truncated HMACs collide: the records found are candidates, verify the value after decrypting them.  The shorter
+/// the truncation the more candidates, and the less the index reveals about which records share a value.
+pub struct BlindIndex<P: BoxProvider> {
+    key: Key<P>,
+    index_key: Zeroizing<[u8; 32]>,
+    truncation: usize,
+}
+
+impl<P: BoxProvider> BlindIndex<P> {
+    /// the longest truncation, the length of an HMAC-SHA256
+    pub const MAX_TRUNCATION: usize = 32;
+
+    /// Creates the index of the records sealed with `key`, its HMACs are truncated to `truncation` bytes.  Fails
+    /// with `Error::InterfaceErrorDetailed` if `truncation` is `0` or more than `MAX_TRUNCATION`.
+    pub fn new(key: &Key<P>, truncation: usize) -> crate::Result<Self> {
+        if truncation == 0 || truncation > Self::MAX_TRUNCATION {
+            return Err(crate::Error::InterfaceErrorDetailed(format!(
+                "The truncation must be between 1 and {} bytes, got {}",
+                Self::MAX_TRUNCATION,
+                truncation
+            )));
+        }
+        let mut index_key = Zeroizing::new([0; 32]);
+        Hkdf::<Sha256>::new(None, key.bytes())
+            .expand(INDEX_KEY_INFO, &mut index_key[..])
+            .map_err(|_| crate::Error::crypto("derive index key", "Unable to derive the index key"))?;
+        Ok(Self {
+            key: key.try_clone()?,
+            index_key,
+            truncation,
+        })
+    }
+
+    /// the length of the truncated HMACs
+    pub fn truncation(&self) -> usize {
+        self.truncation
+    }
+
+    /// The `WriteRequest`s of the index entries of the record `id` for the lookup `fields`, pairs of a field and its
+    /// value.  The data of an entry is the sealed field and value, so the index can be rebuilt by `rekey`.
+    pub fn index(&self, id: Id, fields: &[(&str, &[u8])]) -> crate::Result<Vec<WriteRequest>> {
+        fields
+            .iter()
+            .map(|(field, value)| {
+                let entry = self.entry_id(id, field, value);
+                let mut plain = encode(field, value);
+                let sealed: crate::Result<SealedPayload> = plain.encrypt(&self.key, &entry_ad(&entry));
+                plain.zeroize();
+                Ok(WriteRequest::new(entry, sealed?.as_ref().to_vec()))
+            })
+            .collect()
+    }
+
+    /// The ids of the records whose `field` may have the `value`, from the ids of the store in `list`.  Only the ids
+    /// are looked at, nothing is read or decrypted.  The ids are candidates: truncated HMACs collide, so verify the
+    /// value after decrypting the records.
+    pub fn find_by_hint(&self, field: &str, value: &[u8], list: &ListResult) -> Vec<Id> {
+        let prefix = self.entry_id(Id::load(&[0; ID_LEN]).expect("24 bytes"), field, value);
+        let prefix = &prefix[..prefix.len() - ID_LEN];
+        list.ids()
+            .iter()
+            .filter(|entry| entry.len() == prefix.len() + ID_LEN && entry.starts_with(prefix))
+            .filter_map(|entry| record_of(entry))
+            .collect()
+    }
+
+    /// The `DeleteRequest`s of the index entries of the record `id` among the ids of the store in `list`, of any
+    /// truncation.
+    pub fn remove(&self, id: Id, list: &ListResult) -> Vec<DeleteRequest> {
+        list.ids()
+            .iter()
+            .filter(|entry| record_of(entry) == Some(id))
+            .map(|entry| DeleteRequest::new(entry.clone()))
+            .collect()
+    }
+
+    /// Rebuild the index for `new_key`.  `entries` are the entries of the store, each index entry sealed with the key
+    /// of this index is opened and its field and value are indexed again under `new_key`, entries of other keys are
+    /// left alone.  Returns the index of `new_key`, the `WriteRequest`s of the new entries and the `DeleteRequest`s
+    /// of the old ones.  Rekey the index along with `DBView::rekey` and apply the requests of both in the same
+    /// storage transaction.
+    ///
+    /// Nothing is rebuilt if an entry opens but isn't an index entry, which fails with `Error::RekeyError`, as does
+    /// a `new_key` which equals the key of the index.
+    #[allow(clippy::type_complexity)]
+    pub fn rekey(
+        &self,
+        new_key: &Key<P>,
+        entries: &[ReadResult],
+    ) -> crate::Result<(BlindIndex<P>, Vec<WriteRequest>, Vec<DeleteRequest>)> {
+        if new_key == &self.key {
+            return Err(crate::Error::RekeyError(String::from("The new key equals the old key")));
+        }
+        let rekeyed = Self::new(new_key, self.truncation)?;
+
+        let mut to_write = Vec::new();
+        let mut to_delete = Vec::new();
+        for entry in entries {
+            let id = match record_of(entry.id()) {
+                Some(id) => id,
+                None => continue,
+            };
+            let plain = match open_entry(&self.key, entry) {
+                Some(plain) => plain,
+                None => continue,
+            };
+            let fields = decode(&plain)
+                .map(|(field, value)| rekeyed.index(id, &[(field, value)]))
+                .ok_or_else(|| crate::Error::RekeyError(String::from("An index entry is corrupt")))?;
+            to_write.extend(fields?);
+            to_delete.push(DeleteRequest::new(entry.id().to_vec()));
+        }
+        Ok((rekeyed, to_write, to_delete))
+    }
+
+    /// the id of the index entry of `field` with `value` of the record `id`
+    fn entry_id(&self, id: Id, field: &str, value: &[u8]) -> Vec<u8> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.index_key[..]).expect("HMAC accepts keys of any length");
+        let mut plain = encode(field, value);
+        mac.update(&plain);
+        plain.zeroize();
+        let mac = mac.finalize().into_bytes();
+        [MARKER, &mac[..self.truncation], id.as_ref()].concat()
+    }
+}
+
+/// the length of the field, the field and the value
+fn encode(field: &str, value: &[u8]) -> Vec<u8> {
+    [&(field.len() as u64).to_be_bytes(), field.as_bytes(), value].concat()
+}
+
+fn decode(plain: &[u8]) -> Option<(&str, &[u8])> {
+    let len = u64::from_be_bytes(plain.get(..8)?.try_into().ok()?);
+    let end = 8usize.checked_add(len.try_into().ok()?)?;
+    let field = std::str::from_utf8(plain.get(8..end)?).ok()?;
+    Some((field, &plain[end..]))
+}
+
+/// the AD of an index entry, binds its data to the id so it can't be moved to another field, value or record
+fn entry_ad(entry: &[u8]) -> Vec<u8> {
+    AssociatedData::new("vault index").field("entry", entry).finish()
+}
+
+/// the id of the record of an index entry of the store, `None` for other entries
+pub(crate) fn record_of(entry: &[u8]) -> Option<Id> {
+    if entry.len() <= MARKER.len() + ID_LEN || !entry.starts_with(MARKER) {
+        return None;
+    }
+    Id::load(&entry[entry.len() - ID_LEN..]).ok()
+}
+
+/// the field and value of an index entry sealed with `key`, `None` if it doesn't open with the key
+pub(crate) fn open_entry<P: BoxProvider>(key: &Key<P>, entry: &ReadResult) -> Option<Zeroizing<Vec<u8>>> {
+    SealedPayload::from(entry.data().to_vec())
+        .decrypt(key, &entry_ad(entry.id()))
+        .ok()
+        .map(Zeroizing::new)
+}
+
+impl<P: BoxProvider> DBWriter<P> {
+    /// Write the `data` to the chain like `write` and index the record for the lookup `fields` in `index`, see
+    /// `BlindIndex::index`.  Fails with `Error::InterfaceErrorDetailed` if `index` isn't the index of the key of
+    /// the view.
+    pub fn write_indexed(
+        self,
+        data: &[u8],
+        hint: RecordHint,
+        index: &BlindIndex<P>,
+        fields: &[(&str, &[u8])],
+    ) -> crate::Result<(Id, Vec<WriteRequest>)> {
+        if index.key != self.view.key {
+            return Err(crate::Error::InterfaceErrorDetailed(String::from(
+                "The index isn't the index of the key of the view",
+            )));
+        }
+        let (id, mut requests) = self.write(data, hint)?;
+        requests.extend(index.index(id, fields)?);
+        Ok((id, requests))
+    }
+}
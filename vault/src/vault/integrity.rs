@@ -0,0 +1,186 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+use crate::vault::{DeleteRequest, ReadResult, WriteRequest};
+
+use std::collections::BTreeMap;
+
+use sha2::{Digest, Sha256};
+
+/// the number of bits of the hash of an id which select its bucket
+const BUCKET_BITS: u32 = 10;
+/// the number of buckets, the leaves of the tree above them
+const BUCKETS: usize = 1 << BUCKET_BITS;
+
+/// A Merkle tree over the entries of a store, for a root which proves that no entry was modified, added or removed.
+///
+/// Each entry is a leaf of its id and the SHA-256 of its data, the ciphertext.  The leaves are grouped in buckets by
+/// the hash of their id and ordered by id within them, and a complete binary tree of the buckets hashes up to the
+/// root, so the root only depends on the entries and not on the order they were added in.  Updating an entry
+/// re-hashes its bucket and the path above it.
+///
+/// The root covers the ciphertext: writing a record again changes the root even if the plaintext is the same, as
+/// every seal uses a fresh nonce.
+#[derive(Clone)]
+pub struct IntegrityTree {
+    buckets: Vec<BTreeMap<Vec<u8>, [u8; 32]>>,
+    // the nodes of the complete binary tree, `nodes[1]` is the root and the buckets are the last `BUCKETS` nodes
+    nodes: Vec<[u8; 32]>,
+}
+
+impl IntegrityTree {
+    /// the tree of a store without entries
+    pub fn new() -> Self {
+        let empty = bucket_hash(&BTreeMap::new());
+        let mut nodes = vec![[0; 32]; 2 * BUCKETS];
+        for node in nodes[BUCKETS..].iter_mut() {
+            *node = empty;
+        }
+        let mut tree = Self {
+            buckets: vec![BTreeMap::new(); BUCKETS],
+            nodes,
+        };
+        tree.rehash_all();
+        tree
+    }
+
+    /// the tree of the `entries` of a store
+    pub fn from_entries(entries: &[ReadResult]) -> Self {
+        let mut tree = Self::new();
+        for entry in entries {
+            let (bucket, leaf) = leaf(entry.id(), entry.data());
+            tree.buckets[bucket].insert(entry.id().to_vec(), leaf);
+        }
+        for bucket in 0..BUCKETS {
+            tree.nodes[BUCKETS + bucket] = bucket_hash(&tree.buckets[bucket]);
+        }
+        tree.rehash_all();
+        tree
+    }
+
+    /// the root of the tree
+    pub fn root(&self) -> [u8; 32] {
+        self.nodes[1]
+    }
+
+    /// the number of entries
+    pub fn len(&self) -> usize {
+        self.buckets.iter().map(BTreeMap::len).sum()
+    }
+
+    /// whether the tree has no entries
+    pub fn is_empty(&self) -> bool {
+        self.buckets.iter().all(BTreeMap::is_empty)
+    }
+
+    /// updates the tree for the `request` applied to the store
+    pub fn write(&mut self, request: &WriteRequest) {
+        let (bucket, leaf) = leaf(request.id(), request.data());
+        self.buckets[bucket].insert(request.id().to_vec(), leaf);
+        self.rehash(bucket);
+    }
+
+    /// updates the tree for the `request` applied to the store
+    pub fn delete(&mut self, request: &DeleteRequest) {
+        let bucket = bucket_of(request.id());
+        if self.buckets[bucket].remove(request.id()).is_some() {
+            self.rehash(bucket);
+        }
+    }
+
+    /// The ids of the entries which differ between the trees: modified, or in only one of them.  Ordered by bucket
+    /// and id.
+    pub fn diff(&self, other: &IntegrityTree) -> Vec<Vec<u8>> {
+        let mut ids = Vec::new();
+        for bucket in 0..BUCKETS {
+            if self.nodes[BUCKETS + bucket] == other.nodes[BUCKETS + bucket] {
+                continue;
+            }
+            let (ours, theirs) = (&self.buckets[bucket], &other.buckets[bucket]);
+            ids.extend(
+                ours.iter()
+                    .filter(|(id, leaf)| theirs.get(*id) != Some(leaf))
+                    .map(|(id, _)| id.clone()),
+            );
+            ids.extend(theirs.keys().filter(|id| !ours.contains_key(*id)).cloned());
+        }
+        ids
+    }
+
+    /// Checks that the `entries` of the store match the tree and have the root `expected_root`.  Fails with
+    /// `Error::IntegrityMismatch` listing the ids of the entries which differ between the store and the tree, no ids
+    /// if they match but `expected_root` is another root.
+    pub fn verify(&self, expected_root: [u8; 32], entries: &[ReadResult]) -> crate::Result<()> {
+        let actual = Self::from_entries(entries);
+        if actual.root() == expected_root && actual.root() == self.root() {
+            return Ok(());
+        }
+        Err(crate::Error::IntegrityMismatch {
+            ids: self.diff(&actual),
+        })
+    }
+
+    /// re-hashes the path from `bucket` to the root
+    fn rehash(&mut self, bucket: usize) {
+        let mut node = BUCKETS + bucket;
+        self.nodes[node] = bucket_hash(&self.buckets[bucket]);
+        while node > 1 {
+            node /= 2;
+            self.nodes[node] = inner_hash(&self.nodes[2 * node], &self.nodes[2 * node + 1]);
+        }
+    }
+
+    fn rehash_all(&mut self) {
+        for node in (1..BUCKETS).rev() {
+            self.nodes[node] = inner_hash(&self.nodes[2 * node], &self.nodes[2 * node + 1]);
+        }
+    }
+}
+
+impl Default for IntegrityTree {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// the bucket of the entry `id` and its leaf
+fn leaf(id: &[u8], data: &[u8]) -> (usize, [u8; 32]) {
+    let leaf = Sha256::new()
+        .chain_update([0])
+        .chain_update((id.len() as u64).to_be_bytes())
+        .chain_update(id)
+        .chain_update(Sha256::digest(data))
+        .finalize();
+    (bucket_of(id), leaf.into())
+}
+
+fn bucket_of(id: &[u8]) -> usize {
+    let hash = Sha256::digest(id);
+    (u16::from_be_bytes([hash[0], hash[1]]) >> (16 - BUCKET_BITS)) as usize
+}
+
+/// the hash of the leaves of a bucket in the order of their ids
+fn bucket_hash(leaves: &BTreeMap<Vec<u8>, [u8; 32]>) -> [u8; 32] {
+    let mut hash = Sha256::new().chain_update([1]);
+    for leaf in leaves.values() {
+        hash.update(leaf);
+    }
+    hash.finalize().into()
+}
+
+fn inner_hash(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    Sha256::new()
+        .chain_update([2])
+        .chain_update(left)
+        .chain_update(right)
+        .finalize()
+        .into()
+}
@@ -0,0 +1,383 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+use crate::{
+    crypto_box::{AssociatedData, BoxProvider, Decrypt, Encrypt, Key},
+    ct::ct_eq,
+    types::transactions::SealedPayload,
+    vault::{DeleteRequest, IntegrityTree, ListResult, ReadRequest, ReadResult, WriteRequest},
+};
+
+use std::{collections::HashMap, convert::TryInto};
+
+use sha2::{Digest, Sha256};
+use zeroize::Zeroize;
+
+/// the id of the entry of the journal of the batch being committed
+const JOURNAL_ID: &[u8] = b"vault journal";
+/// the id of the entry which marks the journal as committed
+const COMMIT_ID: &[u8] = b"vault journal commit";
+
+/// The store of a vault, which applies the requests of the vault.  A single write or delete must be atomic, e.g. a
+/// file written to a temporary file and renamed, `JournaledVault` builds atomic batches on it.
+pub trait Store {
+    /// the ids of all entries
+    fn list(&self) -> crate::Result<ListResult>;
+
+    /// the entry of the `request`, `None` if there is none
+    fn read(&self, request: ReadRequest) -> crate::Result<Option<ReadResult>>;
+
+    /// write the entry of the `request`, replacing an existing one
+    fn write(&mut self, request: WriteRequest) -> crate::Result<()>;
+
+    /// delete the entry of the `request`, if there is one
+    fn delete(&mut self, request: DeleteRequest) -> crate::Result<()>;
+}
+
+/// The steps of a commit of `JournaledVault::transaction`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CommitStep {
+    /// the staged requests are journaled
+    Journal,
+    /// the journal is marked as committed, from here on the batch is applied even after a crash
+    Commit,
+    /// the staged requests are applied to the store
+    Apply,
+    /// the journal and the commit mark are removed
+    Cleanup,
+}
+
+/// What `JournaledVault::recover` found of an interrupted commit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Recovery {
+    /// there was no interrupted commit
+    Clean,
+    /// a batch which wasn't committed was discarded
+    Discarded,
+    /// a committed batch was applied
+    Replayed,
+}
+
+/// A store of a vault whose writes and deletes are grouped in atomic transactions, see `transaction`.  The staged
+/// requests are journaled to the store, sealed with the key, before the commit mark is written, so a commit
+/// interrupted by a crash is either applied completely or discarded by the next `open`.
+///
+/// The vault keeps the `IntegrityTree` of the store up to date with its commits, see `integrity_root`.  Only a
+/// single `JournaledVault` may write the store at a time, the journal has a fixed id.
+pub struct JournaledVault<P: BoxProvider, S: Store> {
+    key: Key<P>,
+    store: S,
+    tree: IntegrityTree,
+    #[cfg(feature = "test-utils")]
+    crash_before: Option<CommitStep>,
+}
+
+/// A transaction of a `JournaledVault`, stages the writes and deletes until the transaction is committed.  Reads
+/// see the staged state.
+pub struct VaultTransaction<'a, S: Store> {
+    store: &'a S,
+    staged: Vec<Op>,
+    // the staged data of the ids, `None` for the deleted ones
+    state: HashMap<Vec<u8>, Option<Vec<u8>>>,
+}
+
+/// a staged request
+enum Op {
+    Write(WriteRequest),
+    Delete(DeleteRequest),
+}
+
+impl<P: BoxProvider, S: Store> JournaledVault<P, S> {
+    /// Opens the `store`, whose journal is sealed with `key`, and recovers an interrupted commit, see `recover`.
+    pub fn open(key: Key<P>, store: S) -> crate::Result<Self> {
+        let mut vault = Self {
+            key,
+            store,
+            tree: IntegrityTree::new(),
+            #[cfg(feature = "test-utils")]
+            crash_before: None,
+        };
+        vault.recover()?;
+        vault.tree = IntegrityTree::from_entries(&vault.entries()?);
+        Ok(vault)
+    }
+
+    /// Recovers an interrupted commit: a committed batch is applied again, one which wasn't committed is discarded.
+    /// Fails with `Error::AuthenticationFailed` if the commit mark or the journal don't open with the key or don't
+    /// match, and with `Error::InvalidEntry` if the journal is malformed.
+    pub fn recover(&mut self) -> crate::Result<Recovery> {
+        let journal = self.store.read(ReadRequest::new(JOURNAL_ID.to_vec()))?;
+        let commit = self.store.read(ReadRequest::new(COMMIT_ID.to_vec()))?;
+        let recovery = match (journal, commit) {
+            (None, None) => return Ok(Recovery::Clean),
+            (Some(journal), Some(commit)) => {
+                let mut digest = SealedPayload::from(commit.data().to_vec()).decrypt(&self.key, &commit_ad())?;
+                let committed = ct_eq(&digest, &Sha256::digest(journal.data()));
+                digest.zeroize();
+                if !committed {
+                    return Err(crate::Error::AuthenticationFailed);
+                }
+                let mut plain = SealedPayload::from(journal.data().to_vec()).decrypt(&self.key, &journal_ad())?;
+                let ops = decode(&plain);
+                plain.zeroize();
+                self.apply(ops?)?;
+                Recovery::Replayed
+            }
+            (_, _) => Recovery::Discarded,
+        };
+        self.cleanup()?;
+        Ok(recovery)
+    }
+
+    /// Runs `f` in a transaction and commits the requests it staged if it returns `Ok`, atomically: after a crash
+    /// the store holds either all of them or none.  Nothing is written if `f` fails or panics.  Returns the result
+    /// of `f`, or the error of the store if the commit fails; a commit which fails after the journal is marked as
+    /// committed is completed by `recover`.
+    pub fn transaction<T>(
+        &mut self,
+        f: impl FnOnce(&mut VaultTransaction<'_, S>) -> crate::Result<T>,
+    ) -> crate::Result<T> {
+        let mut tx = VaultTransaction {
+            store: &self.store,
+            staged: Vec::new(),
+            state: HashMap::new(),
+        };
+        let result = f(&mut tx)?;
+        let staged = tx.staged;
+        if !staged.is_empty() {
+            self.commit(staged)?;
+        }
+        Ok(result)
+    }
+
+    /// The root of the `IntegrityTree` of the entries of the store, without the journal.  It is updated with each
+    /// commit and changes with every write, also of the same plaintext, as each seal uses a fresh nonce.
+    pub fn integrity_root(&self) -> [u8; 32] {
+        self.tree.root()
+    }
+
+    /// Checks that the entries of the store have the root `expected_root` and weren't modified, added or removed
+    /// since the vault opened the store other than by its commits.  Fails with `Error::IntegrityMismatch` which
+    /// lists the ids of the entries changed behind the vault, see `IntegrityTree::verify`.
+    pub fn verify_integrity(&self, expected_root: [u8; 32]) -> crate::Result<()> {
+        self.tree.verify(expected_root, &self.entries()?)
+    }
+
+    /// the store of the vault
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    /// the store of the vault, see `recover` for a store left behind by a failed commit
+    pub fn into_store(self) -> S {
+        self.store
+    }
+
+    /// Stops the next commit before `step` as if the process crashed, the commit fails with
+    /// `Error::InterfaceErrorDetailed` and leaves the store as it is.
+    #[cfg(feature = "test-utils")]
+    pub fn crash_before(&mut self, step: CommitStep) {
+        self.crash_before = Some(step);
+    }
+
+    fn commit(&mut self, staged: Vec<Op>) -> crate::Result<()> {
+        self.step(CommitStep::Journal)?;
+        let mut plain = encode(&staged);
+        let journal: crate::Result<SealedPayload> = plain.encrypt(&self.key, &journal_ad());
+        plain.zeroize();
+        let journal = journal?;
+        self.store
+            .write(WriteRequest::new(JOURNAL_ID.to_vec(), journal.as_ref().to_vec()))?;
+
+        self.step(CommitStep::Commit)?;
+        let digest = Sha256::digest(journal.as_ref()).to_vec();
+        let commit: SealedPayload = digest.encrypt(&self.key, &commit_ad())?;
+        self.store
+            .write(WriteRequest::new(COMMIT_ID.to_vec(), commit.as_ref().to_vec()))?;
+
+        self.step(CommitStep::Apply)?;
+        self.apply(staged)?;
+
+        self.step(CommitStep::Cleanup)?;
+        self.cleanup()
+    }
+
+    fn apply(&mut self, ops: Vec<Op>) -> crate::Result<()> {
+        for op in ops {
+            match op {
+                Op::Write(request) => {
+                    self.tree.write(&request);
+                    self.store.write(request)?
+                }
+                Op::Delete(request) => {
+                    self.tree.delete(&request);
+                    self.store.delete(request)?
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// the entries of the store, without the journal
+    fn entries(&self) -> crate::Result<Vec<ReadResult>> {
+        let mut entries = Vec::new();
+        for id in self.store.list()?.into_iter().filter(|id| !is_journal(id)) {
+            if let Some(entry) = self.store.read(ReadRequest::new(id))? {
+                entries.push(entry);
+            }
+        }
+        Ok(entries)
+    }
+
+    /// removes the commit mark before the journal, a journal without a mark is never applied
+    fn cleanup(&mut self) -> crate::Result<()> {
+        self.store.delete(DeleteRequest::new(COMMIT_ID.to_vec()))?;
+        self.store.delete(DeleteRequest::new(JOURNAL_ID.to_vec()))
+    }
+
+    #[cfg(feature = "test-utils")]
+    fn step(&mut self, step: CommitStep) -> crate::Result<()> {
+        if self.crash_before == Some(step) {
+            self.crash_before = None;
+            return Err(crate::Error::InterfaceErrorDetailed(format!(
+                "Simulated crash before {:?}",
+                step
+            )));
+        }
+        Ok(())
+    }
+
+    #[cfg(not(feature = "test-utils"))]
+    fn step(&mut self, _step: CommitStep) -> crate::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a, S: Store> VaultTransaction<'a, S> {
+    /// stages the write of the `request`
+    pub fn write(&mut self, request: WriteRequest) -> crate::Result<()> {
+        check_id(request.id())?;
+        self.state.insert(request.id().to_vec(), Some(request.data().to_vec()));
+        self.staged.push(Op::Write(request));
+        Ok(())
+    }
+
+    /// stages the delete of the `request`
+    pub fn delete(&mut self, request: DeleteRequest) -> crate::Result<()> {
+        check_id(request.id())?;
+        self.state.insert(request.id().to_vec(), None);
+        self.staged.push(Op::Delete(request));
+        Ok(())
+    }
+
+    /// the entry of the `request` with the staged requests applied
+    pub fn read(&self, request: ReadRequest) -> crate::Result<Option<ReadResult>> {
+        match self.state.get(request.id()) {
+            Some(Some(data)) => Ok(Some(ReadResult::new(request.into(), data.clone()))),
+            Some(None) => Ok(None),
+            None => self.store.read(request),
+        }
+    }
+
+    /// the ids of the entries with the staged requests applied
+    pub fn list(&self) -> crate::Result<ListResult> {
+        let mut ids: Vec<Vec<u8>> = self
+            .store
+            .list()?
+            .into_iter()
+            .filter(|id| !self.state.contains_key(id) && !is_journal(id))
+            .collect();
+        ids.extend(
+            self.state
+                .iter()
+                .filter(|(_, data)| data.is_some())
+                .map(|(id, _)| id.clone()),
+        );
+        Ok(ListResult::new(ids))
+    }
+
+    /// Nested transactions aren't supported, fails with `Error::NestedTransaction`.
+    pub fn transaction<T>(
+        &mut self,
+        _f: impl FnOnce(&mut VaultTransaction<'_, S>) -> crate::Result<T>,
+    ) -> crate::Result<T> {
+        Err(crate::Error::NestedTransaction)
+    }
+}
+
+fn is_journal(id: &[u8]) -> bool {
+    id == JOURNAL_ID || id == COMMIT_ID
+}
+
+/// the entries of the journal can't be written in a transaction
+fn check_id(id: &[u8]) -> crate::Result<()> {
+    if is_journal(id) {
+        return Err(crate::Error::InvalidEntry {
+            reason: String::from("The id of the entry is reserved for the journal"),
+        });
+    }
+    Ok(())
+}
+
+fn journal_ad() -> Vec<u8> {
+    AssociatedData::new("vault journal").finish()
+}
+
+fn commit_ad() -> Vec<u8> {
+    AssociatedData::new("vault journal commit").finish()
+}
+
+/// the staged requests: a tag, the length of the id and the id, the length of the data and the data of writes
+fn encode(ops: &[Op]) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    for op in ops {
+        let (tag, id, data) = match op {
+            Op::Write(request) => (0u8, request.id(), Some(request.data())),
+            Op::Delete(request) => (1u8, request.id(), None),
+        };
+        bytes.push(tag);
+        bytes.extend_from_slice(&(id.len() as u64).to_be_bytes());
+        bytes.extend_from_slice(id);
+        if let Some(data) = data {
+            bytes.extend_from_slice(&(data.len() as u64).to_be_bytes());
+            bytes.extend_from_slice(data);
+        }
+    }
+    bytes
+}
+
+fn decode(mut bytes: &[u8]) -> crate::Result<Vec<Op>> {
+    let corrupt = || crate::Error::InvalidEntry {
+        reason: String::from("The journal is malformed"),
+    };
+    let field = |bytes: &mut &[u8]| -> crate::Result<Vec<u8>> {
+        let len = bytes.get(..8).ok_or_else(corrupt)?;
+        let len: usize = u64::from_be_bytes(len.try_into().expect("8 bytes"))
+            .try_into()
+            .map_err(|_| corrupt())?;
+        let end = len.checked_add(8).ok_or_else(corrupt)?;
+        let value = bytes.get(8..end).ok_or_else(corrupt)?.to_vec();
+        *bytes = &bytes[end..];
+        Ok(value)
+    };
+
+    let mut ops = Vec::new();
+    while let Some((tag, rest)) = bytes.split_first() {
+        bytes = rest;
+        let id = field(&mut bytes)?;
+        ops.push(match tag {
+            0 => Op::Write(WriteRequest::new(id, field(&mut bytes)?)),
+            1 => Op::Delete(DeleteRequest::new(id)),
+            _ => return Err(corrupt()),
+        });
+    }
+    Ok(ops)
+}
@@ -0,0 +1,260 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+use crate::{
+    crypto_box::{open_box, seal_box, AssociatedData, BoxProvider, KdfParams, Key},
+    types::{
+        transactions::{DataTransaction, InitTransaction, RevocationTransaction},
+        utils::{Id, RecordHint, Val},
+    },
+    vault::{
+        concurrent::insert, Clock, DBView, DeleteRequest, ExpiryPolicy, ReadRequest, Record, Store, SystemClock,
+        WriteRequest,
+    },
+};
+
+use std::{
+    convert::TryInto,
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+/// the id of the entry with the salt, the KDF parameters and the check value of the password
+const LOCK_ID: &[u8] = b"vault lock";
+const SALT_LEN: usize = 16;
+/// the salt and the big endian memory cost, iterations and parallelism
+const PARAMS_LEN: usize = SALT_LEN + 12;
+
+/// A vault whose key is derived from a password and which can be locked, e.g. after being idle.  `lock` drops the
+/// key of the vault, its only key, while the store stays open; the vault keeps no plaintext and derived keys only
+/// live during an operation.  `unlock` derives the key from the password again.  Every operation of a locked vault
+/// fails with `Error::VaultLocked`.
+///
+/// The salt, the KDF parameters and a check value of the password are stored in the entry `vault lock` of the
+/// store.  With `lock_after` the vault locks itself once it wasn't used for a while, by the time of its `Clock`.
+pub struct LockableVault<P: BoxProvider, S: Store> {
+    store: S,
+    owner: Id,
+    view: Option<DBView<P>>,
+    lock_after: Option<Duration>,
+    last_used: SystemTime,
+    clock: Arc<dyn Clock + Send + Sync>,
+    #[cfg(feature = "test-utils")]
+    key_hooks: Vec<crate::crypto_box::DropHook>,
+}
+
+impl<P: BoxProvider, S: Store> LockableVault<P, S> {
+    /// Creates a vault in `store` with the key derived from `password` with `params`, and the chain of `owner`.  The
+    /// vault is unlocked.  Fails with `Error::InvalidEntry` if the store already has a lockable vault.
+    pub fn create(mut store: S, owner: Id, password: &[u8], params: KdfParams) -> crate::Result<Self> {
+        if store.read(ReadRequest::new(LOCK_ID.to_vec()))?.is_some() {
+            return Err(crate::Error::InvalidEntry {
+                reason: String::from("The store already has a lockable vault"),
+            });
+        }
+        let salt = P::random_array::<SALT_LEN>().map_err(Into::into)?;
+        let key = Key::<P>::derive_from_password(password, &salt, params)?;
+        let mut entry = salt.to_vec();
+        entry.extend_from_slice(&params.memory_cost.to_be_bytes());
+        entry.extend_from_slice(&params.iterations.to_be_bytes());
+        entry.extend_from_slice(&params.parallelism.to_be_bytes());
+        entry.extend_from_slice(&seal_box(&key, &check_ad(), &[])?);
+        store.write(WriteRequest::new(LOCK_ID.to_vec(), entry))?;
+
+        let mut view = DBView::load(key, store.list()?)?;
+        if view.chain.get(&owner).is_none() {
+            let init = Record::new(&view.key, InitTransaction::new(owner, Val::from(0u64)));
+            store.write(init.write())?;
+            insert(&mut view, Some(init))?;
+        }
+        let mut vault = Self::open(store, owner)?;
+        vault.view = Some(view);
+        Ok(vault)
+    }
+
+    /// Opens the vault created by `create` in `store`, the vault is locked.  Fails with `Error::InvalidEntry` if
+    /// the store has no lockable vault.
+    pub fn open(store: S, owner: Id) -> crate::Result<Self> {
+        let clock: Arc<dyn Clock + Send + Sync> = Arc::new(SystemClock);
+        let vault = Self {
+            store,
+            owner,
+            view: None,
+            lock_after: None,
+            last_used: clock.now(),
+            clock,
+            #[cfg(feature = "test-utils")]
+            key_hooks: Vec::new(),
+        };
+        vault.lock_entry()?;
+        Ok(vault)
+    }
+
+    /// Derives the key from `password` and loads the vault.  Fails with `Error::WrongPassword` if the password
+    /// doesn't match, the vault stays locked then.  Unlocking an unlocked vault derives the key again.
+    pub fn unlock(&mut self, password: &[u8]) -> crate::Result<()> {
+        let entry = self.lock_entry()?;
+        let be_u32 = |at: usize| u32::from_be_bytes(entry[at..at + 4].try_into().expect("4 bytes"));
+        let params = KdfParams {
+            memory_cost: be_u32(SALT_LEN),
+            iterations: be_u32(SALT_LEN + 4),
+            parallelism: be_u32(SALT_LEN + 8),
+        };
+        if !params.is_bounded() {
+            return Err(crate::Error::InvalidEntry {
+                reason: "The KDF parameters of the lock entry exceed the supported bounds".into(),
+            });
+        }
+        #[allow(unused_mut)]
+        let mut key = Key::<P>::derive_from_password(password, &entry[..SALT_LEN], params)?;
+        #[cfg(feature = "test-utils")]
+        for hook in &self.key_hooks {
+            let hook = hook.clone();
+            key.on_drop(move |bytes| hook(bytes));
+        }
+        match open_box(&key, &check_ad(), &entry[PARAMS_LEN..]) {
+            Ok(_) => {}
+            Err(crate::Error::AuthenticationFailed) => return Err(crate::Error::WrongPassword),
+            Err(e) => return Err(e),
+        }
+        self.view = Some(DBView::load(key, self.store.list()?)?);
+        self.last_used = self.clock.now();
+        Ok(())
+    }
+
+    /// Drops the key of the vault, which wipes it.  The store stays open.
+    pub fn lock(&mut self) {
+        self.view = None;
+    }
+
+    /// whether the vault is locked, or was idle for longer than `lock_after`
+    pub fn is_locked(&self) -> bool {
+        self.view.is_none() || self.is_idle()
+    }
+
+    /// lock the vault once it wasn't used for `idle`, `None` keeps it unlocked until `lock`
+    pub fn lock_after(&mut self, idle: Option<Duration>) {
+        self.lock_after = idle;
+    }
+
+    /// take the time of `clock` for `lock_after` instead of the system clock
+    pub fn with_clock(mut self, clock: Arc<dyn Clock + Send + Sync>) -> Self {
+        self.last_used = clock.now();
+        self.clock = clock;
+        self
+    }
+
+    /// Marks the vault as used, which restarts the idle time of `lock_after`.  Fails with `Error::VaultLocked` if
+    /// the vault is locked, it locks the vault if it was idle for too long.
+    pub fn touch(&mut self) -> crate::Result<()> {
+        self.unlocked().map(|_| ())
+    }
+
+    /// The ids and hints of the valid records.
+    pub fn records(&mut self) -> crate::Result<Vec<(Id, RecordHint)>> {
+        Ok(self.unlocked()?.0.records().collect())
+    }
+
+    /// Reads the record `id`.  Fails with `Error::RecordNotFound` if there is no valid record for the `id` or its
+    /// payload is missing.
+    pub fn read(&mut self, id: Id) -> crate::Result<Vec<u8>> {
+        let (view, store) = self.unlocked()?;
+        let record = view.valid.get(&id).ok_or(crate::Error::RecordNotFound { id })?;
+        let payload = store.read(ReadRequest::payload::<P>(id))?;
+        let payload = payload.ok_or(crate::Error::RecordNotFound { id })?;
+        record.open_payload_in(&view.key, &view.ad, payload.data(), &ExpiryPolicy::default())
+    }
+
+    /// Writes `data` as a new record and returns its id.
+    pub fn write(&mut self, data: &[u8], hint: RecordHint) -> crate::Result<Id> {
+        let owner = self.owner;
+        let (view, store) = self.unlocked()?;
+        let id = Id::random::<P>()?;
+        let ctr = view.chain.force_last(&owner).ctr() + 1;
+        let record = Record::seal(&view.key, &view.ad, DataTransaction::new(owner, ctr, id, hint));
+        for request in record.write_payload_in(&view.key, &view.ad, data, None)? {
+            store.write(request)?;
+        }
+        insert(view, Some(record))?;
+        Ok(id)
+    }
+
+    /// Revokes the record `id` and deletes its payload.  Fails with `Error::RecordNotFound` if there is no valid
+    /// record for the `id`.
+    pub fn revoke(&mut self, id: Id) -> crate::Result<()> {
+        let owner = self.owner;
+        let (view, store) = self.unlocked()?;
+        if view.valid.get(&id).is_none() {
+            return Err(crate::Error::RecordNotFound { id });
+        }
+        let ctr = view.chain.force_last(&owner).ctr() + 1;
+        let record = Record::seal(&view.key, &view.ad, RevocationTransaction::new(owner, ctr, id));
+        store.write(record.write())?;
+        store.delete(DeleteRequest::uid(id))?;
+        insert(view, Some(record))
+    }
+
+    /// the store of the vault
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    /// locks the vault and returns its store
+    pub fn into_store(self) -> S {
+        self.store
+    }
+
+    /// Adds `hook` to the drop hooks of the keys the vault derives from now on, see `Key::on_drop`.
+    #[cfg(feature = "test-utils")]
+    pub fn on_key_drop(&mut self, hook: impl Fn(&mut [u8]) + Send + Sync + 'static) {
+        self.key_hooks.push(Arc::new(hook));
+    }
+
+    fn is_idle(&self) -> bool {
+        match self.lock_after {
+            Some(idle) => self
+                .clock
+                .now()
+                .duration_since(self.last_used)
+                .is_ok_and(|elapsed| elapsed >= idle),
+            None => false,
+        }
+    }
+
+    /// the view and the store of an unlocked vault, locks the vault if it was idle for too long and restarts the
+    /// idle time otherwise
+    fn unlocked(&mut self) -> crate::Result<(&mut DBView<P>, &mut S)> {
+        if self.is_idle() {
+            self.lock();
+        }
+        match &mut self.view {
+            Some(view) => {
+                self.last_used = self.clock.now();
+                Ok((view, &mut self.store))
+            }
+            None => Err(crate::Error::VaultLocked { holder_pid: None }),
+        }
+    }
+
+    fn lock_entry(&self) -> crate::Result<Vec<u8>> {
+        let entry = self.store.read(ReadRequest::new(LOCK_ID.to_vec()))?;
+        match entry {
+            Some(entry) if entry.data().len() > PARAMS_LEN => Ok(entry.data().to_vec()),
+            _ => Err(crate::Error::InvalidEntry {
+                reason: String::from("The store has no lockable vault"),
+            }),
+        }
+    }
+}
+
+fn check_ad() -> Vec<u8> {
+    AssociatedData::new("vault lock check").finish()
+}
@@ -0,0 +1,344 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+use crate::{
+    crypto_box::{AssociatedData, BoxProvider, Decrypt, Encrypt, Key},
+    types::{
+        transactions::{DataTransaction, SealedPayload},
+        utils::{Id, RecordHint},
+    },
+    vault::{
+        expiry::{open_expiring, seal_payload, Clock},
+        namespace::RecordAd,
+        DBReader, DBView, DBWriter, ReadRequest, ReadResult, Record, WriteRequest,
+    },
+};
+
+use std::{
+    collections::BTreeMap,
+    convert::TryInto,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use zeroize::Zeroize;
+
+/// the HKDF info of the metadata key, which keeps it apart from the record key and from other derived keys
+const META_KEY_INFO: &[u8] = b"vault record metadata key";
+/// the suffix of the id of the metadata entry of a record
+const SUFFIX: &[u8] = b"meta";
+/// the length of a record id
+const ID_LEN: usize = 24;
+
+/// The metadata of a record, sealed next to it under a key derived from the vault key so it doesn't leak how the
+/// records are used.  Its encoding is at most `MAX_LEN` bytes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordMeta {
+    /// when the record was written
+    pub created: SystemTime,
+    /// when the record was last written, updated by `DBWriter::overwrite`
+    pub modified: SystemTime,
+    /// a free form label
+    pub label: Option<String>,
+    /// metadata of the application
+    pub custom: BTreeMap<String, Vec<u8>>,
+}
+
+impl RecordMeta {
+    /// the largest encoding of the metadata, checked when it is written
+    pub const MAX_LEN: usize = 4096;
+
+    /// metadata of a record written at `now`
+    pub fn new(now: SystemTime) -> Self {
+        Self {
+            created: now,
+            modified: now,
+            label: None,
+            custom: BTreeMap::new(),
+        }
+    }
+
+    /// sets the label
+    pub fn with_label(mut self, label: impl Into<String>) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+
+    /// adds the custom metadata `name`
+    pub fn with_custom(mut self, name: impl Into<String>, value: impl Into<Vec<u8>>) -> Self {
+        self.custom.insert(name.into(), value.into());
+        self
+    }
+
+    /// the times, each as seconds and nanoseconds since the UNIX epoch, the label and the custom metadata, each
+    /// string and value prefixed by its length
+    fn encode(&self) -> crate::Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        for time in [self.created, self.modified].iter() {
+            let since = time
+                .duration_since(UNIX_EPOCH)
+                .map_err(|_| crate::Error::InvalidEntry {
+                    reason: String::from("The metadata has a time before the UNIX epoch"),
+                })?;
+            bytes.extend_from_slice(&since.as_secs().to_be_bytes());
+            bytes.extend_from_slice(&since.subsec_nanos().to_be_bytes());
+        }
+        match &self.label {
+            Some(label) => {
+                bytes.push(1);
+                put(&mut bytes, label.as_bytes());
+            }
+            None => bytes.push(0),
+        }
+        bytes.extend_from_slice(&(self.custom.len() as u32).to_be_bytes());
+        for (name, value) in &self.custom {
+            put(&mut bytes, name.as_bytes());
+            put(&mut bytes, value);
+        }
+        if bytes.len() > Self::MAX_LEN {
+            let len = bytes.len();
+            bytes.zeroize();
+            return Err(crate::Error::MetadataTooLarge {
+                len,
+                limit: Self::MAX_LEN,
+            });
+        }
+        Ok(bytes)
+    }
+
+    fn decode(mut bytes: &[u8]) -> crate::Result<Self> {
+        let corrupt = || crate::Error::InvalidEntry {
+            reason: String::from("The metadata is malformed"),
+        };
+        let take = |bytes: &mut &[u8], len: usize| -> crate::Result<Vec<u8>> {
+            let value = bytes.get(..len).ok_or_else(corrupt)?.to_vec();
+            *bytes = &bytes[len..];
+            Ok(value)
+        };
+        let be_u32 = |bytes: &mut &[u8]| -> crate::Result<u32> {
+            Ok(u32::from_be_bytes(take(bytes, 4)?.try_into().expect("4 bytes")))
+        };
+        let time = |bytes: &mut &[u8]| -> crate::Result<SystemTime> {
+            let secs = u64::from_be_bytes(take(bytes, 8)?.try_into().expect("8 bytes"));
+            let nanos = be_u32(bytes)?;
+            UNIX_EPOCH.checked_add(Duration::new(secs, nanos)).ok_or_else(corrupt)
+        };
+        let field = |bytes: &mut &[u8]| -> crate::Result<Vec<u8>> {
+            let len = be_u32(bytes)?;
+            take(bytes, len as usize)
+        };
+        let string =
+            |bytes: &mut &[u8]| -> crate::Result<String> { String::from_utf8(field(bytes)?).map_err(|_| corrupt()) };
+
+        let created = time(&mut bytes)?;
+        let modified = time(&mut bytes)?;
+        let label = match take(&mut bytes, 1)?[0] {
+            0 => None,
+            1 => Some(string(&mut bytes)?),
+            _ => return Err(corrupt()),
+        };
+        let mut custom = BTreeMap::new();
+        for _ in 0..be_u32(&mut bytes)? {
+            let name = string(&mut bytes)?;
+            custom.insert(name, field(&mut bytes)?);
+        }
+        if !bytes.is_empty() {
+            return Err(corrupt());
+        }
+        Ok(Self {
+            created,
+            modified,
+            label,
+            custom,
+        })
+    }
+}
+
+fn put(bytes: &mut Vec<u8>, value: &[u8]) {
+    bytes.extend_from_slice(&(value.len() as u32).to_be_bytes());
+    bytes.extend_from_slice(value);
+}
+
+/// the id of the metadata entry of the record `id`
+fn entry_id(id: Id) -> Vec<u8> {
+    [id.as_ref(), SUFFIX].concat()
+}
+
+/// the record of a metadata entry of the store, `None` for other entries
+pub(crate) fn record_of(entry: &[u8]) -> Option<Id> {
+    if entry.len() != ID_LEN + SUFFIX.len() || !entry.ends_with(SUFFIX) {
+        return None;
+    }
+    Id::load(&entry[..ID_LEN]).ok()
+}
+
+fn meta_ad(ad: &RecordAd, id: Id) -> Vec<u8> {
+    AssociatedData::new("vault record metadata")
+        .field("record", &ad.payload(id))
+        .finish()
+}
+
+/// the request writing the `meta` of the record `id`
+pub(crate) fn seal_meta<P: BoxProvider>(
+    key: &Key<P>,
+    ad: &RecordAd,
+    id: Id,
+    meta: &RecordMeta,
+) -> crate::Result<WriteRequest> {
+    let mut plain = meta.encode()?;
+    let sealed: crate::Result<SealedPayload> = plain.encrypt(&key.derive_child(META_KEY_INFO)?, &meta_ad(ad, id));
+    plain.zeroize();
+    Ok(WriteRequest::new(entry_id(id), sealed?.as_ref().to_vec()))
+}
+
+/// the metadata of the record `id` stored as `data`
+pub(crate) fn open_meta<P: BoxProvider>(key: &Key<P>, ad: &RecordAd, id: Id, data: &[u8]) -> crate::Result<RecordMeta> {
+    let mut plain = SealedPayload::from(data.to_vec()).decrypt(&key.derive_child(META_KEY_INFO)?, &meta_ad(ad, id))?;
+    let meta = RecordMeta::decode(&plain);
+    plain.zeroize();
+    meta
+}
+
+/// the request re-sealing the metadata `current` of the record `id` from `old_key` to `new_key`, `None` if the
+/// record has no metadata.  The metadata is opened under `new_key` again.
+pub(crate) fn rekey_meta<P: BoxProvider>(
+    old_key: &Key<P>,
+    new_key: &Key<P>,
+    ad: &RecordAd,
+    id: Id,
+    current: Option<ReadResult>,
+) -> crate::Result<Option<WriteRequest>> {
+    match current {
+        Some(res) => {
+            let meta = open_meta(old_key, ad, id, res.data())?;
+            let sealed = seal_meta(new_key, ad, id, &meta)?;
+            match open_meta(new_key, ad, id, sealed.data())? == meta {
+                true => Ok(Some(sealed)),
+                false => Err(crate::Error::AuthenticationFailed),
+            }
+        }
+        None => Ok(None),
+    }
+}
+
+/// the request reading the metadata of the record `id`
+pub(crate) fn read_request(id: Id) -> ReadRequest {
+    ReadRequest::new(entry_id(id))
+}
+
+/// the request writing the metadata `current` of the record `id` with `modified` set to `now`, `None` if the
+/// record has no metadata
+pub(crate) fn touch<P: BoxProvider>(
+    key: &Key<P>,
+    ad: &RecordAd,
+    id: Id,
+    now: SystemTime,
+    current: Option<ReadResult>,
+) -> crate::Result<Option<WriteRequest>> {
+    match current {
+        Some(res) => {
+            let mut meta = open_meta(key, ad, id, res.data())?;
+            meta.modified = now.max(meta.created);
+            Ok(Some(seal_meta(key, ad, id, &meta)?))
+        }
+        None => Ok(None),
+    }
+}
+
+impl<P: BoxProvider> DBView<P> {
+    /// The ids, hints and metadata of the valid records, the metadata entries are read with `read`.  Only the
+    /// metadata is opened, never the payloads.  Records without metadata have `None`; fails if metadata doesn't open.
+    #[allow(clippy::type_complexity)]
+    pub fn list_with_meta(
+        &self,
+        mut read: impl FnMut(ReadRequest) -> Option<ReadResult>,
+    ) -> crate::Result<Vec<(Id, RecordHint, Option<RecordMeta>)>> {
+        self.records()
+            .map(|(id, hint)| {
+                let meta = match read(read_request(id)) {
+                    Some(res) => Some(open_meta(&self.key, &self.ad, id, res.data())?),
+                    None => None,
+                };
+                Ok((id, hint, meta))
+            })
+            .collect()
+    }
+}
+
+impl<'a, P: BoxProvider> DBReader<'a, P> {
+    /// The metadata of the record `id`, read with `read`, `None` if it has none.  Fails with
+    /// `Error::RecordNotFound` if there is no valid record for the `id`.
+    pub fn read_meta(
+        &self,
+        id: Id,
+        read: impl FnOnce(ReadRequest) -> Option<ReadResult>,
+    ) -> crate::Result<Option<RecordMeta>> {
+        self.prepare_read(id)?;
+        match read(read_request(id)) {
+            Some(res) => Ok(Some(open_meta(&self.view.key, &self.view.ad, id, res.data())?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Opens the record `id` and its metadata, read with `read`, see `read` and `read_meta`.
+    pub fn read_with_meta(
+        &self,
+        id: Id,
+        mut read: impl FnMut(ReadRequest) -> Option<ReadResult>,
+    ) -> crate::Result<(Vec<u8>, Option<RecordMeta>)> {
+        let res = read(self.prepare_read(id)?).ok_or(crate::Error::RecordNotFound { id })?;
+        let data = self.read(res)?;
+        Ok((data, self.read_meta(id, read)?))
+    }
+}
+
+impl<P: BoxProvider> DBWriter<P> {
+    /// Write the `data` to the chain like `write`, with the metadata `meta`.  Fails with `Error::MetadataTooLarge`
+    /// if the metadata exceeds `RecordMeta::MAX_LEN` bytes, nothing is written then.
+    pub fn write_with_meta(
+        self,
+        data: &[u8],
+        hint: RecordHint,
+        meta: &RecordMeta,
+    ) -> crate::Result<(Id, Vec<WriteRequest>)> {
+        let view = &self.view;
+        let id = Id::random::<P>()?;
+        let ctr = view.chain.force_last(&self.owner).ctr() + 1;
+        let record = Record::seal(&view.key, &view.ad, DataTransaction::new(self.owner, ctr, id, hint));
+        let meta = seal_meta(&view.key, &view.ad, id, meta)?;
+        let mut requests = record.write_payload_in(&view.key, &view.ad, data, None)?;
+        requests.insert(1, meta);
+        Ok((id, requests))
+    }
+
+    /// Overwrite the payload of the record `id` with `data`, the record keeps its id, hint and expiry.  The payload
+    /// and the metadata are read with `read`, the `modified` time of the metadata is set to the time of `clock`.
+    /// Fails with `Error::RecordNotFound` if there is no valid record for the `id` or its payload is missing.
+    pub fn overwrite(
+        self,
+        id: Id,
+        data: &[u8],
+        clock: &dyn Clock,
+        mut read: impl FnMut(ReadRequest) -> Option<ReadResult>,
+    ) -> crate::Result<Vec<WriteRequest>> {
+        let view = &self.view;
+        let record = view.valid.get(&id).ok_or(crate::Error::RecordNotFound { id })?;
+        let old = read(ReadRequest::payload::<P>(id)).ok_or(crate::Error::RecordNotFound { id })?;
+        let (mut plain, expires_at) = open_expiring(&view.key, &view.ad.payload(id), old.data())?;
+        plain.zeroize();
+
+        let payload = seal_payload(&view.key, &view.ad.payload(id), data, expires_at)?;
+        let mut requests = vec![WriteRequest::payload(id, payload)];
+        requests.extend(touch(&view.key, &view.ad, id, clock.now(), read(read_request(id)))?);
+        // the transaction is unchanged, writing it again restores it if it went missing
+        requests.push(record.write());
+        Ok(requests)
+    }
+}
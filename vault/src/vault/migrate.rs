@@ -0,0 +1,141 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+use crate::{
+    crypto_box::{BoxProvider, Key},
+    types::{
+        transactions::{DataTransaction, InitTransaction},
+        utils::{Id, RecordHint, Val},
+    },
+    vault::{
+        expiry::{open_expiring, seal_payload},
+        namespace::RecordAd,
+        DBView, ReadRequest, Record, Store, WriteRequest,
+    },
+};
+
+use std::collections::{HashMap, HashSet};
+
+use zeroize::Zeroize;
+
+/// The outcome of `migrate_records`.
+#[derive(Debug, Default)]
+pub struct MigrationReport {
+    /// the records written to the destination
+    pub migrated: Vec<Id>,
+    /// the records in the destination already and the ones the filter left out
+    pub skipped: Vec<Id>,
+    /// the records which failed to migrate and why, the others are migrated regardless
+    pub failed: Vec<(Id, crate::Error)>,
+}
+
+/// Migrates the valid records of `src_vault`, sealed with `src_key` of the provider `Src`, to `dst_vault` sealed with
+/// `dst_key` of the provider `Dst`.  One record at a time is read, opened, passed to `filter` and re-sealed, its
+/// plaintext is wiped right after; nothing is written in the clear.  A record keeps its id, owner, hint and expiry
+/// and is appended to the chain of its owner in the destination.
+///
+/// `filter` gets the id, the hint and the plaintext of each record: it may change the plaintext and returns
+/// whether to migrate the record.  Records with an id in the destination are skipped without being opened, so
+/// running the migration again, e.g. after it was interrupted, resumes where it stopped.
+///
+/// Records which fail to open or seal are reported in `MigrationReport::failed`, errors of the stores abort the
+/// migration.
+pub fn migrate_records<Src, Dst, S, D>(
+    src_vault: &S,
+    src_key: &Key<Src>,
+    dst_vault: &mut D,
+    dst_key: &Key<Dst>,
+    mut filter: impl FnMut(Id, RecordHint, &mut Vec<u8>) -> bool,
+) -> crate::Result<MigrationReport>
+where
+    Src: BoxProvider,
+    Dst: BoxProvider,
+    S: Store,
+    D: Store,
+{
+    let src = DBView::load(src_key.try_clone()?, src_vault.list()?)?;
+    let dst = DBView::load(dst_key.try_clone()?, dst_vault.list()?)?;
+    // the revoked records of the destination count as migrated too
+    let present: HashSet<Id> = dst
+        .chain
+        .all()
+        .filter_map(|record| record.typed::<DataTransaction>())
+        .map(|data| data.id)
+        .collect();
+    let mut ctrs: HashMap<Id, Val> = dst
+        .chain
+        .owners()
+        .filter_map(|(owner, records)| Some((*owner, records.last()?.ctr())))
+        .collect();
+
+    let mut records: Vec<&Record> = src.valid.all().collect();
+    records.sort_by_key(|record| (record.owner(), record.ctr()));
+
+    let mut report = MigrationReport::default();
+    let ad = RecordAd::default();
+    for record in records {
+        let data = record.force_typed::<DataTransaction>();
+        let (id, owner) = (data.id, record.owner());
+        if present.contains(&id) {
+            report.skipped.push(id);
+            continue;
+        }
+        let payload = match src_vault.read(ReadRequest::payload::<Src>(id))? {
+            Some(payload) => payload,
+            None => {
+                report.failed.push((id, crate::Error::RecordNotFound { id }));
+                continue;
+            }
+        };
+        let (mut plain, expires_at) = match open_expiring(src_key, &ad.payload(id), payload.data()) {
+            Ok(opened) => opened,
+            Err(e) => {
+                report.failed.push((id, e));
+                continue;
+            }
+        };
+        let keep = filter(id, data.record_hint, &mut plain);
+        let sealed = if keep {
+            Some(seal_payload(dst_key, &ad.payload(id), &plain, expires_at))
+        } else {
+            None
+        };
+        plain.zeroize();
+        let sealed = match sealed {
+            Some(Ok(sealed)) => sealed,
+            Some(Err(e)) => {
+                report.failed.push((id, e));
+                continue;
+            }
+            None => {
+                report.skipped.push(id);
+                continue;
+            }
+        };
+
+        // the chain of the owner, and the payload before the transaction so an interruption leaves no record
+        // without its payload
+        let ctr = match ctrs.get(&owner) {
+            Some(ctr) => *ctr + 1,
+            None => {
+                let init = InitTransaction::new(owner, Val::from(0u64));
+                dst_vault.write(Record::new(dst_key, init).write())?;
+                Val::from(1u64)
+            }
+        };
+        let transaction = DataTransaction::new(owner, ctr, id, data.record_hint);
+        dst_vault.write(WriteRequest::payload(id, sealed))?;
+        dst_vault.write(Record::new(dst_key, transaction).write())?;
+        ctrs.insert(owner, ctr);
+        report.migrated.push(id);
+    }
+    Ok(report)
+}
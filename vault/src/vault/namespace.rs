@@ -0,0 +1,226 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+use crate::{
+    crypto_box::{AssociatedData, BoxProvider, Key},
+    types::{
+        transactions::InitTransaction,
+        utils::{Id, Val},
+    },
+    vault::{DBView, DeleteRequest, GcReport, ListResult, ReadRequest, ReadResult, Record, WriteRequest},
+};
+
+#[cfg(feature = "insecure-serde")]
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// the length of the tag in front of the ids of the entries of a namespace
+const TAG_LEN: usize = 16;
+
+/// The AD the records of a view are sealed with.  Without namespace transactions are sealed without AD and payloads
+/// with the id of their record, as they always were.
+#[cfg_attr(feature = "insecure-serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct RecordAd {
+    namespace: Option<Vec<u8>>,
+}
+
+impl RecordAd {
+    /// the name of the namespace, `None` without namespace
+    pub fn namespace(&self) -> Option<&[u8]> {
+        self.namespace.as_deref()
+    }
+
+    /// the AD of the transactions
+    pub fn transaction(&self) -> Vec<u8> {
+        match &self.namespace {
+            None => Vec::new(),
+            Some(namespace) => AssociatedData::new("vault transaction")
+                .field("namespace", namespace)
+                .finish(),
+        }
+    }
+
+    /// the AD of the payload of the record `id`
+    pub fn payload(&self, id: Id) -> Vec<u8> {
+        match &self.namespace {
+            None => id.as_ref().to_vec(),
+            Some(namespace) => AssociatedData::new("vault payload")
+                .field("namespace", namespace)
+                .field("record_id", id.as_ref())
+                .finish(),
+        }
+    }
+}
+
+/// A namespace of a store shared by several vaults, e.g. one per tenant, each with its own key.  The name of the
+/// namespace is part of the AD of every transaction and payload of it, so ciphertext copied between namespaces fails
+/// to open with `Error::AuthenticationFailed` even under the same key.  The ids of the entries of a namespace in the
+/// store start with the tag of the namespace, a hash of its name, so they can be listed, collected and deleted per
+/// namespace.
+///
+/// The views of a namespace are loaded with `load` and work with the ids of the namespace: their requests are turned
+/// into requests of the store with `tag_writes`, `tag_deletes` and `tag_read`, the read results of the store into
+/// results of the namespace with `untag` or `reader`.
+pub struct VaultNamespace<P: BoxProvider> {
+    name: String,
+    key: Key<P>,
+    ad: RecordAd,
+    tag: [u8; TAG_LEN],
+}
+
+impl<P: BoxProvider> VaultNamespace<P> {
+    /// the namespace `name` with its records sealed under `key`.  Fails with `Error::InvalidEntry` if `name` is
+    /// empty.
+    pub fn new(name: &str, key: Key<P>) -> crate::Result<Self> {
+        if name.is_empty() {
+            return Err(crate::Error::InvalidEntry {
+                reason: String::from("The name of the namespace is empty"),
+            });
+        }
+        let hash = Sha256::digest(
+            AssociatedData::new("vault namespace tag")
+                .field("namespace", name.as_bytes())
+                .as_bytes(),
+        );
+        let mut tag = [0; TAG_LEN];
+        tag.copy_from_slice(&hash[..TAG_LEN]);
+
+        Ok(Self {
+            name: String::from(name),
+            key,
+            ad: RecordAd {
+                namespace: Some(name.as_bytes().to_vec()),
+            },
+            tag,
+        })
+    }
+
+    /// the name of the namespace
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// the key of the namespace
+    pub fn key(&self) -> &Key<P> {
+        &self.key
+    }
+
+    /// the tag in front of the ids of the entries of the namespace
+    pub fn tag(&self) -> &[u8] {
+        &self.tag
+    }
+
+    /// checks if the entry `id` of the store belongs to the namespace
+    pub fn contains(&self, id: &[u8]) -> bool {
+        id.len() > TAG_LEN && id.starts_with(&self.tag)
+    }
+
+    /// the ids of the namespace among the ids of the store in `list`
+    pub fn list(&self, list: &ListResult) -> ListResult {
+        ListResult::new(
+            list.ids()
+                .iter()
+                .filter(|id| self.contains(id))
+                .map(|id| id[TAG_LEN..].to_vec())
+                .collect(),
+        )
+    }
+
+    /// open the view of the namespace from the ids of the store in `list`, see `DBView::load`
+    pub fn load(&self, list: &ListResult) -> crate::Result<DBView<P>> {
+        DBView::load_in(self.key.try_clone()?, self.ad.clone(), self.list(list))
+    }
+
+    /// the request of the store creating the chain of `owner` in the namespace, see `DBWriter::create_chain`
+    pub fn create_chain(&self, owner: Id) -> WriteRequest {
+        let transaction = InitTransaction::new(owner, Val::from(0u64));
+        self.tag_write(Record::seal(&self.key, &self.ad, transaction).write())
+    }
+
+    /// the write requests of a view of the namespace as requests of the store
+    pub fn tag_writes(&self, requests: Vec<WriteRequest>) -> Vec<WriteRequest> {
+        requests.into_iter().map(|request| self.tag_write(request)).collect()
+    }
+
+    /// the delete requests of a view of the namespace as requests of the store
+    pub fn tag_deletes(&self, requests: Vec<DeleteRequest>) -> Vec<DeleteRequest> {
+        requests
+            .into_iter()
+            .map(|request| DeleteRequest::new(self.tagged(request.id())))
+            .collect()
+    }
+
+    /// a read request of a view of the namespace as request of the store
+    pub fn tag_read(&self, request: ReadRequest) -> ReadRequest {
+        ReadRequest::new(self.tagged(request.id()))
+    }
+
+    /// a read result of the store as result of the namespace, `None` if the entry isn't part of the namespace
+    pub fn untag(&self, result: ReadResult) -> Option<ReadResult> {
+        let (id, data) = result.into();
+        match self.contains(&id) {
+            true => Some(ReadResult::new(id[TAG_LEN..].to_vec(), data)),
+            false => None,
+        }
+    }
+
+    /// reads of a view of the namespace with the reads of the store `read`, e.g. for `DBView::rekey`
+    pub fn reader<'a>(
+        &'a self,
+        mut read: impl FnMut(ReadRequest) -> Option<ReadResult> + 'a,
+    ) -> impl FnMut(ReadRequest) -> Option<ReadResult> + 'a {
+        move |request| self.untag(read(self.tag_read(request))?)
+    }
+
+    /// collect the garbage of the namespace among the `entries` of the store, see `DBView::gc`.  `in_flight` and the
+    /// returned requests are requests of the store.
+    #[allow(clippy::type_complexity)]
+    pub fn gc(
+        &self,
+        entries: &[ReadResult],
+        in_flight: &[WriteRequest],
+    ) -> crate::Result<(GcReport, Vec<WriteRequest>, Vec<DeleteRequest>)> {
+        let entries: Vec<ReadResult> = entries.iter().cloned().filter_map(|entry| self.untag(entry)).collect();
+        let in_flight: Vec<WriteRequest> = in_flight
+            .iter()
+            .filter(|request| self.contains(request.id()))
+            .map(|request| WriteRequest::new(request.id()[TAG_LEN..].to_vec(), request.data().to_vec()))
+            .collect();
+        let (report, to_write, to_delete) = DBView::gc_in(&self.key, &self.ad, &entries, &in_flight)?;
+        Ok((report, self.tag_writes(to_write), self.tag_deletes(to_delete)))
+    }
+
+    /// delete the namespace from the store: the requests overwrite the data of every entry of the namespace among the
+    /// `entries` of the store with zeros, for stores which overwrite in place, and delete every entry.  Apply the
+    /// writes first.
+    pub fn delete(&self, entries: &[ReadResult]) -> (Vec<WriteRequest>, Vec<DeleteRequest>) {
+        let entries = entries.iter().filter(|entry| self.contains(entry.id()));
+        let mut to_write = Vec::new();
+        let mut to_delete = Vec::new();
+        for entry in entries {
+            if !entry.data().is_empty() {
+                to_write.push(WriteRequest::new(entry.id().to_vec(), vec![0; entry.data().len()]));
+            }
+            to_delete.push(DeleteRequest::new(entry.id().to_vec()));
+        }
+        (to_write, to_delete)
+    }
+
+    fn tag_write(&self, request: WriteRequest) -> WriteRequest {
+        let (id, data) = request.into();
+        WriteRequest::new(self.tagged(&id), data)
+    }
+
+    fn tagged(&self, id: &[u8]) -> Vec<u8> {
+        [&self.tag[..], id].concat()
+    }
+}
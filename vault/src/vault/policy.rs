@@ -0,0 +1,142 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+use crate::types::utils::Id;
+
+use std::{collections::BTreeMap, time::SystemTime};
+
+/// The operation a `Policy` decides on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Operation {
+    /// opening the payload of a record
+    Read,
+    /// writing a new record or overwriting the payload of one
+    Write,
+    /// revoking a record
+    Delete,
+    /// listing a record with its hint and metadata
+    List,
+}
+
+/// The claims of a caller, e.g. its role, as names and values.  The vault doesn't interpret them, only the
+/// `Policy` does.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Claims(BTreeMap<String, String>);
+
+impl Claims {
+    /// no claims
+    pub const fn new() -> Self {
+        Self(BTreeMap::new())
+    }
+
+    /// adds the claim `name`
+    pub fn with(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.0.insert(name.into(), value.into());
+        self
+    }
+
+    /// the value of the claim `name`
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.0.get(name).map(String::as_str)
+    }
+}
+
+/// What a `Policy` decides on: the `operation` on the `record`, in the `namespace` of the vault if it has one, by
+/// a caller with `claims`, at `timestamp`.
+#[derive(Debug, Clone, Copy)]
+pub struct AccessContext<'a> {
+    /// the record, for a write the id of the new record
+    pub record: Id,
+    /// the name of the namespace of the vault
+    pub namespace: Option<&'a [u8]>,
+    /// the operation on the record
+    pub operation: Operation,
+    /// the claims of the caller
+    pub claims: &'a Claims,
+    /// when the operation happens
+    pub timestamp: SystemTime,
+}
+
+/// The decision of a `Policy`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Decision {
+    /// the operation may go on
+    Allow,
+    /// the operation fails with `Error::AccessDenied` with the reason
+    Deny(String),
+}
+
+/// A rule for the records a caller may access, evaluated before a key is used, so a denied operation neither opens
+/// nor seals anything.  Closures `Fn(&AccessContext) -> Decision` are policies too.
+pub trait Policy {
+    /// decides on the operation of `ctx`
+    fn allow(&self, ctx: &AccessContext) -> Decision;
+}
+
+impl<F: Fn(&AccessContext) -> Decision> Policy for F {
+    fn allow(&self, ctx: &AccessContext) -> Decision {
+        self(ctx)
+    }
+}
+
+/// The default `Policy`, it allows everything.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AllowAll;
+
+impl Policy for AllowAll {
+    fn allow(&self, _: &AccessContext) -> Decision {
+        Decision::Allow
+    }
+}
+
+/// Allows an operation if all of its policies allow it, denies it with the reason of the first one which doesn't.
+/// Without policies it allows everything.
+#[derive(Default)]
+pub struct AllOf(pub Vec<Box<dyn Policy + Send + Sync>>);
+
+impl Policy for AllOf {
+    fn allow(&self, ctx: &AccessContext) -> Decision {
+        self.0
+            .iter()
+            .map(|policy| policy.allow(ctx))
+            .find(|decision| *decision != Decision::Allow)
+            .unwrap_or(Decision::Allow)
+    }
+}
+
+/// Allows an operation if any of its policies allows it, denies it with the reasons of all of them otherwise.
+/// Without policies it denies everything.
+#[derive(Default)]
+pub struct AnyOf(pub Vec<Box<dyn Policy + Send + Sync>>);
+
+impl Policy for AnyOf {
+    fn allow(&self, ctx: &AccessContext) -> Decision {
+        let mut reasons = Vec::new();
+        for policy in &self.0 {
+            match policy.allow(ctx) {
+                Decision::Allow => return Decision::Allow,
+                Decision::Deny(reason) => reasons.push(reason),
+            }
+        }
+        match reasons.is_empty() {
+            true => Decision::Deny(String::from("No policy allows the operation")),
+            false => Decision::Deny(reasons.join("; ")),
+        }
+    }
+}
+
+/// fails with `Error::AccessDenied` if `policy` denies the operation of `ctx`
+pub(crate) fn check(policy: &dyn Policy, ctx: &AccessContext) -> crate::Result<()> {
+    match policy.allow(ctx) {
+        Decision::Allow => Ok(()),
+        Decision::Deny(reason) => Err(crate::Error::AccessDenied { reason }),
+    }
+}
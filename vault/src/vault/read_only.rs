@@ -0,0 +1,93 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+use crate::{
+    crypto_box::BoxProvider,
+    types::utils::{Id, RecordHint},
+    vault::{DBReader, DBView, ExpiryPolicy, ListResult, ReadRequest, ReadResult, VersionInfo},
+};
+
+use std::{collections::HashMap, sync::Arc};
+
+/// A handle to a `DBView` which can only read, see `DBView::read_only`.  It lists and opens records but has no
+/// writer, rekey or any other method creating requests to change the vault, and it can't be turned back into a
+/// `DBView`.  Clones share the view.
+///
+/// ```
+/// # use vault::{BoxProvider, DBView, Id, RecordHint};
+/// # fn f<P: BoxProvider>(view: DBView<P>, id: Id) -> vault::Result<()> {
+/// let read_only = view.read_only();
+/// let request = read_only.reader().prepare_read(id)?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Writing, re-keying or getting a `DBView` back doesn't compile, see the cases in `tests/ui`.
+pub struct ReadOnlyVault<P: BoxProvider> {
+    view: Arc<DBView<P>>,
+}
+
+impl<P: BoxProvider> Clone for ReadOnlyVault<P> {
+    fn clone(&self) -> Self {
+        Self {
+            view: self.view.clone(),
+        }
+    }
+}
+
+impl<P: BoxProvider> DBView<P> {
+    /// Converts the `DBView` into a `ReadOnlyVault`.
+    pub fn read_only(self) -> ReadOnlyVault<P> {
+        ReadOnlyVault { view: Arc::new(self) }
+    }
+}
+
+impl<P: BoxProvider> ReadOnlyVault<P> {
+    /// Creates an iterator over all valid records, see `DBView::records`.
+    pub fn records(&self) -> impl ExactSizeIterator<Item = (Id, RecordHint)> + '_ {
+        self.view.records()
+    }
+
+    /// Creates an iterator over all record ids, see `DBView::all`.
+    pub fn all(&self) -> impl Iterator<Item = Id> + '_ {
+        self.view.all()
+    }
+
+    /// Check the balance of valid records compared to total records, see `DBView::absolute_balance`.
+    pub fn absolute_balance(&self) -> (usize, usize) {
+        self.view.absolute_balance()
+    }
+
+    /// Get highest counter from the vault, see `DBView::chain_ctrs`.
+    pub fn chain_ctrs(&self) -> HashMap<Id, u64> {
+        self.view.chain_ctrs()
+    }
+
+    /// Check the age of the chains, see `DBView::not_older_than`.
+    pub fn not_older_than(&self, chain_ctrs: &HashMap<Id, u64>) -> crate::Result<()> {
+        self.view.not_older_than(chain_ctrs)
+    }
+
+    /// The ids of the valid records which are expired under `policy`, see `DBView::expired`.
+    pub fn expired(&self, read: impl FnMut(ReadRequest) -> Option<ReadResult>, policy: &ExpiryPolicy) -> Vec<Id> {
+        self.view.expired(read, policy)
+    }
+
+    /// The versions of the versioned record `id`, see `DBView::history`.
+    pub fn history(&self, id: Id, list: &ListResult) -> crate::Result<Vec<VersionInfo>> {
+        self.view.history(id, list)
+    }
+
+    /// Creates a `DBReader` of the vault.
+    pub fn reader(&self) -> DBReader<'_, P> {
+        self.view.reader()
+    }
+}
@@ -20,10 +20,15 @@ use crate::{
         utils::{Id, Val},
         AsView,
     },
+    vault::{
+        expiry::{self, ExpiryPolicy},
+        namespace::RecordAd,
+    },
 };
 
 use std::{
     fmt::{self, Debug, Formatter},
+    time::SystemTime,
     vec::IntoIter,
 };
 
@@ -83,6 +88,11 @@ impl ReadRequest {
             id: id.as_ref().to_vec(),
         }
     }
+
+    /// create a read request of an entry of the store
+    pub(in crate) fn new(id: Vec<u8>) -> Self {
+        Self { id }
+    }
     /// id of a record
     pub fn id(&self) -> &[u8] {
         &self.id
@@ -107,6 +117,11 @@ impl ReadResult {
 }
 
 impl WriteRequest {
+    /// create a request to write `data` to the entry `id` of the store
+    pub(in crate) fn new(id: Vec<u8>, data: Vec<u8>) -> Self {
+        Self { id, data }
+    }
+
     /// create a new write request
     pub(in crate) fn transaction(transaction: &SealedTransaction) -> Self {
         Self {
@@ -135,6 +150,11 @@ impl WriteRequest {
 }
 
 impl DeleteRequest {
+    /// create a request to delete the entry `id` of the store
+    pub(in crate) fn new(id: Vec<u8>) -> Self {
+        Self { id }
+    }
+
     /// create new delete request
     pub(in crate) fn transaction(transaction: &SealedTransaction) -> Self {
         Self {
@@ -158,14 +178,35 @@ impl DeleteRequest {
 impl Record {
     /// open a transaction from record by id
     pub fn open<P: BoxProvider>(key: &Key<P>, id: &[u8]) -> Option<Self> {
+        Self::try_open(key, &RecordAd::default(), id, 0).ok().flatten()
+    }
+
+    /// open a transaction from record by id, `None` if it doesn't open with the key.  Fails with
+    /// `Error::CorruptRecord` if it opens but isn't a transaction, `offset` is the position of the record in the list.
+    pub(in crate) fn try_open<P: BoxProvider>(
+        key: &Key<P>,
+        ad: &RecordAd,
+        id: &[u8],
+        offset: usize,
+    ) -> crate::Result<Option<Self>> {
         // get fields and create transaction
         let sealed = SealedTransaction::from(id.to_vec());
-        let packed = sealed.decrypt(key, b"").ok()?;
-        Some(Self((packed, sealed)))
+        match sealed.decrypt(key, &ad.transaction()) {
+            Ok(packed) => Ok(Some(Self((packed, sealed)))),
+            Err(crate::Error::ConversionError { .. }) => Err(crate::Error::CorruptRecord { offset }),
+            Err(_) => Ok(None),
+        }
     }
     /// create a new record
     pub fn new<P: BoxProvider>(key: &Key<P>, transaction: Transaction) -> Self {
-        let sealed = transaction.encrypt(key, b"").expect("Failed to encrypt transaction");
+        Self::seal(key, &RecordAd::default(), transaction)
+    }
+
+    /// create a new record sealed with the AD `ad`
+    pub(in crate) fn seal<P: BoxProvider>(key: &Key<P>, ad: &RecordAd, transaction: Transaction) -> Self {
+        let sealed = transaction
+            .encrypt(key, &ad.transaction())
+            .expect("Failed to encrypt transaction");
         Self((transaction, sealed))
     }
 
@@ -231,11 +272,59 @@ impl Record {
         ])
     }
 
-    /// open the payload given a key and the cipher.
+    /// create a set of write requests for a payload which expires at `expires_at`, to the second.  The expiry is
+    /// bound to the payload, it can only be extended by writing the data again.
+    pub fn write_expiring_payload<P: BoxProvider>(
+        &self,
+        key: &Key<P>,
+        data: &[u8],
+        expires_at: SystemTime,
+    ) -> crate::Result<Vec<WriteRequest>> {
+        self.write_payload_in(key, &RecordAd::default(), data, Some(expires_at))
+    }
+
+    /// create a set of write requests for a payload sealed with the AD `ad`
+    pub(in crate) fn write_payload_in<P: BoxProvider>(
+        &self,
+        key: &Key<P>,
+        ad: &RecordAd,
+        data: &[u8],
+        expires_at: Option<SystemTime>,
+    ) -> crate::Result<Vec<WriteRequest>> {
+        let id = self.force_typed::<DataTransaction>().id;
+        let payload = expiry::seal_payload(key, &ad.payload(id), data, expires_at)?;
+        Ok(vec![
+            WriteRequest::payload(id, payload),
+            WriteRequest::transaction(self.sealed()),
+        ])
+    }
+
+    /// open the payload given a key and the cipher.  Fails with `Error::RecordExpired` if it expired by the system
+    /// clock, see `open_payload_with`.
     pub fn open_payload<P: BoxProvider>(&self, key: &Key<P>, data: &[u8]) -> crate::Result<Vec<u8>> {
+        self.open_payload_with(key, data, &ExpiryPolicy::default())
+    }
+
+    /// open the payload given a key and the cipher.  Fails with `Error::RecordExpired` if it expired under `policy`.
+    pub fn open_payload_with<P: BoxProvider>(
+        &self,
+        key: &Key<P>,
+        data: &[u8],
+        policy: &ExpiryPolicy,
+    ) -> crate::Result<Vec<u8>> {
+        self.open_payload_in(key, &RecordAd::default(), data, policy)
+    }
+
+    /// open the payload sealed with the AD `ad`
+    pub(in crate) fn open_payload_in<P: BoxProvider>(
+        &self,
+        key: &Key<P>,
+        ad: &RecordAd,
+        data: &[u8],
+        policy: &ExpiryPolicy,
+    ) -> crate::Result<Vec<u8>> {
         let id = self.force_typed::<DataTransaction>().id;
-        let payload = SealedPayload::from(data.to_vec()).decrypt(key, id.as_ref())?;
-        Ok(payload)
+        expiry::open_payload(key, &ad.payload(id), data, policy)
     }
 }
 
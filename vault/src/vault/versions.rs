@@ -0,0 +1,337 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+use crate::{
+    crypto_box::{AssociatedData, BoxProvider, Decrypt, Encrypt, Key},
+    ct::ct_eq,
+    types::{
+        transactions::{DataTransaction, SealedPayload},
+        utils::{Id, RecordHint},
+    },
+    vault::{
+        expiry::Clock, namespace::RecordAd, DBReader, DBView, DBWriter, DeleteRequest, ListResult, ReadRequest,
+        ReadResult, Record, WriteRequest,
+    },
+};
+
+use std::{
+    convert::TryInto,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use zeroize::Zeroize;
+
+/// the length of a record id
+const ID_LEN: usize = 24;
+/// the length of the id of a version entry: the record id, the version and the timestamp
+const ENTRY_LEN: usize = ID_LEN + 16;
+
+/// The metadata of a version of a versioned record, see `DBView::history`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct VersionInfo {
+    /// the number of the version, starting at `1`
+    pub version: u64,
+    /// the time the version was written, to the second
+    pub timestamp: SystemTime,
+}
+
+/// How many old versions of a versioned record `DBWriter::prune` keeps.  The latest version is always kept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RetentionPolicy {
+    /// keep the `n` latest versions
+    KeepLast(usize),
+    /// keep the versions written within the duration
+    KeepFor(Duration),
+}
+
+impl RetentionPolicy {
+    /// keep the `n` latest versions
+    pub fn keep_last(n: usize) -> Self {
+        RetentionPolicy::KeepLast(n)
+    }
+
+    /// keep the versions written within `duration`
+    pub fn keep_for(duration: Duration) -> Self {
+        RetentionPolicy::KeepFor(duration)
+    }
+}
+
+/// the id of the entry of `version` of the record `id`
+fn entry_id(id: Id, info: VersionInfo) -> Vec<u8> {
+    [
+        id.as_ref(),
+        &info.version.to_be_bytes(),
+        &secs(info.timestamp).to_be_bytes(),
+    ]
+    .concat()
+}
+
+/// the AD of the box of `version` of the record `id`, binds the record, the version and the timestamp so a version
+/// can't be replayed as another one
+fn version_ad(ad: &RecordAd, id: Id, info: VersionInfo) -> Vec<u8> {
+    AssociatedData::new("vault version")
+        .field("record", &ad.payload(id))
+        .field("version", &info.version.to_be_bytes())
+        .field("timestamp", &secs(info.timestamp).to_be_bytes())
+        .finish()
+}
+
+fn secs(timestamp: SystemTime) -> u64 {
+    timestamp.duration_since(UNIX_EPOCH).map_or(0, |since| since.as_secs())
+}
+
+/// the versions of the record `id` among the ids of `list`, ordered by version
+fn versions(id: Id, list: &ListResult) -> Vec<VersionInfo> {
+    let mut versions: Vec<VersionInfo> = list
+        .ids()
+        .iter()
+        .filter(|entry| entry.len() == ENTRY_LEN && entry.starts_with(id.as_ref()))
+        .map(|entry| {
+            let be_u64 = |at: usize| u64::from_be_bytes(entry[at..at + 8].try_into().expect("8 bytes"));
+            VersionInfo {
+                version: be_u64(ID_LEN),
+                timestamp: UNIX_EPOCH + Duration::from_secs(be_u64(ID_LEN + 8)),
+            }
+        })
+        .collect();
+    versions.sort_by_key(|info| info.version);
+    versions
+}
+
+/// the version of an entry of the store, `None` for other entries
+pub(crate) fn parse_entry(entry: &[u8]) -> Option<(Id, VersionInfo)> {
+    if entry.len() != ENTRY_LEN {
+        return None;
+    }
+    let id = Id::load(&entry[..ID_LEN]).ok()?;
+    versions(id, &ListResult::new(vec![entry.to_vec()]))
+        .pop()
+        .map(|info| (id, info))
+}
+
+/// open the version `info` of the record `id` stored as `data`
+pub(crate) fn open_version<P: BoxProvider>(
+    key: &Key<P>,
+    ad: &RecordAd,
+    id: Id,
+    info: VersionInfo,
+    data: &[u8],
+) -> crate::Result<Vec<u8>> {
+    SealedPayload::from(data.to_vec()).decrypt(key, &version_ad(ad, id, info))
+}
+
+/// the box of `data` as the version `info` of the record `id`
+fn seal_version<P: BoxProvider>(
+    key: &Key<P>,
+    ad: &RecordAd,
+    id: Id,
+    info: VersionInfo,
+    data: &[u8],
+) -> crate::Result<WriteRequest> {
+    let sealed: SealedPayload = data.to_vec().encrypt(key, &version_ad(ad, id, info))?;
+    Ok(WriteRequest::new(entry_id(id, info), sealed.as_ref().to_vec()))
+}
+
+/// the requests re-sealing the versions of the record `id` among the ids of `list` from `old_key` to `new_key`, read
+/// with `read`.  Every version is opened under `new_key` again.  Fails if a version is missing or doesn't open.
+pub(crate) fn rekey_versions<P: BoxProvider>(
+    old_key: &Key<P>,
+    new_key: &Key<P>,
+    ad: &RecordAd,
+    id: Id,
+    list: &ListResult,
+    mut read: impl FnMut(ReadRequest) -> Option<ReadResult>,
+) -> crate::Result<Vec<WriteRequest>> {
+    versions(id, list)
+        .into_iter()
+        .map(|info| {
+            let res = read(ReadRequest::new(entry_id(id, info))).ok_or(crate::Error::VersionNotFound { id })?;
+            let mut plain = open_version(old_key, ad, id, info, res.data())?;
+            let sealed = seal_version(new_key, ad, id, info, &plain);
+            let verified = sealed.and_then(|sealed| {
+                let mut reopened = open_version(new_key, ad, id, info, sealed.data())?;
+                let same = ct_eq(&reopened, &plain);
+                reopened.zeroize();
+                match same {
+                    true => Ok(sealed),
+                    false => Err(crate::Error::AuthenticationFailed),
+                }
+            });
+            plain.zeroize();
+            verified
+        })
+        .collect()
+}
+
+impl<P: BoxProvider> DBView<P> {
+    /// The versions of the versioned record `id` among the ids of the store in `list`, ordered by version.  Only the
+    /// ids are looked at, nothing is read or opened.  Fails with `Error::RecordNotFound` if there is no valid record
+    /// for the `id`.
+    pub fn history(&self, id: Id, list: &ListResult) -> crate::Result<Vec<VersionInfo>> {
+        match self.valid.get(&id) {
+            Some(_) => Ok(versions(id, list)),
+            None => Err(crate::Error::RecordNotFound { id }),
+        }
+    }
+}
+
+impl<'a, P: BoxProvider> DBReader<'a, P> {
+    /// Open the version `version` of the versioned record `id`, read with `read`.  `list` are the ids of the store.
+    /// Fails with `Error::VersionNotFound` if there is no such version and with `Error::AuthenticationFailed` if the
+    /// entry of the version holds the box of another version or record.
+    pub fn read_version(
+        &self,
+        id: Id,
+        version: u64,
+        list: &ListResult,
+        read: impl FnMut(ReadRequest) -> Option<ReadResult>,
+    ) -> crate::Result<Vec<u8>> {
+        let info = self
+            .view
+            .history(id, list)?
+            .into_iter()
+            .find(|info| info.version == version);
+        self.open_version(id, info, read)
+    }
+
+    /// Open the version of the versioned record `id` which was current at `timestamp`, the latest version written
+    /// at or before it, see `read_version`.
+    pub fn read_at(
+        &self,
+        id: Id,
+        timestamp: SystemTime,
+        list: &ListResult,
+        read: impl FnMut(ReadRequest) -> Option<ReadResult>,
+    ) -> crate::Result<Vec<u8>> {
+        let info = self
+            .view
+            .history(id, list)?
+            .into_iter()
+            .rev()
+            .find(|info| secs(info.timestamp) <= secs(timestamp));
+        self.open_version(id, info, read)
+    }
+
+    /// Open the latest version of the versioned record `id`, see `read_version`.
+    pub fn read_latest(
+        &self,
+        id: Id,
+        list: &ListResult,
+        read: impl FnMut(ReadRequest) -> Option<ReadResult>,
+    ) -> crate::Result<Vec<u8>> {
+        let info = self.view.history(id, list)?.pop();
+        self.open_version(id, info, read)
+    }
+
+    fn open_version(
+        &self,
+        id: Id,
+        info: Option<VersionInfo>,
+        mut read: impl FnMut(ReadRequest) -> Option<ReadResult>,
+    ) -> crate::Result<Vec<u8>> {
+        let info = info.ok_or(crate::Error::VersionNotFound { id })?;
+        let res = read(ReadRequest::new(entry_id(id, info))).ok_or(crate::Error::VersionNotFound { id })?;
+        open_version(&self.view.key, &self.view.ad, id, info, res.data())
+    }
+}
+
+impl<P: BoxProvider> DBWriter<P> {
+    /// Write the `data` to the chain as a versioned record, `data` is its first version.  Returns the id of the
+    /// record and the `WriteRequest`s, see `write_version`.
+    pub fn write_versioned(
+        self,
+        data: &[u8],
+        hint: RecordHint,
+        clock: &dyn Clock,
+    ) -> crate::Result<(Id, Vec<WriteRequest>)> {
+        let id = Id::random::<P>()?;
+        let ctr = self.view.chain.force_last(&self.owner).ctr() + 1;
+        let transaction = DataTransaction::new(self.owner, ctr, id, hint);
+        let record = Record::seal(&self.view.key, &self.view.ad, transaction);
+
+        let info = VersionInfo {
+            version: 1,
+            timestamp: clock.now(),
+        };
+        let version = self.seal_version(id, info, data)?;
+        Ok((id, vec![version, record.write()]))
+    }
+
+    /// Write the `data` as the next version of the versioned record `id`.  `list` are the ids of the store, the
+    /// version is one more than the latest one in it and the timestamp the time of `clock`, but not before the
+    /// timestamp of the latest version.  Returns the number of the version and its `WriteRequest`.  Fails with
+    /// `Error::RecordNotFound` if there is no valid record for the `id`.
+    pub fn write_version(
+        self,
+        id: Id,
+        data: &[u8],
+        list: &ListResult,
+        clock: &dyn Clock,
+    ) -> crate::Result<(u64, WriteRequest)> {
+        let latest = self.view.history(id, list)?.pop();
+        let now = clock.now();
+        let info = match latest {
+            None => VersionInfo {
+                version: 1,
+                timestamp: now,
+            },
+            Some(latest) => VersionInfo {
+                version: latest.version + 1,
+                timestamp: now.max(latest.timestamp),
+            },
+        };
+        Ok((info.version, self.seal_version(id, info, data)?))
+    }
+
+    /// Remove the versions of the versioned record `id` which `policy` doesn't keep at the time of `clock`, the
+    /// latest version is always kept.  `list` are the ids of the store and the entries of the removed versions are
+    /// read with `read`: the `WriteRequest`s overwrite them with zeros, for stores which overwrite in place, and the
+    /// `DeleteRequest`s delete them.  Apply the writes first.
+    pub fn prune(
+        self,
+        id: Id,
+        policy: RetentionPolicy,
+        list: &ListResult,
+        clock: &dyn Clock,
+        mut read: impl FnMut(ReadRequest) -> Option<ReadResult>,
+    ) -> crate::Result<(Vec<WriteRequest>, Vec<DeleteRequest>)> {
+        let mut versions = self.view.history(id, list)?;
+        // the latest version is kept by every policy
+        versions.pop();
+        let now = clock.now();
+        let pruned = match policy {
+            RetentionPolicy::KeepLast(n) => {
+                let keep = n.saturating_sub(1).min(versions.len());
+                versions.truncate(versions.len() - keep);
+                versions
+            }
+            RetentionPolicy::KeepFor(duration) => versions
+                .into_iter()
+                .filter(|info| now.duration_since(info.timestamp).is_ok_and(|age| age > duration))
+                .collect(),
+        };
+
+        let mut to_write = Vec::new();
+        let mut to_delete = Vec::new();
+        for info in pruned {
+            let entry = entry_id(id, info);
+            if let Some(res) = read(ReadRequest::new(entry.clone())) {
+                to_write.push(WriteRequest::new(entry.clone(), vec![0; res.data().len()]));
+            }
+            to_delete.push(DeleteRequest::new(entry));
+        }
+        Ok((to_write, to_delete))
+    }
+
+    fn seal_version(&self, id: Id, info: VersionInfo, data: &[u8]) -> crate::Result<WriteRequest> {
+        seal_version(&self.view.key, &self.view.ad, id, info, data)
+    }
+}
@@ -0,0 +1,360 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+//! A write-ahead log in front of a `Store`.
+//!
+//! The log file is laid out as
+//!
+//! | bytes | field |
+//! |---|---|
+//! | 4 | the magic bytes `RCWL` |
+//! | 1 | the format version |
+//! | n | the entries of the writes and deletes which weren't checkpointed yet |
+//!
+//! An entry is the big endian `u32` length of its sealed request, the sealed request and the first 8 bytes of the
+//! SHA-256 digest of the length and the sealed request.  A request is its kind, 1 for a write and 2 for a delete, the
+//! `u32` length of its id, its id and its data, sealed with the key of the log and its position in the log as AD.
+
+use crate::{
+    crypto_box::{open_box, seal_box, AssociatedData, BoxProvider, Key},
+    persist_hooks::{create, sync_dir},
+    vault::{DeleteRequest, ListResult, ReadRequest, ReadResult, Store, WriteRequest},
+};
+
+use std::{
+    collections::HashMap,
+    convert::TryInto,
+    fs::{File, OpenOptions},
+    io::{Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+use sha2::{Digest, Sha256};
+use zeroize::Zeroize;
+
+/// the magic bytes starting a log file
+pub const MAGIC: [u8; 4] = *b"RCWL";
+/// the current format version
+pub const VERSION: u8 = 1;
+
+const HEADER_LEN: u64 = 5;
+const WRITE: u8 = 1;
+const DELETE: u8 = 2;
+const CHECKSUM_LEN: usize = 8;
+
+/// How often a `WalStore` syncs its log.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Durability {
+    /// sync the log and apply every request before it returns
+    EveryWrite,
+    /// sync the log and apply the requests once this many are pending, or by `WalStore::sync`.  The pending requests
+    /// are lost by a crash.
+    Batched(usize),
+}
+
+/// The steps of applying the logged requests, see `WalStore::crash_before`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WalStep {
+    /// applying the synced requests to the store
+    Apply,
+    /// truncating the log once the requests are applied
+    Checkpoint,
+}
+
+/// The outcome of replaying the log in `WalStore::open`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WalRecovery {
+    /// the number of complete entries which were applied to the store again
+    pub replayed: usize,
+    /// the bytes of an entry torn by a crash which were discarded
+    pub discarded: u64,
+}
+
+/// A `Store` which seals every write and delete into a log file and syncs it before the requests are applied to the
+/// inner store, so the store can always be brought back to a state before or after a request.  An interrupted
+/// request is replayed, or discarded if its entry is incomplete, by the next `open`.  The log is truncated once its
+/// requests are applied.
+///
+/// The inner store must persist a request before it returns, like `FileStore`.  Only a single `WalStore` may use a
+/// log at a time.
+pub struct WalStore<P: BoxProvider, S: Store> {
+    key: Key<P>,
+    store: S,
+    path: PathBuf,
+    log: File,
+    len: u64,
+    entries: u64,
+    durability: Durability,
+    // the requests which are logged but not applied, `None` for the deleted ids
+    pending: Vec<(Vec<u8>, Option<Vec<u8>>)>,
+    staged: HashMap<Vec<u8>, Option<Vec<u8>>>,
+    recovery: WalRecovery,
+    #[cfg(feature = "test-utils")]
+    crash_before: Option<WalStep>,
+}
+
+impl<P: BoxProvider, S: Store> WalStore<P, S> {
+    /// Opens the log at `path`, sealed with `key`, in front of `store`, or creates it if there is none.  The
+    /// complete entries of the log are applied to the store and the log is truncated, see `recovery`.  Fails with
+    /// `Error::AuthenticationFailed` if an entry doesn't open with the key and with `Error::CorruptStore` if the file
+    /// isn't a log or an entry before the last one is corrupt.
+    pub fn open(key: Key<P>, store: S, path: impl Into<PathBuf>, durability: Durability) -> crate::Result<Self> {
+        let path = path.into();
+        if !path.exists() {
+            let mut log = create(&path)?;
+            log.write_all(&MAGIC)?;
+            log.write_all(&[VERSION])?;
+            log.sync_all()?;
+            sync_dir(&path)?;
+        }
+        let log = OpenOptions::new().read(true).write(true).open(&path)?;
+        let mut wal = Self {
+            key,
+            store,
+            path,
+            log,
+            len: HEADER_LEN,
+            entries: 0,
+            durability,
+            pending: Vec::new(),
+            staged: HashMap::new(),
+            recovery: WalRecovery::default(),
+            #[cfg(feature = "test-utils")]
+            crash_before: None,
+        };
+        wal.replay()?;
+        Ok(wal)
+    }
+
+    /// the outcome of replaying the log when it was opened
+    pub fn recovery(&self) -> WalRecovery {
+        self.recovery
+    }
+
+    /// the path of the log
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// the inner store, without the pending requests
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    /// the number of requests which are logged but not synced and applied yet
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Syncs the log, applies the pending requests to the store and truncates the log.
+    pub fn sync(&mut self) -> crate::Result<()> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        self.log.sync_data()?;
+        self.step(WalStep::Apply)?;
+        for (id, data) in std::mem::take(&mut self.pending) {
+            match data {
+                Some(data) => self.store.write(WriteRequest::new(id, data))?,
+                None => self.store.delete(DeleteRequest::new(id))?,
+            }
+        }
+        self.staged.clear();
+        self.step(WalStep::Checkpoint)?;
+        self.checkpoint()
+    }
+
+    /// syncs the pending requests and returns the store
+    pub fn into_store(mut self) -> crate::Result<S> {
+        self.sync()?;
+        Ok(self.store)
+    }
+
+    /// Stops the next `sync` before `step` as if the process crashed, it fails with `Error::InterfaceErrorDetailed`
+    /// and leaves the log and the store as they are.  Open the log again afterwards.
+    #[cfg(feature = "test-utils")]
+    pub fn crash_before(&mut self, step: WalStep) {
+        self.crash_before = Some(step);
+    }
+
+    /// applies the complete entries of the log to the store and truncates it
+    fn replay(&mut self) -> crate::Result<()> {
+        let mut bytes = Vec::new();
+        self.log.seek(SeekFrom::Start(0))?;
+        self.log.read_to_end(&mut bytes)?;
+        if bytes.len() < HEADER_LEN as usize || bytes[..4] != MAGIC {
+            return Err(corrupt("The file isn't a log"));
+        }
+        if bytes[4] != VERSION {
+            return Err(crate::Error::UnsupportedVersion(bytes[4]));
+        }
+
+        let mut rest = &bytes[HEADER_LEN as usize..];
+        let mut requests = Vec::new();
+        while !rest.is_empty() {
+            let sealed_len = match rest.get(..4) {
+                Some(len) => u32::from_be_bytes(len.try_into().expect("4 bytes")) as usize,
+                None => break,
+            };
+            let entry_len = 4 + sealed_len + CHECKSUM_LEN;
+            if entry_len > rest.len() {
+                break;
+            }
+            let (entry, tail) = rest.split_at(entry_len);
+            if Sha256::digest(&entry[..4 + sealed_len])[..CHECKSUM_LEN] != entry[4 + sealed_len..] {
+                if tail.is_empty() {
+                    break;
+                }
+                return Err(corrupt("An entry of the log is corrupt"));
+            }
+            let ad = entry_ad(requests.len() as u64);
+            let mut plain = open_box(&self.key, &ad, &entry[4..4 + sealed_len])?;
+            let request = decode(&plain);
+            plain.zeroize();
+            requests.push(request?);
+            rest = tail;
+        }
+        self.recovery = WalRecovery {
+            replayed: requests.len(),
+            discarded: rest.len() as u64,
+        };
+
+        for (id, data) in requests {
+            match data {
+                Some(data) => self.store.write(WriteRequest::new(id, data))?,
+                None => self.store.delete(DeleteRequest::new(id))?,
+            }
+        }
+        self.checkpoint()
+    }
+
+    /// truncates the log to its header, all of its requests are applied
+    fn checkpoint(&mut self) -> crate::Result<()> {
+        self.log.set_len(HEADER_LEN)?;
+        self.log.sync_all()?;
+        self.len = HEADER_LEN;
+        self.entries = 0;
+        Ok(())
+    }
+
+    /// seals the request into the log and syncs it by the durability
+    fn append(&mut self, id: Vec<u8>, data: Option<Vec<u8>>) -> crate::Result<()> {
+        let mut plain = encode(&id, data.as_deref());
+        let sealed = seal_box(&self.key, &entry_ad(self.entries), &plain);
+        plain.zeroize();
+        let sealed = sealed?;
+
+        let mut entry = Vec::with_capacity(4 + sealed.len() + CHECKSUM_LEN);
+        entry.extend_from_slice(&(sealed.len() as u32).to_be_bytes());
+        entry.extend_from_slice(&sealed);
+        let checksum = Sha256::digest(&entry);
+        entry.extend_from_slice(&checksum[..CHECKSUM_LEN]);
+        self.log.seek(SeekFrom::Start(self.len))?;
+        self.log.write_all(&entry)?;
+        self.len += entry.len() as u64;
+        self.entries += 1;
+
+        self.staged.insert(id.clone(), data.clone());
+        self.pending.push((id, data));
+        match self.durability {
+            Durability::EveryWrite => self.sync(),
+            Durability::Batched(max) if self.pending.len() >= max => self.sync(),
+            Durability::Batched(_) => Ok(()),
+        }
+    }
+
+    #[cfg(feature = "test-utils")]
+    fn step(&mut self, step: WalStep) -> crate::Result<()> {
+        if self.crash_before == Some(step) {
+            self.crash_before = None;
+            return Err(crate::Error::InterfaceErrorDetailed(format!(
+                "Simulated crash before {:?}",
+                step
+            )));
+        }
+        Ok(())
+    }
+
+    #[cfg(not(feature = "test-utils"))]
+    fn step(&mut self, _step: WalStep) -> crate::Result<()> {
+        Ok(())
+    }
+}
+
+impl<P: BoxProvider, S: Store> Store for WalStore<P, S> {
+    fn list(&self) -> crate::Result<ListResult> {
+        let mut ids: Vec<Vec<u8>> = self
+            .store
+            .list()?
+            .into_iter()
+            .filter(|id| !self.staged.contains_key(id))
+            .collect();
+        ids.extend(
+            self.staged
+                .iter()
+                .filter(|(_, data)| data.is_some())
+                .map(|(id, _)| id.clone()),
+        );
+        Ok(ListResult::new(ids))
+    }
+
+    fn read(&self, request: ReadRequest) -> crate::Result<Option<ReadResult>> {
+        match self.staged.get(request.id()) {
+            Some(Some(data)) => Ok(Some(ReadResult::new(request.id().to_vec(), data.clone()))),
+            Some(None) => Ok(None),
+            None => self.store.read(request),
+        }
+    }
+
+    fn write(&mut self, request: WriteRequest) -> crate::Result<()> {
+        let (id, data) = request.into();
+        self.append(id, Some(data))
+    }
+
+    fn delete(&mut self, request: DeleteRequest) -> crate::Result<()> {
+        self.append(request.into(), None)
+    }
+}
+
+fn corrupt(reason: &str) -> crate::Error {
+    crate::Error::CorruptStore(reason.into())
+}
+
+/// the AD of the `n`th entry of the log, so entries can't be dropped or reordered
+fn entry_ad(n: u64) -> Vec<u8> {
+    AssociatedData::new("vault wal")
+        .field("entry", &n.to_be_bytes())
+        .finish()
+}
+
+fn encode(id: &[u8], data: Option<&[u8]>) -> Vec<u8> {
+    let mut plain = Vec::with_capacity(5 + id.len() + data.map_or(0, <[u8]>::len));
+    plain.push(if data.is_some() { WRITE } else { DELETE });
+    plain.extend_from_slice(&(id.len() as u32).to_be_bytes());
+    plain.extend_from_slice(id);
+    plain.extend_from_slice(data.unwrap_or(&[]));
+    plain
+}
+
+fn decode(plain: &[u8]) -> crate::Result<(Vec<u8>, Option<Vec<u8>>)> {
+    let malformed = || crate::Error::InvalidEntry {
+        reason: String::from("An entry of the log is malformed"),
+    };
+    let id_len = plain.get(1..5).ok_or_else(malformed)?;
+    let id_len = u32::from_be_bytes(id_len.try_into().expect("4 bytes")) as usize;
+    let id = plain.get(5..5 + id_len).ok_or_else(malformed)?.to_vec();
+    let data = &plain[5 + id_len..];
+    match plain[0] {
+        WRITE => Ok((id, Some(data.to_vec()))),
+        DELETE if data.is_empty() => Ok((id, None)),
+        _ => Err(malformed()),
+    }
+}
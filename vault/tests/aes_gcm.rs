@@ -0,0 +1,135 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+#![cfg(feature = "provider-aes-gcm")]
+
+mod utils;
+
+use utils::hex;
+use vault::{providers::AesGcm256, BoxProvider, Decrypt, Encrypt, Error, Key};
+
+#[test]
+fn test_aes_gcm_roundtrip() {
+    let key = Key::<AesGcm256>::random().unwrap();
+
+    let sealed = b"some data".to_vec().encrypt(&key, b"ad").unwrap();
+    let opened: Vec<u8> = sealed.decrypt(&key, b"ad").unwrap();
+    assert_eq!(opened, b"some data");
+
+    let sealed = AesGcm256::box_seal(&key, b"", b"").unwrap();
+    assert_eq!(sealed.len(), AesGcm256::box_overhead());
+    assert_eq!(AesGcm256::box_open(&key, b"", &sealed).unwrap(), b"");
+}
+
+#[test]
+fn test_aes_gcm_authentication_failure() {
+    let key = Key::<AesGcm256>::random().unwrap();
+    let sealed = AesGcm256::box_seal(&key, b"ad", b"some data").unwrap();
+
+    for i in 0..sealed.len() {
+        let mut tampered = sealed.clone();
+        tampered[i] ^= 1;
+        assert!(matches!(
+            AesGcm256::box_open(&key, b"ad", &tampered),
+            Err(Error::AuthenticationFailed)
+        ));
+    }
+    // data too short for a box is malformed, a shorter box doesn't authenticate
+    for len in 0..sealed.len() {
+        match AesGcm256::box_open(&key, b"ad", &sealed[..len]) {
+            Err(Error::MalformedCiphertext { expected_min, got }) => {
+                assert_eq!((expected_min, got), (AesGcm256::box_overhead(), len));
+            }
+            Err(Error::AuthenticationFailed) => assert!(len >= AesGcm256::box_overhead()),
+            other => panic!("unexpected result for {} bytes: {:?}", len, other),
+        }
+    }
+    assert!(matches!(
+        AesGcm256::box_open(&key, b"other ad", &sealed),
+        Err(Error::AuthenticationFailed)
+    ));
+}
+
+/// test cases 13 to 16 of the GCM specification submitted to NIST
+#[test]
+fn test_aes_gcm_nist_vectors() {
+    let zero_key = "0000000000000000000000000000000000000000000000000000000000000000";
+    let key = "feffe9928665731c6d6a8f9467308308feffe9928665731c6d6a8f9467308308";
+    let plain = "d9313225f88406e5a55909c5aff5269a86a7a9531534f7da2e4c303d8a318a721c3c0c95956809532fcf0e2449a6b525\
+                 b16aedf5aa0de657ba637b39";
+    let cipher = "522dc1f099567d07f47f37a32a84427d643a8cdcbfe5c0c97598a2bd2555d1aa8cb08e48590dbb3da7b08b1056828838\
+                  c5f61e6393ba7a0abcc9f662";
+
+    let vectors = [
+        (
+            zero_key,
+            "000000000000000000000000",
+            "",
+            "",
+            "",
+            "530f8afbc74536b9a963b4f1c4cb738b",
+        ),
+        (
+            zero_key,
+            "000000000000000000000000",
+            "",
+            "00000000000000000000000000000000",
+            "cea7403d4d606b6e074ec5d3baf39d18",
+            "d0d1c8a799996bf0265b98b5d48ab919",
+        ),
+        (
+            key,
+            "cafebabefacedbaddecaf888",
+            "",
+            "d9313225f88406e5a55909c5aff5269a86a7a9531534f7da2e4c303d8a318a721c3c0c95956809532fcf0e2449a6b525\
+             b16aedf5aa0de657ba637b391aafd255",
+            "522dc1f099567d07f47f37a32a84427d643a8cdcbfe5c0c97598a2bd2555d1aa8cb08e48590dbb3da7b08b1056828838\
+             c5f61e6393ba7a0abcc9f662898015ad",
+            "b094dac5d93471bdec1a502270e3cc6c",
+        ),
+        (
+            key,
+            "cafebabefacedbaddecaf888",
+            "feedfacedeadbeeffeedfacedeadbeefabaddad2",
+            plain,
+            cipher,
+            "76fc6ece0f4e1768cddf8853bb2d551b",
+        ),
+    ];
+
+    for (key, nonce, ad, plain, cipher, tag) in vectors.iter() {
+        let key = Key::<AesGcm256>::load(hex(key)).unwrap();
+        let sealed = [hex(nonce), hex(cipher), hex(tag)].concat();
+        assert_eq!(AesGcm256::box_open(&key, &hex(ad), &sealed).unwrap(), hex(plain));
+    }
+}
+
+#[cfg(feature = "provider-xchacha")]
+#[test]
+fn test_aes_gcm_xchacha_interop() {
+    use vault::providers::XChaChaPoly;
+
+    let key = Key::<AesGcm256>::random().unwrap();
+    let other: Key<XChaChaPoly> = key.clone().convert().unwrap();
+
+    // long enough for a box of both providers
+    let sealed = AesGcm256::box_seal(&key, b"ad", b"some longer data").unwrap();
+    assert!(matches!(
+        XChaChaPoly::box_open(&other, b"ad", &sealed),
+        Err(Error::AuthenticationFailed)
+    ));
+
+    let sealed = XChaChaPoly::box_seal(&other, b"ad", b"some data").unwrap();
+    assert!(matches!(
+        AesGcm256::box_open(&key, b"ad", &sealed),
+        Err(Error::AuthenticationFailed)
+    ));
+}
@@ -0,0 +1,179 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+#![cfg(feature = "age-export")]
+
+use std::io::Read;
+
+use age::secrecy::SecretString;
+use vault::{export_age_with_work_factor, import_age, Error, AGE_MAX_WORK_FACTOR};
+
+/// the passphrase of the files in `tests/age`, which the `age` crate of rage encrypted with a work factor of 10
+const PASSPHRASE: &str = "correct horse battery staple";
+
+/// the plaintexts of the files in `tests/age`
+fn pattern(len: usize) -> Vec<u8> {
+    (0..len).map(|i| (i % 251) as u8).collect()
+}
+
+/// decrypt with the `age` crate
+fn rage_decrypt(passphrase: &str, data: &[u8]) -> Vec<u8> {
+    let identity = age::scrypt::Identity::new(SecretString::from(passphrase.to_owned()));
+    let mut reader = age::Decryptor::new(data)
+        .unwrap()
+        .decrypt(std::iter::once(&identity as &dyn age::Identity))
+        .unwrap();
+    let mut plain = Vec::new();
+    reader.read_to_end(&mut plain).unwrap();
+    plain
+}
+
+fn age_error(result: vault::Result<Vec<u8>>) -> String {
+    match result {
+        Err(Error::AgeError(message)) => message,
+        other => panic!("expected an age error, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_age_reference_files() {
+    let files: [(&[u8], usize); 4] = [
+        (include_bytes!("age/empty.age"), 0),
+        (include_bytes!("age/short.age"), 20),
+        (include_bytes!("age/one_chunk.age"), 65536),
+        (include_bytes!("age/two_chunks.age"), 65537),
+    ];
+    for (file, len) in files.iter() {
+        assert_eq!(import_age(PASSPHRASE, file).unwrap(), pattern(*len));
+        assert!(matches!(
+            import_age("another passphrase", file),
+            Err(Error::AuthenticationFailed)
+        ));
+    }
+}
+
+#[test]
+fn test_age_decrypts_with_rage() {
+    for len in [0, 20, 65535, 65536, 65537, 3 * 65536 + 5] {
+        let plain = pattern(len);
+        let file = export_age_with_work_factor(PASSPHRASE, &plain, 10).unwrap();
+        assert!(file.starts_with(b"age-encryption.org/v1\n-> scrypt "));
+        assert_eq!(rage_decrypt(PASSPHRASE, &file), plain);
+        assert_eq!(import_age(PASSPHRASE, &file).unwrap(), plain);
+    }
+
+    // the header ends with the MAC line, the 16 byte nonce and the chunks of 64 KiB and a 16 byte tag follow
+    let file = export_age_with_work_factor(PASSPHRASE, &pattern(65537), 10).unwrap();
+    let header = file.windows(5).position(|w| w == b"\n--- ").unwrap() + 5 + 43 + 1;
+    assert_eq!(file.len(), header + 16 + 65536 + 16 + 1 + 16);
+    let header = std::str::from_utf8(&file[..header]).unwrap();
+    let lines: Vec<_> = header.lines().collect();
+    assert_eq!(lines.len(), 4);
+    assert!(lines[1].ends_with(" 10"), "{}", lines[1]);
+    assert_eq!(lines[2].len(), 43);
+}
+
+#[test]
+fn test_age_header_mac() {
+    let file = include_bytes!("age/short.age").to_vec();
+    let mac = file.windows(4).position(|w| w == b"--- ").unwrap() + 4;
+
+    let mut modified = file.clone();
+    modified[mac] = if modified[mac] == b'A' { b'B' } else { b'A' };
+    assert!(matches!(
+        import_age(PASSPHRASE, &modified),
+        Err(Error::AuthenticationFailed)
+    ));
+
+    // a MAC of another length
+    let mut modified = file.clone();
+    modified.drain(mac..mac + 4);
+    assert!(matches!(
+        import_age(PASSPHRASE, &modified),
+        Err(Error::AuthenticationFailed)
+    ));
+
+    // a modified payload nonce
+    let mut modified = file.clone();
+    let nonce = file.iter().skip(mac).position(|b| *b == b'\n').unwrap() + mac + 1;
+    modified[nonce] ^= 1;
+    assert!(matches!(
+        import_age(PASSPHRASE, &modified),
+        Err(Error::AuthenticationFailed)
+    ));
+}
+
+#[test]
+fn test_age_truncated() {
+    let file = include_bytes!("age/two_chunks.age");
+    // within the final chunk, and the whole final chunk, which makes the full chunk before it the last one
+    for len in [file.len() - 17, file.len() - 16 - 1 - 16] {
+        assert!(matches!(
+            import_age(PASSPHRASE, &file[..len]),
+            Err(Error::AuthenticationFailed)
+        ));
+    }
+    // a final chunk without plaintext or shorter than its tag
+    assert!(age_error(import_age(PASSPHRASE, &file[..file.len() - 1])).contains("Truncated"));
+    assert!(age_error(import_age(PASSPHRASE, &file[..file.len() - 2])).contains("Truncated"));
+    assert!(import_age(PASSPHRASE, &[&file[..], &[0]].concat()).is_err());
+
+    // a truncated full chunk
+    let file = include_bytes!("age/one_chunk.age");
+    for len in [file.len() - 1, file.len() - 16] {
+        assert!(matches!(
+            import_age(PASSPHRASE, &file[..len]),
+            Err(Error::AuthenticationFailed)
+        ));
+    }
+    // anywhere in the header and the nonce
+    let header = file.windows(5).position(|w| w == b"\n--- ").unwrap() + 5 + 43 + 1 + 16;
+    for len in 0..header + 16 {
+        assert!(import_age(PASSPHRASE, &file[..len]).is_err());
+    }
+}
+
+#[test]
+fn test_age_rejects() {
+    let file = include_bytes!("age/short.age");
+    let text = |file: &[u8]| String::from_utf8_lossy(file).into_owned();
+    let stanza = text(&file[..]).lines().nth(1).unwrap().to_owned();
+    let replace = |from: &str, to: &str| text(&file[..]).replacen(from, to, 1).into_bytes();
+
+    assert!(age_error(import_age(PASSPHRASE, b"age-encryption.org/v2\n")).contains("age v1"));
+
+    let message = age_error(import_age(PASSPHRASE, &replace(" 10\n", " 23\n")));
+    assert!(message.contains(&AGE_MAX_WORK_FACTOR.to_string()), "{}", message);
+    for factor in [" 010\n", " 0\n", " +10\n", " 1O\n"] {
+        assert!(age_error(import_age(PASSPHRASE, &replace(" 10\n", factor))).contains("work factor"));
+    }
+
+    let body = text(&file[..]).lines().nth(2).unwrap().to_owned();
+    let x25519 = "-> X25519 TEiF0ypqr+bpvcqXNyCVJpL7OuwPdVwPL7KQEbFDOCc";
+    let message = age_error(import_age(PASSPHRASE, &replace(&stanza, x25519)));
+    assert!(message.contains("passphrase"), "{}", message);
+    let message = age_error(import_age(
+        PASSPHRASE,
+        &replace(&stanza, &format!("{}\n{}\n{}", x25519, body, stanza)),
+    ));
+    assert!(message.contains("only stanza"), "{}", message);
+
+    // padding and an empty body line
+    assert!(import_age(PASSPHRASE, &replace(&body, &format!("{}=", body))).is_err());
+    assert!(import_age(PASSPHRASE, &replace("\n---", "\n\n---")).is_err());
+    assert!(age_error(import_age(PASSPHRASE, &replace("-> ", "->  "))).contains("arguments"));
+
+    assert!(matches!(
+        export_age_with_work_factor(PASSPHRASE, b"", 0),
+        Err(Error::InterfaceErrorDetailed(_))
+    ));
+    assert!(export_age_with_work_factor(PASSPHRASE, b"", AGE_MAX_WORK_FACTOR + 1).is_err());
+}
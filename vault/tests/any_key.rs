@@ -0,0 +1,106 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+mod utils;
+
+use std::convert::Infallible;
+
+use utils::provider::Provider;
+use vault::{open_with_any, Decrypt, Encrypt, Error, Key};
+
+struct Plain(Vec<u8>);
+struct Sealed(Vec<u8>);
+
+impl AsRef<[u8]> for Plain {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl AsRef<[u8]> for Sealed {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl From<Vec<u8>> for Plain {
+    fn from(data: Vec<u8>) -> Self {
+        Self(data)
+    }
+}
+
+impl From<Vec<u8>> for Sealed {
+    fn from(data: Vec<u8>) -> Self {
+        Self(data)
+    }
+}
+
+impl Encrypt<Sealed> for Plain {}
+impl Decrypt<Infallible, Plain> for Sealed {}
+
+#[test]
+fn test_any_key_index() {
+    let old = Key::<Provider>::random().unwrap();
+    let new = Key::<Provider>::random().unwrap();
+    let sealed = Plain(b"record".to_vec()).encrypt(&old, b"ad").unwrap();
+
+    let (index, plain): (usize, Plain) = sealed.decrypt_with_any(&[&new, &old], b"ad").unwrap();
+    assert_eq!(index, 1);
+    assert_eq!(plain.0, b"record");
+
+    let (index, plain): (usize, Plain) = sealed.decrypt_with_any(&[&old, &new], b"ad").unwrap();
+    assert_eq!(index, 0);
+    assert_eq!(plain.0, b"record");
+
+    assert_eq!(
+        open_with_any(&[&new, &old], b"ad", &sealed.0).unwrap(),
+        (1, b"record".to_vec())
+    );
+}
+
+#[test]
+fn test_any_key_no_match() {
+    let key = Key::<Provider>::random().unwrap();
+    let others: Vec<_> = (0..3).map(|_| Key::<Provider>::random().unwrap()).collect();
+    let others: Vec<_> = others.iter().collect();
+    let sealed = Plain(b"record".to_vec()).encrypt(&key, b"ad").unwrap();
+
+    assert!(matches!(
+        Decrypt::<Infallible, Plain>::decrypt_with_any(&sealed, &others, b"ad"),
+        Err(Error::NoMatchingKey { attempts: 3 })
+    ));
+    // the right key with the wrong AD doesn't match either
+    assert!(matches!(
+        open_with_any(&[&key], b"other ad", &sealed.0),
+        Err(Error::NoMatchingKey { attempts: 1 })
+    ));
+}
+
+#[test]
+fn test_any_key_malformed() {
+    let key = Key::<Provider>::random().unwrap();
+
+    match open_with_any(&[&key, &key], b"ad", &[0; 8]) {
+        Err(Error::MalformedCiphertext { got: 8, .. }) => {}
+        other => panic!("a truncated box must fail before the keys are tried: {:?}", other),
+    }
+}
+
+#[test]
+fn test_any_key_empty() {
+    let key = Key::<Provider>::random().unwrap();
+    let sealed = Plain(b"record".to_vec()).encrypt(&key, b"ad").unwrap();
+
+    assert!(matches!(
+        open_with_any::<Provider>(&[], b"ad", &sealed.0),
+        Err(Error::InterfaceErrorDetailed(_))
+    ));
+}
@@ -0,0 +1,273 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+mod utils;
+
+use std::convert::Infallible;
+
+use utils::provider::Provider;
+use vault::{
+    armor::{self, SEALED_DATA_LABEL},
+    Decrypt, Encrypt, Error, Key,
+};
+
+/// armored form of the key bytes `0..32`
+const ARMORED: &str = "vaultkey1qqsqqqgzqvzq2ps8pqys5zcvp58q7yq3zgf3g9gkzuvpjxsmrsw3u8l425dax";
+/// armored form of the 16 byte key `0..16`
+const ARMORED_SHORT: &str = "vaultkey1qqgqqqgzqvzq2ps8pqys5zcvp58q7egv07mq";
+
+const CHARSET: &str = "qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+/// PEM block of `123456789`, whose CRC-24 is the check value `21cf02`
+const PEM: &str = "-----BEGIN VAULT SEALED DATA-----\nMTIzNDU2Nzg5\n=Ic8C\n-----END VAULT SEALED DATA-----\n";
+
+struct Plain(Vec<u8>);
+struct Armored(String);
+
+impl AsRef<[u8]> for Plain {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl AsRef<[u8]> for Armored {
+    fn as_ref(&self) -> &[u8] {
+        self.0.as_bytes()
+    }
+}
+
+impl From<Vec<u8>> for Plain {
+    fn from(data: Vec<u8>) -> Self {
+        Self(data)
+    }
+}
+
+impl Encrypt<Plain> for Plain {}
+impl Decrypt<Infallible, Plain> for Armored {}
+
+fn pattern(len: usize) -> Vec<u8> {
+    (0..len).map(|i| (i * 7) as u8).collect()
+}
+
+#[test]
+fn test_armor_roundtrip() {
+    let key = Key::<Provider>::load((0..32).collect()).unwrap();
+    assert_eq!(key.to_armored(), ARMORED);
+    assert_eq!(Key::<Provider>::from_armored(ARMORED).unwrap(), key);
+
+    let key = Key::<Provider>::random().unwrap();
+    assert_eq!(Key::<Provider>::from_armored(&key.to_armored()).unwrap(), key);
+}
+
+#[test]
+fn test_armor_whitespace_and_case() {
+    let key = Key::<Provider>::from_armored(ARMORED).unwrap();
+    let armored = format!(" \t{}\n", ARMORED.to_uppercase());
+    assert_eq!(Key::<Provider>::from_armored(&armored).unwrap(), key);
+}
+
+#[test]
+fn test_armor_detects_corruption() {
+    let prefix = "vaultkey1".len();
+    for index in prefix..ARMORED.len() {
+        for replacement in CHARSET.chars() {
+            let mut corrupted = ARMORED.to_string();
+            if corrupted[index..].starts_with(replacement) {
+                continue;
+            }
+            corrupted.replace_range(index..index + 1, &replacement.to_string());
+            assert!(
+                Key::<Provider>::from_armored(&corrupted).is_err(),
+                "corruption at index {} not detected",
+                index
+            );
+        }
+    }
+
+    let flipped = ARMORED.replacen("u8l4", "u8l5", 1);
+    match Key::<Provider>::from_armored(&flipped) {
+        Err(Error::ArmorChecksumMismatch) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn test_armor_rejects_malformed() {
+    for len in 0..ARMORED.len() {
+        assert!(Key::<Provider>::from_armored(&ARMORED[..len]).is_err());
+    }
+    assert!(matches!(
+        Key::<Provider>::from_armored(&ARMORED.replacen("vaultkey1", "vaultkey2", 1)),
+        Err(Error::ArmorError(_))
+    ));
+    assert!(matches!(
+        Key::<Provider>::from_armored(&ARMORED.replacen('q', "b", 1)),
+        Err(Error::ArmorError(_))
+    ));
+}
+
+#[test]
+fn test_armor_rejects_wrong_length() {
+    assert!(matches!(
+        Key::<Provider>::from_armored(ARMORED_SHORT),
+        Err(Error::InvalidKeyLength {
+            expected: 32,
+            actual: 16
+        })
+    ));
+}
+
+#[test]
+fn test_pem_golden() {
+    assert_eq!(armor::to_pem(SEALED_DATA_LABEL, b"123456789"), PEM);
+    assert_eq!(armor::from_pem(SEALED_DATA_LABEL, PEM).unwrap(), b"123456789");
+
+    // the CRC-24 of no data is its initial value `b704ce`
+    let empty = "-----BEGIN KEY-----\n=twTO\n-----END KEY-----\n";
+    assert_eq!(armor::to_pem("KEY", b""), empty);
+    assert!(armor::from_pem("KEY", empty).unwrap().is_empty());
+}
+
+#[test]
+fn test_pem_roundtrip() {
+    for len in 0..200 {
+        let data = pattern(len);
+        let pem = armor::to_pem(SEALED_DATA_LABEL, &data);
+        let lines: Vec<_> = pem.lines().collect();
+        assert_eq!(lines.len(), 3 + (len.div_ceil(3) * 4).div_ceil(64));
+        let body = &lines[1..lines.len() - 2];
+        if let Some((last, full)) = body.split_last() {
+            assert!(full.iter().all(|line| line.len() == 64));
+            assert!(last.len() <= 64);
+        }
+        assert_eq!(armor::from_pem(SEALED_DATA_LABEL, &pem).unwrap(), data);
+    }
+}
+
+#[test]
+fn test_pem_tolerates_formatting() {
+    let data = pattern(100);
+    let pem = armor::to_pem(SEALED_DATA_LABEL, &data);
+
+    let crlf = pem.replace('\n', "\r\n");
+    let indented = pem.lines().map(|line| format!("  \t{}\n", line)).collect::<String>();
+    let prose = format!(
+        "Hi,\n\nthe data is below.\n\n{}\nThanks!\n-----END VAULT SEALED DATA-----\n",
+        pem
+    );
+    // reflowed lines and empty lines, as left by mail clients
+    let body: String = pem.lines().skip(1).take(3).collect();
+    let reflowed = format!(
+        "-----BEGIN VAULT SEALED DATA-----\n{}\n\n{}\n{}\n{}\n-----END VAULT SEALED DATA-----",
+        &body[..50],
+        &body[50..100],
+        &body[100..],
+        pem.lines().nth(4).unwrap()
+    );
+    for text in [crlf, indented, prose, reflowed] {
+        assert_eq!(armor::from_pem(SEALED_DATA_LABEL, &text).unwrap(), data, "{}", text);
+    }
+}
+
+#[test]
+fn test_pem_detects_corruption() {
+    let pem = armor::to_pem(SEALED_DATA_LABEL, &pattern(100));
+    let start = "-----BEGIN VAULT SEALED DATA-----\n".len();
+    let end = pem.len() - "\n-----END VAULT SEALED DATA-----\n".len();
+    for index in start..end {
+        if pem[index..].starts_with(['\n', '=']) {
+            continue;
+        }
+        let mut corrupted = pem.clone();
+        let replacement = if pem[index..].starts_with('A') { "B" } else { "A" };
+        corrupted.replace_range(index..index + 1, replacement);
+        assert!(
+            armor::from_pem(SEALED_DATA_LABEL, &corrupted).is_err(),
+            "corruption at index {} not detected",
+            index
+        );
+    }
+
+    // the error doesn't tell the checksums
+    let mismatch = armor::from_pem(SEALED_DATA_LABEL, &PEM.replacen("MTIz", "MTIy", 1)).unwrap_err();
+    assert_eq!(mismatch, Error::ArmorChecksumMismatch);
+    assert!(!mismatch.to_string().contains("21cf02"));
+    assert_eq!(
+        armor::from_pem(SEALED_DATA_LABEL, &PEM.replacen("Ic8C", "Ic8D", 1)),
+        Err(Error::ArmorChecksumMismatch)
+    );
+}
+
+fn armor_error(result: vault::Result<Vec<u8>>) -> String {
+    match result {
+        Err(Error::ArmorError(message)) => message,
+        other => panic!("expected an armor error, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_pem_rejects_malformed() {
+    match armor::from_pem("VAULT KEY", PEM) {
+        Err(Error::ArmorLabelMismatch { expected, found }) => {
+            assert_eq!(expected, "VAULT KEY");
+            assert_eq!(found, SEALED_DATA_LABEL);
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+
+    let message = armor_error(armor::from_pem(
+        SEALED_DATA_LABEL,
+        &PEM.replacen("END VAULT SEALED DATA", "END VAULT KEY", 1),
+    ));
+    assert!(
+        message.contains("END VAULT KEY") && message.contains("line `4`"),
+        "{}",
+        message
+    );
+    assert!(armor_error(armor::from_pem(SEALED_DATA_LABEL, "no armor here")).contains("BEGIN"));
+    assert!(armor_error(armor::from_pem(SEALED_DATA_LABEL, &PEM[..PEM.len() - 33])).contains("END"));
+    assert!(armor_error(armor::from_pem(SEALED_DATA_LABEL, &PEM.replacen("=Ic8C\n", "", 1))).contains("checksum"));
+    assert!(armor_error(armor::from_pem(SEALED_DATA_LABEL, &PEM.replacen("=Ic8C", "=Ic8", 1))).contains("line `3`"));
+
+    let message = armor_error(armor::from_pem(SEALED_DATA_LABEL, &PEM.replacen("MTIz", "MT-z", 1)));
+    assert!(message.contains("`-` on line `2`"), "{}", message);
+    let message = armor_error(armor::from_pem(
+        SEALED_DATA_LABEL,
+        &PEM.replacen("=Ic8C\n", "=Ic8C\nMTIz\n", 1),
+    ));
+    assert!(message.contains("after the checksum"), "{}", message);
+    assert!(armor_error(armor::from_pem(
+        SEALED_DATA_LABEL,
+        &PEM.replacen("MTIzNDU2Nzg5", "MTIzNDU2Nzg", 1)
+    ))
+    .contains("Base64"));
+}
+
+#[test]
+fn test_encrypt_armored() {
+    let key = Key::<Provider>::random().unwrap();
+    let armored = Plain(b"some data".to_vec()).encrypt_armored(&key, b"ad").unwrap();
+    assert!(armored.starts_with("-----BEGIN VAULT SEALED DATA-----\n"));
+    assert!(armored.ends_with("-----END VAULT SEALED DATA-----\n"));
+
+    let pasted = Armored(format!("ticket #42:\r\n{}\r\n", armored.replace('\n', "\r\n")));
+    assert_eq!(pasted.decrypt_armored(&key, b"ad").unwrap().0, b"some data");
+    assert!(matches!(
+        pasted.decrypt_armored(&key, b"other ad"),
+        Err(Error::AuthenticationFailed)
+    ));
+
+    let other = armor::to_pem("VAULT KEY", &armor::from_pem(SEALED_DATA_LABEL, &armored).unwrap());
+    assert!(matches!(
+        Armored(other).decrypt_armored(&key, b"ad"),
+        Err(Error::ArmorLabelMismatch { .. })
+    ));
+}
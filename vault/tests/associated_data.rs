@@ -0,0 +1,91 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+mod utils;
+
+use utils::provider::Provider;
+use vault::{AssociatedData, Decrypt, Encrypt, Error, Key};
+
+#[test]
+fn test_associated_data_canonical() {
+    let split = |a: &[u8], b: &[u8]| AssociatedData::new("test").field("a", a).field("b", b).finish();
+    assert_ne!(split(b"ab", b"c"), split(b"a", b"bc"));
+    assert_ne!(split(b"", b"abc"), split(b"abc", b""));
+
+    // the names and the domain are part of the encoding as well
+    assert_ne!(
+        AssociatedData::new("test").field("ab", b"c").finish(),
+        AssociatedData::new("test").field("a", b"bc").finish()
+    );
+    assert_ne!(
+        AssociatedData::new("test").field("a", b"").finish(),
+        AssociatedData::new("test").finish()
+    );
+    assert_ne!(
+        AssociatedData::new("ab").field("c", b"").finish(),
+        AssociatedData::new("a").field("bc", b"").finish()
+    );
+
+    // the encoding is stable
+    let ad = AssociatedData::new("d").field("n", &[1]);
+    assert_eq!(
+        ad.as_bytes(),
+        &b"vault-ad-v1\0\0\0\0\0\0\0\x01d\0\0\0\0\0\0\0\x01n\0\0\0\0\0\0\0\x01\x01"[..]
+    );
+    assert_eq!(ad.clone().finish(), ad.as_bytes());
+}
+
+#[test]
+fn test_associated_data_roundtrip() {
+    let key = Key::<Provider>::random().unwrap();
+    let ad = || {
+        AssociatedData::new("record")
+            .field("record_id", b"id")
+            .field("version", &[1])
+    };
+
+    let sealed = b"some data".to_vec().encrypt_ad(&key, &ad()).unwrap();
+    let opened: Vec<u8> = sealed.decrypt_ad(&key, &ad()).unwrap();
+    assert_eq!(opened, b"some data");
+
+    let other_domain = AssociatedData::new("payload")
+        .field("record_id", b"id")
+        .field("version", &[1]);
+    let other_field = AssociatedData::new("record")
+        .field("record_id", b"id")
+        .field("version", &[2]);
+    for other in [other_domain, other_field].iter() {
+        let opened: vault::Result<Vec<u8>> = sealed.decrypt_ad(&key, other);
+        assert!(matches!(opened, Err(Error::AuthenticationFailed)));
+    }
+}
+
+#[test]
+fn test_associated_data_raw_compatible() {
+    let key = Key::<Provider>::random().unwrap();
+    let ad = AssociatedData::new("record").field("record_id", b"id");
+
+    // typed AD is the same as its bytes given as raw AD
+    let sealed = b"some data".to_vec().encrypt_ad(&key, &ad).unwrap();
+    let opened: Vec<u8> = sealed.decrypt(&key, ad.as_bytes()).unwrap();
+    assert_eq!(opened, b"some data");
+
+    let sealed = b"some data".to_vec().encrypt(&key, &ad.clone().finish()).unwrap();
+    let opened: Vec<u8> = sealed.decrypt_ad(&key, &ad).unwrap();
+    assert_eq!(opened, b"some data");
+
+    // raw AD keeps working and doesn't open with typed AD
+    let sealed = b"some data".to_vec().encrypt(&key, b"id").unwrap();
+    let opened: Vec<u8> = sealed.decrypt(&key, b"id").unwrap();
+    assert_eq!(opened, b"some data");
+    let opened: vault::Result<Vec<u8>> = sealed.decrypt_ad(&key, &ad);
+    assert!(matches!(opened, Err(Error::AuthenticationFailed)));
+}
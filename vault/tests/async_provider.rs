@@ -0,0 +1,158 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+#![cfg(feature = "async")]
+
+mod utils;
+
+use std::{
+    convert::Infallible,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use utils::provider::Provider;
+use vault::{AsyncBoxProvider, BoxProvider, DecryptAsync, EncryptAsync, Error, Key};
+
+struct Plain(Vec<u8>);
+struct Sealed(Vec<u8>);
+
+impl AsRef<[u8]> for Plain {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl AsRef<[u8]> for Sealed {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl From<Vec<u8>> for Plain {
+    fn from(data: Vec<u8>) -> Self {
+        Self(data)
+    }
+}
+
+impl From<Vec<u8>> for Sealed {
+    fn from(data: Vec<u8>) -> Self {
+        Self(data)
+    }
+}
+
+impl EncryptAsync<Sealed> for Plain {}
+impl DecryptAsync<Infallible, Plain> for Sealed {}
+
+const LATENCY: Duration = Duration::from_millis(100);
+
+/// the number of requests in flight and the most there have been at once
+static IN_FLIGHT: AtomicUsize = AtomicUsize::new(0);
+static MAX_IN_FLIGHT: AtomicUsize = AtomicUsize::new(0);
+
+/// a mock of a remote KMS, every request takes `LATENCY`
+struct Kms;
+
+impl Kms {
+    async fn round_trip() {
+        let in_flight = IN_FLIGHT.fetch_add(1, Ordering::SeqCst) + 1;
+        MAX_IN_FLIGHT.fetch_max(in_flight, Ordering::SeqCst);
+        tokio::time::sleep(LATENCY).await;
+        IN_FLIGHT.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+#[async_trait]
+impl AsyncBoxProvider for Kms {
+    type Marker = Provider;
+    type Error = Error;
+
+    async fn box_seal(key: &Key<Provider>, ad: &[u8], data: &[u8]) -> vault::Result<Vec<u8>> {
+        Self::round_trip().await;
+        <Provider as BoxProvider>::box_seal(key, ad, data)
+    }
+
+    async fn box_open(key: &Key<Provider>, ad: &[u8], data: &[u8]) -> vault::Result<Vec<u8>> {
+        Self::round_trip().await;
+        <Provider as BoxProvider>::box_open(key, ad, data)
+    }
+
+    async fn random_buf(buf: &mut [u8]) -> vault::Result<()> {
+        Self::round_trip().await;
+        <Provider as BoxProvider>::random_buf(buf)
+    }
+}
+
+#[tokio::test]
+async fn test_async_roundtrip() {
+    let key = Key::<Provider>::random().unwrap();
+
+    let sealed: Sealed = Plain(b"some data".to_vec())
+        .encrypt_async::<Kms>(&key, b"ad")
+        .await
+        .unwrap();
+    let opened: Plain = sealed.decrypt_async::<Kms>(&key, b"ad").await.unwrap();
+    assert_eq!(opened.0, b"some data");
+
+    let opened: vault::Result<Plain> = sealed.decrypt_async::<Kms>(&key, b"other ad").await;
+    assert!(opened.is_err());
+
+    let mut buf = [0; 32];
+    Kms::random_buf(&mut buf).await.unwrap();
+    assert_ne!(buf, [0; 32]);
+}
+
+#[tokio::test]
+async fn test_async_concurrent_seals_interleave() {
+    let key = Key::<Provider>::random().unwrap();
+    let plains: Vec<Plain> = (0..4u8).map(|i| Plain(vec![i; 16])).collect();
+
+    let start = Instant::now();
+    let (a, b, c, d) = tokio::join!(
+        plains[0].encrypt_async::<Kms>(&key, b""),
+        plains[1].encrypt_async::<Kms>(&key, b""),
+        plains[2].encrypt_async::<Kms>(&key, b""),
+        plains[3].encrypt_async::<Kms>(&key, b""),
+    );
+    let elapsed = start.elapsed();
+
+    // the seals wait for the KMS at the same time instead of one after another
+    assert_eq!(MAX_IN_FLIGHT.load(Ordering::SeqCst), 4);
+    assert!(elapsed < LATENCY * 4, "seals took {:?}", elapsed);
+
+    for (i, sealed) in [a, b, c, d].iter().enumerate() {
+        let opened: Plain = sealed
+            .as_ref()
+            .unwrap()
+            .decrypt_async::<Provider>(&key, b"")
+            .await
+            .unwrap();
+        assert_eq!(opened.0, vec![i as u8; 16]);
+    }
+}
+
+#[tokio::test]
+async fn test_async_adapter() {
+    // a sync provider is an async provider
+    let key = Key::<Provider>::random().unwrap();
+
+    let sealed: Sealed = Plain(b"some data".to_vec())
+        .encrypt_async::<Provider>(&key, b"ad")
+        .await
+        .unwrap();
+    let opened = <Provider as BoxProvider>::box_open(&key, b"ad", &sealed.0).unwrap();
+    assert_eq!(opened, b"some data");
+
+    let sealed = <Provider as BoxProvider>::box_seal(&key, b"ad", b"some data").unwrap();
+    let opened: Plain = Sealed(sealed).decrypt_async::<Provider>(&key, b"ad").await.unwrap();
+    assert_eq!(opened.0, b"some data");
+}
@@ -0,0 +1,169 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+#![cfg(feature = "audit")]
+
+mod utils;
+
+use std::{
+    convert::Infallible,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use sha2::{Digest, Sha256};
+use utils::provider::Provider;
+use vault::{
+    audit_failures, clear_audit_sink, set_audit_sink, AuditEvent, AuditOperation, AuditSink, BoxProvider, Decrypt,
+    Encrypt, Key,
+};
+
+struct Plain(Vec<u8>);
+struct Sealed(Vec<u8>);
+
+impl AsRef<[u8]> for Plain {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl AsRef<[u8]> for Sealed {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl From<Vec<u8>> for Sealed {
+    fn from(data: Vec<u8>) -> Self {
+        Self(data)
+    }
+}
+
+impl From<Vec<u8>> for Plain {
+    fn from(data: Vec<u8>) -> Self {
+        Self(data)
+    }
+}
+
+impl Encrypt<Sealed> for Plain {}
+impl Decrypt<Infallible, Plain> for Sealed {}
+
+/// the sink is global, so the tests take turns
+static SERIAL: Mutex<()> = Mutex::new(());
+
+#[derive(Clone, Default)]
+struct Collect(Arc<Mutex<Vec<AuditEvent>>>);
+
+impl AuditSink for Collect {
+    fn record(&self, event: AuditEvent) {
+        self.0.lock().unwrap().push(event);
+    }
+}
+
+impl Collect {
+    fn take(&self) -> Vec<AuditEvent> {
+        std::mem::take(&mut *self.0.lock().unwrap())
+    }
+}
+
+fn collect() -> (MutexGuard<'static, ()>, Collect) {
+    let guard = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    let sink = Collect::default();
+    set_audit_sink(sink.clone());
+    (guard, sink)
+}
+
+#[test]
+fn test_audit_seal_open() {
+    let (_guard, sink) = collect();
+
+    let key = Key::<Provider>::random().unwrap();
+    let sealed: Sealed = Plain(b"some secret data".to_vec()).encrypt(&key, b"some ad").unwrap();
+    let _: Plain = sealed.decrypt(&key, b"some ad").unwrap();
+    let mut tampered = sealed.0.clone();
+    tampered[0] ^= 1;
+    assert!(Sealed(tampered).decrypt(&key, b"some ad").is_err());
+    clear_audit_sink();
+
+    let events = sink.take();
+    let operations: Vec<_> = events.iter().map(|e| (e.operation, e.success)).collect();
+    assert_eq!(
+        operations,
+        [
+            (AuditOperation::KeyGenerate, true),
+            (AuditOperation::Seal, true),
+            (AuditOperation::Open, true),
+            (AuditOperation::Open, false)
+        ]
+    );
+
+    let ad_hash: [u8; 32] = Sha256::digest(b"some ad").into();
+    assert!(events.iter().all(|e| e.fingerprint == key.fingerprint()));
+    assert_eq!(events[0].ad_hash, None);
+    assert!(events[1..].iter().all(|e| e.ad_hash == Some(ad_hash)));
+    assert_eq!(events[1].payload_len, 16);
+    assert_eq!(events[2].payload_len, sealed.0.len());
+
+    // nothing is reported without a sink
+    let _: Sealed = Plain(b"some data".to_vec()).encrypt(&key, b"").unwrap();
+    assert!(sink.take().is_empty());
+}
+
+#[test]
+fn test_audit_key_drop() {
+    let (_guard, sink) = collect();
+
+    let key = Key::<Provider>::load(vec![7; Provider::box_key_len()]).unwrap();
+    let fingerprint = key.fingerprint();
+    drop(key);
+    clear_audit_sink();
+
+    let events = sink.take();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].operation, AuditOperation::KeyDrop);
+    assert_eq!(events[0].fingerprint, fingerprint);
+}
+
+#[test]
+fn test_audit_no_secrets() {
+    let (_guard, sink) = collect();
+
+    let key = Key::<Provider>::load(vec![0xa5; Provider::box_key_len()]).unwrap();
+    let plain = vec![0x5a; 64];
+    let _: Sealed = Plain(plain.clone()).encrypt(&key, b"").unwrap();
+    clear_audit_sink();
+
+    let debug = format!("{:?}", sink.take());
+    assert!(!debug.contains(&format!("{:?}", plain)));
+    assert!(!debug.contains(&format!("{:?}", key.bytes())));
+}
+
+#[test]
+fn test_audit_sink_panic() {
+    struct Panic;
+
+    impl AuditSink for Panic {
+        fn record(&self, _: AuditEvent) {
+            panic!("sink failure");
+        }
+    }
+
+    let _guard = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    let failures = audit_failures();
+    set_audit_sink(Panic);
+
+    let key = Key::<Provider>::random().unwrap();
+    let sealed: Sealed = Plain(b"some data".to_vec()).encrypt(&key, b"").unwrap();
+    let opened: Plain = sealed.decrypt(&key, b"").unwrap();
+    clear_audit_sink();
+
+    assert_eq!(opened.0, b"some data");
+    assert_eq!(audit_failures() - failures, 3);
+}
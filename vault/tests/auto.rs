@@ -0,0 +1,112 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+#![cfg(feature = "provider-auto")]
+
+use std::sync::Mutex;
+
+use vault::{
+    providers::{AesGcm256, Auto, ProviderKind, XChaChaPoly},
+    BoxProvider, Error, Key,
+};
+
+/// the forced provider is global, so the tests take turns
+static SERIAL: Mutex<()> = Mutex::new(());
+
+fn seal_with(kind: ProviderKind, key: &Key<Auto>, ad: &[u8], data: &[u8]) -> Vec<u8> {
+    Auto::force(kind);
+    let sealed = Auto::box_seal(key, ad, data).unwrap();
+    Auto::clear_forced();
+    sealed
+}
+
+#[test]
+fn test_auto_forced() {
+    let _serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    let key = Key::<Auto>::random().unwrap();
+
+    Auto::force(ProviderKind::AesGcm);
+    assert_eq!(Auto::selected(), ProviderKind::AesGcm);
+    Auto::force(ProviderKind::XChaCha);
+    assert_eq!(Auto::selected(), ProviderKind::XChaCha);
+    Auto::clear_forced();
+    assert_eq!(Auto::selected(), Auto::detected());
+
+    for (kind, id) in [(ProviderKind::AesGcm, *b"a256"), (ProviderKind::XChaCha, *b"xc20")].iter() {
+        let sealed = seal_with(*kind, &key, b"ad", b"some data");
+        assert_eq!(&sealed[..4], id);
+        assert_eq!(sealed.len(), 9 + Auto::box_overhead());
+    }
+    assert!(Auto::self_test().unwrap().passed());
+}
+
+#[test]
+fn test_auto_cross_provider_open() {
+    let _serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    let key = Key::<Auto>::random().unwrap();
+
+    // the box opens with the provider in its header, whichever is preferred
+    let aes = seal_with(ProviderKind::AesGcm, &key, b"ad", b"some data");
+    let xchacha = seal_with(ProviderKind::XChaCha, &key, b"ad", b"some data");
+    for preferred in [ProviderKind::AesGcm, ProviderKind::XChaCha].iter() {
+        Auto::force(*preferred);
+        assert_eq!(Auto::box_open(&key, b"ad", &aes).unwrap(), b"some data");
+        assert_eq!(Auto::box_open(&key, b"ad", &xchacha).unwrap(), b"some data");
+    }
+    Auto::clear_forced();
+
+    // the inner box is a box of the provider with the header as prefix of the AD
+    let header_len = Auto::box_overhead() - AesGcm256::box_overhead();
+    let (header, inner) = aes.split_at(header_len);
+    let aes_key = Key::<AesGcm256>::load_from_slice(key.bytes()).unwrap();
+    let ad = [header, b"ad"].concat();
+    assert_eq!(AesGcm256::box_open(&aes_key, &ad, inner).unwrap(), b"some data");
+
+    let header_len = Auto::box_overhead() - XChaChaPoly::box_overhead();
+    let xchacha_key = Key::<XChaChaPoly>::load_from_slice(key.bytes()).unwrap();
+    let ad = [&xchacha[..header_len], b"ad"].concat();
+    assert_eq!(
+        XChaChaPoly::box_open(&xchacha_key, &ad, &xchacha[header_len..]).unwrap(),
+        b"some data"
+    );
+}
+
+#[test]
+fn test_auto_header_tampering() {
+    let _serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    let key = Key::<Auto>::random().unwrap();
+
+    let sealed = seal_with(ProviderKind::AesGcm, &key, b"ad", b"some data");
+    for i in 0..Auto::box_overhead() - AesGcm256::box_overhead() {
+        let mut tampered = sealed.clone();
+        tampered[i] ^= 1;
+        assert!(Auto::box_open(&key, b"ad", &tampered).is_err());
+    }
+
+    // a box relabeled as a box of the other provider doesn't open
+    let mut relabeled = sealed.clone();
+    relabeled[..4].copy_from_slice(b"xc20");
+    assert!(matches!(
+        Auto::box_open(&key, b"ad", &relabeled),
+        Err(Error::AuthenticationFailed)
+    ));
+
+    let mut unknown = sealed;
+    unknown[..4].copy_from_slice(b"zzzz");
+    assert!(matches!(
+        Auto::box_open(&key, b"ad", &unknown),
+        Err(Error::ProviderMismatch { .. })
+    ));
+    assert!(matches!(
+        Auto::box_open(&key, b"ad", b"short"),
+        Err(Error::MalformedCiphertext { .. })
+    ));
+}
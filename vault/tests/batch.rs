@@ -0,0 +1,229 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+mod utils;
+
+use crypto::XChaChaPoly;
+use random::primitives::cipher::AeadCipher;
+use utils::provider::Provider;
+use vault::{
+    decrypt_many, decrypt_many_collect_errors, encrypt_many, encrypt_many_collect_errors, BoxProvider, Error, Key,
+};
+
+/// a provider which derives the nonce from the AD, so sealing is reproducible for distinct ADs
+struct Deterministic;
+
+impl BoxProvider for Deterministic {
+    type Error = vault::Error;
+
+    fn box_key_len() -> usize {
+        32
+    }
+
+    fn box_overhead() -> usize {
+        Provider::box_overhead()
+    }
+
+    fn box_id() -> [u8; 4] {
+        *b"dtrm"
+    }
+
+    fn box_seal(key: &Key<Self>, ad: &[u8], data: &[u8]) -> vault::Result<Vec<u8>> {
+        let mut boxx = vec![0; data.len() + Self::box_overhead()];
+        let (nonce, cipher) = boxx.split_at_mut(24);
+        nonce[..ad.len()].copy_from_slice(ad);
+
+        XChaChaPoly
+            .seal_with(cipher, data, ad, key.bytes(), nonce)
+            .map_err(|_| vault::Error::crypto("seal", "Unable to seal data"))?;
+        Ok(boxx)
+    }
+
+    fn box_open(key: &Key<Self>, ad: &[u8], data: &[u8]) -> vault::Result<Vec<u8>> {
+        Provider::box_open(&Key::load(key.bytes().to_vec())?, ad, data)
+    }
+
+    fn random_buf(buf: &mut [u8]) -> vault::Result<()> {
+        Provider::random_buf(buf)
+    }
+}
+
+fn records(n: usize) -> (Vec<Vec<u8>>, Vec<[u8; 8]>) {
+    let data = (0..n).map(|i| format!("record {}", i).into_bytes()).collect();
+    let ads = (0..n).map(|i| (i as u64).to_be_bytes()).collect();
+    (data, ads)
+}
+
+fn items<'a>(data: &'a [Vec<u8>], ads: &'a [[u8; 8]]) -> Vec<(&'a [u8], &'a [u8])> {
+    data.iter().zip(ads).map(|(data, ad)| (&data[..], &ad[..])).collect()
+}
+
+#[test]
+fn test_many_roundtrip() {
+    let key = Key::<Provider>::random().unwrap();
+    let (data, ads) = records(1000);
+
+    let sealed = encrypt_many(&key, &items(&data, &ads)).unwrap();
+    assert_eq!(sealed.len(), data.len());
+    for (i, boxx) in sealed.iter().enumerate() {
+        assert_eq!(Provider::box_open(&key, &ads[i], boxx).unwrap(), data[i]);
+    }
+
+    let sealed: Vec<_> = sealed.iter().zip(&ads).map(|(boxx, ad)| (boxx, &ad[..])).collect();
+    assert_eq!(decrypt_many(&key, &sealed).unwrap(), data);
+}
+
+#[test]
+fn test_many_matches_serial() {
+    let key = Key::<Deterministic>::random().unwrap();
+    let (data, ads) = records(500);
+
+    let serial: Vec<_> = data
+        .iter()
+        .zip(&ads)
+        .map(|(data, ad)| Deterministic::box_seal(&key, ad, data).unwrap())
+        .collect();
+    assert_eq!(encrypt_many(&key, &items(&data, &ads)).unwrap(), serial);
+}
+
+#[test]
+fn test_many_fail_fast() {
+    let key = Key::<Provider>::random().unwrap();
+    let (data, ads) = records(100);
+    let mut sealed = encrypt_many(&key, &items(&data, &ads)).unwrap();
+    sealed[42][30] ^= 1;
+
+    let sealed: Vec<_> = sealed.iter().zip(&ads).map(|(boxx, ad)| (boxx, &ad[..])).collect();
+    assert!(decrypt_many(&key, &sealed).is_err());
+}
+
+#[test]
+fn test_many_collect_errors() {
+    let key = Key::<Provider>::random().unwrap();
+    let (data, ads) = records(100);
+    let mut sealed = encrypt_many(&key, &items(&data, &ads)).unwrap();
+    sealed[42][30] ^= 1;
+    sealed[7].truncate(3);
+
+    let sealed: Vec<_> = sealed.iter().zip(&ads).map(|(boxx, ad)| (boxx, &ad[..])).collect();
+    let opened = decrypt_many_collect_errors(&key, &sealed);
+    assert_eq!(opened.len(), 100);
+    for (i, result) in opened.iter().enumerate() {
+        match result {
+            Ok(plain) => assert_eq!(plain, &data[i]),
+            Err(_) => assert!(i == 7 || i == 42, "record {} failed", i),
+        }
+    }
+    assert_eq!(opened.iter().filter(|r| r.is_err()).count(), 2);
+}
+
+#[test]
+fn test_many_key_uses() {
+    let key = Key::<Provider>::random().unwrap().with_max_uses(60);
+    let (data, ads) = records(100);
+
+    let sealed = encrypt_many_collect_errors(&key, &items(&data, &ads));
+    assert_eq!(sealed.iter().filter(|r| r.is_ok()).count(), 60);
+    assert!(sealed
+        .iter()
+        .filter_map(|r| r.as_ref().err())
+        .all(|e| matches!(e, Error::KeyExhausted(60))));
+
+    assert!(matches!(
+        encrypt_many(&key, &items(&data, &ads)),
+        Err(Error::KeyExhausted(60))
+    ));
+}
+
+#[test]
+fn test_many_empty() {
+    let key = Key::<Provider>::random().unwrap();
+    let none: [(&[u8], &[u8]); 0] = [];
+    assert!(encrypt_many(&key, &none).unwrap().is_empty());
+    assert!(decrypt_many(&key, &none).unwrap().is_empty());
+}
+
+#[cfg(feature = "parallel")]
+mod parallel {
+    use super::*;
+
+    use std::{
+        collections::HashSet,
+        sync::Mutex,
+        thread::{self, ThreadId},
+        time::{Duration, Instant},
+    };
+
+    static THREADS: Mutex<Option<HashSet<ThreadId>>> = Mutex::new(None);
+
+    /// a provider which takes a millisecond per box and records the threads it runs on.  Waiting instead of
+    /// computing shows the scaling on machines with few cores as well.
+    struct Slow;
+
+    impl BoxProvider for Slow {
+        type Error = vault::Error;
+
+        fn box_key_len() -> usize {
+            32
+        }
+
+        fn box_overhead() -> usize {
+            Provider::box_overhead()
+        }
+
+        fn box_id() -> [u8; 4] {
+            *b"slow"
+        }
+
+        fn box_seal(key: &Key<Self>, ad: &[u8], data: &[u8]) -> vault::Result<Vec<u8>> {
+            THREADS
+                .lock()
+                .unwrap()
+                .get_or_insert_with(HashSet::new)
+                .insert(thread::current().id());
+            thread::sleep(Duration::from_millis(1));
+            Provider::box_seal(&Key::load(key.bytes().to_vec())?, ad, data)
+        }
+
+        fn box_open(key: &Key<Self>, ad: &[u8], data: &[u8]) -> vault::Result<Vec<u8>> {
+            Provider::box_open(&Key::load(key.bytes().to_vec())?, ad, data)
+        }
+
+        fn random_buf(buf: &mut [u8]) -> vault::Result<()> {
+            Provider::random_buf(buf)
+        }
+    }
+
+    #[test]
+    fn test_many_scales() {
+        let key = Key::<Slow>::random().unwrap();
+        let (data, ads) = records(400);
+        let items = items(&data, &ads);
+
+        let serial = Instant::now();
+        for (data, ad) in &items {
+            Slow::box_seal(&key, ad, data).unwrap();
+        }
+        let serial = serial.elapsed();
+
+        *THREADS.lock().unwrap() = None;
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(8).build().unwrap();
+        let parallel = Instant::now();
+        let sealed = pool.install(|| encrypt_many(&key, &items)).unwrap();
+        let parallel = parallel.elapsed();
+
+        assert!(THREADS.lock().unwrap().as_ref().unwrap().len() >= 4);
+        assert!(parallel * 4 < serial, "{:?} parallel, {:?} serial", parallel, serial);
+
+        let sealed: Vec<_> = sealed.iter().zip(&ads).map(|(boxx, ad)| (boxx, &ad[..])).collect();
+        assert_eq!(pool.install(|| decrypt_many(&key, &sealed)).unwrap(), data);
+    }
+}
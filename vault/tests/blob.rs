@@ -0,0 +1,177 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+mod utils;
+
+use std::convert::{Infallible, TryFrom, TryInto};
+
+use rand::{Rng, RngCore};
+use utils::{
+    hex,
+    provider::{IetfProvider, Provider},
+};
+use vault::{BoxProvider, Decrypt, Encrypt, Error, Key, SealedBlob};
+
+struct Plain(Vec<u8>);
+struct Sealed(Vec<u8>);
+
+impl AsRef<[u8]> for Plain {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl AsRef<[u8]> for Sealed {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl From<Vec<u8>> for Plain {
+    fn from(data: Vec<u8>) -> Self {
+        Self(data)
+    }
+}
+
+impl From<Vec<u8>> for Sealed {
+    fn from(data: Vec<u8>) -> Self {
+        Self(data)
+    }
+}
+
+impl Encrypt<Sealed> for Plain {}
+impl Decrypt<Infallible, Plain> for Sealed {}
+
+/// the SHA-256 hash of `ad`
+const AD_HASH: &str = "70ba33708cbfb103f1a8e34afef333ba7dc021022b2d9aaa583aabb8058d8d67";
+
+#[test]
+fn test_blob_roundtrip() {
+    let key = Key::<Provider>::random().unwrap();
+    let sealed: Sealed = Plain(b"some data".to_vec()).encrypt_blob(&key, b"ad").unwrap();
+    assert_eq!(
+        sealed.0.len(),
+        SealedBlob::HEADER_LEN + b"some data".len() + Provider::box_overhead()
+    );
+
+    let blob = SealedBlob::try_from(sealed.0.clone()).unwrap();
+    assert_eq!(blob.version, SealedBlob::VERSION);
+    assert_eq!(&blob.provider_id, b"xcp1");
+    assert_eq!(blob.ad_hash.to_vec(), hex(AD_HASH));
+    assert_eq!(Vec::from(blob.clone()), sealed.0);
+    assert_eq!(blob.open(&key, b"ad").unwrap(), b"some data");
+
+    assert_eq!(sealed.decrypt_blob(&key, b"ad").unwrap().0, b"some data");
+}
+
+#[test]
+fn test_blob_framing_golden() {
+    let blob = SealedBlob {
+        version: 1,
+        provider_id: *b"xcp1",
+        ad_hash: hex(AD_HASH).try_into().unwrap(),
+        ciphertext: vec![0xde, 0xad, 0xbe, 0xef],
+    };
+    let bytes = hex(&["01", "0400", "78637031", "2000", AD_HASH, "04000000", "deadbeef"].concat());
+
+    assert_eq!(blob.to_bytes(), bytes);
+    assert_eq!(SealedBlob::from_bytes(&bytes).unwrap(), blob);
+}
+
+#[cfg(feature = "test-utils")]
+#[test]
+fn test_blob_golden() {
+    use vault::test_utils::TestProvider;
+
+    let key = Key::<TestProvider>::load(vec![7; 32]).unwrap();
+    let sealed: Sealed = Plain(b"some data".to_vec()).encrypt_blob(&key, b"ad").unwrap();
+    assert_eq!(
+        sealed.0,
+        hex(concat!(
+            "01040074657374200070ba33708cbfb103f1a8e34afef333ba7dc021022b2d9a",
+            "aa583aabb8058d8d67210000000000000000000001d247815e39c45cf0d55fb2",
+            "baa9f7f36fecebc5af33efd5abcc"
+        ))
+    );
+    assert_eq!(sealed.decrypt_blob(&key, b"ad").unwrap().0, b"some data");
+}
+
+#[test]
+fn test_blob_rejects() {
+    let key = Key::<Provider>::random().unwrap();
+    let bytes = Plain(b"some data".to_vec()).encrypt_blob(&key, b"ad").unwrap().0;
+
+    let mut future = bytes.clone();
+    future[0] = 2;
+    assert!(matches!(
+        SealedBlob::from_bytes(&future),
+        Err(Error::UnsupportedVersion(2))
+    ));
+
+    let trailing = [&bytes[..], &[0]].concat();
+    assert!(matches!(
+        SealedBlob::from_bytes(&trailing),
+        Err(Error::CryptoError { .. })
+    ));
+
+    // the provider id and the AD hash have fixed lengths in version 1
+    let mut long_id = bytes.clone();
+    long_id[1] = 5;
+    assert!(SealedBlob::from_bytes(&long_id).is_err());
+    let mut short_hash = bytes.clone();
+    short_hash[7] = 31;
+    assert!(SealedBlob::from_bytes(&short_hash).is_err());
+
+    let blob = SealedBlob::from_bytes(&bytes).unwrap();
+    assert!(matches!(blob.open(&key, b"other ad"), Err(Error::AuthenticationFailed)));
+    let other = Key::<IetfProvider>::random().unwrap();
+    assert!(matches!(blob.open(&other, b"ad"), Err(Error::ProviderMismatch { .. })));
+
+    // the header is authenticated
+    let mut forged = blob.clone();
+    forged.provider_id = *b"icp1";
+    assert!(forged.open(&other, b"ad").is_err());
+}
+
+#[test]
+fn test_blob_truncated() {
+    let key = Key::<Provider>::random().unwrap();
+    let bytes = Plain(b"some data".to_vec()).encrypt_blob(&key, b"ad").unwrap().0;
+
+    for len in 0..bytes.len() {
+        assert!(SealedBlob::from_bytes(&bytes[..len]).is_err(), "{} bytes", len);
+    }
+}
+
+#[test]
+fn test_blob_random_input() {
+    let mut rng = rand::thread_rng();
+    let key = Key::<Provider>::random().unwrap();
+    let bytes = Plain(b"some data".to_vec()).encrypt_blob(&key, b"ad").unwrap().0;
+
+    for _ in 0..10_000 {
+        let mut data = vec![0; rng.gen_range(0..128)];
+        rng.fill_bytes(&mut data);
+        if rng.gen() {
+            // mostly valid headers get further into the parser
+            let len = data.len().min(bytes.len());
+            data[..len].copy_from_slice(&bytes[..len]);
+            if len > 0 {
+                data[rng.gen_range(0..len)] ^= 1 << rng.gen_range(0..8);
+            }
+        }
+
+        if let Ok(blob) = SealedBlob::from_bytes(&data) {
+            assert_eq!(blob.to_bytes(), data);
+            assert!(blob.open(&key, b"ad").is_err());
+        }
+    }
+}
@@ -0,0 +1,156 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+mod utils;
+
+use rand::RngCore;
+use utils::provider::Provider;
+use vault::{open_chunked, seal_chunked, BoxProvider, ChunkedCiphertext, Error, Key};
+
+fn random_data(len: usize) -> Vec<u8> {
+    let mut data = vec![0; len];
+    rand::thread_rng().fill_bytes(&mut data);
+    data
+}
+
+#[test]
+fn test_chunked_exact_multiple() {
+    let key = Key::<Provider>::random().unwrap();
+    let data = random_data(4 * 256);
+
+    let chunked = seal_chunked(&key, b"ad", &data, 256).unwrap();
+    assert_eq!(chunked.len(), 4);
+    assert!(chunked
+        .chunks()
+        .iter()
+        .all(|chunk| chunk.len() == 256 + Provider::box_overhead()));
+    assert_eq!(open_chunked(&key, b"ad", &chunked).unwrap(), data);
+}
+
+#[test]
+fn test_chunked_ragged() {
+    let key = Key::<Provider>::random().unwrap();
+    let data = random_data(1000);
+
+    let chunked = seal_chunked(&key, b"ad", &data, 256).unwrap();
+    assert_eq!(chunked.len(), 4);
+    assert_eq!(chunked.chunks()[3].len(), 1000 - 768 + Provider::box_overhead());
+    assert_eq!(open_chunked(&key, b"ad", &chunked).unwrap(), data);
+
+    for i in 0..4 {
+        let end = ((i + 1) * 256).min(data.len());
+        assert_eq!(chunked.open_chunk(&key, b"ad", i).unwrap(), &data[i * 256..end]);
+    }
+}
+
+#[test]
+fn test_chunked_single() {
+    let key = Key::<Provider>::random().unwrap();
+
+    let data = random_data(100);
+    let chunked = seal_chunked(&key, b"ad", &data, 256).unwrap();
+    assert_eq!(chunked.len(), 1);
+    assert_eq!(open_chunked(&key, b"ad", &chunked).unwrap(), data);
+
+    let chunked = seal_chunked(&key, b"ad", &[], 256).unwrap();
+    assert_eq!(chunked.len(), 1);
+    assert!(open_chunked(&key, b"ad", &chunked).unwrap().is_empty());
+}
+
+#[test]
+fn test_chunked_reorder() {
+    let key = Key::<Provider>::random().unwrap();
+    let chunked = seal_chunked(&key, b"ad", &random_data(1000), 256).unwrap();
+
+    let mut chunks = chunked.chunks().to_vec();
+    chunks.swap(1, 2);
+    let reordered = reframe(&chunks);
+    assert!(open_chunked(&key, b"ad", &reordered).is_err());
+    assert!(reordered.open_chunk(&key, b"ad", 0).is_ok());
+    assert!(reordered.open_chunk(&key, b"ad", 1).is_err());
+    assert!(reordered.open_chunk(&key, b"ad", 2).is_err());
+
+    let mut chunks = chunked.chunks().to_vec();
+    chunks[2] = chunks[1].clone();
+    assert!(open_chunked(&key, b"ad", &reframe(&chunks)).is_err());
+}
+
+#[test]
+fn test_chunked_truncated() {
+    let key = Key::<Provider>::random().unwrap();
+    let chunked = seal_chunked(&key, b"ad", &random_data(1000), 256).unwrap();
+
+    // dropping the last chunk changes the total of the other chunks
+    let chunks = &chunked.chunks()[..3];
+    let truncated = reframe(chunks);
+    assert!(open_chunked(&key, b"ad", &truncated).is_err());
+    assert!(truncated.open_chunk(&key, b"ad", 0).is_err());
+
+    assert!(open_chunked(&key, b"ad", &reframe(&[])).is_err());
+    assert!(matches!(
+        chunked.open_chunk(&key, b"ad", 4),
+        Err(Error::InterfaceErrorDetailed(_))
+    ));
+    assert!(open_chunked(&key, b"other ad", &chunked).is_err());
+}
+
+#[test]
+fn test_chunked_framing() {
+    let key = Key::<Provider>::random().unwrap();
+    let data = random_data(1000);
+    let chunked = seal_chunked(&key, b"ad", &data, 256).unwrap();
+
+    let bytes = chunked.to_bytes();
+    let overhead = 4 * Provider::box_overhead();
+    assert_eq!(bytes.len(), 4 + 4 * 4 + data.len() + overhead);
+    assert_eq!(&bytes[..4], &4u32.to_be_bytes());
+
+    let parsed = ChunkedCiphertext::from_bytes(&bytes).unwrap();
+    assert_eq!(parsed, chunked);
+    assert_eq!(open_chunked(&key, b"ad", &parsed).unwrap(), data);
+
+    assert!(ChunkedCiphertext::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    assert!(ChunkedCiphertext::from_bytes(&[&bytes[..], &[0]].concat()).is_err());
+    assert!(ChunkedCiphertext::from_bytes(&u32::MAX.to_be_bytes()).is_err());
+}
+
+#[test]
+fn test_chunked_serde() {
+    let key = Key::<Provider>::random().unwrap();
+    let data = random_data(600);
+    let chunked = seal_chunked(&key, b"ad", &data, 256).unwrap();
+
+    let json = serde_json::to_string(&chunked).unwrap();
+    let parsed: ChunkedCiphertext = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed, chunked);
+    assert_eq!(open_chunked(&key, b"ad", &parsed).unwrap(), data);
+
+    assert!(serde_json::from_str::<ChunkedCiphertext>("[0, 0, 0, 1]").is_err());
+}
+
+#[test]
+fn test_chunked_invalid_size() {
+    let key = Key::<Provider>::random().unwrap();
+    assert!(matches!(
+        seal_chunked(&key, b"ad", b"data", 0),
+        Err(Error::InterfaceErrorDetailed(_))
+    ));
+}
+
+/// frame `chunks` like `ChunkedCiphertext::to_bytes` and parse them
+fn reframe(chunks: &[Vec<u8>]) -> ChunkedCiphertext {
+    let mut bytes = (chunks.len() as u32).to_be_bytes().to_vec();
+    for chunk in chunks {
+        bytes.extend_from_slice(&(chunk.len() as u32).to_be_bytes());
+        bytes.extend_from_slice(chunk);
+    }
+    ChunkedCiphertext::from_bytes(&bytes).unwrap()
+}
@@ -0,0 +1,95 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+mod utils;
+
+use utils::provider::Provider;
+use vault::{providers::CommittingBox, BoxProvider, Error, Key};
+
+#[test]
+fn test_committing_roundtrip() {
+    type Committing = CommittingBox<Provider>;
+    let key = Key::<Committing>::random().unwrap();
+
+    let sealed = Committing::box_seal(&key, b"ad", b"some data").unwrap();
+    assert_eq!(sealed.len(), 9 + Committing::box_overhead());
+    assert_eq!(Committing::box_overhead(), Provider::box_overhead() + 48);
+    assert_eq!(Committing::box_open(&key, b"ad", &sealed).unwrap(), b"some data");
+    assert_eq!(Committing::box_id(), *b"kxcp");
+
+    assert!(Committing::box_open(&key, b"other ad", &sealed).is_err());
+    let other = Key::<Committing>::random().unwrap();
+    assert!(matches!(
+        Committing::box_open(&other, b"ad", &sealed),
+        Err(Error::AuthenticationFailed)
+    ));
+
+    let mut corrupt = sealed.clone();
+    *corrupt.last_mut().unwrap() ^= 1;
+    assert!(matches!(
+        Committing::box_open(&key, b"ad", &corrupt),
+        Err(Error::AuthenticationFailed)
+    ));
+    assert!(Committing::box_open(&key, b"ad", &sealed[..47]).is_err());
+
+    // the inner box is a box of the wrapped provider
+    let inner = Key::<Provider>::load_from_slice(key.bytes()).unwrap();
+    let inner_box = &sealed[..sealed.len() - 48];
+    assert_eq!(Provider::box_open(&inner, b"ad", inner_box).unwrap(), b"some data");
+}
+
+/// a box crafted to open under two AES-GCM keys: the last ciphertext block is chosen so the GHASH tags of both keys
+/// are equal
+#[cfg(feature = "provider-aes-gcm")]
+#[test]
+fn test_committing_gcm_key_collision() {
+    use utils::hex;
+    use vault::providers::AesGcm256;
+
+    let first = [0x11; 32];
+    let second = [0x22; 32];
+    let collision = hex(concat!(
+        "000000000000000000000000",
+        "74776f206b6579732c206f6e65206278d5d55995e078d17ea7b531823e3d93db",
+        "95cf02b10e454bb2916ec7d2bf9e2a22"
+    ));
+
+    // the raw provider opens the box under both keys
+    let raw = AesGcm256::box_open(&Key::load(first.to_vec()).unwrap(), b"", &collision).unwrap();
+    assert_eq!(
+        raw,
+        hex("b0bd71478acae4ea2d3ad26bbed2b8f724b5d60b18dc8f92de5072193a600385")
+    );
+    let raw = AesGcm256::box_open(&Key::load(second.to_vec()).unwrap(), b"", &collision).unwrap();
+    assert_eq!(
+        raw,
+        hex("bc87bb34c40ce6788c553af827feb2dd0fdcd5f66ecb03ce1f6aef145a263ea4")
+    );
+
+    // the attacker commits the box to the first key
+    type Committing = CommittingBox<AesGcm256>;
+    let first = Key::<Committing>::load(first.to_vec()).unwrap();
+    let second = Key::<Committing>::load(second.to_vec()).unwrap();
+    let trailer = Committing::box_seal(&first, b"", b"")
+        .unwrap()
+        .split_off(AesGcm256::box_overhead());
+    let committed = [collision, trailer].concat();
+
+    let opened = Committing::box_open(&first, b"", &committed).unwrap();
+    assert_eq!(
+        opened,
+        hex("b0bd71478acae4ea2d3ad26bbed2b8f724b5d60b18dc8f92de5072193a600385")
+    );
+    assert!(matches!(
+        Committing::box_open(&second, b"", &committed),
+        Err(Error::AuthenticationFailed)
+    ));
+}
@@ -0,0 +1,151 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+#![cfg(feature = "compress")]
+
+mod utils;
+
+use std::convert::Infallible;
+
+use rand::RngCore;
+use utils::provider::Provider;
+use vault::{BoxProvider, Decrypt, Encrypt, Error, Key, MAX_DECOMPRESSED_LEN};
+
+struct Plain(Vec<u8>);
+struct Sealed(Vec<u8>);
+
+impl AsRef<[u8]> for Plain {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl AsRef<[u8]> for Sealed {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl From<Vec<u8>> for Plain {
+    fn from(data: Vec<u8>) -> Self {
+        Self(data)
+    }
+}
+
+impl From<Vec<u8>> for Sealed {
+    fn from(data: Vec<u8>) -> Self {
+        Self(data)
+    }
+}
+
+impl Encrypt<Sealed> for Plain {}
+impl Decrypt<Infallible, Plain> for Sealed {}
+
+fn document() -> Vec<u8> {
+    (0..1000)
+        .map(|i| format!(r#"{{"id":{},"name":"record","tags":["a","b","c"],"active":true}}"#, i))
+        .collect::<Vec<_>>()
+        .join(",")
+        .into_bytes()
+}
+
+#[test]
+fn test_compressed_roundtrip() {
+    let key = Key::<Provider>::random().unwrap();
+    let data = document();
+
+    let sealed = Plain(data.clone()).encrypt_compressed(&key, b"ad", 3).unwrap();
+    assert!(sealed.0.len() < data.len() / 5, "{} of {}", sealed.0.len(), data.len());
+
+    let opened: Plain = sealed.decrypt_decompressed(&key, b"ad").unwrap();
+    assert_eq!(opened.0, data);
+}
+
+#[test]
+fn test_compressed_levels() {
+    let key = Key::<Provider>::random().unwrap();
+    let data = document();
+
+    for level in &[1, 9, 19] {
+        let sealed = Plain(data.clone()).encrypt_compressed(&key, b"ad", *level).unwrap();
+        let opened: Plain = sealed.decrypt_decompressed(&key, b"ad").unwrap();
+        assert_eq!(opened.0, data);
+    }
+}
+
+#[test]
+fn test_incompressible_is_stored() {
+    let key = Key::<Provider>::random().unwrap();
+    let mut data = vec![0; 4096];
+    rand::thread_rng().fill_bytes(&mut data);
+
+    let sealed = Plain(data.clone()).encrypt_compressed(&key, b"ad", 3).unwrap();
+    assert_eq!(sealed.0.len(), 1 + data.len() + Provider::box_overhead());
+
+    let sealed: Sealed = sealed;
+    let stored: Plain = sealed.decrypt(&key, b"ad").unwrap();
+    assert_eq!(stored.0[0], 0);
+    assert_eq!(&stored.0[1..], &data[..]);
+
+    let opened: Plain = sealed.decrypt_decompressed(&key, b"ad").unwrap();
+    assert_eq!(opened.0, data);
+}
+
+#[test]
+fn test_stored_blob_decrypts() {
+    let key = Key::<Provider>::random().unwrap();
+    let sealed: Sealed = Plain([&[0][..], b"legacy"].concat()).encrypt(&key, b"ad").unwrap();
+
+    let opened: Plain = sealed.decrypt_decompressed(&key, b"ad").unwrap();
+    assert_eq!(opened.0, b"legacy");
+}
+
+#[test]
+fn test_decompression_limit() {
+    let key = Key::<Provider>::random().unwrap();
+    let bomb = vec![0; 8 << 20];
+
+    let sealed = Plain(bomb.clone()).encrypt_compressed(&key, b"ad", 19).unwrap();
+    assert!(sealed.0.len() < 4096);
+
+    match sealed.decrypt_decompressed_with_limit(&key, b"ad", 1 << 20) {
+        Err(Error::DecompressionLimit(limit)) => assert_eq!(limit, 1 << 20),
+        Err(e) => panic!("unexpected error {:?}", e),
+        Ok(_) => panic!("the bomb must not inflate"),
+    }
+
+    let opened: Plain = sealed.decrypt_decompressed_with_limit(&key, b"ad", bomb.len()).unwrap();
+    assert_eq!(opened.0.len(), bomb.len());
+    assert!(bomb.len() < MAX_DECOMPRESSED_LEN);
+}
+
+#[test]
+fn test_decompression_limit_stored() {
+    let key = Key::<Provider>::random().unwrap();
+    let sealed: Sealed = Plain(vec![0; 65]).encrypt(&key, b"ad").unwrap();
+
+    assert!(matches!(
+        sealed.decrypt_decompressed_with_limit(&key, b"ad", 63),
+        Err(Error::DecompressionLimit(63))
+    ));
+    assert!(sealed.decrypt_decompressed_with_limit(&key, b"ad", 64).is_ok());
+}
+
+#[test]
+fn test_unknown_compression_id() {
+    let key = Key::<Provider>::random().unwrap();
+    let sealed: Sealed = Plain(vec![7, 1, 2, 3]).encrypt(&key, b"ad").unwrap();
+
+    assert!(matches!(
+        sealed.decrypt_decompressed(&key, b"ad"),
+        Err(Error::CompressionError(_))
+    ));
+}
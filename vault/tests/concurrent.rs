@@ -0,0 +1,150 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+mod utils;
+
+use std::{
+    collections::HashMap,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use rand::Rng;
+use utils::provider::Provider;
+use vault::{
+    ConcurrentVault, DeleteRequest, Error, Id, Key, ListResult, ReadRequest, ReadResult, RecordHint, SharedKey, Store,
+    WriteRequest,
+};
+
+/// the length of the data of the records, a single byte repeated
+const LEN: usize = 512;
+
+/// a store in memory which counts its writes
+#[derive(Default)]
+struct Entries {
+    entries: HashMap<Vec<u8>, Vec<u8>>,
+    writes: usize,
+}
+
+impl Store for Entries {
+    fn list(&self) -> vault::Result<ListResult> {
+        Ok(ListResult::new(self.entries.keys().cloned().collect()))
+    }
+
+    fn read(&self, request: ReadRequest) -> vault::Result<Option<ReadResult>> {
+        let id: Vec<u8> = request.into();
+        Ok(self.entries.get(&id).map(|data| ReadResult::new(id, data.clone())))
+    }
+
+    fn write(&mut self, request: WriteRequest) -> vault::Result<()> {
+        let (id, data) = request.into();
+        self.entries.insert(id, data);
+        self.writes += 1;
+        Ok(())
+    }
+
+    fn delete(&mut self, request: DeleteRequest) -> vault::Result<()> {
+        let id: Vec<u8> = request.into();
+        self.entries.remove(&id);
+        Ok(())
+    }
+}
+
+fn hint() -> RecordHint {
+    RecordHint::new(b"hint").unwrap()
+}
+
+#[test]
+fn test_concurrent_vault() {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<ConcurrentVault<Provider, Entries>>();
+
+    let key = SharedKey::from(Key::<Provider>::random().unwrap());
+    let owner = Id::random::<Provider>().unwrap();
+    let vault = ConcurrentVault::open(key.clone(), owner, Entries::default()).unwrap();
+    // the vault shares the key with the handle
+    assert_eq!(key.handles(), 2);
+
+    let id = vault.write(b"first", hint()).unwrap();
+    assert_eq!(vault.read(id).unwrap(), b"first");
+    vault.overwrite(id, b"second").unwrap();
+    assert_eq!(vault.read(id).unwrap(), b"second");
+    assert_eq!(vault.records(), [(id, hint())]);
+    vault.revoke(id).unwrap();
+    assert!(matches!(vault.read(id), Err(Error::RecordNotFound { .. })));
+    assert!(matches!(
+        vault.overwrite(id, b"third"),
+        Err(Error::RecordNotFound { .. })
+    ));
+    assert!(matches!(vault.revoke(id), Err(Error::RecordNotFound { .. })));
+
+    // the store reopened has the same records and the chain isn't created again
+    let other = vault.write(b"other", hint()).unwrap();
+    let store = vault.into_store();
+    let writes = store.writes;
+    let vault = ConcurrentVault::open(key, owner, store).unwrap();
+    assert_eq!(vault.records(), [(other, hint())]);
+    assert_eq!(vault.read(other).unwrap(), b"other");
+    assert_eq!(vault.into_store().writes, writes);
+}
+
+#[test]
+fn test_concurrent_vault_stress() {
+    let key = SharedKey::from(Key::<Provider>::random().unwrap());
+    let owner = Id::random::<Provider>().unwrap();
+    let vault = ConcurrentVault::open(key, owner, Entries::default()).unwrap();
+    let ids: Vec<Id> = (0..8u8).map(|b| vault.write(&[b; LEN], hint()).unwrap()).collect();
+    let reads = AtomicUsize::new(0);
+
+    std::thread::scope(|scope| {
+        // writers overwrite the records, and write and revoke others
+        for writer in 0..4u8 {
+            let (vault, ids) = (&vault, &ids);
+            scope.spawn(move || {
+                let mut rng = rand::thread_rng();
+                for round in 0..100u8 {
+                    let byte = writer.wrapping_mul(100).wrapping_add(round);
+                    vault.overwrite(ids[rng.gen_range(0..ids.len())], &[byte; LEN]).unwrap();
+                    if round % 10 == 0 {
+                        let id = vault.write(&[byte; LEN], hint()).unwrap();
+                        vault.revoke(id).unwrap();
+                    }
+                }
+            });
+        }
+
+        // readers never observe a torn payload, and revoked records are gone rather than broken
+        for _ in 0..4 {
+            let (vault, reads) = (&vault, &reads);
+            scope.spawn(move || {
+                for _ in 0..100 {
+                    for (id, _) in vault.records() {
+                        match vault.read(id) {
+                            Ok(data) => {
+                                assert_eq!(data.len(), LEN);
+                                assert!(data.iter().all(|b| *b == data[0]), "torn record {:?}", id);
+                                reads.fetch_add(1, Ordering::Relaxed);
+                            }
+                            Err(Error::RecordNotFound { .. }) => (),
+                            Err(e) => panic!("read failed: {:?}", e),
+                        }
+                    }
+                }
+            });
+        }
+    });
+
+    assert!(reads.load(Ordering::Relaxed) >= 4 * 100 * ids.len());
+    let mut records: Vec<Id> = vault.records().into_iter().map(|(id, _)| id).collect();
+    let mut expected = ids.clone();
+    records.sort_by_key(|id| id.as_ref().to_vec());
+    expected.sort_by_key(|id| id.as_ref().to_vec());
+    assert_eq!(records, expected);
+}
@@ -0,0 +1,111 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+mod utils;
+
+use std::{convert::TryFrom, fmt};
+
+use utils::provider::Provider;
+use vault::{Decrypt, Encrypt, Error, Key};
+
+struct Plain(Vec<u8>);
+struct Sealed(Vec<u8>);
+
+impl AsRef<[u8]> for Plain {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl AsRef<[u8]> for Sealed {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl From<Vec<u8>> for Sealed {
+    fn from(data: Vec<u8>) -> Self {
+        Self(data)
+    }
+}
+
+/// a 32 byte secret which rejects plaintexts of any other length
+struct Secret([u8; 32]);
+
+#[derive(Debug)]
+struct LengthError {
+    expected: usize,
+    actual: usize,
+}
+
+impl fmt::Display for LengthError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "expected {} bytes, got {}", self.expected, self.actual)
+    }
+}
+
+impl TryFrom<Vec<u8>> for Secret {
+    type Error = LengthError;
+
+    fn try_from(data: Vec<u8>) -> Result<Self, Self::Error> {
+        <[u8; 32]>::try_from(data.as_slice())
+            .map(Secret)
+            .map_err(|_| LengthError {
+                expected: 32,
+                actual: data.len(),
+            })
+    }
+}
+
+impl Encrypt<Sealed> for Plain {}
+impl Decrypt<LengthError, Secret> for Sealed {}
+
+#[test]
+fn test_conversion_roundtrip() {
+    let key = Key::<Provider>::random().unwrap();
+    let sealed = Plain(vec![7; 32]).encrypt(&key, b"ad").unwrap();
+
+    let secret: Secret = sealed.decrypt(&key, b"ad").unwrap();
+    assert_eq!(secret.0, [7; 32]);
+}
+
+#[test]
+fn test_conversion_error_surfaces() {
+    let key = Key::<Provider>::random().unwrap();
+    let sealed = Plain(vec![7; 31]).encrypt(&key, b"ad").unwrap();
+
+    let err = Decrypt::<LengthError, Secret>::decrypt(&sealed, &key, b"ad")
+        .err()
+        .unwrap();
+    match &err {
+        Error::ConversionError { type_name, message } => {
+            assert!(type_name.ends_with("Secret"), "{}", type_name);
+            assert!(message.contains("expected: 32"), "{}", message);
+            assert!(message.contains("actual: 31"), "{}", message);
+        }
+        e => panic!("unexpected error {:?}", e),
+    }
+    assert!(err.to_string().contains("Secret"));
+}
+
+#[test]
+fn test_conversion_keeps_authentication_errors() {
+    let key = Key::<Provider>::random().unwrap();
+    let other = Key::<Provider>::random().unwrap();
+    let sealed = Plain(vec![7; 31]).encrypt(&key, b"ad").unwrap();
+
+    // a wrong key fails before the plaintext is converted
+    match Decrypt::<LengthError, Secret>::decrypt(&sealed, &other, b"ad") {
+        Err(Error::ConversionError { .. }) => panic!("the box must not open"),
+        Err(_) => {}
+        Ok(_) => panic!("the box must not open"),
+    }
+}
@@ -0,0 +1,251 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+#![cfg(feature = "cose")]
+
+mod utils;
+
+use aes::Aes128;
+use ccm::{
+    aead::{Aead, KeyInit, Payload},
+    consts::{U13, U8},
+    Ccm,
+};
+use utils::{hex, provider::Provider};
+use vault::{from_cose_encrypt0, to_cose_encrypt0, BoxProvider, Error, Key};
+
+/// the COSE_Encrypt0 example of RFC 9052 appendix C.4.1
+const RFC_EXAMPLE: &str = concat!(
+    "d08343a1010aa1054d89f52f65a1c580933b5261a78c581c5974e1b99a3a4cc09a659aa2e9e7fff161d38ce71cb45ce4",
+    "60ffb569"
+);
+/// the example of RFC 9052 appendix C.4.2, which uses a partial IV
+const RFC_PARTIAL_IV_EXAMPLE: &str =
+    "d08343a1010aa1064261a7581c252a8911d465c125b6764739700f0141ed09192de139e053bd09abca";
+/// the key `our-secret2` of the RFC 9052 examples
+const RFC_KEY: &str = "849b5786457c1491be3a76dcea6c4271";
+/// the IV of RFC 9052 appendix C.4.1
+const RFC_IV: &str = "89f52f65a1c580933b5261a78c";
+
+/// AES-CCM-16-64-128, with the IV of the RFC example as the only nonce
+struct RfcProvider;
+
+impl RfcProvider {
+    const NONCE_LEN: usize = 13;
+    const TAG_LEN: usize = 8;
+}
+
+impl BoxProvider for RfcProvider {
+    type Error = Error;
+
+    fn box_key_len() -> usize {
+        16
+    }
+
+    fn box_overhead() -> usize {
+        Self::NONCE_LEN + Self::TAG_LEN
+    }
+
+    fn box_id() -> [u8; 4] {
+        *b"c128"
+    }
+
+    fn box_nonce_len() -> usize {
+        Self::NONCE_LEN
+    }
+
+    fn cose_algorithm() -> Option<i64> {
+        Some(10)
+    }
+
+    fn box_seal(key: &Key<Self>, ad: &[u8], data: &[u8]) -> vault::Result<Vec<u8>> {
+        Self::box_seal_with_nonce(key, &hex(RFC_IV), ad, data)
+    }
+
+    fn box_seal_with_nonce(key: &Key<Self>, nonce: &[u8], ad: &[u8], data: &[u8]) -> vault::Result<Vec<u8>> {
+        let sealed = Ccm::<Aes128, U8, U13>::new(key.bytes().into())
+            .encrypt(nonce.into(), Payload { msg: data, aad: ad })
+            .map_err(|_| Error::crypto("seal", "Unable to seal data"))?;
+        Ok([nonce, &sealed].concat())
+    }
+
+    fn box_open(key: &Key<Self>, ad: &[u8], data: &[u8]) -> vault::Result<Vec<u8>> {
+        if data.len() < Self::box_overhead() {
+            return Err(Error::AuthenticationFailed);
+        }
+        let (nonce, sealed) = data.split_at(Self::NONCE_LEN);
+        Ccm::<Aes128, U8, U13>::new(key.bytes().into())
+            .decrypt(nonce.into(), Payload { msg: sealed, aad: ad })
+            .map_err(|_| Error::AuthenticationFailed)
+    }
+
+    fn random_buf(buf: &mut [u8]) -> vault::Result<()> {
+        Provider::random_buf(buf)
+    }
+}
+
+fn cose_error(result: vault::Result<Vec<u8>>) -> String {
+    match result {
+        Err(Error::CoseError(message)) => message,
+        other => panic!("expected a COSE error, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_cose_rfc_example() {
+    let key = Key::<RfcProvider>::load(hex(RFC_KEY)).unwrap();
+    assert_eq!(
+        from_cose_encrypt0(&key, b"", &hex(RFC_EXAMPLE)).unwrap(),
+        b"This is the content."
+    );
+    assert_eq!(
+        to_cose_encrypt0(&key, b"", b"This is the content.").unwrap(),
+        hex(RFC_EXAMPLE)
+    );
+
+    // the external AAD is part of the Enc_structure
+    assert!(matches!(
+        from_cose_encrypt0(&key, b"ad", &hex(RFC_EXAMPLE)),
+        Err(Error::AuthenticationFailed)
+    ));
+    // untagged structures are accepted
+    assert_eq!(
+        from_cose_encrypt0(&key, b"", &hex(RFC_EXAMPLE)[1..]).unwrap(),
+        b"This is the content."
+    );
+
+    let message = cose_error(from_cose_encrypt0(&key, b"", &hex(RFC_PARTIAL_IV_EXAMPLE)));
+    assert!(message.contains("Partial IV"), "{}", message);
+}
+
+#[cfg(feature = "provider-aes-gcm")]
+#[test]
+fn test_cose_aes_gcm() {
+    use vault::providers::AesGcm256;
+
+    // sealed with the key 00..1f, the IV 00..0b and the external AAD `ad`
+    let golden = hex(concat!(
+        "d08343a10103a1054c000102030405060708090a0b5824136abf68e58cb13bf929f2abd2861619e6b8f31ae2a625390f",
+        "a37563029d304ea1eea398"
+    ));
+    let key = Key::<AesGcm256>::load((0..32).collect()).unwrap();
+    assert_eq!(
+        from_cose_encrypt0(&key, b"ad", &golden).unwrap(),
+        b"This is the content."
+    );
+
+    let sealed = to_cose_encrypt0(&key, b"ad", b"some data").unwrap();
+    // tag 16, three items, the protected header `{1: 3}` and a 12 byte IV
+    assert_eq!(&sealed[..9], &hex("d08343a10103a1054c")[..]);
+    assert_eq!(sealed.len(), 9 + 12 + 2 + 9 + 16);
+    assert_eq!(from_cose_encrypt0(&key, b"ad", &sealed).unwrap(), b"some data");
+    assert!(matches!(
+        from_cose_encrypt0(&key, b"", &sealed),
+        Err(Error::AuthenticationFailed)
+    ));
+
+    // the RFC example uses AES-CCM
+    let message = cose_error(from_cose_encrypt0(&key, b"", &hex(RFC_EXAMPLE)));
+    assert!(message.contains("10 (AES-CCM-16-64-128)"), "{}", message);
+    assert!(message.contains("3 (A256GCM)"), "{}", message);
+}
+
+#[cfg(feature = "provider-ring")]
+#[test]
+fn test_cose_ring() {
+    use vault::providers::{RingAesGcm, RingChaCha, RingProvider};
+
+    // sealed with the key 00..1f, the IV 00..0b and the external AAD `ad`
+    let golden = hex(concat!(
+        "d08344a1011818a1054c000102030405060708090a0b5824dd936173097ed660c3eb5ad3fb726017ac1ec6c99891ef4e",
+        "3f7b73c17382e227710a6872"
+    ));
+    let key = Key::<RingProvider<RingChaCha>>::load((0..32).collect()).unwrap();
+    assert_eq!(
+        from_cose_encrypt0(&key, b"ad", &golden).unwrap(),
+        b"This is the content."
+    );
+
+    let sealed = to_cose_encrypt0(&key, b"ad", b"some data").unwrap();
+    assert_eq!(&sealed[..10], &hex("d08344a1011818a1054c")[..]);
+    assert_eq!(from_cose_encrypt0(&key, b"ad", &sealed).unwrap(), b"some data");
+
+    let aes = Key::<RingProvider<RingAesGcm>>::load((0..32).collect()).unwrap();
+    let message = cose_error(from_cose_encrypt0(&aes, b"ad", &sealed));
+    assert!(message.contains("24 (ChaCha20/Poly1305)"), "{}", message);
+    let sealed = to_cose_encrypt0(&aes, b"ad", b"some data").unwrap();
+    assert_eq!(from_cose_encrypt0(&aes, b"ad", &sealed).unwrap(), b"some data");
+}
+
+#[cfg(all(feature = "provider-aes-gcm", feature = "provider-ring"))]
+#[test]
+fn test_cose_across_providers() {
+    use vault::providers::{AesGcm256, RingAesGcm, RingProvider};
+
+    let key = Key::<AesGcm256>::random().unwrap();
+    let ring = Key::<RingProvider<RingAesGcm>>::load_from_slice(key.bytes()).unwrap();
+    let sealed = to_cose_encrypt0(&key, b"ad", b"some data").unwrap();
+    assert_eq!(from_cose_encrypt0(&ring, b"ad", &sealed).unwrap(), b"some data");
+    let sealed = to_cose_encrypt0(&ring, b"ad", b"some data").unwrap();
+    assert_eq!(from_cose_encrypt0(&key, b"ad", &sealed).unwrap(), b"some data");
+}
+
+#[test]
+fn test_cose_without_algorithm() {
+    let key = Key::<Provider>::random().unwrap();
+    let message = cose_error(to_cose_encrypt0(&key, b"", b"some data"));
+    assert!(message.contains("xcp1"), "{}", message);
+    assert!(from_cose_encrypt0(&key, b"", &hex(RFC_EXAMPLE)).is_err());
+}
+
+#[test]
+fn test_cose_rejects() {
+    let key = Key::<RfcProvider>::load(hex(RFC_KEY)).unwrap();
+    let example = hex(RFC_EXAMPLE);
+
+    for len in 0..example.len() {
+        assert!(from_cose_encrypt0(&key, b"", &example[..len]).is_err());
+    }
+    assert!(from_cose_encrypt0(&key, b"", &[&example[..], &[0]].concat()).is_err());
+
+    let rejects = [
+        // another tag
+        ("d18343a1010aa1054d89f52f65a1c580933b5261a78c4100", "tag"),
+        // four items
+        ("d08443a1010aa1054d89f52f65a1c580933b5261a78c410000", "COSE_Encrypt0"),
+        // a detached ciphertext
+        ("d08343a1010aa1054d89f52f65a1c580933b5261a78cf6", "Detached"),
+        // the algorithm in the unprotected header
+        ("d08340a2010a054d89f52f65a1c580933b5261a78c4100", "protected header"),
+        // no algorithm
+        ("d08340a1054d89f52f65a1c580933b5261a78c4100", "Missing algorithm"),
+        // no IV
+        ("d08343a1010aa04100", "Missing IV"),
+        // an IV in both headers
+        ("d08345a2010a0540a1054d89f52f65a1c580933b5261a78c4100", "Duplicate"),
+        // a critical header
+        ("d08346a2010a02810fa1054d89f52f65a1c580933b5261a78c4100", "Critical"),
+        // an algorithm name
+        ("d08349a1016641313238474da1054d89f52f65a1c580933b5261a78c4100", "A128GM"),
+    ];
+    for (data, expected) in rejects.iter() {
+        let message = cose_error(from_cose_encrypt0(&key, b"", &hex(data)));
+        assert!(message.contains(expected), "{}: {}", data, message);
+    }
+
+    assert!(matches!(
+        from_cose_encrypt0(&key, b"", &hex("d08343a1010aa1054c000102030405060708090a0b4100")),
+        Err(Error::InvalidNonceLength {
+            expected: 13,
+            actual: 12
+        })
+    ));
+}
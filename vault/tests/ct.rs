@@ -0,0 +1,67 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use vault::ct::{ct_eq, ct_select};
+
+/// random slices of up to 40 bytes from a small alphabet, so equal slices and common prefixes are frequent
+fn random_bytes(rng: &mut StdRng) -> Vec<u8> {
+    let len = rng.gen_range(0..=40);
+    (0..len).map(|_| rng.gen_range(0..4)).collect()
+}
+
+#[test]
+fn test_ct_eq_matches_eq() {
+    let mut rng = StdRng::seed_from_u64(0x5eed);
+    let mut equal = 0;
+    for _ in 0..20_000 {
+        let a = random_bytes(&mut rng);
+        let b = if rng.gen_bool(0.3) {
+            a.clone()
+        } else {
+            random_bytes(&mut rng)
+        };
+        assert_eq!(ct_eq(&a, &b), a == b, "{:?} {:?}", a, b);
+        assert_eq!(ct_eq(&b, &a), a == b);
+        equal += (a == b) as usize;
+    }
+    assert!(equal > 1000);
+}
+
+#[test]
+fn test_ct_eq_lengths() {
+    let mut rng = StdRng::seed_from_u64(0x1e9);
+    for _ in 0..1000 {
+        let a = random_bytes(&mut rng);
+        // a prefix or an extension of a slice is never equal to it
+        let cut = rng.gen_range(0..=a.len());
+        assert_eq!(ct_eq(&a, &a[..cut]), cut == a.len());
+        let mut longer = a.clone();
+        longer.push(rng.gen());
+        assert!(!ct_eq(&a, &longer));
+        assert!(!ct_eq(&longer, &a));
+    }
+    assert!(ct_eq(&[], &[]));
+    assert!(!ct_eq(&[], &[0]));
+}
+
+#[test]
+fn test_ct_select_matches_if() {
+    let mut rng = StdRng::seed_from_u64(0x5e1ec7);
+    for _ in 0..10_000 {
+        let (a, b, choice) = (rng.gen(), rng.gen(), rng.gen());
+        assert_eq!(ct_select(a, b, choice), if choice { b } else { a });
+    }
+    for a in 0..=255u8 {
+        assert_eq!(ct_select(a, !a, false), a);
+        assert_eq!(ct_select(a, !a, true), !a);
+    }
+}
@@ -0,0 +1,1023 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+mod utils;
+
+use std::{
+    cell::Cell,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use utils::{provider::Provider, test_vault::TestVault};
+use vault::{
+    BoxProvider, Clock, DBView, DBWriter, DeleteRequest, Error, ExpiryPolicy, Id, Key, ListResult, ReadOnlyVault,
+    ReadResult, RecordHint, RecordMeta, RetentionPolicy, WriteRequest,
+};
+
+/// a vault with a chain of `owner` and a single record, and the id of the record
+fn vault_with_record(owner: Id) -> (TestVault, Id) {
+    let key = Key::<Provider>::random().unwrap();
+    let mut vault = TestVault::empty(key.clone());
+    let (id, data) = DBWriter::create_chain(&key, owner).into();
+    vault.records.insert(id, data);
+
+    let view = DBView::load(key, vault.list()).unwrap();
+    let (record, requests) = view
+        .writer(owner)
+        .write(b"some data", RecordHint::new(b"hint").unwrap())
+        .unwrap();
+    for request in requests {
+        let (id, data) = request.into();
+        vault.records.insert(id, data);
+    }
+    (vault, record)
+}
+
+#[test]
+fn test_database_record_not_found() {
+    let owner = Id::random::<Provider>().unwrap();
+    let (vault, record) = vault_with_record(owner);
+    let view = DBView::load(vault.key().clone(), vault.list()).unwrap();
+    let missing = Id::random::<Provider>().unwrap();
+
+    let reader = view.reader();
+    assert!(reader.prepare_read(record).is_ok());
+    assert_eq!(
+        reader.prepare_read(missing).err().unwrap(),
+        Error::RecordNotFound { id: missing }
+    );
+    assert_eq!(
+        reader
+            .read(ReadResult::new(missing.as_ref().to_vec(), Vec::new()))
+            .err()
+            .unwrap(),
+        Error::RecordNotFound { id: missing }
+    );
+    assert_eq!(
+        DBView::load(vault.key().clone(), vault.list())
+            .unwrap()
+            .writer(owner)
+            .revoke(missing)
+            .err()
+            .unwrap(),
+        Error::RecordNotFound { id: missing }
+    );
+
+    // a revoked record isn't valid anymore
+    let (revocation, _) = view.writer(owner).revoke(record).unwrap();
+    let mut vault = vault;
+    let (id, data) = revocation.into();
+    vault.records.insert(id, data);
+    let view = DBView::load(vault.key().clone(), vault.list()).unwrap();
+    assert_eq!(
+        view.reader().prepare_read(record).err().unwrap(),
+        Error::RecordNotFound { id: record }
+    );
+}
+
+#[test]
+fn test_database_invalid_entry() {
+    let owner = Id::random::<Provider>().unwrap();
+    let (vault, _) = vault_with_record(owner);
+    let view = DBView::load(vault.key().clone(), vault.list()).unwrap();
+
+    match view.reader().read(ReadResult::new(vec![0; 7], Vec::new())) {
+        Err(Error::InvalidEntry { reason }) => assert!(reason.contains("`7` bytes"), "{}", reason),
+        r => panic!("unexpected result: {:?}", r),
+    }
+}
+
+#[test]
+fn test_database_version_mismatch() {
+    let owner = Id::random::<Provider>().unwrap();
+    let (vault, _) = vault_with_record(owner);
+    let view = DBView::load(vault.key().clone(), vault.list()).unwrap();
+
+    let mut ctrs = view.chain_ctrs();
+    assert_eq!(ctrs[&owner], 1);
+    assert!(view.not_older_than(&ctrs).is_ok());
+    ctrs.insert(owner, 5);
+    assert_eq!(
+        view.not_older_than(&ctrs).err().unwrap(),
+        Error::VersionMismatch { expected: 5, found: 1 }
+    );
+
+    // a chain the database doesn't have at all
+    ctrs.insert(Id::random::<Provider>().unwrap(), 0);
+    ctrs.insert(owner, 1);
+    assert!(matches!(view.not_older_than(&ctrs), Err(Error::VersionError(_))));
+}
+
+#[test]
+fn test_database_corrupt_record() {
+    let owner = Id::random::<Provider>().unwrap();
+    let (vault, _) = vault_with_record(owner);
+
+    // a box of the key which doesn't hold a transaction
+    let corrupt = Provider::box_seal(vault.key(), b"", b"no transaction").unwrap();
+    let mut ids = vault.list().ids().clone();
+    ids.insert(1, corrupt);
+    assert_eq!(
+        DBView::load(vault.key().clone(), ListResult::new(ids)).err().unwrap(),
+        Error::CorruptRecord { offset: 1 }
+    );
+
+    // records of other keys are skipped
+    let other = Key::<Provider>::random().unwrap();
+    let foreign = Provider::box_seal(&other, b"", b"no transaction").unwrap();
+    let mut ids = vault.list().ids().clone();
+    ids.insert(0, foreign);
+    let view = DBView::load(vault.key().clone(), ListResult::new(ids)).unwrap();
+    assert_eq!(view.records().count(), 1);
+}
+
+#[test]
+fn test_database_error_display() {
+    let id = Id::load(&[0; 24]).unwrap();
+    let cases = [
+        (
+            Error::RecordNotFound { id },
+            "Record not found: no valid record `AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA`",
+        ),
+        (
+            Error::InvalidEntry {
+                reason: String::from("bad id"),
+            },
+            "Invalid Entry: `bad id`",
+        ),
+        (
+            Error::VersionMismatch { expected: 5, found: 1 },
+            "Version Mismatch: expected a counter of at least `5`, found `1`",
+        ),
+        (
+            Error::CorruptRecord { offset: 3 },
+            "Corrupt Record: the record at offset `3` of the list opens but isn't a transaction",
+        ),
+    ];
+    for (error, display) in cases.iter() {
+        assert_eq!(error.to_string(), *display);
+    }
+
+    assert_ne!(Error::CorruptRecord { offset: 3 }, Error::CorruptRecord { offset: 4 });
+    assert_ne!(
+        Error::InvalidEntry {
+            reason: String::from("Invalid Entry")
+        },
+        Error::OtherError(String::from("Invalid Entry"))
+    );
+
+    // the stringly variant is still there for downstream matches
+    #[allow(deprecated)]
+    let legacy = Error::DatabaseError(String::from("Invalid Entry"));
+    assert_eq!(legacy.to_string(), "Database Error: `Invalid Entry`");
+}
+
+/// a vault with a chain of `owner` and `count` records, and the ids of the records
+fn vault_with_records(owner: Id, count: usize) -> (TestVault, Vec<Id>) {
+    let (mut vault, first) = vault_with_record(owner);
+    let mut ids = vec![first];
+    for i in 1..count {
+        let view = DBView::load(vault.key().clone(), vault.list()).unwrap();
+        let data = format!("record {}", i);
+        let (id, requests) = view
+            .writer(owner)
+            .write(data.as_bytes(), RecordHint::new(b"hint").unwrap())
+            .unwrap();
+        apply(&mut vault, requests, Vec::new());
+        ids.push(id);
+    }
+    (vault, ids)
+}
+
+fn apply(vault: &mut TestVault, to_write: Vec<WriteRequest>, to_delete: Vec<DeleteRequest>) {
+    for request in to_write {
+        let (id, data) = request.into();
+        vault.records.insert(id, data);
+    }
+    for request in to_delete {
+        let id: Vec<u8> = request.into();
+        vault.records.remove(&id);
+    }
+}
+
+/// the data of every record of the vault opened with `key`
+fn read_all(vault: &TestVault, key: &Key<Provider>, ids: &[Id]) -> Vec<Vec<u8>> {
+    let view = DBView::load(key.clone(), vault.list()).unwrap();
+    let reader = view.reader();
+    ids.iter()
+        .map(|id| {
+            let res = vault.read(reader.prepare_read(*id).unwrap()).unwrap();
+            reader.read(res).unwrap()
+        })
+        .collect()
+}
+
+#[test]
+fn test_database_rekey() {
+    let owner = Id::random::<Provider>().unwrap();
+    let (mut vault, ids) = vault_with_records(owner, 5);
+    let old_key = vault.key().clone();
+    let new_key = Key::<Provider>::random().unwrap();
+    let before = read_all(&vault, &old_key, &ids);
+
+    let view = DBView::load(old_key.clone(), vault.list()).unwrap();
+    let (report, to_write, to_delete) = view
+        .rekey(&old_key, &new_key, &vault.list(), |req| vault.read(req))
+        .unwrap();
+    assert!(report.failed.is_empty());
+    // 5 payloads and the init and 5 data transactions
+    assert_eq!(report.rekeyed, 11);
+    assert_eq!(to_write.len(), 11);
+    assert_eq!(to_delete.len(), 6);
+    apply(&mut vault, to_write, to_delete);
+
+    assert_eq!(read_all(&vault, &new_key, &ids), before);
+    let view = DBView::load(old_key, vault.list()).unwrap();
+    assert_eq!(view.records().count(), 0);
+
+    // the re-keyed vault can be written to
+    vault.key = new_key.clone();
+    let view = DBView::load(new_key, vault.list()).unwrap();
+    assert!(view
+        .writer(owner)
+        .write(b"more", RecordHint::new(b"hint").unwrap())
+        .is_ok());
+}
+
+#[test]
+fn test_database_rekey_rollback() {
+    let owner = Id::random::<Provider>().unwrap();
+    let (vault, ids) = vault_with_records(owner, 5);
+    let old_key = vault.key().clone();
+    let new_key = Key::<Provider>::random().unwrap();
+    let view = DBView::load(old_key.clone(), vault.list()).unwrap();
+
+    // the payload of a record in the middle of the rotation doesn't open
+    let corrupt = ids[2];
+    let (report, to_write, to_delete) = view
+        .rekey(&old_key, &new_key, &vault.list(), |req| {
+            let mut res: (Vec<u8>, Vec<u8>) = vault.read(req)?.into();
+            if res.0 == corrupt.as_ref() {
+                res.1[0] ^= 1;
+            }
+            Some(ReadResult::new(res.0, res.1))
+        })
+        .unwrap();
+    assert_eq!(report.failed, vec![corrupt]);
+    assert_eq!(report.rekeyed, 0);
+    assert!(to_write.is_empty());
+    assert!(to_delete.is_empty());
+
+    // a missing payload
+    let missing = ids[4];
+    let (report, to_write, _) = view
+        .rekey(&old_key, &new_key, &vault.list(), |req| {
+            match req.id() == missing.as_ref() {
+                true => None,
+                false => vault.read(req),
+            }
+        })
+        .unwrap();
+    assert_eq!(report.failed, vec![missing]);
+    assert!(to_write.is_empty());
+
+    // the vault still opens under the old key only
+    assert_eq!(read_all(&vault, &old_key, &ids).len(), 5);
+    let view = DBView::load(new_key, vault.list()).unwrap();
+    assert_eq!(view.records().count(), 0);
+}
+
+#[test]
+fn test_database_rekey_keys() {
+    let owner = Id::random::<Provider>().unwrap();
+    let (vault, _) = vault_with_record(owner);
+    let key = vault.key().clone();
+    let view = DBView::load(key.clone(), vault.list()).unwrap();
+
+    assert!(matches!(
+        view.rekey(&key, &key.clone(), &vault.list(), |req| vault.read(req)),
+        Err(Error::RekeyError(_))
+    ));
+    let other = Key::<Provider>::random().unwrap();
+    assert!(matches!(
+        view.rekey(&other, &Key::random().unwrap(), &vault.list(), |req| vault.read(req)),
+        Err(Error::RekeyError(_))
+    ));
+}
+
+/// a clock tests can fast-forward
+struct TestClock(Cell<SystemTime>);
+
+impl Clock for TestClock {
+    fn now(&self) -> SystemTime {
+        self.0.get()
+    }
+}
+
+impl TestClock {
+    fn advance(&self, by: Duration) {
+        self.0.set(self.0.get() + by);
+    }
+}
+
+/// writes `data` expiring at `expires_at` to the chain of `owner`, `None` writes it without expiry
+fn write_expiring(vault: &mut TestVault, owner: Id, data: &[u8], expires_at: Option<SystemTime>) -> Id {
+    let view = DBView::load(vault.key().clone(), vault.list()).unwrap();
+    let hint = RecordHint::new(b"hint").unwrap();
+    let (id, requests) = match expires_at {
+        Some(expires_at) => view.writer(owner).write_expiring(data, hint, expires_at),
+        None => view.writer(owner).write(data, hint),
+    }
+    .unwrap();
+    apply(vault, requests, Vec::new());
+    id
+}
+
+#[test]
+fn test_database_expiry() {
+    let owner = Id::random::<Provider>().unwrap();
+    let (mut vault, plain) = vault_with_record(owner);
+    let start = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+    let expires_at = start + Duration::from_secs(60);
+    let expiring = write_expiring(&mut vault, owner, b"session token", Some(expires_at));
+
+    let clock = TestClock(Cell::new(start));
+    let policy = ExpiryPolicy {
+        clock: &clock,
+        skew_tolerance: Duration::ZERO,
+    };
+    let read = |policy: ExpiryPolicy, id: Id| {
+        let view = DBView::load(vault.key().clone(), vault.list()).unwrap();
+        let reader = view.reader().with_expiry(policy);
+        let res = vault.read(reader.prepare_read(id).unwrap()).unwrap();
+        reader.read(res)
+    };
+
+    assert_eq!(read(policy, expiring).unwrap(), b"session token");
+    clock.advance(Duration::from_secs(60));
+    assert_eq!(read(policy, expiring).unwrap(), b"session token");
+    clock.advance(Duration::from_secs(1));
+    assert_eq!(
+        read(policy, expiring).err().unwrap(),
+        Error::RecordExpired { expired_at: expires_at }
+    );
+
+    // records without expiry never expire
+    clock.advance(Duration::from_secs(1_000_000_000));
+    assert_eq!(read(policy, plain).unwrap(), b"some data");
+
+    // the tolerance keeps the record readable for a while after the expiry
+    clock.0.set(expires_at + Duration::from_secs(30));
+    let tolerant = ExpiryPolicy {
+        clock: &clock,
+        skew_tolerance: Duration::from_secs(30),
+    };
+    assert!(read(tolerant, expiring).is_ok());
+    clock.advance(Duration::from_secs(1));
+    assert!(matches!(read(tolerant, expiring), Err(Error::RecordExpired { .. })));
+
+    // the system clock is past the expiry too
+    assert!(matches!(
+        read(ExpiryPolicy::default(), expiring),
+        Err(Error::RecordExpired { .. })
+    ));
+    assert!(read(ExpiryPolicy::default(), plain).is_ok());
+}
+
+#[test]
+fn test_database_expiry_bound() {
+    let owner = Id::random::<Provider>().unwrap();
+    let (mut vault, _) = vault_with_record(owner);
+    let expires_at = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+    let id = write_expiring(&mut vault, owner, b"one time code", Some(expires_at));
+    let payload = vault.records[id.as_ref()].clone();
+    let view = DBView::load(vault.key().clone(), vault.list()).unwrap();
+    let reader = view.reader();
+
+    // stripping the expiry doesn't open the payload, with or without the tag
+    let stripped = ReadResult::new(id.as_ref().to_vec(), payload[9..].to_vec());
+    assert_eq!(reader.read(stripped).err().unwrap(), Error::AuthenticationFailed);
+    let stripped = ReadResult::new(id.as_ref().to_vec(), [&payload[..1], &payload[9..]].concat());
+    assert_eq!(reader.read(stripped).err().unwrap(), Error::AuthenticationFailed);
+
+    // nor does extending it without re-sealing
+    let mut extended = payload;
+    extended[1..9].copy_from_slice(&u64::MAX.to_be_bytes());
+    let extended = ReadResult::new(id.as_ref().to_vec(), extended);
+    assert_eq!(reader.read(extended).err().unwrap(), Error::AuthenticationFailed);
+
+    // an expiry before the UNIX epoch
+    let view = DBView::load(vault.key().clone(), vault.list()).unwrap();
+    let before = UNIX_EPOCH - Duration::from_secs(1);
+    assert!(matches!(
+        view.writer(owner)
+            .write_expiring(b"data", RecordHint::new(b"hint").unwrap(), before),
+        Err(Error::InvalidEntry { .. })
+    ));
+}
+
+#[test]
+fn test_database_sweep() {
+    let owner = Id::random::<Provider>().unwrap();
+    let (mut vault, plain) = vault_with_record(owner);
+    let start = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+    let soon = write_expiring(&mut vault, owner, b"soon", Some(start + Duration::from_secs(10)));
+    let later = write_expiring(&mut vault, owner, b"later", Some(start + Duration::from_secs(100)));
+    let never = write_expiring(&mut vault, owner, b"never", None);
+
+    let clock = TestClock(Cell::new(start + Duration::from_secs(50)));
+    let policy = ExpiryPolicy {
+        clock: &clock,
+        skew_tolerance: Duration::ZERO,
+    };
+    let view = DBView::load(vault.key().clone(), vault.list()).unwrap();
+    assert_eq!(view.expired(|req| vault.read(req), &policy), vec![soon]);
+
+    clock.advance(Duration::from_secs(100));
+    let mut expired = view.expired(|req| vault.read(req), &policy);
+    expired.sort_by(|a, b| a.as_ref().cmp(b.as_ref()));
+    let mut expected = vec![soon, later];
+    expected.sort_by(|a, b| a.as_ref().cmp(b.as_ref()));
+    assert_eq!(expired, expected);
+
+    let (to_write, to_delete) = view.writer(owner).sweep(|req| vault.read(req), &policy).unwrap();
+    assert_eq!(to_write.len(), 2);
+    assert_eq!(to_delete.len(), 2);
+    apply(&mut vault, to_write, to_delete);
+
+    let view = DBView::load(vault.key().clone(), vault.list()).unwrap();
+    let mut left: Vec<Id> = view.records().map(|(id, _)| id).collect();
+    left.sort_by(|a, b| a.as_ref().cmp(b.as_ref()));
+    let mut expected = vec![plain, never];
+    expected.sort_by(|a, b| a.as_ref().cmp(b.as_ref()));
+    assert_eq!(left, expected);
+    assert!(view.expired(|req| vault.read(req), &policy).is_empty());
+}
+
+#[test]
+fn test_database_rekey_expiring() {
+    let owner = Id::random::<Provider>().unwrap();
+    let (mut vault, _) = vault_with_record(owner);
+    let expires_at = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+    let id = write_expiring(&mut vault, owner, b"session token", Some(expires_at));
+
+    // the record expired by the system clock, it is re-keyed nevertheless
+    let old_key = vault.key().clone();
+    let new_key = Key::<Provider>::random().unwrap();
+    let view = DBView::load(old_key.clone(), vault.list()).unwrap();
+    let (report, to_write, to_delete) = view
+        .rekey(&old_key, &new_key, &vault.list(), |req| vault.read(req))
+        .unwrap();
+    assert!(report.failed.is_empty());
+    apply(&mut vault, to_write, to_delete);
+
+    // and keeps its expiry
+    let clock = TestClock(Cell::new(expires_at));
+    let policy = ExpiryPolicy {
+        clock: &clock,
+        skew_tolerance: Duration::ZERO,
+    };
+    let view = DBView::load(new_key, vault.list()).unwrap();
+    let reader = view.reader().with_expiry(policy);
+    let res = vault.read(reader.prepare_read(id).unwrap()).unwrap();
+    assert_eq!(reader.read(res.clone()).unwrap(), b"session token");
+    clock.advance(Duration::from_secs(1));
+    assert_eq!(
+        reader.read(res).err().unwrap(),
+        Error::RecordExpired { expired_at: expires_at }
+    );
+}
+
+/// the entries of the store
+fn entries(vault: &TestVault) -> Vec<ReadResult> {
+    vault
+        .records
+        .iter()
+        .map(|(id, data)| ReadResult::new(id.clone(), data.clone()))
+        .collect()
+}
+
+fn store_size(vault: &TestVault) -> usize {
+    vault.records.iter().map(|(id, data)| id.len() + data.len()).sum()
+}
+
+#[test]
+fn test_database_gc() {
+    let owner = Id::random::<Provider>().unwrap();
+    let (mut vault, ids) = vault_with_records(owner, 6);
+    let key = vault.key().clone();
+
+    // revoke half of the records, but the store keeps their payloads
+    for id in &ids[..3] {
+        let view = DBView::load(key.clone(), vault.list()).unwrap();
+        let (revocation, _) = view.writer(owner).revoke(*id).unwrap();
+        apply(&mut vault, vec![revocation], Vec::new());
+    }
+    // a collection of the chain which was interrupted before the old transactions were deleted
+    let view = DBView::load(key.clone(), vault.list()).unwrap();
+    let (to_write, _) = view.writer(owner).gc().unwrap();
+    apply(&mut vault, to_write, Vec::new());
+
+    // a record of another key and a write in flight, its payload is already stored
+    let other = Key::<Provider>::random().unwrap();
+    let foreign = DBWriter::create_chain(&other, Id::random::<Provider>().unwrap());
+    apply(&mut vault, vec![foreign.clone()], Vec::new());
+    let view = DBView::load(key.clone(), vault.list()).unwrap();
+    let (pending, mut in_flight) = view
+        .writer(owner)
+        .write(b"in flight", RecordHint::new(b"hint").unwrap())
+        .unwrap();
+    let transaction = in_flight.pop().unwrap();
+    apply(&mut vault, in_flight, Vec::new());
+
+    let before = store_size(&vault);
+    let live = read_all(&vault, &key, &ids[3..]);
+    let (report, to_write, to_delete) = DBView::gc(&key, &entries(&vault), std::slice::from_ref(&transaction)).unwrap();
+    // the 3 revoked payloads and the 10 old transactions
+    assert_eq!(report.removed_blobs, 13);
+    assert_eq!(to_delete.len(), 13);
+    assert_eq!(to_write.len(), 3);
+    assert!(to_write.iter().all(|request| request.data().iter().all(|b| *b == 0)));
+    apply(&mut vault, to_write, to_delete);
+
+    assert_eq!(before - store_size(&vault), report.reclaimed_bytes as usize);
+    assert_eq!(read_all(&vault, &key, &ids[3..]), live);
+    assert!(vault.records.contains_key(foreign.id()));
+    assert!(vault.records.contains_key(pending.as_ref()));
+
+    // committing the write in flight makes its record readable
+    apply(&mut vault, vec![transaction], Vec::new());
+    assert_eq!(read_all(&vault, &key, &[pending]), vec![b"in flight".to_vec()]);
+
+    // nothing is left to collect
+    let (report, to_write, to_delete) = DBView::gc(&key, &entries(&vault), &[]).unwrap();
+    assert_eq!(report.removed_blobs, 0);
+    assert_eq!(report.reclaimed_bytes, 0);
+    assert!(to_write.is_empty() && to_delete.is_empty());
+}
+
+#[test]
+fn test_database_read_only() {
+    fn assert_send_sync<T: Send + Sync + Clone>() {}
+    assert_send_sync::<ReadOnlyVault<Provider>>();
+
+    let owner = Id::random::<Provider>().unwrap();
+    let (vault, ids) = vault_with_records(owner, 4);
+    let expected = read_all(&vault, vault.key(), &ids);
+    let view = DBView::load(vault.key().clone(), vault.list()).unwrap();
+    let read_only = view.read_only();
+    assert_eq!(read_only.records().count(), 4);
+    assert_eq!(read_only.absolute_balance(), (4, 5));
+    assert!(read_only.not_older_than(&read_only.chain_ctrs()).is_ok());
+
+    // clones are shared by the readers of several threads
+    std::thread::scope(|scope| {
+        for _ in 0..4 {
+            let read_only = read_only.clone();
+            let (vault, ids, expected) = (&vault, &ids, &expected);
+            scope.spawn(move || {
+                let reader = read_only.reader();
+                for (id, data) in ids.iter().zip(expected) {
+                    let res = vault.read(reader.prepare_read(*id).unwrap()).unwrap();
+                    assert_eq!(&reader.read(res).unwrap(), data);
+                }
+            });
+        }
+    });
+}
+
+/// a versioned record of `owner` with the versions `data`, written a minute apart starting at the time of `clock`
+fn write_versions(vault: &mut TestVault, owner: Id, clock: &TestClock, data: &[&[u8]]) -> Id {
+    let view = DBView::load(vault.key().clone(), vault.list()).unwrap();
+    let hint = RecordHint::new(b"versioned").unwrap();
+    let (id, requests) = view.writer(owner).write_versioned(data[0], hint, clock).unwrap();
+    apply(vault, requests, Vec::new());
+    for data in &data[1..] {
+        clock.advance(Duration::from_secs(60));
+        let view = DBView::load(vault.key().clone(), vault.list()).unwrap();
+        let (_, request) = view
+            .writer(owner)
+            .write_version(id, data, &vault.list(), clock)
+            .unwrap();
+        apply(vault, vec![request], Vec::new());
+    }
+    id
+}
+
+#[test]
+fn test_database_versions() {
+    let owner = Id::random::<Provider>().unwrap();
+    let (mut vault, _) = vault_with_record(owner);
+    let start = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+    let clock = TestClock(Cell::new(start));
+    let data: [&[u8]; 5] = [b"v1", b"v2", b"v3", b"v4", b"v5"];
+    let id = write_versions(&mut vault, owner, &clock, &data);
+
+    let view = DBView::load(vault.key().clone(), vault.list()).unwrap();
+    let history = view.history(id, &vault.list()).unwrap();
+    let versions: Vec<u64> = history.iter().map(|info| info.version).collect();
+    assert_eq!(versions, [1, 2, 3, 4, 5]);
+    assert_eq!(history[2].timestamp, start + Duration::from_secs(120));
+
+    let reader = view.reader();
+    let read = |req| vault.read(req);
+    for (n, data) in data.iter().enumerate() {
+        assert_eq!(
+            reader.read_version(id, n as u64 + 1, &vault.list(), read).unwrap(),
+            *data
+        );
+    }
+    assert_eq!(reader.read_latest(id, &vault.list(), read).unwrap(), b"v5");
+    assert_eq!(
+        reader
+            .read_at(id, start + Duration::from_secs(150), &vault.list(), read)
+            .unwrap(),
+        b"v3"
+    );
+    assert!(matches!(
+        reader.read_at(id, start - Duration::from_secs(1), &vault.list(), read),
+        Err(Error::VersionNotFound { .. })
+    ));
+    assert!(matches!(
+        reader.read_version(id, 6, &vault.list(), read),
+        Err(Error::VersionNotFound { .. })
+    ));
+
+    // an older clock doesn't move the timestamps back
+    clock.0.set(start);
+    let (version, request) = DBView::load(vault.key().clone(), vault.list())
+        .unwrap()
+        .writer(owner)
+        .write_version(id, b"v6", &vault.list(), &clock)
+        .unwrap();
+    apply(&mut vault, vec![request], Vec::new());
+    let history = view.history(id, &vault.list()).unwrap();
+    assert_eq!(version, 6);
+    assert_eq!(history[5].timestamp, history[4].timestamp);
+
+    // a plain record has no versions, an unknown one no history
+    let plain = write_expiring(&mut vault, owner, b"plain", None);
+    let view = DBView::load(vault.key().clone(), vault.list()).unwrap();
+    assert!(view.history(plain, &vault.list()).unwrap().is_empty());
+    let missing = Id::random::<Provider>().unwrap();
+    assert!(matches!(
+        view.history(missing, &vault.list()),
+        Err(Error::RecordNotFound { .. })
+    ));
+}
+
+#[test]
+fn test_database_versions_replay() {
+    let owner = Id::random::<Provider>().unwrap();
+    let (mut vault, _) = vault_with_record(owner);
+    let clock = TestClock(Cell::new(UNIX_EPOCH + Duration::from_secs(1_700_000_000)));
+    let id = write_versions(&mut vault, owner, &clock, &[b"old", b"current"]);
+
+    // the ciphertext of the old version copied over the current one doesn't open as the current version
+    let mut entries: Vec<Vec<u8>> = vault
+        .records
+        .keys()
+        .filter(|entry| entry.len() == 40)
+        .cloned()
+        .collect();
+    entries.sort_by_key(|entry| entry[24..32].to_vec());
+    let old = vault.records[&entries[0]].clone();
+    vault.records.insert(entries[1].clone(), old);
+
+    let view = DBView::load(vault.key().clone(), vault.list()).unwrap();
+    let reader = view.reader();
+    let read = |req| vault.read(req);
+    assert!(matches!(
+        reader.read_latest(id, &vault.list(), read),
+        Err(Error::AuthenticationFailed)
+    ));
+    assert_eq!(reader.read_version(id, 1, &vault.list(), read).unwrap(), b"old");
+
+    // nor does it open as the version of another record
+    let other = write_versions(&mut vault, owner, &clock, &[b"other"]);
+    let entry = vault
+        .list()
+        .ids()
+        .iter()
+        .find(|entry| entry.starts_with(other.as_ref()))
+        .cloned();
+    vault.records.insert(entry.unwrap(), vault.records[&entries[0]].clone());
+    let view = DBView::load(vault.key().clone(), vault.list()).unwrap();
+    assert!(matches!(
+        view.reader().read_latest(other, &vault.list(), |req| vault.read(req)),
+        Err(Error::AuthenticationFailed)
+    ));
+}
+
+#[test]
+fn test_database_versions_rekey() {
+    let owner = Id::random::<Provider>().unwrap();
+    let (mut vault, _) = vault_with_record(owner);
+    let clock = TestClock(Cell::new(UNIX_EPOCH + Duration::from_secs(1_700_000_000)));
+    let data: [&[u8]; 3] = [b"v1", b"v2", b"v3"];
+    let id = write_versions(&mut vault, owner, &clock, &data);
+    let old_key = vault.key().clone();
+    let new_key = Key::<Provider>::random().unwrap();
+
+    // a version which doesn't open fails the whole rotation
+    let view = DBView::load(old_key.clone(), vault.list()).unwrap();
+    let entry = vault
+        .list()
+        .ids()
+        .iter()
+        .find(|entry| entry.len() == 40)
+        .cloned()
+        .unwrap();
+    let (report, to_write, to_delete) = view
+        .rekey(&old_key, &new_key, &vault.list(), |req| {
+            let mut res: (Vec<u8>, Vec<u8>) = vault.read(req)?.into();
+            if res.0 == entry {
+                res.1[0] ^= 1;
+            }
+            Some(ReadResult::new(res.0, res.1))
+        })
+        .unwrap();
+    assert_eq!(report.failed, vec![id]);
+    assert!(to_write.is_empty() && to_delete.is_empty());
+
+    let (report, to_write, to_delete) = view
+        .rekey(&old_key, &new_key, &vault.list(), |req| vault.read(req))
+        .unwrap();
+    assert!(report.failed.is_empty());
+    apply(&mut vault, to_write, to_delete);
+    vault.key = new_key.clone();
+
+    // every version opens under the new key
+    let view = DBView::load(new_key, vault.list()).unwrap();
+    let reader = view.reader();
+    for (n, data) in data.iter().enumerate() {
+        assert_eq!(
+            reader
+                .read_version(id, n as u64 + 1, &vault.list(), |req| vault.read(req))
+                .unwrap(),
+            *data
+        );
+    }
+}
+
+#[test]
+fn test_database_versions_prune() {
+    let owner = Id::random::<Provider>().unwrap();
+    let (mut vault, _) = vault_with_record(owner);
+    let start = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+    let clock = TestClock(Cell::new(start));
+    let id = write_versions(&mut vault, owner, &clock, &[b"v1", b"v2", b"v3", b"v4", b"v5"]);
+    let old: Vec<Vec<u8>> = vault
+        .list()
+        .ids()
+        .iter()
+        .filter(|entry| entry.len() == 40)
+        .cloned()
+        .collect();
+
+    // keeping the versions written within two minutes prunes the two older ones
+    let policy = RetentionPolicy::keep_for(Duration::from_secs(120));
+    let (to_write, to_delete) = DBView::load(vault.key().clone(), vault.list())
+        .unwrap()
+        .writer(owner)
+        .prune(id, policy, &vault.list(), &clock, |req| vault.read(req))
+        .unwrap();
+    assert_eq!((to_write.len(), to_delete.len()), (2, 2));
+    assert!(to_write.iter().all(|request| request.data().iter().all(|b| *b == 0)));
+
+    let policy = RetentionPolicy::keep_last(2);
+    let (to_write, to_delete) = DBView::load(vault.key().clone(), vault.list())
+        .unwrap()
+        .writer(owner)
+        .prune(id, policy, &vault.list(), &clock, |req| vault.read(req))
+        .unwrap();
+    assert_eq!((to_write.len(), to_delete.len()), (3, 3));
+    apply(&mut vault, to_write, to_delete);
+
+    let view = DBView::load(vault.key().clone(), vault.list()).unwrap();
+    let versions: Vec<u64> = view
+        .history(id, &vault.list())
+        .unwrap()
+        .iter()
+        .map(|info| info.version)
+        .collect();
+    assert_eq!(versions, [4, 5]);
+    assert_eq!(old.iter().filter(|entry| vault.records.contains_key(*entry)).count(), 2);
+    let reader = view.reader();
+    for version in 1..=3 {
+        assert!(matches!(
+            reader.read_version(id, version, &vault.list(), |req| vault.read(req)),
+            Err(Error::VersionNotFound { .. })
+        ));
+    }
+    assert_eq!(
+        reader
+            .read_version(id, 4, &vault.list(), |req| vault.read(req))
+            .unwrap(),
+        b"v4"
+    );
+
+    // the latest version is always kept
+    let (_, to_delete) = DBView::load(vault.key().clone(), vault.list())
+        .unwrap()
+        .writer(owner)
+        .prune(id, RetentionPolicy::keep_last(0), &vault.list(), &clock, |req| {
+            vault.read(req)
+        })
+        .unwrap();
+    apply(&mut vault, Vec::new(), to_delete);
+    let view = DBView::load(vault.key().clone(), vault.list()).unwrap();
+    assert_eq!(view.history(id, &vault.list()).unwrap().len(), 1);
+    assert_eq!(
+        view.reader()
+            .read_latest(id, &vault.list(), |req| vault.read(req))
+            .unwrap(),
+        b"v5"
+    );
+
+    // the versions of a revoked record are collected
+    let (to_write, to_delete) = DBView::load(vault.key().clone(), vault.list())
+        .unwrap()
+        .writer(owner)
+        .revoke(id)
+        .unwrap();
+    apply(&mut vault, vec![to_write], vec![to_delete]);
+    let (_, to_write, to_delete) = DBView::gc(vault.key(), &entries(&vault), &[]).unwrap();
+    apply(&mut vault, to_write, to_delete);
+    assert!(vault.list().ids().iter().all(|entry| entry.len() != 40));
+}
+
+/// writes `data` with the metadata `meta` to the chain of `owner`
+fn write_with_meta(vault: &mut TestVault, owner: Id, data: &[u8], meta: &RecordMeta) -> Id {
+    let view = DBView::load(vault.key().clone(), vault.list()).unwrap();
+    let (id, requests) = view
+        .writer(owner)
+        .write_with_meta(data, RecordHint::new(b"meta").unwrap(), meta)
+        .unwrap();
+    for request in requests {
+        let (id, data) = request.into();
+        vault.records.insert(id, data);
+    }
+    id
+}
+
+#[test]
+fn test_database_meta() {
+    let owner = Id::random::<Provider>().unwrap();
+    let (mut vault, plain) = vault_with_record(owner);
+    let created = UNIX_EPOCH + Duration::new(1_600_000_000, 123_456_789);
+    let meta = RecordMeta::new(created)
+        .with_label("api token")
+        .with_custom("service", b"mail".to_vec())
+        .with_custom("empty", Vec::new());
+    let id = write_with_meta(&mut vault, owner, b"secret", &meta);
+
+    let view = DBView::load(vault.key().clone(), vault.list()).unwrap();
+    let reader = view.reader();
+    let (data, read) = reader.read_with_meta(id, |req| vault.read(req)).unwrap();
+    assert_eq!(data, b"secret");
+    assert_eq!(read, Some(meta.clone()));
+    assert_eq!(reader.read_meta(plain, |req| vault.read(req)).unwrap(), None);
+
+    // only the metadata is read to list the records
+    let mut listed = view
+        .list_with_meta(|req| {
+            assert_ne!(req.id(), id.as_ref());
+            vault.read(req)
+        })
+        .unwrap();
+    listed.sort_by_key(|(_, _, meta)| meta.is_some());
+    assert_eq!(listed.len(), 2);
+    assert_eq!((listed[0].0, listed[0].2.as_ref()), (plain, None));
+    assert_eq!((listed[1].0, listed[1].2.as_ref()), (id, Some(&meta)));
+
+    // the metadata is bound to its record
+    let other = write_with_meta(&mut vault, owner, b"other", &RecordMeta::new(created));
+    let entry = |id: Id| [id.as_ref(), b"meta"].concat();
+    let copied = vault.records[&entry(id)].clone();
+    vault.records.insert(entry(other), copied);
+    let view = DBView::load(vault.key().clone(), vault.list()).unwrap();
+    assert_eq!(
+        view.reader().read_meta(other, |req| vault.read(req)).err().unwrap(),
+        Error::AuthenticationFailed
+    );
+
+    // and collected with it
+    let (revocation, _) = view.writer(owner).revoke(id).unwrap();
+    let (revocation, data) = revocation.into();
+    vault.records.insert(revocation, data);
+    let (report, _, to_delete) = DBView::gc(vault.key(), &entries(&vault), &[]).unwrap();
+    assert_eq!(report.removed_blobs, 2);
+    assert!(to_delete.iter().any(|request| request.id() == entry(id).as_slice()));
+}
+
+#[test]
+fn test_database_meta_overwrite() {
+    let owner = Id::random::<Provider>().unwrap();
+    let (mut vault, plain) = vault_with_record(owner);
+    let start = UNIX_EPOCH + Duration::from_secs(1_600_000_000);
+    let clock = TestClock(Cell::new(start));
+    let meta = RecordMeta::new(start).with_label("label");
+    let id = write_with_meta(&mut vault, owner, b"old", &meta);
+
+    clock.advance(Duration::from_secs(60));
+    let view = DBView::load(vault.key().clone(), vault.list()).unwrap();
+    let requests = view
+        .writer(owner)
+        .overwrite(id, b"new", &clock, |req| vault.read(req))
+        .unwrap();
+    for request in requests {
+        let (id, data) = request.into();
+        vault.records.insert(id, data);
+    }
+
+    let view = DBView::load(vault.key().clone(), vault.list()).unwrap();
+    let (data, read) = view.reader().read_with_meta(id, |req| vault.read(req)).unwrap();
+    assert_eq!(data, b"new");
+    let read = read.unwrap();
+    assert_eq!(read.created, start);
+    assert_eq!(read.modified, start + Duration::from_secs(60));
+    assert_eq!(read.label, meta.label);
+    assert_eq!(view.records().count(), 2);
+
+    // a record without metadata keeps having none
+    let requests = view
+        .writer(owner)
+        .overwrite(plain, b"plain", &clock, |req| vault.read(req))
+        .unwrap();
+    assert_eq!(requests.len(), 2);
+}
+
+#[test]
+fn test_database_meta_rekey() {
+    let owner = Id::random::<Provider>().unwrap();
+    let (mut vault, plain) = vault_with_record(owner);
+    let meta = RecordMeta::new(UNIX_EPOCH + Duration::from_secs(1_600_000_000)).with_label("api token");
+    let id = write_with_meta(&mut vault, owner, b"secret", &meta);
+    let old_key = vault.key().clone();
+    let new_key = Key::<Provider>::random().unwrap();
+    let view = DBView::load(old_key.clone(), vault.list()).unwrap();
+
+    // metadata which doesn't open fails the whole rotation
+    let entry = [id.as_ref(), b"meta"].concat();
+    let (report, to_write, _) = view
+        .rekey(&old_key, &new_key, &vault.list(), |req| {
+            let mut res: (Vec<u8>, Vec<u8>) = vault.read(req)?.into();
+            if res.0 == entry {
+                res.1[0] ^= 1;
+            }
+            Some(ReadResult::new(res.0, res.1))
+        })
+        .unwrap();
+    assert_eq!(report.failed, vec![id]);
+    assert!(to_write.is_empty());
+
+    let (report, to_write, to_delete) = view
+        .rekey(&old_key, &new_key, &vault.list(), |req| vault.read(req))
+        .unwrap();
+    assert!(report.failed.is_empty());
+    apply(&mut vault, to_write, to_delete);
+    vault.key = new_key.clone();
+
+    // the metadata opens under the new key
+    let view = DBView::load(new_key, vault.list()).unwrap();
+    let mut listed = view.list_with_meta(|req| vault.read(req)).unwrap();
+    listed.sort_by_key(|(_, _, meta)| meta.is_some());
+    assert_eq!((listed[0].0, listed[0].2.as_ref()), (plain, None));
+    assert_eq!((listed[1].0, listed[1].2.as_ref()), (id, Some(&meta)));
+    assert_eq!(view.reader().read_meta(id, |req| vault.read(req)).unwrap(), Some(meta));
+}
+
+#[test]
+fn test_database_meta_cap() {
+    let owner = Id::random::<Provider>().unwrap();
+    let (vault, _) = vault_with_record(owner);
+    let now = SystemTime::now();
+    let hint = RecordHint::new(b"meta").unwrap();
+
+    // the encoding of the times, the label flag and the count is 29 bytes, the custom entry 8 more
+    let fits = RecordMeta::new(now).with_custom("k", vec![0; RecordMeta::MAX_LEN - 29 - 9]);
+    let view = DBView::load(vault.key().clone(), vault.list()).unwrap();
+    assert!(view.writer(owner).write_with_meta(b"data", hint, &fits).is_ok());
+
+    let too_large = fits.with_custom("k", vec![0; RecordMeta::MAX_LEN - 29 - 8]);
+    let view = DBView::load(vault.key().clone(), vault.list()).unwrap();
+    assert_eq!(
+        view.writer(owner)
+            .write_with_meta(b"data", hint, &too_large)
+            .err()
+            .unwrap(),
+        Error::MetadataTooLarge {
+            len: RecordMeta::MAX_LEN + 1,
+            limit: RecordMeta::MAX_LEN
+        }
+    );
+}
@@ -0,0 +1,69 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+mod utils;
+
+use utils::provider::Provider;
+use vault::{BoxProvider, Key, Tag};
+
+/// seal two messages detached and check that their tags and ciphertexts can't be mixed up
+fn check_detached<P: BoxProvider>(tag_len: usize) {
+    let key = Key::<P>::random().unwrap();
+    assert_eq!(P::tag_len(), tag_len);
+
+    let (first, first_tag) = P::box_seal_detached(&key, b"ad", b"first message").unwrap();
+    let (second, second_tag) = P::box_seal_detached(&key, b"ad", b"second message").unwrap();
+    assert_eq!(first_tag.len(), tag_len);
+    assert_eq!(
+        first.len() + first_tag.len(),
+        b"first message".len() + P::box_overhead()
+    );
+
+    assert_eq!(
+        P::box_open_detached(&key, b"ad", &first, &first_tag).unwrap(),
+        b"first message"
+    );
+    assert_eq!(
+        P::box_open_detached(&key, b"ad", &second, &second_tag).unwrap(),
+        b"second message"
+    );
+
+    assert!(P::box_open_detached(&key, b"ad", &first, &second_tag).is_err());
+    assert!(P::box_open_detached(&key, b"ad", &second, &first_tag).is_err());
+    assert!(P::box_open_detached(&key, b"other ad", &first, &first_tag).is_err());
+
+    let short = Tag::from(&first_tag.as_bytes()[1..]);
+    assert!(P::box_open_detached(&key, b"ad", &first, &short).is_err());
+
+    // a detached box is the regular box split in two
+    let joined = [&first[..], first_tag.as_bytes()].concat();
+    assert_eq!(P::box_open(&key, b"ad", &joined).unwrap(), b"first message");
+
+    let (empty, empty_tag) = P::box_seal_detached(&key, b"", b"").unwrap();
+    assert_eq!(P::box_open_detached(&key, b"", &empty, &empty_tag).unwrap(), b"");
+}
+
+#[test]
+fn test_detached_default() {
+    check_detached::<Provider>(Provider::box_overhead());
+}
+
+#[cfg(feature = "provider-xchacha")]
+#[test]
+fn test_detached_xchacha() {
+    check_detached::<vault::providers::XChaChaPoly>(16);
+}
+
+#[cfg(feature = "provider-aes-gcm")]
+#[test]
+fn test_detached_aes_gcm() {
+    check_detached::<vault::providers::AesGcm256>(16);
+}
@@ -0,0 +1,185 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+mod utils;
+
+use std::convert::Infallible;
+
+use rand::{Rng, RngCore};
+use utils::{
+    hex,
+    provider::{IetfProvider, Provider},
+};
+use vault::{BoxProvider, Decrypt, Encrypt, Envelope, Error, Key};
+
+struct Plain(Vec<u8>);
+struct Sealed(Vec<u8>);
+
+impl AsRef<[u8]> for Plain {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl AsRef<[u8]> for Sealed {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl From<Vec<u8>> for Plain {
+    fn from(data: Vec<u8>) -> Self {
+        Self(data)
+    }
+}
+
+impl From<Vec<u8>> for Sealed {
+    fn from(data: Vec<u8>) -> Self {
+        Self(data)
+    }
+}
+
+impl Encrypt<Sealed> for Plain {}
+impl Decrypt<Infallible, Plain> for Sealed {}
+
+#[test]
+fn test_envelope_roundtrip() {
+    let key = Key::<Provider>::random().unwrap();
+    let sealed: Sealed = Plain(b"some data".to_vec()).encrypt_enveloped(&key, b"ad").unwrap();
+    assert_eq!(sealed.0.len(), Envelope::HEADER_LEN + 9 + Provider::box_overhead());
+
+    let opened = sealed.decrypt_enveloped(&key, b"ad").unwrap();
+    assert_eq!(opened.0, b"some data");
+    assert!(sealed.decrypt_enveloped(&key, b"other ad").is_err());
+    assert!(matches!(
+        sealed.decrypt_enveloped(&key, b"longer ad"),
+        Err(Error::AuthenticationFailed)
+    ));
+    // the envelope isn't a plain box
+    assert!(sealed.decrypt(&key, b"ad").is_err());
+}
+
+#[test]
+fn test_envelope_layout() {
+    let key = Key::<Provider>::random().unwrap();
+    let sealed: Sealed = Plain(b"some data".to_vec()).encrypt_enveloped(&key, b"ad").unwrap();
+
+    // version 1 is pinned: magic, version, box id, AD length, ciphertext length
+    let header = [&b"RCEV"[..], &[1], b"xcp1", &hex("00000002"), &hex("0000000000000031")].concat();
+    assert_eq!(&sealed.0[..Envelope::HEADER_LEN], &header[..]);
+
+    let envelope = Envelope::parse(&sealed.0).unwrap();
+    assert_eq!(envelope.version, 1);
+    assert_eq!(envelope.box_id, *b"xcp1");
+    assert_eq!(envelope.ad_len, 2);
+    assert_eq!(envelope.ciphertext, &sealed.0[Envelope::HEADER_LEN..]);
+
+    // the header is authenticated with the AD
+    let ad = [&header[..13], b"ad"].concat();
+    assert_eq!(
+        Provider::box_open(&key, &ad, envelope.ciphertext).unwrap(),
+        b"some data"
+    );
+}
+
+#[cfg(feature = "test-utils")]
+#[test]
+fn test_envelope_golden() {
+    use vault::test_utils::TestProvider;
+
+    let key = Key::<TestProvider>::load(vec![7; 32]).unwrap();
+    let sealed: Sealed = Plain(b"some data".to_vec()).encrypt_enveloped(&key, b"ad").unwrap();
+    assert_eq!(
+        sealed.0,
+        hex(concat!(
+            "5243455601746573740000000200000000000000210000000000000001d247815e",
+            "39c45cf0d55d9ed9bdd184a55becd7ddc37098de6c"
+        ))
+    );
+    assert_eq!(TestProvider::open_count(), 0);
+
+    // unknown versions are rejected before the box is opened
+    let mut future = sealed.0.clone();
+    future[4] = 2;
+    assert!(matches!(
+        Sealed(future).decrypt_enveloped(&key, b"ad"),
+        Err(Error::UnsupportedVersion(2))
+    ));
+    assert_eq!(TestProvider::open_count(), 0);
+    assert_eq!(sealed.decrypt_enveloped(&key, b"ad").unwrap().0, b"some data");
+    assert_eq!(TestProvider::open_count(), 1);
+}
+
+#[test]
+fn test_envelope_rejects() {
+    let key = Key::<Provider>::random().unwrap();
+    let sealed: Sealed = Plain(b"some data".to_vec()).encrypt_enveloped(&key, b"").unwrap();
+
+    for version in [0, 2, 0xff] {
+        let mut other = sealed.0.clone();
+        other[4] = version;
+        assert!(matches!(Envelope::parse(&other), Err(Error::UnsupportedVersion(v)) if v == version));
+        assert!(matches!(
+            Sealed(other).decrypt_enveloped(&key, b""),
+            Err(Error::UnsupportedVersion(v)) if v == version
+        ));
+    }
+
+    let mut magic = sealed.0.clone();
+    magic[0] ^= 1;
+    assert!(matches!(Envelope::parse(&magic), Err(Error::CryptoError { .. })));
+
+    // truncated envelopes and trailing data
+    for len in 0..sealed.0.len() {
+        assert!(Envelope::parse(&sealed.0[..len]).is_err());
+    }
+    assert!(Envelope::parse(&[&sealed.0[..], &[0]].concat()).is_err());
+
+    // oversized length fields
+    for len in [u64::MAX, u32::MAX as u64 + 0x31, 0x32] {
+        let mut oversized = sealed.0.clone();
+        oversized[13..21].copy_from_slice(&len.to_be_bytes());
+        assert!(matches!(Envelope::parse(&oversized), Err(Error::CryptoError { .. })));
+    }
+
+    // another provider
+    let other = Key::<IetfProvider>::load_from_slice(key.bytes()).unwrap();
+    assert!(matches!(
+        sealed.decrypt_enveloped(&other, b""),
+        Err(Error::ProviderMismatch { .. })
+    ));
+}
+
+#[test]
+fn test_envelope_parse_random() {
+    let mut rng = rand::thread_rng();
+    let key = Key::<Provider>::random().unwrap();
+    let sealed: Sealed = Plain(b"some data".to_vec()).encrypt_enveloped(&key, b"").unwrap();
+
+    for _ in 0..10_000 {
+        let mut data = vec![0; rng.gen_range(0..64)];
+        rng.fill_bytes(&mut data);
+        // most random data fails at the magic bytes, so half of the inputs get a valid start
+        if rng.gen() && data.len() >= 5 {
+            data[..5].copy_from_slice(b"RCEV\x01");
+        }
+        if let Ok(envelope) = Envelope::parse(&data) {
+            assert_eq!(envelope.ciphertext.len(), data.len() - Envelope::HEADER_LEN);
+        }
+        assert!(Sealed(data).decrypt_enveloped(&key, b"").is_err());
+
+        // flipping a bit of a valid envelope never opens
+        let mut mutated = sealed.0.clone();
+        let i = rng.gen_range(0..mutated.len());
+        mutated[i] ^= 1 << rng.gen_range(0..8);
+        assert!(Sealed(mutated).decrypt_enveloped(&key, b"").is_err());
+    }
+}
@@ -0,0 +1,161 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+use std::{error::Error as StdError, fmt, io};
+
+use vault::Error;
+
+/// the error of a backend that is caused by an IO error
+#[derive(Debug)]
+struct BackendError(io::Error);
+
+impl fmt::Display for BackendError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "backend failed")
+    }
+}
+
+impl StdError for BackendError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        Some(&self.0)
+    }
+}
+
+fn device_gone() -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, "device gone")
+}
+
+#[test]
+#[allow(deprecated)]
+fn test_error_display() {
+    let cases = [
+        (Error::DatabaseError(String::from("locked")), "Database Error: `locked`"),
+        (Error::OtherError(String::from("unknown")), "Other Error: `unknown`"),
+        (Error::Base64Error, "Base64 Error: invalid length, padding or character"),
+        (
+            Error::InvalidKeyLength {
+                expected: 32,
+                actual: 16,
+            },
+            "Invalid key length: expected `32` bytes, got `16`",
+        ),
+        (
+            Error::crypto("parse blob", "Truncated blob"),
+            "Crypto Error: `parse blob` failed: `Truncated blob`",
+        ),
+        (
+            Error::crypto("seal", BackendError(device_gone())),
+            "Crypto Error: `seal` failed: `backend failed`",
+        ),
+    ];
+    for (error, message) in cases.iter() {
+        assert_eq!(error.to_string(), *message);
+    }
+}
+
+#[test]
+#[allow(deprecated)]
+fn test_error_source() {
+    let error = Error::crypto("seal", BackendError(device_gone()));
+    match &error {
+        Error::CryptoError { op, .. } => assert_eq!(*op, "seal"),
+        other => panic!("unexpected error: {:?}", other),
+    }
+
+    // the chain walks from the vault error over the backend error to its IO error
+    let backend = error.source().unwrap();
+    assert!(backend.downcast_ref::<BackendError>().is_some());
+    let io = backend.source().unwrap().downcast_ref::<io::Error>().unwrap();
+    assert_eq!(io.kind(), io::ErrorKind::NotFound);
+    assert!(backend.source().unwrap().source().is_none());
+
+    let provider = Error::ProviderError(Box::new(device_gone()));
+    assert_eq!(provider.source().unwrap().to_string(), "device gone");
+
+    // errors without a cause have no source
+    assert!(Error::DatabaseError(String::from("locked")).source().is_none());
+    assert!(Error::AuthenticationFailed.source().is_none());
+}
+
+#[test]
+fn test_error_chain_in_anyhow() {
+    fn seal() -> anyhow::Result<()> {
+        Err(Error::crypto("seal", BackendError(device_gone())))?;
+        Ok(())
+    }
+
+    let error = seal().unwrap_err();
+    let chain: Vec<_> = error.chain().map(|e| e.to_string()).collect();
+    assert_eq!(
+        chain,
+        [
+            "Crypto Error: `seal` failed: `backend failed`",
+            "backend failed",
+            "device gone"
+        ]
+    );
+    assert!(error.downcast_ref::<Error>().is_some());
+}
+
+#[test]
+fn test_error_into_io() {
+    let cases: [(fn() -> Error, io::ErrorKind); 6] = [
+        (|| Error::AuthenticationFailed, io::ErrorKind::InvalidData),
+        (
+            || Error::MalformedCiphertext {
+                expected_min: 40,
+                got: 3,
+            },
+            io::ErrorKind::InvalidData,
+        ),
+        (
+            || Error::KeychainNotFound {
+                service: String::from("vault"),
+                account: String::from("alice"),
+            },
+            io::ErrorKind::NotFound,
+        ),
+        (
+            || Error::InvalidKeyLength {
+                expected: 32,
+                actual: 16,
+            },
+            io::ErrorKind::InvalidInput,
+        ),
+        (|| Error::MemoryError(String::from("mlock")), io::ErrorKind::OutOfMemory),
+        (|| Error::crypto("seal", "failed"), io::ErrorKind::Other),
+    ];
+    for (error, kind) in cases.iter() {
+        assert_eq!(error().io_kind(), *kind);
+
+        let io = io::Error::from(error());
+        assert_eq!(io.kind(), *kind);
+        assert_eq!(io.to_string(), error().to_string());
+        assert_eq!(io.get_ref().unwrap().downcast_ref::<Error>(), Some(&error()));
+
+        // and back again
+        assert_eq!(Error::from(io), error());
+    }
+}
+
+#[test]
+fn test_error_from_io() {
+    let error = Error::from(device_gone());
+    assert_eq!(error.io_kind(), io::ErrorKind::NotFound);
+    assert_eq!(error.to_string(), "IO Error: `device gone`");
+    assert_eq!(error.source().unwrap().to_string(), "device gone");
+
+    // an `Error::Io` turns into the original error again
+    let io = io::Error::from(error);
+    assert_eq!(io.kind(), io::ErrorKind::NotFound);
+    assert_eq!(io.to_string(), "device gone");
+    assert!(io.get_ref().unwrap().downcast_ref::<Error>().is_none());
+}
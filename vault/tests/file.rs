@@ -0,0 +1,245 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+#![cfg(feature = "mmap")]
+
+mod utils;
+
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    path::PathBuf,
+    sync::Mutex,
+};
+
+use sha2::{Digest, Sha256};
+use utils::provider::Provider;
+use vault::{open_chunked, open_file, seal_file, BoxProvider, ChunkedCiphertext, Error, Key};
+
+/// a fresh directory in the temporary directory
+fn temp_dir(name: &str) -> PathBuf {
+    let mut suffix = [0; 8];
+    Provider::random_buf(&mut suffix).unwrap();
+    let suffix: String = suffix.iter().map(|b| format!("{:02x}", b)).collect();
+    let dir = std::env::temp_dir().join(format!("vault-{}-{}", name, suffix));
+    fs::create_dir(&dir).unwrap();
+    dir
+}
+
+/// `len` bytes of data which differ from chunk to chunk
+fn data(len: usize) -> Vec<u8> {
+    (0..len).map(|i| (i % 251) as u8).collect()
+}
+
+#[test]
+fn test_file_roundtrip() {
+    let dir = temp_dir("file");
+    let key = Key::<Provider>::random().unwrap();
+
+    for (len, chunks) in [(0, 1), (1, 1), (4096, 1), (4097, 2), (100_000, 25)].iter() {
+        let src = dir.join("plain");
+        let sealed = dir.join("sealed");
+        let opened = dir.join("opened");
+        fs::write(&src, data(*len)).unwrap();
+
+        let report = seal_file(&key, b"ad", &src, &sealed, 4096).unwrap();
+        assert_eq!(report.chunks, *chunks);
+        assert_eq!(report.len, fs::metadata(&sealed).unwrap().len());
+        let sealed_bytes = fs::read(&sealed).unwrap();
+        assert_eq!(report.len as usize, 4 + *len + chunks * (4 + Provider::box_overhead()));
+
+        // the sealed file is the framing of a chunked ciphertext
+        let chunked = ChunkedCiphertext::from_bytes(&sealed_bytes).unwrap();
+        assert_eq!(open_chunked(&key, b"ad", &chunked).unwrap(), data(*len));
+
+        let report = open_file(&key, b"ad", &sealed, &opened).unwrap();
+        assert_eq!(report.chunks, *chunks);
+        assert_eq!(report.len, *len as u64);
+        assert_eq!(fs::read(&opened).unwrap(), data(*len));
+    }
+
+    // only the files themselves are left behind
+    let mut names: Vec<_> = fs::read_dir(&dir).unwrap().map(|e| e.unwrap().file_name()).collect();
+    names.sort();
+    assert_eq!(names, ["opened", "plain", "sealed"]);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_file_digest() {
+    let dir = temp_dir("file-digest");
+    let key = Key::<Provider>::random().unwrap();
+    let src = dir.join("plain");
+    fs::write(&src, data(10_000)).unwrap();
+
+    let report = seal_file(&key, b"ad", &src, &dir.join("sealed"), 1000).unwrap();
+    let sealed = fs::read(dir.join("sealed")).unwrap();
+    assert_eq!(report.digest, <[u8; 32]>::from(Sha256::digest(&sealed)));
+
+    let report = open_file(&key, b"ad", &dir.join("sealed"), &dir.join("opened")).unwrap();
+    assert_eq!(report.digest, <[u8; 32]>::from(Sha256::digest(data(10_000))));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_file_truncated() {
+    let dir = temp_dir("file-truncated");
+    let key = Key::<Provider>::random().unwrap();
+    let src = dir.join("plain");
+    let sealed = dir.join("sealed");
+    let opened = dir.join("opened");
+    fs::write(&src, data(10_000)).unwrap();
+    seal_file(&key, b"ad", &src, &sealed, 1000).unwrap();
+    let sealed_bytes = fs::read(&sealed).unwrap();
+
+    // cut off within a chunk, at the end of a chunk and after the header
+    let chunk = 4 + 1000 + Provider::box_overhead();
+    for len in [sealed_bytes.len() - 1, sealed_bytes.len() - chunk, 4, 0].iter() {
+        fs::write(&sealed, &sealed_bytes[..*len]).unwrap();
+        assert!(
+            open_file(&key, b"ad", &sealed, &opened).is_err(),
+            "opened {} bytes",
+            len
+        );
+        assert!(!opened.exists());
+    }
+
+    // dropping the last chunk and fixing the count fails to authenticate
+    let mut dropped = sealed_bytes[..sealed_bytes.len() - chunk].to_vec();
+    dropped[..4].copy_from_slice(&9u32.to_be_bytes());
+    fs::write(&sealed, &dropped).unwrap();
+    assert!(matches!(
+        open_file(&key, b"ad", &sealed, &opened),
+        Err(Error::AuthenticationFailed)
+    ));
+
+    // trailing data
+    fs::write(&sealed, [&sealed_bytes[..], b"x"].concat()).unwrap();
+    assert!(open_file(&key, b"ad", &sealed, &opened).is_err());
+    assert!(!opened.exists());
+
+    // a failed open leaves an existing destination as it was
+    fs::write(&opened, b"old").unwrap();
+    assert!(open_file(&key, b"other ad", &sealed, &opened).is_err());
+    assert_eq!(fs::read(&opened).unwrap(), b"old");
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+/// the file `Tampering` appends to on its next seal
+static TAMPER: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// a provider which modifies a file while it is being sealed
+struct Tampering;
+
+impl BoxProvider for Tampering {
+    type Error = vault::Error;
+
+    fn box_key_len() -> usize {
+        Provider::box_key_len()
+    }
+
+    fn box_overhead() -> usize {
+        Provider::box_overhead()
+    }
+
+    fn box_id() -> [u8; 4] {
+        *b"tamp"
+    }
+
+    fn box_seal(key: &Key<Self>, ad: &[u8], data: &[u8]) -> vault::Result<Vec<u8>> {
+        if let Some(path) = TAMPER.lock().unwrap().take() {
+            OpenOptions::new().append(true).open(path)?.write_all(b"more")?;
+        }
+        Provider::box_seal(&Key::load(key.bytes().to_vec())?, ad, data)
+    }
+
+    fn box_open(key: &Key<Self>, ad: &[u8], data: &[u8]) -> vault::Result<Vec<u8>> {
+        Provider::box_open(&Key::load(key.bytes().to_vec())?, ad, data)
+    }
+
+    fn random_buf(buf: &mut [u8]) -> vault::Result<()> {
+        Provider::random_buf(buf)
+    }
+}
+
+#[test]
+fn test_file_source_modified() {
+    let dir = temp_dir("file-modified");
+    let key = Key::<Tampering>::random().unwrap();
+    let src = dir.join("plain");
+    let sealed = dir.join("sealed");
+    fs::write(&src, data(10_000)).unwrap();
+
+    *TAMPER.lock().unwrap() = Some(src.clone());
+    assert!(matches!(
+        seal_file(&key, b"ad", &src, &sealed, 1000),
+        Err(Error::SourceModified { path }) if path == src
+    ));
+    assert!(!sealed.exists());
+
+    // unchanged files seal
+    seal_file(&key, b"ad", &src, &sealed, 1000).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_file_other_directory() {
+    let src_dir = temp_dir("file-src");
+    let dst_dir = temp_dir("file-dst");
+    let key = Key::<Provider>::random().unwrap();
+    let src = src_dir.join("plain");
+    fs::write(&src, data(5000)).unwrap();
+
+    // the temporary file is created next to the destination and renamed there
+    seal_file(&key, b"ad", &src, &dst_dir.join("sealed"), 1000).unwrap();
+    open_file(&key, b"ad", &dst_dir.join("sealed"), &src_dir.join("opened")).unwrap();
+    assert_eq!(fs::read(src_dir.join("opened")).unwrap(), data(5000));
+    assert_eq!(fs::read_dir(&dst_dir).unwrap().count(), 1);
+
+    // a missing destination directory fails before anything is sealed
+    let missing = dst_dir.join("missing").join("sealed");
+    assert!(matches!(
+        seal_file(&key, b"ad", &src, &missing, 1000),
+        Err(Error::Io(_))
+    ));
+    assert!(matches!(
+        seal_file(&key, b"ad", &src_dir.join("missing"), &dst_dir.join("sealed"), 1000),
+        Err(Error::Io(_))
+    ));
+    assert!(matches!(
+        seal_file(&key, b"ad", &src, &dst_dir.join("sealed"), 0),
+        Err(Error::InterfaceErrorDetailed(_))
+    ));
+    fs::remove_dir_all(&src_dir).unwrap();
+    fs::remove_dir_all(&dst_dir).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn test_file_planted_symlink() {
+    let dir = temp_dir("file-symlink");
+    let key = Key::<Provider>::random().unwrap();
+    let src = dir.join("plain");
+    let victim = dir.join("victim");
+    fs::write(&src, data(5000)).unwrap();
+    fs::write(&victim, b"untouched").unwrap();
+
+    // a link at the predictable temporary name is neither followed nor replaced
+    std::os::unix::fs::symlink(&victim, dir.join("sealed.tmp")).unwrap();
+    seal_file(&key, b"ad", &src, &dir.join("sealed"), 1000).unwrap();
+    assert_eq!(fs::read(&victim).unwrap(), b"untouched");
+    assert!(fs::symlink_metadata(dir.join("sealed.tmp"))
+        .unwrap()
+        .file_type()
+        .is_symlink());
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 4);
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,362 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+mod utils;
+
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, BufRead, BufReader, Write},
+    path::PathBuf,
+    process::{Command, Stdio},
+    time::{Duration, Instant},
+};
+
+use utils::provider::Provider;
+use vault::{
+    AutoCompact, BoxProvider, ConcurrentVault, DBView, Error, FileStore, Id, Key, LockMode, RecordHint, RecordMeta,
+    SharedKey, Store,
+};
+
+/// the path of the store `test_file_store_lock_child` holds
+const CHILD_STORE: &str = "VAULT_TEST_LOCK_CHILD";
+
+/// a fresh directory in the temporary directory
+fn temp_dir(name: &str) -> PathBuf {
+    let mut suffix = [0; 8];
+    Provider::random_buf(&mut suffix).unwrap();
+    let suffix: String = suffix.iter().map(|b| format!("{:02x}", b)).collect();
+    let dir = std::env::temp_dir().join(format!("vault-{}-{}", name, suffix));
+    fs::create_dir(&dir).unwrap();
+    dir
+}
+
+fn hint() -> RecordHint {
+    RecordHint::new(b"hint").unwrap()
+}
+
+/// the data of the `n`th write of a record
+fn data(record: usize, n: usize) -> Vec<u8> {
+    vec![(record * 31 + n) as u8; 256]
+}
+
+/// writes 10 records to the store at `path` and overwrites each of them until the file is 10 times as long as its
+/// live entries, returns the ids of the records and the id of a record with metadata
+fn churn(key: &SharedKey<Provider>, owner: Id, store: FileStore) -> (FileStore, Vec<Id>, Id) {
+    let vault = ConcurrentVault::open(key.clone(), owner, store).unwrap();
+    let ids: Vec<Id> = (0..10).map(|i| vault.write(&data(i, 0), hint()).unwrap()).collect();
+    let mut store = vault.into_store();
+
+    let meta = RecordMeta::new(std::time::UNIX_EPOCH).with_label("with meta");
+    let view = DBView::load(key.key().clone(), store.list().unwrap()).unwrap();
+    let (with_meta, requests) = view.writer(owner).write_with_meta(b"meta", hint(), &meta).unwrap();
+    for request in requests {
+        store.write(request).unwrap();
+    }
+
+    let vault = ConcurrentVault::open(key.clone(), owner, store).unwrap();
+    for n in 1..=15 {
+        for (i, id) in ids.iter().enumerate() {
+            vault.overwrite(*id, &data(i, n)).unwrap();
+        }
+    }
+    let store = vault.into_store();
+    assert!(store.len() > 10 * store.live_len());
+    (store, ids, with_meta)
+}
+
+/// checks every record holds its last write and the record with metadata kept it
+fn check(key: &SharedKey<Provider>, owner: Id, store: FileStore, ids: &[Id], with_meta: Id) -> FileStore {
+    let view = DBView::load(key.key().clone(), store.list().unwrap()).unwrap();
+    let (payload, meta) = view
+        .reader()
+        .read_with_meta(with_meta, |req| store.read(req).unwrap())
+        .unwrap();
+    assert_eq!(payload, b"meta");
+    assert_eq!(meta.unwrap().label.as_deref(), Some("with meta"));
+
+    let vault = ConcurrentVault::open(key.clone(), owner, store).unwrap();
+    for (i, id) in ids.iter().enumerate() {
+        assert_eq!(vault.read(*id).unwrap(), data(i, 15));
+    }
+    vault.into_store()
+}
+
+#[test]
+fn test_file_store_compact() {
+    let dir = temp_dir("compact");
+    let path = dir.join("vault");
+    let key = SharedKey::from(Key::<Provider>::random().unwrap());
+    let owner = Id::random::<Provider>().unwrap();
+    let (mut store, ids, with_meta) = churn(&key, owner, FileStore::open(&path).unwrap());
+    let entries = store.list().unwrap().ids().len();
+    let live_len = store.live_len();
+    let before = fs::metadata(&path).unwrap().len();
+
+    let report = store.compact().unwrap();
+    assert_eq!(report.before, before);
+    assert_eq!(report.after, fs::metadata(&path).unwrap().len());
+    assert_eq!(report.after, live_len + 5);
+    assert!(report.before > 10 * report.after);
+    assert_eq!(store.list().unwrap().ids().len(), entries);
+
+    // the compacted store reads the same, before and after it's opened again
+    let store = check(&key, owner, store, &ids, with_meta);
+    drop(store);
+    let store = FileStore::open(&path).unwrap();
+    assert_eq!(store.len(), report.after);
+    let mut store = check(&key, owner, store, &ids, with_meta);
+    assert_eq!(store.list().unwrap().ids().len(), entries);
+
+    // compacting a compact store changes nothing
+    assert_eq!(store.compact().unwrap().after, report.after);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_file_store_auto_compact() {
+    let dir = temp_dir("auto-compact");
+    let path = dir.join("vault");
+    let key = SharedKey::from(Key::<Provider>::random().unwrap());
+    let owner = Id::random::<Provider>().unwrap();
+    let mut store = FileStore::open(&path).unwrap();
+    store.auto_compact(Some(AutoCompact {
+        min_len: 16 << 10,
+        max_ratio: 4,
+    }));
+
+    let vault = ConcurrentVault::open(key.clone(), owner, store).unwrap();
+    let ids: Vec<Id> = (0..10).map(|i| vault.write(&data(i, 0), hint()).unwrap()).collect();
+    for n in 1..=15 {
+        for (i, id) in ids.iter().enumerate() {
+            vault.overwrite(*id, &data(i, n)).unwrap();
+        }
+    }
+    for (i, id) in ids.iter().enumerate() {
+        assert_eq!(vault.read(*id).unwrap(), data(i, 15));
+    }
+    let store = vault.into_store();
+    assert!(store.len() <= (16 << 10).max(4 * store.live_len() + 5));
+    assert_eq!(fs::metadata(&path).unwrap().len(), store.len());
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_file_store_torn_frame() {
+    let dir = temp_dir("torn");
+    let path = dir.join("vault");
+    let key = SharedKey::from(Key::<Provider>::random().unwrap());
+    let owner = Id::random::<Provider>().unwrap();
+    let vault = ConcurrentVault::open(key.clone(), owner, FileStore::open(&path).unwrap()).unwrap();
+    let id = vault.write(b"kept", hint()).unwrap();
+    let len = vault.into_store().len();
+
+    // a write torn by a crash is dropped
+    let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+    file.write_all(&[1, 0, 0, 0, 24, 0, 0, 1, 0, 7, 7]).unwrap();
+    drop(file);
+    let store = FileStore::open(&path).unwrap();
+    assert_eq!(store.len(), len);
+    assert_eq!(fs::metadata(&path).unwrap().len(), len);
+    let vault = ConcurrentVault::open(key, owner, store).unwrap();
+    assert_eq!(vault.read(id).unwrap(), b"kept");
+    drop(vault);
+
+    // other files aren't stores
+    fs::write(&path, b"not a store").unwrap();
+    assert!(matches!(FileStore::open(&path), Err(Error::CorruptStore(_))));
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_file_store_lock() {
+    let dir = temp_dir("lock");
+    let path = dir.join("vault");
+    let key = SharedKey::from(Key::<Provider>::random().unwrap());
+    let owner = Id::random::<Provider>().unwrap();
+    let vault = ConcurrentVault::open(key.clone(), owner, FileStore::open(&path).unwrap()).unwrap();
+    let id = vault.write(b"data", hint()).unwrap();
+
+    // a second exclusive open fails, with the id of the holder where it can be read
+    let holder_pid = if cfg!(unix) { Some(std::process::id()) } else { None };
+    assert_eq!(FileStore::open(&path).err().unwrap(), Error::VaultLocked { holder_pid });
+    assert!(matches!(
+        FileStore::open_with(&path, LockMode::Shared),
+        Err(Error::VaultLocked { .. })
+    ));
+    // the lock survives a compaction, which replaces the file
+    let mut store = vault.into_store();
+    store.compact().unwrap();
+    assert!(matches!(FileStore::open(&path), Err(Error::VaultLocked { .. })));
+    drop(store);
+
+    // many readers, no writer
+    let readers: Vec<FileStore> = (0..2)
+        .map(|_| FileStore::open_with(&path, LockMode::Shared).unwrap())
+        .collect();
+    assert_eq!(
+        FileStore::open(&path).err().unwrap(),
+        Error::VaultLocked { holder_pid: None }
+    );
+    let view = DBView::load(key.key().clone(), readers[0].list().unwrap()).unwrap();
+    let res = readers[1]
+        .read(view.reader().prepare_read(id).unwrap())
+        .unwrap()
+        .unwrap();
+    assert_eq!(view.reader().read(res).unwrap(), b"data");
+    let mut reader = readers.into_iter().next().unwrap();
+    assert_eq!(reader.lock_mode(), LockMode::Shared);
+    match reader.compact() {
+        Err(Error::Io(e)) => assert_eq!(e.kind(), io::ErrorKind::PermissionDenied),
+        other => panic!("compacted a shared store: {:?}", other.map(|_| ())),
+    }
+    drop(reader);
+
+    // released by the drop of the last reader
+    let store = FileStore::open(&path).unwrap();
+    assert_eq!(store.lock_mode(), LockMode::Exclusive);
+    drop(store);
+    // readers don't create a store
+    assert!(matches!(
+        FileStore::open_with(dir.join("missing"), LockMode::Shared),
+        Err(Error::Io(e)) if e.kind() == io::ErrorKind::NotFound
+    ));
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_file_store_lock_timeout() {
+    let dir = temp_dir("lock-timeout");
+    let path = dir.join("vault");
+    let store = FileStore::open(&path).unwrap();
+
+    let start = Instant::now();
+    assert!(matches!(
+        FileStore::try_open_timeout(&path, LockMode::Exclusive, Duration::from_millis(50)),
+        Err(Error::VaultLocked { .. })
+    ));
+    assert!(start.elapsed() >= Duration::from_millis(50));
+
+    // the holder releases the lock while the other one waits
+    let holder = std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(50));
+        drop(store);
+    });
+    FileStore::try_open_timeout(&path, LockMode::Shared, Duration::from_secs(10)).unwrap();
+    holder.join().unwrap();
+
+    // a panic releases the lock too
+    let held = path.clone();
+    let panicked = std::thread::spawn(move || {
+        let _store = FileStore::open(&held).unwrap();
+        panic!("holding the lock");
+    });
+    assert!(panicked.join().is_err());
+    FileStore::open(&path).unwrap();
+    fs::remove_dir_all(dir).unwrap();
+}
+
+/// holds the store at `CHILD_STORE` for `test_file_store_lock_process` until it is killed, only runs as its child
+#[test]
+fn test_file_store_lock_child() {
+    let path = match std::env::var_os(CHILD_STORE) {
+        Some(path) => PathBuf::from(path),
+        None => return,
+    };
+    let _store = FileStore::open(path).unwrap();
+    println!("locked");
+    io::stdout().flush().unwrap();
+    io::stdin().read_line(&mut String::new()).unwrap();
+}
+
+#[test]
+fn test_file_store_lock_process() {
+    let dir = temp_dir("lock-process");
+    let path = dir.join("vault");
+    // the lock file left by a holder which died doesn't keep others out
+    fs::write(dir.join("vault.lock"), b"4000000000").unwrap();
+    drop(FileStore::open(&path).unwrap());
+
+    let mut child = Command::new(std::env::current_exe().unwrap())
+        .args(["test_file_store_lock_child", "--exact", "--nocapture"])
+        .env(CHILD_STORE, &path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let stdout = BufReader::new(child.stdout.take().unwrap());
+    // libtest may print the name of the test on the same line
+    assert!(stdout.lines().any(|line| line.unwrap().ends_with("locked")));
+
+    let holder_pid = if cfg!(unix) { Some(child.id()) } else { None };
+    assert_eq!(FileStore::open(&path).err().unwrap(), Error::VaultLocked { holder_pid });
+    assert!(matches!(
+        FileStore::try_open_timeout(&path, LockMode::Shared, Duration::from_millis(20)),
+        Err(Error::VaultLocked { .. })
+    ));
+
+    // the holder dies without releasing the lock, the OS does
+    child.kill().unwrap();
+    child.wait().unwrap();
+    let store = FileStore::try_open_timeout(&path, LockMode::Exclusive, Duration::from_secs(10)).unwrap();
+    if cfg!(unix) {
+        assert_eq!(
+            fs::read_to_string(dir.join("vault.lock")).unwrap(),
+            std::process::id().to_string()
+        );
+    }
+    drop(store);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[cfg(feature = "test-utils")]
+mod crash {
+    use super::*;
+    use vault::CompactStep;
+
+    #[test]
+    fn test_file_store_crash_during_compaction() {
+        let dir = temp_dir("crash");
+        let path = dir.join("vault");
+        let tmp = dir.join("vault.compact");
+        let key = SharedKey::from(Key::<Provider>::random().unwrap());
+        let owner = Id::random::<Provider>().unwrap();
+        let (mut store, ids, with_meta) = churn(&key, owner, FileStore::open(&path).unwrap());
+        let before = store.len();
+
+        // killed before the swap, the compacted file is left next to the intact store
+        store.crash_before(CompactStep::Swap);
+        assert!(matches!(store.compact(), Err(Error::InterfaceErrorDetailed(_))));
+        drop(store);
+        assert!(tmp.exists());
+        let store = FileStore::open(&path).unwrap();
+        assert!(!tmp.exists());
+        assert_eq!(store.len(), before);
+        let store = check(&key, owner, store, &ids, with_meta);
+        drop(store);
+
+        // killed while writing the compacted file, which is torn
+        let compacted = fs::read(&path).unwrap();
+        fs::write(&tmp, &compacted[..compacted.len() / 3]).unwrap();
+        let store = FileStore::open(&path).unwrap();
+        assert!(!tmp.exists());
+        assert_eq!(store.len(), before);
+        let mut store = check(&key, owner, store, &ids, with_meta);
+
+        // killed after the swap, the compacted file is the store and the old one isn't wiped
+        store.crash_before(CompactStep::Wipe);
+        assert!(matches!(store.compact(), Err(Error::InterfaceErrorDetailed(_))));
+        drop(store);
+        let store = FileStore::open(&path).unwrap();
+        assert!(store.len() < before / 10);
+        check(&key, owner, store, &ids, with_meta);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,62 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+#![cfg(feature = "guarded-memory")]
+
+mod utils;
+
+use std::sync::{Arc, Mutex};
+
+use utils::provider::Provider;
+use vault::{BoxProvider, Key};
+
+#[test]
+fn test_guarded_key_allocation() {
+    let key = Key::<Provider>::random_guarded().unwrap();
+    assert!(key.is_guarded());
+    assert_eq!(key.bytes().len(), Provider::box_key_len());
+    assert!(key.bytes().iter().any(|b| *b != 0));
+
+    let clone = key.clone();
+    assert!(clone.is_guarded());
+    assert_eq!(clone, key);
+    assert_ne!(clone.bytes().as_ptr(), key.bytes().as_ptr());
+
+    let clone = key.try_clone().unwrap();
+    assert!(clone.is_guarded());
+    assert_eq!(clone, key);
+    assert!(!Key::<Provider>::random().unwrap().try_clone().unwrap().is_guarded());
+
+    assert!(!Key::<Provider>::random().unwrap().is_guarded());
+}
+
+#[test]
+fn test_guarded_key_box_seal() {
+    let key = Key::<Provider>::random_guarded().unwrap();
+    let heap = Key::<Provider>::load(key.bytes().to_vec()).unwrap();
+
+    let sealed = Provider::box_seal(&key, b"ad", b"guarded data").unwrap();
+    assert_eq!(Provider::box_open(&heap, b"ad", &sealed).unwrap(), b"guarded data");
+    assert_eq!(Provider::box_open(&key, b"ad", &sealed).unwrap(), b"guarded data");
+}
+
+#[test]
+fn test_guarded_key_drop() {
+    let seen = Arc::new(Mutex::new(Vec::new()));
+
+    let mut key = Key::<Provider>::random_guarded().unwrap();
+    let bytes = key.bytes().to_vec();
+    let sink = seen.clone();
+    key.on_drop(move |bytes| sink.lock().unwrap().extend_from_slice(bytes));
+
+    drop(key);
+    assert_eq!(*seen.lock().unwrap(), bytes);
+}
@@ -0,0 +1,151 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+mod utils;
+
+use std::convert::Infallible;
+
+use utils::{alloc::WatchingAllocator, provider::Provider};
+use vault::{BoxProvider, Decrypt, Encrypt, Key};
+
+#[global_allocator]
+static ALLOC: WatchingAllocator = WatchingAllocator;
+
+struct Plain(Vec<u8>);
+struct Sealed(Vec<u8>);
+
+impl AsRef<[u8]> for Plain {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl AsRef<[u8]> for Sealed {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl From<Vec<u8>> for Sealed {
+    fn from(data: Vec<u8>) -> Self {
+        Self(data)
+    }
+}
+
+impl From<Sealed> for Vec<u8> {
+    fn from(sealed: Sealed) -> Self {
+        sealed.0
+    }
+}
+
+impl From<Vec<u8>> for Plain {
+    fn from(data: Vec<u8>) -> Self {
+        Self(data)
+    }
+}
+
+impl Encrypt<Sealed> for Plain {}
+impl Decrypt<Infallible, Plain> for Sealed {}
+
+/// seal and open `data` in place and check the number of allocations of both steps
+fn roundtrip_in_place<P: BoxProvider>(data: &[u8]) -> (usize, usize) {
+    let key = Key::<P>::random().unwrap();
+    let mut buf = Vec::with_capacity(data.len() + P::box_overhead());
+    buf.extend_from_slice(data);
+
+    let before = WatchingAllocator::allocations();
+    Plain::encrypt_in_place(&key, b"ad", &mut buf).unwrap();
+    let sealing = WatchingAllocator::allocations() - before;
+    assert_eq!(buf.len(), data.len() + P::box_overhead());
+
+    // the box can be opened the usual way
+    let opened: Plain = Sealed(buf.clone()).decrypt(&key, b"ad").unwrap();
+    assert_eq!(opened.0, data);
+
+    let before = WatchingAllocator::allocations();
+    Sealed::decrypt_in_place(&key, b"ad", &mut buf).unwrap();
+    let opening = WatchingAllocator::allocations() - before;
+    assert_eq!(buf, data);
+
+    // boxes sealed the usual way can be opened in place
+    let mut sealed = Plain(data.to_vec()).encrypt(&key, b"ad").unwrap().0;
+    Sealed::decrypt_in_place(&key, b"ad", &mut sealed).unwrap();
+    assert_eq!(sealed, data);
+
+    let mut sealed = Plain(data.to_vec()).encrypt(&key, b"ad").unwrap().0;
+    assert!(Sealed::decrypt_in_place(&key, b"other ad", &mut sealed).is_err());
+
+    (sealing, opening)
+}
+
+/// open `data` sealed the usual way with `decrypt_owned` and with `decrypt` and return the allocations of both
+fn roundtrip_owned<P: BoxProvider>(data: &[u8]) -> (usize, usize) {
+    let key = Key::<P>::random().unwrap();
+    let sealed: Sealed = Plain(data.to_vec()).encrypt(&key, b"ad").unwrap();
+    let copy = Sealed(sealed.0.clone());
+    let ptr = sealed.0.as_ptr();
+
+    let before = WatchingAllocator::allocations();
+    let opened: Plain = copy.decrypt(&key, b"ad").unwrap();
+    let copying = WatchingAllocator::allocations() - before;
+
+    let before = WatchingAllocator::allocations();
+    let owned: Plain = sealed.decrypt_owned(&key, b"ad").unwrap();
+    let owning = WatchingAllocator::allocations() - before;
+    assert_eq!(owned.0, opened.0);
+    assert_eq!(owned.0, data);
+    // the plaintext is in the allocation of the ciphertext
+    assert_eq!(owned.0.as_ptr(), ptr);
+
+    let sealed: Sealed = Plain(data.to_vec()).encrypt(&key, b"ad").unwrap();
+    assert!(sealed.decrypt_owned(&key, b"other ad").is_err());
+
+    (copying, owning)
+}
+
+#[test]
+fn test_in_place_fallback() {
+    roundtrip_in_place::<Provider>(b"");
+    roundtrip_in_place::<Provider>(b"some data");
+}
+
+#[test]
+fn test_owned_fallback() {
+    roundtrip_owned::<Provider>(b"");
+    let (copying, owning) = roundtrip_owned::<Provider>(b"some data");
+    assert!(owning <= copying);
+}
+
+#[cfg(feature = "provider-xchacha")]
+#[test]
+fn test_in_place_xchacha_allocations() {
+    use vault::providers::XChaChaPoly;
+
+    assert_eq!(roundtrip_in_place::<XChaChaPoly>(b""), (0, 0));
+    assert_eq!(roundtrip_in_place::<XChaChaPoly>(&[7; 1000]), (0, 0));
+
+    let (copying, owning) = roundtrip_owned::<XChaChaPoly>(&[7; 1000]);
+    assert_eq!(owning, 0);
+    assert!(copying > 0);
+}
+
+#[cfg(feature = "provider-aes-gcm")]
+#[test]
+fn test_in_place_aes_gcm_allocations() {
+    use vault::providers::AesGcm256;
+
+    assert_eq!(roundtrip_in_place::<AesGcm256>(b""), (0, 0));
+    assert_eq!(roundtrip_in_place::<AesGcm256>(&[7; 1000]), (0, 0));
+
+    let (copying, owning) = roundtrip_owned::<AesGcm256>(&[7; 1000]);
+    assert_eq!(owning, 0);
+    assert!(copying > 0);
+}
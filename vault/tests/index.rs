@@ -0,0 +1,220 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+mod utils;
+
+use utils::{provider::Provider, test_vault::TestVault};
+use vault::{BlindIndex, DBView, DBWriter, DeleteRequest, Error, Id, Key, ReadResult, RecordHint, WriteRequest};
+
+fn apply(vault: &mut TestVault, to_write: Vec<WriteRequest>, to_delete: Vec<DeleteRequest>) {
+    for request in to_write {
+        let (id, data) = request.into();
+        vault.records.insert(id, data);
+    }
+    for request in to_delete {
+        let id: Vec<u8> = request.into();
+        vault.records.remove(&id);
+    }
+}
+
+/// a vault with a chain of `owner`
+fn vault_with_chain(owner: Id) -> TestVault {
+    let key = Key::<Provider>::random().unwrap();
+    let mut vault = TestVault::empty(key.clone());
+    let (id, data) = DBWriter::create_chain(&key, owner).into();
+    vault.records.insert(id, data);
+    vault
+}
+
+/// writes a record of `owner` with the data `email` indexed by the field `email`
+fn write(vault: &mut TestVault, index: &BlindIndex<Provider>, owner: Id, email: &[u8]) -> Id {
+    let view = DBView::load(vault.key().clone(), vault.list()).unwrap();
+    let (id, requests) = view
+        .writer(owner)
+        .write_indexed(email, RecordHint::new(b"user").unwrap(), index, &[("email", email)])
+        .unwrap();
+    apply(vault, requests, Vec::new());
+    id
+}
+
+fn read(vault: &TestVault, id: Id) -> Vec<u8> {
+    let view = DBView::load(vault.key().clone(), vault.list()).unwrap();
+    let reader = view.reader();
+    reader
+        .read(vault.read(reader.prepare_read(id).unwrap()).unwrap())
+        .unwrap()
+}
+
+fn entries(vault: &TestVault) -> Vec<ReadResult> {
+    vault
+        .records
+        .iter()
+        .map(|(id, data)| ReadResult::new(id.clone(), data.clone()))
+        .collect()
+}
+
+#[test]
+fn test_index_lookup() {
+    let owner = Id::random::<Provider>().unwrap();
+    let mut vault = vault_with_chain(owner);
+    let index = BlindIndex::new(vault.key(), 16).unwrap();
+    let alice = write(&mut vault, &index, owner, b"alice@example.com");
+    let bob = write(&mut vault, &index, owner, b"bob@example.com");
+
+    assert_eq!(
+        index.find_by_hint("email", b"alice@example.com", &vault.list()),
+        [alice]
+    );
+    assert_eq!(index.find_by_hint("email", b"bob@example.com", &vault.list()), [bob]);
+    assert!(index
+        .find_by_hint("email", b"carol@example.com", &vault.list())
+        .is_empty());
+    // the field is part of the HMAC
+    assert!(index
+        .find_by_hint("name", b"alice@example.com", &vault.list())
+        .is_empty());
+
+    // the index entries hold neither the field nor the value in the clear
+    let leaks = |needle: &[u8]| {
+        vault.records.iter().any(|(id, data)| {
+            [id, data]
+                .iter()
+                .any(|bytes| bytes.windows(needle.len()).any(|w| w == needle))
+        })
+    };
+    assert!(!leaks(b"alice@example.com"));
+    assert!(!leaks(b"email"));
+
+    // an index of another key or another truncation finds nothing
+    let other = BlindIndex::new(&Key::<Provider>::random().unwrap(), 16).unwrap();
+    assert!(other
+        .find_by_hint("email", b"alice@example.com", &vault.list())
+        .is_empty());
+    let shorter = BlindIndex::new(vault.key(), 8).unwrap();
+    assert!(shorter
+        .find_by_hint("email", b"alice@example.com", &vault.list())
+        .is_empty());
+    let view = DBView::load(vault.key().clone(), vault.list()).unwrap();
+    assert!(matches!(
+        view.writer(owner)
+            .write_indexed(b"x", RecordHint::new(b"user").unwrap(), &other, &[]),
+        Err(Error::InterfaceErrorDetailed(_))
+    ));
+
+    for truncation in [0, BlindIndex::<Provider>::MAX_TRUNCATION + 1].iter() {
+        assert!(matches!(
+            BlindIndex::new(vault.key(), *truncation),
+            Err(Error::InterfaceErrorDetailed(_))
+        ));
+    }
+}
+
+#[test]
+fn test_index_collisions() {
+    let owner = Id::random::<Provider>().unwrap();
+    let mut vault = vault_with_chain(owner);
+    // a single byte of HMAC collides among a hundred values
+    let index = BlindIndex::new(vault.key(), 1).unwrap();
+    let emails: Vec<Vec<u8>> = (0..100)
+        .map(|i| format!("user{}@example.com", i).into_bytes())
+        .collect();
+    let ids: Vec<Id> = emails
+        .iter()
+        .map(|email| write(&mut vault, &index, owner, email))
+        .collect();
+
+    let mut collided = false;
+    for (email, id) in emails.iter().zip(ids.iter()) {
+        let candidates = index.find_by_hint("email", email, &vault.list());
+        assert!(candidates.contains(id));
+        collided |= candidates.len() > 1;
+
+        // verifying after decrypting leaves the record
+        let verified: Vec<Id> = candidates
+            .into_iter()
+            .filter(|candidate| read(&vault, *candidate) == *email)
+            .collect();
+        assert_eq!(verified, [*id]);
+    }
+    assert!(collided);
+}
+
+#[test]
+fn test_index_rekey() {
+    let owner = Id::random::<Provider>().unwrap();
+    let mut vault = vault_with_chain(owner);
+    let old_key = vault.key().clone();
+    let index = BlindIndex::new(&old_key, 12).unwrap();
+    let alice = write(&mut vault, &index, owner, b"alice@example.com");
+    let bob = write(&mut vault, &index, owner, b"bob@example.com");
+
+    // an index entry of another key is left alone
+    let foreign_key = Key::<Provider>::random().unwrap();
+    let foreign = BlindIndex::new(&foreign_key, 12).unwrap();
+    let foreign_id = Id::random::<Provider>().unwrap();
+    apply(
+        &mut vault,
+        foreign.index(foreign_id, &[("email", b"eve@example.com")]).unwrap(),
+        Vec::new(),
+    );
+
+    let new_key = Key::<Provider>::random().unwrap();
+    let view = DBView::load(old_key.clone(), vault.list()).unwrap();
+    let (_, mut to_write, mut to_delete) = view
+        .rekey(&old_key, &new_key, &vault.list(), |req| vault.read(req))
+        .unwrap();
+    let (rekeyed, index_writes, index_deletes) = index.rekey(&new_key, &entries(&vault)).unwrap();
+    assert_eq!((index_writes.len(), index_deletes.len()), (2, 2));
+    to_write.extend(index_writes);
+    to_delete.extend(index_deletes);
+    apply(&mut vault, to_write, to_delete);
+    vault.key = new_key.clone();
+
+    assert_eq!(rekeyed.truncation(), 12);
+    assert_eq!(
+        rekeyed.find_by_hint("email", b"alice@example.com", &vault.list()),
+        [alice]
+    );
+    assert_eq!(rekeyed.find_by_hint("email", b"bob@example.com", &vault.list()), [bob]);
+    assert_eq!(read(&vault, alice), b"alice@example.com");
+    assert!(index
+        .find_by_hint("email", b"alice@example.com", &vault.list())
+        .is_empty());
+    assert_eq!(
+        foreign.find_by_hint("email", b"eve@example.com", &vault.list()),
+        [foreign_id]
+    );
+    assert!(matches!(
+        rekeyed.rekey(&new_key, &entries(&vault)),
+        Err(Error::RekeyError(_))
+    ));
+
+    // the entries of a removed or revoked record are gone
+    let view = DBView::load(new_key.clone(), vault.list()).unwrap();
+    let to_delete = rekeyed.remove(bob, &vault.list());
+    assert_eq!(to_delete.len(), 1);
+    apply(&mut vault, Vec::new(), to_delete);
+    assert!(rekeyed
+        .find_by_hint("email", b"bob@example.com", &vault.list())
+        .is_empty());
+    let (to_write, to_delete) = view.writer(owner).revoke(alice).unwrap();
+    apply(&mut vault, vec![to_write], vec![to_delete]);
+    let (report, to_write, to_delete) = DBView::gc(&new_key, &entries(&vault), &[]).unwrap();
+    assert!(report.removed_blobs > 0);
+    apply(&mut vault, to_write, to_delete);
+    assert!(rekeyed
+        .find_by_hint("email", b"alice@example.com", &vault.list())
+        .is_empty());
+    assert_eq!(
+        foreign.find_by_hint("email", b"eve@example.com", &vault.list()),
+        [foreign_id]
+    );
+}
@@ -0,0 +1,141 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+mod utils;
+
+use std::{cell::Cell, convert::Infallible};
+
+use utils::provider::Provider;
+use vault::{BoxProvider, BoxProviderInstance, Decrypt, Encrypt, Error, Key};
+
+struct Plain(Vec<u8>);
+struct Sealed(Vec<u8>);
+
+impl AsRef<[u8]> for Plain {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl AsRef<[u8]> for Sealed {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl From<Vec<u8>> for Plain {
+    fn from(data: Vec<u8>) -> Self {
+        Self(data)
+    }
+}
+
+impl From<Vec<u8>> for Sealed {
+    fn from(data: Vec<u8>) -> Self {
+        Self(data)
+    }
+}
+
+impl Encrypt<Sealed> for Plain {}
+impl Decrypt<Infallible, Plain> for Sealed {}
+
+/// a stateful mock of an HSM session, which counts the calls and expires after `lifetime` of them
+struct Session {
+    calls: Cell<usize>,
+    lifetime: usize,
+}
+
+impl Session {
+    fn new(lifetime: usize) -> Self {
+        Self {
+            calls: Cell::new(0),
+            lifetime,
+        }
+    }
+
+    fn call(&self) -> vault::Result<()> {
+        self.calls.set(self.calls.get() + 1);
+        match self.calls.get() > self.lifetime {
+            true => Err(Error::crypto("open", "Session expired")),
+            false => Ok(()),
+        }
+    }
+}
+
+impl BoxProviderInstance for Session {
+    type Marker = Provider;
+    type Error = Error;
+
+    fn box_overhead(&self) -> usize {
+        <Provider as BoxProvider>::box_overhead()
+    }
+
+    fn box_seal(&self, key: &Key<Provider>, ad: &[u8], data: &[u8]) -> vault::Result<Vec<u8>> {
+        self.call()?;
+        <Provider as BoxProvider>::box_seal(key, ad, data)
+    }
+
+    fn box_open(&self, key: &Key<Provider>, ad: &[u8], data: &[u8]) -> vault::Result<Vec<u8>> {
+        self.call()?;
+        <Provider as BoxProvider>::box_open(key, ad, data)
+    }
+}
+
+#[test]
+fn test_instance_roundtrip() {
+    let session = Session::new(10);
+    let key = Key::<Provider>::random().unwrap();
+
+    let sealed: Sealed = Plain(b"some data".to_vec())
+        .encrypt_with(&session, &key, b"ad")
+        .unwrap();
+    assert_eq!(sealed.0.len(), 9 + session.box_overhead());
+    let opened: Plain = sealed.decrypt_with(&session, &key, b"ad").unwrap();
+    assert_eq!(opened.0, b"some data");
+    assert_eq!(session.calls.get(), 2);
+
+    // boxes of the instance are boxes of the marker
+    let opened: Plain = sealed.decrypt(&key, b"ad").unwrap();
+    assert_eq!(opened.0, b"some data");
+    assert_eq!(session.calls.get(), 2);
+}
+
+#[test]
+fn test_instance_session_expiry() {
+    let session = Session::new(1);
+    let key = Key::<Provider>::random().unwrap();
+
+    let sealed: Sealed = Plain(b"some data".to_vec()).encrypt_with(&session, &key, b"").unwrap();
+    let opened: vault::Result<Plain> = sealed.decrypt_with(&session, &key, b"");
+    assert!(matches!(opened, Err(Error::CryptoError { .. })));
+
+    // a new session of the same backend opens the box
+    let opened: Plain = sealed.decrypt_with(&Session::new(1), &key, b"").unwrap();
+    assert_eq!(opened.0, b"some data");
+}
+
+#[test]
+fn test_instance_adapter() {
+    let key = Key::<Provider>::random().unwrap();
+
+    let sealed: Sealed = Plain(b"some data".to_vec())
+        .encrypt_with(&Provider, &key, b"ad")
+        .unwrap();
+    let opened: Plain = sealed.decrypt(&key, b"ad").unwrap();
+    assert_eq!(opened.0, b"some data");
+
+    let sealed: Sealed = Plain(b"some data".to_vec()).encrypt(&key, b"ad").unwrap();
+    assert_eq!(
+        BoxProviderInstance::box_overhead(&Provider),
+        <Provider as BoxProvider>::box_overhead()
+    );
+    let opened: Plain = sealed.decrypt_with(&Provider, &key, b"ad").unwrap();
+    assert_eq!(opened.0, b"some data");
+}
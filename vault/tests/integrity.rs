@@ -0,0 +1,210 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+mod utils;
+
+use rand::{seq::SliceRandom, Rng};
+use utils::{provider::Provider, test_vault::TestVault};
+use vault::{
+    DBView, DBWriter, DeleteRequest, Error, Id, IntegrityTree, Key, ReadResult, Record, RecordHint, WriteRequest,
+};
+
+fn entries(vault: &TestVault) -> Vec<ReadResult> {
+    vault
+        .records
+        .iter()
+        .map(|(id, data)| ReadResult::new(id.clone(), data.clone()))
+        .collect()
+}
+
+/// applies the requests to the vault and the tree
+fn apply(vault: &mut TestVault, tree: &mut IntegrityTree, to_write: Vec<WriteRequest>, to_delete: Vec<DeleteRequest>) {
+    for request in to_write {
+        tree.write(&request);
+        let (id, data) = request.into();
+        vault.records.insert(id, data);
+    }
+    for request in to_delete {
+        tree.delete(&request);
+        let id: Vec<u8> = request.into();
+        vault.records.remove(&id);
+    }
+}
+
+/// the record of the data transaction of `id` in the vault
+fn record(vault: &TestVault, key: &Key<Provider>, id: Id) -> Record {
+    vault
+        .records
+        .keys()
+        .filter_map(|entry| Record::open(key, entry))
+        // the init transaction is the only one with the counter 0 and a valid record has no revocation
+        .find(|record| record.ctr().u64() > 0 && record.force_uid() == id)
+        .unwrap()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[test]
+fn test_integrity_incremental() {
+    let key = Key::<Provider>::random().unwrap();
+    let owner = Id::random::<Provider>().unwrap();
+    let mut vault = TestVault::empty(key.clone());
+    let mut tree = IntegrityTree::new();
+    apply(
+        &mut vault,
+        &mut tree,
+        vec![DBWriter::create_chain(&key, owner)],
+        Vec::new(),
+    );
+
+    let mut rng = rand::thread_rng();
+    let mut ids: Vec<Id> = Vec::new();
+    for i in 0..100 {
+        let view = DBView::load(key.clone(), vault.list()).unwrap();
+        let (to_write, to_delete) = match rng.gen_range(0..4) {
+            // overwrite the payload of a record with the same data
+            0 if !ids.is_empty() => {
+                let id = ids[rng.gen_range(0..ids.len())];
+                let reader = view.reader();
+                let data = reader
+                    .read(vault.read(reader.prepare_read(id).unwrap()).unwrap())
+                    .unwrap();
+                let root = tree.root();
+                let requests = record(&vault, &key, id).write_payload(&key, &data).unwrap();
+                apply(&mut vault, &mut tree, requests, Vec::new());
+                assert_ne!(tree.root(), root);
+                (Vec::new(), Vec::new())
+            }
+            1 if !ids.is_empty() => {
+                let id = ids.swap_remove(rng.gen_range(0..ids.len()));
+                let (to_write, to_delete) = view.writer(owner).revoke(id).unwrap();
+                (vec![to_write], vec![to_delete])
+            }
+            _ => {
+                let data = format!("record {}", i);
+                let (id, to_write) = view
+                    .writer(owner)
+                    .write(data.as_bytes(), RecordHint::new(b"hint").unwrap())
+                    .unwrap();
+                ids.push(id);
+                (to_write, Vec::new())
+            }
+        };
+        apply(&mut vault, &mut tree, to_write, to_delete);
+        assert_eq!(
+            tree.root(),
+            IntegrityTree::from_entries(&entries(&vault)).root(),
+            "step {}",
+            i
+        );
+    }
+    assert_eq!(tree.len(), vault.records.len());
+
+    // garbage collection deletes and rewrites most of the store
+    let view = DBView::load(key.clone(), vault.list()).unwrap();
+    let (to_write, to_delete) = view.writer(owner).gc().unwrap();
+    apply(&mut vault, &mut tree, to_write, to_delete);
+    assert_eq!(tree.root(), IntegrityTree::from_entries(&entries(&vault)).root());
+
+    // deleting an entry twice is the same as deleting it once
+    let (_, to_delete) = DBView::load(key, vault.list())
+        .unwrap()
+        .writer(owner)
+        .revoke(ids[0])
+        .unwrap();
+    tree.delete(&to_delete);
+    let root = tree.root();
+    tree.delete(&to_delete);
+    assert_eq!(tree.root(), root);
+    vault.records.remove(to_delete.id());
+    assert_eq!(tree.root(), IntegrityTree::from_entries(&entries(&vault)).root());
+}
+
+#[test]
+fn test_integrity_canonical() {
+    let entries: Vec<ReadResult> = (0..50u8)
+        .map(|i| ReadResult::new(vec![i; 24], vec![i; usize::from(i)]))
+        .collect();
+    let root = IntegrityTree::from_entries(&entries).root();
+
+    // the root doesn't depend on the order of the entries, nor on the process computing it
+    let mut shuffled = entries.clone();
+    shuffled.shuffle(&mut rand::thread_rng());
+    assert_eq!(IntegrityTree::from_entries(&shuffled).root(), root);
+    assert_eq!(
+        hex(&root),
+        "9bb13741061f56a97b95de3e03e5a1fbb2e9472c38363fa4e40bc0282b085a9b"
+    );
+    assert_eq!(
+        hex(&IntegrityTree::new().root()),
+        hex(&IntegrityTree::from_entries(&[]).root())
+    );
+
+    // an id and data moved between entries changes the root
+    let mut moved = entries.clone();
+    moved[0] = ReadResult::new(vec![0; 23], vec![0; 1]);
+    assert_ne!(IntegrityTree::from_entries(&moved).root(), root);
+}
+
+#[test]
+fn test_integrity_verify() {
+    let key = Key::<Provider>::random().unwrap();
+    let owner = Id::random::<Provider>().unwrap();
+    let mut vault = TestVault::empty(key.clone());
+    let mut tree = IntegrityTree::new();
+    apply(
+        &mut vault,
+        &mut tree,
+        vec![DBWriter::create_chain(&key, owner)],
+        Vec::new(),
+    );
+    let mut ids = Vec::new();
+    for i in 0..10 {
+        let view = DBView::load(key.clone(), vault.list()).unwrap();
+        let (id, to_write) = view
+            .writer(owner)
+            .write(format!("record {}", i).as_bytes(), RecordHint::new(b"hint").unwrap())
+            .unwrap();
+        ids.push(id);
+        apply(&mut vault, &mut tree, to_write, Vec::new());
+    }
+    let root = tree.root();
+    tree.verify(root, &entries(&vault)).unwrap();
+
+    // a modified, an added and a removed entry
+    let modified = ids[0].as_ref().to_vec();
+    vault.records.get_mut(&modified).unwrap()[0] ^= 1;
+    vault.records.insert(b"added".to_vec(), b"data".to_vec());
+    let removed = ids[1].as_ref().to_vec();
+    vault.records.remove(&removed);
+    let mut expected = vec![modified, b"added".to_vec(), removed];
+    expected.sort();
+    match tree.verify(root, &entries(&vault)) {
+        Err(Error::IntegrityMismatch { mut ids }) => {
+            ids.sort();
+            assert_eq!(ids, expected);
+        }
+        other => panic!("unexpected {:?}", other),
+    }
+
+    // a store matching the tree but not the expected root
+    let tree = IntegrityTree::from_entries(&entries(&vault));
+    match tree.verify(root, &entries(&vault)) {
+        Err(Error::IntegrityMismatch { ids }) => assert!(ids.is_empty()),
+        other => panic!("unexpected {:?}", other),
+    }
+    assert_eq!(
+        Error::IntegrityMismatch { ids: expected }.to_string(),
+        "Integrity check failed: `3` entries differ"
+    );
+}
@@ -0,0 +1,145 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+mod utils;
+
+use std::convert::Infallible;
+
+use utils::{alloc::WatchingAllocator, provider::Provider};
+use vault::{BoxProvider, Decrypt, Encrypt, Key};
+
+#[global_allocator]
+static ALLOC: WatchingAllocator = WatchingAllocator;
+
+struct Plain(Vec<u8>);
+struct Sealed(Vec<u8>);
+
+impl AsRef<[u8]> for Plain {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl AsRef<[u8]> for Sealed {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl From<Vec<u8>> for Sealed {
+    fn from(data: Vec<u8>) -> Self {
+        Self(data)
+    }
+}
+
+impl From<Vec<u8>> for Plain {
+    fn from(data: Vec<u8>) -> Self {
+        Self(data)
+    }
+}
+
+impl Encrypt<Sealed> for Plain {}
+impl Decrypt<Infallible, Plain> for Sealed {}
+
+/// the number of allocations of `f`
+fn allocations<R>(f: impl FnOnce() -> R) -> (R, usize) {
+    let before = WatchingAllocator::allocations();
+    let result = f();
+    (result, WatchingAllocator::allocations() - before)
+}
+
+/// seal and open records of `len` bytes into reused buffers.  Returns the allocations per record of
+/// `encrypt`/`decrypt` and of `encrypt_into`/`decrypt_into`.
+fn compare_allocations<P: BoxProvider>(len: usize) -> ((usize, usize), (usize, usize)) {
+    let key = Key::<P>::random().unwrap();
+    let plain = Plain(vec![7; len]);
+
+    let (sealed, seal) = allocations(|| plain.encrypt(&key, b"ad").unwrap());
+    let (opened, open) = allocations(|| Decrypt::<Infallible, Plain>::decrypt(&sealed, &key, b"ad").unwrap());
+    assert_eq!(opened.0, plain.0);
+
+    let mut sealed = Vec::new();
+    let mut opened = Vec::new();
+    plain.encrypt_into(&key, b"ad", &mut sealed).unwrap();
+    Sealed(sealed.clone()).decrypt_into(&key, b"ad", &mut opened).unwrap();
+    let (sealed_ptr, opened_ptr) = (sealed.as_ptr(), opened.as_ptr());
+
+    let mut seal_into = 0;
+    let mut open_into = 0;
+    let mut boxx = Sealed(Vec::with_capacity(len + P::box_overhead()));
+    for i in 0..10u8 {
+        let record = Plain(vec![i; len]);
+        let (written, n) = allocations(|| record.encrypt_into(&key, b"ad", &mut sealed).unwrap());
+        assert_eq!(written, len + P::box_overhead());
+        assert_eq!(sealed.len(), written);
+        seal_into = seal_into.max(n);
+
+        boxx.0.clear();
+        boxx.0.extend_from_slice(&sealed);
+        let (read, n) = allocations(|| boxx.decrypt_into(&key, b"ad", &mut opened).unwrap());
+        assert_eq!(read, len);
+        assert_eq!(opened, record.0);
+        open_into = open_into.max(n);
+
+        // the buffers are reused
+        assert_eq!(sealed.as_ptr(), sealed_ptr);
+        assert_eq!(opened.as_ptr(), opened_ptr);
+    }
+
+    ((seal, open), (seal_into, open_into))
+}
+
+#[test]
+fn test_into_fallback() {
+    let ((seal, open), (seal_into, open_into)) = compare_allocations::<Provider>(1000);
+    assert!(seal_into <= seal, "{} > {}", seal_into, seal);
+    assert!(open_into <= open, "{} > {}", open_into, open);
+}
+
+#[test]
+fn test_into_failure() {
+    let key = Key::<Provider>::random().unwrap();
+    let mut out = Vec::new();
+    Plain(b"some data".to_vec())
+        .encrypt_into(&key, b"ad", &mut out)
+        .unwrap();
+
+    let sealed = Sealed(out.clone());
+    assert!(sealed.decrypt_into(&key, b"other ad", &mut out).is_err());
+    assert!(out.is_empty());
+
+    let exhausted = Key::<Provider>::random().unwrap().with_max_uses(0);
+    out.extend_from_slice(b"left over");
+    assert!(Plain(b"some data".to_vec())
+        .encrypt_into(&exhausted, b"ad", &mut out)
+        .is_err());
+    assert!(out.is_empty());
+}
+
+#[cfg(feature = "provider-xchacha")]
+#[test]
+fn test_into_xchacha_allocations() {
+    use vault::providers::XChaChaPoly;
+
+    let ((seal, open), into) = compare_allocations::<XChaChaPoly>(1000);
+    assert!(seal > 0 && open > 0);
+    assert_eq!(into, (0, 0));
+}
+
+#[cfg(feature = "provider-aes-gcm")]
+#[test]
+fn test_into_aes_gcm_allocations() {
+    use vault::providers::AesGcm256;
+
+    let ((seal, open), into) = compare_allocations::<AesGcm256>(1000);
+    assert!(seal > 0 && open > 0);
+    assert_eq!(into, (0, 0));
+}
@@ -0,0 +1,335 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+mod utils;
+
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    panic::{self, AssertUnwindSafe},
+    rc::Rc,
+};
+
+use utils::{provider::Provider, test_vault::TestVault};
+use vault::{
+    BlindIndex, DBView, DBWriter, DeleteRequest, Error, Id, JournaledVault, Key, ListResult, ReadRequest, ReadResult,
+    RecordHint, Store, VaultTransaction, WriteRequest,
+};
+
+impl Store for TestVault {
+    fn list(&self) -> vault::Result<ListResult> {
+        Ok(TestVault::list(self))
+    }
+
+    fn read(&self, request: ReadRequest) -> vault::Result<Option<ReadResult>> {
+        Ok(TestVault::read(self, request))
+    }
+
+    fn write(&mut self, request: WriteRequest) -> vault::Result<()> {
+        let (id, data) = request.into();
+        self.records.insert(id, data);
+        Ok(())
+    }
+
+    fn delete(&mut self, request: DeleteRequest) -> vault::Result<()> {
+        let id: Vec<u8> = request.into();
+        self.records.remove(&id);
+        Ok(())
+    }
+}
+
+/// a journaled vault with a chain of `owner` and a record, and the id of the record
+fn vault_with_record(owner: Id) -> (JournaledVault<Provider, TestVault>, Id) {
+    let key = Key::<Provider>::random().unwrap();
+    let mut store = TestVault::empty(key.clone());
+    let (id, data) = DBWriter::create_chain(&key, owner).into();
+    store.records.insert(id, data);
+    let mut vault = JournaledVault::open(key.clone(), store).unwrap();
+    let record = vault.transaction(|tx| write(tx, &key, owner, b"old", "old")).unwrap();
+    (vault, record)
+}
+
+/// stages a record of `owner` with `data` and an index entry of it for `name`, returns the id of the record
+fn write<S: Store>(
+    tx: &mut VaultTransaction<'_, S>,
+    key: &Key<Provider>,
+    owner: Id,
+    data: &[u8],
+    name: &str,
+) -> vault::Result<Id> {
+    let view = DBView::load(key.clone(), tx.list()?)?;
+    let index = BlindIndex::new(key, 16)?;
+    let (id, requests) =
+        view.writer(owner)
+            .write_indexed(data, RecordHint::new(b"hint")?, &index, &[("name", name.as_bytes())])?;
+    for request in requests {
+        tx.write(request)?;
+    }
+    Ok(id)
+}
+
+/// stages the revocation of the record `id` of `owner`
+fn revoke<S: Store>(tx: &mut VaultTransaction<'_, S>, key: &Key<Provider>, owner: Id, id: Id) -> vault::Result<()> {
+    let view = DBView::load(key.clone(), tx.list()?)?;
+    let (to_write, to_delete) = view.writer(owner).revoke(id)?;
+    tx.write(to_write)?;
+    tx.delete(to_delete)
+}
+
+/// the data of the valid records of the store
+fn records(store: &TestVault) -> Vec<Vec<u8>> {
+    let view = DBView::load(store.key().clone(), store.list()).unwrap();
+    let reader = view.reader();
+    let mut data: Vec<Vec<u8>> = view
+        .records()
+        .map(|(id, _)| {
+            reader
+                .read(store.read(reader.prepare_read(id).unwrap()).unwrap())
+                .unwrap()
+        })
+        .collect();
+    data.sort();
+    data
+}
+
+#[test]
+fn test_journal_transaction() {
+    let owner = Id::random::<Provider>().unwrap();
+    let (mut vault, old) = vault_with_record(owner);
+    let key = vault.store().key().clone();
+    let before = vault.store().records.len();
+
+    let new = vault
+        .transaction(|tx| {
+            let new = write(tx, &key, owner, b"new", "new")?;
+            revoke(tx, &key, owner, old)?;
+
+            // reads see the staged state
+            let view = DBView::load(key.clone(), tx.list()?)?;
+            assert!(view.records().any(|(id, _)| id == new));
+            assert!(view.records().all(|(id, _)| id != old));
+            let reader = view.reader();
+            assert!(tx.read(reader.prepare_read(new)?)?.is_some());
+            assert!(tx.read(ReadRequest::payload::<Provider>(old))?.is_none());
+            Ok(new)
+        })
+        .unwrap();
+
+    let store = vault.store();
+    assert_eq!(records(store), [b"new".to_vec()]);
+    assert!(store.read(ReadRequest::payload::<Provider>(new)).is_some());
+    // a payload, a transaction and an index entry were added, a revocation added and a payload deleted
+    assert_eq!(store.records.len(), before + 3 + 1 - 1);
+    assert!(store.records.keys().all(|id| !id.starts_with(b"vault journal")));
+}
+
+#[test]
+fn test_journal_rollback() {
+    let owner = Id::random::<Provider>().unwrap();
+    let (mut vault, old) = vault_with_record(owner);
+    let key = vault.store().key().clone();
+    let before: HashMap<Vec<u8>, Vec<u8>> = vault.store().records.clone();
+
+    // an error discards the staged requests
+    let result = vault.transaction(|tx| {
+        write(tx, &key, owner, b"new", "new")?;
+        revoke(tx, &key, owner, old)?;
+        Err::<(), _>(Error::InterfaceErrorDetailed(String::from("abort")))
+    });
+    assert!(matches!(result, Err(Error::InterfaceErrorDetailed(_))));
+    assert_eq!(vault.store().records, before);
+
+    // and so does a panic
+    let result: std::thread::Result<vault::Result<()>> = panic::catch_unwind(AssertUnwindSafe(|| {
+        vault.transaction(|tx| {
+            write(tx, &key, owner, b"new", "new")?;
+            panic!("interrupted");
+        })
+    }));
+    assert!(result.is_err());
+    assert_eq!(vault.store().records, before);
+
+    // nested transactions are rejected
+    let result = vault.transaction(|tx| {
+        write(tx, &key, owner, b"new", "new")?;
+        tx.transaction(|_| Ok(()))
+    });
+    assert!(matches!(result, Err(Error::NestedTransaction)));
+    assert_eq!(vault.store().records, before);
+    assert_eq!(records(vault.store()), [b"old".to_vec()]);
+}
+
+/// a store shared with the test, which modifies it behind the vault
+struct Shared(Rc<RefCell<TestVault>>);
+
+impl Store for Shared {
+    fn list(&self) -> vault::Result<ListResult> {
+        Store::list(&*self.0.borrow())
+    }
+
+    fn read(&self, request: ReadRequest) -> vault::Result<Option<ReadResult>> {
+        Store::read(&*self.0.borrow(), request)
+    }
+
+    fn write(&mut self, request: WriteRequest) -> vault::Result<()> {
+        self.0.borrow_mut().write(request)
+    }
+
+    fn delete(&mut self, request: DeleteRequest) -> vault::Result<()> {
+        self.0.borrow_mut().delete(request)
+    }
+}
+
+#[test]
+fn test_journal_integrity() {
+    let owner = Id::random::<Provider>().unwrap();
+    let (vault, old) = vault_with_record(owner);
+    let key = vault.store().key().clone();
+    let store = Rc::new(RefCell::new(vault.into_store()));
+    let mut vault = JournaledVault::open(key.clone(), Shared(store.clone())).unwrap();
+    let root = vault.integrity_root();
+    vault.verify_integrity(root).unwrap();
+
+    // each commit updates the root, which matches the store reopened by another process
+    vault
+        .transaction(|tx| {
+            write(tx, &key, owner, b"new", "new")?;
+            revoke(tx, &key, owner, old)
+        })
+        .unwrap();
+    assert_ne!(vault.integrity_root(), root);
+    let root = vault.integrity_root();
+    vault.verify_integrity(root).unwrap();
+    let reopened = JournaledVault::open(key.clone(), Shared(store.clone())).unwrap();
+    assert_eq!(reopened.integrity_root(), root);
+
+    // the entries modified, added and removed behind the vault are reported
+    let mut changed: Vec<Vec<u8>> = store.borrow().records.keys().take(2).cloned().collect();
+    store.borrow_mut().records.get_mut(&changed[0]).unwrap().push(0);
+    store.borrow_mut().records.remove(&changed[1]);
+    store.borrow_mut().records.insert(b"added".to_vec(), Vec::new());
+    changed.push(b"added".to_vec());
+    changed.sort();
+    match vault.verify_integrity(root) {
+        Err(Error::IntegrityMismatch { mut ids }) => {
+            ids.sort();
+            assert_eq!(ids, changed);
+        }
+        other => panic!("unexpected {:?}", other),
+    }
+
+    // the root logged before doesn't verify the store after a commit
+    let vault = JournaledVault::open(key, Shared(store)).unwrap();
+    assert!(matches!(
+        vault.verify_integrity(root),
+        Err(Error::IntegrityMismatch { ids }) if ids.is_empty()
+    ));
+}
+
+#[cfg(feature = "test-utils")]
+mod crash {
+    use super::*;
+    use vault::{CommitStep, Recovery};
+
+    type Entries = HashMap<Vec<u8>, Vec<u8>>;
+
+    /// a vault whose commit of a new record and the revocation of the old one crashed before `step`, the store before
+    /// the commit and the ids of the records
+    fn crash_before(step: CommitStep) -> (JournaledVault<Provider, TestVault>, Entries, Id) {
+        let owner = Id::random::<Provider>().unwrap();
+        let (mut vault, old) = vault_with_record(owner);
+        let key = vault.store().key().clone();
+        let before = vault.store().records.clone();
+
+        vault.crash_before(step);
+        let result = vault.transaction(|tx| {
+            write(tx, &key, owner, b"new", "new")?;
+            revoke(tx, &key, owner, old)
+        });
+        assert!(matches!(result, Err(Error::InterfaceErrorDetailed(_))));
+        (vault, before, old)
+    }
+
+    fn journaled(store: &TestVault) -> usize {
+        store
+            .records
+            .keys()
+            .filter(|id| id.starts_with(b"vault journal"))
+            .count()
+    }
+
+    #[test]
+    fn test_journal_crash_before_journal() {
+        let (mut vault, before, _) = crash_before(CommitStep::Journal);
+        assert_eq!(vault.store().records, before);
+        assert_eq!(vault.recover().unwrap(), Recovery::Clean);
+    }
+
+    #[test]
+    fn test_journal_crash_before_commit() {
+        let (vault, before, _) = crash_before(CommitStep::Commit);
+        // the batch is journaled but not committed
+        assert_eq!(journaled(vault.store()), 1);
+        assert_eq!(records(vault.store()), [b"old".to_vec()]);
+
+        // reopening the store after the crash discards the partial batch
+        let store = vault.into_store();
+        let mut vault = JournaledVault::open(store.key().clone(), store).unwrap();
+        assert_eq!(vault.store().records, before);
+        assert_eq!(vault.recover().unwrap(), Recovery::Clean);
+
+        let (mut vault, before, _) = crash_before(CommitStep::Commit);
+        assert_eq!(vault.recover().unwrap(), Recovery::Discarded);
+        assert_eq!(vault.store().records, before);
+    }
+
+    #[test]
+    fn test_journal_crash_after_commit() {
+        for step in [CommitStep::Apply, CommitStep::Cleanup].iter() {
+            let (vault, _, old) = crash_before(*step);
+            assert_eq!(journaled(vault.store()), 2);
+
+            // reopening the store after the crash applies the committed batch
+            let store = vault.into_store();
+            let vault = JournaledVault::open(store.key().clone(), store).unwrap();
+            assert_eq!(records(vault.store()), [b"new".to_vec()]);
+            assert!(vault.store().read(ReadRequest::payload::<Provider>(old)).is_none());
+            assert_eq!(journaled(vault.store()), 0);
+
+            let (mut vault, _, _) = crash_before(*step);
+            assert_eq!(vault.recover().unwrap(), Recovery::Replayed);
+            assert_eq!(records(vault.store()), [b"new".to_vec()]);
+        }
+    }
+
+    #[test]
+    fn test_journal_tampered() {
+        let (vault, _, _) = crash_before(CommitStep::Apply);
+        let key = vault.store().key().clone();
+
+        // a modified journal doesn't match the commit mark
+        let mut store = vault.into_store();
+        let journal = store.records.get_mut(b"vault journal".as_slice()).unwrap();
+        let last = journal.len() - 1;
+        journal[last] ^= 1;
+        assert!(matches!(
+            JournaledVault::open(key.clone(), store),
+            Err(Error::AuthenticationFailed)
+        ));
+
+        // nor does the journal of another key
+        let (vault, _, _) = crash_before(CommitStep::Apply);
+        assert!(matches!(
+            JournaledVault::open(Key::<Provider>::random().unwrap(), vault.into_store()),
+            Err(Error::AuthenticationFailed)
+        ));
+    }
+}
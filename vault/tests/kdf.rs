@@ -0,0 +1,48 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+#![cfg(feature = "password-kdf")]
+
+mod utils;
+
+use utils::provider::Provider;
+use vault::{KdfParams, Key};
+
+/// cheap parameters to keep the tests fast.
+const PARAMS: KdfParams = KdfParams {
+    memory_cost: 32,
+    iterations: 1,
+    parallelism: 1,
+};
+
+#[test]
+fn test_derive_from_password() {
+    let salt = b"a salt of 16 b!!";
+
+    let key = Key::<Provider>::derive_from_password(b"password", salt, PARAMS).unwrap();
+    let same = Key::<Provider>::derive_from_password(b"password", salt, PARAMS).unwrap();
+    assert_eq!(key.bytes().len(), 32);
+    assert_eq!(key, same);
+
+    let other_salt = Key::<Provider>::derive_from_password(b"password", b"another salt 16b", PARAMS).unwrap();
+    assert_ne!(key, other_salt);
+
+    let other_password = Key::<Provider>::derive_from_password(b"passw0rd", salt, PARAMS).unwrap();
+    assert_ne!(key, other_password);
+}
+
+#[test]
+fn test_derive_from_password_short_salt() {
+    assert!(matches!(
+        Key::<Provider>::derive_from_password(b"password", b"too short", PARAMS),
+        Err(vault::Error::InterfaceError)
+    ));
+}
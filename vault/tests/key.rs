@@ -0,0 +1,482 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+mod utils;
+
+use std::{
+    collections::{HashMap, HashSet},
+    convert::TryFrom,
+    hash::{Hash, Hasher},
+    sync::{Arc, Mutex},
+};
+
+use utils::{
+    alloc::WatchingAllocator,
+    provider::{IetfProvider, Provider},
+};
+use vault::{BoxProvider, Decrypt, Encrypt, Error, Key, KeyFingerprint, KeyMeta};
+
+#[global_allocator]
+static ALLOC: WatchingAllocator = WatchingAllocator;
+
+/// the allocator can only watch one allocation at a time.
+static WATCH_LOCK: Mutex<()> = Mutex::new(());
+
+fn watch(bytes: &[u8]) {
+    WatchingAllocator::watch(bytes.as_ptr(), bytes.len());
+}
+
+#[test]
+fn test_key_wiped_on_drop() {
+    let _lock = WATCH_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+    let key = Key::<Provider>::random().unwrap();
+    watch(key.bytes());
+    drop(key);
+
+    assert_eq!(WatchingAllocator::freed_wiped(), Some(true));
+}
+
+#[test]
+fn test_key_clone_wiped_independently() {
+    let _lock = WATCH_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+    let key = Key::<Provider>::random().unwrap();
+    let clone = key.clone();
+
+    watch(clone.bytes());
+    drop(clone);
+    assert_eq!(WatchingAllocator::freed_wiped(), Some(true));
+
+    // the original is untouched by dropping its clone
+    assert!(key.bytes().iter().any(|b| *b != 0));
+    watch(key.bytes());
+    drop(key);
+    assert_eq!(WatchingAllocator::freed_wiped(), Some(true));
+}
+
+#[test]
+fn test_key_failed_load_wiped() {
+    let _lock = WATCH_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+    let rejected = vec![0xa5; 31];
+    watch(&rejected);
+    assert!(Key::<Provider>::load(rejected).is_err());
+
+    assert_eq!(WatchingAllocator::freed_wiped(), Some(true));
+}
+
+#[test]
+fn test_key_failed_load_wipes_capacity() {
+    let _lock = WATCH_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+    // the bytes beyond the length were key material as well
+    let mut rejected = vec![0xa5; 64];
+    rejected.truncate(31);
+    WatchingAllocator::watch(rejected.as_ptr(), 64);
+    assert!(Key::<Provider>::load(rejected).is_err());
+
+    assert_eq!(WatchingAllocator::freed_wiped(), Some(true));
+}
+
+#[cfg(feature = "test-utils")]
+#[test]
+fn test_key_rejected_bytes_wiped() {
+    use vault::test_utils::take_wiped_buffers;
+
+    take_wiped_buffers();
+    assert!(Key::<Provider>::load(vec![0xa5; 31]).is_err());
+    assert_eq!(take_wiped_buffers(), [vec![0; 31]]);
+
+    // keys decoded from a key source go through the same path
+    std::env::set_var("VAULT_TEST_KEY_REJECTED", "a5a5a5");
+    assert!(Key::<Provider>::from_env_with("VAULT_TEST_KEY_REJECTED", vault::KeyEncoding::Hex, true).is_err());
+    assert_eq!(take_wiped_buffers(), [vec![0; 3], vec![0; 6]]);
+}
+
+#[test]
+fn test_key_drop_closure() {
+    let seen = Arc::new(Mutex::new(Vec::new()));
+
+    let mut key = Key::<Provider>::random().unwrap();
+    let bytes = key.bytes().to_vec();
+
+    let sink = seen.clone();
+    key.on_drop(move |bytes| sink.lock().unwrap().extend_from_slice(bytes));
+
+    // clones share the hook and call it when they are dropped
+    let clone = key.clone();
+    drop(clone);
+    assert_eq!(*seen.lock().unwrap(), bytes);
+
+    drop(key);
+    assert_eq!(seen.lock().unwrap().len(), 2 * bytes.len());
+    assert_eq!(&seen.lock().unwrap()[bytes.len()..], bytes.as_slice());
+}
+
+#[test]
+fn test_key_multiple_drop_hooks() {
+    let seen = Arc::new(Mutex::new(Vec::new()));
+
+    let mut key = Key::<Provider>::random().unwrap();
+    let bytes = key.bytes().to_vec();
+
+    let first = seen.clone();
+    key.on_drop(move |_| first.lock().unwrap().push("first"));
+    let second = seen.clone();
+    let expected = bytes.clone();
+    key.on_drop(move |bytes| {
+        assert_eq!(bytes, expected.as_slice());
+        second.lock().unwrap().push("second")
+    });
+    drop(key);
+
+    assert_eq!(*seen.lock().unwrap(), ["first", "second"]);
+
+    let mut key = Key::<Provider>::random().unwrap();
+    let sink = seen.clone();
+    key.on_drop(move |_| sink.lock().unwrap().push("cleared"));
+    key.clear_drop_hooks();
+    drop(key);
+
+    assert_eq!(seen.lock().unwrap().len(), 2);
+}
+
+#[test]
+fn test_key_panicking_drop_hook() {
+    let _lock = WATCH_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let seen = Arc::new(Mutex::new(Vec::new()));
+
+    let mut key = Key::<Provider>::random().unwrap();
+    watch(key.bytes());
+
+    key.on_drop(|_| panic!("hook failed"));
+    let sink = seen.clone();
+    key.on_drop(move |_| sink.lock().unwrap().push("after panic"));
+
+    let dropped = std::panic::catch_unwind(std::panic::AssertUnwindSafe(move || drop(key)));
+    assert!(dropped.is_ok());
+    assert_eq!(*seen.lock().unwrap(), ["after panic"]);
+    assert_eq!(WatchingAllocator::freed_wiped(), Some(true));
+}
+
+#[test]
+fn test_key_derive_child() {
+    let master = Key::<Provider>::random().unwrap();
+
+    let child = master.derive_child(b"records").unwrap();
+    assert_eq!(child, master.derive_child(b"records").unwrap());
+    assert_ne!(child, master.derive_child(b"metadata").unwrap());
+    assert_ne!(child, master);
+    assert_eq!(child.bytes().len(), master.bytes().len());
+
+    let sealed = b"child data".to_vec().encrypt(&child, b"ad").unwrap();
+    assert!(sealed.decrypt(&master, b"ad").is_err());
+    let opened: Vec<u8> = sealed
+        .decrypt(&master.derive_child(b"records").unwrap(), b"ad")
+        .unwrap();
+    assert_eq!(opened, b"child data");
+}
+
+#[test]
+fn test_key_split_combine() {
+    let key = Key::<Provider>::random().unwrap();
+
+    let shares = key.split(2, 3).unwrap();
+    assert_eq!(shares.len(), 3);
+    for pair in [[0, 1], [1, 2], [2, 0]].iter() {
+        let subset = [shares[pair[0]].clone(), shares[pair[1]].clone()];
+        assert_eq!(Key::<Provider>::combine(&subset).unwrap(), key);
+    }
+
+    let mut shares = key.split(3, 5).unwrap();
+    shares.reverse();
+    assert_eq!(Key::<Provider>::combine(&shares[1..4]).unwrap(), key);
+    assert_eq!(Key::<Provider>::combine(&shares).unwrap(), key);
+
+    let shares = key.split(1, 2).unwrap();
+    assert_eq!(Key::<Provider>::combine(&shares[1..]).unwrap(), key);
+}
+
+#[test]
+fn test_key_combine_failures() {
+    let key = Key::<Provider>::random().unwrap();
+    let mut shares = key.split(3, 5).unwrap();
+
+    assert!(matches!(
+        Key::<Provider>::combine(&shares[..2]),
+        Err(vault::Error::NotEnoughShares {
+            required: 3,
+            provided: 2
+        })
+    ));
+
+    shares[1].bytes[7] ^= 1;
+    let index = shares[1].index;
+    match Key::<Provider>::combine(&shares[..3]) {
+        Err(vault::Error::CorruptShare(i)) => assert_eq!(i, index),
+        _ => panic!("corrupted share was not detected"),
+    }
+
+    assert!(key.split(0, 3).is_err());
+    assert!(key.split(4, 3).is_err());
+}
+
+#[test]
+fn test_key_fingerprint() {
+    let key = Key::<Provider>::load((0..32).collect()).unwrap();
+    let fingerprint = key.fingerprint();
+
+    // pinned so the format never silently changes.
+    let expected = "143c9503a3cdb668b32b80f15686bfbef5eca464c9cc1df00601f16ddcd603a3";
+    assert_eq!(fingerprint.to_string(), expected);
+    assert_eq!(expected.parse::<KeyFingerprint>().unwrap(), fingerprint);
+    assert_eq!(key.clone().fingerprint(), fingerprint);
+    assert_ne!(Key::<Provider>::random().unwrap().fingerprint(), fingerprint);
+
+    assert!("143c95".parse::<KeyFingerprint>().is_err());
+    assert!(expected.replace('c', "x").parse::<KeyFingerprint>().is_err());
+}
+
+#[test]
+fn test_key_eq() {
+    let key = Key::<Provider>::load(vec![7; 32]).unwrap();
+    assert_eq!(key, key.clone());
+    assert_eq!(key, Key::load(vec![7; 32]).unwrap());
+
+    let mut last = vec![7; 32];
+    last[31] = 8;
+    assert_ne!(key, Key::load(last).unwrap());
+    assert_ne!(key, Key::load(vec![0; 32]).unwrap());
+}
+
+/// a rough smoke test that comparing keys doesn't short circuit on the first differing byte.
+#[test]
+#[ignore]
+fn test_key_eq_timing() {
+    use std::time::Instant;
+
+    let key = Key::<Provider>::load(vec![7; 32]).unwrap();
+    let mut first = vec![7; 32];
+    first[0] = 0;
+    let first = Key::<Provider>::load(first).unwrap();
+    let mut last = vec![7; 32];
+    last[31] = 0;
+    let last = Key::<Provider>::load(last).unwrap();
+
+    let time = |other: &Key<Provider>| {
+        let start = Instant::now();
+        for _ in 0..1_000_000 {
+            assert!(std::hint::black_box(&key) != std::hint::black_box(other));
+        }
+        start.elapsed().as_secs_f64()
+    };
+    // warm up
+    time(&first);
+
+    let (first, last) = (time(&first), time(&last));
+    let ratio = first.max(last) / first.min(last);
+    assert!(ratio < 1.5, "timing differs by a factor of {}", ratio);
+}
+
+/// a hasher which records everything written to it
+#[derive(Default)]
+struct RecordingHasher(Vec<u8>);
+
+impl Hasher for RecordingHasher {
+    fn finish(&self) -> u64 {
+        0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.extend_from_slice(bytes)
+    }
+}
+
+#[test]
+// the usage counter of a key is interior mutable but not part of its hash.
+#[allow(clippy::mutable_key_type)]
+fn test_key_hash() {
+    let key = Key::<Provider>::load((0..32).collect()).unwrap();
+
+    let mut hasher = RecordingHasher::default();
+    key.hash(&mut hasher);
+    assert!(!hasher.0.windows(key.bytes().len()).any(|w| w == key.bytes()));
+    assert!(hasher.0.windows(32).any(|w| w == key.fingerprint().as_ref()));
+
+    let other = Key::<Provider>::random().unwrap();
+    let mut map = HashMap::new();
+    map.insert(key.clone(), "key");
+    map.insert(other.clone(), "other");
+    assert_eq!(map.get(&Key::load((0..32).collect()).unwrap()), Some(&"key"));
+    assert_eq!(map.get(&other), Some(&"other"));
+    assert_eq!(map.get(&Key::random().unwrap()), None);
+
+    let mut set = HashSet::new();
+    assert!(set.insert(key.clone()));
+    assert!(!set.insert(key.clone()));
+    assert!(set.insert(other));
+    assert!(set.contains(&key));
+    assert_eq!(set.len(), 2);
+}
+
+#[test]
+fn test_key_load_from_slice() {
+    let source = [3u8; 32];
+    let key = Key::<Provider>::load_from_slice(&source).unwrap();
+    assert_eq!(key.bytes(), &source[..]);
+    assert_ne!(key.bytes().as_ptr(), source.as_ptr());
+    assert_eq!(Key::<Provider>::try_from(&source[..]).unwrap(), key);
+
+    drop(key);
+    assert_eq!(source, [3u8; 32]);
+
+    for len in [0, 31, 33, 64].iter() {
+        let source = vec![3u8; *len];
+        match Key::<Provider>::try_from(source.as_slice()) {
+            Err(vault::Error::InvalidKeyLength { expected, actual }) => {
+                assert_eq!(expected, 32);
+                assert_eq!(actual, *len);
+            }
+            _ => panic!("loaded key with invalid length"),
+        }
+        assert_eq!(source, vec![3u8; *len]);
+    }
+}
+
+/// a provider with 16 byte keys which can't seal anything
+struct ShortKeyProvider;
+
+impl BoxProvider for ShortKeyProvider {
+    type Error = Error;
+
+    fn box_key_len() -> usize {
+        16
+    }
+
+    fn box_overhead() -> usize {
+        0
+    }
+
+    fn box_id() -> [u8; 4] {
+        *b"shrt"
+    }
+
+    fn box_seal(_: &Key<Self>, _: &[u8], _: &[u8]) -> vault::Result<Vec<u8>> {
+        Err(Error::InterfaceError)
+    }
+
+    fn box_open(_: &Key<Self>, _: &[u8], _: &[u8]) -> vault::Result<Vec<u8>> {
+        Err(Error::InterfaceError)
+    }
+
+    fn random_buf(buf: &mut [u8]) -> vault::Result<()> {
+        Provider::random_buf(buf)
+    }
+}
+
+#[test]
+fn test_key_convert() {
+    let seen = Arc::new(Mutex::new(0));
+    let meta = KeyMeta {
+        id: [1; 16],
+        version: 3,
+        created_at: 0,
+    };
+
+    let mut key = Key::<Provider>::random().unwrap().with_meta(meta);
+    let bytes = key.bytes().to_vec();
+    let ptr = key.bytes().as_ptr();
+    let sink = seen.clone();
+    key.on_drop(move |_| *sink.lock().unwrap() += 1);
+
+    let converted: Key<IetfProvider> = key.convert().unwrap();
+    assert_eq!(*seen.lock().unwrap(), 0);
+    assert_eq!(converted.bytes(), bytes.as_slice());
+    assert_eq!(converted.bytes().as_ptr(), ptr);
+    assert_eq!(converted.meta(), Some(&meta));
+
+    let sealed = IetfProvider::box_seal(&converted, b"", b"data").unwrap();
+    let back: Key<Provider> = converted.convert().unwrap();
+    assert_eq!(back.bytes(), bytes.as_slice());
+    assert!(IetfProvider::box_open(&back.clone().convert().unwrap(), b"", &sealed).is_ok());
+
+    drop(back);
+    assert_eq!(*seen.lock().unwrap(), 2);
+}
+
+#[test]
+fn test_key_convert_length_mismatch() {
+    let key = Key::<Provider>::random().unwrap();
+    match key.convert::<ShortKeyProvider>() {
+        Err(Error::InvalidKeyLength { expected, actual }) => {
+            assert_eq!(expected, 16);
+            assert_eq!(actual, 32);
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn test_key_max_uses() {
+    let key = Key::<Provider>::random().unwrap().with_max_uses(2);
+    assert_eq!(key.remaining_uses(), Some(2));
+
+    let sealed = b"first".to_vec().encrypt(&key, b"").unwrap();
+    // clones share the counter
+    let clone = key.clone();
+    b"second".to_vec().encrypt(&clone, b"").unwrap();
+    assert_eq!(key.remaining_uses(), Some(0));
+
+    match b"third".to_vec().encrypt(&key, b"") {
+        Err(Error::KeyExhausted(2)) => {}
+        other => panic!("unexpected result: {:?}", other.map(|_| ())),
+    }
+    assert!(matches!(clone.checkout_use(), Err(Error::KeyExhausted(2))));
+
+    // opening is never limited
+    for _ in 0..3 {
+        let opened: Vec<u8> = sealed.decrypt(&key, b"").unwrap();
+        assert_eq!(opened, b"first");
+    }
+
+    // a new limit starts a new counter
+    let renewed = clone.with_max_uses(1);
+    assert!(b"fourth".to_vec().encrypt(&renewed, b"").is_ok());
+    assert_eq!(key.remaining_uses(), Some(0));
+    assert_eq!(Key::<Provider>::random().unwrap().remaining_uses(), None);
+}
+
+#[test]
+fn test_key_debug_redacted() {
+    let key = Key::<Provider>::load((0..32).map(|b| 0xa0 ^ b).collect()).unwrap();
+    let debug = format!("{:?}", key);
+    let pretty = format!("{:#?}", key);
+
+    let hex: String = key.bytes().iter().map(|b| format!("{:02x}", b)).collect();
+    let decimal = format!("{:?}", key.bytes());
+    for output in [&debug, &pretty] {
+        assert!(!output.contains(&hex), "{}", output);
+        assert!(!output.contains(&hex[..8]), "{}", output);
+        assert!(!output.contains(&decimal[1..20]), "{}", output);
+        assert!(output.contains("len: 32"), "{}", output);
+        assert!(output.contains(&key.fingerprint().to_string()), "{}", output);
+        assert!(output.contains("Provider"), "{}", output);
+    }
+
+    // also inside of results and errors
+    let result: vault::Result<Key<Provider>> = Ok(key.clone());
+    assert!(!format!("{:?}", result).contains(&decimal[1..20]));
+
+    // unless the bytes are revealed explicitly
+    let revealed = format!("{:?}", key.reveal_debug());
+    assert!(revealed.contains(&decimal), "{}", revealed);
+}
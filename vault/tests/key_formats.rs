@@ -0,0 +1,223 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+#![cfg(feature = "key-formats")]
+
+mod utils;
+
+use utils::{hex, provider::Provider};
+use vault::{BoxProvider, Error, Key};
+
+/// a provider with keys of `LEN` bytes that never seals anything, for the keys of RFC 7517
+struct KeyOnly<const LEN: usize>;
+
+impl<const LEN: usize> BoxProvider for KeyOnly<LEN> {
+    type Error = Error;
+
+    fn box_key_len() -> usize {
+        LEN
+    }
+
+    fn box_overhead() -> usize {
+        0
+    }
+
+    fn box_id() -> [u8; 4] {
+        *b"none"
+    }
+
+    fn jwk_algorithm() -> Option<&'static str> {
+        match LEN {
+            16 => Some("A128KW"),
+            _ => None,
+        }
+    }
+
+    fn box_seal(_key: &Key<Self>, _ad: &[u8], _data: &[u8]) -> vault::Result<Vec<u8>> {
+        Err(Error::crypto("seal", "Unsupported"))
+    }
+
+    fn box_open(_key: &Key<Self>, _ad: &[u8], _data: &[u8]) -> vault::Result<Vec<u8>> {
+        Err(Error::crypto("open", "Unsupported"))
+    }
+
+    fn random_buf(buf: &mut [u8]) -> vault::Result<()> {
+        Provider::random_buf(buf)
+    }
+}
+
+fn json(s: &str) -> serde_json::Value {
+    serde_json::from_str(s).unwrap()
+}
+
+#[test]
+fn test_jwk_rfc_7517() {
+    // the symmetric keys of RFC 7517 appendix A.3
+    let key = Key::<KeyOnly<16>>::from_jwk(r#"{"kty":"oct","alg":"A128KW","k":"GawgguFyGrWKav7AX4VKUg"}"#).unwrap();
+    assert_eq!(key.bytes(), &hex("19ac2082e1721ab58a6afec05f854a52")[..]);
+    assert_eq!(
+        json(&key.to_jwk().unwrap()),
+        json(r#"{"kty":"oct","alg":"A128KW","k":"GawgguFyGrWKav7AX4VKUg"}"#)
+    );
+
+    let jwk = r#"{"kty":"oct",
+        "k":"AyM1SysPpbyDfgZld3umj1qzKObwVMkoqQ-EstJQLr_T-1qS0gZH75aKtMN3Yj0iPS4hcgUuTwjAzZr1Z9CAow",
+        "kid":"HMAC key used in JWS spec Appendix A.1 example"}"#;
+    let key = Key::<KeyOnly<64>>::from_jwk(jwk).unwrap();
+    assert_eq!(
+        key.bytes(),
+        &hex(concat!(
+            "0323354b2b0fa5bc837e0665777ba68f5ab328e6f054c928a90f84b2d2502ebfd3fb5a92d20647ef968ab4c377623d22",
+            "3d2e2172052e4f08c0cd9af567d080a3"
+        ))[..]
+    );
+    // without a JWA name there is no `alg`
+    assert_eq!(
+        json(&key.to_jwk().unwrap()),
+        json(
+            r#"{"kty":"oct","k":"AyM1SysPpbyDfgZld3umj1qzKObwVMkoqQ-EstJQLr_T-1qS0gZH75aKtMN3Yj0iPS4hcgUuTwjAzZr1Z9CAow"}"#
+        )
+    );
+}
+
+#[test]
+fn test_jwk_roundtrip() {
+    let key = Key::<Provider>::random().unwrap();
+    let jwk = key.to_jwk().unwrap();
+    assert!(!jwk.contains('='));
+    assert_eq!(Key::<Provider>::from_jwk(&jwk).unwrap(), key);
+
+    #[cfg(feature = "provider-xchacha")]
+    {
+        use vault::providers::XChaChaPoly;
+
+        let key = Key::<XChaChaPoly>::random().unwrap();
+        let jwk = key.to_jwk().unwrap();
+        assert_eq!(json(&jwk)["alg"], "XC20P");
+        assert_eq!(Key::<XChaChaPoly>::from_jwk(&jwk).unwrap(), key);
+    }
+}
+
+#[test]
+fn test_jwk_rejects() {
+    let from_jwk = |jwk: &str| Key::<KeyOnly<16>>::from_jwk(jwk);
+
+    assert!(matches!(
+        from_jwk(r#"{"kty":"RSA","k":"GawgguFyGrWKav7AX4VKUg"}"#),
+        Err(Error::UnsupportedKeyType(kty)) if kty == "RSA"
+    ));
+    assert!(matches!(
+        from_jwk(r#"{"kty":"oct","k":"GawgguFyGrWKav7AX4VKUg=="}"#),
+        Err(Error::Base64ErrorDetailed(message)) if message.contains("padded")
+    ));
+    // the last character carries unused bits, which have to be zero
+    assert!(matches!(
+        from_jwk(r#"{"kty":"oct","k":"GawgguFyGrWKav7AX4VKUh"}"#),
+        Err(Error::Base64ErrorDetailed(message)) if message.contains("canonical")
+    ));
+    assert!(matches!(
+        from_jwk(r#"{"kty":"oct","k":"GawgguFyGrWKav7AX4VKU"}"#),
+        Err(Error::Base64ErrorDetailed(_))
+    ));
+    assert!(matches!(
+        from_jwk(r#"{"kty":"oct","k":"Gawgg+FyGrWKav7AX4VKUg"}"#),
+        Err(Error::Base64Error)
+    ));
+    assert!(matches!(
+        from_jwk(r#"{"kty":"oct","k":"GawgguFyGrWKav7AX4VKUgAA"}"#),
+        Err(Error::InvalidKeyLength {
+            expected: 16,
+            actual: 18
+        })
+    ));
+    assert!(matches!(
+        from_jwk(r#"{"kty":"oct","alg":"A256GCM","k":"GawgguFyGrWKav7AX4VKUg"}"#),
+        Err(Error::JwkError(message)) if message.contains("A256GCM") && message.contains("A128KW")
+    ));
+    assert!(matches!(from_jwk(r#"{"kty":"oct"}"#), Err(Error::JwkError(_))));
+    assert!(matches!(
+        from_jwk(r#"{"k":"GawgguFyGrWKav7AX4VKUg"}"#),
+        Err(Error::JwkError(_))
+    ));
+    assert!(matches!(from_jwk("GawgguFyGrWKav7AX4VKUg"), Err(Error::JwkError(_))));
+    assert!(matches!(
+        from_jwk(r#"{"kty":"oct","k":"GawgguFyGrWKav7AX4VKUg","k":"GawgguFyGrWKav7AX4VKUg"}"#),
+        Err(Error::JwkError(_))
+    ));
+
+    // a provider without a JWA name can't check the algorithm
+    assert!(matches!(
+        Key::<KeyOnly<64>>::from_jwk(r#"{"kty":"oct","alg":"HS512","k":"AyM1SysPpbyDfgZld3umj1qzKObwVMkoqQ-EstJQLr_T-1qS0gZH75aKtMN3Yj0iPS4hcgUuTwjAzZr1Z9CAow"}"#),
+        Err(Error::JwkError(message)) if message.contains("HS512")
+    ));
+}
+
+/// the X25519 and Ed25519 keys of the OpenSSL fixtures in `tests/pkcs8`, encrypted with `correct-horse`
+const X25519: &str = "b0e084c540105fb7bf935d79e3b2330d769bfa0b379f42da2334b59c7f9ac354";
+const ED25519: &str = "1cb5770d5d13ff215f9768944ccec630d9c077f88c74c7b2d3f91030ca4aaeb2";
+
+#[test]
+fn test_pkcs8_openssl() {
+    let fixtures: [(&[u8], &str); 4] = [
+        // openssl pkcs8 -topk8 -v2 aes-256-cbc -v2prf hmacWithSHA256
+        (include_bytes!("pkcs8/x25519_aes256_sha256.der"), X25519),
+        // openssl pkcs8 -topk8 -v2 aes-128-cbc -v2prf hmacWithSHA1 -iter 1000
+        (include_bytes!("pkcs8/x25519_aes128_sha1.der"), X25519),
+        // openssl pkcs8 -topk8 -v2 aes-192-cbc -v2prf hmacWithSHA512
+        (include_bytes!("pkcs8/ed25519_aes192_sha512.der"), ED25519),
+        // an AES key, assembled with `openssl asn1parse -genconf`, `openssl kdf` and `openssl enc`
+        (
+            include_bytes!("pkcs8/secret_aes256_sha256.der"),
+            "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f",
+        ),
+    ];
+    for (der, expected) in fixtures.iter() {
+        let key = Key::<Provider>::from_pkcs8_encrypted(der, b"correct-horse").unwrap();
+        assert_eq!(key.bytes(), &hex(expected)[..]);
+
+        assert!(matches!(
+            Key::<Provider>::from_pkcs8_encrypted(der, b"wrong-horse"),
+            Err(Error::AuthenticationFailed)
+        ));
+        assert!(matches!(
+            Key::<KeyOnly<16>>::from_pkcs8_encrypted(der, b"correct-horse"),
+            Err(Error::InvalidKeyLength {
+                expected: 16,
+                actual: 32
+            })
+        ));
+    }
+}
+
+#[test]
+fn test_pkcs8_rejects() {
+    let der = include_bytes!("pkcs8/x25519_aes256_sha256.der");
+    for len in 0..der.len() {
+        assert!(Key::<Provider>::from_pkcs8_encrypted(&der[..len], b"correct-horse").is_err());
+    }
+    assert!(matches!(
+        Key::<Provider>::from_pkcs8_encrypted(&[&der[..], &[0]].concat(), b"correct-horse"),
+        Err(Error::Pkcs8Error(_))
+    ));
+
+    // openssl pkcs8 -topk8 -v2 des3, which isn't supported
+    let des3 = include_bytes!("pkcs8/x25519_pbes2_des3.der");
+    assert!(matches!(
+        Key::<Provider>::from_pkcs8_encrypted(des3, b"correct-horse"),
+        Err(Error::Pkcs8Error(message)) if message.contains("1.2.840.113549.3.7")
+    ));
+    // openssl pkcs8 -topk8 -v1 PBE-SHA1-3DES
+    let pbes1 = include_bytes!("pkcs8/x25519_pbes1_3des.der");
+    assert!(matches!(
+        Key::<Provider>::from_pkcs8_encrypted(pbes1, b"correct-horse"),
+        Err(Error::Pkcs8Error(_))
+    ));
+}
@@ -0,0 +1,232 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+#![cfg(feature = "keychain")]
+
+mod utils;
+
+use std::{any::Any, collections::BTreeMap, sync::Mutex, sync::Once};
+
+use keyring::credential::{Credential, CredentialApi, CredentialBuilderApi};
+use utils::provider::Provider;
+use vault::{
+    keychain::{self, KEYCHAIN_MAX_LEN},
+    BoxProvider, Error, Key,
+};
+
+/// the passwords of the in-memory keychain by service and account
+static STORE: Mutex<BTreeMap<(String, String), Vec<u8>>> = Mutex::new(BTreeMap::new());
+
+/// the service of the accounts whose keychain is locked
+const LOCKED: &str = "locked";
+
+/// a credential of the in-memory keychain, which, unlike the mock of `keyring`, persists across entries
+#[derive(Debug)]
+struct MemoryCredential {
+    service: String,
+    account: String,
+}
+
+impl MemoryCredential {
+    fn id(&self) -> keyring::Result<(String, String)> {
+        if self.service == LOCKED {
+            return Err(keyring::Error::NoStorageAccess("the keychain is locked".into()));
+        }
+        Ok((self.service.clone(), self.account.clone()))
+    }
+}
+
+impl CredentialApi for MemoryCredential {
+    fn set_secret(&self, secret: &[u8]) -> keyring::Result<()> {
+        STORE.lock().unwrap().insert(self.id()?, secret.to_vec());
+        Ok(())
+    }
+
+    fn get_secret(&self) -> keyring::Result<Vec<u8>> {
+        STORE
+            .lock()
+            .unwrap()
+            .get(&self.id()?)
+            .cloned()
+            .ok_or(keyring::Error::NoEntry)
+    }
+
+    fn delete_credential(&self) -> keyring::Result<()> {
+        STORE
+            .lock()
+            .unwrap()
+            .remove(&self.id()?)
+            .map(drop)
+            .ok_or(keyring::Error::NoEntry)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+struct MemoryBuilder;
+
+impl CredentialBuilderApi for MemoryBuilder {
+    fn build(&self, _target: Option<&str>, service: &str, user: &str) -> keyring::Result<Box<Credential>> {
+        Ok(Box::new(MemoryCredential {
+            service: service.to_owned(),
+            account: user.to_owned(),
+        }))
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// use the in-memory keychain.  Every test uses its own accounts, as the tests run in parallel.
+fn setup() {
+    static SETUP: Once = Once::new();
+    SETUP.call_once(|| keyring::set_default_credential_builder(Box::new(MemoryBuilder)));
+}
+
+fn stored(account: &str) -> Option<String> {
+    let store = STORE.lock().unwrap();
+    let password = store.get(&(String::from("vault"), account.to_owned()))?;
+    Some(String::from_utf8(password.clone()).unwrap())
+}
+
+fn set_stored(account: &str, password: &str) {
+    let mut store = STORE.lock().unwrap();
+    store.insert(
+        (String::from("vault"), account.to_owned()),
+        password.as_bytes().to_vec(),
+    );
+}
+
+fn not_found(result: vault::Result<impl std::fmt::Debug>, account: &str) {
+    match result {
+        Err(Error::KeychainNotFound {
+            service,
+            account: found,
+        }) => {
+            assert_eq!(service, "vault");
+            assert_eq!(found, account);
+        }
+        other => panic!("expected a missing key, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_keychain_roundtrip() {
+    setup();
+    let key = Key::<Provider>::random().unwrap();
+    keychain::store_key("vault", "roundtrip", &key).unwrap();
+    assert_eq!(stored("roundtrip").unwrap(), key.to_armored());
+    assert_eq!(keychain::load_key::<Provider>("vault", "roundtrip").unwrap(), key);
+
+    // storing again replaces the key
+    let other = Key::<Provider>::random().unwrap();
+    keychain::store_key("vault", "roundtrip", &other).unwrap();
+    assert_eq!(keychain::load_key::<Provider>("vault", "roundtrip").unwrap(), other);
+
+    keychain::delete_key("vault", "roundtrip").unwrap();
+    assert!(stored("roundtrip").is_none());
+    not_found(keychain::load_key::<Provider>("vault", "roundtrip"), "roundtrip");
+    not_found(keychain::delete_key("vault", "roundtrip"), "roundtrip");
+}
+
+#[test]
+fn test_keychain_verifies_stored_key() {
+    setup();
+    not_found(keychain::load_key::<Provider>("vault", "missing"), "missing");
+
+    // a key of 16 bytes for a provider with keys of 32 bytes
+    set_stored("short", "vaultkey1qqgqqqgzqvzq2ps8pqys5zcvp58q7egv07mq");
+    assert!(matches!(
+        keychain::load_key::<Provider>("vault", "short"),
+        Err(Error::InvalidKeyLength {
+            expected: 32,
+            actual: 16
+        })
+    ));
+
+    let key = Key::<Provider>::random().unwrap();
+    keychain::store_key("vault", "modified", &key).unwrap();
+    let mut armored = stored("modified").unwrap();
+    let last = if armored.ends_with('q') { "p" } else { "q" };
+    armored.replace_range(armored.len() - 1.., last);
+    set_stored("modified", &armored);
+    assert!(keychain::load_key::<Provider>("vault", "modified").is_err());
+
+    let mut store = STORE.lock().unwrap();
+    store.insert((String::from("vault"), String::from("binary")), vec![0xff; 4]);
+    drop(store);
+    assert!(matches!(
+        keychain::load_key::<Provider>("vault", "binary"),
+        Err(Error::KeychainError(_))
+    ));
+}
+
+/// a provider with keys of 1024 bytes, whose armored form is too long for the Windows Credential Manager
+struct LargeKeys;
+
+impl BoxProvider for LargeKeys {
+    type Error = Error;
+
+    fn box_key_len() -> usize {
+        1024
+    }
+
+    fn box_overhead() -> usize {
+        0
+    }
+
+    fn box_id() -> [u8; 4] {
+        *b"none"
+    }
+
+    fn box_seal(_key: &Key<Self>, _ad: &[u8], _data: &[u8]) -> vault::Result<Vec<u8>> {
+        Err(Error::crypto("seal", "Unsupported"))
+    }
+
+    fn box_open(_key: &Key<Self>, _ad: &[u8], _data: &[u8]) -> vault::Result<Vec<u8>> {
+        Err(Error::crypto("open", "Unsupported"))
+    }
+
+    fn random_buf(buf: &mut [u8]) -> vault::Result<()> {
+        Provider::random_buf(buf)
+    }
+}
+
+#[test]
+fn test_keychain_errors() {
+    setup();
+    let key = Key::<LargeKeys>::random().unwrap();
+    match keychain::store_key("vault", "large", &key) {
+        Err(Error::PayloadTooLarge { len, limit }) => {
+            assert_eq!(len as usize, key.to_armored().len());
+            assert_eq!(limit, KEYCHAIN_MAX_LEN);
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+    assert!(stored("large").is_none());
+
+    let key = Key::<Provider>::random().unwrap();
+    assert!(matches!(
+        keychain::store_key(LOCKED, "locked", &key),
+        Err(Error::KeychainError(_))
+    ));
+    assert!(matches!(
+        keychain::load_key::<Provider>(LOCKED, "locked"),
+        Err(Error::KeychainError(_))
+    ));
+    assert!(matches!(
+        keychain::delete_key(LOCKED, "locked"),
+        Err(Error::KeychainDeleteFailed(_))
+    ));
+}
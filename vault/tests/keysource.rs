@@ -0,0 +1,257 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+mod utils;
+
+use std::{fs, path::PathBuf};
+
+use utils::provider::Provider;
+use vault::{BoxProvider, Error, Key, KeyEncoding, MAX_KEY_SOURCE_LEN};
+
+/// the key bytes `0..32`
+fn key_bytes() -> Vec<u8> {
+    (0..32).collect()
+}
+
+const HEX: &str = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f";
+const BASE64: &str = "AAECAwQFBgcICQoLDA0ODxAREhMUFRYXGBkaGxwdHh8=";
+/// the uri-safe base64 of the key bytes `0xfb..=0xff` repeated
+const URI_SAFE: &str = "-_z9_v_7_P3-__v8_f7_-_z9_v_7_P3-__v8_f7_-_w=";
+
+/// a fresh path in the temporary directory
+fn temp_path(name: &str) -> PathBuf {
+    let mut suffix = [0; 8];
+    Provider::random_buf(&mut suffix).unwrap();
+    let suffix: String = suffix.iter().map(|b| format!("{:02x}", b)).collect();
+    std::env::temp_dir().join(format!("vault-{}-{}", name, suffix))
+}
+
+/// write `contents` to a fresh file
+fn temp_file(name: &str, contents: &[u8]) -> PathBuf {
+    let path = temp_path(name);
+    fs::write(&path, contents).unwrap();
+    path
+}
+
+fn expect_len(result: vault::Result<Key<Provider>>, actual: usize) {
+    match result {
+        Err(Error::InvalidKeyLength {
+            expected,
+            actual: found,
+        }) => {
+            assert_eq!(expected, 32);
+            assert_eq!(found, actual);
+        }
+        other => panic!("expected an invalid key length, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_key_from_env() {
+    let expected = Key::<Provider>::load(key_bytes()).unwrap();
+    for (var, value) in [
+        ("VAULT_TEST_KEY_HEX", HEX.to_owned()),
+        ("VAULT_TEST_KEY_UPPER_HEX", HEX.to_uppercase()),
+        ("VAULT_TEST_KEY_BASE64", BASE64.to_owned()),
+        ("VAULT_TEST_KEY_NEWLINE", format!("{}\n", HEX)),
+        ("VAULT_TEST_KEY_CRLF", format!("{}\r\n", BASE64)),
+    ] {
+        std::env::set_var(var, &value);
+        assert_eq!(Key::<Provider>::from_env(var).unwrap(), expected, "{}", var);
+        assert_eq!(std::env::var(var).unwrap(), value);
+    }
+
+    let key: Vec<u8> = (0..32).map(|i| 0xfb + i % 5).collect();
+    std::env::set_var("VAULT_TEST_KEY_URI_SAFE", URI_SAFE);
+    assert_eq!(
+        Key::<Provider>::from_env("VAULT_TEST_KEY_URI_SAFE").unwrap().bytes(),
+        &key[..]
+    );
+
+    // a single newline only
+    std::env::set_var("VAULT_TEST_KEY_NEWLINES", format!("{}\n\n", HEX));
+    assert!(Key::<Provider>::from_env("VAULT_TEST_KEY_NEWLINES").is_err());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_key_from_env_raw() {
+    use std::{ffi::OsString, os::unix::ffi::OsStringExt};
+
+    // raw bytes, which are neither hex nor base64
+    let key: Vec<u8> = (0..32).map(|i| 0x80 + i).collect();
+    std::env::set_var("VAULT_TEST_KEY_RAW", OsString::from_vec(key.clone()));
+    assert_eq!(
+        Key::<Provider>::from_env("VAULT_TEST_KEY_RAW").unwrap().bytes(),
+        &key[..]
+    );
+
+    // 32 hex digits are a raw key, not a hex key of 16 bytes
+    let digits = &HEX[..32];
+    std::env::set_var("VAULT_TEST_KEY_RAW_DIGITS", digits);
+    let key = Key::<Provider>::from_env("VAULT_TEST_KEY_RAW_DIGITS").unwrap();
+    assert_eq!(key.bytes(), digits.as_bytes());
+    expect_len(
+        Key::<Provider>::from_env_with("VAULT_TEST_KEY_RAW_DIGITS", KeyEncoding::Hex, false),
+        16,
+    );
+}
+
+#[test]
+fn test_key_from_env_with() {
+    let expected = Key::<Provider>::load(key_bytes()).unwrap();
+    std::env::set_var("VAULT_TEST_KEY_REMOVE", HEX);
+    let key = Key::<Provider>::from_env_with("VAULT_TEST_KEY_REMOVE", KeyEncoding::Hex, true).unwrap();
+    assert_eq!(key, expected);
+    assert!(std::env::var_os("VAULT_TEST_KEY_REMOVE").is_none());
+
+    // the variable is removed even if it doesn't hold a key
+    std::env::set_var("VAULT_TEST_KEY_REMOVE_INVALID", "not a key");
+    assert!(Key::<Provider>::from_env_with("VAULT_TEST_KEY_REMOVE_INVALID", KeyEncoding::Hex, true).is_err());
+    assert!(std::env::var_os("VAULT_TEST_KEY_REMOVE_INVALID").is_none());
+
+    match Key::<Provider>::from_env("VAULT_TEST_KEY_REMOVE") {
+        Err(Error::KeySourceError(message)) => assert!(message.contains("VAULT_TEST_KEY_REMOVE"), "{}", message),
+        other => panic!("unexpected result: {:?}", other),
+    }
+
+    std::env::set_var("VAULT_TEST_KEY_EXPLICIT", BASE64);
+    assert!(matches!(
+        Key::<Provider>::from_env_with("VAULT_TEST_KEY_EXPLICIT", KeyEncoding::Hex, false),
+        Err(Error::KeySourceError(_))
+    ));
+    assert_eq!(
+        Key::<Provider>::from_env_with("VAULT_TEST_KEY_EXPLICIT", KeyEncoding::Base64, false).unwrap(),
+        expected
+    );
+    expect_len(
+        Key::<Provider>::from_env_with("VAULT_TEST_KEY_EXPLICIT", KeyEncoding::Raw, false),
+        BASE64.len(),
+    );
+
+    std::env::set_var("VAULT_TEST_KEY_SHORT", &HEX[..32]);
+    expect_len(
+        Key::<Provider>::from_env_with("VAULT_TEST_KEY_SHORT", KeyEncoding::Hex, false),
+        16,
+    );
+    std::env::set_var(
+        "VAULT_TEST_KEY_LONG_BASE64",
+        "AAECAwQFBgcICQoLDA0ODxAREhMUFRYXGBkaGxwdHh8g",
+    );
+    expect_len(Key::<Provider>::from_env("VAULT_TEST_KEY_LONG_BASE64"), 33);
+
+    std::env::set_var("VAULT_TEST_KEY_LARGE", "0".repeat(MAX_KEY_SOURCE_LEN + 2));
+    assert!(matches!(
+        Key::<Provider>::from_env("VAULT_TEST_KEY_LARGE"),
+        Err(Error::PayloadTooLarge { len, limit: MAX_KEY_SOURCE_LEN }) if len as usize == MAX_KEY_SOURCE_LEN + 2
+    ));
+}
+
+#[test]
+fn test_key_from_file() {
+    let expected = Key::<Provider>::load(key_bytes()).unwrap();
+    for contents in [
+        HEX.to_owned(),
+        format!("{}\n", HEX),
+        format!("{}\r\n", HEX.to_uppercase()),
+    ] {
+        let path = temp_file("hex-key", contents.as_bytes());
+        assert_eq!(Key::<Provider>::from_hex_file(&path).unwrap(), expected);
+        assert_eq!(
+            Key::<Provider>::from_file_with(&path, KeyEncoding::Auto).unwrap(),
+            expected
+        );
+        fs::remove_file(path).unwrap();
+    }
+    let path = temp_file("base64-key", format!("{}\n", BASE64).as_bytes());
+    assert_eq!(
+        Key::<Provider>::from_file_with(&path, KeyEncoding::Base64).unwrap(),
+        expected
+    );
+    assert!(Key::<Provider>::from_hex_file(&path).is_err());
+    fs::remove_file(path).unwrap();
+
+    let path = temp_file("raw-key", &key_bytes());
+    assert_eq!(Key::<Provider>::from_raw_file(&path).unwrap(), expected);
+    fs::remove_file(path).unwrap();
+
+    // a newline after a raw key is stripped, one that is the last byte of the key is kept
+    let path = temp_file("raw-key-newline", &[&key_bytes()[..], b"\n"].concat());
+    assert_eq!(Key::<Provider>::from_raw_file(&path).unwrap(), expected);
+    fs::remove_file(path).unwrap();
+    let mut key = key_bytes();
+    key[31] = b'\n';
+    let path = temp_file("raw-key-last-newline", &key);
+    assert_eq!(Key::<Provider>::from_raw_file(&path).unwrap().bytes(), &key[..]);
+    fs::remove_file(path).unwrap();
+
+    let path = temp_file("short-raw-key", &key_bytes()[..16]);
+    expect_len(Key::<Provider>::from_raw_file(&path), 16);
+    fs::remove_file(path).unwrap();
+
+    let path = temp_file("large-key", &vec![b'0'; MAX_KEY_SOURCE_LEN * 2]);
+    assert!(matches!(
+        Key::<Provider>::from_hex_file(&path),
+        Err(Error::PayloadTooLarge { .. })
+    ));
+    fs::remove_file(&path).unwrap();
+
+    match Key::<Provider>::from_hex_file(&path) {
+        Err(Error::Io(e)) => {
+            assert_eq!(e.kind(), std::io::ErrorKind::NotFound);
+            assert!(e.to_string().contains("large-key"), "{}", e);
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn test_key_from_fd() {
+    use std::os::unix::io::OwnedFd;
+
+    let expected = Key::<Provider>::load(key_bytes()).unwrap();
+    let path = temp_file("fd-key", format!("{}\n", HEX).as_bytes());
+    let fd = OwnedFd::from(fs::File::open(&path).unwrap());
+    assert_eq!(Key::<Provider>::from_fd(fd).unwrap(), expected);
+
+    let fd = OwnedFd::from(fs::File::open(&path).unwrap());
+    // the 64 hex digits are valid base64 of 48 bytes
+    expect_len(Key::<Provider>::from_fd_with(fd, KeyEncoding::Base64), 48);
+    fs::remove_file(path).unwrap();
+}
+
+#[cfg(feature = "test-utils")]
+#[test]
+fn test_key_source_wipes_temporaries() {
+    use vault::test_utils::take_wiped_buffers;
+
+    take_wiped_buffers();
+    let path = temp_file("wiped-key", format!("{}\n", HEX).as_bytes());
+    Key::<Provider>::from_hex_file(&path).unwrap();
+    fs::remove_file(path).unwrap();
+    // the read buffer
+    let wiped = take_wiped_buffers();
+    assert_eq!(wiped.len(), 1);
+    assert_eq!(wiped[0], vec![0; MAX_KEY_SOURCE_LEN + 1]);
+
+    // the base64 key, which has only 27 bytes, and the value of the variable
+    std::env::set_var("VAULT_TEST_KEY_WIPED", "AAECAwQFBgcICQoLDA0ODxAREhMUFRYXGBka");
+    expect_len(Key::<Provider>::from_env("VAULT_TEST_KEY_WIPED"), 27);
+    let wiped = take_wiped_buffers();
+    assert_eq!(wiped.iter().map(Vec::len).collect::<Vec<_>>(), [27, 36]);
+    assert!(wiped.iter().flatten().all(|b| *b == 0));
+
+    // the value is wiped on errors too
+    std::env::set_var("VAULT_TEST_KEY_WIPED", "not a key");
+    assert!(Key::<Provider>::from_env_with("VAULT_TEST_KEY_WIPED", KeyEncoding::Hex, true).is_err());
+    assert_eq!(take_wiped_buffers(), [vec![0; 9]]);
+}
@@ -0,0 +1,155 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+#![cfg(feature = "key-exchange")]
+
+mod utils;
+
+use std::convert::TryFrom;
+
+use utils::provider::Provider;
+use vault::{
+    kx::{derive_shared, PublicKey, StaticSecret},
+    BoxProvider, Error, Key,
+};
+
+fn hex(s: &str) -> [u8; 32] {
+    <[u8; 32]>::try_from(utils::hex(s).as_slice()).unwrap()
+}
+
+#[test]
+fn test_kx_rfc7748() {
+    // section 5.2
+    let vectors = [
+        (
+            "a546e36bf0527c9d3b16154b82465edd62144c0ac1fc5a18506a2244ba449ac4",
+            "e6db6867583030db3594c1a424b15f7c726624ec26b3353b10a903a6d0ab1c4c",
+            "c3da55379de9c6908e94ea4df28d084f32eccf03491c71f754b4075577a28552",
+        ),
+        (
+            "4b66e9d4d1b4673c5ad22691957d6af5c11b6421e0ea01d42ca4169e7918ba0d",
+            "e5210f12786811d3f4b7959d0538ae2c31dbe7106fc03c3efc4cd549c715a493",
+            "95cbde9476e8907d7aade45cb4b873f88b595a68799fa152e6f8f7647aac7957",
+        ),
+    ];
+    for (scalar, u, expected) in vectors.iter() {
+        let shared = StaticSecret::from_bytes(hex(scalar))
+            .diffie_hellman(&PublicKey::from_bytes(hex(u)))
+            .unwrap();
+        assert_eq!(*shared, hex(expected));
+    }
+
+    // the iterations of section 5.2
+    let (mut k, mut u) = (
+        hex(&format!("09{}", "00".repeat(31))),
+        hex(&format!("09{}", "00".repeat(31))),
+    );
+    for i in 1..=1000 {
+        let next = *StaticSecret::from_bytes(k)
+            .diffie_hellman(&PublicKey::from_bytes(u))
+            .unwrap();
+        u = k;
+        k = next;
+        if i == 1 {
+            assert_eq!(
+                k,
+                hex("422c8e7a6227d7bca1350b3e2bb7279f7897b87bb6854b783c60e80311ae3079")
+            );
+        }
+    }
+    assert_eq!(
+        k,
+        hex("684cf59ba83309552800ef566f2f4d3c1c3887c49360e3875f2eb94d99532c51")
+    );
+
+    // section 6.1
+    let alice = StaticSecret::from_bytes(hex("77076d0a7318a57d3c16c17251b26645df4c2f87ebc0992ab177fba51db92c2a"));
+    let bob = StaticSecret::from_bytes(hex("5dab087e624a8a4b79e17f8b83800ee66f3bb1292618b6fd1c2f8b27ff88e0eb"));
+    assert_eq!(
+        alice.public_key(),
+        PublicKey::from_bytes(hex("8520f0098930a754748b7ddcb43ef75a0dbf3a0d26381af4eba4a98eaa9b4e6a"))
+    );
+    assert_eq!(
+        bob.public_key(),
+        PublicKey::from_bytes(hex("de9edb7d7b7dc1b4d35b61c2ece435373f8343c85b78674dadfc7e146f882b4f"))
+    );
+    let shared = hex("4a5d9d5ba4ce2de1728e3bf480350f25e07e21c947d19e3376f09b3c1e161742");
+    assert_eq!(*alice.diffie_hellman(&bob.public_key()).unwrap(), shared);
+    assert_eq!(*bob.diffie_hellman(&alice.public_key()).unwrap(), shared);
+}
+
+#[test]
+fn test_kx_derive_shared() {
+    let alice = StaticSecret::random::<Provider>().unwrap();
+    let bob = StaticSecret::random::<Provider>().unwrap();
+
+    // both sides derive the same key
+    let for_alice: Key<Provider> = derive_shared(&alice, &bob.public_key(), b"sync").unwrap();
+    let for_bob: Key<Provider> = derive_shared(&bob, &alice.public_key(), b"sync").unwrap();
+    assert_eq!(for_alice, for_bob);
+    assert_eq!(for_alice.bytes().len(), Provider::box_key_len());
+    let sealed = Provider::box_seal(&for_alice, b"ad", b"secret").unwrap();
+    assert_eq!(Provider::box_open(&for_bob, b"ad", &sealed).unwrap(), b"secret");
+
+    // the context separates the keys
+    let other: Key<Provider> = derive_shared(&alice, &bob.public_key(), b"backup").unwrap();
+    assert_ne!(for_alice, other);
+    let empty: Key<Provider> = derive_shared(&alice, &bob.public_key(), b"").unwrap();
+    assert_ne!(for_alice, empty);
+    assert_ne!(other, empty);
+
+    // so does a third party
+    let carol = StaticSecret::random::<Provider>().unwrap();
+    let with_carol: Key<Provider> = derive_shared(&alice, &carol.public_key(), b"sync").unwrap();
+    assert_ne!(for_alice, with_carol);
+
+    // the key isn't the raw shared secret
+    assert_ne!(for_alice.bytes(), &alice.diffie_hellman(&bob.public_key()).unwrap()[..]);
+}
+
+#[test]
+fn test_kx_low_order() {
+    let secret = StaticSecret::random::<Provider>().unwrap();
+    let mut one = [0; 32];
+    one[0] = 1;
+    // 0, 1 and a point of order 8
+    let points = [
+        [0; 32],
+        one,
+        hex("e0eb7a7c3b41b8ae1656e3faf19fc46ada098deb9c32b1fd866205165f49b800"),
+    ];
+    for point in points.iter() {
+        let public = PublicKey::from_bytes(*point);
+        assert!(matches!(
+            secret.diffie_hellman(&public),
+            Err(Error::CryptoError { op: "key exchange", .. })
+        ));
+        assert!(derive_shared::<Provider>(&secret, &public, b"sync").is_err());
+    }
+}
+
+#[test]
+fn test_kx_bytes() {
+    let secret = StaticSecret::random::<Provider>().unwrap();
+    assert_eq!(format!("{:?}", secret), "StaticSecret(..)");
+
+    let restored = StaticSecret::try_from(secret.bytes()).unwrap();
+    assert_eq!(restored.public_key(), secret.public_key());
+    let public = PublicKey::try_from(secret.public_key().as_ref()).unwrap();
+    assert_eq!(public, secret.public_key());
+
+    let expected = Error::InvalidKeyLength {
+        expected: 32,
+        actual: 31,
+    };
+    assert_eq!(StaticSecret::try_from(&[0; 31][..]).err().unwrap(), expected);
+    assert_eq!(PublicKey::try_from(&[0; 31][..]).err().unwrap(), expected);
+}
@@ -0,0 +1,213 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+#![cfg(feature = "password-kdf")]
+
+mod utils;
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use utils::provider::Provider;
+use vault::{
+    Clock, DeleteRequest, Error, Id, KdfParams, ListResult, LockableVault, ReadRequest, ReadResult, RecordHint, Store,
+    WriteRequest,
+};
+
+/// cheap parameters to keep the tests fast.
+const PARAMS: KdfParams = KdfParams {
+    memory_cost: 32,
+    iterations: 1,
+    parallelism: 1,
+};
+
+#[derive(Default)]
+struct Entries(HashMap<Vec<u8>, Vec<u8>>);
+
+impl Store for Entries {
+    fn list(&self) -> vault::Result<ListResult> {
+        Ok(ListResult::new(self.0.keys().cloned().collect()))
+    }
+
+    fn read(&self, request: ReadRequest) -> vault::Result<Option<ReadResult>> {
+        let id: Vec<u8> = request.into();
+        Ok(self.0.get(&id).map(|data| ReadResult::new(id, data.clone())))
+    }
+
+    fn write(&mut self, request: WriteRequest) -> vault::Result<()> {
+        let (id, data) = request.into();
+        self.0.insert(id, data);
+        Ok(())
+    }
+
+    fn delete(&mut self, request: DeleteRequest) -> vault::Result<()> {
+        let id: Vec<u8> = request.into();
+        self.0.remove(&id);
+        Ok(())
+    }
+}
+
+struct TestClock(Mutex<SystemTime>);
+
+impl Clock for TestClock {
+    fn now(&self) -> SystemTime {
+        *self.0.lock().unwrap()
+    }
+}
+
+impl TestClock {
+    fn advance(&self, by: Duration) {
+        *self.0.lock().unwrap() += by;
+    }
+}
+
+fn hint() -> RecordHint {
+    RecordHint::new(b"hint").unwrap()
+}
+
+fn assert_locked(vault: &mut LockableVault<Provider, Entries>, id: Id) {
+    assert!(vault.is_locked());
+    assert_eq!(vault.read(id).err().unwrap(), Error::VaultLocked { holder_pid: None });
+    assert_eq!(
+        vault.write(b"data", hint()).err().unwrap(),
+        Error::VaultLocked { holder_pid: None }
+    );
+    assert_eq!(vault.revoke(id).err().unwrap(), Error::VaultLocked { holder_pid: None });
+    assert_eq!(vault.records().err().unwrap(), Error::VaultLocked { holder_pid: None });
+    assert_eq!(vault.touch().err().unwrap(), Error::VaultLocked { holder_pid: None });
+}
+
+#[test]
+fn test_lockable_vault() {
+    let owner = Id::random::<Provider>().unwrap();
+    let mut vault = LockableVault::<Provider, _>::create(Entries::default(), owner, b"password", PARAMS).unwrap();
+    assert!(!vault.is_locked());
+    let id = vault.write(b"secret", hint()).unwrap();
+    assert_eq!(vault.read(id).unwrap(), b"secret");
+
+    vault.lock();
+    assert_locked(&mut vault, id);
+    // the store stays open
+    assert_eq!(vault.store().list().unwrap().ids().len(), 4);
+
+    assert_eq!(vault.unlock(b"wrong").err().unwrap(), Error::WrongPassword);
+    assert_locked(&mut vault, id);
+    vault.unlock(b"password").unwrap();
+    assert_eq!(vault.read(id).unwrap(), b"secret");
+    assert_eq!(vault.records().unwrap(), [(id, hint())]);
+
+    // the vault opens locked
+    let mut vault = LockableVault::<Provider, _>::open(vault.into_store(), owner).unwrap();
+    assert_locked(&mut vault, id);
+    vault.unlock(b"password").unwrap();
+    vault.revoke(id).unwrap();
+    assert!(vault.records().unwrap().is_empty());
+
+    assert!(matches!(
+        LockableVault::<Provider, _>::create(vault.into_store(), owner, b"password", PARAMS),
+        Err(Error::InvalidEntry { .. })
+    ));
+    assert!(matches!(
+        LockableVault::<Provider, _>::open(Entries::default(), owner),
+        Err(Error::InvalidEntry { .. })
+    ));
+}
+
+#[test]
+fn test_lockable_vault_kdf_bounds() {
+    let owner = Id::random::<Provider>().unwrap();
+    let vault = LockableVault::<Provider, _>::create(Entries::default(), owner, b"password", PARAMS).unwrap();
+    let mut store = vault.into_store();
+
+    // a lock entry asking for 4 TiB of memory is refused before anything is derived
+    let entry = store.0.get_mut(&b"vault lock"[..]).unwrap();
+    entry[16..20].copy_from_slice(&u32::MAX.to_be_bytes());
+    let mut vault = LockableVault::<Provider, _>::open(store, owner).unwrap();
+    assert!(matches!(vault.unlock(b"password"), Err(Error::InvalidEntry { .. })));
+    assert!(vault.is_locked());
+}
+
+#[test]
+fn test_lockable_vault_auto_lock() {
+    let owner = Id::random::<Provider>().unwrap();
+    let clock = Arc::new(TestClock(Mutex::new(UNIX_EPOCH + Duration::from_secs(1_600_000_000))));
+    let mut vault = LockableVault::<Provider, _>::create(Entries::default(), owner, b"password", PARAMS)
+        .unwrap()
+        .with_clock(clock.clone());
+    vault.lock_after(Some(Duration::from_secs(60)));
+    let id = vault.write(b"secret", hint()).unwrap();
+
+    // every operation and `touch` restart the idle time
+    clock.advance(Duration::from_secs(45));
+    vault.read(id).unwrap();
+    clock.advance(Duration::from_secs(45));
+    vault.touch().unwrap();
+    clock.advance(Duration::from_secs(45));
+    assert!(!vault.is_locked());
+
+    clock.advance(Duration::from_secs(15));
+    assert!(vault.is_locked());
+    assert_locked(&mut vault, id);
+    vault.unlock(b"password").unwrap();
+    assert_eq!(vault.read(id).unwrap(), b"secret");
+
+    vault.lock_after(None);
+    clock.advance(Duration::from_secs(3600));
+    assert!(!vault.is_locked());
+}
+
+#[cfg(feature = "test-utils")]
+#[test]
+fn test_lockable_vault_wipes_keys() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let owner = Id::random::<Provider>().unwrap();
+    let store = LockableVault::<Provider, _>::create(Entries::default(), owner, b"password", PARAMS)
+        .unwrap()
+        .into_store();
+    let mut vault = LockableVault::<Provider, _>::open(store, owner).unwrap();
+    let dropped = Arc::new(AtomicUsize::new(0));
+    let with_bytes = Arc::new(AtomicUsize::new(0));
+    let (counter, keys) = (dropped.clone(), with_bytes.clone());
+    vault.on_key_drop(move |bytes| {
+        counter.fetch_add(1, Ordering::SeqCst);
+        if bytes.iter().any(|b| *b != 0) {
+            keys.fetch_add(1, Ordering::SeqCst);
+        }
+    });
+
+    vault.unlock(b"password").unwrap();
+    let id = vault.write(b"secret", hint()).unwrap();
+    vault.read(id).unwrap();
+    // the vault holds a single key while it's unlocked
+    assert_eq!(dropped.load(Ordering::SeqCst), 0);
+    vault.lock();
+    assert_eq!(dropped.load(Ordering::SeqCst), 1);
+    // the hook saw the key before it was wiped
+    assert_eq!(with_bytes.load(Ordering::SeqCst), 1);
+    vault.lock();
+    assert_eq!(dropped.load(Ordering::SeqCst), 1);
+
+    // the key of a wrong password is dropped too
+    assert_eq!(vault.unlock(b"wrong").err().unwrap(), Error::WrongPassword);
+    assert_eq!(dropped.load(Ordering::SeqCst), 2);
+    assert_locked(&mut vault, id);
+
+    // unlocking again replaces the key
+    vault.unlock(b"password").unwrap();
+    vault.unlock(b"password").unwrap();
+    assert_eq!(dropped.load(Ordering::SeqCst), 3);
+    drop(vault);
+    assert_eq!(dropped.load(Ordering::SeqCst), 4);
+}
@@ -0,0 +1,154 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+mod utils;
+
+use std::time::{Duration, SystemTime};
+
+use utils::provider::Provider;
+use vault::{BoxProvider, Error, GuardedOpen, Key, LockoutPolicy, LockoutState};
+
+fn policy() -> LockoutPolicy {
+    LockoutPolicy {
+        threshold: 3,
+        base_delay: Duration::from_secs(2),
+        max_delay: Duration::from_secs(10),
+    }
+}
+
+fn seconds(secs: u64) -> SystemTime {
+    SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000 + secs)
+}
+
+#[test]
+fn test_lockout_backoff() {
+    let key = Key::<Provider>::random().unwrap();
+    let wrong = Key::<Provider>::random().unwrap();
+    let sealed = Provider::box_seal(&key, b"ad", b"some data").unwrap();
+    let mut guard = GuardedOpen::new(policy());
+
+    // guess wrong passphrases until the key is locked out
+    for _ in 0..2 {
+        assert!(matches!(
+            guard.open_at(&wrong, b"ad", &sealed, seconds(0)),
+            Err(Error::AuthenticationFailed)
+        ));
+    }
+    assert_eq!(
+        guard.open_at(&wrong, b"ad", &sealed, seconds(0)).err().unwrap(),
+        Error::TooManyAttempts {
+            retry_after: Duration::from_secs(2)
+        }
+    );
+    assert_eq!(guard.state().failures(&wrong.fingerprint()), 3);
+
+    // while locked the box isn't opened and the failure doesn't count
+    assert_eq!(
+        guard.open_at(&wrong, b"ad", &sealed, seconds(1)).err().unwrap(),
+        Error::TooManyAttempts {
+            retry_after: Duration::from_secs(1)
+        }
+    );
+    assert_eq!(guard.state().failures(&wrong.fingerprint()), 3);
+
+    // every further failure doubles the delay up to the maximum
+    let mut now = 2;
+    for delay in [4, 8, 10, 10].iter() {
+        assert_eq!(
+            guard.open_at(&wrong, b"ad", &sealed, seconds(now)).err().unwrap(),
+            Error::TooManyAttempts {
+                retry_after: Duration::from_secs(*delay)
+            }
+        );
+        now += delay;
+    }
+
+    // other keys aren't locked
+    assert_eq!(guard.open_at(&key, b"ad", &sealed, seconds(now)).unwrap(), b"some data");
+}
+
+#[test]
+fn test_lockout_reset_on_success() {
+    let key = Key::<Provider>::random().unwrap();
+    let sealed = Provider::box_seal(&key, b"ad", b"some data").unwrap();
+    let mut guard = GuardedOpen::new(policy());
+
+    for _ in 0..2 {
+        assert!(guard.open_at(&key, b"other ad", &sealed, seconds(0)).is_err());
+    }
+    assert_eq!(guard.state().failures(&key.fingerprint()), 2);
+    assert_eq!(guard.open_at(&key, b"ad", &sealed, seconds(0)).unwrap(), b"some data");
+    assert_eq!(guard.state().failures(&key.fingerprint()), 0);
+
+    // the count starts over
+    for _ in 0..2 {
+        assert!(matches!(
+            guard.open_at(&key, b"other ad", &sealed, seconds(0)),
+            Err(Error::AuthenticationFailed)
+        ));
+    }
+
+    // a lockout can be lifted by the caller
+    assert!(matches!(
+        guard.open_at(&key, b"other ad", &sealed, seconds(0)),
+        Err(Error::TooManyAttempts { .. })
+    ));
+    guard.state_mut().reset(&key.fingerprint());
+    assert_eq!(guard.open_at(&key, b"ad", &sealed, seconds(0)).unwrap(), b"some data");
+}
+
+#[test]
+fn test_lockout_malformed_not_counted() {
+    let key = Key::<Provider>::random().unwrap();
+    let mut guard = GuardedOpen::new(policy());
+
+    for _ in 0..10 {
+        assert!(matches!(
+            guard.open_at(&key, b"ad", b"short", seconds(0)),
+            Err(Error::MalformedCiphertext { .. })
+        ));
+    }
+    assert_eq!(guard.state().failures(&key.fingerprint()), 0);
+    assert_eq!(guard.state().locked_until(&key.fingerprint()), None);
+}
+
+#[test]
+fn test_lockout_state_persistence() {
+    let key = Key::<Provider>::random().unwrap();
+    let wrong = Key::<Provider>::random().unwrap();
+    let sealed = Provider::box_seal(&key, b"ad", b"some data").unwrap();
+    let mut guard = GuardedOpen::new(policy());
+
+    for _ in 0..3 {
+        assert!(guard.open_at(&wrong, b"ad", &sealed, seconds(0)).is_err());
+    }
+    assert!(guard.open_at(&key, b"other ad", &sealed, seconds(0)).is_err());
+
+    let json = serde_json::to_string(guard.state()).unwrap();
+    let state: LockoutState = serde_json::from_str(&json).unwrap();
+    assert_eq!(&state, guard.state());
+    assert_eq!(state.failures(&wrong.fingerprint()), 3);
+    assert_eq!(state.locked_until(&wrong.fingerprint()), Some(seconds(2)));
+
+    // the lockout survives the restart
+    let mut restored = GuardedOpen::<Provider>::with_state(policy(), state);
+    assert_eq!(
+        restored.open_at(&wrong, b"ad", &sealed, seconds(1)).err().unwrap(),
+        Error::TooManyAttempts {
+            retry_after: Duration::from_secs(1)
+        }
+    );
+    assert!(matches!(
+        restored.open_at(&wrong, b"ad", &sealed, seconds(2)),
+        Err(Error::TooManyAttempts { retry_after }) if retry_after == Duration::from_secs(4)
+    ));
+    assert_eq!(restored.state().failures(&key.fingerprint()), 1);
+}
@@ -0,0 +1,89 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+mod utils;
+
+use utils::provider::Provider;
+use vault::{Authenticate, BoxProvider, Error, Key, Tag, Verify};
+
+struct Metadata(Vec<u8>);
+
+impl AsRef<[u8]> for Metadata {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl Authenticate<Tag> for Metadata {}
+impl Verify for Metadata {}
+
+#[test]
+fn test_mac_roundtrip() {
+    let key = Key::<Provider>::random().unwrap();
+    let meta = Metadata(b"size=1024;created=1600000000".to_vec());
+
+    let mac: Tag = meta.authenticate(&key, b"record 1").unwrap();
+    assert_eq!(mac.len(), 32);
+    assert_eq!(
+        mac.as_bytes(),
+        &Provider::box_mac(&key, b"record 1", &meta.0).unwrap()[..]
+    );
+    meta.verify(&key, b"record 1", mac.as_bytes()).unwrap();
+}
+
+#[test]
+fn test_mac_cross_key() {
+    let key = Key::<Provider>::random().unwrap();
+    let other = Key::<Provider>::random().unwrap();
+    let mac = Provider::box_mac(&key, b"ad", b"label").unwrap();
+
+    assert!(matches!(
+        Provider::box_verify_mac(&other, b"ad", b"label", &mac),
+        Err(Error::AuthenticationFailed)
+    ));
+}
+
+#[test]
+fn test_mac_binds_ad_and_data() {
+    let key = Key::<Provider>::random().unwrap();
+    let mac = Provider::box_mac(&key, b"ad", b"label").unwrap();
+
+    assert!(Provider::box_verify_mac(&key, b"other ad", b"label", &mac).is_err());
+    assert!(Provider::box_verify_mac(&key, b"ad", b"other label", &mac).is_err());
+    // the length of the AD is part of the MAC, so bytes can't move between the AD and the data
+    assert!(Provider::box_verify_mac(&key, b"adl", b"abel", &mac).is_err());
+}
+
+#[test]
+fn test_mac_truncated() {
+    let key = Key::<Provider>::random().unwrap();
+    let mac = Provider::box_mac(&key, b"ad", b"label").unwrap();
+
+    for len in &[0, 1, 16, 31] {
+        assert!(Provider::box_verify_mac(&key, b"ad", b"label", &mac[..*len]).is_err());
+    }
+    assert!(Provider::box_verify_mac(&key, b"ad", b"label", &[&mac[..], &[0]].concat()).is_err());
+
+    let mut flipped = mac.clone();
+    flipped[31] ^= 1;
+    assert!(Provider::box_verify_mac(&key, b"ad", b"label", &flipped).is_err());
+}
+
+#[test]
+fn test_mac_is_not_a_tag() {
+    let key = Key::<Provider>::random().unwrap();
+
+    // a MAC can't stand in for the tag of a box of the same data
+    let (sealed, tag) = Provider::box_seal_detached(&key, b"ad", b"label").unwrap();
+    let mac = Provider::box_mac(&key, b"ad", b"label").unwrap();
+    assert!(Provider::box_open_detached(&key, b"ad", &sealed, &Tag::from(&mac[..16])).is_err());
+    assert!(Provider::box_verify_mac(&key, b"ad", b"label", tag.as_bytes()).is_err());
+}
@@ -0,0 +1,161 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+mod utils;
+
+use std::convert::Infallible;
+
+use utils::provider::Provider;
+use vault::{providers::CommittingBox, BoxProvider, Decrypt, Encrypt, Error, Key};
+
+#[derive(Debug)]
+struct Plain(Vec<u8>);
+struct Sealed(Vec<u8>);
+
+impl AsRef<[u8]> for Plain {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl AsRef<[u8]> for Sealed {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl From<Vec<u8>> for Sealed {
+    fn from(data: Vec<u8>) -> Self {
+        Self(data)
+    }
+}
+
+impl From<Vec<u8>> for Plain {
+    fn from(data: Vec<u8>) -> Self {
+        Self(data)
+    }
+}
+
+impl Encrypt<Sealed> for Plain {}
+impl Decrypt<Infallible, Plain> for Sealed {}
+
+fn open<P: BoxProvider>(data: &[u8], key: &Key<P>, ad: &[u8]) -> vault::Result<Plain> {
+    Sealed(data.to_vec()).decrypt(key, ad)
+}
+
+/// check that `Decrypt` tells truncated data, tampered boxes and wrong keys apart for the provider `P`
+fn check_errors<P: BoxProvider>() {
+    let key = Key::<P>::random().unwrap();
+    let sealed = Plain(b"some data".to_vec()).encrypt(&key, b"ad").unwrap().0;
+
+    for len in 0..P::box_overhead() {
+        match open(&sealed[..len], &key, b"ad") {
+            Err(Error::MalformedCiphertext { expected_min, got }) => {
+                assert_eq!(expected_min, P::box_overhead());
+                assert_eq!(got, len);
+            }
+            other => panic!("unexpected result for {} bytes: {:?}", len, other),
+        }
+    }
+
+    for i in 0..sealed.len() {
+        let mut tampered = sealed.clone();
+        tampered[i] ^= 0x80;
+        assert!(matches!(open(&tampered, &key, b"ad"), Err(Error::AuthenticationFailed)));
+    }
+
+    let other = Key::<P>::random().unwrap();
+    assert!(matches!(open(&sealed, &other, b"ad"), Err(Error::AuthenticationFailed)));
+    assert!(matches!(
+        open(&sealed, &key, b"other ad"),
+        Err(Error::AuthenticationFailed)
+    ));
+
+    // the in place and the buffer reusing variants check the length the same way
+    let mut buf = sealed[..P::box_overhead() - 1].to_vec();
+    assert!(matches!(
+        Sealed::decrypt_in_place(&key, b"ad", &mut buf),
+        Err(Error::MalformedCiphertext { .. })
+    ));
+    let mut out = Vec::new();
+    assert!(matches!(
+        Sealed(sealed[..1].to_vec()).decrypt_into(&key, b"ad", &mut out),
+        Err(Error::MalformedCiphertext { got: 1, .. })
+    ));
+}
+
+#[test]
+fn test_malformed_test_provider() {
+    // the provider of the tests doesn't check the length itself
+    check_errors::<Provider>();
+    check_errors::<CommittingBox<Provider>>();
+}
+
+#[cfg(feature = "provider-aes-gcm")]
+#[test]
+fn test_malformed_aes_gcm() {
+    check_errors::<vault::providers::AesGcm256>();
+}
+
+#[cfg(feature = "provider-xchacha")]
+#[test]
+fn test_malformed_xchacha() {
+    check_errors::<vault::providers::XChaChaPoly>();
+}
+
+#[cfg(feature = "provider-siv")]
+#[test]
+fn test_malformed_siv() {
+    check_errors::<vault::providers::AesGcmSiv>();
+}
+
+#[cfg(feature = "provider-ring")]
+#[test]
+fn test_malformed_ring() {
+    use vault::providers::{RingAesGcm, RingChaCha, RingProvider};
+    check_errors::<RingProvider<RingAesGcm>>();
+    check_errors::<RingProvider<RingChaCha>>();
+}
+
+#[cfg(feature = "provider-sodium")]
+#[test]
+fn test_malformed_sodium() {
+    check_errors::<vault::providers::Sodium>();
+}
+
+#[cfg(all(feature = "aead-interop", feature = "provider-xchacha"))]
+#[test]
+fn test_malformed_rust_crypto() {
+    check_errors::<vault::providers::RustCryptoBox<vault::providers::RustChaCha>>();
+}
+
+#[test]
+fn test_malformed_direct_open() {
+    // the in-tree providers check the length when they are called directly as well
+    let key = Key::<CommittingBox<Provider>>::random().unwrap();
+    assert!(matches!(
+        CommittingBox::<Provider>::box_open(&key, b"ad", &[0; 4]),
+        Err(Error::MalformedCiphertext { got: 4, .. })
+    ));
+
+    #[cfg(feature = "provider-xchacha")]
+    {
+        use vault::providers::XChaChaPoly;
+        let key = Key::<XChaChaPoly>::random().unwrap();
+        assert!(matches!(
+            XChaChaPoly::box_open(&key, b"ad", &[0; 39]),
+            Err(Error::MalformedCiphertext {
+                expected_min: 40,
+                got: 39
+            })
+        ));
+    }
+}
@@ -0,0 +1,134 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+#![cfg(feature = "test-utils")]
+
+use std::{
+    collections::hash_map::DefaultHasher,
+    convert::Infallible,
+    hash::{Hash, Hasher},
+};
+
+use vault::{test_utils::TestProvider, BoxProvider, Decrypt, Encrypt, Error, Key, KeyMeta};
+
+struct Plain(Vec<u8>);
+struct Sealed(Vec<u8>);
+
+impl AsRef<[u8]> for Plain {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl AsRef<[u8]> for Sealed {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl From<Vec<u8>> for Plain {
+    fn from(data: Vec<u8>) -> Self {
+        Self(data)
+    }
+}
+
+impl From<Vec<u8>> for Sealed {
+    fn from(data: Vec<u8>) -> Self {
+        Self(data)
+    }
+}
+
+impl Encrypt<Sealed> for Plain {}
+impl Decrypt<Infallible, Plain> for Sealed {}
+
+fn meta(version: u32) -> KeyMeta {
+    KeyMeta {
+        id: [7; 16],
+        version,
+        created_at: 1_600_000_000,
+    }
+}
+
+fn hash(key: &Key<TestProvider>) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    key.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[test]
+fn test_meta_ignored_by_eq_and_hash() {
+    let key = Key::<TestProvider>::random().unwrap();
+    let with_meta = key.clone().with_meta(meta(1));
+
+    assert_eq!(key.meta(), None);
+    assert_eq!(with_meta.meta(), Some(&meta(1)));
+    assert_eq!(with_meta.clone().meta(), Some(&meta(1)));
+    assert_eq!(key, with_meta);
+    assert_eq!(hash(&key), hash(&with_meta));
+}
+
+#[test]
+fn test_meta_ad_binding() {
+    let key = Key::<TestProvider>::random().unwrap().with_meta(meta(1));
+    let plain = Plain(b"some data".to_vec());
+
+    let sealed: Sealed = plain.encrypt_with_meta(&key, b"ad").unwrap();
+    assert_eq!(&sealed.0[..16], &[7; 16]);
+    assert_eq!(&sealed.0[16..KeyMeta::HEADER_LEN], &1u32.to_be_bytes());
+    assert_eq!(
+        sealed.0.len(),
+        KeyMeta::HEADER_LEN + TestProvider::box_overhead() + plain.0.len()
+    );
+
+    let opened = sealed.decrypt_with_meta(&key, b"ad").unwrap();
+    assert_eq!(opened.0, plain.0);
+
+    // the header is authenticated as part of the AD.
+    let stripped = Sealed(sealed.0[KeyMeta::HEADER_LEN..].to_vec());
+    assert!(stripped.decrypt(&key, b"ad").is_err());
+    assert!(sealed.decrypt_with_meta(&key, b"other ad").is_err());
+}
+
+#[test]
+fn test_meta_mismatch() {
+    let key = Key::<TestProvider>::random().unwrap().with_meta(meta(1));
+    let sealed: Sealed = Plain(b"some data".to_vec()).encrypt_with_meta(&key, b"").unwrap();
+
+    // same bytes but a newer version is detected before opening the box.
+    let rotated = key.clone().with_meta(meta(2));
+    match sealed.decrypt_with_meta(&rotated, b"") {
+        Err(Error::KeyMismatch { id, version }) => {
+            assert_eq!(id, "07".repeat(16));
+            assert_eq!(version, 1);
+        }
+        other => panic!("unexpected result: {:?}", other.map(|p| p.0)),
+    }
+    assert_eq!(TestProvider::open_count(), 0);
+
+    // a different key claiming the same meta fails at the box.
+    let other = Key::<TestProvider>::random().unwrap().with_meta(meta(1));
+    assert!(matches!(
+        sealed.decrypt_with_meta(&other, b""),
+        Err(Error::AuthenticationFailed)
+    ));
+    assert_eq!(TestProvider::open_count(), 1);
+
+    // a key without meta can't be used.
+    let bare = Key::<TestProvider>::random().unwrap();
+    assert!(matches!(
+        sealed.decrypt_with_meta(&bare, b""),
+        Err(Error::InterfaceError)
+    ));
+    assert!(matches!(
+        Plain(vec![]).encrypt_with_meta(&bare, b""),
+        Err(Error::InterfaceError)
+    ));
+}
@@ -0,0 +1,252 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+mod utils;
+
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    sync::{Mutex, MutexGuard},
+    time::{Duration, SystemTime},
+};
+
+use utils::provider::Provider;
+use vault::{
+    metrics::{self, clear_metrics, set_metrics, InMemoryMetrics},
+    rekey_all, ConcurrentVault, DBView, DBWriter, Decrypt, DeleteRequest, Encrypt, Id, Key, ListResult, ReadRequest,
+    ReadResult, RecordHint, RekeyOptions, SharedKey, Store, WriteRequest,
+};
+
+struct Plain(Vec<u8>);
+struct Sealed(Vec<u8>);
+
+impl AsRef<[u8]> for Plain {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl AsRef<[u8]> for Sealed {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl From<Vec<u8>> for Sealed {
+    fn from(data: Vec<u8>) -> Self {
+        Self(data)
+    }
+}
+
+impl From<Vec<u8>> for Plain {
+    fn from(data: Vec<u8>) -> Self {
+        Self(data)
+    }
+}
+
+impl Encrypt<Sealed> for Plain {}
+impl Decrypt<Infallible, Plain> for Sealed {}
+
+#[derive(Default)]
+struct Entries(HashMap<Vec<u8>, Vec<u8>>);
+
+impl Store for Entries {
+    fn list(&self) -> vault::Result<ListResult> {
+        Ok(ListResult::new(self.0.keys().cloned().collect()))
+    }
+
+    fn read(&self, request: ReadRequest) -> vault::Result<Option<ReadResult>> {
+        let id: Vec<u8> = request.into();
+        Ok(self.0.get(&id).map(|data| ReadResult::new(id, data.clone())))
+    }
+
+    fn write(&mut self, request: WriteRequest) -> vault::Result<()> {
+        let (id, data) = request.into();
+        self.0.insert(id, data);
+        Ok(())
+    }
+
+    fn delete(&mut self, request: DeleteRequest) -> vault::Result<()> {
+        let id: Vec<u8> = request.into();
+        self.0.remove(&id);
+        Ok(())
+    }
+}
+
+/// the metrics are global, so the tests take turns
+static SERIAL: Mutex<()> = Mutex::new(());
+
+fn in_memory() -> (MutexGuard<'static, ()>, InMemoryMetrics) {
+    let guard = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+    let metrics = InMemoryMetrics::default();
+    set_metrics(metrics.clone());
+    (guard, metrics)
+}
+
+fn sealed(key: &Key<Provider>, data: &[u8]) -> Sealed {
+    Plain(data.to_vec()).encrypt(key, b"ad").unwrap()
+}
+
+#[test]
+fn test_metrics_seal_open() {
+    let (_guard, metrics) = in_memory();
+    let key = Key::<Provider>::random().unwrap();
+
+    let boxes: Vec<Sealed> = [10, 20, 30].iter().map(|len| sealed(&key, &vec![7; *len])).collect();
+    for sealed in &boxes[..2] {
+        sealed.decrypt(&key, b"ad").unwrap();
+    }
+    let mut tampered = boxes[2].0.clone();
+    tampered[0] ^= 1;
+    assert!(Sealed(tampered).decrypt(&key, b"ad").is_err());
+    assert!(boxes[0].decrypt(&key, b"other ad").is_err());
+
+    let expected = [
+        (metrics::AUTH_FAILURES, 2),
+        (metrics::OPENS, 2),
+        (metrics::SEALED_BYTES, 60),
+        (metrics::SEALS, 3),
+    ];
+    assert_eq!(metrics.counters(), expected.iter().copied().collect());
+
+    // nothing is reported without metrics
+    clear_metrics();
+    sealed(&key, b"unreported").decrypt(&key, b"ad").unwrap();
+    assert_eq!(metrics.counter(metrics::SEALS), 3);
+    assert_eq!(metrics.counter(metrics::OPENS), 2);
+}
+
+#[test]
+fn test_metrics_rekey() {
+    let (_guard, metrics) = in_memory();
+    let old_key = Key::<Provider>::random().unwrap();
+    let new_key = Key::<Provider>::random().unwrap();
+    let other_key = Key::<Provider>::random().unwrap();
+    let records = vec![
+        (0, sealed(&old_key, b"first"), &b"ad"[..]),
+        (1, sealed(&old_key, b"second"), &b"ad"[..]),
+        (2, sealed(&other_key, b"foreign"), &b"ad"[..]),
+        (3, sealed(&old_key, b"third"), &b"ad"[..]),
+    ];
+    metrics.clear();
+
+    let options = RekeyOptions {
+        workers: 1,
+        ..Default::default()
+    };
+    let report = rekey_all(&records, &old_key, &new_key, options, |_, _| {}, |_, _, _| Ok(())).unwrap();
+    assert_eq!(report.rekeyed, 3);
+
+    let expected = [
+        (metrics::AUTH_FAILURES, 1),
+        (metrics::OPENS, 3),
+        (metrics::REKEYED_RECORDS, 3),
+        (metrics::REKEY_FAILURES, 1),
+        (metrics::REKEYS, 1),
+        (metrics::SEALED_BYTES, 16),
+        (metrics::SEALS, 3),
+    ];
+    assert_eq!(metrics.counters(), expected.iter().copied().collect());
+    assert_eq!(metrics.durations(metrics::REKEY_DURATION), [report.elapsed]);
+    clear_metrics();
+}
+
+#[test]
+fn test_metrics_gc() {
+    let (_guard, metrics) = in_memory();
+    let key = Key::<Provider>::random().unwrap();
+    let owner = Id::random::<Provider>().unwrap();
+    let vault = ConcurrentVault::open(SharedKey::from(key.clone()), owner, Entries::default()).unwrap();
+    let hint = RecordHint::new(b"hint").unwrap();
+    vault.write(b"kept", hint).unwrap();
+    let revoked = vault.write(b"revoked", hint).unwrap();
+    let store = vault.into_store();
+
+    // the payload of the revoked record, left behind by a store which failed to delete it
+    let payload: Vec<u8> = ReadRequest::payload::<Provider>(revoked).into();
+    let sealed = store.0[&payload].clone();
+    let vault = ConcurrentVault::open(SharedKey::from(key.clone()), owner, store).unwrap();
+    vault.revoke(revoked).unwrap();
+    let mut store = vault.into_store();
+    store.0.insert(payload, sealed);
+    metrics.clear();
+
+    let entries: Vec<ReadResult> = store
+        .0
+        .iter()
+        .map(|(id, data)| ReadResult::new(id.clone(), data.clone()))
+        .collect();
+    let (report, _, _) = DBView::gc(&key, &entries, &[]).unwrap();
+    assert_eq!(report.removed_blobs, 1);
+    assert_eq!(metrics.counter(metrics::GC_RUNS), 1);
+    assert_eq!(metrics.counter(metrics::GC_REMOVED_BLOBS), report.removed_blobs as u64);
+    assert_eq!(metrics.counter(metrics::GC_RECLAIMED_BYTES), report.reclaimed_bytes);
+    assert_eq!(metrics.durations(metrics::GC_DURATION), [report.duration]);
+    // the metrics have no labels, only the names of the module
+    assert!(metrics.counters().keys().all(|name| name.starts_with("vault_")));
+    clear_metrics();
+}
+
+#[test]
+fn test_metrics_expiring() {
+    let (_guard, metrics) = in_memory();
+    let key = Key::<Provider>::random().unwrap();
+    let owner = Id::random::<Provider>().unwrap();
+    let mut store = Entries::default();
+    store.write(DBWriter::create_chain(&key, owner)).unwrap();
+    let view = DBView::load(key.clone(), store.list().unwrap()).unwrap();
+    let expires_at = SystemTime::now() + Duration::from_secs(3600);
+    let (id, requests) = view
+        .writer(owner)
+        .write_expiring(b"session token", RecordHint::new(b"hint").unwrap(), expires_at)
+        .unwrap();
+    for request in requests {
+        store.write(request).unwrap();
+    }
+    let view = DBView::load(key, store.list().unwrap()).unwrap();
+    let reader = view.reader();
+    let payload = store.read(reader.prepare_read(id).unwrap()).unwrap().unwrap();
+    metrics.clear();
+
+    // an expiring payload opens with a single box
+    assert_eq!(reader.read(payload).unwrap(), b"session token");
+    assert_eq!(metrics.counter(metrics::OPENS), 1);
+    assert_eq!(metrics.counter(metrics::AUTH_FAILURES), 0);
+    clear_metrics();
+}
+
+#[cfg(feature = "password-kdf")]
+#[test]
+fn test_metrics_snapshot() {
+    use vault::{snapshot, BoxProvider, KdfParams};
+
+    let (_guard, metrics) = in_memory();
+    let mut suffix = [0; 8];
+    Provider::random_buf(&mut suffix).unwrap();
+    let suffix: String = suffix.iter().map(|b| format!("{:02x}", b)).collect();
+    let path = std::env::temp_dir().join(format!("vault-metrics-{}", suffix));
+    let records = vec![ReadResult::new(b"id".to_vec(), b"data".to_vec())];
+    let params = KdfParams {
+        memory_cost: 32,
+        iterations: 1,
+        parallelism: 1,
+    };
+
+    snapshot::export_with_params::<Provider>(&records, b"password", &path, params).unwrap();
+    assert_eq!(snapshot::import::<Provider>(&path, b"password").unwrap().len(), 1);
+    assert!(snapshot::import::<Provider>(&path, b"wrong").is_err());
+    assert_eq!(metrics.counter(metrics::SNAPSHOT_EXPORTS), 1);
+    assert_eq!(metrics.counter(metrics::SNAPSHOT_IMPORTS), 1);
+    assert_eq!(metrics.durations(metrics::SNAPSHOT_EXPORT_DURATION).len(), 1);
+    assert_eq!(metrics.durations(metrics::SNAPSHOT_IMPORT_DURATION).len(), 1);
+    std::fs::remove_file(path).unwrap();
+    clear_metrics();
+}
@@ -0,0 +1,219 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+mod utils;
+
+use std::{
+    collections::HashMap,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use utils::provider::{IetfProvider, Provider};
+use vault::{
+    migrate_records, BoxProvider, Clock, DBView, DBWriter, DeleteRequest, Error, ExpiryPolicy, Id, Key, ListResult,
+    ReadRequest, ReadResult, RecordHint, Store, WriteRequest,
+};
+
+/// a store in memory which fails the writes after `fail_after` of them
+#[derive(Default)]
+struct Entries {
+    entries: HashMap<Vec<u8>, Vec<u8>>,
+    fail_after: Option<usize>,
+}
+
+impl Store for Entries {
+    fn list(&self) -> vault::Result<ListResult> {
+        Ok(ListResult::new(self.entries.keys().cloned().collect()))
+    }
+
+    fn read(&self, request: ReadRequest) -> vault::Result<Option<ReadResult>> {
+        let id: Vec<u8> = request.into();
+        Ok(self.entries.get(&id).map(|data| ReadResult::new(id, data.clone())))
+    }
+
+    fn write(&mut self, request: WriteRequest) -> vault::Result<()> {
+        match self.fail_after.as_mut() {
+            Some(0) => return Err(Error::InterfaceErrorDetailed(String::from("disk full"))),
+            Some(left) => *left -= 1,
+            None => (),
+        }
+        let (id, data) = request.into();
+        self.entries.insert(id, data);
+        Ok(())
+    }
+
+    fn delete(&mut self, request: DeleteRequest) -> vault::Result<()> {
+        let id: Vec<u8> = request.into();
+        self.entries.remove(&id);
+        Ok(())
+    }
+}
+
+impl Entries {
+    fn apply(&mut self, requests: Vec<WriteRequest>) {
+        for request in requests {
+            self.write(request).unwrap();
+        }
+    }
+
+    /// the data and hint of each valid record opened with `key`
+    fn records<P: BoxProvider>(&self, key: &Key<P>) -> HashMap<Id, (Vec<u8>, RecordHint)> {
+        let view = DBView::load(key.clone(), self.list().unwrap()).unwrap();
+        let reader = view.reader();
+        view.records()
+            .map(|(id, hint)| {
+                let res = self.read(reader.prepare_read(id).unwrap()).unwrap().unwrap();
+                (id, (reader.read(res).unwrap(), hint))
+            })
+            .collect()
+    }
+}
+
+struct At(SystemTime);
+
+impl Clock for At {
+    fn now(&self) -> SystemTime {
+        self.0
+    }
+}
+
+/// a vault of two owners with `count` records each, one of them expiring and one revoked
+fn source(key: &Key<Provider>, count: usize) -> (Entries, Vec<Id>) {
+    let mut store = Entries::default();
+    let owners = [Id::random::<Provider>().unwrap(), Id::random::<Provider>().unwrap()];
+    for owner in owners.iter() {
+        store.apply(vec![DBWriter::create_chain(key, *owner)]);
+    }
+    let mut ids = Vec::new();
+    for i in 0..2 * count {
+        let view = DBView::load(key.clone(), store.list().unwrap()).unwrap();
+        let writer = view.writer(owners[i % 2]);
+        let data = format!("secret {}", i).into_bytes();
+        let hint = RecordHint::new(format!("hint {}", i)).unwrap();
+        let (id, requests) = match i {
+            0 => writer.write_expiring(&data, hint, UNIX_EPOCH + Duration::from_secs(4_000_000_000)),
+            _ => writer.write(&data, hint),
+        }
+        .unwrap();
+        store.apply(requests);
+        ids.push(id);
+    }
+    let view = DBView::load(key.clone(), store.list().unwrap()).unwrap();
+    let (revocation, delete) = view.writer(owners[1]).revoke(ids[1]).unwrap();
+    store.apply(vec![revocation]);
+    store.delete(delete).unwrap();
+    (store, ids)
+}
+
+#[test]
+fn test_migrate_records() {
+    let src_key = Key::<Provider>::random().unwrap();
+    let dst_key = Key::<IetfProvider>::random().unwrap();
+    let (src, ids) = source(&src_key, 5);
+    let mut dst = Entries::default();
+
+    let report = migrate_records(&src, &src_key, &mut dst, &dst_key, |_, _, _| true).unwrap();
+    assert_eq!(report.migrated.len(), 9);
+    assert!(report.skipped.is_empty() && report.failed.is_empty());
+    assert!(!report.migrated.contains(&ids[1]));
+
+    // the same ids, hints and plaintext, sealed by the other provider
+    let migrated = dst.records(&dst_key);
+    assert_eq!(migrated, src.records(&src_key));
+    // nothing opens with the source key
+    assert_eq!(
+        DBView::load(src_key.clone(), dst.list().unwrap())
+            .unwrap()
+            .records()
+            .count(),
+        0
+    );
+    let payload = |store: &Entries, id: Id| store.entries[id.as_ref()].len();
+    assert_eq!(
+        payload(&dst, ids[2]) - IetfProvider::box_overhead(),
+        payload(&src, ids[2]) - Provider::box_overhead()
+    );
+
+    // the expiry is kept
+    let view = DBView::load(dst_key, dst.list().unwrap()).unwrap();
+    let clock = At(UNIX_EPOCH + Duration::from_secs(4_100_000_000));
+    let policy = ExpiryPolicy {
+        clock: &clock,
+        skew_tolerance: Duration::ZERO,
+    };
+    assert_eq!(view.expired(|req| dst.read(req).unwrap(), &policy), [ids[0]]);
+}
+
+#[test]
+fn test_migrate_records_filter() {
+    let src_key = Key::<Provider>::random().unwrap();
+    let dst_key = Key::<IetfProvider>::random().unwrap();
+    let (src, ids) = source(&src_key, 3);
+    let mut dst = Entries::default();
+
+    let report = migrate_records(&src, &src_key, &mut dst, &dst_key, |id, _, plain| {
+        if id == ids[2] {
+            plain.extend_from_slice(b" v2");
+        }
+        id != ids[3]
+    })
+    .unwrap();
+    assert_eq!(
+        (report.migrated.len(), report.skipped, report.failed.len()),
+        (4, vec![ids[3]], 0)
+    );
+    let migrated = dst.records(&dst_key);
+    assert_eq!(migrated[&ids[2]].0, b"secret 2 v2");
+    assert_eq!(migrated[&ids[4]].0, b"secret 4");
+    assert!(!migrated.contains_key(&ids[3]));
+}
+
+#[test]
+fn test_migrate_records_resume() {
+    let src_key = Key::<Provider>::random().unwrap();
+    let dst_key = Key::<IetfProvider>::random().unwrap();
+    let (mut src, ids) = source(&src_key, 5);
+
+    let mut expected = src.records(&src_key);
+    expected.remove(&ids[4]);
+    // a record which doesn't open fails alone
+    src.entries.get_mut(ids[4].as_ref()).unwrap()[0] ^= 1;
+
+    // interrupted in the middle of a record
+    let mut dst = Entries {
+        fail_after: Some(8),
+        ..Entries::default()
+    };
+    assert!(matches!(
+        migrate_records(&src, &src_key, &mut dst, &dst_key, |_, _, _| true),
+        Err(Error::InterfaceErrorDetailed(_))
+    ));
+    let partial = dst.records(&dst_key);
+    assert!(!partial.is_empty() && partial.len() < 8);
+
+    // running it again migrates the rest
+    dst.fail_after = None;
+    let report = migrate_records(&src, &src_key, &mut dst, &dst_key, |_, _, _| true).unwrap();
+    assert_eq!(report.skipped.len(), partial.len());
+    assert_eq!(report.migrated.len(), 8 - partial.len());
+    assert_eq!(report.failed.len(), 1);
+    assert_eq!(report.failed[0].0, ids[4]);
+    assert!(matches!(report.failed[0].1, Error::AuthenticationFailed));
+
+    assert_eq!(dst.records(&dst_key), expected);
+
+    // and again changes nothing
+    let before = dst.entries.clone();
+    let report = migrate_records(&src, &src_key, &mut dst, &dst_key, |_, _, _| panic!("opened")).unwrap();
+    assert_eq!((report.migrated.len(), report.skipped.len()), (0, 8));
+    assert_eq!(report.failed.len(), 1);
+    assert_eq!(dst.entries, before);
+}
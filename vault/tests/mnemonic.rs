@@ -0,0 +1,111 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+#![cfg(feature = "mnemonic")]
+
+mod utils;
+
+use utils::provider::Provider;
+use vault::{Error, Key};
+
+/// 256 bit test vectors from the BIP39 specification.
+const VECTORS: &[([u8; 32], &str)] = &[
+    (
+        [0x00; 32],
+        "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon \
+         abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon art",
+    ),
+    (
+        [0x7f; 32],
+        "legal winner thank year wave sausage worth useful legal winner thank year wave sausage worth useful \
+         legal winner thank year wave sausage worth title",
+    ),
+    (
+        [0x80; 32],
+        "letter advice cage absurd amount doctor acoustic avoid letter advice cage absurd amount doctor acoustic \
+         avoid letter advice cage absurd amount doctor acoustic bless",
+    ),
+    (
+        [0xff; 32],
+        "zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo vote",
+    ),
+];
+
+#[test]
+fn test_mnemonic_vectors() {
+    for (entropy, phrase) in VECTORS {
+        let key = Key::<Provider>::load(entropy.to_vec()).unwrap();
+        let mnemonic = key.to_mnemonic().unwrap();
+        assert_eq!(mnemonic.phrase(), *phrase);
+        assert_eq!(mnemonic.word_count(), 24);
+
+        let restored = Key::<Provider>::from_mnemonic(phrase, "").unwrap();
+        assert_eq!(restored, key);
+    }
+}
+
+#[test]
+fn test_mnemonic_passphrase() {
+    // seed of the first vector with the passphrase "TREZOR" from the BIP39 specification.
+    let key = Key::<Provider>::from_mnemonic_seed(VECTORS[0].1, "TREZOR").unwrap();
+    let expected = [
+        0xbd, 0xa8, 0x54, 0x46, 0xc6, 0x84, 0x13, 0x70, 0x70, 0x90, 0xa5, 0x20, 0x22, 0xed, 0xd2, 0x6a, 0x1c, 0x94,
+        0x62, 0x29, 0x50, 0x29, 0xf2, 0xe6, 0x0c, 0xd7, 0xc4, 0xf2, 0xbb, 0xd3, 0x09, 0x71,
+    ];
+    assert_eq!(key.bytes(), &expected[..]);
+
+    // the exported key bytes have no passphrase
+    let key = Key::<Provider>::random().unwrap();
+    let mnemonic = key.to_mnemonic().unwrap();
+    assert_eq!(
+        Key::<Provider>::from_mnemonic(mnemonic.phrase(), "pw"),
+        Err(Error::InterfaceError)
+    );
+    assert_ne!(
+        Key::<Provider>::from_mnemonic_seed(mnemonic.phrase(), "pw").unwrap(),
+        key
+    );
+}
+
+#[test]
+fn test_mnemonic_tolerant_parsing() {
+    let key = Key::<Provider>::load(vec![0x7f; 32]).unwrap();
+    let phrase = format!("  {}\n", VECTORS[1].1.to_uppercase().replace(' ', " \t "));
+
+    let restored = Key::<Provider>::from_mnemonic(&phrase, "").unwrap();
+    assert_eq!(restored, key);
+}
+
+#[test]
+fn test_mnemonic_invalid() {
+    let phrase = VECTORS[1].1.replacen("thank", "thunk", 1);
+    match Key::<Provider>::from_mnemonic(&phrase, "") {
+        Err(Error::InvalidMnemonicWord(2)) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+
+    // swapping two words keeps every word valid but breaks the checksum.
+    let phrase = VECTORS[1].1.replacen("legal winner", "winner legal", 1);
+    assert!(matches!(
+        Key::<Provider>::from_mnemonic(&phrase, ""),
+        Err(Error::MnemonicError(_))
+    ));
+
+    // a valid 12 word phrase encodes a key which is too short for the provider.
+    let phrase = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+    assert!(matches!(
+        Key::<Provider>::from_mnemonic(phrase, ""),
+        Err(Error::InvalidKeyLength {
+            expected: 32,
+            actual: 16
+        })
+    ));
+}
@@ -0,0 +1,192 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+mod utils;
+
+use utils::{provider::Provider, test_vault::TestVault};
+use vault::{DBView, DeleteRequest, Error, Id, Key, ReadResult, RecordHint, VaultNamespace, WriteRequest};
+
+fn apply(vault: &mut TestVault, to_write: Vec<WriteRequest>, to_delete: Vec<DeleteRequest>) {
+    for request in to_write {
+        let (id, data) = request.into();
+        vault.records.insert(id, data);
+    }
+    for request in to_delete {
+        let id: Vec<u8> = request.into();
+        vault.records.remove(&id);
+    }
+}
+
+/// creates the chain of `owner` in `namespace`
+fn create_chain(vault: &mut TestVault, namespace: &VaultNamespace<Provider>, owner: Id) {
+    apply(vault, vec![namespace.create_chain(owner)], Vec::new());
+}
+
+/// writes `data` to the chain of `owner` in `namespace` and returns the id of the record
+fn write(vault: &mut TestVault, namespace: &VaultNamespace<Provider>, owner: Id, data: &[u8]) -> Id {
+    let view = namespace.load(&vault.list()).unwrap();
+    let (id, requests) = view
+        .writer(owner)
+        .write(data, RecordHint::new(b"hint").unwrap())
+        .unwrap();
+    apply(vault, namespace.tag_writes(requests), Vec::new());
+    id
+}
+
+fn read(vault: &TestVault, namespace: &VaultNamespace<Provider>, id: Id) -> vault::Result<Vec<u8>> {
+    let view = namespace.load(&vault.list()).unwrap();
+    let reader = view.reader();
+    let res = vault.read(namespace.tag_read(reader.prepare_read(id)?)).unwrap();
+    reader.read(namespace.untag(res).unwrap())
+}
+
+fn entries(vault: &TestVault) -> Vec<ReadResult> {
+    vault
+        .records
+        .iter()
+        .map(|(id, data)| ReadResult::new(id.clone(), data.clone()))
+        .collect()
+}
+
+#[test]
+fn test_namespaces() {
+    let mut vault = TestVault::empty(Key::random().unwrap());
+    let a = VaultNamespace::new("tenant-a", Key::<Provider>::random().unwrap()).unwrap();
+    let b = VaultNamespace::new("tenant-b", Key::<Provider>::random().unwrap()).unwrap();
+    assert_ne!(a.tag(), b.tag());
+    let owner = Id::random::<Provider>().unwrap();
+    create_chain(&mut vault, &a, owner);
+    create_chain(&mut vault, &b, owner);
+
+    let in_a = write(&mut vault, &a, owner, b"secret of a");
+    let in_b = write(&mut vault, &b, owner, b"secret of b");
+    assert_eq!(read(&vault, &a, in_a).unwrap(), b"secret of a");
+    assert_eq!(read(&vault, &b, in_b).unwrap(), b"secret of b");
+
+    // every entry is tagged with its namespace
+    let list = vault.list();
+    assert_eq!(a.list(&list).ids().len() + b.list(&list).ids().len(), list.ids().len());
+    assert_eq!(a.list(&list).ids().len(), 3);
+    let view = a.load(&list).unwrap();
+    assert_eq!(view.records().map(|(id, _)| id).collect::<Vec<_>>(), vec![in_a]);
+
+    // a namespace doesn't see the records of another one
+    assert_eq!(
+        read(&vault, &a, in_b).err().unwrap(),
+        Error::RecordNotFound { id: in_b }
+    );
+
+    assert!(matches!(
+        VaultNamespace::new("", Key::<Provider>::random().unwrap()),
+        Err(Error::InvalidEntry { .. })
+    ));
+}
+
+#[test]
+fn test_namespace_copied_ciphertext() {
+    // the same key in both namespaces, only the AD keeps them apart
+    let key = Key::<Provider>::random().unwrap();
+    let mut vault = TestVault::empty(key.clone());
+    let a = VaultNamespace::new("tenant-a", key.clone()).unwrap();
+    let b = VaultNamespace::new("tenant-b", key.clone()).unwrap();
+    let owner = Id::random::<Provider>().unwrap();
+    create_chain(&mut vault, &a, owner);
+    create_chain(&mut vault, &b, owner);
+    let in_a = write(&mut vault, &a, owner, b"secret of a");
+    let in_b = write(&mut vault, &b, owner, b"data of b");
+
+    // copy the payload of the record of a over the payload of the record of b
+    let payload_of_a = vault.records[&[a.tag(), in_a.as_ref()].concat()].clone();
+    vault
+        .records
+        .insert([b.tag(), in_b.as_ref()].concat(), payload_of_a.clone());
+    assert_eq!(read(&vault, &b, in_b).err().unwrap(), Error::AuthenticationFailed);
+
+    // and its transactions into b
+    let copied: Vec<(Vec<u8>, Vec<u8>)> = vault
+        .records
+        .iter()
+        .filter(|(id, _)| a.contains(id))
+        .map(|(id, data)| ([b.tag(), &id[a.tag().len()..]].concat(), data.clone()))
+        .collect();
+    vault.records.extend(copied);
+    let view = b.load(&vault.list()).unwrap();
+    assert_eq!(view.records().map(|(id, _)| id).collect::<Vec<_>>(), vec![in_b]);
+
+    // nor do records of a namespace open without it
+    let plain = DBView::load(key, a.list(&vault.list())).unwrap();
+    assert_eq!(plain.records().count(), 0);
+}
+
+#[test]
+fn test_namespace_delete() {
+    let mut vault = TestVault::empty(Key::random().unwrap());
+    let a = VaultNamespace::new("tenant-a", Key::<Provider>::random().unwrap()).unwrap();
+    let b = VaultNamespace::new("tenant-b", Key::<Provider>::random().unwrap()).unwrap();
+    let owner = Id::random::<Provider>().unwrap();
+    create_chain(&mut vault, &a, owner);
+    create_chain(&mut vault, &b, owner);
+    write(&mut vault, &a, owner, b"secret of a");
+    let in_b = write(&mut vault, &b, owner, b"secret of b");
+
+    let (to_write, to_delete) = a.delete(&entries(&vault));
+    assert_eq!(to_write.len(), 1);
+    assert!(to_write.iter().all(|request| request.data().iter().all(|b| *b == 0)));
+    assert_eq!(to_delete.len(), 3);
+    apply(&mut vault, to_write, to_delete);
+
+    assert!(a.list(&vault.list()).ids().is_empty());
+    assert_eq!(read(&vault, &b, in_b).unwrap(), b"secret of b");
+}
+
+#[test]
+fn test_namespace_gc() {
+    let mut vault = TestVault::empty(Key::random().unwrap());
+    let a = VaultNamespace::new("tenant-a", Key::<Provider>::random().unwrap()).unwrap();
+    let b = VaultNamespace::new("tenant-b", Key::<Provider>::random().unwrap()).unwrap();
+    let owner = Id::random::<Provider>().unwrap();
+    create_chain(&mut vault, &a, owner);
+    create_chain(&mut vault, &b, owner);
+    let revoked = write(&mut vault, &a, owner, b"revoked");
+    let kept = write(&mut vault, &a, owner, b"kept");
+    let in_b = write(&mut vault, &b, owner, b"secret of b");
+
+    // revoke a record of a, but the store keeps its payload
+    let view = a.load(&vault.list()).unwrap();
+    let (revocation, _) = view.writer(owner).revoke(revoked).unwrap();
+    apply(&mut vault, a.tag_writes(vec![revocation]), Vec::new());
+
+    let (report, to_write, to_delete) = a.gc(&entries(&vault), &[]).unwrap();
+    assert_eq!(report.removed_blobs, 1);
+    assert!(to_delete.iter().all(|request| a.contains(request.id())));
+    apply(&mut vault, to_write, to_delete);
+
+    assert_eq!(read(&vault, &a, kept).unwrap(), b"kept");
+    assert_eq!(read(&vault, &b, in_b).unwrap(), b"secret of b");
+    let (report, _, _) = b.gc(&entries(&vault), &[]).unwrap();
+    assert_eq!(report.removed_blobs, 0);
+
+    // the re-keyed namespace reads its payloads through its reader
+    let new_key = Key::<Provider>::random().unwrap();
+    let view = a.load(&vault.list()).unwrap();
+    let (report, to_write, to_delete) = view
+        .rekey(
+            a.key(),
+            &new_key,
+            &a.list(&vault.list()),
+            a.reader(|req| vault.read(req)),
+        )
+        .unwrap();
+    assert!(report.failed.is_empty());
+    apply(&mut vault, a.tag_writes(to_write), a.tag_deletes(to_delete));
+    let a = VaultNamespace::new("tenant-a", new_key).unwrap();
+    assert_eq!(read(&vault, &a, kept).unwrap(), b"kept");
+}
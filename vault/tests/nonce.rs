@@ -0,0 +1,102 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+mod utils;
+
+use utils::{hex, provider::Provider};
+use vault::{BoxProvider, Error, Key};
+
+/// seal a fixed key, nonce, ad and plaintext and compare the result to the pinned box
+#[allow(dead_code)]
+fn check_golden<P: BoxProvider<Error = Error>>(key: &str, nonce: &str, ad: &str, plain: &[u8], expected: &str) {
+    let key = Key::<P>::load(hex(key)).unwrap();
+    let nonce = hex(nonce);
+    assert_eq!(P::box_nonce_len(), nonce.len());
+
+    let sealed = P::box_seal_with_nonce(&key, &nonce, &hex(ad), plain).unwrap();
+    assert_eq!(sealed, [nonce, hex(expected)].concat());
+    assert_eq!(P::box_open(&key, &hex(ad), &sealed).unwrap(), plain);
+
+    assert!(matches!(
+        P::box_seal_with_nonce(&key, &[0; 3], &hex(ad), plain),
+        Err(Error::InvalidNonceLength { actual: 3, .. })
+    ));
+}
+
+/// the XChaCha20-Poly1305 test vector A.3.1 of draft-irtf-cfrg-xchacha-03
+#[allow(dead_code)]
+fn check_xchacha_golden<P: BoxProvider<Error = Error>>() {
+    check_golden::<P>(
+        "808182838485868788898a8b8c8d8e8f909192939495969798999a9b9c9d9e9f",
+        "404142434445464748494a4b4c4d4e4f5051525354555657",
+        "50515253c0c1c2c3c4c5c6c7",
+        b"Ladies and Gentlemen of the class of '99: If I could offer you only one tip for the future, \
+          sunscreen would be it.",
+        "bd6d179d3e83d43b9576579493c0e939572a1700252bfaccbed2902c21396cbb731c7f1b0b4aa6440bf3a82f4eda7e39ae64c67\
+         08c54c216cb96b72e1213b4522f8c9ba40db5d945b11b69b982c1bb9e3f3fac2bc369488f76b2383565d3fff921f9664c97637d\
+         a9768812f615c68b13b52ec0875924c1c7987947deafd8780acf49",
+    );
+}
+
+#[test]
+fn test_explicit_nonce_unsupported() {
+    let key = Key::<Provider>::random().unwrap();
+    assert_eq!(Provider::box_nonce_len(), 0);
+    assert!(Provider::box_seal_with_nonce(&key, &[], b"", b"data").is_err());
+}
+
+#[cfg(feature = "provider-xchacha")]
+#[test]
+fn test_explicit_nonce_xchacha() {
+    check_xchacha_golden::<vault::providers::XChaChaPoly>();
+}
+
+#[cfg(feature = "provider-sodium")]
+#[test]
+fn test_explicit_nonce_sodium() {
+    check_xchacha_golden::<vault::providers::Sodium>();
+}
+
+/// test case 16 of the GCM specification submitted to NIST
+#[cfg(feature = "provider-aes-gcm")]
+#[test]
+fn test_explicit_nonce_aes_gcm() {
+    check_golden::<vault::providers::AesGcm256>(
+        "feffe9928665731c6d6a8f9467308308feffe9928665731c6d6a8f9467308308",
+        "cafebabefacedbaddecaf888",
+        "feedfacedeadbeeffeedfacedeadbeefabaddad2",
+        &hex(
+            "d9313225f88406e5a55909c5aff5269a86a7a9531534f7da2e4c303d8a318a721c3c0c95956809532fcf0e2449a6b525\
+             b16aedf5aa0de657ba637b39",
+        ),
+        "522dc1f099567d07f47f37a32a84427d643a8cdcbfe5c0c97598a2bd2555d1aa8cb08e48590dbb3da7b08b1056828838\
+         c5f61e6393ba7a0abcc9f66276fc6ece0f4e1768cddf8853bb2d551b",
+    );
+}
+
+#[cfg(all(debug_assertions, feature = "test-utils", feature = "provider-xchacha"))]
+#[test]
+fn test_explicit_nonce_reuse_detected() {
+    use vault::providers::XChaChaPoly;
+
+    let key = Key::<XChaChaPoly>::random().unwrap();
+    let nonce = XChaChaPoly::random_vec(XChaChaPoly::box_nonce_len()).unwrap();
+
+    XChaChaPoly::box_seal_with_nonce(&key, &nonce, b"", b"first").unwrap();
+    let reused = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        XChaChaPoly::box_seal_with_nonce(&key, &nonce, b"", b"second")
+    }));
+    assert!(reused.is_err());
+
+    // random nonces are not affected
+    XChaChaPoly::box_seal(&key, b"", b"third").unwrap();
+    XChaChaPoly::box_seal(&key, b"", b"fourth").unwrap();
+}
@@ -0,0 +1,128 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+mod utils;
+
+use std::convert::Infallible;
+
+use utils::provider::Provider;
+use vault::{BoxProvider, Decrypt, Encrypt, Error, Key, PaddingScheme};
+
+struct Plain(Vec<u8>);
+struct Sealed(Vec<u8>);
+
+impl AsRef<[u8]> for Plain {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl AsRef<[u8]> for Sealed {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl From<Vec<u8>> for Sealed {
+    fn from(data: Vec<u8>) -> Self {
+        Self(data)
+    }
+}
+
+impl From<Vec<u8>> for Plain {
+    fn from(data: Vec<u8>) -> Self {
+        Self(data)
+    }
+}
+
+impl Encrypt<Sealed> for Plain {}
+impl Decrypt<Infallible, Plain> for Sealed {}
+
+fn roundtrip(key: &Key<Provider>, plain: &[u8], scheme: PaddingScheme) -> Vec<u8> {
+    let sealed: Sealed = Plain(plain.to_vec()).encrypt_padded(key, b"ad", scheme).unwrap();
+    let opened: Plain = sealed.decrypt_unpadded(key, b"ad").unwrap();
+    assert_eq!(opened.0, plain);
+    sealed.0
+}
+
+#[test]
+fn test_padding_buckets() {
+    let key = Key::<Provider>::random().unwrap();
+    let scheme = PaddingScheme::Bucket(32);
+    let overhead = Provider::box_overhead();
+
+    assert_eq!(roundtrip(&key, b"", scheme).len(), 32 + overhead);
+
+    // the length takes 8 bytes of the bucket
+    assert_eq!(roundtrip(&key, &[1; 24], scheme).len(), 32 + overhead);
+    assert_eq!(roundtrip(&key, &[1; 25], scheme).len(), 64 + overhead);
+
+    // secrets of different lengths in the same bucket can't be told apart by their length
+    let short = roundtrip(&key, b"hunter2", scheme);
+    let long = roundtrip(&key, b"correct horse battery", scheme);
+    assert_eq!(short.len(), long.len());
+
+    // trailing zeros are kept
+    roundtrip(&key, &[0; 5], scheme);
+    roundtrip(&key, b"data\0\0\0", scheme);
+
+    let sealed: vault::Result<Sealed> = Plain(b"data".to_vec()).encrypt_padded(&key, b"ad", PaddingScheme::Bucket(0));
+    assert!(matches!(sealed, Err(Error::InterfaceErrorDetailed(_))));
+}
+
+#[test]
+fn test_padding_padme() {
+    let key = Key::<Provider>::random().unwrap();
+    let overhead = Provider::box_overhead();
+
+    for len in [0, 1, 7, 100, 1000, 4096].iter() {
+        roundtrip(&key, &vec![0xa5; *len], PaddingScheme::Padme);
+        roundtrip(&key, &vec![0xa5; *len], PaddingScheme::None);
+    }
+    assert_eq!(roundtrip(&key, b"data", PaddingScheme::None).len(), 8 + 4 + overhead);
+
+    let padded: Vec<usize> = [0, 1, 9, 92, 1000, 1 << 20]
+        .iter()
+        .map(|len| PaddingScheme::Padme.padded_len(*len).unwrap())
+        .collect();
+    assert_eq!(padded, [8, 10, 18, 104, 1024, (1 << 20) + (1 << 15)]);
+    for len in 0..5000 {
+        let padded = PaddingScheme::Padme.padded_len(len).unwrap();
+        assert!(padded >= len + 8 && padded - (len + 8) <= (len + 8) * 12 / 100);
+    }
+}
+
+#[test]
+fn test_padding_malformed_after_authentication() {
+    let key = Key::<Provider>::random().unwrap();
+    let other = Key::<Provider>::random().unwrap();
+
+    // a box which opens but claims more plaintext than it holds
+    let mut plain = 100u64.to_be_bytes().to_vec();
+    plain.extend_from_slice(b"data");
+    let sealed = Sealed(Provider::box_seal(&key, b"ad", &plain).unwrap());
+
+    let opened: vault::Result<Plain> = sealed.decrypt_unpadded(&key, b"ad");
+    assert!(matches!(
+        opened,
+        Err(Error::MalformedCiphertext {
+            expected_min: 108,
+            got: 12
+        })
+    ));
+    let sealed = Sealed(Provider::box_seal(&key, b"ad", b"short").unwrap());
+    let opened: vault::Result<Plain> = sealed.decrypt_unpadded(&key, b"ad");
+    assert!(matches!(opened, Err(Error::MalformedCiphertext { .. })));
+
+    // the padding isn't looked at before the box opens
+    let opened: vault::Result<Plain> = sealed.decrypt_unpadded(&other, b"ad");
+    assert!(matches!(opened, Err(Error::AuthenticationFailed)));
+}
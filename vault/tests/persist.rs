@@ -0,0 +1,93 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+mod utils;
+
+use std::{
+    fs,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use utils::provider::Provider;
+use vault::{persist_hooks, BoxProvider, Key};
+
+/// a fresh path in the temporary directory
+fn temp_path(name: &str) -> PathBuf {
+    let mut suffix = [0; 8];
+    Provider::random_buf(&mut suffix).unwrap();
+    let suffix: String = suffix.iter().map(|b| format!("{:02x}", b)).collect();
+    std::env::temp_dir().join(format!("vault-{}-{}", name, suffix))
+}
+
+#[test]
+fn test_persist_on_drop() {
+    let path = temp_path("persist");
+    let kek = Key::<Provider>::random().unwrap();
+
+    let mut key = Key::<Provider>::random().unwrap();
+    let expected = key.bytes().to_vec();
+    key.on_drop(persist_hooks::encrypted_file(path.clone(), kek.clone()));
+    drop(key);
+
+    let stored = fs::read(&path).unwrap();
+    assert_eq!(stored.len(), expected.len() + Provider::box_overhead());
+    assert!(!stored.windows(expected.len()).any(|w| w == &expected[..]));
+    // no temporary file is left next to the stored key
+    let prefix = format!("{}.", path.file_name().unwrap().to_string_lossy());
+    assert!(!fs::read_dir(path.parent().unwrap()).unwrap().any(|e| {
+        let name = e.unwrap().file_name().to_string_lossy().into_owned();
+        name.starts_with(&prefix) && name.ends_with(".tmp")
+    }));
+
+    let loaded: Key<Provider> = persist_hooks::load_persisted_key(&path, &kek).unwrap();
+    assert_eq!(loaded.bytes(), &expected[..]);
+
+    let wrong_kek = Key::<Provider>::random().unwrap();
+    assert!(persist_hooks::load_persisted_key::<Provider, Provider>(&path, &wrong_kek).is_err());
+
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_persist_later_hooks() {
+    let path = temp_path("later-hooks");
+    let kek = Key::<Provider>::random().unwrap();
+
+    // the hooks after the persisting one still get the key bytes
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let mut key = Key::<Provider>::random().unwrap();
+    let expected = key.bytes().to_vec();
+    key.on_drop(persist_hooks::encrypted_file(path.clone(), kek.clone()));
+    let later = seen.clone();
+    key.on_drop(move |bytes: &mut [u8]| later.lock().unwrap().extend_from_slice(bytes));
+    drop(key);
+
+    assert_eq!(*seen.lock().unwrap(), expected);
+    let loaded: Key<Provider> = persist_hooks::load_persisted_key(&path, &kek).unwrap();
+    assert_eq!(loaded.bytes(), &expected[..]);
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_persist_replaces_file() {
+    let path = temp_path("replace");
+    let kek = Key::<Provider>::random().unwrap();
+
+    persist_hooks::persist(&path, &kek, &[1; 32]).unwrap();
+    persist_hooks::persist(&path, &kek, &[2; 32]).unwrap();
+
+    let loaded: Key<Provider> = persist_hooks::load_persisted_key(&path, &kek).unwrap();
+    assert_eq!(loaded.bytes(), &[2; 32]);
+
+    fs::remove_file(&path).unwrap();
+    assert!(persist_hooks::load_persisted_key::<Provider, Provider>(&path, &kek).is_err());
+}
@@ -0,0 +1,274 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+mod utils;
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex},
+    time::SystemTime,
+};
+
+use utils::provider::Provider;
+use vault::{
+    AccessContext, AllOf, AnyOf, Claims, ConcurrentVault, DBView, Decision, DeleteRequest, Error, Id, Key, ListResult,
+    Operation, Policy, ReadRequest, ReadResult, RecordHint, RecordMeta, SharedKey, Store, WriteRequest,
+};
+
+/// a store in memory which counts its writes
+#[derive(Default)]
+struct Entries {
+    entries: HashMap<Vec<u8>, Vec<u8>>,
+    writes: usize,
+}
+
+impl Store for Entries {
+    fn list(&self) -> vault::Result<ListResult> {
+        Ok(ListResult::new(self.entries.keys().cloned().collect()))
+    }
+
+    fn read(&self, request: ReadRequest) -> vault::Result<Option<ReadResult>> {
+        let id: Vec<u8> = request.into();
+        Ok(self.entries.get(&id).map(|data| ReadResult::new(id, data.clone())))
+    }
+
+    fn write(&mut self, request: WriteRequest) -> vault::Result<()> {
+        let (id, data) = request.into();
+        self.entries.insert(id, data);
+        self.writes += 1;
+        Ok(())
+    }
+
+    fn delete(&mut self, request: DeleteRequest) -> vault::Result<()> {
+        let id: Vec<u8> = request.into();
+        self.entries.remove(&id);
+        Ok(())
+    }
+}
+
+/// the records in `backup_only` may only be accessed by the backup role
+struct BackupOnly {
+    backup_only: Arc<Mutex<HashSet<Id>>>,
+}
+
+impl Policy for BackupOnly {
+    fn allow(&self, ctx: &AccessContext) -> Decision {
+        let restricted = self.backup_only.lock().unwrap().contains(&ctx.record);
+        match !restricted || ctx.claims.get("role") == Some("backup") {
+            true => Decision::Allow,
+            false => Decision::Deny(String::from("backup role required")),
+        }
+    }
+}
+
+/// allows the `operations` only
+struct Only(Vec<Operation>);
+
+impl Policy for Only {
+    fn allow(&self, ctx: &AccessContext) -> Decision {
+        match self.0.contains(&ctx.operation) {
+            true => Decision::Allow,
+            false => Decision::Deny(format!("{:?} not allowed", ctx.operation)),
+        }
+    }
+}
+
+fn hint() -> RecordHint {
+    RecordHint::new(b"hint").unwrap()
+}
+
+fn denied(reason: &str) -> Error {
+    Error::AccessDenied {
+        reason: String::from(reason),
+    }
+}
+
+fn ids(records: Vec<(Id, RecordHint)>) -> Vec<Id> {
+    records.into_iter().map(|(id, _)| id).collect()
+}
+
+#[test]
+fn test_policy_claims() {
+    let key = SharedKey::from(Key::<Provider>::random().unwrap());
+    let owner = Id::random::<Provider>().unwrap();
+    let backup_only = Arc::new(Mutex::new(HashSet::new()));
+    let vault = ConcurrentVault::open(key, owner, Entries::default())
+        .unwrap()
+        .with_policy(BackupOnly {
+            backup_only: backup_only.clone(),
+        });
+    let backup = Claims::new().with("role", "backup");
+    let app = Claims::new().with("role", "app");
+
+    let public = vault.write(b"public", hint()).unwrap();
+    let secret = vault.with_claims(&backup).write(b"secret", hint()).unwrap();
+    backup_only.lock().unwrap().insert(secret);
+
+    // allowed
+    assert_eq!(vault.read(public).unwrap(), b"public");
+    assert_eq!(vault.with_claims(&app).read(public).unwrap(), b"public");
+    assert_eq!(vault.with_claims(&backup).read(secret).unwrap(), b"secret");
+    assert_eq!(vault.with_claims(&backup).claims(), &backup);
+
+    // denied, for every operation
+    assert_eq!(vault.read(secret).err().unwrap(), denied("backup role required"));
+    let as_app = vault.with_claims(&app);
+    assert_eq!(as_app.read(secret).err().unwrap(), denied("backup role required"));
+    assert_eq!(
+        as_app.overwrite(secret, b"x").err().unwrap(),
+        denied("backup role required")
+    );
+    assert_eq!(as_app.revoke(secret).err().unwrap(), denied("backup role required"));
+    assert_eq!(
+        as_app.read(secret).err().unwrap().io_kind(),
+        std::io::ErrorKind::PermissionDenied
+    );
+
+    // listing is filtered by the policy
+    assert_eq!(ids(as_app.records()), [public]);
+    let mut all = ids(vault.with_claims(&backup).records());
+    all.sort_by_key(|id| id.as_ref().to_vec());
+    let mut expected = vec![public, secret];
+    expected.sort_by_key(|id| id.as_ref().to_vec());
+    assert_eq!(all, expected);
+
+    // the record is untouched by the denied operations
+    assert_eq!(vault.with_claims(&backup).read(secret).unwrap(), b"secret");
+    vault.with_claims(&backup).revoke(secret).unwrap();
+    assert!(matches!(
+        vault.with_claims(&backup).read(secret),
+        Err(Error::RecordNotFound { .. })
+    ));
+}
+
+#[test]
+fn test_policy_before_key() {
+    let key = SharedKey::from(Key::<Provider>::random().unwrap());
+    let owner = Id::random::<Provider>().unwrap();
+    let vault = ConcurrentVault::open(key.clone(), owner, Entries::default()).unwrap();
+    let id = vault.write(b"data", hint()).unwrap();
+
+    // the payload doesn't open anymore, a denied read fails before it is opened
+    let mut store = vault.into_store();
+    let payload = store.read(ReadRequest::payload::<Provider>(id)).unwrap().unwrap();
+    let mut corrupt = payload.data().to_vec();
+    *corrupt.last_mut().unwrap() ^= 1;
+    store.entries.insert(payload.id().to_vec(), corrupt);
+    let vault = ConcurrentVault::open(key.clone(), owner, store)
+        .unwrap()
+        .with_policy(Only(vec![Operation::List]));
+    assert_eq!(vault.read(id).err().unwrap(), denied("Read not allowed"));
+
+    // a denied write writes nothing
+    let store = vault.into_store();
+    let writes = store.writes;
+    let vault = ConcurrentVault::open(key, owner, store)
+        .unwrap()
+        .with_policy(Only(vec![Operation::Read]));
+    assert_eq!(vault.write(b"data", hint()).err().unwrap(), denied("Write not allowed"));
+    assert_eq!(vault.into_store().writes, writes);
+}
+
+#[test]
+fn test_policy_composition() {
+    let key = SharedKey::from(Key::<Provider>::random().unwrap());
+    let owner = Id::random::<Provider>().unwrap();
+    let admin = Claims::new().with("role", "admin");
+    let reader = Claims::new().with("role", "reader");
+    let is_admin = |ctx: &AccessContext| match ctx.claims.get("role") {
+        Some("admin") => Decision::Allow,
+        _ => Decision::Deny(String::from("not an admin")),
+    };
+    let not_expired = |ctx: &AccessContext| match ctx.timestamp <= SystemTime::now() {
+        true => Decision::Allow,
+        false => Decision::Deny(String::from("from the future")),
+    };
+
+    // admins may do everything, everybody may read and list
+    let policy = AllOf(vec![
+        Box::new(not_expired),
+        Box::new(AnyOf(vec![
+            Box::new(is_admin),
+            Box::new(Only(vec![Operation::Read, Operation::List])),
+        ])),
+    ]);
+    let vault = ConcurrentVault::open(key, owner, Entries::default())
+        .unwrap()
+        .with_policy(policy);
+    let id = vault.with_claims(&admin).write(b"data", hint()).unwrap();
+    assert_eq!(vault.with_claims(&reader).read(id).unwrap(), b"data");
+    assert_eq!(ids(vault.with_claims(&reader).records()), [id]);
+    assert_eq!(
+        vault.with_claims(&reader).overwrite(id, b"other").err().unwrap(),
+        denied("not an admin; Write not allowed")
+    );
+    assert_eq!(
+        vault.with_claims(&reader).revoke(id).err().unwrap(),
+        denied("not an admin; Delete not allowed")
+    );
+    vault.with_claims(&admin).overwrite(id, b"other").unwrap();
+    assert_eq!(vault.read(id).unwrap(), b"other");
+
+    // the first denial of `AllOf` wins
+    let context = AccessContext {
+        record: id,
+        namespace: None,
+        operation: Operation::Read,
+        claims: &admin,
+        timestamp: SystemTime::now(),
+    };
+    let both = AllOf(vec![
+        Box::new(Only(vec![Operation::List])),
+        Box::new(|_: &AccessContext| Decision::Deny(String::from("never"))),
+    ]);
+    assert_eq!(both.allow(&context), Decision::Deny(String::from("Read not allowed")));
+
+    // empty compositions
+    assert_eq!(AllOf::default().allow(&context), Decision::Allow);
+    assert_eq!(
+        AnyOf::default().allow(&context),
+        Decision::Deny(String::from("No policy allows the operation"))
+    );
+}
+
+#[test]
+fn test_policy_list_with_meta() {
+    let key = Key::<Provider>::random().unwrap();
+    let owner = Id::random::<Provider>().unwrap();
+    let vault = ConcurrentVault::open(SharedKey::from(key.clone()), owner, Entries::default()).unwrap();
+    let plain = vault.write(b"plain", hint()).unwrap();
+
+    // a record with metadata
+    let mut store = vault.into_store();
+    let view = DBView::load(key.clone(), store.list().unwrap()).unwrap();
+    let meta = RecordMeta::new(SystemTime::now()).with_label("label");
+    let (labeled, requests) = view.writer(owner).write_with_meta(b"labeled", hint(), &meta).unwrap();
+    for request in requests {
+        store.write(request).unwrap();
+    }
+
+    let hidden = labeled;
+    let vault = ConcurrentVault::open(SharedKey::from(key), owner, store)
+        .unwrap()
+        .with_policy(move |ctx: &AccessContext| match ctx.record == hidden {
+            true => Decision::Deny(String::from("hidden")),
+            false => Decision::Allow,
+        });
+    assert_eq!(vault.list_with_meta().unwrap(), [(plain, hint(), None)]);
+    assert_eq!(vault.read(labeled).err().unwrap(), denied("hidden"));
+
+    let vault = ConcurrentVault::open(vault.key().clone(), owner, vault.into_store()).unwrap();
+    let mut listed = vault.list_with_meta().unwrap();
+    listed.sort_by_key(|(id, _, _)| id.as_ref().to_vec());
+    let mut expected = vec![(plain, hint(), None), (labeled, hint(), Some(meta))];
+    expected.sort_by_key(|(id, _, _)| id.as_ref().to_vec());
+    assert_eq!(listed, expected);
+}
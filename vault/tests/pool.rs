@@ -0,0 +1,112 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+mod utils;
+
+use std::convert::Infallible;
+
+use utils::provider::Provider;
+use vault::{BoxProvider, BufferPool, Decrypt, Key};
+
+struct Sealed(Vec<u8>);
+struct Plain(Vec<u8>);
+
+impl AsRef<[u8]> for Sealed {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl From<Vec<u8>> for Plain {
+    fn from(data: Vec<u8>) -> Self {
+        Self(data)
+    }
+}
+
+impl Decrypt<Infallible, Plain> for Sealed {}
+
+/// the spare capacity of `buf`, which the pool zeroes
+fn spare(buf: &mut Vec<u8>) -> Vec<u8> {
+    let len = buf.len();
+    let capacity = buf.capacity();
+    // the spare capacity is initialized, the pool wiped it
+    unsafe {
+        buf.set_len(capacity);
+        let spare = buf[len..].to_vec();
+        buf.set_len(len);
+        spare
+    }
+}
+
+#[test]
+fn test_pool_roundtrip() {
+    let key = Key::<Provider>::random().unwrap();
+    let pool = BufferPool::default();
+
+    let sealed = pool.seal_with(&key, b"ad", b"some data").unwrap();
+    assert_eq!(sealed.len(), 9 + Provider::box_overhead());
+    let opened: Plain = Sealed(sealed.to_vec()).decrypt(&key, b"ad").unwrap();
+    assert_eq!(opened.0, b"some data");
+
+    // the box can be taken out of the pool
+    let sealed = pool.seal_with(&key, b"ad", b"some data").unwrap().into_vec();
+    let opened: Plain = Sealed(sealed).decrypt(&key, b"ad").unwrap();
+    assert_eq!(opened.0, b"some data");
+}
+
+#[test]
+fn test_pool_reuse_wiped() {
+    let key = Key::<Provider>::random().unwrap();
+    let pool = BufferPool::new(4, 1024);
+
+    let sealed = pool.seal_with(&key, b"ad", &[0xa5; 100]).unwrap();
+    let ptr = sealed.as_ptr();
+    drop(sealed);
+    assert_eq!(pool.idle(), 1);
+
+    // the buffer is wiped before it can be reused
+    let mut buf = pool.buffer();
+    assert_eq!(buf.as_ptr(), ptr);
+    assert!(buf.is_empty() && buf.capacity() >= 100 + Provider::box_overhead());
+    assert!(spare(&mut buf).iter().all(|b| *b == 0));
+    pool.recycle(buf);
+
+    let sealed = pool.seal_with(&key, b"ad", b"other data").unwrap();
+    assert_eq!(sealed.as_ptr(), ptr);
+    assert_eq!(pool.idle(), 0);
+}
+
+#[test]
+fn test_pool_limits() {
+    let key = Key::<Provider>::random().unwrap();
+    let pool = BufferPool::new(2, 256);
+
+    // buffers beyond the high-water mark shrink
+    drop(pool.seal_with(&key, b"", &[1; 4096]).unwrap());
+    let mut buf = pool.buffer();
+    assert!(buf.capacity() <= 256);
+    assert!(spare(&mut buf).iter().all(|b| *b == 0));
+
+    // at most `max_buffers` buffers are kept
+    let sealed: Vec<_> = (0..4).map(|_| pool.seal_with(&key, b"", b"data").unwrap()).collect();
+    drop(sealed);
+    assert_eq!(pool.idle(), 2);
+}
+
+#[test]
+fn test_pool_failure() {
+    let key = Key::<Provider>::random().unwrap().with_max_uses(1);
+    let pool = BufferPool::default();
+
+    drop(pool.seal_with(&key, b"", b"data").unwrap());
+    assert!(pool.seal_with(&key, b"", b"data").is_err());
+    assert_eq!(pool.idle(), 1);
+}
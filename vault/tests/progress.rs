@@ -0,0 +1,209 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+mod utils;
+
+use std::{io::Cursor, sync::Mutex};
+
+use utils::provider::Provider;
+use vault::{
+    open_chunked_with_progress, rekey_all_with_progress, seal_chunked, seal_chunked_with_progress, BoxProvider, Error,
+    Key, Progress, RekeyOptions,
+};
+
+/// a `Progress` which records the reports and is cancelled after `cancel_after` of them
+#[derive(Default)]
+struct Recorder {
+    reports: Mutex<Vec<(u64, Option<u64>)>>,
+    cancel_after: Option<usize>,
+}
+
+impl Recorder {
+    fn cancel_after(reports: usize) -> Self {
+        Self {
+            reports: Mutex::default(),
+            cancel_after: Some(reports),
+        }
+    }
+
+    fn reports(&self) -> Vec<(u64, Option<u64>)> {
+        self.reports.lock().unwrap().clone()
+    }
+}
+
+impl Progress for Recorder {
+    fn report(&self, done: u64, total: Option<u64>) {
+        self.reports.lock().unwrap().push((done, total));
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancel_after
+            .is_some_and(|after| self.reports.lock().unwrap().len() >= after)
+    }
+}
+
+fn assert_monotonic(reports: &[(u64, Option<u64>)]) {
+    assert!(reports.windows(2).all(|pair| pair[0].0 <= pair[1].0), "{:?}", reports);
+}
+
+#[test]
+fn test_chunked_progress() {
+    let key = Key::<Provider>::random().unwrap();
+    let data = vec![0xa5; 10_000];
+
+    let progress = Recorder::default();
+    let chunked = seal_chunked_with_progress(&key, b"ad", &data, 1000, &progress).unwrap();
+    let expected: Vec<_> = (1..=10).map(|done| (done, Some(10))).collect();
+    assert_eq!(progress.reports(), expected);
+
+    let progress = Recorder::default();
+    assert_eq!(
+        open_chunked_with_progress(&key, b"ad", &chunked, &progress).unwrap(),
+        data
+    );
+    assert_eq!(progress.reports(), expected);
+}
+
+#[test]
+fn test_chunked_cancel() {
+    let key = Key::<Provider>::random().unwrap().with_max_uses(10);
+    let data = vec![0xa5; 10_000];
+
+    let progress = Recorder::cancel_after(3);
+    assert!(matches!(
+        seal_chunked_with_progress(&key, b"ad", &data, 1000, &progress),
+        Err(Error::Cancelled)
+    ));
+    assert_eq!(progress.reports().len(), 3);
+    // the chunks after the cancellation aren't sealed
+    assert_eq!(key.remaining_uses(), Some(7));
+
+    let chunked = seal_chunked(&key, b"ad", &data[..7000], 1000).unwrap();
+    let progress = Recorder::cancel_after(5);
+    assert!(matches!(
+        open_chunked_with_progress(&key, b"ad", &chunked, &progress),
+        Err(Error::Cancelled)
+    ));
+    assert_eq!(progress.reports().len(), 5);
+}
+
+#[test]
+fn test_stream_progress() {
+    let key = Key::<Provider>::random().unwrap();
+    let data = vec![0xa5; 9500];
+
+    let progress = Recorder::default();
+    let mut sealed = Vec::new();
+    Provider::seal_stream_with_progress(&key, b"ad", &mut Cursor::new(&data), &mut sealed, 1000, &progress).unwrap();
+    let reports = progress.reports();
+    assert_monotonic(&reports);
+    assert_eq!(reports.len(), 10);
+    assert_eq!(reports.last(), Some(&(9500, None)));
+
+    let progress = Recorder::default();
+    let mut opened = Vec::new();
+    Provider::open_stream_with_progress(&key, b"ad", &mut Cursor::new(&sealed), &mut opened, 1000, &progress).unwrap();
+    assert_eq!(opened, data);
+    assert_eq!(progress.reports(), reports);
+
+    // a cancelled stream isn't complete
+    let progress = Recorder::cancel_after(4);
+    let mut partial = Vec::new();
+    let sealed =
+        Provider::seal_stream_with_progress(&key, b"ad", &mut Cursor::new(&data), &mut partial, 1000, &progress);
+    assert!(matches!(sealed, Err(Error::Cancelled)));
+    assert!(Provider::open_stream(&key, b"ad", &mut Cursor::new(&partial), &mut Vec::new(), 1000).is_err());
+}
+
+#[test]
+fn test_rekey_cancel() {
+    let old = Key::<Provider>::random().unwrap();
+    let new = Key::<Provider>::random().unwrap();
+    let ads: Vec<[u8; 4]> = (0..200u32).map(u32::to_be_bytes).collect();
+    let sealed: Vec<Vec<u8>> = ads
+        .iter()
+        .map(|ad| Provider::box_seal(&old, ad, b"secret").unwrap())
+        .collect();
+    let records: Vec<_> = (0..200u32)
+        .zip(&sealed)
+        .zip(&ads)
+        .map(|((id, data), ad)| (id, data.as_slice(), ad.as_ref()))
+        .collect();
+
+    let options = RekeyOptions {
+        workers: 1,
+        ..Default::default()
+    };
+    let progress = Recorder::cancel_after(2);
+    let mut committed = Vec::new();
+    let result = rekey_all_with_progress(&records, &old, &new, options, &progress, |offset, _, _| {
+        committed.push(offset);
+        Ok(())
+    });
+    assert!(matches!(result, Err(Error::Cancelled)));
+
+    // the rounds before the cancellation are committed in order
+    let reports = progress.reports();
+    assert_monotonic(&reports);
+    assert_eq!(reports.len(), 2);
+    assert_eq!(reports[1].1, Some(200));
+    assert_eq!(committed, (0..reports[1].0 as usize).collect::<Vec<_>>());
+
+    let options = RekeyOptions {
+        resume_from: committed.len(),
+        ..options
+    };
+    let progress = Recorder::default();
+    let report = rekey_all_with_progress(&records, &old, &new, options, &progress, |_, _, _| Ok(())).unwrap();
+    assert_eq!(report.rekeyed, 200 - committed.len());
+    assert_eq!(progress.reports().last(), Some(&(200, Some(200))));
+}
+
+#[cfg(feature = "mmap")]
+#[test]
+fn test_file_cancel() {
+    use std::fs;
+    use vault::{open_file_with_progress, seal_file, seal_file_with_progress};
+
+    let mut suffix = [0; 8];
+    Provider::random_buf(&mut suffix).unwrap();
+    let suffix: String = suffix.iter().map(|b| format!("{:02x}", b)).collect();
+    let dir = std::env::temp_dir().join(format!("vault-progress-{}", suffix));
+    fs::create_dir(&dir).unwrap();
+
+    let key = Key::<Provider>::random().unwrap();
+    let src = dir.join("plain");
+    let sealed = dir.join("sealed");
+    fs::write(&src, vec![0xa5; 10_000]).unwrap();
+
+    let progress = Recorder::cancel_after(4);
+    assert!(matches!(
+        seal_file_with_progress(&key, b"ad", &src, &sealed, 1000, &progress),
+        Err(Error::Cancelled)
+    ));
+    assert_eq!(
+        progress.reports(),
+        (1..=4).map(|done| (done, Some(10))).collect::<Vec<_>>()
+    );
+    // neither the destination nor the temporary file are left behind
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+    seal_file(&key, b"ad", &src, &sealed, 1000).unwrap();
+    fs::write(dir.join("opened"), b"old").unwrap();
+    let progress = Recorder::cancel_after(9);
+    assert!(matches!(
+        open_file_with_progress(&key, b"ad", &sealed, &dir.join("opened"), &progress),
+        Err(Error::Cancelled)
+    ));
+    assert_eq!(fs::read(dir.join("opened")).unwrap(), b"old");
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 3);
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,109 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+mod utils;
+
+use std::{error::Error as StdError, fmt};
+
+use utils::provider::Provider;
+use vault::{BoxProvider, Decrypt, Encrypt, Error, Key};
+
+/// the errors of a provider backed by a device
+#[derive(Debug)]
+enum DeviceError {
+    /// the device is busy, retrying may succeed
+    Busy,
+    /// an error of the vault
+    Vault(Error),
+}
+
+impl fmt::Display for DeviceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeviceError::Busy => write!(f, "device busy"),
+            DeviceError::Vault(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl StdError for DeviceError {}
+
+impl From<Error> for DeviceError {
+    fn from(e: Error) -> Self {
+        DeviceError::Vault(e)
+    }
+}
+
+impl From<DeviceError> for Error {
+    fn from(e: DeviceError) -> Self {
+        match e {
+            DeviceError::Vault(e) => e,
+            e => Error::ProviderError(Box::new(e)),
+        }
+    }
+}
+
+/// a provider whose device refuses to open boxes
+struct BusyProvider;
+
+impl BoxProvider for BusyProvider {
+    type Error = DeviceError;
+
+    fn box_key_len() -> usize {
+        Provider::box_key_len()
+    }
+
+    fn box_overhead() -> usize {
+        Provider::box_overhead()
+    }
+
+    fn box_id() -> [u8; 4] {
+        Provider::box_id()
+    }
+
+    fn box_seal(key: &Key<Self>, ad: &[u8], data: &[u8]) -> Result<Vec<u8>, DeviceError> {
+        let key = Key::<Provider>::load_from_slice(key.bytes())?;
+        Ok(Provider::box_seal(&key, ad, data)?)
+    }
+
+    fn box_open(_: &Key<Self>, _: &[u8], _: &[u8]) -> Result<Vec<u8>, DeviceError> {
+        Err(DeviceError::Busy)
+    }
+
+    fn random_buf(buf: &mut [u8]) -> Result<(), DeviceError> {
+        Ok(Provider::random_buf(buf)?)
+    }
+}
+
+#[test]
+fn test_provider_error_conversion() {
+    let key = Key::<BusyProvider>::random().unwrap();
+    let sealed = b"some data".to_vec().encrypt(&key, b"").unwrap();
+
+    let e = match sealed.decrypt(&key, b"") {
+        Err(e) => e,
+        Ok(opened) => panic!("unexpected plaintext: {:?}", opened as Vec<u8>),
+    };
+    assert!(matches!(e, Error::ProviderError(_)));
+    assert_eq!(e.to_string(), "Provider Error: `device busy`");
+
+    let source = e.source().unwrap();
+    assert!(matches!(source.downcast_ref::<DeviceError>(), Some(DeviceError::Busy)));
+}
+
+#[test]
+fn test_provider_error_from_vault() {
+    // the errors of the default methods reach the provider error type and back
+    let key = Key::<BusyProvider>::random().unwrap();
+    let e = BusyProvider::box_seal_with_nonce(&key, &[], b"", b"").unwrap_err();
+    assert!(matches!(e, DeviceError::Vault(Error::CryptoError { .. })));
+    assert!(matches!(Error::from(e), Error::CryptoError { .. }));
+}
@@ -0,0 +1,182 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+mod utils;
+
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use utils::{alloc::WatchingAllocator, provider::Provider};
+use vault::{BoxProvider, Error, Key};
+
+#[global_allocator]
+static ALLOC: WatchingAllocator = WatchingAllocator;
+
+/// counter of `ZeroProvider`
+static COUNTER: AtomicU8 = AtomicU8::new(0);
+
+/// a provider whose random bytes cycle through 0, 1 and 2, so a third of them is zero.  It has no limit on the
+/// length of random vectors.
+struct ZeroProvider;
+
+impl BoxProvider for ZeroProvider {
+    type Error = Error;
+
+    fn box_key_len() -> usize {
+        32
+    }
+
+    fn box_overhead() -> usize {
+        0
+    }
+
+    fn box_id() -> [u8; 4] {
+        *b"zero"
+    }
+
+    fn box_seal(_: &Key<Self>, _: &[u8], data: &[u8]) -> vault::Result<Vec<u8>> {
+        Ok(data.to_vec())
+    }
+
+    fn box_open(_: &Key<Self>, _: &[u8], data: &[u8]) -> vault::Result<Vec<u8>> {
+        Ok(data.to_vec())
+    }
+
+    fn max_random_len() -> usize {
+        usize::MAX
+    }
+
+    fn random_buf(buf: &mut [u8]) -> vault::Result<()> {
+        buf.iter_mut()
+            .for_each(|b| *b = COUNTER.fetch_add(1, Ordering::SeqCst) % 3);
+        Ok(())
+    }
+}
+
+/// a provider whose `random_buf` writes `PartialProvider::FILLED` bytes and fails if the buffer is longer.  The
+/// buffer is watched by the `WatchingAllocator`.
+struct PartialProvider;
+
+impl PartialProvider {
+    const FILLED: usize = 8;
+}
+
+impl BoxProvider for PartialProvider {
+    type Error = Error;
+
+    fn box_key_len() -> usize {
+        32
+    }
+
+    fn box_overhead() -> usize {
+        0
+    }
+
+    fn box_id() -> [u8; 4] {
+        *b"part"
+    }
+
+    fn box_seal(_: &Key<Self>, _: &[u8], data: &[u8]) -> vault::Result<Vec<u8>> {
+        Ok(data.to_vec())
+    }
+
+    fn box_open(_: &Key<Self>, _: &[u8], data: &[u8]) -> vault::Result<Vec<u8>> {
+        Ok(data.to_vec())
+    }
+
+    fn random_buf(buf: &mut [u8]) -> vault::Result<()> {
+        WatchingAllocator::watch(buf.as_ptr(), buf.len());
+        let filled = buf.len().min(Self::FILLED);
+        buf[..filled].iter_mut().for_each(|b| *b = 0xa5);
+        match buf.len() > Self::FILLED {
+            true => Err(Error::crypto("random", "Interrupted")),
+            false => Ok(()),
+        }
+    }
+}
+
+#[test]
+fn test_random_failure_wiped() {
+    assert!(PartialProvider::random_vec(64).is_err());
+    assert_eq!(WatchingAllocator::freed_wiped(), Some(true));
+    assert!(Key::<PartialProvider>::random().is_err());
+    assert_eq!(WatchingAllocator::freed_wiped(), Some(true));
+
+    assert!(PartialProvider::random_array::<16>().is_err());
+
+    let mut buf = [1; 16];
+    assert!(PartialProvider::random_nonzero_buf(&mut buf).is_err());
+    assert_eq!(buf, [0; 16]);
+
+    // short buffers are filled
+    assert_eq!(PartialProvider::random_vec(8).unwrap(), [0xa5; 8]);
+    assert_eq!(PartialProvider::random_array::<4>().unwrap(), [0xa5; 4]);
+}
+
+#[test]
+fn test_random_array() {
+    let nonce: [u8; 24] = Provider::random_array().unwrap();
+    let salt = Provider::random_array::<32>().unwrap();
+    assert_eq!(nonce.len(), 24);
+    assert_eq!(salt.len(), 32);
+
+    assert_ne!(Provider::random_array::<32>().unwrap(), salt);
+    assert_eq!(Provider::random_array::<0>().unwrap(), [0u8; 0]);
+}
+
+#[test]
+fn test_random_nonzero_buf() {
+    let mut buf = [0; 64];
+    ZeroProvider::random_nonzero_buf(&mut buf).unwrap();
+    assert!(buf.iter().all(|b| *b != 0));
+
+    let mut buf = [0; 64];
+    Provider::random_nonzero_buf(&mut buf).unwrap();
+    assert!(buf.iter().all(|b| *b != 0));
+
+    let mut other = [0; 64];
+    Provider::random_nonzero_buf(&mut other).unwrap();
+    assert_ne!(buf, other);
+}
+
+#[test]
+fn test_random_vec_unchanged() {
+    let vec = Provider::random_vec(24).unwrap();
+    assert_eq!(vec.len(), 24);
+    assert_ne!(Provider::random_vec(24).unwrap(), vec);
+
+    // zero bytes stay possible
+    assert!(ZeroProvider::random_vec(3).unwrap().contains(&0));
+}
+
+#[test]
+fn test_random_vec_limit() {
+    assert_eq!(Provider::max_random_len(), 1 << 30);
+    assert_eq!(Provider::random_vec(Provider::max_random_len()).unwrap().len(), 1 << 30);
+
+    match Provider::random_vec(usize::MAX) {
+        Err(Error::InterfaceErrorDetailed(e)) => assert!(e.contains(&usize::MAX.to_string())),
+        r => panic!("unexpected result: {:?}", r.map(|v| v.len())),
+    }
+    assert!(Provider::random_vec((1 << 30) + 1).is_err());
+}
+
+#[test]
+fn test_random_vec_allocation_failure() {
+    // without a limit the allocation fails instead of aborting
+    assert!(matches!(
+        ZeroProvider::random_vec(usize::MAX),
+        Err(Error::MemoryError(_))
+    ));
+    assert!(matches!(
+        ZeroProvider::random_vec(isize::MAX as usize),
+        Err(Error::MemoryError(_))
+    ));
+}
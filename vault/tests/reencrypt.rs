@@ -0,0 +1,73 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+mod utils;
+
+use utils::provider::{IetfProvider, Provider};
+use vault::{reencrypt, reencrypt_across, BoxProvider, Error, Key};
+
+#[test]
+fn test_reencrypt() {
+    let old = Key::<Provider>::random().unwrap();
+    let new = Key::<Provider>::random().unwrap();
+    let sealed = Provider::box_seal(&old, b"ad", b"secret").unwrap();
+
+    let rotated = reencrypt(&sealed, &old, &new, b"ad").unwrap();
+    assert_ne!(rotated, sealed);
+    assert_eq!(Provider::box_open(&new, b"ad", &rotated).unwrap(), b"secret");
+    assert!(Provider::box_open(&old, b"ad", &rotated).is_err());
+    assert_eq!(Provider::box_open(&old, b"ad", &sealed).unwrap(), b"secret");
+}
+
+#[test]
+fn test_reencrypt_wrong_old_key() {
+    let old = Key::<Provider>::random().unwrap();
+    let other = Key::<Provider>::random().unwrap();
+    let new = Key::<Provider>::random().unwrap().with_max_uses(1);
+    let sealed = Provider::box_seal(&old, b"ad", b"secret").unwrap();
+
+    assert!(reencrypt(&sealed, &other, &new, b"ad").is_err());
+    assert!(reencrypt(&sealed, &old, &new, b"other ad").is_err());
+    // failed rotations don't use the new key
+    assert_eq!(new.remaining_uses(), Some(1));
+
+    reencrypt(&sealed, &old, &new, b"ad").unwrap();
+    assert!(matches!(
+        reencrypt(&sealed, &old, &new, b"ad"),
+        Err(Error::KeyExhausted(1))
+    ));
+}
+
+#[test]
+fn test_reencrypt_rebind_ad() {
+    let old = Key::<Provider>::random().unwrap();
+    let new = Key::<Provider>::random().unwrap();
+    let sealed = Provider::box_seal(&old, b"record 1", b"secret").unwrap();
+
+    let rebound = reencrypt_across(&sealed, &old, &new, b"record 1", b"record 2").unwrap();
+    assert_eq!(Provider::box_open(&new, b"record 2", &rebound).unwrap(), b"secret");
+    assert!(Provider::box_open(&new, b"record 1", &rebound).is_err());
+
+    // the same key can be kept to only rebind the AD
+    let rebound = reencrypt_across(&sealed, &old, &old, b"record 1", b"record 2").unwrap();
+    assert_eq!(Provider::box_open(&old, b"record 2", &rebound).unwrap(), b"secret");
+}
+
+#[test]
+fn test_reencrypt_across_providers() {
+    let old = Key::<Provider>::random().unwrap();
+    let new = Key::<IetfProvider>::random().unwrap();
+    let sealed = Provider::box_seal(&old, b"ad", b"secret").unwrap();
+
+    let migrated = reencrypt_across(&sealed, &old, &new, b"ad", b"ad").unwrap();
+    assert_eq!(migrated.len(), b"secret".len() + IetfProvider::box_overhead());
+    assert_eq!(IetfProvider::box_open(&new, b"ad", &migrated).unwrap(), b"secret");
+}
@@ -0,0 +1,196 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+mod utils;
+
+use utils::provider::Provider;
+use vault::{reencrypt, rekey_all, BoxProvider, Error, Key, RekeyOptions};
+
+const RECORDS: u32 = 3000;
+
+/// seal `RECORDS` synthetic records under `key`, the AD of a record is its id
+fn records(key: &Key<Provider>) -> (Vec<[u8; 4]>, Vec<Vec<u8>>) {
+    let ads: Vec<[u8; 4]> = (0..RECORDS).map(u32::to_be_bytes).collect();
+    let sealed = ads
+        .iter()
+        .map(|ad| Provider::box_seal(key, ad, &[ad.as_ref(), b"secret"].concat()).unwrap())
+        .collect();
+    (ads, sealed)
+}
+
+fn items<'a>(ads: &'a [[u8; 4]], sealed: &'a [Vec<u8>]) -> Vec<(u32, &'a [u8], &'a [u8])> {
+    (0..RECORDS)
+        .zip(sealed)
+        .zip(ads)
+        .map(|((id, data), ad)| (id, data.as_slice(), ad.as_ref()))
+        .collect()
+}
+
+#[test]
+fn test_rekey_matches_serial() {
+    let old = Key::<Provider>::random().unwrap();
+    let new = Key::<Provider>::random().unwrap();
+    let (ads, sealed) = records(&old);
+    let items = items(&ads, &sealed);
+
+    for workers in [0, 1, 4].iter() {
+        let mut committed = Vec::new();
+        let mut progress = Vec::new();
+        let options = RekeyOptions {
+            workers: *workers,
+            ..Default::default()
+        };
+        let report = rekey_all(
+            &items,
+            &old,
+            &new,
+            options,
+            |processed, total| progress.push((processed, total)),
+            |offset, id, data| {
+                committed.push((offset, *id, data));
+                Ok(())
+            },
+        )
+        .unwrap();
+        assert_eq!(report.rekeyed, RECORDS as usize);
+        assert!(report.failed.is_empty());
+
+        // committed in the order of the records
+        assert_eq!(committed.len(), RECORDS as usize);
+        for (index, (offset, id, data)) in committed.iter().enumerate() {
+            assert_eq!((*offset, *id), (index, index as u32));
+            let serial = reencrypt(&sealed[index], &old, &new, &ads[index]).unwrap();
+            assert_eq!(
+                Provider::box_open(&new, &ads[index], data).unwrap(),
+                Provider::box_open(&new, &ads[index], &serial).unwrap()
+            );
+            assert!(Provider::box_open(&old, &ads[index], data).is_err());
+        }
+
+        assert!(progress.windows(2).all(|pair| pair[0].0 < pair[1].0));
+        assert_eq!(progress.last(), Some(&(RECORDS as usize, RECORDS as usize)));
+    }
+}
+
+#[test]
+fn test_rekey_failures_and_dry_run() {
+    let old = Key::<Provider>::random().unwrap();
+    let other = Key::<Provider>::random().unwrap();
+    let new = Key::<Provider>::random().unwrap().with_max_uses(RECORDS as u64);
+    let (ads, mut sealed) = records(&old);
+    sealed[7] = Provider::box_seal(&other, &ads[7], b"secret").unwrap();
+    sealed[1500][0] ^= 1;
+    let items = items(&ads, &sealed);
+
+    let options = RekeyOptions {
+        workers: 3,
+        dry_run: true,
+        ..Default::default()
+    };
+    let report = rekey_all(
+        &items,
+        &old,
+        &new,
+        options,
+        |_, _| {},
+        |_, _, _| panic!("committed in a dry run"),
+    )
+    .unwrap();
+    assert_eq!(report.failed, [7, 1500]);
+    assert_eq!(report.rekeyed, RECORDS as usize - 2);
+    assert_eq!(new.remaining_uses(), Some(RECORDS as u64));
+
+    // the records which don't open are skipped
+    let mut committed = Vec::new();
+    let options = RekeyOptions {
+        workers: 3,
+        ..Default::default()
+    };
+    let report = rekey_all(
+        &items,
+        &old,
+        &new,
+        options,
+        |_, _| {},
+        |_, id, _| {
+            committed.push(*id);
+            Ok(())
+        },
+    )
+    .unwrap();
+    assert_eq!(report.failed, [7, 1500]);
+    assert_eq!(committed.len(), RECORDS as usize - 2);
+    assert!(!committed.contains(&7) && !committed.contains(&1500));
+    assert_eq!(new.remaining_uses(), Some(2));
+}
+
+#[test]
+fn test_rekey_resume_after_abort() {
+    let old = Key::<Provider>::random().unwrap();
+    let new = Key::<Provider>::random().unwrap();
+    let (ads, sealed) = records(&old);
+    let items = items(&ads, &sealed);
+    let mut rekeyed: Vec<Option<Vec<u8>>> = vec![None; RECORDS as usize];
+
+    // abort after 1234 records were committed
+    let options = RekeyOptions {
+        workers: 4,
+        ..Default::default()
+    };
+    let mut commits = 0;
+    let result = rekey_all(
+        &items,
+        &old,
+        &new,
+        options,
+        |_, _| {},
+        |offset, _, data| {
+            if commits == 1234 {
+                return Err(Error::InterfaceErrorDetailed("simulated abort".into()));
+            }
+            commits += 1;
+            rekeyed[offset] = Some(data);
+            Ok(())
+        },
+    );
+    assert!(matches!(result, Err(Error::InterfaceErrorDetailed(_))));
+
+    // the committed records are a prefix of the records
+    let resume_from = rekeyed.iter().position(Option::is_none).unwrap();
+    assert_eq!(resume_from, 1234);
+    assert!(rekeyed[resume_from..].iter().all(Option::is_none));
+
+    let mut progress = Vec::new();
+    let options = RekeyOptions {
+        workers: 4,
+        resume_from,
+        ..Default::default()
+    };
+    let report = rekey_all(
+        &items,
+        &old,
+        &new,
+        options,
+        |processed, _| progress.push(processed),
+        |offset, _, data| {
+            assert!(rekeyed[offset].replace(data).is_none());
+            Ok(())
+        },
+    )
+    .unwrap();
+    assert_eq!(report.rekeyed, RECORDS as usize - resume_from);
+    assert!(progress[0] > resume_from);
+
+    for (index, data) in rekeyed.iter().enumerate() {
+        let opened = Provider::box_open(&new, &ads[index], data.as_ref().unwrap()).unwrap();
+        assert_eq!(opened, [ads[index].as_ref(), b"secret"].concat());
+    }
+}
@@ -0,0 +1,104 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+#![cfg(feature = "provider-ring")]
+
+mod utils;
+
+use utils::hex;
+use vault::{
+    providers::{RingAesGcm, RingChaCha, RingProvider},
+    BoxProvider, Error, Key,
+};
+
+fn check_provider<P: BoxProvider<Error = Error>>() {
+    assert_eq!(P::box_key_len(), 32);
+    assert_eq!(P::box_overhead(), 28);
+
+    let key = Key::<P>::random().unwrap();
+    for data in [&b""[..], b"some data", &[7; 1000]] {
+        let sealed = P::box_seal(&key, b"ad", data).unwrap();
+        assert_eq!(sealed.len(), data.len() + P::box_overhead());
+        assert_eq!(P::box_open(&key, b"ad", &sealed).unwrap(), data);
+    }
+
+    let sealed = P::box_seal(&key, b"ad", b"some data").unwrap();
+    for i in [0, 12, sealed.len() - 1] {
+        let mut corrupt = sealed.clone();
+        corrupt[i] ^= 1;
+        assert!(matches!(
+            P::box_open(&key, b"ad", &corrupt),
+            Err(Error::AuthenticationFailed)
+        ));
+    }
+    assert!(P::box_open(&key, b"other ad", &sealed).is_err());
+    assert!(P::box_open(&Key::random().unwrap(), b"ad", &sealed).is_err());
+    assert!(P::box_open(&key, b"ad", &sealed[..27]).is_err());
+    assert!(P::self_test().unwrap().passed());
+}
+
+#[test]
+fn test_ring_chacha() {
+    check_provider::<RingProvider<RingChaCha>>();
+}
+
+#[test]
+fn test_ring_aes_gcm() {
+    check_provider::<RingProvider<RingAesGcm>>();
+}
+
+/// test case 14 of the GCM specification submitted to NIST
+#[test]
+fn test_ring_aes_gcm_nist_vector() {
+    let key = Key::<RingProvider<RingAesGcm>>::load(vec![0; 32]).unwrap();
+    let boxx = hex("000000000000000000000000cea7403d4d606b6e074ec5d3baf39d18d0d1c8a799996bf0265b98b5d48ab919");
+    assert_eq!(RingProvider::<RingAesGcm>::box_open(&key, b"", &boxx).unwrap(), [0; 16]);
+}
+
+#[cfg(feature = "provider-aes-gcm")]
+#[test]
+fn test_ring_aes_gcm_interop() {
+    use vault::providers::AesGcm256;
+
+    let key = Key::<AesGcm256>::random().unwrap();
+    let ring = Key::<RingProvider<RingAesGcm>>::load_from_slice(key.bytes()).unwrap();
+
+    let sealed = AesGcm256::box_seal(&key, b"ad", b"some data").unwrap();
+    assert_eq!(
+        RingProvider::<RingAesGcm>::box_open(&ring, b"ad", &sealed).unwrap(),
+        b"some data"
+    );
+    let sealed = RingProvider::<RingAesGcm>::box_seal(&ring, b"ad", b"some data").unwrap();
+    assert_eq!(AesGcm256::box_open(&key, b"ad", &sealed).unwrap(), b"some data");
+
+    let sealed = RingProvider::<RingAesGcm>::seal_tagged(&ring, b"ad", b"some data").unwrap();
+    assert_eq!(AesGcm256::open_tagged(&key, b"ad", &sealed).unwrap(), b"some data");
+}
+
+#[cfg(all(feature = "aead-interop", feature = "provider-xchacha"))]
+#[test]
+fn test_ring_chacha_interop() {
+    use vault::providers::{RustChaCha, RustCryptoBox};
+
+    let key = Key::<RustCryptoBox<RustChaCha>>::random().unwrap();
+    let ring = Key::<RingProvider<RingChaCha>>::load_from_slice(key.bytes()).unwrap();
+
+    let sealed = RustCryptoBox::<RustChaCha>::box_seal(&key, b"ad", b"some data").unwrap();
+    assert_eq!(
+        RingProvider::<RingChaCha>::box_open(&ring, b"ad", &sealed).unwrap(),
+        b"some data"
+    );
+    let sealed = RingProvider::<RingChaCha>::box_seal(&ring, b"ad", b"some data").unwrap();
+    assert_eq!(
+        RustCryptoBox::<RustChaCha>::box_open(&key, b"ad", &sealed).unwrap(),
+        b"some data"
+    );
+}
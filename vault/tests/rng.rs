@@ -0,0 +1,43 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+#![cfg(feature = "rand")]
+
+mod utils;
+
+use rand::{rngs::StdRng, SeedableRng};
+use utils::provider::Provider;
+use vault::{BoxProvider, Key};
+
+#[test]
+fn test_random_with_rng() {
+    let key = Key::<Provider>::random_with_rng(&mut StdRng::seed_from_u64(42)).unwrap();
+    let same = Key::<Provider>::random_with_rng(&mut StdRng::seed_from_u64(42)).unwrap();
+    let other = Key::<Provider>::random_with_rng(&mut StdRng::seed_from_u64(43)).unwrap();
+
+    assert_eq!(key.bytes().len(), Provider::box_key_len());
+    assert_eq!(key, same);
+    assert_ne!(key, other);
+}
+
+#[test]
+fn test_random_with_rng_consumes_key_len() {
+    let mut rng = StdRng::seed_from_u64(7);
+    let key = Key::<Provider>::random_with_rng(&mut rng).unwrap();
+
+    let mut expected = [0; 64];
+    Provider::random_buf_with_rng(&mut StdRng::seed_from_u64(7), &mut expected).unwrap();
+    assert_eq!(key.bytes(), &expected[..32]);
+
+    let mut next = [0; 32];
+    Provider::random_buf_with_rng(&mut rng, &mut next).unwrap();
+    assert_eq!(next, expected[32..]);
+}
@@ -0,0 +1,140 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+#![cfg(all(feature = "aead-interop", feature = "provider-xchacha"))]
+
+use vault::{
+    providers::{RustChaCha, RustCryptoAlgorithm, RustCryptoBox, RustXChaCha},
+    BoxProvider, Error, Key,
+};
+
+fn check_roundtrip<P: BoxProvider<Error = Error>>(nonce_len: usize) {
+    assert_eq!(P::box_key_len(), 32);
+    assert_eq!(P::box_nonce_len(), nonce_len);
+    assert_eq!(P::box_overhead(), nonce_len + 16);
+
+    let key = Key::<P>::random().unwrap();
+    for data in [&b""[..], b"some data", &[7; 1000]] {
+        let sealed = P::box_seal(&key, b"ad", data).unwrap();
+        assert_eq!(sealed.len(), data.len() + P::box_overhead());
+        assert_eq!(P::box_open(&key, b"ad", &sealed).unwrap(), data);
+    }
+    assert_ne!(
+        P::box_seal(&key, b"ad", b"some data").unwrap(),
+        P::box_seal(&key, b"ad", b"some data").unwrap()
+    );
+    assert!(P::self_test().unwrap().passed());
+}
+
+fn check_tamper<P: BoxProvider<Error = Error>>() {
+    let key = Key::<P>::random().unwrap();
+    let sealed = P::box_seal(&key, b"ad", b"some data").unwrap();
+
+    for i in [0, P::box_nonce_len(), sealed.len() - 1] {
+        let mut corrupt = sealed.clone();
+        corrupt[i] ^= 1;
+        assert!(matches!(
+            P::box_open(&key, b"ad", &corrupt),
+            Err(Error::AuthenticationFailed)
+        ));
+    }
+    assert!(matches!(
+        P::box_open(&key, b"other ad", &sealed),
+        Err(Error::AuthenticationFailed)
+    ));
+    let other = Key::<P>::random().unwrap();
+    assert!(matches!(
+        P::box_open(&other, b"ad", &sealed),
+        Err(Error::AuthenticationFailed)
+    ));
+    assert!(matches!(
+        P::box_open(&key, b"ad", &sealed[..P::box_overhead() - 1]),
+        Err(Error::MalformedCiphertext { .. })
+    ));
+}
+
+#[test]
+fn test_rust_crypto_chacha() {
+    check_roundtrip::<RustCryptoBox<RustChaCha>>(12);
+    check_tamper::<RustCryptoBox<RustChaCha>>();
+}
+
+#[test]
+fn test_rust_crypto_xchacha() {
+    check_roundtrip::<RustCryptoBox<RustXChaCha>>(24);
+    check_tamper::<RustCryptoBox<RustXChaCha>>();
+}
+
+#[test]
+fn test_rust_crypto_box_id() {
+    assert_eq!(RustCryptoBox::<RustChaCha>::box_id(), *b"c20p");
+    assert_eq!(RustCryptoBox::<RustXChaCha>::box_id(), *b"xc20");
+    assert_ne!(
+        RustCryptoBox::<RustChaCha>::box_id(),
+        RustCryptoBox::<RustXChaCha>::box_id()
+    );
+
+    let key = Key::<RustCryptoBox<RustXChaCha>>::random().unwrap();
+    let sealed = RustCryptoBox::<RustXChaCha>::seal_tagged(&key, b"", b"some data").unwrap();
+    let other = Key::<RustCryptoBox<RustChaCha>>::load_from_slice(key.bytes()).unwrap();
+    assert!(matches!(
+        RustCryptoBox::<RustChaCha>::open_tagged(&other, b"", &sealed),
+        Err(Error::ProviderMismatch { .. })
+    ));
+}
+
+/// the adapter and the hand written provider produce the same boxes
+#[cfg(feature = "provider-xchacha")]
+#[test]
+fn test_rust_crypto_xchacha_interop() {
+    use vault::providers::XChaChaPoly;
+
+    let key = Key::<XChaChaPoly>::random().unwrap();
+    let adapter = Key::<RustCryptoBox<RustXChaCha>>::load_from_slice(key.bytes()).unwrap();
+
+    let sealed = XChaChaPoly::box_seal(&key, b"ad", b"some data").unwrap();
+    assert_eq!(
+        RustCryptoBox::<RustXChaCha>::box_open(&adapter, b"ad", &sealed).unwrap(),
+        b"some data"
+    );
+    let sealed = RustCryptoBox::<RustXChaCha>::box_seal(&adapter, b"ad", b"some data").unwrap();
+    assert_eq!(XChaChaPoly::box_open(&key, b"ad", &sealed).unwrap(), b"some data");
+
+    // the providers share the id, so tagged boxes open with either
+    let sealed = XChaChaPoly::seal_tagged(&key, b"ad", b"some data").unwrap();
+    assert_eq!(
+        RustCryptoBox::<RustXChaCha>::open_tagged(&adapter, b"ad", &sealed).unwrap(),
+        b"some data"
+    );
+}
+
+/// a cipher the vault doesn't ship a marker for
+struct CustomChaCha;
+
+impl RustCryptoAlgorithm for CustomChaCha {
+    type Aead = chacha20poly1305::ChaCha20Poly1305;
+
+    const BOX_ID: [u8; 4] = *b"cust";
+}
+
+#[test]
+fn test_rust_crypto_custom_algorithm() {
+    check_roundtrip::<RustCryptoBox<CustomChaCha>>(12);
+    assert_eq!(RustCryptoBox::<CustomChaCha>::box_id(), *b"cust");
+
+    let key = Key::<RustCryptoBox<CustomChaCha>>::random().unwrap();
+    let chacha = Key::<RustCryptoBox<RustChaCha>>::load_from_slice(key.bytes()).unwrap();
+    let sealed = RustCryptoBox::<CustomChaCha>::box_seal(&key, b"ad", b"some data").unwrap();
+    assert_eq!(
+        RustCryptoBox::<RustChaCha>::box_open(&chacha, b"ad", &sealed).unwrap(),
+        b"some data"
+    );
+}
@@ -0,0 +1,118 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+mod utils;
+
+use utils::alloc::WatchingAllocator;
+#[allow(unused_imports)]
+use vault::{BoxProvider, Encrypt, Key};
+
+#[global_allocator]
+static ALLOC: WatchingAllocator = WatchingAllocator;
+
+struct Plain(Vec<u8>);
+struct Sealed(Vec<u8>);
+
+impl AsRef<[u8]> for Plain {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl From<Vec<u8>> for Sealed {
+    fn from(data: Vec<u8>) -> Self {
+        Self(data)
+    }
+}
+
+impl Encrypt<Sealed> for Plain {}
+
+/// the nonce records of debug builds allocate, see `test_utils::record_nonce`
+#[cfg(feature = "test-utils")]
+use vault::test_utils::without_nonce_records;
+
+#[cfg(not(feature = "test-utils"))]
+fn without_nonce_records<R>(f: impl FnOnce() -> R) -> R {
+    f()
+}
+
+/// assert that `Encrypt::encrypt` makes one allocation per seal, the box with its final length
+#[allow(dead_code)]
+fn check_one_allocation<P: BoxProvider>() {
+    let key = Key::<P>::random().unwrap();
+    for len in [0, 1, 200, 4096].iter() {
+        let plain = Plain(vec![0xa5; *len]);
+        // the first seal may initialize the provider
+        let _: Sealed = plain.encrypt(&key, b"ad").unwrap();
+
+        let (sealed, allocations) = without_nonce_records(|| {
+            let before = WatchingAllocator::allocations();
+            let sealed: Sealed = plain.encrypt(&key, b"ad").unwrap();
+            (sealed, WatchingAllocator::allocations() - before)
+        });
+        assert_eq!(allocations, 1, "{} allocations to seal {} bytes", allocations, len);
+        assert_eq!(sealed.0.len(), len + P::box_overhead());
+        assert_eq!(sealed.0.capacity(), sealed.0.len());
+    }
+}
+
+#[test]
+#[cfg(feature = "provider-xchacha")]
+fn test_xchacha_one_allocation() {
+    check_one_allocation::<vault::providers::XChaChaPoly>();
+}
+
+#[test]
+#[cfg(feature = "provider-aes-gcm")]
+fn test_aes_gcm_one_allocation() {
+    check_one_allocation::<vault::providers::AesGcm256>();
+}
+
+#[test]
+#[cfg(feature = "provider-siv")]
+fn test_siv_one_allocation() {
+    check_one_allocation::<vault::providers::AesGcmSiv>();
+}
+
+#[test]
+#[cfg(feature = "provider-ring")]
+fn test_ring_one_allocation() {
+    check_one_allocation::<vault::providers::RingProvider<vault::providers::RingChaCha>>();
+    check_one_allocation::<vault::providers::RingProvider<vault::providers::RingAesGcm>>();
+}
+
+#[test]
+#[cfg(all(feature = "aead-interop", feature = "provider-xchacha"))]
+fn test_rust_crypto_one_allocation() {
+    check_one_allocation::<vault::providers::RustCryptoBox<vault::providers::RustChaCha>>();
+    check_one_allocation::<vault::providers::RustCryptoBox<vault::providers::RustXChaCha>>();
+}
+
+#[test]
+#[cfg(feature = "provider-sodium")]
+fn test_sodium_one_allocation() {
+    check_one_allocation::<vault::providers::Sodium>();
+}
+
+#[test]
+#[cfg(all(feature = "provider-auto", feature = "test-utils"))]
+fn test_auto_reuses_the_box() {
+    // the derived key and the AD of the inner box are allocated besides the box
+    let key = Key::<vault::providers::Auto>::random().unwrap();
+    let plain = Plain(vec![0xa5; 200]);
+    let _: Sealed = plain.encrypt(&key, b"ad").unwrap();
+    let allocations = without_nonce_records(|| {
+        let before = WatchingAllocator::allocations();
+        let _: Sealed = plain.encrypt(&key, b"ad").unwrap();
+        WatchingAllocator::allocations() - before
+    });
+    assert!(allocations <= 3, "{} allocations", allocations);
+}
@@ -0,0 +1,127 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+#![cfg(feature = "asymmetric")]
+
+mod utils;
+
+use utils::provider::Provider;
+use vault::{kx::StaticSecret, sealed_box, BoxProvider, Error, Key};
+
+#[test]
+fn test_sealed_box_roundtrip() {
+    let recipient = StaticSecret::random::<Provider>().unwrap();
+    let public = recipient.public_key();
+
+    for data in [&b""[..], b"a secret", &[7; 1000]].iter() {
+        let blob = sealed_box::seal::<Provider>(&public, b"ad", data).unwrap();
+        assert_eq!(blob.len(), data.len() + sealed_box::overhead::<Provider>());
+        assert_eq!(blob.len(), data.len() + 32 + Provider::box_overhead());
+        assert_eq!(sealed_box::open::<Provider>(&recipient, b"ad", &blob).unwrap(), *data);
+    }
+
+    // every box has its own ephemeral key
+    let first = sealed_box::seal::<Provider>(&public, b"ad", b"data").unwrap();
+    let second = sealed_box::seal::<Provider>(&public, b"ad", b"data").unwrap();
+    assert_ne!(first[..32], second[..32]);
+    assert_ne!(first, second);
+}
+
+#[test]
+fn test_sealed_box_tampering() {
+    let recipient = StaticSecret::random::<Provider>().unwrap();
+    let blob = sealed_box::seal::<Provider>(&recipient.public_key(), b"ad", b"a secret").unwrap();
+
+    // every byte is authenticated, the ephemeral public key included
+    for i in 0..blob.len() {
+        for bit in [0x01, 0x80].iter() {
+            let mut tampered = blob.clone();
+            tampered[i] ^= bit;
+            assert_eq!(
+                sealed_box::open::<Provider>(&recipient, b"ad", &tampered),
+                Err(Error::AuthenticationFailed),
+                "byte {} bit {:x}",
+                i,
+                bit
+            );
+        }
+    }
+    assert_eq!(
+        sealed_box::open::<Provider>(&recipient, b"other ad", &blob),
+        Err(Error::AuthenticationFailed)
+    );
+
+    // an ephemeral key of low order
+    let mut low_order = blob.clone();
+    low_order[..32].copy_from_slice(&[0; 32]);
+    assert_eq!(
+        sealed_box::open::<Provider>(&recipient, b"ad", &low_order),
+        Err(Error::AuthenticationFailed)
+    );
+
+    // truncated boxes
+    let min = sealed_box::overhead::<Provider>();
+    for len in [0, 31, 32, min - 1].iter() {
+        assert_eq!(
+            sealed_box::open::<Provider>(&recipient, b"ad", &blob[..*len]),
+            Err(Error::MalformedCiphertext {
+                expected_min: min,
+                got: *len
+            })
+        );
+    }
+    assert_eq!(
+        sealed_box::open::<Provider>(&recipient, b"ad", &blob[..min]),
+        Err(Error::AuthenticationFailed)
+    );
+}
+
+#[test]
+fn test_sealed_box_wrong_recipient() {
+    let recipient = StaticSecret::random::<Provider>().unwrap();
+    let other = StaticSecret::random::<Provider>().unwrap();
+    let blob = sealed_box::seal::<Provider>(&recipient.public_key(), b"ad", b"a secret").unwrap();
+
+    assert_eq!(
+        sealed_box::open::<Provider>(&other, b"ad", &blob),
+        Err(Error::AuthenticationFailed)
+    );
+    assert_eq!(
+        sealed_box::open::<Provider>(&recipient, b"ad", &blob).unwrap(),
+        b"a secret"
+    );
+}
+
+#[test]
+fn test_sealed_box_from_key() {
+    let key = Key::<Provider>::random().unwrap();
+    let recipient = StaticSecret::from_key(&key, b"inbox").unwrap();
+
+    // the secret is derived again from the key, nothing else has to be stored
+    let blob = sealed_box::seal::<Provider>(&recipient.public_key(), b"ad", b"a secret").unwrap();
+    let derived = StaticSecret::from_key(&key, b"inbox").unwrap();
+    assert_eq!(derived.public_key(), recipient.public_key());
+    assert_eq!(
+        sealed_box::open::<Provider>(&derived, b"ad", &blob).unwrap(),
+        b"a secret"
+    );
+
+    // other infos and other keys yield other secrets
+    let other_info = StaticSecret::from_key(&key, b"backup").unwrap();
+    assert_ne!(other_info.public_key(), recipient.public_key());
+    let other_key = StaticSecret::from_key(&Key::<Provider>::random().unwrap(), b"inbox").unwrap();
+    assert_ne!(other_key.public_key(), recipient.public_key());
+    assert_eq!(
+        sealed_box::open::<Provider>(&other_info, b"ad", &blob),
+        Err(Error::AuthenticationFailed)
+    );
+    assert_ne!(recipient.bytes(), key.bytes());
+}
@@ -0,0 +1,148 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+mod utils;
+
+use utils::provider::Provider;
+use vault::{BoxProvider, Error, Key, SelfTestReport};
+
+/// a broken provider which "seals" by xoring the data with the first key byte, so tampered boxes open
+struct XorProvider;
+
+impl BoxProvider for XorProvider {
+    type Error = Error;
+
+    fn box_key_len() -> usize {
+        32
+    }
+
+    fn box_overhead() -> usize {
+        0
+    }
+
+    fn box_id() -> [u8; 4] {
+        *b"xor1"
+    }
+
+    fn box_seal(key: &Key<Self>, _: &[u8], data: &[u8]) -> vault::Result<Vec<u8>> {
+        Ok(data.iter().map(|b| b ^ key.bytes()[0]).collect())
+    }
+
+    fn box_open(key: &Key<Self>, ad: &[u8], data: &[u8]) -> vault::Result<Vec<u8>> {
+        Self::box_seal(key, ad, data)
+    }
+
+    fn random_buf(buf: &mut [u8]) -> vault::Result<()> {
+        Provider::random_buf(buf)
+    }
+}
+
+/// a broken provider whose random bytes are all the same
+struct StuckProvider;
+
+impl BoxProvider for StuckProvider {
+    type Error = Error;
+
+    fn box_key_len() -> usize {
+        Provider::box_key_len()
+    }
+
+    fn box_overhead() -> usize {
+        Provider::box_overhead()
+    }
+
+    fn box_id() -> [u8; 4] {
+        *b"stck"
+    }
+
+    fn box_seal(key: &Key<Self>, ad: &[u8], data: &[u8]) -> vault::Result<Vec<u8>> {
+        Provider::box_seal(&Key::load_from_slice(key.bytes())?, ad, data)
+    }
+
+    fn box_open(key: &Key<Self>, ad: &[u8], data: &[u8]) -> vault::Result<Vec<u8>> {
+        Provider::box_open(&Key::load_from_slice(key.bytes())?, ad, data)
+    }
+
+    fn random_buf(buf: &mut [u8]) -> vault::Result<()> {
+        buf.iter_mut().for_each(|b| *b = 0);
+        Ok(())
+    }
+}
+
+fn failures(report: &SelfTestReport) -> Vec<&str> {
+    report.failures().map(|c| c.name.as_str()).collect()
+}
+
+#[allow(dead_code)]
+fn check_passes<P: BoxProvider>(checks: &[&str]) {
+    let report = P::self_test().unwrap();
+    let names: Vec<&str> = report.checks.iter().map(|c| c.name.as_str()).collect();
+    assert_eq!(names, checks);
+    assert!(report.passed(), "failed checks: {:?}", failures(&report));
+    report.ensure_passed().unwrap();
+}
+
+#[test]
+fn test_self_test_passes() {
+    check_passes::<Provider>(&["round trip", "overhead", "tamper", "random"]);
+}
+
+#[cfg(feature = "provider-xchacha")]
+#[test]
+fn test_self_test_xchacha() {
+    check_passes::<vault::providers::XChaChaPoly>(&["round trip", "overhead", "tamper", "random", "known answer"]);
+}
+
+#[cfg(feature = "provider-aes-gcm")]
+#[test]
+fn test_self_test_aes_gcm() {
+    check_passes::<vault::providers::AesGcm256>(&["round trip", "overhead", "tamper", "random", "known answer"]);
+}
+
+#[cfg(feature = "provider-siv")]
+#[test]
+fn test_self_test_siv() {
+    check_passes::<vault::providers::AesGcmSiv>(&["round trip", "overhead", "tamper", "random"]);
+}
+
+#[cfg(feature = "provider-sodium")]
+#[test]
+fn test_self_test_sodium() {
+    check_passes::<vault::providers::Sodium>(&["round trip", "overhead", "tamper", "random", "known answer"]);
+}
+
+#[test]
+fn test_self_test_tamper_failure() {
+    let report = XorProvider::self_test().unwrap();
+    assert!(!report.passed());
+    assert_eq!(failures(&report), ["tamper"]);
+
+    match report.ensure_passed() {
+        Err(e @ Error::SelfTestFailed(_)) => assert_eq!(e.to_string(), "Self test failed: `tamper`"),
+        r => panic!("unexpected result: {:?}", r),
+    }
+}
+
+#[test]
+fn test_self_test_random_failure() {
+    let report = StuckProvider::self_test().unwrap();
+    assert_eq!(failures(&report), ["random"]);
+    assert!(matches!(report.ensure_passed(), Err(Error::SelfTestFailed(check)) if check == "random"));
+}
+
+#[test]
+fn test_self_test_report_serde() {
+    let report = Provider::self_test().unwrap();
+    let json = serde_json::to_string(&report).unwrap();
+    assert!(json.contains("\"name\":\"round trip\""));
+    assert!(json.contains("\"passed\":true"));
+    assert_eq!(serde_json::from_str::<SelfTestReport>(&json).unwrap(), report);
+}
@@ -0,0 +1,126 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+#![cfg(feature = "serde-base64")]
+
+mod utils;
+
+use utils::provider::Provider;
+use vault::{seal_chunked, ChunkedCiphertext, Key, KeyFingerprint, SealedBlob, Tag, WrappedKey};
+
+#[test]
+fn test_tag_json() {
+    let tag = Tag::from(vec![0xfb, 0xff, 0x00, 0x01]);
+    let json = serde_json::to_string(&tag).unwrap();
+    assert_eq!(json, r#""+/8AAQ==""#);
+    assert_eq!(serde_json::from_str::<Tag>(&json).unwrap(), tag);
+}
+
+#[test]
+fn test_tag_bincode() {
+    let tag = Tag::from(vec![0xfb, 0xff, 0x00, 0x01]);
+    let bytes = bincode::serialize(&tag).unwrap();
+    // raw bytes with a length, like `Vec<u8>` without the feature
+    assert_eq!(bytes, bincode::serialize(&vec![0xfbu8, 0xff, 0x00, 0x01]).unwrap());
+    assert_eq!(bytes, [&4u64.to_le_bytes()[..], &[0xfb, 0xff, 0x00, 0x01]].concat());
+    assert_eq!(bincode::deserialize::<Tag>(&bytes).unwrap(), tag);
+}
+
+#[test]
+fn test_fingerprint() {
+    let fingerprint = Key::<Provider>::random().unwrap().fingerprint();
+
+    let json = serde_json::to_string(&fingerprint).unwrap();
+    assert_eq!(json.len(), 2 + 44);
+    assert!(json.ends_with(r#"=""#));
+    assert_eq!(serde_json::from_str::<KeyFingerprint>(&json).unwrap(), fingerprint);
+
+    // binary formats keep the fixed size array without a length
+    let bytes = bincode::serialize(&fingerprint).unwrap();
+    assert_eq!(bytes, fingerprint.as_ref());
+    assert_eq!(bincode::deserialize::<KeyFingerprint>(&bytes).unwrap(), fingerprint);
+}
+
+#[test]
+fn test_wrapped_key() {
+    let kek = Key::<Provider>::random().unwrap();
+    let key = Key::<Provider>::random().unwrap();
+    let wrapped = key.to_wrapped(&kek).unwrap();
+
+    let json = serde_json::to_value(&wrapped).unwrap();
+    assert!(json["sealed"].is_string());
+    let parsed: WrappedKey<Provider> = serde_json::from_value(json).unwrap();
+    assert_eq!(parsed.unwrap(&kek).unwrap().bytes(), key.bytes());
+
+    let bytes = bincode::serialize(&wrapped).unwrap();
+    let parsed: WrappedKey<Provider> = bincode::deserialize(&bytes).unwrap();
+    assert_eq!(parsed.sealed(), wrapped.sealed());
+}
+
+#[test]
+fn test_ciphertexts() {
+    let key = Key::<Provider>::random().unwrap();
+
+    let chunked = seal_chunked(&key, b"ad", &[7; 1000], 256).unwrap();
+    let json = serde_json::to_string(&chunked).unwrap();
+    assert!(json.starts_with('"'));
+    assert_eq!(serde_json::from_str::<ChunkedCiphertext>(&json).unwrap(), chunked);
+    let bytes = bincode::serialize(&chunked).unwrap();
+    assert_eq!(bincode::deserialize::<ChunkedCiphertext>(&bytes).unwrap(), chunked);
+
+    let blob = SealedBlob::seal(&key, b"ad", b"some data").unwrap();
+    let json = serde_json::to_string(&blob).unwrap();
+    assert!(json.starts_with('"'));
+    assert_eq!(serde_json::from_str::<SealedBlob>(&json).unwrap(), blob);
+    let bytes = bincode::serialize(&blob).unwrap();
+    assert_eq!(&bytes[8..], &blob.to_bytes()[..]);
+    assert_eq!(bincode::deserialize::<SealedBlob>(&bytes).unwrap(), blob);
+}
+
+#[test]
+fn test_rejects_invalid() {
+    let err = serde_json::from_str::<Tag>(r#""not base64!""#).unwrap_err();
+    assert!(err.to_string().contains("base64"), "{}", err);
+
+    // the uri-safe character set, missing padding and non canonical padding bits
+    for invalid in &[r#""-_8AAQ==""#, r#""+/8AAQ""#, r#""QR==""#] {
+        assert!(serde_json::from_str::<Tag>(invalid).is_err(), "{}", invalid);
+    }
+
+    let short = serde_json::to_string(&Tag::from(vec![0; 31])).unwrap();
+    let err = serde_json::from_str::<KeyFingerprint>(&short).unwrap_err();
+    assert!(err.to_string().contains("32 bytes"), "{}", err);
+
+    let err = serde_json::from_str::<SealedBlob>(r#""AQ==""#).unwrap_err();
+    assert!(err.to_string().contains("Truncated"), "{}", err);
+}
+
+#[test]
+fn test_integer_arrays() {
+    // JSON written without the feature
+    let tag: Tag = serde_json::from_str("[251, 255, 0, 1]").unwrap();
+    assert_eq!(tag.as_bytes(), &[0xfb, 0xff, 0x00, 0x01]);
+
+    let fingerprint = Key::<Provider>::random().unwrap().fingerprint();
+    let old = serde_json::to_string(fingerprint.as_ref()).unwrap();
+    assert!(old.starts_with('['));
+    assert_eq!(serde_json::from_str::<KeyFingerprint>(&old).unwrap(), fingerprint);
+
+    let key = Key::<Provider>::random().unwrap();
+    let wrapped = key.to_wrapped(&key).unwrap();
+    let old = format!(r#"{{"sealed":{}}}"#, serde_json::to_string(wrapped.sealed()).unwrap());
+    let parsed: WrappedKey<Provider> = serde_json::from_str(&old).unwrap();
+    assert_eq!(parsed.sealed(), wrapped.sealed());
+
+    let chunked = seal_chunked(&key, b"ad", &[7; 100], 64).unwrap();
+    let old = serde_json::to_string(&chunked.to_bytes()).unwrap();
+    assert_eq!(serde_json::from_str::<ChunkedCiphertext>(&old).unwrap(), chunked);
+}
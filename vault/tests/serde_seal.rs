@@ -0,0 +1,113 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+#![cfg(feature = "serde-seal")]
+
+mod utils;
+
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Serialize};
+use utils::provider::Provider;
+use vault::{open_serde, open_serde_with_limit, seal_serde, seal_serde_with_limit, BoxProvider, Error, Key};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Account {
+    name: String,
+    email: Option<String>,
+    phone: Option<String>,
+    groups: BTreeMap<String, HashMap<String, Vec<u32>>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Other {
+    id: u64,
+    enabled: bool,
+}
+
+fn account() -> Account {
+    let mut admins = HashMap::new();
+    admins.insert(String::from("servers"), vec![1, 2, 3]);
+    admins.insert(String::from("printers"), vec![]);
+    let mut groups = BTreeMap::new();
+    groups.insert(String::from("admins"), admins);
+    groups.insert(String::from("users"), HashMap::new());
+
+    Account {
+        name: String::from("alice"),
+        email: Some(String::from("alice@example.com")),
+        phone: None,
+        groups,
+    }
+}
+
+#[test]
+fn test_serde_roundtrip() {
+    let key = Key::<Provider>::random().unwrap();
+
+    let sealed = seal_serde(&key, b"ad", &account()).unwrap();
+    let opened: Account = open_serde(&key, b"ad", &sealed).unwrap();
+    assert_eq!(opened, account());
+
+    let sealed = seal_serde(&key, b"", &Some(7u8)).unwrap();
+    assert_eq!(open_serde::<_, Option<u8>>(&key, b"", &sealed).unwrap(), Some(7));
+}
+
+#[test]
+fn test_serde_errors() {
+    let key = Key::<Provider>::random().unwrap();
+    let sealed = seal_serde(&key, b"ad", &account()).unwrap();
+
+    // a value of another type fails to deserialize instead of failing to open
+    match open_serde::<_, Other>(&key, b"ad", &sealed) {
+        Err(Error::DeserializeError(_)) => {}
+        r => panic!("unexpected result: {:?}", r),
+    }
+    let sealed_other = seal_serde(&key, b"ad", &Other { id: 7, enabled: true }).unwrap();
+    assert!(matches!(
+        open_serde::<_, Account>(&key, b"ad", &sealed_other),
+        Err(Error::DeserializeError(_))
+    ));
+
+    // a wrong key or AD fails to open
+    let other = Key::<Provider>::random().unwrap();
+    assert!(matches!(
+        open_serde::<_, Account>(&other, b"ad", &sealed),
+        Err(Error::AuthenticationFailed)
+    ));
+    assert!(matches!(
+        open_serde::<_, Account>(&key, b"other ad", &sealed),
+        Err(Error::AuthenticationFailed)
+    ));
+}
+
+#[test]
+fn test_serde_size_limit() {
+    let key = Key::<Provider>::random().unwrap();
+    let value = vec![7u8; 100];
+
+    match seal_serde_with_limit(&key, b"", &value, 100) {
+        Err(Error::PayloadTooLarge { len, limit }) => assert_eq!((len, limit), (101, 100)),
+        r => panic!("unexpected result: {:?}", r),
+    }
+    let sealed = seal_serde_with_limit(&key, b"", &value, 101).unwrap();
+    assert_eq!(sealed.len(), 101 + Provider::box_overhead());
+
+    assert!(matches!(
+        open_serde_with_limit::<_, Vec<u8>>(&key, b"", &sealed, 100),
+        Err(Error::PayloadTooLarge { len: 101, limit: 100 })
+    ));
+    assert_eq!(
+        open_serde_with_limit::<_, Vec<u8>>(&key, b"", &sealed, 101).unwrap(),
+        value
+    );
+    assert_eq!(open_serde::<_, Vec<u8>>(&key, b"", &sealed).unwrap(), value);
+}
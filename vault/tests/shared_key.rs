@@ -0,0 +1,122 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+mod utils;
+
+use std::{
+    convert::Infallible,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+};
+
+use utils::provider::Provider;
+use vault::{BoxProvider, Decrypt, Encrypt, Key, SharedKey};
+
+struct Plain(Vec<u8>);
+struct Sealed(Vec<u8>);
+
+impl AsRef<[u8]> for Plain {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl AsRef<[u8]> for Sealed {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl From<Vec<u8>> for Sealed {
+    fn from(data: Vec<u8>) -> Self {
+        Self(data)
+    }
+}
+
+impl From<Vec<u8>> for Plain {
+    fn from(data: Vec<u8>) -> Self {
+        Self(data)
+    }
+}
+
+impl Encrypt<Sealed> for Plain {}
+impl Decrypt<Infallible, Plain> for Sealed {}
+
+fn counted() -> (SharedKey<Provider>, Arc<AtomicUsize>) {
+    let wipes = Arc::new(AtomicUsize::new(0));
+    let mut key = Key::<Provider>::random().unwrap();
+    let counter = wipes.clone();
+    key.on_drop(move |_| {
+        counter.fetch_add(1, Ordering::SeqCst);
+    });
+    (SharedKey::from(key), wipes)
+}
+
+#[test]
+fn test_shared_key_wiped_once() {
+    let (shared, wipes) = counted();
+
+    let handles: Vec<_> = (0..32).map(|_| shared.clone()).collect();
+    assert_eq!(shared.handles(), 33);
+    assert!(handles.iter().all(|h| h.bytes().as_ptr() == shared.bytes().as_ptr()));
+
+    drop(handles);
+    assert_eq!(wipes.load(Ordering::SeqCst), 0);
+    drop(shared);
+    assert_eq!(wipes.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn test_shared_key_encrypt() {
+    let key = Key::<Provider>::random().unwrap();
+    let bytes = key.bytes().to_vec();
+    let shared = SharedKey::from(key);
+
+    let sealed: Sealed = Plain(b"some data".to_vec()).encrypt(&shared, b"ad").unwrap();
+    let opened: Plain = sealed.decrypt(&shared.clone(), b"ad").unwrap();
+    assert_eq!(opened.0, b"some data");
+    assert_eq!(Provider::box_open(&shared, b"ad", &sealed.0).unwrap(), b"some data");
+
+    // the key comes back once it's the last handle
+    let other = shared.clone();
+    let shared = shared.try_unwrap().err().unwrap();
+    drop(other);
+    assert_eq!(shared.try_unwrap().unwrap().bytes(), &bytes[..]);
+}
+
+#[test]
+fn test_shared_key_threads() {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<SharedKey<Provider>>();
+
+    let (shared, wipes) = counted();
+    let sealed: Sealed = Plain(b"some data".to_vec()).encrypt(&shared, b"").unwrap();
+    let sealed = Arc::new(sealed);
+
+    let workers: Vec<_> = (0..8)
+        .map(|_| {
+            let key = shared.clone();
+            let sealed = sealed.clone();
+            thread::spawn(move || {
+                let opened: Plain = sealed.decrypt(&key, b"").unwrap();
+                opened.0
+            })
+        })
+        .collect();
+    drop(shared);
+    for worker in workers {
+        assert_eq!(worker.join().unwrap(), b"some data");
+    }
+    assert_eq!(wipes.load(Ordering::SeqCst), 1);
+}
@@ -0,0 +1,197 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+#![cfg(feature = "provider-ed25519")]
+
+mod utils;
+
+use std::convert::TryFrom;
+
+use utils::{hex, provider::Provider};
+use vault::{providers::Ed25519, BoxProvider, Error, Key, Sign, SignProvider, SigningKey, VerifySig};
+
+fn malformed(verified: vault::Result<()>) -> bool {
+    matches!(verified, Err(Error::MalformedSignature(_)))
+}
+
+#[test]
+fn test_sign_rfc8032() {
+    // section 7.1, tests 1, 2, 3 and SHA(abc)
+    let vectors = [
+        (
+            "9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60",
+            "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a",
+            "",
+            "e5564300c360ac729086e2cc806e828a84877f1eb8e5d974d873e065224901555fb8821590a33bacc61e39701cf9b46bd25bf5f0595bbe24655141438e7a100b",
+        ),
+        (
+            "4ccd089b28ff96da9db6c346ec114e0f5b8a319f35aba624da8cf6ed4fb8a6fb",
+            "3d4017c3e843895a92b70aa74d1b7ebc9c982ccf2ec4968cc0cd55f12af4660c",
+            "72",
+            "92a009a9f0d4cab8720e820b5f642540a2b27b5416503f8fb3762223ebdb69da085ac1e43e15996e458f3613d0f11d8c387b2eaeb4302aeeb00d291612bb0c00",
+        ),
+        (
+            "c5aa8df43f9f837bedb7442f31dcb7b166d38535076f094b85ce3a2e0b4458f7",
+            "fc51cd8e6218a1a38da47ed00230f0580816ed13ba3303ac5deb911548908025",
+            "af82",
+            "6291d657deec24024827e69c3abe01a30ce548a284743a445e3680d7db5ac3ac18ff9b538d16f290ae67f760984dc6594a7c15e9716ed28dc027beceea1ec40a",
+        ),
+        (
+            "833fe62409237b9d62ec77587520911e9a759cec1d19755b7da901b96dca3d42",
+            "ec172b93ad5e563bf4932c70e1245034c35467ef2efd4d64ebf819683467e2bf",
+            "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f",
+            "dc2a4459e7369633a52b1bf277839a00201009a3efbf3ecb69bea2186c26b58909351fc9ac90b3ecfdfbc7c66431e0303dca179c138ac17ad9bef1177331a704",
+        ),
+    ];
+    for (secret, public, message, signature) in vectors.iter() {
+        let key = SigningKey::<Ed25519>::load(hex(secret)).unwrap();
+        let message = hex(message);
+        assert_eq!(key.public_key().unwrap(), hex(public));
+        assert_eq!(message.sign(&key).unwrap(), hex(signature));
+        message
+            .verify_signature::<Ed25519>(&hex(public), &hex(signature))
+            .unwrap();
+    }
+}
+
+#[test]
+fn test_sign_errors() {
+    let key = SigningKey::<Ed25519>::random().unwrap();
+    let public = key.public_key().unwrap();
+    let signature = b"some data".sign(&key).unwrap();
+    assert_eq!(signature.len(), Ed25519::signature_len());
+    assert_eq!(public.len(), Ed25519::public_key_len());
+    b"some data".verify_signature::<Ed25519>(&public, &signature).unwrap();
+
+    // well-formed signatures which don't match
+    let other = SigningKey::<Ed25519>::random().unwrap().public_key().unwrap();
+    assert_eq!(
+        b"some data".verify_signature::<Ed25519>(&other, &signature),
+        Err(Error::SignatureMismatch)
+    );
+    assert_eq!(
+        b"other data".verify_signature::<Ed25519>(&public, &signature),
+        Err(Error::SignatureMismatch)
+    );
+    for i in 0..64 {
+        let mut tampered = signature.clone();
+        tampered[i] ^= 1;
+        let verified = b"some data".verify_signature::<Ed25519>(&public, &tampered);
+        match i < 32 {
+            // a tampered R may not be a point
+            true => assert!(verified == Err(Error::SignatureMismatch) || malformed(verified)),
+            // S stays canonical
+            false => assert_eq!(verified, Err(Error::SignatureMismatch)),
+        }
+    }
+
+    // malformed signatures and public keys
+    let verify = |public: &[u8], signature: &[u8]| b"some data".verify_signature::<Ed25519>(public, signature);
+    assert!(malformed(verify(&public, &signature[..63])));
+    assert!(malformed(verify(&public, &[&signature[..], &[0]].concat())));
+    assert!(malformed(verify(&public[..31], &signature)));
+    let mut not_a_point = [0; 32];
+    not_a_point[0] = 2;
+    assert!(malformed(verify(&not_a_point, &signature)));
+    let mut r_not_a_point = signature.clone();
+    r_not_a_point[..32].copy_from_slice(&not_a_point);
+    assert!(malformed(verify(&public, &r_not_a_point)));
+
+    // the identity is of small order: as public key it verifies R = [S]B for any S, as R it isn't a valid nonce
+    let identity = hex("0100000000000000000000000000000000000000000000000000000000000000");
+    let base = hex("5866666666666666666666666666666666666666666666666666666666666666");
+    let forged = [&base[..], &identity[..]].concat();
+    assert!(malformed(verify(&identity, &forged)));
+    let mut small_r = signature.clone();
+    small_r[..32].copy_from_slice(&identity);
+    assert!(malformed(verify(&public, &small_r)));
+
+    // S + L is the same scalar, but not canonical
+    let order = hex("edd3f55c1a631258d69cf7a2def9de1400000000000000000000000000000010");
+    let mut malleated = signature.clone();
+    let mut carry = 0;
+    for (s, l) in malleated[32..].iter_mut().zip(order.iter()) {
+        let sum = *s as u16 + *l as u16 + carry;
+        *s = sum as u8;
+        carry = sum >> 8;
+    }
+    assert!(malformed(verify(&public, &malleated)));
+
+    // both are invalid data
+    assert_ne!(
+        std::mem::discriminant(&Error::SignatureMismatch),
+        std::mem::discriminant(&Error::MalformedSignature(String::new()))
+    );
+    assert_eq!(Error::SignatureMismatch.io_kind(), std::io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn test_sign_key() {
+    let key = SigningKey::<Ed25519>::random().unwrap();
+    assert_ne!(key, SigningKey::<Ed25519>::random().unwrap());
+    assert_eq!(key.clone(), key);
+    assert_eq!(SigningKey::<Ed25519>::try_from(key.bytes()).unwrap(), key);
+    assert!(!key.is_guarded());
+
+    let debug = format!("{:?}", key);
+    assert!(debug.contains("len: 32"));
+    assert!(!debug.contains(&format!("{:?}", key.bytes())));
+
+    assert_eq!(
+        SigningKey::<Ed25519>::load(vec![0; 31]).err().unwrap(),
+        Error::InvalidKeyLength {
+            expected: 32,
+            actual: 31
+        }
+    );
+
+    // signatures are deterministic
+    assert_eq!(b"data".sign(&key).unwrap(), b"data".to_vec().sign(&key).unwrap());
+    assert_eq!("data".sign(&key).unwrap(), b"data".sign(&key).unwrap());
+}
+
+#[test]
+fn test_sign_then_seal() {
+    let signing = SigningKey::<Ed25519>::random().unwrap();
+    let public = signing.public_key().unwrap();
+    let key = Key::<Provider>::random().unwrap();
+
+    // the signature is sealed along with the data, only holders of the key learn who signed it
+    let data = b"a record";
+    let signed = [&data[..], &data.sign(&signing).unwrap()].concat();
+    let sealed = Provider::box_seal(&key, b"ad", &signed).unwrap();
+
+    let opened = Provider::box_open(&key, b"ad", &sealed).unwrap();
+    let (opened_data, signature) = opened.split_at(opened.len() - Ed25519::signature_len());
+    assert_eq!(opened_data, data);
+    opened_data.verify_signature::<Ed25519>(&public, signature).unwrap();
+}
+
+#[test]
+fn test_seal_then_sign() {
+    let signing = SigningKey::<Ed25519>::random().unwrap();
+    let public = signing.public_key().unwrap();
+    let key = Key::<Provider>::random().unwrap();
+
+    // the signature covers the sealed box, anyone can check who produced it without the key
+    let sealed = Provider::box_seal(&key, b"ad", b"a record").unwrap();
+    let signature = sealed.sign(&signing).unwrap();
+    sealed.verify_signature::<Ed25519>(&public, &signature).unwrap();
+    assert_eq!(Provider::box_open(&key, b"ad", &sealed).unwrap(), b"a record");
+
+    // a tampered box fails the signature before it is opened
+    let mut tampered = sealed.clone();
+    *tampered.last_mut().unwrap() ^= 1;
+    assert_eq!(
+        tampered.verify_signature::<Ed25519>(&public, &signature),
+        Err(Error::SignatureMismatch)
+    );
+}
@@ -0,0 +1,105 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+#![cfg(feature = "provider-siv")]
+
+mod utils;
+
+use utils::hex;
+use vault::{providers::AesGcmSiv, BoxProvider, Decrypt, Encrypt, Error, Key};
+
+#[test]
+fn test_siv_roundtrip() {
+    let key = Key::<AesGcmSiv>::random().unwrap();
+
+    let sealed = b"some data".to_vec().encrypt(&key, b"ad").unwrap();
+    let opened: Vec<u8> = sealed.decrypt(&key, b"ad").unwrap();
+    assert_eq!(opened, b"some data");
+
+    let sealed = AesGcmSiv::box_seal(&key, b"", b"").unwrap();
+    assert_eq!(sealed.len(), AesGcmSiv::box_overhead());
+    assert_eq!(AesGcmSiv::box_open(&key, b"", &sealed).unwrap(), b"");
+}
+
+#[test]
+fn test_siv_authentication_failure() {
+    let key = Key::<AesGcmSiv>::random().unwrap();
+    let sealed = AesGcmSiv::box_seal(&key, b"ad", b"some data").unwrap();
+
+    for i in 0..sealed.len() {
+        let mut tampered = sealed.clone();
+        tampered[i] ^= 1;
+        assert!(matches!(
+            AesGcmSiv::box_open(&key, b"ad", &tampered),
+            Err(Error::AuthenticationFailed)
+        ));
+    }
+    // data too short for a box is malformed, a shorter box doesn't authenticate
+    for len in 0..sealed.len() {
+        match AesGcmSiv::box_open(&key, b"ad", &sealed[..len]) {
+            Err(Error::MalformedCiphertext { expected_min, got }) => {
+                assert_eq!((expected_min, got), (AesGcmSiv::box_overhead(), len));
+            }
+            Err(Error::AuthenticationFailed) => assert!(len >= AesGcmSiv::box_overhead()),
+            other => panic!("unexpected result for {} bytes: {:?}", len, other),
+        }
+    }
+    assert!(matches!(
+        AesGcmSiv::box_open(&key, b"other ad", &sealed),
+        Err(Error::AuthenticationFailed)
+    ));
+}
+
+#[test]
+fn test_siv_nonce_reuse() {
+    let key = Key::<AesGcmSiv>::random().unwrap();
+    let nonce = [9; 12];
+
+    let first = AesGcmSiv::box_seal_with_nonce(&key, &nonce, b"ad", b"same plaintext").unwrap();
+    let second = AesGcmSiv::box_seal_with_nonce(&key, &nonce, b"ad", b"same plaintext").unwrap();
+    assert_eq!(first, second);
+
+    // differing plaintexts under the same nonce don't share a keystream
+    let a = AesGcmSiv::box_seal_with_nonce(&key, &nonce, b"ad", &[0; 32]).unwrap();
+    let b = AesGcmSiv::box_seal_with_nonce(&key, &nonce, b"ad", &[1; 32]).unwrap();
+    let xored: Vec<u8> = a[12..44].iter().zip(&b[12..44]).map(|(a, b)| a ^ b).collect();
+    assert_ne!(xored, [1; 32]);
+    assert_ne!(&a[44..], &b[44..]);
+
+    assert_eq!(AesGcmSiv::box_open(&key, b"ad", &a).unwrap(), [0; 32]);
+    assert_eq!(AesGcmSiv::box_open(&key, b"ad", &b).unwrap(), [1; 32]);
+
+    assert!(matches!(
+        AesGcmSiv::box_seal_with_nonce(&key, &[0; 24], b"", b""),
+        Err(Error::InvalidNonceLength {
+            expected: 12,
+            actual: 24
+        })
+    ));
+}
+
+/// AES-256-GCM-SIV test vectors of RFC 8452 appendix C.2
+#[test]
+fn test_siv_known_answer() {
+    let key = Key::<AesGcmSiv>::load(hex("0100000000000000000000000000000000000000000000000000000000000000")).unwrap();
+    let nonce = hex("030000000000000000000000");
+
+    let sealed = AesGcmSiv::box_seal_with_nonce(&key, &nonce, b"", b"").unwrap();
+    assert_eq!(sealed[12..], hex("07f5f4169bbf55a8400cd47ea6fd400f")[..]);
+
+    let plain = hex("0100000000000000");
+    let sealed = AesGcmSiv::box_seal_with_nonce(&key, &nonce, b"", &plain).unwrap();
+    assert_eq!(
+        sealed[12..],
+        hex("c2ef328e5c71c83b843122130f7364b761e0b97427e3df28")[..]
+    );
+    assert_eq!(AesGcmSiv::box_open(&key, b"", &sealed).unwrap(), plain);
+}
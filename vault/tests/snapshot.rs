@@ -0,0 +1,408 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+#![cfg(feature = "password-kdf")]
+
+mod utils;
+
+use std::{fs, path::PathBuf};
+
+use utils::provider::{IetfProvider, Provider};
+use vault::{snapshot, BoxProvider, Error, KdfParams, ReadResult};
+
+/// cheap parameters to keep the tests fast.
+const PARAMS: KdfParams = KdfParams {
+    memory_cost: 32,
+    iterations: 1,
+    parallelism: 1,
+};
+
+fn temp_dir(name: &str) -> PathBuf {
+    let mut suffix = [0; 8];
+    Provider::random_buf(&mut suffix).unwrap();
+    let suffix: String = suffix.iter().map(|b| format!("{:02x}", b)).collect();
+    let dir = std::env::temp_dir().join(format!("vault-{}-{}", name, suffix));
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn records() -> Vec<ReadResult> {
+    (0..100u8)
+        .map(|i| ReadResult::new(vec![i; 24], vec![i; i as usize * 1000]))
+        .collect()
+}
+
+fn assert_same(a: &[ReadResult], b: &[ReadResult]) {
+    assert_eq!(a.len(), b.len());
+    for (a, b) in a.iter().zip(b) {
+        assert_eq!(a.id(), b.id());
+        assert_eq!(a.data(), b.data());
+    }
+}
+
+fn round_trip<P: BoxProvider>(name: &str) {
+    let dir = temp_dir(name);
+    let path = dir.join("vault.snapshot");
+    let records = records();
+
+    snapshot::export_with_params::<P>(&records, b"password", &path, PARAMS).unwrap();
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+    let imported = snapshot::import::<P>(&path, b"password").unwrap();
+    assert_same(&records, &imported);
+
+    let mut count = 0;
+    snapshot::import_each::<P>(&path, b"password", |record| {
+        assert_eq!(record.id(), records[count].id());
+        count += 1;
+        Ok(())
+    })
+    .unwrap();
+    assert_eq!(count, records.len());
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_round_trip() {
+    round_trip::<Provider>("snapshot");
+    round_trip::<IetfProvider>("snapshot-ietf");
+}
+
+#[test]
+fn test_empty() {
+    let dir = temp_dir("snapshot-empty");
+    let path = dir.join("vault.snapshot");
+
+    snapshot::export_with_params::<Provider>(&[], b"password", &path, PARAMS).unwrap();
+    assert!(snapshot::import::<Provider>(&path, b"password").unwrap().is_empty());
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_wrong_password() {
+    let dir = temp_dir("snapshot-password");
+    let path = dir.join("vault.snapshot");
+
+    snapshot::export_with_params::<Provider>(&records(), b"password", &path, PARAMS).unwrap();
+    assert!(matches!(
+        snapshot::import::<Provider>(&path, b"passw0rd"),
+        Err(Error::WrongPassword)
+    ));
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_corrupt() {
+    let dir = temp_dir("snapshot-corrupt");
+    let path = dir.join("vault.snapshot");
+    snapshot::export_with_params::<Provider>(&records(), b"password", &path, PARAMS).unwrap();
+    let bytes = fs::read(&path).unwrap();
+    let corrupt = dir.join("corrupt.snapshot");
+
+    // truncated inside the body, the trailer and the header
+    for len in [bytes.len() / 2, bytes.len() - 1, snapshot::HEADER_LEN + 10, 20, 0] {
+        fs::write(&corrupt, &bytes[..len]).unwrap();
+        let mut parsed = false;
+        let result = snapshot::import_each::<Provider>(&corrupt, b"password", |_| {
+            parsed = true;
+            Ok(())
+        });
+        assert!(matches!(result, Err(Error::CorruptSnapshot(_))), "{}", len);
+        assert!(!parsed);
+    }
+
+    // a flipped bit in the body or the MAC
+    for at in [snapshot::HEADER_LEN + 100, bytes.len() / 2, bytes.len() - 1] {
+        let mut tampered = bytes.clone();
+        tampered[at] ^= 1;
+        fs::write(&corrupt, &tampered).unwrap();
+        let mut parsed = false;
+        let result = snapshot::import_each::<Provider>(&corrupt, b"password", |_| {
+            parsed = true;
+            Ok(())
+        });
+        assert!(matches!(result, Err(Error::CorruptSnapshot(_))), "{}", at);
+        assert!(!parsed);
+    }
+
+    // appended bytes
+    let mut appended = bytes.clone();
+    appended.extend_from_slice(b"more");
+    fs::write(&corrupt, &appended).unwrap();
+    assert!(matches!(
+        snapshot::import::<Provider>(&corrupt, b"password"),
+        Err(Error::CorruptSnapshot(_))
+    ));
+
+    // not a snapshot
+    fs::write(&corrupt, b"not a snapshot at all, just some bytes").unwrap();
+    assert!(matches!(
+        snapshot::import::<Provider>(&corrupt, b"password"),
+        Err(Error::CorruptSnapshot(_))
+    ));
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_header() {
+    let dir = temp_dir("snapshot-header");
+    let path = dir.join("vault.snapshot");
+    snapshot::export_with_params::<Provider>(&records(), b"password", &path, PARAMS).unwrap();
+    let bytes = fs::read(&path).unwrap();
+    assert_eq!(bytes[..4], snapshot::MAGIC);
+    assert_eq!(bytes[4], snapshot::VERSION);
+
+    assert!(matches!(
+        snapshot::import::<IetfProvider>(&path, b"password"),
+        Err(Error::ProviderMismatch { .. })
+    ));
+
+    let mut version = bytes.clone();
+    version[4] = 2;
+    let other = dir.join("other.snapshot");
+    fs::write(&other, &version).unwrap();
+    assert!(matches!(
+        snapshot::import::<Provider>(&other, b"password"),
+        Err(Error::UnsupportedVersion(2))
+    ));
+
+    // a changed salt derives another key and fails as a wrong password would
+    let mut salt = bytes;
+    salt[30] ^= 1;
+    fs::write(&other, &salt).unwrap();
+    assert!(matches!(
+        snapshot::import::<Provider>(&other, b"password"),
+        Err(Error::WrongPassword)
+    ));
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+mod merge {
+    use super::*;
+    use std::{
+        collections::HashMap,
+        time::{Duration, SystemTime, UNIX_EPOCH},
+    };
+    use vault::{
+        snapshot::{MergePolicy, Side},
+        Clock, DBView, DBWriter, DeleteRequest, Id, Key, ListResult, ReadRequest, RecordHint, WriteRequest,
+    };
+
+    struct FixedClock(SystemTime);
+
+    impl Clock for FixedClock {
+        fn now(&self) -> SystemTime {
+            self.0
+        }
+    }
+
+    /// the entries of the store of a site
+    #[derive(Clone, Default)]
+    struct Site(HashMap<Vec<u8>, Vec<u8>>);
+
+    impl Site {
+        fn apply(&mut self, to_write: Vec<WriteRequest>, to_delete: Vec<DeleteRequest>) {
+            for request in to_write {
+                let (id, data) = request.into();
+                self.0.insert(id, data);
+            }
+            for request in to_delete {
+                let id: Vec<u8> = request.into();
+                self.0.remove(&id);
+            }
+        }
+
+        fn list(&self) -> ListResult {
+            ListResult::new(self.0.keys().cloned().collect())
+        }
+
+        fn read(&self, request: ReadRequest) -> Option<ReadResult> {
+            let id: Vec<u8> = request.into();
+            self.0.get(&id).map(|data| ReadResult::new(id, data.clone()))
+        }
+
+        fn view(&self, key: &Key<Provider>) -> DBView<Provider> {
+            DBView::load(key.clone(), self.list()).unwrap()
+        }
+
+        fn entries(&self) -> Vec<ReadResult> {
+            self.0
+                .iter()
+                .map(|(id, data)| ReadResult::new(id.clone(), data.clone()))
+                .collect()
+        }
+
+        /// the site of the `entries` exported and imported through a snapshot
+        fn from_snapshot(name: &str, entries: &[ReadResult]) -> Self {
+            let dir = temp_dir(name);
+            let path = dir.join("merged.snapshot");
+            snapshot::export_with_params::<Provider>(entries, b"password", &path, PARAMS).unwrap();
+            let imported = snapshot::import::<Provider>(&path, b"password").unwrap();
+            fs::remove_dir_all(dir).unwrap();
+            Site(imported.into_iter().map(Into::into).collect())
+        }
+    }
+
+    fn hint() -> RecordHint {
+        RecordHint::new(b"hint").unwrap()
+    }
+
+    /// writes `data` to the chain of `owner` at `site`
+    fn write(site: &mut Site, key: &Key<Provider>, owner: Id, data: &[u8]) -> Id {
+        let (id, requests) = site.view(key).writer(owner).write(data, hint()).unwrap();
+        site.apply(requests, Vec::new());
+        id
+    }
+
+    fn read(site: &Site, key: &Key<Provider>, id: Id) -> vault::Result<Vec<u8>> {
+        let view = site.view(key);
+        let reader = view.reader();
+        let res = site.read(reader.prepare_read(id)?).unwrap();
+        reader.read(res)
+    }
+
+    #[test]
+    fn test_merge_disjoint() {
+        let key = Key::<Provider>::random().unwrap();
+        let (owner_a, owner_b) = (Id::random::<Provider>().unwrap(), Id::random::<Provider>().unwrap());
+        let (mut a, mut b) = (Site::default(), Site::default());
+        a.apply(vec![DBWriter::create_chain(&key, owner_a)], Vec::new());
+        b.apply(vec![DBWriter::create_chain(&key, owner_b)], Vec::new());
+        let in_a = write(&mut a, &key, owner_a, b"written at a");
+        let in_b = write(&mut b, &key, owner_b, b"written at b");
+
+        let diff = snapshot::diff(&a.entries(), &b.entries());
+        assert_eq!((diff.only_in_a.len(), diff.only_in_b.len()), (3, 3));
+        assert!(diff.different.is_empty());
+        assert!(diff.only_in_a.iter().any(|info| info.id == in_a.as_ref()));
+        assert!(snapshot::diff(&a.entries(), &a.entries()).is_empty());
+
+        let merged = snapshot::merge(&a.entries(), &b.entries(), MergePolicy::PreferNewer);
+        let merged = Site::from_snapshot("merge-disjoint", &merged);
+        assert_eq!(merged.view(&key).records().count(), 2);
+        assert_eq!(read(&merged, &key, in_a).unwrap(), b"written at a");
+        assert_eq!(read(&merged, &key, in_b).unwrap(), b"written at b");
+        assert!(snapshot::diff(
+            &merged.entries(),
+            &snapshot::merge(&b.entries(), &a.entries(), MergePolicy::PreferA)
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn test_merge_conflicts() {
+        let key = Key::<Provider>::random().unwrap();
+        let owner = Id::random::<Provider>().unwrap();
+        let start = UNIX_EPOCH + Duration::from_secs(1_600_000_000);
+        let mut a = Site::default();
+        a.apply(vec![DBWriter::create_chain(&key, owner)], Vec::new());
+        let (id, requests) = a
+            .view(&key)
+            .writer(owner)
+            .write_versioned(b"first", hint(), &FixedClock(start))
+            .unwrap();
+        a.apply(requests, Vec::new());
+
+        // both sites write the second version, b writes it later
+        let mut b = a.clone();
+        for (site, data, at) in [(&mut a, b"edit at a", 10), (&mut b, b"edit at b", 20)] {
+            let clock = FixedClock(start + Duration::from_secs(at));
+            let list = site.list();
+            let (version, request) = site
+                .view(&key)
+                .writer(owner)
+                .write_version(id, data, &list, &clock)
+                .unwrap();
+            assert_eq!(version, 2);
+            site.apply(vec![request], Vec::new());
+        }
+
+        let diff = snapshot::diff(&a.entries(), &b.entries());
+        assert!(diff.only_in_a.is_empty() && diff.only_in_b.is_empty());
+        assert_eq!(diff.different.len(), 1);
+        let (in_a, in_b) = &diff.different[0];
+        assert_eq!(in_a.version.unwrap().version, 2);
+        assert_eq!(in_b.time(), Some(start + Duration::from_secs(20)));
+
+        let latest = |merged: Vec<ReadResult>| {
+            let merged = Site::from_snapshot("merge-conflicts", &merged);
+            let list = merged.list();
+            assert_eq!(merged.view(&key).history(id, &list).unwrap().len(), 2);
+            merged
+                .view(&key)
+                .reader()
+                .read_latest(id, &list, |req| merged.read(req))
+                .unwrap()
+        };
+        let (a, b) = (a.entries(), b.entries());
+        assert_eq!(latest(snapshot::merge(&a, &b, MergePolicy::PreferNewer)), b"edit at b");
+        assert_eq!(latest(snapshot::merge(&b, &a, MergePolicy::PreferNewer)), b"edit at b");
+        assert_eq!(latest(snapshot::merge(&a, &b, MergePolicy::PreferA)), b"edit at a");
+        assert_eq!(latest(snapshot::merge(&a, &b, MergePolicy::PreferB)), b"edit at b");
+
+        let mut asked = Vec::new();
+        let mut resolve = |a: &snapshot::EntryInfo, b: &snapshot::EntryInfo| {
+            asked.push((a.clone(), b.clone()));
+            Side::A
+        };
+        let merged = snapshot::merge(&a, &b, MergePolicy::Resolve(&mut resolve));
+        assert_eq!(latest(merged), b"edit at a");
+        assert_eq!(asked, diff.different);
+    }
+
+    #[test]
+    fn test_merge_tombstones() {
+        let key = Key::<Provider>::random().unwrap();
+        let owner = Id::random::<Provider>().unwrap();
+        let mut a = Site::default();
+        a.apply(vec![DBWriter::create_chain(&key, owner)], Vec::new());
+        let revoked = write(&mut a, &key, owner, b"revoked at a");
+        let kept = write(&mut a, &key, owner, b"kept");
+        let b = a.clone();
+
+        // a revokes the record and exports the tombstone of its payload
+        let (revocation, delete) = a.view(&key).writer(owner).revoke(revoked).unwrap();
+        let deleted_at = UNIX_EPOCH + Duration::from_secs(1_600_000_000);
+        let tombstone = snapshot::tombstone(delete.id(), deleted_at);
+        a.apply(vec![revocation], vec![delete]);
+        let mut exported = a.entries();
+        exported.push(tombstone.clone());
+
+        for merged in [
+            snapshot::merge(&exported, &b.entries(), MergePolicy::PreferB),
+            snapshot::merge(&b.entries(), &exported, MergePolicy::PreferA),
+        ] {
+            assert!(!merged.iter().any(|entry| entry.id() == revoked.as_ref()));
+            assert!(merged.iter().any(|entry| entry.id() == tombstone.id()));
+
+            // the tombstone keeps the payload deleted through further merges
+            let merged = Site::from_snapshot("merge-tombstones", &merged);
+            let again = snapshot::merge(&b.entries(), &merged.entries(), MergePolicy::PreferA);
+            assert!(!again.iter().any(|entry| entry.id() == revoked.as_ref()));
+
+            let info = snapshot::diff(&merged.entries(), &b.entries())
+                .only_in_a
+                .into_iter()
+                .find(|info| info.id == tombstone.id())
+                .unwrap();
+            assert_eq!(info.deleted_at, Some(deleted_at));
+            assert_eq!(
+                merged.view(&key).records().map(|(id, _)| id).collect::<Vec<_>>(),
+                vec![kept]
+            );
+            assert_eq!(read(&merged, &key, kept).unwrap(), b"kept");
+        }
+    }
+}
@@ -0,0 +1,156 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+#![cfg(feature = "provider-sodium")]
+
+use std::{convert::Infallible, os::raw::c_int, ptr};
+
+use vault::{providers::Sodium, BoxProvider, Decrypt, Encrypt, Error, Key};
+
+// the libsodium functions used as reference.  They are linked through the `provider-sodium` feature.
+extern "C" {
+    fn crypto_aead_xchacha20poly1305_ietf_encrypt(
+        c: *mut u8,
+        clen: *mut u64,
+        m: *const u8,
+        mlen: u64,
+        ad: *const u8,
+        adlen: u64,
+        nsec: *const u8,
+        npub: *const u8,
+        k: *const u8,
+    ) -> c_int;
+    fn crypto_aead_xchacha20poly1305_ietf_decrypt(
+        m: *mut u8,
+        mlen: *mut u64,
+        nsec: *mut u8,
+        c: *const u8,
+        clen: u64,
+        ad: *const u8,
+        adlen: u64,
+        npub: *const u8,
+        k: *const u8,
+    ) -> c_int;
+}
+
+/// a box sealed outside of the vault
+struct Sealed(Vec<u8>);
+
+impl AsRef<[u8]> for Sealed {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl Decrypt<Infallible, Vec<u8>> for Sealed {}
+
+/// seal `data` the way a libsodium application does
+fn libsodium_seal(key: &[u8], nonce: &[u8; 24], ad: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut cipher = vec![0; data.len() + 16];
+    let mut len = 0;
+    let ret = unsafe {
+        crypto_aead_xchacha20poly1305_ietf_encrypt(
+            cipher.as_mut_ptr(),
+            &mut len,
+            data.as_ptr(),
+            data.len() as u64,
+            ad.as_ptr(),
+            ad.len() as u64,
+            ptr::null(),
+            nonce.as_ptr(),
+            key.as_ptr(),
+        )
+    };
+    assert_eq!(ret, 0);
+    assert_eq!(len as usize, cipher.len());
+    [&nonce[..], &cipher].concat()
+}
+
+/// open a box the way a libsodium application does
+fn libsodium_open(key: &[u8], ad: &[u8], data: &[u8]) -> Option<Vec<u8>> {
+    let (nonce, cipher) = data.split_at(24);
+    let mut plain = vec![0; cipher.len() - 16];
+    let mut len = 0;
+    let ret = unsafe {
+        crypto_aead_xchacha20poly1305_ietf_decrypt(
+            plain.as_mut_ptr(),
+            &mut len,
+            ptr::null_mut(),
+            cipher.as_ptr(),
+            cipher.len() as u64,
+            ad.as_ptr(),
+            ad.len() as u64,
+            nonce.as_ptr(),
+            key.as_ptr(),
+        )
+    };
+    match ret {
+        0 => Some(plain),
+        _ => None,
+    }
+}
+
+#[test]
+fn test_sodium_roundtrip() {
+    let key = Key::<Sodium>::random().unwrap();
+
+    let sealed = b"some data".to_vec().encrypt(&key, b"ad").unwrap();
+    let opened: Vec<u8> = sealed.decrypt(&key, b"ad").unwrap();
+    assert_eq!(opened, b"some data");
+
+    let sealed = Sodium::box_seal(&key, b"", b"").unwrap();
+    assert_eq!(sealed.len(), Sodium::box_overhead());
+    assert_eq!(Sodium::box_open(&key, b"", &sealed).unwrap(), b"");
+
+    assert!(matches!(
+        Sodium::box_open(&key, b"other ad", &sealed),
+        Err(Error::AuthenticationFailed)
+    ));
+    assert!(matches!(
+        Sodium::box_open(&key, b"", &sealed[1..]),
+        Err(Error::MalformedCiphertext { .. })
+    ));
+}
+
+#[test]
+fn test_sodium_opens_libsodium_boxes() {
+    let key = Key::<Sodium>::random().unwrap();
+    let mut nonce = [0; 24];
+    Sodium::random_buf(&mut nonce).unwrap();
+
+    let sealed = Sealed(libsodium_seal(key.bytes(), &nonce, b"ad", b"from libsodium"));
+    let opened: Vec<u8> = sealed.decrypt(&key, b"ad").unwrap();
+    assert_eq!(opened, b"from libsodium");
+}
+
+#[test]
+fn test_libsodium_opens_sodium_boxes() {
+    let key = Key::<Sodium>::random().unwrap();
+
+    let sealed = Sodium::box_seal(&key, b"ad", b"from vault").unwrap();
+    assert_eq!(libsodium_open(key.bytes(), b"ad", &sealed).unwrap(), b"from vault");
+    assert_eq!(libsodium_open(key.bytes(), b"other ad", &sealed), None);
+}
+
+#[cfg(feature = "provider-xchacha")]
+#[test]
+fn test_sodium_xchacha_interop() {
+    use vault::providers::XChaChaPoly;
+
+    let key = Key::<Sodium>::random().unwrap();
+    let other: Key<XChaChaPoly> = key.clone().convert().unwrap();
+
+    let sealed = Sodium::box_seal(&key, b"ad", b"some data").unwrap();
+    assert_eq!(XChaChaPoly::box_open(&other, b"ad", &sealed).unwrap(), b"some data");
+
+    let sealed = XChaChaPoly::box_seal(&other, b"ad", b"some data").unwrap();
+    assert_eq!(Sodium::box_open(&key, b"ad", &sealed).unwrap(), b"some data");
+}
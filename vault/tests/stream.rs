@@ -0,0 +1,158 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+mod utils;
+
+use std::io::{self, ErrorKind, Read, Write};
+
+use utils::provider::Provider;
+use vault::{BoxProvider, Error, Key};
+
+const CHUNK_SIZE: usize = 64;
+
+fn seal(key: &Key<Provider>, data: &[u8]) -> Vec<u8> {
+    let mut sealed = Vec::new();
+    Provider::seal_stream(key, b"ad", &mut &data[..], &mut sealed, CHUNK_SIZE).unwrap();
+    sealed
+}
+
+fn open(key: &Key<Provider>, sealed: &[u8]) -> vault::Result<Vec<u8>> {
+    let mut opened = Vec::new();
+    Provider::open_stream(key, b"ad", &mut &sealed[..], &mut opened, CHUNK_SIZE).map(|_| opened)
+}
+
+/// split a sealed stream into its frames
+fn frames(sealed: &[u8]) -> Vec<&[u8]> {
+    let mut frames = Vec::new();
+    let mut rest = sealed;
+    while !rest.is_empty() {
+        let len = u32::from_be_bytes([rest[1], rest[2], rest[3], rest[4]]) as usize;
+        let (frame, tail) = rest.split_at(5 + len);
+        frames.push(frame);
+        rest = tail;
+    }
+    frames
+}
+
+#[test]
+fn test_stream_roundtrip() {
+    let key = Key::<Provider>::random().unwrap();
+
+    for len in &[1, CHUNK_SIZE - 1, CHUNK_SIZE, CHUNK_SIZE + 1, 10 * CHUNK_SIZE + 7] {
+        let data = Provider::random_vec(*len).unwrap();
+        let sealed = seal(&key, &data);
+        assert_eq!(frames(&sealed).len(), len / CHUNK_SIZE + 1);
+        assert_eq!(open(&key, &sealed).unwrap(), data);
+    }
+
+    let mut opened = Vec::new();
+    let sealed = seal(&key, &[0; 100]);
+    assert!(Provider::open_stream(&key, b"other ad", &mut &sealed[..], &mut opened, CHUNK_SIZE).is_err());
+}
+
+#[test]
+fn test_stream_empty() {
+    let key = Key::<Provider>::random().unwrap();
+
+    let sealed = seal(&key, &[]);
+    assert_eq!(frames(&sealed).len(), 1);
+    assert_eq!(open(&key, &sealed).unwrap(), b"");
+    assert!(open(&key, &[]).is_err());
+}
+
+#[test]
+fn test_stream_truncated() {
+    let key = Key::<Provider>::random().unwrap();
+    let sealed = seal(&key, &Provider::random_vec(3 * CHUNK_SIZE + 10).unwrap());
+
+    // dropping whole frames at the end
+    let frames = frames(&sealed);
+    for count in 1..frames.len() {
+        assert!(open(&key, &frames[..count].concat()).is_err());
+    }
+    // cutting the stream anywhere
+    for len in 0..sealed.len() {
+        assert!(open(&key, &sealed[..len]).is_err());
+    }
+    // appending data
+    assert!(open(&key, &[&sealed[..], &[0]].concat()).is_err());
+}
+
+#[test]
+fn test_stream_reordered() {
+    let key = Key::<Provider>::random().unwrap();
+    let sealed = seal(&key, &Provider::random_vec(3 * CHUNK_SIZE).unwrap());
+    let frames = frames(&sealed);
+
+    let mut swapped = frames.clone();
+    swapped.swap(0, 1);
+    assert!(open(&key, &swapped.concat()).is_err());
+
+    let mut skipped = frames.clone();
+    skipped.remove(1);
+    assert!(open(&key, &skipped.concat()).is_err());
+
+    let mut duplicated = frames.clone();
+    duplicated.insert(1, frames[0]);
+    assert!(open(&key, &duplicated.concat()).is_err());
+
+    // marking a frame as last is detected
+    let mut marked = frames[0].to_vec();
+    marked[0] = 1;
+    assert!(open(&key, &marked).is_err());
+}
+
+/// a writer which fails like a full disk
+struct FullDisk;
+
+impl Write for FullDisk {
+    fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+        Err(io::Error::new(ErrorKind::WriteZero, "disk full"))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// a reader which fails with an error of the vault, like a nested stream
+struct FailingStream;
+
+impl Read for FailingStream {
+    fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+        Err(Error::AuthenticationFailed.into())
+    }
+}
+
+#[test]
+fn test_stream_io_errors() {
+    let key = Key::<Provider>::random().unwrap();
+
+    // the kind and the message of the writer's error are kept
+    match Provider::seal_stream(&key, b"ad", &mut &b"some data"[..], &mut FullDisk, CHUNK_SIZE) {
+        Err(Error::Io(e)) => {
+            assert_eq!(e.kind(), ErrorKind::WriteZero);
+            assert_eq!(e.to_string(), "disk full");
+        }
+        r => panic!("unexpected result: {:?}", r),
+    }
+    let sealed = seal(&key, b"some data");
+    assert!(matches!(
+        Provider::open_stream(&key, b"ad", &mut &sealed[..], &mut FullDisk, CHUNK_SIZE),
+        Err(Error::Io(_))
+    ));
+
+    // an error of the vault inside the reader's error comes out unwrapped
+    assert_eq!(
+        Provider::open_stream(&key, b"ad", &mut FailingStream, &mut Vec::new(), CHUNK_SIZE).unwrap_err(),
+        Error::AuthenticationFailed
+    );
+}
@@ -0,0 +1,111 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+mod utils;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use utils::provider::{IetfProvider, Provider};
+use vault::{BoxProvider, Error, Key};
+
+/// the number of boxes `CountingProvider` tried to open
+static OPENED: AtomicUsize = AtomicUsize::new(0);
+
+/// a provider which counts the calls of `box_open`
+struct CountingProvider;
+
+impl BoxProvider for CountingProvider {
+    type Error = Error;
+
+    fn box_key_len() -> usize {
+        Provider::box_key_len()
+    }
+
+    fn box_overhead() -> usize {
+        Provider::box_overhead()
+    }
+
+    fn box_id() -> [u8; 4] {
+        *b"cnt1"
+    }
+
+    fn box_seal(key: &Key<Self>, ad: &[u8], data: &[u8]) -> vault::Result<Vec<u8>> {
+        Provider::box_seal(&Key::load_from_slice(key.bytes())?, ad, data)
+    }
+
+    fn box_open(key: &Key<Self>, ad: &[u8], data: &[u8]) -> vault::Result<Vec<u8>> {
+        OPENED.fetch_add(1, Ordering::SeqCst);
+        Provider::box_open(&Key::load_from_slice(key.bytes())?, ad, data)
+    }
+
+    fn random_buf(buf: &mut [u8]) -> vault::Result<()> {
+        Provider::random_buf(buf)
+    }
+}
+
+#[test]
+fn test_tagged_roundtrip() {
+    let key = Key::<Provider>::random().unwrap();
+
+    let sealed = Provider::seal_tagged(&key, b"ad", b"some data").unwrap();
+    assert_eq!(&sealed[..7], b"vbxcp1\x01");
+    assert_eq!(sealed.len(), 7 + 9 + Provider::box_overhead());
+    assert_eq!(Provider::open_tagged(&key, b"ad", &sealed).unwrap(), b"some data");
+
+    assert!(Provider::open_tagged(&key, b"other ad", &sealed).is_err());
+    // the plain boxes are unchanged and have no header
+    assert!(Provider::box_open(&key, b"ad", &sealed[7..]).is_err());
+    assert!(Provider::open_tagged(&key, b"ad", &Provider::box_seal(&key, b"ad", b"some data").unwrap()).is_err());
+}
+
+#[test]
+fn test_tagged_provider_mismatch() {
+    let key = Key::<IetfProvider>::random().unwrap();
+    let sealed = IetfProvider::seal_tagged(&key, b"", b"some data").unwrap();
+
+    let e = Provider::open_tagged(&Key::load_from_slice(key.bytes()).unwrap(), b"", &sealed).unwrap_err();
+    match &e {
+        Error::ProviderMismatch { expected, found } => {
+            assert_eq!(expected, "xcp1");
+            assert_eq!(found, "icp1");
+        }
+        e => panic!("unexpected error: {:?}", e),
+    }
+    assert!(e.to_string().contains("xcp1"));
+    assert!(e.to_string().contains("icp1"));
+}
+
+#[test]
+fn test_tagged_corrupt_header() {
+    let key = Key::<CountingProvider>::random().unwrap();
+    let sealed = CountingProvider::seal_tagged(&key, b"", b"some data").unwrap();
+    assert_eq!(CountingProvider::open_tagged(&key, b"", &sealed).unwrap(), b"some data");
+    assert_eq!(OPENED.load(Ordering::SeqCst), 1);
+
+    for i in [0, 1, 6] {
+        let mut corrupt = sealed.clone();
+        corrupt[i] ^= 1;
+        assert!(matches!(
+            CountingProvider::open_tagged(&key, b"", &corrupt),
+            Err(Error::CryptoError { .. })
+        ));
+    }
+    let mut corrupt = sealed.clone();
+    corrupt[2] = 0xff;
+    assert!(matches!(
+        CountingProvider::open_tagged(&key, b"", &corrupt),
+        Err(Error::ProviderMismatch { ref found, .. }) if found == "\\xffnt1"
+    ));
+    assert!(CountingProvider::open_tagged(&key, b"", &sealed[..6]).is_err());
+
+    // none of the corrupt boxes reached the provider
+    assert_eq!(OPENED.load(Ordering::SeqCst), 1);
+}
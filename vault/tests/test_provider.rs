@@ -0,0 +1,85 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+#![cfg(feature = "test-utils")]
+
+use vault::{test_utils::TestProvider, BoxProvider, Error, Key};
+
+#[test]
+fn test_test_provider_roundtrip() {
+    let key = Key::<TestProvider>::random().unwrap();
+
+    let sealed = TestProvider::box_seal(&key, b"ad", b"some data").unwrap();
+    assert_eq!(sealed.len(), 9 + TestProvider::box_overhead());
+    assert_eq!(TestProvider::box_open(&key, b"ad", &sealed).unwrap(), b"some data");
+
+    // every box gets a new nonce
+    assert_ne!(TestProvider::box_seal(&key, b"ad", b"some data").unwrap(), sealed);
+    assert!(TestProvider::self_test().unwrap().passed());
+}
+
+#[test]
+fn test_test_provider_authentication() {
+    let key = Key::<TestProvider>::random().unwrap();
+    let sealed = TestProvider::box_seal(&key, b"ad", b"some data").unwrap();
+
+    let other = Key::<TestProvider>::random().unwrap();
+    assert!(matches!(
+        TestProvider::box_open(&other, b"ad", &sealed),
+        Err(Error::AuthenticationFailed)
+    ));
+    assert!(TestProvider::box_open(&key, b"other ad", &sealed).is_err());
+    for i in [0, 8, sealed.len() - 1] {
+        let mut corrupt = sealed.clone();
+        corrupt[i] ^= 1;
+        assert!(TestProvider::box_open(&key, b"ad", &corrupt).is_err());
+    }
+    assert!(TestProvider::box_open(&key, b"ad", &sealed[..23]).is_err());
+}
+
+#[test]
+fn test_test_provider_deterministic() {
+    TestProvider::seed(7);
+    let first = TestProvider::random_vec(40).unwrap();
+    let key = Key::<TestProvider>::random().unwrap();
+    let sealed = TestProvider::box_seal(&key, b"", b"some data").unwrap();
+
+    TestProvider::reset();
+    TestProvider::seed(7);
+    assert_eq!(TestProvider::random_vec(40).unwrap(), first);
+    let same = Key::<TestProvider>::random().unwrap();
+    assert_eq!(same, key);
+    assert_eq!(TestProvider::box_seal(&same, b"", b"some data").unwrap(), sealed);
+
+    TestProvider::seed(8);
+    assert_ne!(TestProvider::random_vec(40).unwrap(), first);
+}
+
+#[test]
+fn test_test_provider_counters() {
+    let key = Key::<TestProvider>::random().unwrap();
+    assert_eq!((TestProvider::seal_count(), TestProvider::open_count()), (0, 0));
+
+    let sealed = TestProvider::box_seal(&key, b"", b"some data").unwrap();
+    TestProvider::box_seal(&key, b"", b"more data").unwrap();
+    TestProvider::box_open(&key, b"", &sealed).unwrap();
+    // failed opens count as well
+    assert!(TestProvider::box_open(&key, b"wrong", &sealed).is_err());
+    assert_eq!((TestProvider::seal_count(), TestProvider::open_count()), (2, 2));
+
+    // other threads have their own counters
+    std::thread::spawn(|| assert_eq!(TestProvider::seal_count(), 0))
+        .join()
+        .unwrap();
+
+    TestProvider::reset();
+    assert_eq!((TestProvider::seal_count(), TestProvider::open_count()), (0, 0));
+}
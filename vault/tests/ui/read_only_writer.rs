@@ -0,0 +1,20 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+use vault::{BoxProvider, DBView, Id, RecordHint};
+
+fn write<P: BoxProvider>(view: DBView<P>, owner: Id) -> vault::Result<()> {
+    let read_only = view.read_only();
+    read_only.writer(owner).write(b"data", RecordHint::new(b"hint")?)?;
+    Ok(())
+}
+
+fn main() {}
@@ -0,0 +1,70 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+};
+
+/// An allocator that can watch a single allocation and report whether its bytes were wiped before it was freed.  It
+/// also counts the allocations of each thread.
+pub struct WatchingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+static WATCHED_PTR: AtomicUsize = AtomicUsize::new(0);
+static WATCHED_LEN: AtomicUsize = AtomicUsize::new(0);
+static FREED: AtomicBool = AtomicBool::new(false);
+static WIPED: AtomicBool = AtomicBool::new(false);
+
+impl WatchingAllocator {
+    /// watch the allocation starting at `ptr`.  Only one allocation can be watched at a time.
+    pub fn watch(ptr: *const u8, len: usize) {
+        FREED.store(false, Ordering::SeqCst);
+        WIPED.store(false, Ordering::SeqCst);
+        WATCHED_LEN.store(len, Ordering::SeqCst);
+        WATCHED_PTR.store(ptr as usize, Ordering::SeqCst);
+    }
+
+    /// get the number of allocations made by the current thread so far
+    pub fn allocations() -> usize {
+        ALLOCATIONS.with(|count| count.get())
+    }
+
+    /// returns `Some(wiped)` once the watched allocation was freed.
+    pub fn freed_wiped() -> Option<bool> {
+        match FREED.load(Ordering::SeqCst) {
+            true => Some(WIPED.load(Ordering::SeqCst)),
+            false => None,
+        }
+    }
+}
+
+unsafe impl GlobalAlloc for WatchingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        if ptr as usize == WATCHED_PTR.load(Ordering::SeqCst) {
+            let len = WATCHED_LEN.load(Ordering::SeqCst).min(layout.size());
+            let wiped = std::slice::from_raw_parts(ptr, len).iter().all(|b| *b == 0);
+            WATCHED_PTR.store(0, Ordering::SeqCst);
+            WIPED.store(wiped, Ordering::SeqCst);
+            FREED.store(true, Ordering::SeqCst);
+        }
+        System.dealloc(ptr, layout)
+    }
+}
@@ -9,6 +9,9 @@
 // an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
 // See the License for the specific language governing permissions and limitations under the License.
 
+// every test crate only uses a part of the shared utilities.
+#![allow(dead_code)]
+
 #[macro_export]
 macro_rules! error_line {
     ($str:expr) => {
@@ -16,5 +19,14 @@ macro_rules! error_line {
     };
 }
 
+/// decode the hex string `s`
+pub fn hex(s: &str) -> Vec<u8> {
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+        .collect()
+}
+
+pub mod alloc;
 pub mod provider;
 pub mod test_vault;
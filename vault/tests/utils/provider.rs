@@ -9,7 +9,7 @@
 // an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
 // See the License for the specific language governing permissions and limitations under the License.
 
-use crypto::XChaChaPoly;
+use crypto::{ChaChaPolyIetf, XChaChaPoly};
 use random::{
     primitives::{cipher::AeadCipher, rng::SecureRng},
     OsRng,
@@ -24,6 +24,8 @@ impl Provider {
 }
 
 impl BoxProvider for Provider {
+    type Error = vault::Error;
+
     fn box_key_len() -> usize {
         32
     }
@@ -32,6 +34,10 @@ impl BoxProvider for Provider {
         Self::NONCE_LEN + Self::TAG_LEN
     }
 
+    fn box_id() -> [u8; 4] {
+        *b"xcp1"
+    }
+
     fn box_seal(key: &Key<Self>, ad: &[u8], data: &[u8]) -> vault::Result<Vec<u8>> {
         let mut boxx = vec![0; data.len() + Self::box_overhead()];
         let (nonce, cipher) = boxx.split_at_mut(Self::NONCE_LEN);
@@ -39,20 +45,25 @@ impl BoxProvider for Provider {
 
         XChaChaPoly
             .seal_with(cipher, data, ad, key.bytes(), nonce)
-            .map_err(|_| vault::Error::CryptoError(String::from("Unable to seal data")))?;
+            .map_err(|_| vault::Error::crypto("seal", "Unable to seal data"))?;
         Ok(boxx)
     }
     fn box_open(key: &Key<Self>, ad: &[u8], data: &[u8]) -> vault::Result<Vec<u8>> {
         let mut plain = match data.len() {
             len if len >= Self::box_overhead() => vec![0; len - Self::box_overhead()],
-            _ => return Err(vault::Error::CryptoError(String::from("Truncated cipher"))),
+            got => {
+                return Err(vault::Error::MalformedCiphertext {
+                    expected_min: Self::box_overhead(),
+                    got,
+                })
+            }
         };
 
         let (nonce, cipher) = data.split_at(Self::NONCE_LEN);
 
         XChaChaPoly
             .open_to(&mut plain, cipher, ad, key.bytes(), nonce)
-            .map_err(|_| vault::Error::CryptoError(String::from("Invalid Cipher")))?;
+            .map_err(|_| vault::Error::AuthenticationFailed)?;
 
         Ok(plain)
     }
@@ -60,6 +71,63 @@ impl BoxProvider for Provider {
     fn random_buf(buf: &mut [u8]) -> vault::Result<()> {
         OsRng
             .random(buf)
-            .map_err(|_| vault::Error::CryptoError(String::from("Can't generated random Bytes")))
+            .map_err(|_| vault::Error::crypto("random", "Can't generated random Bytes"))
+    }
+}
+
+/// a provider with a different nonce length than `Provider`
+pub struct IetfProvider;
+impl IetfProvider {
+    const NONCE_LEN: usize = 12;
+    const TAG_LEN: usize = 16;
+}
+
+impl BoxProvider for IetfProvider {
+    type Error = vault::Error;
+
+    fn box_key_len() -> usize {
+        32
+    }
+
+    fn box_overhead() -> usize {
+        Self::NONCE_LEN + Self::TAG_LEN
+    }
+
+    fn box_id() -> [u8; 4] {
+        *b"icp1"
+    }
+
+    fn box_seal(key: &Key<Self>, ad: &[u8], data: &[u8]) -> vault::Result<Vec<u8>> {
+        let mut boxx = vec![0; data.len() + Self::box_overhead()];
+        let (nonce, cipher) = boxx.split_at_mut(Self::NONCE_LEN);
+        Self::random_buf(nonce)?;
+
+        ChaChaPolyIetf
+            .seal_with(cipher, data, ad, key.bytes(), nonce)
+            .map_err(|_| vault::Error::crypto("seal", "Unable to seal data"))?;
+        Ok(boxx)
+    }
+    fn box_open(key: &Key<Self>, ad: &[u8], data: &[u8]) -> vault::Result<Vec<u8>> {
+        let mut plain = match data.len() {
+            len if len >= Self::box_overhead() => vec![0; len - Self::box_overhead()],
+            got => {
+                return Err(vault::Error::MalformedCiphertext {
+                    expected_min: Self::box_overhead(),
+                    got,
+                })
+            }
+        };
+
+        let (nonce, cipher) = data.split_at(Self::NONCE_LEN);
+
+        ChaChaPolyIetf
+            .open_to(&mut plain, cipher, ad, key.bytes(), nonce)
+            .map_err(|_| vault::Error::AuthenticationFailed)?;
+
+        Ok(plain)
+    }
+
+    fn random_buf(buf: &mut [u8]) -> vault::Result<()> {
+        Provider::random_buf(buf)
     }
 }
@@ -0,0 +1,230 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+mod utils;
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use utils::provider::Provider;
+use vault::{
+    BoxProvider, ConcurrentVault, DBView, DBWriter, Durability, Error, FileStore, Id, Key, ReadRequest, RecordHint,
+    SharedKey, Store, WalRecovery, WalStore,
+};
+
+/// a fresh directory in the temporary directory
+fn temp_dir(name: &str) -> PathBuf {
+    let mut suffix = [0; 8];
+    Provider::random_buf(&mut suffix).unwrap();
+    let suffix: String = suffix.iter().map(|b| format!("{:02x}", b)).collect();
+    let dir = std::env::temp_dir().join(format!("vault-{}-{}", name, suffix));
+    fs::create_dir(&dir).unwrap();
+    dir
+}
+
+fn hint() -> RecordHint {
+    RecordHint::new(b"hint").unwrap()
+}
+
+/// opens the store in `dir` behind its log
+fn open(dir: &Path, key: &Key<Provider>, durability: Durability) -> WalStore<Provider, FileStore> {
+    let store = FileStore::open(dir.join("vault")).unwrap();
+    WalStore::open(key.clone(), store, dir.join("vault.wal"), durability).unwrap()
+}
+
+/// a vault behind the log in `dir` with the record `first` overwritten with `second`, returns the id of the record
+fn vault_with_record(dir: &Path, key: &Key<Provider>, shared: &SharedKey<Provider>, owner: Id) -> Id {
+    let vault = ConcurrentVault::open(shared.clone(), owner, open(dir, key, Durability::EveryWrite)).unwrap();
+    let id = vault.write(b"first", hint()).unwrap();
+    vault.overwrite(id, b"second").unwrap();
+    id
+}
+
+#[test]
+fn test_wal() {
+    let dir = temp_dir("wal");
+    let key = Key::<Provider>::random().unwrap();
+    let shared = SharedKey::from(Key::<Provider>::random().unwrap());
+    let owner = Id::random::<Provider>().unwrap();
+    let id = vault_with_record(&dir, &key, &shared, owner);
+    // every request is checkpointed
+    assert_eq!(fs::metadata(dir.join("vault.wal")).unwrap().len(), 5);
+
+    let wal = open(&dir, &key, Durability::EveryWrite);
+    assert_eq!(wal.recovery(), WalRecovery::default());
+    let vault = ConcurrentVault::open(shared, owner, wal).unwrap();
+    assert_eq!(vault.read(id).unwrap(), b"second");
+    drop(vault);
+
+    // an empty log opens, other files don't
+    let store = FileStore::open(dir.join("vault")).unwrap();
+    fs::write(dir.join("other.wal"), b"RCWL\x01").unwrap();
+    assert!(WalStore::open(key.clone(), store, dir.join("other.wal"), Durability::EveryWrite).is_ok());
+    let store = FileStore::open(dir.join("vault")).unwrap();
+    fs::write(dir.join("other.wal"), b"RCSN\x01").unwrap();
+    assert!(matches!(
+        WalStore::open(key, store, dir.join("other.wal"), Durability::EveryWrite),
+        Err(Error::CorruptStore(_))
+    ));
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_wal_batched() {
+    let dir = temp_dir("wal-batched");
+    let key = Key::<Provider>::random().unwrap();
+    let shared = SharedKey::from(Key::<Provider>::random().unwrap());
+    let owner = Id::random::<Provider>().unwrap();
+    let mut wal = open(&dir, &key, Durability::Batched(3));
+    wal.write(DBWriter::create_chain(shared.key(), owner)).unwrap();
+
+    // the record is pending, the store doesn't have it yet but the log reads it
+    let view = DBView::load(shared.key().clone(), wal.list().unwrap()).unwrap();
+    let (id, requests) = view.writer(owner).write(b"batched", hint()).unwrap();
+    let mut requests = requests.into_iter();
+    wal.write(requests.next().unwrap()).unwrap();
+    assert_eq!(wal.pending(), 2);
+    assert!(wal.store().list().unwrap().ids().is_empty());
+    assert!(wal.read(ReadRequest::payload::<Provider>(id)).unwrap().is_some());
+    assert!(fs::metadata(wal.path()).unwrap().len() > 5);
+
+    // the third request syncs the batch
+    wal.write(requests.next().unwrap()).unwrap();
+    assert_eq!(wal.pending(), 0);
+    assert_eq!(wal.store().list().unwrap().ids().len(), 3);
+    assert_eq!(fs::metadata(wal.path()).unwrap().len(), 5);
+
+    let vault = ConcurrentVault::open(shared, owner, wal).unwrap();
+    vault.revoke(id).unwrap();
+    let wal = vault.into_store();
+    assert_eq!(wal.pending(), 2);
+    let store = wal.into_store().unwrap();
+    assert_eq!(store.list().unwrap().ids().len(), 3);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[cfg(feature = "test-utils")]
+mod crash {
+    use super::*;
+    use vault::WalStep;
+
+    /// a vault behind a log with a single record
+    struct Fixture {
+        dir: PathBuf,
+        key: Key<Provider>,
+        shared: SharedKey<Provider>,
+        owner: Id,
+        id: Id,
+    }
+
+    impl Fixture {
+        fn new(name: &str) -> Self {
+            let dir = temp_dir(name);
+            let key = Key::<Provider>::random().unwrap();
+            let shared = SharedKey::from(Key::<Provider>::random().unwrap());
+            let owner = Id::random::<Provider>().unwrap();
+            let id = vault_with_record(&dir, &key, &shared, owner);
+            Self {
+                dir,
+                key,
+                shared,
+                owner,
+                id,
+            }
+        }
+
+        /// overwrites the record with `data` and crashes before `step`
+        fn crash_overwrite(&self, data: &[u8], durability: Durability, step: WalStep) {
+            let mut wal = open(&self.dir, &self.key, durability);
+            wal.crash_before(step);
+            let vault = ConcurrentVault::open(self.shared.clone(), self.owner, wal).unwrap();
+            assert!(matches!(
+                vault.overwrite(self.id, data),
+                Err(Error::InterfaceErrorDetailed(_))
+            ));
+        }
+
+        fn read(&self, wal: WalStore<Provider, FileStore>) -> Vec<u8> {
+            let vault = ConcurrentVault::open(self.shared.clone(), self.owner, wal).unwrap();
+            vault.read(self.id).unwrap()
+        }
+    }
+
+    #[test]
+    fn test_wal_crash() {
+        let fixture = Fixture::new("wal-crash");
+        let cases = [
+            (Durability::EveryWrite, WalStep::Apply, 1),
+            (Durability::EveryWrite, WalStep::Checkpoint, 1),
+            (Durability::Batched(2), WalStep::Apply, 2),
+            (Durability::Batched(2), WalStep::Checkpoint, 2),
+        ];
+        for (i, (durability, step, replayed)) in cases.iter().enumerate() {
+            let data = format!("data {}", i);
+            fixture.crash_overwrite(data.as_bytes(), *durability, *step);
+
+            // the log is sealed
+            let log = fs::read(fixture.dir.join("vault.wal")).unwrap();
+            assert!(!log.windows(data.len()).any(|window| window == data.as_bytes()));
+            let store = FileStore::open(fixture.dir.join("vault")).unwrap();
+            assert!(matches!(
+                WalStore::open(
+                    Key::<Provider>::random().unwrap(),
+                    store,
+                    fixture.dir.join("vault.wal"),
+                    Durability::EveryWrite
+                ),
+                Err(Error::AuthenticationFailed)
+            ));
+
+            // the logged requests are applied again
+            let wal = open(&fixture.dir, &fixture.key, Durability::EveryWrite);
+            assert_eq!(wal.recovery().replayed, *replayed);
+            assert_eq!(fs::metadata(wal.path()).unwrap().len(), 5);
+            assert_eq!(fixture.read(wal), data.as_bytes());
+        }
+        fs::remove_dir_all(&fixture.dir).unwrap();
+    }
+
+    #[test]
+    fn test_wal_torn_entry() {
+        let fixture = Fixture::new("wal-torn");
+        fixture.crash_overwrite(b"third", Durability::EveryWrite, WalStep::Apply);
+
+        // the entry of the overwrite was torn before it was synced, the old value is kept
+        let path = fixture.dir.join("vault.wal");
+        let log = fs::read(&path).unwrap();
+        fs::write(&path, &log[..log.len() - 3]).unwrap();
+        let wal = open(&fixture.dir, &fixture.key, Durability::EveryWrite);
+        assert_eq!(
+            wal.recovery(),
+            WalRecovery {
+                replayed: 0,
+                discarded: log.len() as u64 - 8
+            }
+        );
+        assert_eq!(fixture.read(wal), b"second");
+
+        // a corrupt entry before the last one isn't a crash
+        fixture.crash_overwrite(b"fourth", Durability::Batched(2), WalStep::Apply);
+        let mut log = fs::read(&path).unwrap();
+        log[10] ^= 1;
+        fs::write(&path, &log).unwrap();
+        let store = FileStore::open(fixture.dir.join("vault")).unwrap();
+        assert!(matches!(
+            WalStore::open(fixture.key.clone(), store, &path, Durability::EveryWrite),
+            Err(Error::CorruptStore(_))
+        ));
+        fs::remove_dir_all(&fixture.dir).unwrap();
+    }
+}
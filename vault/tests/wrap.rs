@@ -0,0 +1,57 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+mod utils;
+
+use utils::provider::{IetfProvider, Provider};
+use vault::{BoxProvider, Key, WrappedKey};
+
+#[test]
+fn test_wrapped_key() {
+    let kek = Key::<Provider>::random().unwrap();
+    let key = Key::<Provider>::random().unwrap();
+
+    let wrapped = key.to_wrapped(&kek).unwrap();
+    let json = serde_json::to_string(&wrapped).unwrap();
+    let bytes_json = serde_json::to_string(key.bytes()).unwrap();
+    assert!(!json.contains(&bytes_json[1..bytes_json.len() - 1]));
+
+    let wrapped: WrappedKey<Provider> = serde_json::from_str(&json).unwrap();
+    assert_eq!(wrapped.unwrap(&kek).unwrap(), key);
+
+    let wrong = Key::<Provider>::random().unwrap();
+    assert!(wrapped.unwrap(&wrong).is_err());
+}
+
+#[test]
+fn test_wrap_with_other_provider() {
+    let kek = Key::<IetfProvider>::random().unwrap();
+    let key = Key::<Provider>::random().unwrap();
+
+    let wrapped = key.wrap_with(&kek, b"vault id").unwrap();
+    assert_eq!(wrapped.len(), key.bytes().len() + 28);
+    assert_eq!(Key::<Provider>::unwrap_with(&wrapped, &kek, b"vault id").unwrap(), key);
+
+    assert!(Key::<Provider>::unwrap_with(&wrapped, &kek, b"other id").is_err());
+    let wrong = Key::<IetfProvider>::random().unwrap();
+    assert!(Key::<Provider>::unwrap_with(&wrapped, &wrong, b"vault id").is_err());
+
+    // the wrapped key round trips the other way around as well
+    let wrapped = kek.wrap_with(&key, b"").unwrap();
+    assert_eq!(Key::<IetfProvider>::unwrap_with(&wrapped, &key, b"").unwrap(), kek);
+}
+
+#[test]
+fn test_unwrap_with_wrong_length() {
+    let kek = Key::<Provider>::random().unwrap();
+    let sealed = Provider::box_seal(&kek, b"", &[1; 16]).unwrap();
+    assert!(Key::<Provider>::unwrap_with(&sealed, &kek, b"").is_err());
+}
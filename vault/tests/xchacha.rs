@@ -0,0 +1,71 @@
+// Copyright 2020 IOTA Stiftung
+//
+// Licensed under the Apache License, Version 2.0 (the "License"); you may not use this file except in compliance with
+// the License. You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software distributed under the License is distributed on
+// an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and limitations under the License.
+
+#![cfg(feature = "provider-xchacha")]
+
+mod utils;
+
+use utils::hex;
+use vault::{providers::XChaChaPoly, BoxProvider, Decrypt, Encrypt, Key};
+
+#[test]
+fn test_xchacha_roundtrip() {
+    let key = Key::<XChaChaPoly>::random().unwrap();
+
+    let sealed = b"some data".to_vec().encrypt(&key, b"ad").unwrap();
+    let opened: Vec<u8> = sealed.decrypt(&key, b"ad").unwrap();
+    assert_eq!(opened, b"some data");
+
+    let sealed = XChaChaPoly::box_seal(&key, b"", b"").unwrap();
+    assert_eq!(sealed.len(), XChaChaPoly::box_overhead());
+    assert_eq!(XChaChaPoly::box_open(&key, b"", &sealed).unwrap(), b"");
+
+    // every box uses a fresh nonce
+    assert_ne!(sealed, XChaChaPoly::box_seal(&key, b"", b"").unwrap());
+}
+
+#[test]
+fn test_xchacha_rejects_tampering() {
+    let key = Key::<XChaChaPoly>::random().unwrap();
+    let sealed = XChaChaPoly::box_seal(&key, b"ad", b"some data").unwrap();
+
+    for i in 0..sealed.len() {
+        let mut tampered = sealed.clone();
+        tampered[i] ^= 1;
+        assert!(XChaChaPoly::box_open(&key, b"ad", &tampered).is_err());
+    }
+    for len in 0..sealed.len() {
+        assert!(XChaChaPoly::box_open(&key, b"ad", &sealed[..len]).is_err());
+    }
+
+    assert!(XChaChaPoly::box_open(&key, b"other ad", &sealed).is_err());
+    assert!(XChaChaPoly::box_open(&Key::random().unwrap(), b"ad", &sealed).is_err());
+}
+
+/// test vector A.3.1 of draft-irtf-cfrg-xchacha-03
+#[test]
+fn test_xchacha_known_answer() {
+    let key =
+        Key::<XChaChaPoly>::load(hex("808182838485868788898a8b8c8d8e8f909192939495969798999a9b9c9d9e9f")).unwrap();
+    let nonce = hex("404142434445464748494a4b4c4d4e4f5051525354555657");
+    let ad = hex("50515253c0c1c2c3c4c5c6c7");
+    let plain = b"Ladies and Gentlemen of the class of '99: If I could offer you only one tip for the future, \
+                  sunscreen would be it.";
+    let cipher = hex(
+        "bd6d179d3e83d43b9576579493c0e939572a1700252bfaccbed2902c21396cbb731c7f1b0b4aa6440bf3a82f4eda7e39ae64c67\
+         08c54c216cb96b72e1213b4522f8c9ba40db5d945b11b69b982c1bb9e3f3fac2bc369488f76b2383565d3fff921f9664c97637d\
+         a9768812f615c68b13b52e",
+    );
+    let tag = hex("c0875924c1c7987947deafd8780acf49");
+
+    let sealed = [nonce, cipher, tag].concat();
+    assert_eq!(XChaChaPoly::box_open(&key, &ad, &sealed).unwrap(), &plain[..]);
+}